@@ -0,0 +1,78 @@
+//! Format FEA files
+
+use std::path::PathBuf;
+
+use clap::Parser;
+use fea_rs::compile::{self, error::GlyphOrderError};
+
+/// Reformat a FEA file, normalizing indentation and whitespace.
+///
+/// Included files are not formatted.
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("{err}");
+        std::process::exit(1)
+    }
+}
+
+fn run() -> Result<(), Error> {
+    env_logger::init();
+    let args = Args::parse();
+    let fea = std::fs::read_to_string(&args.input)?;
+    let glyph_map = args
+        .glyph_order
+        .as_ref()
+        .map(|path| {
+            let glyph_order = std::fs::read_to_string(path)?;
+            compile::parse_glyph_order(&glyph_order).map_err(Error::from)
+        })
+        .transpose()?;
+
+    let formatted = fea_rs::util::format_string(fea.as_str(), glyph_map.as_ref())
+        .map_err(|errs| Error::Parse(errs.display().to_string()))?;
+
+    if args.check {
+        if formatted != fea {
+            return Err(Error::Unformatted(args.input));
+        }
+    } else if args.write {
+        std::fs::write(&args.input, formatted)?;
+    } else {
+        print!("{formatted}");
+    }
+    Ok(())
+}
+
+#[derive(Debug, thiserror::Error)]
+enum Error {
+    #[error("io error: '{0}'")]
+    File(#[from] std::io::Error),
+    #[error("invalid glyph map: '{0}'")]
+    InvalidGlyphMap(#[from] GlyphOrderError),
+    #[error("{0}")]
+    Parse(String),
+    #[error("{} is not formatted", .0.display())]
+    Unformatted(PathBuf),
+}
+
+/// Format FEA files
+#[derive(Parser, Debug)]
+#[command(author, version, long_about = None)]
+struct Args {
+    /// The FEA file to format.
+    input: PathBuf,
+
+    /// Path to a file containing the glyph order.
+    ///
+    /// This is only needed to parse glyph names that contain hyphens.
+    #[arg(short, long)]
+    glyph_order: Option<PathBuf>,
+
+    /// Write the result back to the input file, instead of to stdout.
+    #[arg(short, long, conflicts_with = "check")]
+    write: bool,
+
+    /// Exit with an error if the file is not already formatted.
+    #[arg(long)]
+    check: bool,
+}
@@ -56,6 +56,16 @@ impl Display for GlyphIdent {
     }
 }
 
+impl From<GlyphSet> for GlyphOrClass {
+    fn from(src: GlyphSet) -> GlyphOrClass {
+        let mut glyphs = src.iter();
+        match (glyphs.next(), glyphs.next()) {
+            (Some(gid), None) => GlyphOrClass::Glyph(gid),
+            _ => GlyphOrClass::Class(src.iter().collect()),
+        }
+    }
+}
+
 impl GlyphOrClass {
     pub(crate) fn len(&self) -> usize {
         match self {
@@ -1,6 +1,6 @@
 //! Compiling OpenType Layout tables
 
-use crate::{parse::ParseTree, DiagnosticSet, GlyphMap};
+use crate::{parse::ParseTree, DiagnosticSet, GlyphMap, GlyphSet};
 use fontdrasil::types::GlyphName;
 use write_fonts::{tables::layout::builders::CaretValueBuilder as CaretValue, types::GlyphId16};
 
@@ -13,7 +13,10 @@ use self::{
 use self::error::UfoGlyphOrderError;
 
 pub use compiler::Compiler;
-pub use feature_writer::{FeatureBuilder, FeatureProvider, NopFeatureProvider, PendingLookup};
+pub use feature_writer::{
+    ContextualPosBuilder, ContextualSubBuilder, FeatureBuilder, FeatureProvider,
+    LookupSubtableBuilder, NopFeatureProvider, PendingLookup,
+};
 pub use language_system::LanguageSystem;
 pub use lookups::{FeatureKey, LookupId};
 pub use opts::Opts;
@@ -31,9 +34,11 @@ mod features;
 mod glyph_range;
 pub(crate) mod glyphsapp_syntax_ext;
 mod language_system;
+mod lint;
 mod lookups;
 mod opts;
 mod output;
+mod subset;
 mod tables;
 mod tags;
 mod validate;
@@ -50,6 +55,36 @@ pub fn validate<V: VariationInfo>(
     DiagnosticSet::new(ctx.errors, node, usize::MAX)
 }
 
+/// Run the lint pass, returning any warnings.
+///
+/// These are about things that are legal but are probably mistakes, such as
+/// classes that are never used or rules that can never apply. The tree should
+/// already have passed [`validate`].
+pub fn lint(node: &ParseTree) -> DiagnosticSet {
+    let mut ctx = lint::LintCtx::new(node.source_map());
+    ctx.lint_root(&node.typed_root());
+    DiagnosticSet::new(ctx.into_warnings(), node, usize::MAX)
+}
+
+/// Add to `glyphs` every glyph the tree's substitution rules can replace them with.
+///
+/// Context is ignored, so the result may include glyphs that no input can
+/// actually reach; it is meant for choosing the glyphs of a subset.
+pub fn substitution_closure(tree: &ParseTree, glyph_map: &GlyphMap, glyphs: &mut GlyphSet) {
+    let ctx = subset::SubsetCtx::new(tree.root(), glyph_map);
+    ctx.close_over_substitutions(tree.root(), glyphs);
+}
+
+/// Rewrite the tree as a feature file that mentions only the glyphs in `keep`.
+///
+/// Other glyphs are removed from classes, and rules that mention one directly,
+/// or a class left with none of the kept glyphs, are removed. Included files
+/// are inlined, so the result stands alone. The tree should have been parsed
+/// with the glyph map of the whole font.
+pub fn subset(tree: &ParseTree, glyph_map: &GlyphMap, keep: &GlyphSet) -> String {
+    subset::SubsetCtx::new(tree.root(), glyph_map).subset(tree.root(), keep)
+}
+
 /// Run the compilation pass.
 ///
 /// If successful, returns the [`Compilation`] result, and any warnings.
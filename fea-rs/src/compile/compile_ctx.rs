@@ -8,7 +8,7 @@ use std::{
     ops::Range,
 };
 
-use fontdrasil::coords::{DesignCoord, NormalizedCoord, NormalizedLocation, UserCoord};
+use fontdrasil::coords::{NormalizedLocation, UserCoord};
 use smol_str::SmolStr;
 use write_fonts::{
     tables::{
@@ -1253,16 +1253,7 @@ impl<'a, F: FeatureProvider, V: VariationInfo> CompilationCtx<'a, F, V> {
                 let tag = axis_value.axis_tag().to_raw();
                 // All the tags are valid if we made it here, safe to unwrap
                 let (_, axis) = var_info.axis(tag).unwrap();
-                let coord = match axis_value.value().parse() {
-                    super::AxisLocation::Normalized(value) => NormalizedCoord::new(value),
-                    super::AxisLocation::User(value) => {
-                        UserCoord::new(value).to_normalized(&axis.converter)
-                    }
-                    super::AxisLocation::Design(value) => {
-                        DesignCoord::new(value).to_normalized(&axis.converter)
-                    }
-                };
-                pos.insert(tag, coord);
+                pos.insert(tag, axis_value.value().parse().to_normalized(axis));
             }
             locations.insert(pos, metric_loc.value().parse_signed());
         }
@@ -1656,7 +1647,6 @@ impl<'a, F: FeatureProvider, V: VariationInfo> CompilationCtx<'a, F, V> {
                 typed::StatTableItem::DesignAxis(value) => {
                     let tag = value.tag().to_raw();
                     let ordering = value.ordering().parse_unsigned().unwrap();
-                    //FIXME: validate
                     let name = value.names().map(|n| self.resolve_name_spec(&n)).collect();
                     stat.records.push(super::tables::AxisRecord {
                         tag,
@@ -2051,8 +2041,8 @@ impl<'a, F: FeatureProvider, V: VariationInfo> CompilationCtx<'a, F, V> {
             .conditions()
             .map(|cond| {
                 let tag = cond.tag().to_raw();
-                let min = UserCoord::new(cond.min_value().parse_signed());
-                let max = UserCoord::new(cond.max_value().parse_signed());
+                let min = UserCoord::new(cond.min_value().parse());
+                let max = UserCoord::new(cond.max_value().parse());
                 let (axis_index, axis) = var_info.axis(tag).unwrap();
 
                 ConditionFormat1 {
@@ -1,4 +1,9 @@
 //! API for the client to manually add additional features
+//!
+//! A [`FeatureProvider`] is given a [`FeatureBuilder`], to which it adds lookups
+//! made with the `write_fonts` layout builders, such as `LigatureSubBuilder` or
+//! `PairPosBuilder`, or with [`ContextualSubBuilder`] and [`ContextualPosBuilder`].
+//! These are merged into the lookups compiled from FEA.
 
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
@@ -6,9 +11,11 @@ use write_fonts::{
     tables::{
         gpos::builders::{
             CursivePosBuilder, MarkToBaseBuilder, MarkToLigBuilder, MarkToMarkBuilder,
-            PairPosBuilder,
+            PairPosBuilder, SinglePosBuilder,
+        },
+        gsub::builders::{
+            AlternateSubBuilder, LigatureSubBuilder, MultipleSubBuilder, SingleSubBuilder,
         },
-        gsub::builders::SingleSubBuilder,
         layout::{builders::LookupBuilder, ConditionSet, LookupFlag},
     },
     types::{GlyphId16, Tag},
@@ -23,8 +30,8 @@ use super::{
     features::{AllFeatures, FeatureLookups},
     language_system::{DefaultLanguageSystems, LanguageSystem},
     lookups::{
-        AllLookups, FeatureKey, FilterSetId, LookupId, LookupIdMap, PositionLookup,
-        SubstitutionLookup,
+        AllLookups, ChainOrNot, ContextBuilder, FeatureKey, FilterSetId, LookupId, LookupIdMap,
+        PositionLookup, SubstitutionLookup,
     },
     tables::{GdefBuilder, Tables},
     CaretValue,
@@ -61,6 +68,10 @@ pub struct FeatureBuilder<'a> {
     feature_variations: Option<RawFeatureVariations>,
 }
 
+/// A type that can be the subtables of a [`PendingLookup`]
+///
+/// This is implemented for the `write_fonts` builders of the non-contextual
+/// lookup types, and for [`ContextualSubBuilder`] and [`ContextualPosBuilder`].
 pub trait LookupSubtableBuilder: Sized {
     #[doc(hidden)]
     fn to_pos_lookup(
@@ -119,6 +130,84 @@ impl<T> PendingLookup<T> {
     }
 }
 
+/// The rules of a contextual substitution subtable, built outside of FEA
+///
+/// Each rule matches a sequence of glyph sets, and applies lookups at positions
+/// in the input sequence; the lookups are identified by the [`LookupId`]s
+/// returned from [`FeatureBuilder::add_lookup`]. The FEA equivalent of a rule
+/// is something like `sub [a b] f' lookup SUB_F i';`.
+///
+/// If any rule has a backtrack or lookahead sequence the lookup is compiled as
+/// a chained contextual lookup, otherwise as a contextual lookup.
+#[derive(Clone, Debug, Default)]
+pub struct ContextualSubBuilder(ContextBuilder);
+
+/// The rules of a contextual positioning subtable, built outside of FEA
+///
+/// This works like [`ContextualSubBuilder`]. As with contextual positioning in
+/// FEA, the lookup is always compiled as a chained contextual lookup.
+#[derive(Clone, Debug, Default)]
+pub struct ContextualPosBuilder(ContextBuilder);
+
+impl ContextualSubBuilder {
+    /// Add a rule, applying the lookups paired with each item of `input`
+    ///
+    /// All sequences are in text order, as in FEA: the last item of
+    /// `backtrack` matches the glyph just before the input.
+    ///
+    /// # Panics
+    ///
+    /// If `input` is empty.
+    pub fn add_rule(
+        &mut self,
+        backtrack: Vec<GlyphSet>,
+        input: Vec<(GlyphSet, Vec<LookupId>)>,
+        lookahead: Vec<GlyphSet>,
+    ) {
+        add_rule(&mut self.0, backtrack, input, lookahead)
+    }
+}
+
+impl ContextualPosBuilder {
+    /// Add a rule, applying the lookups paired with each item of `input`
+    ///
+    /// All sequences are in text order, as in FEA: the last item of
+    /// `backtrack` matches the glyph just before the input.
+    ///
+    /// # Panics
+    ///
+    /// If `input` is empty.
+    pub fn add_rule(
+        &mut self,
+        backtrack: Vec<GlyphSet>,
+        input: Vec<(GlyphSet, Vec<LookupId>)>,
+        lookahead: Vec<GlyphSet>,
+    ) {
+        add_rule(&mut self.0, backtrack, input, lookahead)
+    }
+}
+
+fn add_rule(
+    builder: &mut ContextBuilder,
+    backtrack: Vec<GlyphSet>,
+    input: Vec<(GlyphSet, Vec<LookupId>)>,
+    lookahead: Vec<GlyphSet>,
+) {
+    assert!(
+        !input.is_empty(),
+        "a contextual rule needs an input sequence"
+    );
+    // backtrack is stored in reverse order, as in the final table
+    builder.add(
+        backtrack.into_iter().rev().map(Into::into).collect(),
+        input
+            .into_iter()
+            .map(|(glyphs, lookups)| (glyphs.into(), lookups))
+            .collect(),
+        lookahead.into_iter().map(Into::into).collect(),
+    );
+}
+
 /// An externally created lookup.
 ///
 /// This exists so that we can avoid making our internal types `pub`.
@@ -275,12 +364,42 @@ macro_rules! impl_builder_trait {
 }
 
 impl_builder_trait!(SingleSubBuilder, Gsub);
+impl_builder_trait!(MultipleSubBuilder, Gsub);
+impl_builder_trait!(AlternateSubBuilder, Gsub);
+impl_builder_trait!(LigatureSubBuilder, Gsub);
+impl_builder_trait!(SinglePosBuilder, Gpos);
 impl_builder_trait!(PairPosBuilder, Gpos);
 impl_builder_trait!(MarkToBaseBuilder, Gpos);
 impl_builder_trait!(MarkToMarkBuilder, Gpos);
 impl_builder_trait!(MarkToLigBuilder, Gpos);
 impl_builder_trait!(CursivePosBuilder, Gpos);
 
+impl LookupSubtableBuilder for ContextualSubBuilder {
+    fn to_pos_lookup(
+        flags: LookupFlag,
+        filter_set: Option<FilterSetId>,
+        subtables: Vec<Self>,
+    ) -> ExternalLookup {
+        let subtables = subtables.into_iter().map(|sub| sub.0).collect();
+        ExternalLookup(Inner::Gsub(
+            ChainOrNot::new(flags, filter_set, subtables).into(),
+        ))
+    }
+}
+
+impl LookupSubtableBuilder for ContextualPosBuilder {
+    fn to_pos_lookup(
+        flags: LookupFlag,
+        filter_set: Option<FilterSetId>,
+        subtables: Vec<Self>,
+    ) -> ExternalLookup {
+        let subtables = subtables.into_iter().map(|sub| sub.0).collect();
+        ExternalLookup(Inner::Gpos(
+            ChainOrNot::new(flags, filter_set, subtables).into(),
+        ))
+    }
+}
+
 // features that can be added by a feature writer
 const CURS: Tag = Tag::new(b"curs");
 const MARK: Tag = Tag::new(b"mark");
@@ -352,13 +471,10 @@ impl MergeCtx<'_> {
         self.do_curs();
         self.do_kern_and_dist();
         self.do_marks();
+        self.do_remaining();
 
         // okay so now 'processed_lookups' should contain insertion points for
         // all of our lookups
-
-        if !self.ext_pos_lookups.is_empty() {
-            log::warn!("feature merging left unhandled features!");
-        }
         let mut id_map = self.finalize_gpos();
         self.finalize_gsub(&mut id_map);
 
@@ -590,6 +706,18 @@ impl MergeCtx<'_> {
         self.finalize_lookups_for_feature(MKMK, inserts[3].unwrap());
     }
 
+    fn do_remaining(&mut self) {
+        // lookups of other features, and those that are only referenced from
+        // contextual lookups, go at the end in the order they were added
+        let lookups = std::mem::take(&mut self.ext_pos_lookups)
+            .into_iter()
+            .collect::<Vec<_>>();
+        if !lookups.is_empty() {
+            let pos = self.insertion_point_for_append();
+            self.processed_lookups.push((pos, lookups));
+        }
+    }
+
     fn finalize_lookups_for_feature(&mut self, feature: Tag, pos: InsertionPoint) {
         let lookups = self.take_lookups_for_features(&[feature]);
         if !lookups.is_empty() {
@@ -647,10 +775,17 @@ impl ExternalFeatures {
 
 #[cfg(test)]
 mod tests {
-    use write_fonts::tables::layout::ConditionSet;
+    use fontdrasil::types::GlyphName;
+    use write_fonts::tables::{
+        gpos::{self as write_gpos, builders::ValueRecordBuilder},
+        gsub as write_gsub,
+        layout::ConditionSet,
+    };
 
     use super::*;
 
+    use crate::{compile::NopVariationInfo, GlyphMap};
+
     use crate::compile::tags::{LANG_DFLT, SCRIPT_DFLT};
 
     impl AllFeatures {
@@ -927,6 +1062,78 @@ mod tests {
         );
     }
 
+    struct ContextualProvider;
+
+    impl FeatureProvider for ContextualProvider {
+        fn add_features(&self, builder: &mut FeatureBuilder) {
+            let glyphs =
+                |ids: &[u16]| -> GlyphSet { ids.iter().copied().map(GlyphId16::new).collect() };
+            let mut liga = LigatureSubBuilder::default();
+            liga.insert(
+                vec![GlyphId16::new(1), GlyphId16::new(2)],
+                GlyphId16::new(3),
+            );
+            let liga =
+                builder.add_lookup(PendingLookup::new(vec![liga], LookupFlag::empty(), None));
+
+            let mut single = SinglePosBuilder::default();
+            single.insert(
+                GlyphId16::new(1),
+                ValueRecordBuilder::new().with_x_advance(-20),
+            );
+            let single =
+                builder.add_lookup(PendingLookup::new(vec![single], LookupFlag::empty(), None));
+            let mut contextual = ContextualPosBuilder::default();
+            contextual.add_rule(
+                vec![glyphs(&[4, 5])],
+                vec![(glyphs(&[1]), vec![single])],
+                vec![],
+            );
+            let contextual = builder.add_lookup(PendingLookup::new(
+                vec![contextual],
+                LookupFlag::empty(),
+                None,
+            ));
+
+            builder.add_to_default_language_systems(Tag::new(b"liga"), &[liga]);
+            builder.add_to_default_language_systems(Tag::new(b"cpsp"), &[contextual]);
+        }
+    }
+
+    #[test]
+    fn external_ligature_and_contextual_lookups() {
+        let glyph_map = [".notdef", "a", "b", "a_b", "c", "d"]
+            .into_iter()
+            .map(GlyphName::new)
+            .collect::<GlyphMap>();
+        let (tree, _) = crate::parse::parse_string("languagesystem DFLT dflt;");
+        let (compilation, _) = crate::compile::compile::<NopVariationInfo, _>(
+            &tree,
+            &glyph_map,
+            None,
+            Some(&ContextualProvider),
+            Default::default(),
+        )
+        .unwrap();
+
+        let gsub = compilation.gsub.unwrap();
+        assert!(matches!(
+            gsub.lookup_list.lookups.as_slice(),
+            [lookup] if matches!(**lookup, write_gsub::SubstitutionLookup::Ligature(_))
+        ));
+        let gpos = compilation.gpos.unwrap();
+        // the lookup the contextual lookup refers to isn't in a feature, and
+        // goes at the end
+        assert!(matches!(
+            gpos.lookup_list.lookups.as_slice(),
+            [contextual, single] if matches!(**contextual, write_gpos::PositionLookup::ChainContextual(_))
+                && matches!(**single, write_gpos::PositionLookup::Single(_))
+        ));
+        let cpsp = &gpos.feature_list.feature_records[0];
+        assert_eq!(Tag::new(b"cpsp"), cpsp.feature_tag);
+        assert_eq!(vec![0], cpsp.feature.lookup_list_indices);
+    }
+
     #[test]
     fn dist_feature_ignores_kern_marker() {
         let mut external = mock_external_features(&[MARK, DIST]);
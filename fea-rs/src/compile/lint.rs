@@ -0,0 +1,425 @@
+//! the lint pass
+//!
+//! This looks for things that are valid, but are probably not what the author
+//! intended: classes and lookups that are defined but never used, rules that
+//! repeat or can never apply because of an earlier rule, and language systems
+//! that no rule is registered for.
+//!
+//! This works only on the AST, and should only be run on a tree that has been
+//! validated.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::Range,
+};
+
+use smol_str::SmolStr;
+
+use crate::{
+    parse::SourceMap,
+    token_tree::typed::{self, AstNode},
+    Diagnostic, Kind, Node, NodeOrToken,
+};
+
+pub(crate) struct LintCtx<'a> {
+    source_map: &'a SourceMap,
+    warnings: Vec<(Range<usize>, String)>,
+}
+
+/// The state of the current lookup while linting a block of rules
+#[derive(Default)]
+struct LookupState {
+    /// the kind of rule, as it determines the type of lookup
+    kind: Option<Kind>,
+    subtable: usize,
+    rules: HashSet<SmolStr>,
+    /// single and multiple substitutions, by target
+    sub_targets: HashMap<SmolStr, usize>,
+    /// the first item of class pair positioning rules, by subtable
+    class_pairs: HashMap<SmolStr, usize>,
+}
+
+impl<'a> LintCtx<'a> {
+    pub(crate) fn new(source_map: &'a SourceMap) -> Self {
+        LintCtx {
+            source_map,
+            warnings: Vec::new(),
+        }
+    }
+
+    fn warning(&mut self, range: Range<usize>, message: impl Into<String>) {
+        self.warnings.push((range, message.into()));
+    }
+
+    /// The warnings, in the order they appear in the source
+    pub(crate) fn into_warnings(mut self) -> Vec<Diagnostic> {
+        self.warnings.sort_by_key(|(range, _)| range.start);
+        self.warnings
+            .into_iter()
+            .map(|(range, message)| {
+                let (file, range) = self.source_map.resolve_range(range);
+                Diagnostic::warning(file, range, message)
+            })
+            .collect()
+    }
+
+    pub(crate) fn lint_root(&mut self, node: &typed::Root) {
+        self.lint_unused_classes(node.node());
+        self.lint_unreferenced_lookups(node);
+        self.lint_language_systems(node);
+        for item in node.statements() {
+            if let Some(feature) = typed::Feature::cast(item) {
+                self.lint_rules(feature.statements());
+            } else if let Some(lookup) = typed::LookupBlock::cast(item) {
+                self.lint_rules(lookup.statements());
+            }
+        }
+    }
+
+    fn lint_unused_classes(&mut self, root: &Node) {
+        // the first definition of each class, and every class name that isn't
+        // being defined
+        let mut defined = BTreeMap::new();
+        let mut used = HashSet::new();
+        let mut definitions = HashSet::new();
+        visit(root, &mut |item| {
+            let name = if let Some(def) = typed::GlyphClassDef::cast(item) {
+                def.class_name()
+            } else if let Some(def) = typed::MarkClassDef::cast(item) {
+                def.mark_class_name()
+            } else {
+                if let Some(name) = typed::GlyphClassName::cast(item) {
+                    if !definitions.contains(&name.range().start) {
+                        used.insert(name.text().clone());
+                    }
+                }
+                return;
+            };
+            definitions.insert(name.range().start);
+            defined
+                .entry(name.text().clone())
+                .or_insert_with(|| name.range());
+        });
+
+        for (name, range) in defined {
+            if !used.contains(&name) {
+                self.warning(range, format!("glyph class '{name}' is never used"));
+            }
+        }
+    }
+
+    fn lint_unreferenced_lookups(&mut self, root: &typed::Root) {
+        let mut referenced = HashSet::new();
+        visit(root.node(), &mut |item| {
+            if let Some(lookup) = typed::LookupRef::cast(item) {
+                referenced.insert(lookup.label().text.clone());
+            }
+        });
+        // lookups defined in a feature block are used by that feature
+        for lookup in root.statements().filter_map(typed::LookupBlock::cast) {
+            let label = lookup.label();
+            if !referenced.contains(&label.text) {
+                self.warning(
+                    label.range(),
+                    format!("lookup '{}' is never referenced", label.text),
+                );
+            }
+        }
+    }
+
+    fn lint_language_systems(&mut self, root: &typed::Root) {
+        let language_systems = root
+            .statements()
+            .filter_map(typed::LanguageSystem::cast)
+            .collect::<Vec<_>>();
+        let mut has_features = false;
+        let mut used = HashSet::new();
+        for feature in root.statements().filter_map(typed::Feature::cast) {
+            has_features = true;
+            let mut statements = feature.statements().peekable();
+            // rules before the first script statement are registered for all
+            // of the default language systems, so none of them are unused
+            if statements
+                .peek()
+                .and_then(|item| typed::Script::cast(item))
+                .is_none()
+            {
+                return;
+            }
+            let mut script = None;
+            for item in statements {
+                if let Some(node) = typed::Script::cast(item) {
+                    let tag = node.tag().text().clone();
+                    used.insert((tag.clone(), SmolStr::new("dflt")));
+                    script = Some(tag);
+                } else if let Some(node) = typed::Language::cast(item) {
+                    if let Some(script) = script.clone() {
+                        used.insert((script, node.tag().text().clone()));
+                    }
+                }
+            }
+        }
+        // features may also come from elsewhere, so without any there is
+        // nothing to go on
+        if !has_features {
+            return;
+        }
+        for system in language_systems {
+            let key = (
+                system.script().text().clone(),
+                system.language().text().clone(),
+            );
+            if !used.contains(&key) {
+                self.warning(
+                    system.range(),
+                    format!(
+                        "no rules are registered for language system '{} {}'",
+                        key.0, key.1
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Look for rules that repeat or can't apply because of an earlier rule
+    fn lint_rules<'b>(&mut self, statements: impl Iterator<Item = &'b NodeOrToken>) {
+        let mut lookup = LookupState::default();
+        for item in statements {
+            if let Some(node) = typed::LookupBlock::cast(item) {
+                self.lint_rules(node.statements());
+                lookup = Default::default();
+            } else if typed::Script::cast(item).is_some()
+                || typed::Language::cast(item).is_some()
+                || typed::LookupFlag::cast(item).is_some()
+                || typed::LookupRef::cast(item).is_some()
+            {
+                lookup = Default::default();
+            } else if item.kind() == Kind::SubtableNode {
+                lookup.subtable += 1;
+            } else if let Some(rule) = typed::GsubStatement::cast(item) {
+                if self.lint_duplicate_rule(&mut lookup, item) {
+                    self.lint_gsub_rule(&mut lookup, &rule);
+                }
+            } else if let Some(rule) = typed::GposStatement::cast(item) {
+                if self.lint_duplicate_rule(&mut lookup, item) {
+                    self.lint_gpos_rule(&mut lookup, &rule);
+                }
+            }
+        }
+    }
+
+    /// Returns `false` if this rule is the same as an earlier one
+    fn lint_duplicate_rule(&mut self, lookup: &mut LookupState, item: &NodeOrToken) -> bool {
+        // a change in the type of rule starts a new lookup, although single
+        // and multiple substitutions are combined
+        let kind = match item.kind() {
+            Kind::GsubType1 => Kind::GsubType2,
+            other => other,
+        };
+        if lookup.kind.replace(kind).is_some_and(|prev| prev != kind) {
+            *lookup = LookupState {
+                kind: Some(kind),
+                ..Default::default()
+            };
+        }
+        if !lookup.rules.insert(rule_text(item)) {
+            self.warning(item.range(), "rule is a duplicate of an earlier rule");
+            return false;
+        }
+        true
+    }
+
+    fn lint_gsub_rule(&mut self, lookup: &mut LookupState, rule: &typed::GsubStatement) {
+        let target = match rule {
+            typed::GsubStatement::Type1(rule) => match rule.target() {
+                target @ (typed::GlyphOrClass::Glyph(_) | typed::GlyphOrClass::Cid(_)) => {
+                    target.range()
+                }
+                _ => return,
+            },
+            typed::GsubStatement::Type2(rule) => rule.target().range(),
+            _ => return,
+        };
+        let text = rule_text_in(rule.node().unwrap(), target.clone());
+        if let Some(subtable) = lookup.sub_targets.get(&text) {
+            let message = if *subtable == lookup.subtable {
+                format!("'{text}' is already substituted by an earlier rule")
+            } else {
+                format!("'{text}' is already substituted in an earlier subtable")
+            };
+            self.warning(target, message);
+        } else {
+            lookup.sub_targets.insert(text, lookup.subtable);
+        }
+    }
+
+    fn lint_gpos_rule(&mut self, lookup: &mut LookupState, rule: &typed::GposStatement) {
+        let typed::GposStatement::Type2(rule) = rule else {
+            return;
+        };
+        let is_class = |item: &typed::GlyphOrClass| {
+            matches!(
+                item,
+                typed::GlyphOrClass::NamedClass(_) | typed::GlyphOrClass::Class(_)
+            )
+        };
+        let (first, second) = (rule.first_item(), rule.second_item());
+        let text = rule_text_in(rule.node(), first.range());
+        match lookup.class_pairs.get(&text) {
+            // a class pair subtable positions everything in its coverage, even
+            // if only by zero
+            Some(subtable) if *subtable < lookup.subtable => self.warning(
+                first.range(),
+                format!("pairs starting with '{text}' are already positioned by a class pair rule in an earlier subtable"),
+            ),
+            Some(_) => (),
+            None if is_class(&first) && is_class(&second) => {
+                lookup.class_pairs.insert(text, lookup.subtable);
+            }
+            None => (),
+        }
+    }
+}
+
+/// Call `f` on every node and token in the tree
+pub(super) fn visit(node: &Node, f: &mut impl FnMut(&NodeOrToken)) {
+    for child in node.iter_children() {
+        f(child);
+        if let NodeOrToken::Node(node) = child {
+            visit(node, f);
+        }
+    }
+}
+
+/// The tokens of a rule, without whitespace or comments
+fn rule_text(item: &NodeOrToken) -> SmolStr {
+    match item {
+        NodeOrToken::Token(token) => token.text.clone(),
+        NodeOrToken::Node(node) => rule_text_in(node, node.range()),
+    }
+}
+
+/// The tokens of `node` within `range`, without whitespace or comments
+fn rule_text_in(node: &Node, range: Range<usize>) -> SmolStr {
+    let mut text = String::new();
+    let mut tokens = Vec::new();
+    collect_tokens(node, &range, &mut tokens);
+    for token in tokens {
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(&token);
+    }
+    text.into()
+}
+
+fn collect_tokens(node: &Node, range: &Range<usize>, out: &mut Vec<SmolStr>) {
+    for child in node.iter_children() {
+        let child_range = child.range();
+        if child_range.end <= range.start || child_range.start >= range.end {
+            continue;
+        }
+        match child {
+            NodeOrToken::Node(node) => collect_tokens(node, range, out),
+            NodeOrToken::Token(token) if !token.kind.is_trivia() => out.push(token.text.clone()),
+            NodeOrToken::Token(_) => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint_messages(fea: &str) -> Vec<String> {
+        let (tree, diagnostics) = crate::parse::parse_string(fea);
+        assert!(!diagnostics.has_errors(), "{}", diagnostics.display());
+        let mut ctx = LintCtx::new(tree.source_map());
+        ctx.lint_root(&tree.typed_root());
+        ctx.into_warnings()
+            .into_iter()
+            .map(|warning| warning.message.text)
+            .collect()
+    }
+
+    #[test]
+    fn unused_classes_and_lookups() {
+        let fea = "\
+@used = [a b];
+@alias = @used;
+@unused = [c];
+markClass [acute] <anchor 0 500> @TOP;
+markClass [grave] <anchor 0 500> @TOP;
+markClass [cedilla] <anchor 0 0> @BOTTOM;
+lookup referenced { sub @alias by c; } referenced;
+lookup orphan { sub a by b; } orphan;
+feature test {
+    lookup referenced;
+    pos base a <anchor 250 500> mark @TOP;
+} test;
+";
+        assert_eq!(
+            lint_messages(fea),
+            [
+                "glyph class '@unused' is never used",
+                "glyph class '@BOTTOM' is never used",
+                "lookup 'orphan' is never referenced",
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_and_shadowed_rules() {
+        let fea = "\
+feature test {
+    sub a by b;
+    sub a by  b;
+    sub a by c;
+    sub [a b] by [c d];
+    sub f i by f_i;
+    sub a by d;
+    lookupflag IgnoreMarks;
+    sub a by c;
+} test;
+
+feature kern {
+    pos @upper @lower -10;
+    subtable;
+    pos @upper a -20;
+    pos a b -30;
+} kern;
+";
+        assert_eq!(
+            lint_messages(fea),
+            [
+                "rule is a duplicate of an earlier rule",
+                "'a' is already substituted by an earlier rule",
+                "pairs starting with '@upper' are already positioned by a class pair rule in an earlier subtable",
+            ]
+        );
+    }
+
+    #[test]
+    fn unused_language_systems() {
+        let fea = "\
+languagesystem DFLT dflt;
+languagesystem latn dflt;
+languagesystem latn TRK;
+feature locl {
+    script latn;
+    language TRK;
+    sub i by i.loclTRK;
+} locl;
+";
+        assert_eq!(
+            lint_messages(fea),
+            ["no rules are registered for language system 'DFLT dflt'"]
+        );
+
+        // rules outside of a script statement use every language system
+        let fea = "languagesystem DFLT dflt;\nlanguagesystem latn dflt;\nfeature liga { sub f i by f_i; } liga;\n";
+        assert!(lint_messages(fea).is_empty());
+        // as may features from somewhere other than this file
+        assert!(lint_messages("languagesystem DFLT dflt;\n").is_empty());
+    }
+}
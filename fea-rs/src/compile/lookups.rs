@@ -1,11 +1,13 @@
 //! gsub/gpos lookup table stuff
 
 mod contextual;
+mod optimize;
 
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     convert::TryInto,
     fmt::Debug,
+    hash::Hash,
 };
 
 use smol_str::SmolStr;
@@ -29,10 +31,10 @@ use write_fonts::{
         },
         layout::{
             builders::{Builder, LookupBuilder},
-            ConditionSet as RawConditionSet, Feature, FeatureList, FeatureRecord,
-            FeatureTableSubstitution, FeatureTableSubstitutionRecord, FeatureVariationRecord,
-            FeatureVariations, LangSys, LangSysRecord, LookupFlag, LookupList, Script, ScriptList,
-            ScriptRecord,
+            ChainedSequenceContext, ConditionSet as RawConditionSet, Feature, FeatureList,
+            FeatureRecord, FeatureTableSubstitution, FeatureTableSubstitutionRecord,
+            FeatureVariationRecord, FeatureVariations, LangSys, LangSysRecord, LookupFlag,
+            LookupList, Script, ScriptList, ScriptRecord, SequenceContext, SequenceLookupRecord,
         },
         variations::ivs_builder::VariationStoreBuilder,
     },
@@ -41,7 +43,6 @@ use write_fonts::{
 
 use crate::{
     common::{GlyphId16, GlyphOrClass, GlyphSet},
-    compile::lookups::contextual::ChainOrNot,
     Kind, Opts,
 };
 
@@ -52,6 +53,8 @@ use contextual::{
     SubChainContextBuilder, SubContextBuilder,
 };
 
+pub(crate) use contextual::{ChainOrNot, ContextBuilder};
+
 pub(crate) type FilterSetId = u16;
 
 #[derive(Clone, Debug, Default)]
@@ -81,7 +84,8 @@ pub(crate) enum PositionLookup {
 //
 // Note: this is only used in the API for adding external features ( aka feature
 // writers) and so we only implement the conversion for the specific lookup types
-// that we want to allow the client to add externally.
+// that we want to allow the client to add externally. Contextual lookups are
+// added via `ChainOrNot`, below.
 macro_rules! impl_into_lookup {
     ($builder:ty, $typ:ident, $variant:ident) => {
         impl From<LookupBuilder<$builder>> for $typ {
@@ -92,12 +96,36 @@ macro_rules! impl_into_lookup {
     };
 }
 
+impl_into_lookup!(SinglePosBuilder, PositionLookup, Single);
 impl_into_lookup!(PairPosBuilder, PositionLookup, Pair);
 impl_into_lookup!(MarkToBaseBuilder, PositionLookup, MarkToBase);
 impl_into_lookup!(MarkToMarkBuilder, PositionLookup, MarkToMark);
 impl_into_lookup!(MarkToLigBuilder, PositionLookup, MarkToLig);
 impl_into_lookup!(CursivePosBuilder, PositionLookup, Cursive);
 impl_into_lookup!(SingleSubBuilder, SubstitutionLookup, Single);
+impl_into_lookup!(MultipleSubBuilder, SubstitutionLookup, Multiple);
+impl_into_lookup!(AlternateSubBuilder, SubstitutionLookup, Alternate);
+impl_into_lookup!(LigatureSubBuilder, SubstitutionLookup, Ligature);
+
+impl From<ChainOrNot> for PositionLookup {
+    fn from(src: ChainOrNot) -> PositionLookup {
+        //NOTE: we currently force all GPOS7 into GPOS8, to match
+        //the behaviour of fonttools.
+        match src {
+            ChainOrNot::Context(lookup) => PositionLookup::ChainedContextual(lookup.convert()),
+            ChainOrNot::Chain(lookup) => PositionLookup::ChainedContextual(lookup.convert()),
+        }
+    }
+}
+
+impl From<ChainOrNot> for SubstitutionLookup {
+    fn from(src: ChainOrNot) -> SubstitutionLookup {
+        match src {
+            ChainOrNot::Context(lookup) => SubstitutionLookup::Contextual(lookup.convert()),
+            ChainOrNot::Chain(lookup) => SubstitutionLookup::ChainedContextual(lookup.convert()),
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub(crate) enum SubstitutionLookup {
@@ -183,6 +211,8 @@ pub(crate) struct PosSubBuilder<T> {
     features: BTreeMap<(Tag, Vec<LookupIdx>), FeatureIdx>,
     // map a conditionset to a map of target features and the lookups to substitute
     variations: HashMap<RawConditionSet, HashMap<FeatureIdx, Vec<LookupIdx>>>,
+    // merge lookups that compile to identical tables
+    dedup_lookups: bool,
 }
 
 trait RemapIds {
@@ -387,16 +417,7 @@ impl AllLookups {
                 let id = LookupId::Gpos(self.gpos.len());
                 assert_eq!(id, lookup.root_id); // sanity check
                 let (lookup, anon_lookups) = lookup.into_lookups();
-                match lookup {
-                    ChainOrNot::Context(lookup) => self
-                        .gpos
-                        //NOTE: we currently force all GPOS7 into GPOS8, to match
-                        //the behaviour of fonttools.
-                        .push(PositionLookup::ChainedContextual(lookup.convert())),
-                    ChainOrNot::Chain(lookup) => self
-                        .gpos
-                        .push(PositionLookup::ChainedContextual(lookup.convert())),
-                }
+                self.gpos.push(lookup.into());
                 self.gpos.extend(anon_lookups);
                 id
             }
@@ -404,14 +425,7 @@ impl AllLookups {
                 let id = LookupId::Gsub(self.gsub.len());
                 assert_eq!(id, lookup.root_id); // sanity check
                 let (lookup, anon_lookups) = lookup.into_lookups();
-                match lookup {
-                    ChainOrNot::Context(lookup) => self
-                        .gsub
-                        .push(SubstitutionLookup::Contextual(lookup.convert())),
-                    ChainOrNot::Chain(lookup) => self
-                        .gsub
-                        .push(SubstitutionLookup::ChainedContextual(lookup.convert())),
-                }
+                self.gsub.push(lookup.into());
                 self.gsub.extend(anon_lookups);
                 id
             }
@@ -735,7 +749,13 @@ impl AllLookups {
         opts: &Opts,
     ) -> (Option<write_gsub::Gsub>, Option<write_gpos::Gpos>) {
         let mut gpos_builder = PosSubBuilder::new(self.gpos.clone());
-        let mut gsub_builder = PosSubBuilder::new(self.gsub.clone());
+        let mut gsub = self.gsub.clone();
+        if opts.optimize_layout {
+            gsub.iter_mut().for_each(optimize::merge_subtables);
+        }
+        let mut gsub_builder = PosSubBuilder::new(gsub);
+        gpos_builder.dedup_lookups = opts.dedup_lookups;
+        gsub_builder.dedup_lookups = opts.dedup_lookups;
 
         for (key, feature_lookups) in features.iter() {
             let required = features.is_required(key);
@@ -1111,6 +1131,7 @@ impl<T> PosSubBuilder<T> {
             scripts: Default::default(),
             features: Default::default(),
             variations: Default::default(),
+            dedup_lookups: false,
         }
     }
 
@@ -1158,7 +1179,7 @@ impl<T> PosSubBuilder<T> {
 impl<T> PosSubBuilder<T>
 where
     T: Builder,
-    T::Output: Default,
+    T::Output: Default + Hash + Eq + RemapLookupIndices,
 {
     #[allow(clippy::type_complexity)] // i love my big dumb tuple
     fn build_raw(
@@ -1174,10 +1195,27 @@ where
             return None;
         }
 
+        let lookups = self
+            .lookups
+            .into_iter()
+            .map(|x| x.build(var_store))
+            .collect::<Vec<_>>();
+        let (lookups, id_map) = if self.dedup_lookups {
+            let lang_sys_lookups =
+                lookups_by_lang_sys(&self.scripts, &self.features, &self.variations);
+            dedup_lookups(lookups, lang_sys_lookups)
+        } else {
+            let id_map = (0..lookups.len() as LookupIdx).collect();
+            (lookups, id_map)
+        };
+        let remap = |ids: Vec<LookupIdx>| -> Vec<LookupIdx> {
+            ids.into_iter().map(|id| id_map[id as usize]).collect()
+        };
+
         // push empty items so we can insert by index
         let mut features = vec![Default::default(); self.features.len()];
         for ((tag, lookups), idx) in self.features {
-            features[idx as usize] = FeatureRecord::new(tag, Feature::new(None, lookups));
+            features[idx as usize] = FeatureRecord::new(tag, Feature::new(None, remap(lookups)));
         }
 
         let scripts = self
@@ -1198,12 +1236,6 @@ where
             })
             .collect::<Vec<_>>();
 
-        let lookups = self
-            .lookups
-            .into_iter()
-            .map(|x| x.build(var_store))
-            .collect();
-
         let variations = if self.variations.is_empty() {
             None
         } else {
@@ -1221,7 +1253,7 @@ where
                                 .map(|(feat_id, lookup_ids)| {
                                     FeatureTableSubstitutionRecord::new(
                                         feat_id,
-                                        Feature::new(None, lookup_ids),
+                                        Feature::new(None, remap(lookup_ids)),
                                     )
                                 })
                                 .collect(),
@@ -1241,6 +1273,203 @@ where
     }
 }
 
+/// For each language system, the set of lookups it may apply.
+///
+/// This includes lookups that are only used under some feature variation.
+fn lookups_by_lang_sys(
+    scripts: &BTreeMap<Tag, BTreeMap<Tag, LangSys>>,
+    features: &BTreeMap<(Tag, Vec<LookupIdx>), FeatureIdx>,
+    variations: &HashMap<RawConditionSet, HashMap<FeatureIdx, Vec<LookupIdx>>>,
+) -> Vec<BTreeSet<LookupIdx>> {
+    let mut feature_lookups = vec![Vec::new(); features.len()];
+    for ((_, lookups), idx) in features {
+        feature_lookups[*idx as usize].extend(lookups.iter().copied());
+    }
+    for (idx, lookups) in variations.values().flatten() {
+        feature_lookups[*idx as usize].extend(lookups.iter().copied());
+    }
+
+    scripts
+        .values()
+        .flat_map(|langs| langs.values())
+        .map(|lang_sys| {
+            lang_sys
+                .feature_indices
+                .iter()
+                .chain(Some(&lang_sys.required_feature_index))
+                .filter_map(|idx| feature_lookups.get(*idx as usize))
+                .flatten()
+                .copied()
+                .collect()
+        })
+        .collect()
+}
+
+/// Merge lookups that compile to the same table as some earlier lookup.
+///
+/// This happens when generated features (such as kerning) contain lookups that
+/// are also written out in the user's FEA. The lookups in a language system are
+/// applied in lookup list order, so a lookup is only merged if no language
+/// system that uses it also uses the earlier lookup, or any lookup between the
+/// two; otherwise the font would shape differently.
+///
+/// Returns the remaining lookups, and a map from old to new lookup indices.
+fn dedup_lookups<T>(
+    lookups: Vec<T>,
+    mut lang_sys_lookups: Vec<BTreeSet<LookupIdx>>,
+) -> (Vec<T>, Vec<LookupIdx>)
+where
+    T: Hash + Eq + RemapLookupIndices,
+{
+    let mut merge_into: Vec<Option<LookupIdx>> = vec![None; lookups.len()];
+    let mut first_seen = HashMap::new();
+    for (i, lookup) in lookups.iter().enumerate() {
+        let i: LookupIdx = i.try_into().expect("lookup index overflows u16");
+        let Some(&first) = first_seen.get(lookup) else {
+            first_seen.insert(lookup, i);
+            continue;
+        };
+        let can_merge = lang_sys_lookups
+            .iter()
+            .all(|ids| !ids.contains(&i) || ids.range(first..i).next().is_none());
+        if can_merge {
+            merge_into[i as usize] = Some(first);
+            // the merged lookup now runs in the position of the earlier one
+            for ids in lang_sys_lookups.iter_mut() {
+                if ids.remove(&i) {
+                    ids.insert(first);
+                }
+            }
+        }
+    }
+
+    let mut id_map = Vec::with_capacity(lookups.len());
+    let mut next_id = 0;
+    for target in &merge_into {
+        match target {
+            Some(first) => id_map.push(id_map[*first as usize]),
+            None => {
+                id_map.push(next_id);
+                next_id += 1;
+            }
+        }
+    }
+
+    if next_id as usize == lookups.len() {
+        return (lookups, id_map);
+    }
+
+    let mut lookups = lookups
+        .into_iter()
+        .zip(merge_into)
+        .filter_map(|(lookup, target)| target.is_none().then_some(lookup))
+        .collect::<Vec<_>>();
+    for lookup in &mut lookups {
+        lookup.remap_lookup_indices(&id_map);
+    }
+    (lookups, id_map)
+}
+
+/// Update the lookups referenced by a compiled (contextual) lookup.
+pub(crate) trait RemapLookupIndices {
+    fn remap_lookup_indices(&mut self, id_map: &[LookupIdx]);
+}
+
+impl RemapLookupIndices for write_gpos::PositionLookup {
+    fn remap_lookup_indices(&mut self, id_map: &[LookupIdx]) {
+        // we never generate extension lookups
+        match self {
+            write_gpos::PositionLookup::Contextual(lookup) => lookup
+                .subtables
+                .iter_mut()
+                .for_each(|sub| remap_sequence_context(sub, id_map)),
+            write_gpos::PositionLookup::ChainContextual(lookup) => lookup
+                .subtables
+                .iter_mut()
+                .for_each(|sub| remap_chained_sequence_context(sub, id_map)),
+            _ => (),
+        }
+    }
+}
+
+impl RemapLookupIndices for write_gsub::SubstitutionLookup {
+    fn remap_lookup_indices(&mut self, id_map: &[LookupIdx]) {
+        match self {
+            write_gsub::SubstitutionLookup::Contextual(lookup) => lookup
+                .subtables
+                .iter_mut()
+                .for_each(|sub| remap_sequence_context(sub, id_map)),
+            write_gsub::SubstitutionLookup::ChainContextual(lookup) => lookup
+                .subtables
+                .iter_mut()
+                .for_each(|sub| remap_chained_sequence_context(sub, id_map)),
+            _ => (),
+        }
+    }
+}
+
+fn remap_sequence_context(table: &mut SequenceContext, id_map: &[LookupIdx]) {
+    match table {
+        SequenceContext::Format1(table) => {
+            for rule_set in table.seq_rule_sets.iter_mut().filter_map(|x| x.as_mut()) {
+                for rule in rule_set.seq_rules.iter_mut() {
+                    remap_lookup_records(&mut rule.seq_lookup_records, id_map);
+                }
+            }
+        }
+        SequenceContext::Format2(table) => {
+            for rule_set in table
+                .class_seq_rule_sets
+                .iter_mut()
+                .filter_map(|x| x.as_mut())
+            {
+                for rule in rule_set.class_seq_rules.iter_mut() {
+                    remap_lookup_records(&mut rule.seq_lookup_records, id_map);
+                }
+            }
+        }
+        SequenceContext::Format3(table) => {
+            remap_lookup_records(&mut table.seq_lookup_records, id_map)
+        }
+    }
+}
+
+fn remap_chained_sequence_context(table: &mut ChainedSequenceContext, id_map: &[LookupIdx]) {
+    match table {
+        ChainedSequenceContext::Format1(table) => {
+            for rule_set in table
+                .chained_seq_rule_sets
+                .iter_mut()
+                .filter_map(|x| x.as_mut())
+            {
+                for rule in rule_set.chained_seq_rules.iter_mut() {
+                    remap_lookup_records(&mut rule.seq_lookup_records, id_map);
+                }
+            }
+        }
+        ChainedSequenceContext::Format2(table) => {
+            for rule_set in table
+                .chained_class_seq_rule_sets
+                .iter_mut()
+                .filter_map(|x| x.as_mut())
+            {
+                for rule in rule_set.chained_class_seq_rules.iter_mut() {
+                    remap_lookup_records(&mut rule.seq_lookup_records, id_map);
+                }
+            }
+        }
+        ChainedSequenceContext::Format3(table) => {
+            remap_lookup_records(&mut table.seq_lookup_records, id_map)
+        }
+    }
+}
+
+fn remap_lookup_records(records: &mut [SequenceLookupRecord], id_map: &[LookupIdx]) {
+    for record in records {
+        record.lookup_list_index = id_map[record.lookup_list_index as usize];
+    }
+}
+
 impl Builder for PosSubBuilder<PositionLookup> {
     type Output = Option<write_gpos::Gpos>;
 
@@ -1300,3 +1529,58 @@ fn is_gpos_rule(kind: Kind) -> bool {
             | Kind::GposType8
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a lookup that is identified by a name, and that may call another lookup
+    #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+    struct FakeLookup(&'static str, Option<LookupIdx>);
+
+    impl RemapLookupIndices for FakeLookup {
+        fn remap_lookup_indices(&mut self, id_map: &[LookupIdx]) {
+            if let Some(id) = self.1.as_mut() {
+                *id = id_map[*id as usize];
+            }
+        }
+    }
+
+    #[test]
+    fn dedup_lookups_in_separate_lang_systems() {
+        let lookups = vec![
+            FakeLookup("a", None),
+            FakeLookup("b", None),
+            FakeLookup("a", None),
+            FakeLookup("c", Some(2)),
+        ];
+        let lang_systems = vec![BTreeSet::from([0, 1]), BTreeSet::from([2, 3])];
+        let (lookups, id_map) = dedup_lookups(lookups, lang_systems);
+        assert_eq!(id_map, [0, 1, 0, 2]);
+        assert_eq!(
+            lookups,
+            [
+                FakeLookup("a", None),
+                FakeLookup("b", None),
+                FakeLookup("c", Some(0))
+            ]
+        );
+    }
+
+    #[test]
+    fn dont_dedup_if_order_would_change() {
+        let lookups = vec![
+            FakeLookup("a", None),
+            FakeLookup("b", None),
+            FakeLookup("a", None),
+        ];
+        // the same lang system applies the lookup twice
+        let (_, id_map) = dedup_lookups(lookups.clone(), vec![BTreeSet::from([0, 2])]);
+        assert_eq!(id_map, [0, 1, 2]);
+        // 'b' would run after 'a' instead of before it
+        let (_, id_map) = dedup_lookups(lookups.clone(), vec![BTreeSet::from([1, 2])]);
+        assert_eq!(id_map, [0, 1, 2]);
+        let (_, id_map) = dedup_lookups(lookups, vec![BTreeSet::from([0]), BTreeSet::from([2])]);
+        assert_eq!(id_map, [0, 1, 0]);
+    }
+}
@@ -52,6 +52,26 @@ pub(crate) enum ChainOrNot {
     Chain(LookupBuilder<ChainContextBuilder>),
 }
 
+impl ChainOrNot {
+    /// A lookup with these subtables, which only needs to be a chain lookup if
+    /// some rule has a backtrack or lookahead sequence.
+    pub(crate) fn new(
+        flags: LookupFlag,
+        mark_set: Option<FilterSetId>,
+        subtables: Vec<ContextBuilder>,
+    ) -> Self {
+        if subtables.iter().any(ContextBuilder::is_chain_rule) {
+            ChainOrNot::Chain(LookupBuilder::new_with_lookups(
+                flags,
+                mark_set,
+                subtables.into_iter().map(ChainContextBuilder).collect(),
+            ))
+        } else {
+            ChainOrNot::Context(LookupBuilder::new_with_lookups(flags, mark_set, subtables))
+        }
+    }
+}
+
 // a little helper trait for generating the right kind of LookupId
 trait MakeLookupId {
     fn make_id(raw: usize) -> LookupId;
@@ -91,15 +111,7 @@ impl<T> ContextualLookupBuilder<T> {
             ..
         } = self;
         finished_anon_lookups.extend(current_anon_lookups);
-        let lookup = if subtables.iter().any(ContextBuilder::is_chain_rule) {
-            ChainOrNot::Chain(LookupBuilder::new_with_lookups(
-                flags,
-                mark_set,
-                subtables.into_iter().map(ChainContextBuilder).collect(),
-            ))
-        } else {
-            ChainOrNot::Context(LookupBuilder::new_with_lookups(flags, mark_set, subtables))
-        };
+        let lookup = ChainOrNot::new(flags, mark_set, subtables);
         (lookup, finished_anon_lookups)
     }
 
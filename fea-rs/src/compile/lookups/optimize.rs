@@ -0,0 +1,182 @@
+//! Shrinking lookups before they are compiled
+//!
+//! Only GSUB subtables are merged. The GPOS builders from write-fonts don't let
+//! us read back their rules, which we would need to check coverage. The format
+//! of each subtable, coverage and class definition is chosen by write-fonts when
+//! it is built, for GPOS and GSUB alike, so there is nothing to select here.
+
+use std::collections::HashSet;
+
+use write_fonts::{
+    tables::{
+        gsub::builders::{
+            AlternateSubBuilder, LigatureSubBuilder, MultipleSubBuilder, SingleSubBuilder,
+        },
+        layout::builders::LookupBuilder,
+    },
+    types::GlyphId16,
+};
+
+use super::SubstitutionLookup;
+
+/// Merge neighbouring subtables that cover disjoint sets of glyphs.
+///
+/// A glyph is only handled by subtables whose coverage includes it, so if two
+/// adjacent subtables have no glyphs in common they can be combined without
+/// changing the result of shaping. This happens when the source contains
+/// explicit `subtable` statements, or when rules are appended to a lookup from
+/// different places.
+///
+/// The formats of the merged subtables (and their coverage tables) are chosen
+/// when they are built, so fewer subtables also means fewer, and often more
+/// compact, coverage tables.
+pub(super) fn merge_subtables(lookup: &mut SubstitutionLookup) {
+    match lookup {
+        SubstitutionLookup::Single(lookup) => merge_disjoint(lookup),
+        SubstitutionLookup::Multiple(lookup) => merge_disjoint(lookup),
+        SubstitutionLookup::Alternate(lookup) => merge_disjoint(lookup),
+        SubstitutionLookup::Ligature(lookup) => merge_disjoint(lookup),
+        // the coverage of these does not tell us which rules apply
+        SubstitutionLookup::Contextual(_)
+        | SubstitutionLookup::ChainedContextual(_)
+        | SubstitutionLookup::Reverse(_) => (),
+    }
+}
+
+fn merge_disjoint<T: MergeSubtable>(lookup: &mut LookupBuilder<T>) {
+    let mut merged: Vec<T> = Vec::with_capacity(lookup.subtables.len());
+    // the coverage of the last item in `merged`
+    let mut covered = HashSet::new();
+    for subtable in std::mem::take(&mut lookup.subtables) {
+        let coverage = subtable.coverage();
+        let disjoint = coverage.iter().all(|gid| !covered.contains(gid));
+        if let (Some(last), true) = (merged.last_mut(), disjoint) {
+            last.merge(subtable);
+            covered.extend(coverage);
+            continue;
+        }
+        covered = coverage.into_iter().collect();
+        merged.push(subtable);
+    }
+    lookup.subtables = merged;
+}
+
+/// A subtable builder that can absorb the rules of another.
+trait MergeSubtable {
+    /// The glyphs that this subtable may be applied to.
+    fn coverage(&self) -> Vec<GlyphId16>;
+
+    /// Add all of the rules in `other` to `self`.
+    fn merge(&mut self, other: Self);
+}
+
+impl MergeSubtable for SingleSubBuilder {
+    fn coverage(&self) -> Vec<GlyphId16> {
+        self.iter().map(|(target, _)| target).collect()
+    }
+
+    fn merge(&mut self, other: Self) {
+        for (target, replacement) in other.iter() {
+            self.insert(target, replacement);
+        }
+    }
+}
+
+impl MergeSubtable for MultipleSubBuilder {
+    fn coverage(&self) -> Vec<GlyphId16> {
+        self.iter().map(|(target, _)| *target).collect()
+    }
+
+    fn merge(&mut self, other: Self) {
+        for (target, replacement) in other.iter() {
+            self.insert(*target, replacement.clone());
+        }
+    }
+}
+
+impl MergeSubtable for AlternateSubBuilder {
+    fn coverage(&self) -> Vec<GlyphId16> {
+        self.iter().map(|(target, _)| *target).collect()
+    }
+
+    fn merge(&mut self, other: Self) {
+        for (target, alternates) in other.iter() {
+            self.insert(*target, alternates.clone());
+        }
+    }
+}
+
+impl MergeSubtable for LigatureSubBuilder {
+    fn coverage(&self) -> Vec<GlyphId16> {
+        self.iter().map(|(first, _)| *first).collect()
+    }
+
+    fn merge(&mut self, other: Self) {
+        for (first, ligatures) in other.iter() {
+            for (rest, replacement) in ligatures {
+                let target = std::iter::once(*first)
+                    .chain(rest.iter().copied())
+                    .collect();
+                self.insert(target, *replacement);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use write_fonts::tables::layout::LookupFlag;
+
+    use super::*;
+
+    fn gid(id: u16) -> GlyphId16 {
+        GlyphId16::new(id)
+    }
+
+    fn single_subtable(pairs: &[(u16, u16)]) -> SingleSubBuilder {
+        let mut builder = SingleSubBuilder::default();
+        for (target, replacement) in pairs {
+            builder.insert(gid(*target), gid(*replacement));
+        }
+        builder
+    }
+
+    #[test]
+    fn merge_only_disjoint_neighbours() {
+        let mut lookup = LookupBuilder::new(LookupFlag::empty(), None);
+        lookup.subtables = vec![
+            single_subtable(&[(1, 10), (2, 20)]),
+            single_subtable(&[(3, 30)]),
+            // 1 is covered by the first subtable, so this one must stay
+            single_subtable(&[(1, 11), (4, 40)]),
+            single_subtable(&[(2, 21)]),
+        ];
+        let mut lookup = SubstitutionLookup::Single(lookup);
+        merge_subtables(&mut lookup);
+        let SubstitutionLookup::Single(lookup) = lookup else {
+            unreachable!()
+        };
+        let subtables = lookup
+            .subtables
+            .iter()
+            .map(|sub| sub.iter().map(|(a, b)| (a.to_u16(), b.to_u16())).collect())
+            .collect::<Vec<Vec<_>>>();
+        assert_eq!(
+            subtables,
+            [
+                vec![(1, 10), (2, 20), (3, 30)],
+                vec![(1, 11), (2, 21), (4, 40)]
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_ligatures() {
+        let mut first = LigatureSubBuilder::default();
+        first.insert(vec![gid(1), gid(2)], gid(10));
+        let mut second = LigatureSubBuilder::default();
+        second.insert(vec![gid(3), gid(4), gid(5)], gid(11));
+        first.merge(second);
+        assert_eq!(first.coverage(), [gid(1), gid(3)]);
+    }
+}
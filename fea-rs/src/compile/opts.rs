@@ -14,6 +14,8 @@ pub struct Opts {
     pub(crate) max_n_errors: usize,
     pub(crate) compile_gsub: bool,
     pub(crate) compile_gpos: bool,
+    pub(crate) dedup_lookups: bool,
+    pub(crate) optimize_layout: bool,
 }
 
 impl Opts {
@@ -50,6 +52,28 @@ impl Opts {
         self.compile_gsub = flag;
         self
     }
+
+    /// Specify whether identical lookups should be merged. Default is `false`.
+    ///
+    /// This is useful when some lookups are generated by a [`FeatureProvider`],
+    /// and may duplicate lookups already written in the FEA. A lookup is only
+    /// merged when this does not change the order in which lookups are applied.
+    ///
+    /// [`FeatureProvider`]: crate::compile::FeatureProvider
+    pub fn dedup_lookups(mut self, flag: bool) -> Self {
+        self.dedup_lookups = flag;
+        self
+    }
+
+    /// Specify whether lookups should be made smaller where this doesn't
+    /// change their behaviour. Default is `false`.
+    ///
+    /// Currently this merges adjacent GSUB subtables that apply to disjoint
+    /// sets of glyphs. Leave this off to match the output of feaLib.
+    pub fn optimize_layout(mut self, flag: bool) -> Self {
+        self.optimize_layout = flag;
+        self
+    }
 }
 
 impl Default for Opts {
@@ -59,6 +83,8 @@ impl Default for Opts {
             max_n_errors: DEFAULT_N_MESSAGES_TO_PRINT,
             compile_gsub: true,
             compile_gpos: true,
+            dedup_lookups: false,
+            optimize_layout: false,
         }
     }
 }
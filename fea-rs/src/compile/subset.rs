@@ -0,0 +1,514 @@
+//! the subset pass
+//!
+//! To compile only some of a font's glyphs, the subset first has to grow to
+//! include whatever its glyphs can be substituted with, and the feature file
+//! then has to stop mentioning the glyphs that were left out. Both are done on
+//! the AST, against the glyph map of the whole font.
+
+use std::collections::{BTreeMap, HashMap};
+
+use smol_str::SmolStr;
+use write_fonts::types::GlyphId16;
+
+use crate::{
+    common::{GlyphIdent, GlyphSet},
+    token_tree::typed::{self, AstNode, ContextualRuleNode},
+    GlyphMap, Kind, Node, NodeOrToken, Token,
+};
+
+use super::{glyph_range, lint::visit};
+
+pub(crate) struct SubsetCtx<'a> {
+    glyph_map: &'a GlyphMap,
+    names: BTreeMap<GlyphId16, GlyphIdent>,
+    /// Every glyph in each named glyph class and mark class
+    classes: HashMap<SmolStr, Vec<GlyphId16>>,
+}
+
+impl<'a> SubsetCtx<'a> {
+    pub(crate) fn new(root: &Node, glyph_map: &'a GlyphMap) -> Self {
+        let mut ctx = SubsetCtx {
+            glyph_map,
+            names: glyph_map.reverse_map(),
+            classes: HashMap::new(),
+        };
+        visit(root, &mut |item| {
+            if let Some(def) = typed::GlyphClassDef::cast(item) {
+                let glyphs = match (def.class_def(), def.class_alias()) {
+                    (Some(literal), _) => ctx.literal(&literal),
+                    (None, Some(alias)) => ctx.named(&alias),
+                    (None, None) => Vec::new(),
+                };
+                ctx.classes.insert(def.class_name().text().clone(), glyphs);
+            } else if let Some(def) = typed::MarkClassDef::cast(item) {
+                let glyphs = ctx.resolve(&def.glyph_class());
+                ctx.classes
+                    .entry(def.mark_class_name().text().clone())
+                    .or_default()
+                    .extend(glyphs);
+            }
+        });
+        ctx
+    }
+
+    /// Add what `glyphs` can be substituted with, until nothing more is added.
+    ///
+    /// Context is ignored, so this may add glyphs that no input can reach.
+    pub(crate) fn close_over_substitutions(&self, root: &Node, glyphs: &mut GlyphSet) {
+        loop {
+            let len = glyphs.len();
+            visit(root, &mut |item| {
+                if let Some(rule) = typed::GsubStatement::cast(item) {
+                    self.close_over_rule(&rule, glyphs);
+                }
+            });
+            if glyphs.len() == len {
+                break;
+            }
+        }
+    }
+
+    fn close_over_rule(&self, rule: &typed::GsubStatement, glyphs: &mut GlyphSet) {
+        let (targets, replacements) = match rule {
+            typed::GsubStatement::Type1(rule) => {
+                let replacement = rule.replacement();
+                if let Some(pairs) = self.class_pairs(&rule.target(), replacement.as_ref()) {
+                    for (target, replacement) in pairs {
+                        if glyphs.contains(target) {
+                            glyphs.insert(replacement);
+                        }
+                    }
+                    return;
+                }
+                (
+                    vec![self.resolve(&rule.target())],
+                    replacement.map(|r| self.resolve(&r)).unwrap_or_default(),
+                )
+            }
+            typed::GsubStatement::Type2(rule) => (
+                vec![self.glyph(&rule.target())],
+                rule.replacement().flat_map(|g| self.glyph(&g)).collect(),
+            ),
+            typed::GsubStatement::Type3(rule) => (
+                vec![self.glyph(&rule.target())],
+                self.class(&rule.alternates()),
+            ),
+            typed::GsubStatement::Type4(rule) => (
+                rule.target().map(|t| self.resolve(&t)).collect(),
+                self.glyph(&rule.replacement()),
+            ),
+            typed::GsubStatement::Type6(rule) => {
+                let Some(inline) = rule.inline_rule() else {
+                    return;
+                };
+                self.close_over_inline(&rule.input(), &inline, glyphs);
+                return;
+            }
+            typed::GsubStatement::Type8(rule) => {
+                let Some(inline) = rule.inline_rule() else {
+                    return;
+                };
+                self.close_over_inline(&rule.input(), &inline, glyphs);
+                return;
+            }
+            // these only substitute by way of lookups, whose rules we visit anyway
+            typed::GsubStatement::Type5(_) | typed::GsubStatement::Ignore(_) => return,
+        };
+        if targets
+            .iter()
+            .all(|target| target.iter().any(|gid| glyphs.contains(*gid)))
+        {
+            for gid in replacements {
+                glyphs.insert(gid);
+            }
+        }
+    }
+
+    fn close_over_inline(
+        &self,
+        input: &typed::InputSequence,
+        inline: &typed::InlineSubRule,
+        glyphs: &mut GlyphSet,
+    ) {
+        let targets: Vec<_> = input.items().map(|item| item.target()).collect();
+        let replacements: Vec<_> = inline.replacements().collect();
+        if let ([target], [replacement]) = (targets.as_slice(), replacements.as_slice()) {
+            if let Some(pairs) = self.class_pairs(target, Some(replacement)) {
+                for (target, replacement) in pairs {
+                    if glyphs.contains(target) {
+                        glyphs.insert(replacement);
+                    }
+                }
+                return;
+            }
+        }
+        if targets.iter().all(|target| {
+            self.resolve(target)
+                .into_iter()
+                .any(|gid| glyphs.contains(gid))
+        }) {
+            for gid in replacements.iter().flat_map(|r| self.resolve(r)) {
+                glyphs.insert(gid);
+            }
+        }
+    }
+
+    /// The feature file, mentioning only the glyphs in `keep`.
+    ///
+    /// Glyphs that aren't kept are removed from classes, and statements that
+    /// mention one directly, or a class that is left empty, are removed.
+    pub(crate) fn subset(&self, root: &Node, keep: &GlyphSet) -> String {
+        let mut out = Writer::default();
+        self.subset_block(root, keep, &mut out);
+        out.text
+    }
+
+    fn subset_block(&self, node: &Node, keep: &GlyphSet, out: &mut Writer) {
+        for child in node.iter_children() {
+            match child {
+                NodeOrToken::Token(token) => out.push(token),
+                NodeOrToken::Node(node) if is_block(node) => self.subset_block(node, keep, out),
+                NodeOrToken::Node(_) => {
+                    if let Some(text) = self.subset_statement(child, keep) {
+                        out.text.push_str(&text);
+                    }
+                }
+            }
+        }
+    }
+
+    fn subset_statement(&self, item: &NodeOrToken, keep: &GlyphSet) -> Option<String> {
+        // class to class substitutions pair the glyphs up, so pairs are kept or
+        // removed together
+        if let Some(rule) = typed::Gsub1::cast(item) {
+            if let Some(pairs) = self.class_pairs(&rule.target(), rule.replacement().as_ref()) {
+                let (target, replacement): (Vec<_>, Vec<_>) = pairs
+                    .into_iter()
+                    .filter(|(target, replacement)| {
+                        keep.contains(*target) && keep.contains(*replacement)
+                    })
+                    .unzip();
+                if target.is_empty() {
+                    return None;
+                }
+                return Some(format!(
+                    "sub [{}] by [{}];",
+                    self.glyph_names(&target),
+                    self.glyph_names(&replacement)
+                ));
+            }
+        }
+        let inline = typed::Gsub6::cast(item)
+            .and_then(|rule| Some((rule.input(), rule.inline_rule()?)))
+            .or_else(|| {
+                typed::Gsub8::cast(item).and_then(|rule| Some((rule.input(), rule.inline_rule()?)))
+            });
+        if let Some((input, inline)) = inline {
+            let targets: Vec<_> = input.items().map(|item| item.target()).collect();
+            let replacements: Vec<_> = inline.replacements().collect();
+            if let ([target], [replacement]) = (targets.as_slice(), replacements.as_slice()) {
+                let pairs = self.class_pairs(target, Some(replacement));
+                if pairs.is_some_and(|pairs| {
+                    pairs.iter().any(|(target, replacement)| {
+                        keep.contains(*target) != keep.contains(*replacement)
+                    })
+                }) {
+                    log::warn!(
+                        "removing a contextual substitution whose classes can't be subset together"
+                    );
+                    return None;
+                }
+            }
+        }
+
+        let mut out = Writer::default();
+        self.write_statement(item.as_node()?, keep, &mut out)
+            .then_some(out.text)
+    }
+
+    /// Write the statement, returning `false` if it has to be removed
+    fn write_statement(&self, node: &Node, keep: &GlyphSet, out: &mut Writer) -> bool {
+        // entries of a GDEF GlyphClassDef may be left empty
+        let may_be_empty = node.kind() == Kind::GdefClassDefEntryNode;
+        for child in node.iter_children() {
+            match child {
+                NodeOrToken::Token(token) => match token.kind {
+                    Kind::GlyphName | Kind::Cid if !self.is_kept(token, keep) => return false,
+                    Kind::NamedGlyphClass if self.is_empty(&token.text, keep) => {
+                        if !may_be_empty {
+                            return false;
+                        }
+                        out.skip();
+                    }
+                    _ => out.push(token),
+                },
+                NodeOrToken::Node(class) if class.kind() == Kind::GlyphClass => {
+                    let mut members = Writer::default();
+                    if self.write_class(class, keep, &mut members) {
+                        out.text.push_str(&members.text);
+                    } else if !may_be_empty {
+                        return false;
+                    }
+                }
+                NodeOrToken::Node(child) => {
+                    if !self.write_statement(child, keep, out) {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Write the kept members of a class literal, returning `false` if there are none
+    fn write_class(&self, node: &Node, keep: &GlyphSet, out: &mut Writer) -> bool {
+        let mut any_kept = false;
+        for child in node.iter_children() {
+            match child {
+                NodeOrToken::Token(token) => {
+                    let kept = match token.kind {
+                        Kind::GlyphName | Kind::Cid => self.is_kept(token, keep),
+                        Kind::NamedGlyphClass => !self.is_empty(&token.text, keep),
+                        _ => {
+                            out.push(token);
+                            continue;
+                        }
+                    };
+                    if kept {
+                        any_kept = true;
+                        out.push(token);
+                    } else {
+                        out.skip();
+                    }
+                }
+                NodeOrToken::Node(range) if range.kind() == Kind::GlyphRange => {
+                    let kept: Vec<_> = self
+                        .range(range)
+                        .into_iter()
+                        .filter(|gid| keep.contains(*gid))
+                        .collect();
+                    if !kept.is_empty() {
+                        any_kept = true;
+                        out.text.push_str(&self.glyph_names(&kept));
+                    }
+                }
+                NodeOrToken::Node(node) => node.iter_tokens().for_each(|token| out.push(token)),
+            }
+        }
+        any_kept
+    }
+
+    fn is_kept(&self, token: &Token, keep: &GlyphSet) -> bool {
+        let gid = match token.kind {
+            Kind::Cid => token
+                .text
+                .parse::<u16>()
+                .ok()
+                .and_then(|cid| self.glyph_map.get(&cid)),
+            _ => self.glyph_map.get(&token.text),
+        };
+        // validation will report glyphs we don't know
+        gid.map(|gid| keep.contains(gid)).unwrap_or(true)
+    }
+
+    fn is_empty(&self, class: &SmolStr, keep: &GlyphSet) -> bool {
+        self.classes
+            .get(class)
+            .is_some_and(|glyphs| !glyphs.iter().any(|gid| keep.contains(*gid)))
+    }
+
+    /// The glyphs of a class to class substitution, in pairs
+    fn class_pairs(
+        &self,
+        target: &typed::GlyphOrClass,
+        replacement: Option<&typed::GlyphOrClass>,
+    ) -> Option<Vec<(GlyphId16, GlyphId16)>> {
+        let replacement = replacement.filter(|r| r.is_class())?;
+        if !target.is_class() {
+            return None;
+        }
+        let (target, replacement) = (self.resolve(target), self.resolve(replacement));
+        (target.len() == replacement.len()).then(|| target.into_iter().zip(replacement).collect())
+    }
+
+    fn resolve(&self, item: &typed::GlyphOrClass) -> Vec<GlyphId16> {
+        match item {
+            typed::GlyphOrClass::Glyph(name) => {
+                self.glyph_map.get(name.text()).into_iter().collect()
+            }
+            typed::GlyphOrClass::Cid(cid) => self.glyph_map.get(&cid.parse()).into_iter().collect(),
+            typed::GlyphOrClass::NamedClass(name) => self.named(name),
+            typed::GlyphOrClass::Class(literal) => self.literal(literal),
+            typed::GlyphOrClass::Null(_) => Vec::new(),
+        }
+    }
+
+    fn glyph(&self, glyph: &typed::Glyph) -> Vec<GlyphId16> {
+        match glyph {
+            typed::Glyph::Named(name) => self.glyph_map.get(name.text()).into_iter().collect(),
+            typed::Glyph::Cid(cid) => self.glyph_map.get(&cid.parse()).into_iter().collect(),
+            typed::Glyph::Null(_) => Vec::new(),
+        }
+    }
+
+    fn class(&self, class: &typed::GlyphClass) -> Vec<GlyphId16> {
+        match class {
+            typed::GlyphClass::Named(name) => self.named(name),
+            typed::GlyphClass::Literal(literal) => self.literal(literal),
+        }
+    }
+
+    fn named(&self, name: &typed::GlyphClassName) -> Vec<GlyphId16> {
+        self.classes.get(name.text()).cloned().unwrap_or_default()
+    }
+
+    fn literal(&self, literal: &typed::GlyphClassLiteral) -> Vec<GlyphId16> {
+        let mut glyphs = Vec::new();
+        for item in literal.items() {
+            if let Some(range) = item.as_node().filter(|n| n.kind() == Kind::GlyphRange) {
+                glyphs.extend(self.range(range));
+            } else if let Some(item) = typed::GlyphOrClass::cast(item) {
+                glyphs.extend(self.resolve(&item));
+            }
+        }
+        glyphs
+    }
+
+    fn range(&self, node: &Node) -> Vec<GlyphId16> {
+        let Some(range) = typed::GlyphRange::try_from_node(node) else {
+            return Vec::new();
+        };
+        let (start, end) = (range.start(), range.end());
+        let mut glyphs = Vec::new();
+        // a malformed range is reported by validation
+        let _ = match start.kind {
+            Kind::Cid => {
+                glyph_range::cid(start, end, |cid| glyphs.extend(self.glyph_map.get(&cid)))
+            }
+            _ => glyph_range::named(start, end, |name| glyphs.extend(self.glyph_map.get(name))),
+        };
+        glyphs
+    }
+
+    /// The glyphs as they would be written in a class, escaped
+    fn glyph_names(&self, glyphs: &[GlyphId16]) -> String {
+        glyphs
+            .iter()
+            .filter_map(|gid| self.names.get(gid))
+            .map(|ident| match ident {
+                GlyphIdent::Name(name) => format!("\\{name}"),
+                GlyphIdent::Cid(cid) => format!("\\{cid}"),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Feature, lookup and table blocks, as opposed to statements
+fn is_block(node: &Node) -> bool {
+    node.kind() == Kind::SourceFile
+        || node
+            .iter_children()
+            .any(|child| child.kind() == Kind::LBrace)
+}
+
+/// Accumulates the text of the subset feature file
+#[derive(Default)]
+struct Writer {
+    text: String,
+    /// A backslash escapes the token after it, so it goes wherever that does
+    backslash: bool,
+}
+
+impl Writer {
+    fn push(&mut self, token: &Token) {
+        if token.kind == Kind::Backslash {
+            self.backslash = true;
+            return;
+        }
+        if std::mem::take(&mut self.backslash) {
+            self.text.push('\\');
+        }
+        self.text.push_str(&token.text);
+    }
+
+    /// Leave out the next token, and the space before it
+    fn skip(&mut self) {
+        self.backslash = false;
+        self.text.truncate(self.text.trim_end().len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fontdrasil::types::GlyphName;
+
+    use super::*;
+
+    const GLYPHS: &[&str] = &[
+        ".notdef", "a", "b", "c", "f", "i", "f_i", "a.sc", "b.sc", "c.sc", "a.alt", "acute",
+    ];
+
+    fn glyph_map() -> GlyphMap {
+        GLYPHS.iter().map(|name| GlyphName::new(*name)).collect()
+    }
+
+    fn glyph_set(glyph_map: &GlyphMap, names: &[&str]) -> GlyphSet {
+        names
+            .iter()
+            .map(|name| glyph_map.get(*name).unwrap())
+            .collect()
+    }
+
+    fn names(glyph_map: &GlyphMap, glyphs: &GlyphSet) -> Vec<String> {
+        let names = glyph_map.reverse_map();
+        glyphs.iter().map(|gid| names[&gid].to_string()).collect()
+    }
+
+    fn parse(fea: &str) -> Node {
+        let (tree, diagnostics) = crate::parse::parse_string(fea);
+        assert!(!diagnostics.has_errors(), "{}", diagnostics.display());
+        tree.root().clone()
+    }
+
+    const FEA: &str = "\
+@lc = [a b c];
+@sc = [a.sc b.sc c.sc];
+markClass [acute] <anchor 0 500> @TOP;
+feature smcp { sub @lc by @sc; } smcp;
+feature liga { sub f i by f_i; } liga;
+feature salt { sub a from [a.alt]; } salt;
+feature mark { pos base [a b] <anchor 250 500> mark @TOP; } mark;
+";
+
+    #[test]
+    fn closure_follows_substitutions() {
+        let glyph_map = glyph_map();
+        let root = parse(FEA);
+        let ctx = SubsetCtx::new(&root, &glyph_map);
+        let mut glyphs = glyph_set(&glyph_map, &["a", "f"]);
+        ctx.close_over_substitutions(&root, &mut glyphs);
+        // f_i needs an i as well
+        assert_eq!(vec!["a", "f", "a.sc", "a.alt"], names(&glyph_map, &glyphs));
+    }
+
+    #[test]
+    fn subset_removes_rules_for_missing_glyphs() {
+        let glyph_map = glyph_map();
+        let root = parse(FEA);
+        let ctx = SubsetCtx::new(&root, &glyph_map);
+        let keep = glyph_set(&glyph_map, &["a", "c", "f", "a.sc", "c.sc", "a.alt"]);
+        assert_eq!(
+            "\
+@lc = [a c];
+@sc = [a.sc c.sc];
+
+feature smcp { sub [\\a \\c] by [\\a.sc \\c.sc]; } smcp;
+feature liga {  } liga;
+feature salt { sub a from [a.alt]; } salt;
+feature mark {  } mark;
+",
+            ctx.subset(&root, &keep)
+        );
+    }
+}
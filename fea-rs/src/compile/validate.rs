@@ -9,6 +9,7 @@ use std::{
     ops::Range,
 };
 
+use fontdrasil::coords::NormalizedCoord;
 use smol_str::SmolStr;
 use write_fonts::{read::tables::name::Encoding, types::Tag};
 
@@ -235,7 +236,15 @@ impl<'a, V: VariationInfo> ValidationCtx<'a, V> {
             self.warning(node.label().range(), "duplicate condition set definition");
         }
 
+        let mut seen_axes = HashSet::new();
         for condition in node.conditions() {
+            let tag = condition.tag();
+            if !seen_axes.insert(tag.to_raw()) {
+                self.error(
+                    tag.range(),
+                    format!("repeated condition for axis '{}'", tag.to_raw()),
+                );
+            }
             self.validate_condition(&condition);
         }
     }
@@ -249,7 +258,7 @@ impl<'a, V: VariationInfo> ValidationCtx<'a, V> {
             self.error(condition.tag().range(), "unknown axis");
             return;
         };
-        if (condition.min_value().parse_signed() as f64) < axis.min.into_inner().0 {
+        if condition.min_value().parse() < axis.min.into_inner().0 {
             self.error(
                 condition.min_value().range(),
                 format!(
@@ -258,7 +267,7 @@ impl<'a, V: VariationInfo> ValidationCtx<'a, V> {
                 ),
             );
         }
-        if (condition.max_value().parse_signed() as f64) > axis.max.into_inner().0 {
+        if condition.max_value().parse() > axis.max.into_inner().0 {
             self.error(
                 condition.max_value().range(),
                 format!(
@@ -426,34 +435,43 @@ impl<'a, V: VariationInfo> ValidationCtx<'a, V> {
 
     fn validate_stat(&mut self, node: &typed::StatTable) {
         let mut seen_fallback_name = false;
+        // axis values can come before the axes they refer to
+        let mut design_axes = HashMap::new();
+        for item in node.statements() {
+            if let typed::StatTableItem::DesignAxis(axis) = item {
+                let tag = axis.tag();
+                if design_axes.insert(tag.to_raw(), tag.clone()).is_some() {
+                    self.error(
+                        tag.range(),
+                        format!("DesignAxis already defined for '{}'", tag.to_raw()),
+                    );
+                }
+                if axis.ordering().parse_unsigned().is_none() {
+                    self.error(axis.ordering().range(), "expected positive number");
+                }
+                if axis.names().next().is_none() {
+                    self.error(tag.range(), "DesignAxis must have a name");
+                }
+            }
+        }
+
         for item in node.statements() {
             match item {
-                typed::StatTableItem::ElidedFallbackName(_) => {
+                typed::StatTableItem::ElidedFallbackName(name) => {
                     if seen_fallback_name {
-                        self.error(item.range(), "fallback name must only be defined once");
+                        self.error(name.range(), "fallback name must only be defined once");
                     }
                     seen_fallback_name = true;
-                }
-                typed::StatTableItem::AxisValue(axis) => {
-                    let mut seen_location_format = None;
-                    for item in axis.statements() {
-                        if let typed::StatAxisValueItem::Location(loc) = item {
-                            let format = match loc.value() {
-                                typed::StatLocationValue::Value(_) => 'a',
-                                typed::StatLocationValue::MinMax { .. } => 'b',
-                                typed::StatLocationValue::Linked { .. } => 'c',
-                            };
-                            let prev_format = seen_location_format.replace(format);
-                            match (prev_format, format) {
-                                (Some('a'), 'a') => (),
-                                (Some(_), 'a') => self.error(loc.range(), "multiple location statements, but previous statement was not format 'a'"),
-                                (Some(_), 'b' | 'c') => self.error(loc.range(),format!("location statement format '{format}' must be only statement")),
-                                _ => (),
-                            }
+                    if let Some(id) = name.elided_fallback_name_id() {
+                        if id.parse_unsigned().is_none() {
+                            self.error(id.range(), "expected positive number");
                         }
                     }
                 }
-                _ => (),
+                typed::StatTableItem::AxisValue(axis) => {
+                    self.validate_stat_axis_value(&axis, &design_axes)
+                }
+                typed::StatTableItem::DesignAxis(_) => (),
             }
         }
         if !seen_fallback_name {
@@ -464,6 +482,54 @@ impl<'a, V: VariationInfo> ValidationCtx<'a, V> {
         }
     }
 
+    fn validate_stat_axis_value(
+        &mut self,
+        node: &typed::StatAxisValue,
+        design_axes: &HashMap<Tag, typed::Tag>,
+    ) {
+        let mut seen_location_format = None;
+        let mut seen_tags = HashSet::new();
+        let mut has_name = false;
+        for item in node.statements() {
+            match item {
+                typed::StatAxisValueItem::Location(loc) => {
+                    let format = match loc.value() {
+                        typed::StatLocationValue::Value(_) => 'a',
+                        typed::StatLocationValue::MinMax { .. } => 'b',
+                        typed::StatLocationValue::Linked { .. } => 'c',
+                    };
+                    let prev_format = seen_location_format.replace(format);
+                    match (prev_format, format) {
+                        (Some('a'), 'a') => (),
+                        (Some(_), 'a') => self.error(loc.range(), "multiple location statements, but previous statement was not format 'a'"),
+                        (Some(_), 'b' | 'c') => self.error(loc.range(),format!("location statement format '{format}' must be only statement")),
+                        _ => (),
+                    }
+                    let tag = loc.tag();
+                    if !design_axes.contains_key(&tag.to_raw()) {
+                        self.error(
+                            tag.range(),
+                            format!("no DesignAxis defined for '{}'", tag.to_raw()),
+                        );
+                    } else if !seen_tags.insert(tag.to_raw()) {
+                        self.error(
+                            tag.range(),
+                            format!("multiple locations for axis '{}'", tag.to_raw()),
+                        );
+                    }
+                }
+                typed::StatAxisValueItem::NameRecord(_) => has_name = true,
+                typed::StatAxisValueItem::Flag(_) => (),
+            }
+        }
+        if seen_location_format.is_none() {
+            self.error(node.keyword().range(), "AxisValue must have a location");
+        }
+        if !has_name {
+            self.error(node.keyword().range(), "AxisValue must have a name");
+        }
+    }
+
     fn validate_name(&mut self, node: &typed::NameTable) {
         for record in node.statements() {
             let name_id = record.name_id();
@@ -1324,13 +1390,20 @@ impl<'a, V: VariationInfo> ValidationCtx<'a, V> {
             return;
         };
 
+        // as in feaLib, there must be a value at the default location, where
+        // any axes that aren't mentioned are
+        let mut has_default = false;
+        let mut has_errors = false;
         for location_val in metric.location_values() {
+            let mut is_default = true;
             for item in location_val.location().items() {
                 let Some((_, axis)) = var_info.axis(item.axis_tag().to_raw()) else {
                     self.error(item.axis_tag().range(), "unknown axis");
+                    has_errors = true;
                     continue;
                 };
                 let val = item.value().parse();
+                is_default &= val.to_normalized(axis) == NormalizedCoord::new(0.0);
                 match val {
                     super::AxisLocation::User(val) => {
                         let min = axis.min.into_inner().0;
@@ -1340,6 +1413,7 @@ impl<'a, V: VariationInfo> ValidationCtx<'a, V> {
                                 item.value().range(),
                                 format!("value exceeds expected range ({min}, {max})"),
                             );
+                            has_errors = true;
                         }
                     }
                     super::AxisLocation::Design(_) => (), // we don't have info to validate this
@@ -1349,10 +1423,18 @@ impl<'a, V: VariationInfo> ValidationCtx<'a, V> {
                                 item.value().range(),
                                 "normalized value should be in range (-1.0, 1.0)",
                             );
+                            has_errors = true;
                         }
                     }
                 }
             }
+            has_default |= is_default;
+        }
+        if !has_default && !has_errors {
+            self.error(
+                metric.range(),
+                "variable metric needs a value at the default location",
+            );
         }
     }
 
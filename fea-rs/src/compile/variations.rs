@@ -5,7 +5,10 @@ use std::{
     fmt::{Debug, Display},
 };
 
-use fontdrasil::{coords::NormalizedLocation, types::Axis};
+use fontdrasil::{
+    coords::{DesignCoord, NormalizedCoord, NormalizedLocation, UserCoord},
+    types::Axis,
+};
 use ordered_float::OrderedFloat;
 use write_fonts::{tables::variations::VariationRegion, types::Tag};
 
@@ -105,10 +108,21 @@ pub enum AxisLocation {
     Normalized(OrderedFloat<f64>),
 }
 
+impl AxisLocation {
+    /// This location on `axis`, normalized
+    pub(crate) fn to_normalized(self, axis: &Axis) -> NormalizedCoord {
+        match self {
+            AxisLocation::Normalized(value) => NormalizedCoord::new(value),
+            AxisLocation::User(value) => UserCoord::new(value).to_normalized(&axis.converter),
+            AxisLocation::Design(value) => DesignCoord::new(value).to_normalized(&axis.converter),
+        }
+    }
+}
+
 /// Create an axis where user coords == design coords
 #[cfg(any(test, feature = "test", feature = "cli"))]
 fn simple_axis(tag: Tag, min: i16, default: i16, max: i16) -> Axis {
-    use fontdrasil::coords::{CoordConverter, DesignCoord, UserCoord};
+    use fontdrasil::coords::CoordConverter;
 
     let min = UserCoord::new(min);
     let default = UserCoord::new(default);
@@ -3,7 +3,7 @@ use crate::{
     parse::{FileId, SourceList},
     ParseTree,
 };
-use std::{convert::TryInto, ops::Range, sync::Arc};
+use std::{convert::TryInto, ops::Range, path::Path, sync::Arc};
 
 /// A span of a source file.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -161,6 +161,15 @@ impl DiagnosticSet {
         &self.messages
     }
 
+    /// The path of the source a diagnostic is in, and the line and column its span starts at
+    ///
+    /// The line is 1-indexed, the column is the 0-indexed utf-8 offset into the line.
+    pub fn location(&self, diagnostic: &Diagnostic) -> Option<(&Path, usize, usize)> {
+        let source = self.sources.get(&diagnostic.message.file)?;
+        let (line, column) = source.line_col_for_offset(diagnostic.span().start);
+        Some((source.path(), line, column))
+    }
+
     /// Returns an opaque type that can pretty-print the diagnostics
     pub fn display(&self) -> impl std::fmt::Display + '_ {
         DiagnosticDisplayer(self)
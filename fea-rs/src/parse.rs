@@ -19,7 +19,7 @@ pub use lexer::TokenSet;
 pub use source::{FileSystemResolver, SourceLoadError, SourceResolver};
 pub use tree::ParseTree;
 
-pub(crate) use context::{IncludeStatement, ParseContext};
+pub(crate) use context::{parse_src, IncludeStatement, ParseContext};
 pub(crate) use parser::Parser;
 pub(crate) use source::{FileId, Source, SourceList, SourceMap};
 
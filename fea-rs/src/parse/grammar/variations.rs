@@ -13,13 +13,17 @@ pub(crate) fn condition_set(parser: &mut Parser) {
     // a condition looks like:
     // <tag> <min> <max>;, e.g.
     // wght 100 300;
+    // the values may also be floats, e.g. wdth 75.5 100;
     fn condition(parser: &mut Parser) -> bool {
         if !parser.matches(0, TokenSet::TAG_LIKE) {
             return false;
         }
         parser.in_node(AstKind::ConditionNode, |parser| {
-            parser.expect_tag(TokenSet::TOP_SEMI.add(Kind::Number));
-            parser.repeat(|p| p.expect_recover(Kind::Number, TokenSet::TOP_SEMI), 2);
+            parser.expect_tag(TokenSet::TOP_SEMI.union(TokenSet::FLOAT_LIKE));
+            parser.repeat(
+                |p| p.expect_recover(TokenSet::FLOAT_LIKE, TokenSet::TOP_SEMI),
+                2,
+            );
             parser.expect_semi();
         });
 
@@ -47,6 +47,38 @@ fn parse_bad() -> Result<(), Report> {
     .into_error()
 }
 
+// formatting should only ever change whitespace, and formatting twice
+// should be the same as formatting once
+#[test]
+fn format_good() {
+    let glyph_map = parse_test_glyph_order();
+    let non_whitespace = |text: &str| {
+        let source = crate::parse::Source::new("format_good".into(), text.into());
+        let (node, _, _) = crate::parse::parse_src(&source, Some(&glyph_map));
+        node.iter_tokens()
+            .filter(|t| t.kind != crate::Kind::Whitespace)
+            .map(|t| t.text.clone())
+            .collect::<Vec<_>>()
+    };
+    for path in test_utils::iter_fea_files(PARSE_GOOD, Filter::from_env()) {
+        let fea = std::fs::read_to_string(&path).unwrap();
+        let formatted = crate::util::format_string(fea.as_str(), Some(&glyph_map))
+            .unwrap_or_else(|e| panic!("{}: {}", path.display(), e.display()));
+        assert_eq!(
+            non_whitespace(&fea),
+            non_whitespace(&formatted),
+            "{}",
+            path.display()
+        );
+        assert_eq!(
+            formatted,
+            crate::util::format_string(formatted.as_str(), Some(&glyph_map)).unwrap(),
+            "{}",
+            path.display()
+        );
+    }
+}
+
 fn parse_test_glyph_order() -> GlyphMap {
     let raw_glyphs = std::fs::read_to_string(GLYPH_ORDER_PATH).unwrap();
     crate::compile::parse_glyph_order(&raw_glyphs)
@@ -1,5 +1,6 @@
 //! helpers and utilties (mostly for testing/debugging?)
 
+mod formatting;
 pub(crate) mod highlighting;
 pub mod paths;
 #[cfg(any(test, feature = "diff"))]
@@ -7,6 +8,7 @@ pub mod pretty_diff;
 #[cfg(any(test, feature = "test"))]
 pub mod ttx;
 
+pub use formatting::{format_node, format_string};
 pub use highlighting::style_for_kind;
 #[cfg(any(test, feature = "diff"))]
 pub use pretty_diff::write_line_diff;
@@ -0,0 +1,207 @@
+//! Re-serializing FEA with consistent whitespace
+
+use std::{path::Path, sync::Arc};
+
+use crate::{
+    parse::{self, SourceLoadError},
+    DiagnosticSet, GlyphMap, Kind, Node, NodeOrToken,
+};
+
+const INDENT: &str = "    ";
+
+/// Parse and format a block of FEA.
+///
+/// The contents of any included files are not formatted; the include
+/// statements themselves are left in place.
+///
+/// The `glyph_map`, if provided, is used to disambiguate glyph names that
+/// contain hyphens from glyph ranges, as when parsing.
+///
+/// Returns the diagnostics if the input could not be parsed.
+pub fn format_string(
+    text: impl Into<Arc<str>>,
+    glyph_map: Option<&GlyphMap>,
+) -> Result<String, DiagnosticSet> {
+    const SRC_NAME: &str = "util::format_string";
+    let text = text.into();
+    let root_text = text.clone();
+    // included files are treated as empty; we only care that this one parses
+    let (_, diagnostics) = parse::parse_root(
+        SRC_NAME.into(),
+        glyph_map,
+        Box::new(move |path: &Path| -> Result<Arc<str>, SourceLoadError> {
+            if path == Path::new(SRC_NAME) {
+                Ok(root_text.clone())
+            } else {
+                Ok("".into())
+            }
+        }),
+    )
+    .unwrap();
+    if diagnostics.has_errors() {
+        return Err(diagnostics);
+    }
+    let source = parse::Source::new(SRC_NAME.into(), text);
+    let (node, _, _) = parse::parse_src(&source, glyph_map);
+    Ok(format_node(&node))
+}
+
+/// Format a parsed node, such as the root of a [`ParseTree`].
+///
+/// Each statement is written on its own line, indented by its depth in
+/// blocks, with single spaces between tokens. Comments are kept, as are
+/// single blank lines between statements.
+///
+/// If the node contains errors the output may not be valid FEA.
+///
+/// [`ParseTree`]: crate::ParseTree
+pub fn format_node(node: &Node) -> String {
+    let mut formatter = Formatter::default();
+    formatter.node(node);
+    formatter.finish()
+}
+
+#[derive(Default)]
+struct Formatter {
+    out: String,
+    depth: usize,
+    prev: Option<Kind>,
+    // newlines and whether there was any whitespace since the previous token
+    newlines: usize,
+    had_whitespace: bool,
+    // the next token starts a new line
+    break_before: bool,
+}
+
+impl Formatter {
+    fn node(&mut self, node: &Node) {
+        for child in node.iter_children() {
+            match child {
+                NodeOrToken::Token(token) => self.token(token.kind, &token.text),
+                // the contents of an anonymous block aren't FEA
+                NodeOrToken::Node(node) if node.kind() == Kind::AnonBlockNode => {
+                    let text = node.iter_tokens().map(|t| t.as_str()).collect::<String>();
+                    self.start_line(true);
+                    self.out.push_str(&text);
+                    self.prev = Some(Kind::AnonBlockNode);
+                    self.newlines = 0;
+                    self.had_whitespace = false;
+                    self.break_before = true;
+                }
+                NodeOrToken::Node(node) => {
+                    self.node(node);
+                    // the semicolon after an include is optional
+                    if node.kind() == Kind::IncludeNode {
+                        self.break_before = true;
+                    }
+                }
+            }
+        }
+    }
+
+    fn token(&mut self, kind: Kind, text: &str) {
+        match kind {
+            Kind::Whitespace => {
+                self.newlines += text.matches('\n').count();
+                self.had_whitespace = true;
+                return;
+            }
+            Kind::Eof => return,
+            // a comment at the end of a line stays there
+            Kind::Comment if self.break_before && self.newlines == 0 && !self.out.is_empty() => {
+                self.out.push(' ');
+                self.out.push_str(text);
+                self.newlines = 0;
+                self.had_whitespace = false;
+                return;
+            }
+            Kind::RBrace => self.depth = self.depth.saturating_sub(1),
+            _ => (),
+        }
+
+        let starts_line = self.break_before
+            || self.out.is_empty()
+            || kind == Kind::RBrace
+            || (kind == Kind::Comment && self.newlines > 0);
+        if starts_line {
+            self.start_line(kind != Kind::RBrace);
+        } else if self.needs_space(kind) {
+            self.out.push(' ');
+        }
+        self.out.push_str(text);
+
+        self.prev = Some(kind);
+        self.newlines = 0;
+        self.had_whitespace = false;
+        self.break_before = matches!(kind, Kind::Semi | Kind::LBrace | Kind::Comment);
+        if kind == Kind::LBrace {
+            self.depth += 1;
+        }
+    }
+
+    /// Start a new line, preceded by a blank one if there was one in the source
+    /// and `allow_blank` is true.
+    fn start_line(&mut self, allow_blank: bool) {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+            // never at the start of a block
+            if self.newlines > 1 && allow_blank && self.prev != Some(Kind::LBrace) {
+                self.out.push('\n');
+            }
+        }
+        for _ in 0..self.depth {
+            self.out.push_str(INDENT);
+        }
+        self.break_before = false;
+    }
+
+    fn needs_space(&self, kind: Kind) -> bool {
+        if matches!(
+            kind,
+            Kind::Semi
+                | Kind::Comma
+                | Kind::RSquare
+                | Kind::RParen
+                | Kind::RAngle
+                | Kind::SingleQuote
+        ) || matches!(
+            self.prev,
+            Some(Kind::LSquare | Kind::LParen | Kind::LAngle | Kind::Backslash)
+        ) {
+            return false;
+        }
+        // otherwise we only collapse whitespace; the absence of any can be
+        // significant, as in `wght=200:-100`
+        self.had_whitespace
+            || kind == Kind::LBrace
+            || matches!(self.prev, Some(Kind::Comma | Kind::RBrace))
+    }
+
+    fn finish(mut self) -> String {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_whitespace() {
+        let fea = "languagesystem DFLT dflt;\n\n\n@upper = [ A  B\n C ];\nfeature liga {sub f  i by f_i; # fi\n\n\tpos [ a b ] <0 0 100 0>;\n\n} liga;\ntable GDEF {GlyphClassDef @upper,,,;}GDEF;\n";
+        let expected = "languagesystem DFLT dflt;\n\n@upper = [A B C];\nfeature liga {\n    sub f i by f_i; # fi\n\n    pos [a b] <0 0 100 0>;\n} liga;\ntable GDEF {\n    GlyphClassDef @upper, , , ;\n} GDEF;\n";
+        let formatted = format_string(fea, None).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format_string(formatted.as_str(), None).unwrap(), expected);
+    }
+
+    #[test]
+    fn keep_includes() {
+        let fea = "include( other.fea )\nfeature kern {\n    include(kern.fea);\n} kern;\n";
+        let expected = "include(other.fea)\nfeature kern {\n    include(kern.fea);\n} kern;\n";
+        assert_eq!(format_string(fea, None).unwrap(), expected);
+    }
+}
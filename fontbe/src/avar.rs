@@ -1,16 +1,26 @@
 //! Generates a [avar](https://learn.microsoft.com/en-us/typography/opentype/spec/avar) table.
 
+use std::collections::HashMap;
+
 use fontdrasil::{
-    coords::NormalizedCoord,
+    coords::{NormalizedCoord, NormalizedLocation},
     orchestration::{Access, Work},
-    types::Axis,
+    types::{Axes, Axis},
+};
+use fontir::{
+    ir::AxisMapping,
+    orchestration::{Persistable, WorkId as FeWorkId},
+    variations::VariationModel,
 };
-use fontir::orchestration::{Persistable, WorkId as FeWorkId};
 use log::debug;
 use write_fonts::{
     read::FontRead,
-    tables::avar::{Avar, AxisValueMap, SegmentMaps},
+    tables::{
+        avar::{Avar, AxisValueMap, SegmentMaps},
+        variations::{ivs_builder::VariationStoreBuilder, DeltaSetIndexMap},
+    },
     types::F2Dot14,
+    NullableOffsetMarker, OtRound,
 };
 
 use crate::{
@@ -122,6 +132,66 @@ fn to_segment_map(axis: &Axis) -> SegmentMaps {
     SegmentMaps::new(mappings)
 }
 
+/// Add the avar version 2 index map and variation store for `mappings`.
+///
+/// Each axis gets a delta set, in F2Dot14 units, that moves the coordinates
+/// of each mapping's input to its output. Matches fontTools.varLib:
+/// <https://github.com/fonttools/fonttools/blob/1b0e8ac/Lib/fontTools/varLib/__init__.py#L185-L240>
+fn add_axis_mappings(avar: &mut Avar, axes: &Axes, mappings: &[AxisMapping]) -> Result<(), Error> {
+    let mut sources: HashMap<NormalizedLocation, Vec<f64>> = mappings
+        .iter()
+        .map(|mapping| {
+            let deltas = axes
+                .iter()
+                .map(|axis| {
+                    let Some(output) = mapping.output.get(axis.tag) else {
+                        return 0.0;
+                    };
+                    let input = mapping.input.get(axis.tag).map(|c| c.to_f64());
+                    ((output.to_f64() - input.unwrap_or_default()) * 16384.0).ot_round()
+                })
+                .collect();
+            (mapping.input.clone(), deltas)
+        })
+        .collect();
+    // The model needs a source at the default, which maps to itself
+    if !sources.keys().any(|loc| loc.is_default()) {
+        sources.insert(NormalizedLocation::new(), vec![0.0; axes.len()]);
+    }
+
+    let model = VariationModel::new(sources.keys().cloned().collect(), axes.clone())
+        .map_err(Error::AvarModelError)?;
+    let deltas = model.deltas(&sources).map_err(Error::AvarDeltaError)?;
+
+    let mut builder = VariationStoreBuilder::new(axes.len() as u16);
+    let delta_ids: Vec<_> = (0..axes.len())
+        .map(|axis_idx| {
+            builder.add_deltas(
+                deltas
+                    .iter()
+                    .filter(|(region, _)| !region.is_default())
+                    .map(|(region, values)| {
+                        // A delta may be as large as 2.0, which doesn't fit an i16
+                        let delta: f64 = values[axis_idx].ot_round();
+                        (region.to_write_fonts_variation_region(axes), delta as i32)
+                    })
+                    .collect(),
+            )
+        })
+        .collect();
+    let (var_store, index_map) = builder.build();
+    // unwrap since VariationStoreBuilder guarantees that any temporary index returned by
+    // add_deltas will exist in the returned map
+    let axis_index_map: DeltaSetIndexMap = delta_ids
+        .into_iter()
+        .map(|id| index_map.get(id).unwrap())
+        .collect();
+
+    avar.axis_index_map = NullableOffsetMarker::new(Some(axis_index_map));
+    avar.var_store = NullableOffsetMarker::new(Some(var_store));
+    Ok(())
+}
+
 impl Work<Context, AnyWorkId, Error> for AvarWork {
     fn id(&self) -> AnyWorkId {
         WorkId::Avar.into()
@@ -142,8 +212,17 @@ impl Work<Context, AnyWorkId, Error> for AvarWork {
             return Ok(());
         }
         let axis_segment_maps: Vec<_> = static_metadata.axes.iter().map(to_segment_map).collect();
-        // only when all the segment maps are uninteresting, we can omit avar
-        let avar = if axis_segment_maps.iter().any(|segmap| !segmap.is_identity()) {
+        // only when all the segment maps are uninteresting, and there are no
+        // avar2 mappings, we can omit avar
+        let avar = if !static_metadata.axis_mappings.is_empty() {
+            let mut avar = Avar::new(axis_segment_maps);
+            add_axis_mappings(
+                &mut avar,
+                &static_metadata.axes,
+                &static_metadata.axis_mappings,
+            )?;
+            PossiblyEmptyAvar::NonEmpty(avar)
+        } else if axis_segment_maps.iter().any(|segmap| !segmap.is_identity()) {
             PossiblyEmptyAvar::NonEmpty(Avar::new(axis_segment_maps))
         } else {
             PossiblyEmptyAvar::Empty
@@ -156,14 +235,20 @@ impl Work<Context, AnyWorkId, Error> for AvarWork {
 #[cfg(test)]
 mod tests {
     use fontdrasil::{
-        coords::{CoordConverter, DesignCoord, UserCoord},
-        types::Axis,
+        coords::{CoordConverter, DesignCoord, NormalizedLocation, UserCoord},
+        types::{Axes, Axis},
     };
+    use fontir::ir::AxisMapping;
     use std::{cmp, str::FromStr};
-    use write_fonts::tables::avar::SegmentMaps;
-    use write_fonts::types::Tag;
+    use write_fonts::tables::avar::{Avar, SegmentMaps};
+    use write_fonts::{
+        dump_table,
+        read::{tables::avar as read_avar, FontData, FontRead},
+        types::{F2Dot14, MajorMinor, Tag},
+    };
 
-    use super::{default_segment_map, to_segment_map};
+    use super::{add_axis_mappings, default_segment_map, to_segment_map};
+    use crate::test_util;
 
     fn axis(mappings: Vec<(UserCoord, DesignCoord)>, default_idx: usize) -> Axis {
         let default_idx = cmp::min(mappings.len() - 1, default_idx);
@@ -258,4 +343,41 @@ mod tests {
             dump(to_segment_map(&axis(mappings, 3)))
         );
     }
+
+    #[test]
+    fn axis_mappings_make_avar2() {
+        let axes: Axes = vec![
+            test_util::axis("movx", 0.0, 0.0, 1000.0),
+            test_util::axis("movy", 0.0, 0.0, 1000.0),
+        ]
+        .into();
+        // moving all the way along x also moves halfway along y
+        let mappings = vec![AxisMapping {
+            input: NormalizedLocation::for_pos(&[("movx", 1.0)]),
+            output: NormalizedLocation::for_pos(&[("movx", 1.0), ("movy", 0.5)]),
+        }];
+        let mut avar = Avar::new(axes.iter().map(to_segment_map).collect());
+        add_axis_mappings(&mut avar, &axes, &mappings).unwrap();
+
+        let bytes = dump_table(&avar).unwrap();
+        let avar = read_avar::Avar::read(FontData::new(&bytes)).unwrap();
+        assert_eq!(MajorMinor::VERSION_2_0, avar.version());
+        let index_map = avar.axis_index_map().unwrap().unwrap();
+        let var_store = avar.var_store().unwrap().unwrap();
+        let delta = |axis_idx: u32, coords: &[f32]| {
+            let coords: Vec<_> = coords.iter().map(|c| F2Dot14::from_f32(*c)).collect();
+            var_store
+                .compute_delta(index_map.get(axis_idx).unwrap(), &coords)
+                .unwrap()
+        };
+        assert_eq!(
+            vec![0, 8192, 0, 4096],
+            vec![
+                delta(0, &[1.0, 0.0]),
+                delta(1, &[1.0, 0.0]),
+                delta(0, &[0.5, 0.0]),
+                delta(1, &[0.5, 0.0]),
+            ]
+        );
+    }
 }
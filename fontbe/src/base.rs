@@ -0,0 +1,146 @@
+//! Generate a [BASE](https://learn.microsoft.com/en-us/typography/opentype/spec/base) table
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use fontdrasil::orchestration::{Access, Work};
+use fontir::{ir::ScriptBaselines, orchestration::WorkId as FeWorkId};
+use log::warn;
+use write_fonts::{
+    tables::base::{
+        Axis, Base, BaseCoord, BaseScript, BaseScriptList, BaseScriptRecord, BaseTagList,
+        BaseValues,
+    },
+    types::Tag,
+};
+
+use crate::{
+    error::Error,
+    orchestration::{AnyWorkId, BeWork, Context, WorkId},
+};
+
+#[derive(Debug)]
+struct BaseWork;
+
+pub fn create_base_work() -> Box<BeWork> {
+    Box::new(BaseWork)
+}
+
+impl Work<Context, AnyWorkId, Error> for BaseWork {
+    fn id(&self) -> AnyWorkId {
+        WorkId::Base.into()
+    }
+
+    fn read_access(&self) -> Access<AnyWorkId> {
+        Access::Variant(FeWorkId::StaticMetadata.into())
+    }
+
+    fn exec(&self, context: &Context) -> Result<(), Error> {
+        let static_metadata = context.ir.static_metadata.get();
+        let Some(base_table) = static_metadata.misc.base_table.as_ref() else {
+            return Ok(());
+        };
+
+        let horiz_axis = make_axis("horizontal", &base_table.horizontal);
+        let vert_axis = make_axis("vertical", &base_table.vertical);
+        if horiz_axis.is_none() && vert_axis.is_none() {
+            return Ok(());
+        }
+        context.base.set(Base::new(horiz_axis, vert_axis));
+        Ok(())
+    }
+}
+
+/// The BASE axis for the scripts of one direction.
+///
+/// Every script records a position for each baseline of the axis, so a script
+/// that doesn't give one for a baseline another script uses is left out.
+fn make_axis(direction: &str, scripts: &BTreeMap<Tag, ScriptBaselines>) -> Option<Axis> {
+    let baseline_tags: Vec<Tag> = scripts
+        .values()
+        .flat_map(|baselines| baselines.positions.keys().copied())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    let records: Vec<_> = scripts
+        .iter()
+        .filter_map(|(script, baselines)| {
+            let coords = baseline_tags
+                .iter()
+                .map(|tag| baselines.positions.get(tag).copied())
+                .collect::<Option<Vec<_>>>();
+            let default_index = baseline_tags.iter().position(|t| *t == baselines.default);
+            let (Some(coords), Some(default_index)) = (coords, default_index) else {
+                warn!("Dropping {direction} baselines of '{script}', it needs a position for each of {baseline_tags:?}");
+                return None;
+            };
+            let values = BaseValues::new(
+                default_index as u16,
+                coords.into_iter().map(BaseCoord::format_1).collect(),
+            );
+            Some(BaseScriptRecord::new(
+                *script,
+                BaseScript::new(Some(values), None, Vec::new()),
+            ))
+        })
+        .collect();
+    if records.is_empty() {
+        return None;
+    }
+    Some(Axis::new(
+        Some(BaseTagList::new(baseline_tags)),
+        BaseScriptList::new(records),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use fontir::ir::ScriptBaselines;
+    use write_fonts::types::Tag;
+
+    use super::make_axis;
+
+    fn baselines(default: &[u8; 4], positions: &[(&[u8; 4], i16)]) -> ScriptBaselines {
+        ScriptBaselines {
+            default: Tag::new(default),
+            positions: positions
+                .iter()
+                .map(|(tag, position)| (Tag::new(tag), *position))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn scripts_missing_a_baseline_are_dropped() {
+        let scripts = BTreeMap::from([
+            (
+                Tag::new(b"hani"),
+                baselines(b"ideo", &[(b"ideo", -120), (b"romn", 0)]),
+            ),
+            (Tag::new(b"latn"), baselines(b"romn", &[(b"romn", 0)])),
+        ]);
+
+        let axis = make_axis("horizontal", &scripts).unwrap();
+
+        let tags = axis.base_tag_list.as_ref().unwrap();
+        assert_eq!(
+            vec![Tag::new(b"ideo"), Tag::new(b"romn")],
+            tags.baseline_tags
+        );
+        assert_eq!(
+            vec![Tag::new(b"hani")],
+            axis.base_script_list
+                .base_script_records
+                .iter()
+                .map(|r| r.base_script_tag)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn no_axis_without_scripts() {
+        assert!(make_axis("vertical", &BTreeMap::new()).is_none());
+    }
+}
@@ -0,0 +1,331 @@
+//! Generate [CBDT](https://learn.microsoft.com/en-us/typography/opentype/spec/cbdt)
+//! and [CBLC](https://learn.microsoft.com/en-us/typography/opentype/spec/cblc) tables
+//!
+//! Only written if [Flags::CBDT_BITMAPS] is set, otherwise bitmaps go in sbix.
+//! Every glyph uses image format 17, small metrics and PNG data, and every strike
+//! has a single format 1 index subtable spanning its first to last glyph.
+
+use fontdrasil::{
+    orchestration::{Access, AccessBuilder, Work},
+    types::GlyphName,
+};
+use fontir::{
+    ir::{BitmapStrike, GlyphOrder},
+    orchestration::{Flags, WorkId as FeWorkId},
+};
+use log::warn;
+use write_fonts::types::GlyphId16;
+
+use crate::{
+    error::Error,
+    orchestration::{AnyWorkId, BeWork, Context, WorkId},
+};
+
+/// Small metrics and PNG data
+const IMAGE_FORMAT: u16 = 17;
+/// Each glyph has its own offset
+const INDEX_FORMAT: u16 = 1;
+/// The size of a CBLC BitmapSize record
+const BITMAP_SIZE_LEN: usize = 48;
+/// The size of the CBLC header
+const HEADER_LEN: usize = 8;
+/// 8-bit RGBA
+const BIT_DEPTH: u8 = 32;
+/// The strike has horizontal metrics
+const FLAGS_HORIZONTAL_METRICS: u8 = 0x01;
+
+#[derive(Debug)]
+struct CbdtWork;
+
+pub fn create_cbdt_work() -> Box<BeWork> {
+    Box::new(CbdtWork)
+}
+
+impl Work<Context, AnyWorkId, Error> for CbdtWork {
+    fn id(&self) -> AnyWorkId {
+        WorkId::Cbdt.into()
+    }
+
+    fn read_access(&self) -> Access<AnyWorkId> {
+        AccessBuilder::new()
+            .variant(FeWorkId::StaticMetadata)
+            .variant(FeWorkId::GlyphOrder)
+            .variant(FeWorkId::ALL_GLYPHS)
+            .variant(FeWorkId::BitmapStrikes)
+            .build()
+    }
+
+    fn write_access(&self) -> Access<AnyWorkId> {
+        AccessBuilder::new()
+            .variant(WorkId::Cbdt)
+            .variant(WorkId::Cblc)
+            .build()
+    }
+
+    fn also_completes(&self) -> Vec<AnyWorkId> {
+        vec![WorkId::Cblc.into()]
+    }
+
+    fn exec(&self, context: &Context) -> Result<(), Error> {
+        if !context.flags.contains(Flags::CBDT_BITMAPS) {
+            return Ok(());
+        }
+        let Some(strikes) = context.ir.bitmap_strikes.try_get() else {
+            return Ok(());
+        };
+        let static_metadata = context.ir.static_metadata.get();
+        let glyph_order = context.ir.glyph_order.get();
+
+        let mut sizes = Vec::new();
+        for (ppem, strike) in strikes.strikes.iter() {
+            let Ok(ppem) = u8::try_from(*ppem) else {
+                warn!("Dropping the {ppem} ppem bitmaps, CBLC sizes can't exceed 255");
+                continue;
+            };
+            let glyphs = strike_glyphs(strike, &glyph_order, ppem, |name| {
+                let advance = context.ir.get_glyph(name.clone()).default_instance().width;
+                advance * ppem as f64 / static_metadata.units_per_em as f64
+            });
+            if !glyphs.is_empty() {
+                sizes.push((ppem, glyphs));
+            }
+        }
+        if sizes.is_empty() {
+            return Ok(());
+        }
+        let (cbdt, cblc) = to_table_bytes(&sizes);
+        context.cbdt.set(cbdt.into());
+        context.cblc.set(cblc.into());
+        Ok(())
+    }
+}
+
+/// A bitmap as laid out in CBDT
+#[derive(Debug, Clone, PartialEq)]
+struct SizedBitmap<'a> {
+    gid: GlyphId16,
+    height: u8,
+    width: u8,
+    bearing_x: i8,
+    bearing_y: i8,
+    advance: u8,
+    png: &'a [u8],
+}
+
+/// The bitmaps of a strike that small metrics can describe, in glyph id order
+fn strike_glyphs<'a>(
+    strike: &'a BitmapStrike,
+    glyph_order: &GlyphOrder,
+    ppem: u8,
+    advance: impl Fn(&GlyphName) -> f64,
+) -> Vec<SizedBitmap<'a>> {
+    let mut glyphs: Vec<_> = strike
+        .glyphs
+        .iter()
+        .filter_map(|(name, bitmap)| {
+            let gid = glyph_order.glyph_id(name)?;
+            let metrics = bitmap.size().and_then(|(width, height)| {
+                Some((
+                    u8::try_from(height).ok()?,
+                    u8::try_from(width).ok()?,
+                    i8::try_from(bitmap.left).ok()?,
+                    i8::try_from(bitmap.bottom as i32 + height as i32).ok()?,
+                    u8::try_from(advance(name).round() as i64).ok()?,
+                ))
+            });
+            let Some((height, width, bearing_x, bearing_y, advance)) = metrics else {
+                warn!("Dropping the {ppem} ppem bitmap of {name}, it's not a PNG whose metrics fit in CBDT small metrics");
+                return None;
+            };
+            Some(SizedBitmap {
+                gid,
+                height,
+                width,
+                bearing_x,
+                bearing_y,
+                advance,
+                png: &bitmap.png,
+            })
+        })
+        .collect();
+    glyphs.sort_by_key(|glyph| glyph.gid);
+    glyphs
+}
+
+/// Version 3.0 CBDT and CBLC tables, with a size per strike
+fn to_table_bytes(sizes: &[(u8, Vec<SizedBitmap>)]) -> (Vec<u8>, Vec<u8>) {
+    let mut cbdt = Vec::new();
+    cbdt.extend(3u16.to_be_bytes());
+    cbdt.extend(0u16.to_be_bytes());
+
+    let mut bitmap_sizes = Vec::new();
+    let mut index_subtables = Vec::new();
+    let index_start = HEADER_LEN + BITMAP_SIZE_LEN * sizes.len();
+    for (ppem, glyphs) in sizes {
+        let first = glyphs.first().unwrap().gid;
+        let last = glyphs.last().unwrap().gid;
+
+        // glyph data, with an offset from the first glyph for every glyph in range
+        let image_data_offset = cbdt.len();
+        let mut offsets = Vec::new();
+        let mut glyphs_iter = glyphs.iter().peekable();
+        for gid in first.to_u16()..=last.to_u16() {
+            offsets.push((cbdt.len() - image_data_offset) as u32);
+            let Some(glyph) = glyphs_iter.next_if(|glyph| glyph.gid.to_u16() == gid) else {
+                continue;
+            };
+            cbdt.extend([glyph.height, glyph.width]);
+            cbdt.extend(glyph.bearing_x.to_be_bytes());
+            cbdt.extend(glyph.bearing_y.to_be_bytes());
+            cbdt.push(glyph.advance);
+            cbdt.extend((glyph.png.len() as u32).to_be_bytes());
+            cbdt.extend(glyph.png);
+        }
+        offsets.push((cbdt.len() - image_data_offset) as u32);
+
+        // a single record, then its subtable
+        let subtable_list_offset = index_start + index_subtables.len();
+        let mut subtable_list = Vec::new();
+        subtable_list.extend(first.to_u16().to_be_bytes());
+        subtable_list.extend(last.to_u16().to_be_bytes());
+        subtable_list.extend(8u32.to_be_bytes());
+        subtable_list.extend(INDEX_FORMAT.to_be_bytes());
+        subtable_list.extend(IMAGE_FORMAT.to_be_bytes());
+        subtable_list.extend((image_data_offset as u32).to_be_bytes());
+        for offset in offsets {
+            subtable_list.extend(offset.to_be_bytes());
+        }
+
+        bitmap_sizes.extend((subtable_list_offset as u32).to_be_bytes());
+        bitmap_sizes.extend((subtable_list.len() as u32).to_be_bytes());
+        bitmap_sizes.extend(1u32.to_be_bytes());
+        // colorRef
+        bitmap_sizes.extend(0u32.to_be_bytes());
+        let line_metrics = line_metrics(glyphs);
+        bitmap_sizes.extend(line_metrics);
+        bitmap_sizes.extend(line_metrics);
+        bitmap_sizes.extend(first.to_u16().to_be_bytes());
+        bitmap_sizes.extend(last.to_u16().to_be_bytes());
+        bitmap_sizes.extend([*ppem, *ppem, BIT_DEPTH, FLAGS_HORIZONTAL_METRICS]);
+
+        index_subtables.extend(subtable_list);
+    }
+
+    let mut cblc = Vec::new();
+    cblc.extend(3u16.to_be_bytes());
+    cblc.extend(0u16.to_be_bytes());
+    cblc.extend((sizes.len() as u32).to_be_bytes());
+    cblc.extend(bitmap_sizes);
+    cblc.extend(index_subtables);
+    (cbdt, cblc)
+}
+
+/// SbitLineMetrics for the bitmaps of a strike
+fn line_metrics(glyphs: &[SizedBitmap]) -> [u8; 12] {
+    let clamp = |v: i32| v.clamp(i8::MIN as i32, i8::MAX as i32) as i8 as u8;
+    let ascender = glyphs.iter().map(|g| g.bearing_y as i32).max().unwrap();
+    let descender = glyphs
+        .iter()
+        .map(|g| g.bearing_y as i32 - g.height as i32)
+        .min()
+        .unwrap();
+    let width_max = glyphs.iter().map(|g| g.width).max().unwrap();
+    let min_origin_sb = glyphs.iter().map(|g| g.bearing_x as i32).min().unwrap();
+    let min_advance_sb = glyphs
+        .iter()
+        .map(|g| g.advance as i32 - g.bearing_x as i32 - g.width as i32)
+        .min()
+        .unwrap();
+    [
+        clamp(ascender),
+        clamp(descender),
+        width_max,
+        // an upright caret
+        1,
+        0,
+        0,
+        clamp(min_origin_sb),
+        clamp(min_advance_sb),
+        clamp(ascender),
+        clamp(descender),
+        0,
+        0,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use write_fonts::{
+        read::{
+            tables::{
+                bitmap::{BitmapDataFormat, SmallGlyphMetrics},
+                cbdt::Cbdt,
+                cblc::Cblc,
+            },
+            FontData, FontRead,
+        },
+        types::{GlyphId, GlyphId16},
+    };
+
+    use super::{to_table_bytes, SizedBitmap};
+
+    fn bitmap(gid: u16, png: &[u8]) -> SizedBitmap {
+        SizedBitmap {
+            gid: GlyphId16::new(gid),
+            height: 128,
+            width: 136,
+            bearing_x: 0,
+            bearing_y: 101,
+            advance: 136,
+            png,
+        }
+    }
+
+    #[test]
+    fn sparse_glyphs_read_back() {
+        let sizes = [(109, vec![bitmap(2, b"first"), bitmap(4, b"second")])];
+
+        let (cbdt, cblc) = to_table_bytes(&sizes);
+        let cbdt = Cbdt::read(FontData::new(&cbdt)).unwrap();
+        let cblc = Cblc::read(FontData::new(&cblc)).unwrap();
+
+        let size = &cblc.bitmap_sizes()[0];
+        assert_eq!(
+            (101, -27, 136, 109, 32),
+            (
+                size.hori.ascender(),
+                size.hori.descender(),
+                size.hori.width_max(),
+                size.ppem_x(),
+                size.bit_depth()
+            )
+        );
+        let mut read_back = Vec::new();
+        for gid in 2..=4 {
+            let location = size
+                .location(cblc.offset_data(), GlyphId::new(gid))
+                .unwrap();
+            if location.data_size == 0 {
+                continue;
+            }
+            let data = cbdt.data(&location).unwrap();
+            let (format, png) = data.content.extract_data();
+            assert_eq!(BitmapDataFormat::Png, format);
+            assert_eq!(
+                SmallGlyphMetrics {
+                    height: 128,
+                    width: 136,
+                    bearing_x: 0.into(),
+                    bearing_y: 101.into(),
+                    advance: 136,
+                },
+                data.extract_small_metrics()
+            );
+            read_back.push((gid, png.to_vec()));
+        }
+        assert_eq!(
+            vec![(2, b"first".to_vec()), (4, b"second".to_vec())],
+            read_back
+        );
+    }
+}
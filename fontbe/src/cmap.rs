@@ -1,9 +1,19 @@
 //! Generates a [cmap](https://learn.microsoft.com/en-us/typography/opentype/spec/cmap) table.
 
-use fontdrasil::orchestration::{Access, AccessBuilder, Work};
-use fontir::orchestration::WorkId as FeWorkId;
+use std::collections::{HashMap, HashSet};
 
-use write_fonts::{tables::cmap::Cmap, types::GlyphId16};
+use fontdrasil::orchestration::{Access, AccessBuilder, Work};
+use fontir::{
+    ir::{CodepointRange, GlyphOrder},
+    orchestration::{Flags, WorkId as FeWorkId},
+};
+use log::warn;
+use write_fonts::{
+    tables::cmap::{
+        Cmap, CmapSubtable, ConstantMapGroup, EncodingRecord, PlatformId, SequentialMapGroup,
+    },
+    types::{GlyphId, GlyphId16},
+};
 
 use crate::{
     error::Error,
@@ -24,6 +34,7 @@ impl Work<Context, AnyWorkId, Error> for CmapWork {
 
     fn read_access(&self) -> Access<AnyWorkId> {
         AccessBuilder::new()
+            .variant(FeWorkId::StaticMetadata)
             .variant(FeWorkId::GlyphOrder)
             .variant(FeWorkId::ALL_GLYPHS)
             .build()
@@ -34,25 +45,379 @@ impl Work<Context, AnyWorkId, Error> for CmapWork {
         // cmap only accomodates single codepoint : glyph mappings; collect all of those
         let glyph_order = context.ir.glyph_order.get();
 
-        let mappings = glyph_order
-            .names()
-            .map(|glyph_name| context.ir.get_glyph(glyph_name.clone()))
-            .enumerate()
-            .flat_map(|(gid, glyph)| {
-                glyph
-                    .codepoints
-                    .iter()
-                    .map(|codepoint| {
-                        (
-                            char::from_u32(*codepoint).expect("We have an invalid codepoint!"),
-                            GlyphId16::new(gid as u16).into(),
-                        )
-                    })
-                    .collect::<Vec<_>>()
-            });
-
-        let cmap = Cmap::from_mappings(mappings)?;
+        let mut mappings = Vec::new();
+        for (index, glyph_name) in glyph_order.names().enumerate() {
+            // checking every glyph, not just mapped ones, also keeps the ids of the
+            // glyphs format 13 ranges map to in bounds
+            let gid = glyph_id(index)?;
+            let glyph = context.ir.get_glyph(glyph_name.clone());
+            mappings.extend(glyph.codepoints.iter().map(|codepoint| {
+                (
+                    char::from_u32(*codepoint).expect("We have an invalid codepoint!"),
+                    GlyphId::from(gid),
+                )
+            }));
+        }
+        let mut cmap = Cmap::from_mappings(mappings.iter().copied())?;
+        if context.flags.contains(Flags::OMIT_CMAP_FORMAT_4) {
+            replace_format_4(&mut cmap, &mappings);
+        }
+        if context.flags.contains(Flags::CMAP_MAC_ROMAN) {
+            let subtable = mac_roman_subtable(&mappings)?.ok_or_else(|| {
+                Error::InvalidCmap(
+                    "a Macintosh Roman subtable was requested but no glyph maps to a Mac Roman character"
+                        .to_string(),
+                )
+            })?;
+            cmap.encoding_records
+                .push(EncodingRecord::new(PlatformId::Macintosh, 0, subtable));
+        }
+
+        let static_metadata = context.ir.static_metadata.get();
+        let mapped = mappings.iter().map(|(c, _)| *c as u32).collect();
+        let groups = constant_map_groups(&static_metadata.misc.cmap_ranges, &glyph_order, &mapped);
+        if !groups.is_empty() {
+            // header is 16 bytes, each group 12
+            let length = 16 + 12 * groups.len() as u32;
+            let subtable = CmapSubtable::format_13(length, 0, groups.len() as u32, groups);
+            // records are sorted by platform then encoding; (0, 6) is the last Unicode one
+            let pos = cmap
+                .encoding_records
+                .iter()
+                .position(|rec| rec.platform_id != PlatformId::Unicode)
+                .unwrap_or(cmap.encoding_records.len());
+            cmap.encoding_records
+                .insert(pos, EncodingRecord::new(PlatformId::Unicode, 6, subtable));
+        }
+        cmap.encoding_records
+            .sort_by_key(|rec| (rec.platform_id, rec.encoding_id));
+        if let Some(dupe) = cmap
+            .encoding_records
+            .windows(2)
+            .find(|w| (w[0].platform_id, w[0].encoding_id) == (w[1].platform_id, w[1].encoding_id))
+        {
+            return Err(Error::InvalidCmap(format!(
+                "multiple subtables for platform {:?} encoding {}",
+                dupe[0].platform_id, dupe[0].encoding_id
+            )));
+        }
         context.cmap.set(cmap);
         Ok(())
     }
 }
+
+/// The id of the glyph at `index` in the glyph order, if it fits in 16 bits
+fn glyph_id(index: usize) -> Result<GlyphId16, Error> {
+    u32::try_from(index)
+        .ok()
+        .and_then(|index| GlyphId16::try_from(GlyphId::new(index)).ok())
+        .ok_or_else(|| Error::OutOfBounds {
+            what: "glyph id".into(),
+            value: index.to_string(),
+        })
+}
+
+/// Drop the format 4 subtables, making sure format 12 ones map the whole repertoire instead.
+fn replace_format_4(cmap: &mut Cmap, mappings: &[(char, GlyphId)]) {
+    cmap.encoding_records
+        .retain(|rec| !matches!(*rec.subtable, CmapSubtable::Format4(_)));
+    // format 12 is only emitted for fonts with supplementary-plane characters
+    let has_format_12 = cmap
+        .encoding_records
+        .iter()
+        .any(|rec| matches!(*rec.subtable, CmapSubtable::Format12(_)));
+    if has_format_12 || mappings.is_empty() {
+        return;
+    }
+    let subtable = CmapSubtable::format_12(0, sequential_map_groups(mappings));
+    cmap.encoding_records.extend([
+        EncodingRecord::new(PlatformId::Unicode, 4, subtable.clone()),
+        EncodingRecord::new(PlatformId::Windows, 10, subtable),
+    ]);
+}
+
+/// Runs of consecutive codepoints mapped to consecutive glyph ids.
+fn sequential_map_groups(mappings: &[(char, GlyphId)]) -> Vec<SequentialMapGroup> {
+    let mut mappings = mappings
+        .iter()
+        .map(|(c, gid)| (*c as u32, gid.to_u32()))
+        .collect::<Vec<_>>();
+    mappings.sort_unstable();
+    mappings.dedup();
+
+    let mut groups: Vec<SequentialMapGroup> = Vec::new();
+    for (cp, gid) in mappings {
+        if let Some(last) = groups.last_mut() {
+            if last.end_char_code + 1 == cp
+                && last.start_glyph_id + (cp - last.start_char_code) == gid
+            {
+                last.end_char_code = cp;
+                continue;
+            }
+        }
+        groups.push(SequentialMapGroup::new(cp, cp, gid));
+    }
+    groups
+}
+
+/// The Unicode codepoints of Mac Roman 0x80..=0xFF; the lower half matches ASCII.
+#[rustfmt::skip]
+const MAC_ROMAN_HIGH: [u16; 128] = [
+    0x00C4, 0x00C5, 0x00C7, 0x00C9, 0x00D1, 0x00D6, 0x00DC, 0x00E1,
+    0x00E0, 0x00E2, 0x00E4, 0x00E3, 0x00E5, 0x00E7, 0x00E9, 0x00E8,
+    0x00EA, 0x00EB, 0x00ED, 0x00EC, 0x00EE, 0x00EF, 0x00F1, 0x00F3,
+    0x00F2, 0x00F4, 0x00F6, 0x00F5, 0x00FA, 0x00F9, 0x00FB, 0x00FC,
+    0x2020, 0x00B0, 0x00A2, 0x00A3, 0x00A7, 0x2022, 0x00B6, 0x00DF,
+    0x00AE, 0x00A9, 0x2122, 0x00B4, 0x00A8, 0x2260, 0x00C6, 0x00D8,
+    0x221E, 0x00B1, 0x2264, 0x2265, 0x00A5, 0x00B5, 0x2202, 0x2211,
+    0x220F, 0x03C0, 0x222B, 0x00AA, 0x00BA, 0x03A9, 0x00E6, 0x00F8,
+    0x00BF, 0x00A1, 0x00AC, 0x221A, 0x0192, 0x2248, 0x2206, 0x00AB,
+    0x00BB, 0x2026, 0x00A0, 0x00C0, 0x00C3, 0x00D5, 0x0152, 0x0153,
+    0x2013, 0x2014, 0x201C, 0x201D, 0x2018, 0x2019, 0x00F7, 0x25CA,
+    0x00FF, 0x0178, 0x2044, 0x20AC, 0x2039, 0x203A, 0xFB01, 0xFB02,
+    0x2021, 0x00B7, 0x201A, 0x201E, 0x2030, 0x00C2, 0x00CA, 0x00C1,
+    0x00CB, 0x00C8, 0x00CD, 0x00CE, 0x00CF, 0x00CC, 0x00D3, 0x00D4,
+    0xF8FF, 0x00D2, 0x00DA, 0x00DB, 0x00D9, 0x0131, 0x02C6, 0x02DC,
+    0x00AF, 0x02D8, 0x02D9, 0x02DA, 0x00B8, 0x02DD, 0x02DB, 0x02C7,
+];
+
+fn mac_roman_to_unicode(code: u8) -> u32 {
+    if code < 0x80 {
+        code as u32
+    } else {
+        MAC_ROMAN_HIGH[(code - 0x80) as usize] as u32
+    }
+}
+
+/// A format 6 subtable for the (1, 0) Macintosh Roman encoding, None if nothing maps.
+fn mac_roman_subtable(mappings: &[(char, GlyphId)]) -> Result<Option<CmapSubtable>, Error> {
+    let by_codepoint = mappings
+        .iter()
+        .map(|(c, gid)| (*c as u32, *gid))
+        .collect::<HashMap<_, _>>();
+    let glyphs = (0..=u8::MAX)
+        .map(|code| match by_codepoint.get(&mac_roman_to_unicode(code)) {
+            Some(gid) => GlyphId16::try_from(*gid)
+                .map(GlyphId16::to_u16)
+                .map_err(|_| Error::OutOfBounds {
+                    what: "glyph id".into(),
+                    value: gid.to_u32().to_string(),
+                }),
+            None => Ok(0),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let Some(first) = glyphs.iter().position(|gid| *gid != 0) else {
+        return Ok(None);
+    };
+    let last = glyphs.iter().rposition(|gid| *gid != 0).unwrap_or(first);
+    let glyphs = glyphs[first..=last].to_vec();
+    // header is 10 bytes, each entry 2
+    let length = 10 + 2 * glyphs.len() as u16;
+    Ok(Some(CmapSubtable::format_6(
+        length,
+        0,
+        first as u16,
+        glyphs.len() as u16,
+        glyphs,
+    )))
+}
+
+/// The groups of a format 13 subtable, covering the parts of `ranges` that
+/// aren't already `mapped` to a glyph of their own.
+///
+/// Surrogates, U+D800 to U+DFFF, aren't characters so are never mapped.
+fn constant_map_groups(
+    ranges: &[CodepointRange],
+    glyph_order: &GlyphOrder,
+    mapped: &HashSet<u32>,
+) -> Vec<ConstantMapGroup> {
+    // sorted, disjoint, inclusive ranges of codepoints that already have a glyph
+    let mut taken = mapped.iter().map(|cp| (*cp, *cp)).collect::<Vec<_>>();
+    taken.push((0xD800, 0xDFFF));
+    taken.sort_unstable();
+
+    let mut groups = Vec::new();
+    for range in ranges {
+        let Some(gid) = glyph_order.glyph_id(&range.glyph) else {
+            warn!(
+                "cmap range {:04X}-{:04X} maps to '{}', which is not in the font",
+                range.first, range.last, range.glyph
+            );
+            continue;
+        };
+        let new = uncovered(range.first, range.last, &taken);
+        groups.extend(
+            new.iter()
+                .map(|(first, last)| ConstantMapGroup::new(*first, *last, gid.to_u16() as u32)),
+        );
+        taken.extend(new);
+        taken.sort_unstable();
+    }
+    groups.sort_by_key(|group| group.start_char_code);
+    groups
+}
+
+/// The parts of `first..=last` that are not in any of the sorted, disjoint `taken` ranges.
+fn uncovered(first: u32, last: u32, taken: &[(u32, u32)]) -> Vec<(u32, u32)> {
+    let mut result = Vec::new();
+    let mut next = first;
+    for (start, end) in taken {
+        if *end < next {
+            continue;
+        }
+        if *start > last {
+            break;
+        }
+        if *start > next {
+            result.push((next, start - 1));
+        }
+        match end.checked_add(1) {
+            Some(after) => next = after,
+            None => return result,
+        }
+        if next > last {
+            return result;
+        }
+    }
+    result.push((next, last));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gid(gid: u16) -> GlyphId {
+        GlyphId16::new(gid).into()
+    }
+
+    #[test]
+    fn glyph_ids_must_fit_in_16_bits() {
+        assert_eq!(
+            GlyphId16::new(u16::MAX),
+            glyph_id(u16::MAX as usize).unwrap()
+        );
+        assert!(matches!(
+            glyph_id(u16::MAX as usize + 1),
+            Err(Error::OutOfBounds { .. })
+        ));
+    }
+
+    #[test]
+    fn sequential_groups_merge_runs() {
+        let mappings = [
+            ('b', gid(2)),
+            ('a', gid(1)),
+            ('c', gid(3)),
+            ('d', gid(7)),
+            ('\u{1F600}', gid(8)),
+        ];
+        let groups = sequential_map_groups(&mappings)
+            .into_iter()
+            .map(|g| (g.start_char_code, g.end_char_code, g.start_glyph_id))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![(0x61, 0x63, 1), (0x64, 0x64, 7), (0x1F600, 0x1F600, 8)],
+            groups
+        );
+    }
+
+    #[test]
+    fn omit_format_4() {
+        let mappings = [('a', gid(1)), ('b', gid(2))];
+        let mut cmap = Cmap::from_mappings(mappings).unwrap();
+        replace_format_4(&mut cmap, &mappings);
+        let records = cmap
+            .encoding_records
+            .iter()
+            .map(|rec| {
+                (
+                    rec.platform_id,
+                    rec.encoding_id,
+                    matches!(*rec.subtable, CmapSubtable::Format12(_)),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (PlatformId::Unicode, 4, true),
+                (PlatformId::Windows, 10, true)
+            ],
+            records
+        );
+    }
+
+    #[test]
+    fn mac_roman() {
+        // é is 0x8E in Mac Roman, ☃ isn't encoded
+        let mappings = [('A', gid(1)), ('é', gid(2)), ('☃', gid(3))];
+        let Ok(Some(CmapSubtable::Format6(subtable))) = mac_roman_subtable(&mappings) else {
+            panic!("expected a format 6 subtable");
+        };
+        assert_eq!(0x41, subtable.first_code);
+        assert_eq!(0x8E - 0x41 + 1, subtable.entry_count);
+        assert_eq!(1, subtable.glyph_id_array[0]);
+        assert_eq!(2, *subtable.glyph_id_array.last().unwrap());
+        assert_eq!(
+            2,
+            subtable.glyph_id_array.iter().filter(|g| **g != 0).count()
+        );
+    }
+
+    #[test]
+    fn mac_roman_needs_a_mapping() {
+        assert!(mac_roman_subtable(&[('☃', gid(1))]).unwrap().is_none());
+    }
+
+    #[test]
+    fn mac_roman_glyph_ids_must_fit_in_16_bits() {
+        assert!(matches!(
+            mac_roman_subtable(&[('A', GlyphId::new(u16::MAX as u32 + 1))]),
+            Err(Error::OutOfBounds { .. })
+        ));
+    }
+
+    #[test]
+    fn uncovered_ranges() {
+        let taken = [(2, 3), (5, 5), (9, 20)];
+        assert_eq!(vec![(0, 1), (4, 4), (6, 8)], uncovered(0, 10, &taken));
+        assert_eq!(vec![(21, 30)], uncovered(15, 30, &taken));
+        assert_eq!(Vec::<(u32, u32)>::new(), uncovered(2, 3, &taken));
+        assert_eq!(vec![(0, 0x10FFFF)], uncovered(0, 0x10FFFF, &[]));
+    }
+
+    #[test]
+    fn earlier_ranges_and_codepoints_win() {
+        let glyph_order: GlyphOrder = [".notdef", "latin", "fallback"]
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        let ranges = [
+            CodepointRange {
+                first: 0x20,
+                last: 0x7F,
+                glyph: "latin".into(),
+            },
+            CodepointRange {
+                first: 0,
+                last: 0x10FFFF,
+                glyph: "fallback".into(),
+            },
+        ];
+        let mapped = HashSet::from([0x41]);
+        let groups = constant_map_groups(&ranges, &glyph_order, &mapped)
+            .into_iter()
+            .map(|g| (g.start_char_code, g.end_char_code, g.glyph_id))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (0, 0x1F, 2),
+                (0x20, 0x40, 1),
+                (0x42, 0x7F, 1),
+                (0x80, 0xD7FF, 2),
+                (0xE000, 0x10FFFF, 2)
+            ],
+            groups
+        );
+    }
+}
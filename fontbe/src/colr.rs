@@ -1,11 +1,41 @@
 //! Generates a [COLR](https://learn.microsoft.com/en-us/typography/opentype/spec/colr) table.
+//!
+//! Every color glyph is a COLRv1 base glyph that fills its own outline with a gradient.
+//! When the geometry of a gradient differs between masters the variable forms of the
+//! paints are used, with deltas in an ItemVariationStore.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+};
 
 use crate::{
     error::Error,
     orchestration::{AnyWorkId, BeWork, Context, WorkId},
 };
-use fontdrasil::orchestration::{Access, Work};
-use fontir::orchestration::WorkId as FeWorkId;
+use fontdrasil::{
+    orchestration::{Access, AccessBuilder, Work},
+    types::{Axes, GlyphName},
+};
+use fontir::{
+    ir::{ColorPalettes, GradientKind, GradientPaint},
+    orchestration::WorkId as FeWorkId,
+    variations::ModelCache,
+};
+use log::{debug, warn};
+use ordered_float::OrderedFloat;
+use write_fonts::{
+    read::tables::variations::NO_VARIATION_INDEX,
+    tables::{
+        colr::{
+            BaseGlyphList, BaseGlyphPaint, ColorLine, ColorStop, Colr, Extend, LayerList, Paint,
+            VarColorLine, VarColorStop,
+        },
+        variations::{ivs_builder::VariationStoreBuilder, DeltaSetIndexMap, VariationRegion},
+    },
+    types::{F2Dot14, FWord, GlyphId16, UfWord},
+    NullableOffsetMarker, OtRound,
+};
 
 #[derive(Debug)]
 struct ColrWork {}
@@ -20,15 +50,381 @@ impl Work<Context, AnyWorkId, Error> for ColrWork {
     }
 
     fn read_access(&self) -> Access<AnyWorkId> {
-        Access::Variant(FeWorkId::ColorPalettes.into())
+        AccessBuilder::new()
+            .variant(FeWorkId::StaticMetadata)
+            .variant(FeWorkId::GlyphOrder)
+            .variant(FeWorkId::ColorPalettes)
+            .variant(FeWorkId::PaintGraph)
+            .build()
     }
 
     /// Generate [COLR](https://learn.microsoft.com/en-us/typography/opentype/spec/colr)
     fn exec(&self, context: &Context) -> Result<(), Error> {
-        // Temporary: if there is a palette generate an empty COLR, just so we can easily see a ttx_diff
-        if context.ir.colors.try_get().is_some() {
+        let Some(colors) = context.ir.colors.try_get() else {
+            return Ok(());
+        };
+        let Some(paint_graph) = context.ir.paint_graph.try_get() else {
+            // Temporary: if there is a palette generate an empty COLR, just so we can easily see a ttx_diff
             context.colr.set(Default::default());
+            return Ok(());
+        };
+        let static_metadata = context.ir.static_metadata.get();
+        let glyph_order = context.ir.glyph_order.get();
+
+        let mut builder = PaintBuilder::new(
+            &colors,
+            static_metadata.axes.clone(),
+            context.ir.variation_models.clone(),
+        );
+        let mut base_glyph_paints = Vec::new();
+        for (glyph_name, gradient) in paint_graph.base_glyphs.iter() {
+            let Some(gid) = glyph_order.glyph_id(glyph_name) else {
+                warn!("Dropping the gradient of {glyph_name}, it's not in the glyph order");
+                continue;
+            };
+            let paint = builder.add(glyph_name, gid, gradient)?;
+            base_glyph_paints.push(BaseGlyphPaint::new(gid, paint));
         }
+        base_glyph_paints.sort_by_key(|paint| paint.glyph_id);
+        debug!(
+            "COLR has {} base glyph paints, {} of them variable",
+            base_glyph_paints.len(),
+            builder.num_variable
+        );
+        context.colr.set(builder.build(base_glyph_paints));
         Ok(())
     }
 }
+
+/// Builds the paints of base glyphs and the deltas of those that vary
+struct PaintBuilder<'a> {
+    colors: &'a ColorPalettes,
+    axes: Axes,
+    model_cache: Arc<ModelCache>,
+    /// The deltas of each variation index, in order
+    deltas: Vec<Vec<(VariationRegion, i32)>>,
+    num_variable: usize,
+}
+
+impl<'a> PaintBuilder<'a> {
+    fn new(colors: &'a ColorPalettes, axes: Axes, model_cache: Arc<ModelCache>) -> Self {
+        PaintBuilder {
+            colors,
+            axes,
+            model_cache,
+            deltas: Vec::new(),
+            num_variable: 0,
+        }
+    }
+
+    /// A PaintGlyph of the glyph's own outline filled with its gradient
+    fn add(
+        &mut self,
+        glyph_name: &GlyphName,
+        gid: GlyphId16,
+        gradient: &GradientPaint,
+    ) -> Result<Paint, Error> {
+        let (_, default) = gradient
+            .geometry
+            .iter()
+            .find(|(loc, _)| loc.is_default())
+            .expect("gradients have a default location");
+        let default = to_fields(default);
+        let stops = gradient
+            .stops
+            .iter()
+            .map(|stop| {
+                let palette_index = self.palette_index(&stop.color);
+                (F2Dot14::from_f32(stop.offset.0 as f32), palette_index)
+            })
+            .collect::<Vec<_>>();
+        let alpha = F2Dot14::from_f32(1.0);
+
+        if !gradient.is_variable() || self.axes.is_empty() {
+            let color_line = ColorLine::new(
+                Extend::Pad,
+                stops.len() as u16,
+                stops
+                    .into_iter()
+                    .map(|(offset, palette_index)| ColorStop::new(offset, palette_index, alpha))
+                    .collect(),
+            );
+            let [x0, y0, a, b, c, d] = default;
+            let paint = match gradient.kind {
+                GradientKind::Linear => Paint::linear_gradient(color_line, x0, y0, a, b, c, d),
+                GradientKind::Radial => {
+                    Paint::radial_gradient(color_line, x0, y0, to_radius(a), b, c, to_radius(d))
+                }
+            };
+            return Ok(Paint::glyph(paint, gid));
+        }
+
+        let var_index_base = self.add_deltas(glyph_name, gradient)?;
+        self.num_variable += 1;
+        // only the geometry varies, the stops have no variation index
+        let color_line = VarColorLine::new(
+            Extend::Pad,
+            stops.len() as u16,
+            stops
+                .into_iter()
+                .map(|(offset, palette_index)| {
+                    VarColorStop::new(offset, palette_index, alpha, NO_VARIATION_INDEX)
+                })
+                .collect(),
+        );
+        let [x0, y0, a, b, c, d] = default;
+        let paint = match gradient.kind {
+            GradientKind::Linear => {
+                Paint::var_linear_gradient(color_line, x0, y0, a, b, c, d, var_index_base)
+            }
+            GradientKind::Radial => Paint::var_radial_gradient(
+                color_line,
+                x0,
+                y0,
+                to_radius(a),
+                b,
+                c,
+                to_radius(d),
+                var_index_base,
+            ),
+        };
+        Ok(Paint::glyph(paint, gid))
+    }
+
+    /// Gradients name colors, find the matching entry of the first palette
+    fn palette_index(&self, color: &fontir::ir::Color) -> u16 {
+        self.colors.palettes[0]
+            .iter()
+            .position(|c| c == color)
+            .unwrap_or_else(|| {
+                warn!("{color:?} is not in the first palette, using its first entry");
+                0
+            }) as u16
+    }
+
+    /// Adds the deltas of each geometry field, returning the var index of the first
+    fn add_deltas(
+        &mut self,
+        glyph_name: &GlyphName,
+        gradient: &GradientPaint,
+    ) -> Result<u32, Error> {
+        // values must be rounded before computing deltas, as for metrics
+        let sources: HashMap<_, Vec<f64>> = gradient
+            .geometry
+            .iter()
+            .map(|(loc, values)| (loc.clone(), values.iter().map(|v| v.0.ot_round()).collect()))
+            .collect();
+        let locations = sources.keys().cloned().collect::<BTreeSet<_>>();
+        let model = self
+            .model_cache
+            .get(locations, &self.axes)
+            .map_err(|e| Error::VariationModelError(glyph_name.clone(), e))?;
+        let deltas = model
+            .deltas(&sources)
+            .map_err(|e| Error::ColrDeltaError(glyph_name.clone(), e))?;
+
+        let var_index_base = self.deltas.len() as u32;
+        let num_fields = sources.values().next().map(|v| v.len()).unwrap_or_default();
+        for field in 0..num_fields {
+            self.deltas.push(
+                deltas
+                    .iter()
+                    .filter(|(region, _)| !region.is_default())
+                    .map(|(region, values)| {
+                        let delta: f64 = values[field].ot_round();
+                        (
+                            region.to_write_fonts_variation_region(&self.axes),
+                            delta as i32,
+                        )
+                    })
+                    .collect(),
+            );
+        }
+        Ok(var_index_base)
+    }
+
+    fn build(self, base_glyph_paints: Vec<BaseGlyphPaint>) -> Colr {
+        let mut colr = Colr {
+            base_glyph_list: NullableOffsetMarker::new(Some(BaseGlyphList::new(
+                base_glyph_paints.len() as u32,
+                base_glyph_paints,
+            ))),
+            // write-fonts only writes version 1 if a v1 field other than the base glyph
+            // list is set; paints are never shared so the layer list is empty
+            layer_list: NullableOffsetMarker::new(Some(LayerList::new(0, Vec::new()))),
+            ..Default::default()
+        };
+        if self.deltas.is_empty() {
+            return colr;
+        }
+        let mut builder = VariationStoreBuilder::new(self.axes.len() as u16);
+        let delta_ids: Vec<_> = self
+            .deltas
+            .into_iter()
+            .map(|deltas| builder.add_deltas(deltas))
+            .collect();
+        let (var_store, index_map) = builder.build();
+        // unwrap since VariationStoreBuilder guarantees that any temporary index returned by
+        // add_deltas will exist in the returned map
+        let var_index_map: DeltaSetIndexMap = delta_ids
+            .into_iter()
+            .map(|id| index_map.get(id).unwrap())
+            .collect();
+        colr.var_index_map = NullableOffsetMarker::new(Some(var_index_map));
+        colr.item_variation_store = NullableOffsetMarker::new(Some(var_store));
+        colr
+    }
+}
+
+fn to_fields(values: &[OrderedFloat<f64>]) -> [FWord; 6] {
+    let mut fields = [FWord::new(0); 6];
+    for (field, value) in fields.iter_mut().zip(values) {
+        *field = FWord::new(value.0.ot_round());
+    }
+    fields
+}
+
+fn to_radius(value: FWord) -> UfWord {
+    UfWord::new(value.to_i16().max(0) as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use fontdrasil::{coords::NormalizedLocation, types::Axes};
+    use fontir::ir::{Color, ColorPalettes, ColorStop, GradientKind, GradientPaint};
+    use ordered_float::OrderedFloat;
+    use write_fonts::{
+        dump_table,
+        read::{
+            tables::colr::{Colr as ReadColr, Paint as ReadPaint},
+            FontData, FontRead,
+        },
+        types::{F2Dot14, GlyphId16},
+    };
+
+    use super::PaintBuilder;
+
+    fn palettes() -> ColorPalettes {
+        ColorPalettes::new(vec![vec![
+            Color {
+                r: 0,
+                g: 0,
+                b: 255,
+                a: 255,
+            },
+            Color {
+                r: 255,
+                g: 0,
+                b: 0,
+                a: 255,
+            },
+        ]])
+        .unwrap()
+        .unwrap()
+    }
+
+    fn gradient(geometry: Vec<(f64, [f64; 6])>) -> GradientPaint {
+        GradientPaint {
+            kind: GradientKind::Linear,
+            stops: vec![
+                ColorStop {
+                    offset: 0.0.into(),
+                    color: palettes().palettes[0][1].clone(),
+                },
+                ColorStop {
+                    offset: 1.0.into(),
+                    color: palettes().palettes[0][0].clone(),
+                },
+            ],
+            geometry: geometry
+                .into_iter()
+                .map(|(wght_pos, values)| {
+                    (
+                        NormalizedLocation::for_pos(&[("wght", wght_pos)]),
+                        values.into_iter().map(OrderedFloat).collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn build(gradient: &GradientPaint) -> Vec<u8> {
+        let colors = palettes();
+        let mut builder = PaintBuilder::new(&colors, Axes::for_test(&["wght"]), Default::default());
+        let paint = builder
+            .add(&"A".into(), GlyphId16::new(1), gradient)
+            .unwrap();
+        let colr = builder.build(vec![write_fonts::tables::colr::BaseGlyphPaint::new(
+            GlyphId16::new(1),
+            paint,
+        )]);
+        dump_table(&colr).unwrap()
+    }
+
+    fn base_paint<'a>(colr: &ReadColr<'a>) -> ReadPaint<'a> {
+        let list = colr.base_glyph_list().unwrap().unwrap();
+        let record = &list.base_glyph_paint_records()[0];
+        let ReadPaint::Glyph(glyph) = record.paint(list.offset_data()).unwrap() else {
+            panic!("Expected a PaintGlyph");
+        };
+        assert_eq!(1, glyph.glyph_id().to_u16());
+        glyph.paint().unwrap()
+    }
+
+    #[test]
+    fn static_gradient_has_no_variations() {
+        let bytes = build(&gradient(vec![
+            (0.0, [0.0, 0.0, 100.0, 0.0, 0.0, 100.0]),
+            (1.0, [0.0, 0.0, 100.0, 0.0, 0.0, 100.0]),
+        ]));
+        let colr = ReadColr::read(FontData::new(&bytes)).unwrap();
+
+        assert_eq!(1, colr.version());
+        assert!(colr.item_variation_store().is_none());
+        let ReadPaint::LinearGradient(paint) = base_paint(&colr) else {
+            panic!("Expected a PaintLinearGradient");
+        };
+        assert_eq!(
+            (0, 0, 100, 0, 0, 100),
+            (
+                paint.x0().to_i16(),
+                paint.y0().to_i16(),
+                paint.x1().to_i16(),
+                paint.y1().to_i16(),
+                paint.x2().to_i16(),
+                paint.y2().to_i16()
+            )
+        );
+        let stops = paint.color_line().unwrap().color_stops();
+        assert_eq!(
+            vec![1, 0],
+            stops.iter().map(|s| s.palette_index()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn varying_gradient_has_deltas() {
+        let bytes = build(&gradient(vec![
+            (0.0, [0.0, 0.0, 100.0, 0.0, 0.0, 100.0]),
+            (1.0, [10.0, 0.0, 300.0, 0.0, 10.0, 290.0]),
+        ]));
+        let colr = ReadColr::read(FontData::new(&bytes)).unwrap();
+
+        let ReadPaint::VarLinearGradient(paint) = base_paint(&colr) else {
+            panic!("Expected a PaintVarLinearGradient");
+        };
+        assert_eq!(0, paint.var_index_base());
+        assert_eq!(100, paint.x1().to_i16());
+
+        let var_index_map = colr.var_index_map().unwrap().unwrap();
+        let store = colr.item_variation_store().unwrap().unwrap();
+        let at_max = [F2Dot14::from_f32(1.0)];
+        let deltas: Vec<_> = (0..6)
+            .map(|i| {
+                let index = var_index_map.get(i).unwrap();
+                store.compute_delta(index, &at_max).unwrap()
+            })
+            .collect();
+        assert_eq!(vec![10, 0, 200, 0, 10, 190], deltas);
+    }
+}
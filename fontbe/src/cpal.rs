@@ -1,4 +1,7 @@
 //! Generates a [CPAL](https://learn.microsoft.com/en-us/typography/opentype/spec/cpal) table.
+//!
+//! Version 0, unless a palette has a type, e.g. for use on dark backgrounds, which needs
+//! version 1. write-fonts only writes version 0, so the table is written by hand.
 
 use crate::{
     error::Error,
@@ -7,7 +10,11 @@ use crate::{
 use fontdrasil::orchestration::{Access, Work};
 use fontir::{ir, orchestration::WorkId as FeWorkId};
 use log::debug;
-use write_fonts::{tables::cpal::ColorRecord, tables::cpal::Cpal, NullableOffsetMarker};
+
+/// The size of the version 0 header, before the color record indices
+const HEADER_LEN: usize = 12;
+/// The size of the offsets version 1 adds after the color record indices
+const V1_OFFSETS_LEN: usize = 12;
 
 #[derive(Debug)]
 struct CpalWork {}
@@ -16,13 +23,9 @@ pub fn create_cpal_work() -> Box<BeWork> {
     Box::new(CpalWork {})
 }
 
-fn to_cpal_color(c: &ir::Color) -> ColorRecord {
-    ColorRecord {
-        red: c.r,
-        green: c.g,
-        blue: c.b,
-        alpha: c.a,
-    }
+/// A ColorRecord, which is BGRA
+fn to_cpal_color(c: &ir::Color) -> [u8; 4] {
+    [c.b, c.g, c.r, c.a]
 }
 
 impl Work<Context, AnyWorkId, Error> for CpalWork {
@@ -41,38 +44,145 @@ impl Work<Context, AnyWorkId, Error> for CpalWork {
             return Ok(());
         };
 
-        let color_records = colors
-            .palettes
-            .iter()
-            .flat_map(|p| p.iter())
-            .map(to_cpal_color)
-            .collect::<Vec<_>>();
-
-        if color_records.len() > u16::MAX as usize {
+        let num_color_records = colors.palettes.iter().map(|p| p.len()).sum::<usize>();
+        if num_color_records > u16::MAX as usize {
             return Err(Error::OutOfBounds {
                 what: "Too many CPAL colorRecords".to_string(),
-                value: format!("{}", color_records.len()),
+                value: format!("{num_color_records}"),
             });
         }
 
-        let entries_per_palette = colors.palettes[0].len();
         debug!(
-            "CPAL has {} color records in {} palette(s) of {}",
-            color_records.len(),
+            "CPAL has {} color records in {} palette(s) of {}, with types {:?}",
+            num_color_records,
             colors.palettes.len(),
-            entries_per_palette
+            colors.palettes[0].len(),
+            colors.palette_types
         );
-        context.cpal.set(Cpal {
-            num_palette_entries: entries_per_palette as u16,
-            num_palettes: colors.palettes.len() as u16,
-            num_color_records: color_records.len() as u16,
-            color_records_array: NullableOffsetMarker::new(Some(color_records)),
-            color_record_indices: (0..colors.palettes.len())
-                .map(|i| (i * entries_per_palette) as u16)
-                .collect(),
-            ..Default::default()
-        });
+        context.cpal.set(to_table_bytes(&colors).into());
 
         Ok(())
     }
 }
+
+/// Palettes are stored one after another, with no sharing of color records
+fn to_table_bytes(colors: &ir::ColorPalettes) -> Vec<u8> {
+    let num_palettes = colors.palettes.len();
+    let entries_per_palette = colors.palettes[0].len();
+    let version: u16 = if colors.palette_types.is_empty() {
+        0
+    } else {
+        1
+    };
+
+    let mut color_records_offset = HEADER_LEN + 2 * num_palettes;
+    if version == 1 {
+        color_records_offset += V1_OFFSETS_LEN;
+    }
+    let num_color_records = num_palettes * entries_per_palette;
+
+    let mut buf = Vec::new();
+    buf.extend(version.to_be_bytes());
+    buf.extend((entries_per_palette as u16).to_be_bytes());
+    buf.extend((num_palettes as u16).to_be_bytes());
+    buf.extend((num_color_records as u16).to_be_bytes());
+    buf.extend((color_records_offset as u32).to_be_bytes());
+    for i in 0..num_palettes {
+        buf.extend(((i * entries_per_palette) as u16).to_be_bytes());
+    }
+    if version == 1 {
+        let palette_types_offset = color_records_offset + 4 * num_color_records;
+        buf.extend((palette_types_offset as u32).to_be_bytes());
+        // no palette labels or palette entry labels
+        buf.extend(0u32.to_be_bytes());
+        buf.extend(0u32.to_be_bytes());
+    }
+    for color in colors.palettes.iter().flat_map(|p| p.iter()) {
+        buf.extend(to_cpal_color(color));
+    }
+    for flags in colors.palette_types.iter() {
+        buf.extend(flags.to_be_bytes());
+    }
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use fontir::ir::{Color, ColorPalettes};
+    use write_fonts::read::{
+        tables::cpal::{Cpal, PaletteType},
+        FontData, FontRead,
+    };
+
+    use super::to_table_bytes;
+
+    fn palettes() -> ColorPalettes {
+        let color = |r, g, b| Color { r, g, b, a: 255 };
+        ColorPalettes::new(vec![
+            vec![color(255, 0, 0), color(0, 0, 255)],
+            vec![color(255, 128, 128), color(128, 128, 255)],
+        ])
+        .unwrap()
+        .unwrap()
+    }
+
+    fn color_records(cpal: &Cpal) -> Vec<(u8, u8, u8)> {
+        cpal.color_records_array()
+            .unwrap()
+            .unwrap()
+            .iter()
+            .map(|color| (color.red, color.green, color.blue))
+            .collect()
+    }
+
+    #[test]
+    fn version_0_without_palette_types() {
+        let bytes = to_table_bytes(&palettes());
+        let cpal = Cpal::read(FontData::new(&bytes)).unwrap();
+
+        assert_eq!(0, cpal.version());
+        assert!(cpal.palette_types_array().is_none());
+        assert_eq!(
+            (2, 2, vec![0, 2]),
+            (
+                cpal.num_palettes(),
+                cpal.num_palette_entries(),
+                cpal.color_record_indices()
+                    .iter()
+                    .map(|i| i.get())
+                    .collect::<Vec<_>>()
+            )
+        );
+        // entries correspond across palettes, so neither palette is reordered
+        assert_eq!(
+            vec![(255, 0, 0), (0, 0, 255), (255, 128, 128), (128, 128, 255)],
+            color_records(&cpal)
+        );
+    }
+
+    #[test]
+    fn version_1_with_palette_types() {
+        let palettes = palettes().with_palette_types(vec![
+            PaletteType::USABLE_WITH_LIGHT_BACKGROUND.bits(),
+            PaletteType::USABLE_WITH_DARK_BACKGROUND.bits(),
+        ]);
+        let bytes = to_table_bytes(&palettes);
+        let cpal = Cpal::read(FontData::new(&bytes)).unwrap();
+
+        assert_eq!(1, cpal.version());
+        assert_eq!(
+            vec![
+                PaletteType::USABLE_WITH_LIGHT_BACKGROUND,
+                PaletteType::USABLE_WITH_DARK_BACKGROUND
+            ],
+            cpal.palette_types_array()
+                .unwrap()
+                .unwrap()
+                .iter()
+                .map(|flags| flags.get())
+                .collect::<Vec<_>>()
+        );
+        assert!(cpal.palette_labels_array().is_none());
+        assert_eq!(4, color_records(&cpal).len());
+    }
+}
@@ -1,7 +1,7 @@
-use std::{fmt::Display, io, path::PathBuf};
+use std::{fmt::Display, io, ops::RangeInclusive, path::PathBuf};
 
 use fea_rs::compile::error::CompilerError;
-use fontdrasil::{coords::NormalizedLocation, types::GlyphName};
+use fontdrasil::{coords::NormalizedLocation, error::ErrorCategory, types::GlyphName};
 use fontir::{
     error::VariationModelError, ir::KernPair, orchestration::WorkId as FeWorkId,
     variations::DeltaError,
@@ -15,7 +15,7 @@ use write_fonts::{
         glyf::MalformedPath,
         gvar::{iup::IupError, GvarInputError},
     },
-    types::Tag,
+    types::{GlyphId16, Tag},
 };
 
 #[derive(Debug, Error)]
@@ -54,8 +54,14 @@ pub enum Error {
     GlyphDeltaError(GlyphName, DeltaError),
     #[error("Unable to compute deltas for MVAR {0}: {1}")]
     MvarDeltaError(Tag, DeltaError),
+    #[error("Unable to build a variation model for avar2 mappings: {0}")]
+    AvarModelError(VariationModelError),
+    #[error("Unable to compute deltas for avar2 mappings: {0}")]
+    AvarDeltaError(DeltaError),
     #[error("Unable to compute deltas for anchor on '{0}': '{1}'")]
     AnchorDeltaError(GlyphName, DeltaError),
+    #[error("Unable to compute deltas for the gradient of '{0}': '{1}'")]
+    ColrDeltaError(GlyphName, DeltaError),
     #[error("Unable to compute deltas for kern pair '{}/{}': '{error}'", .pair.0, .pair.1)]
     KernDeltaError { pair: KernPair, error: DeltaError },
     #[error("Unable to assemble gvar")]
@@ -74,6 +80,8 @@ pub enum Error {
     FileExpected(PathBuf),
     #[error("Missing {0}")]
     MissingTable(Tag),
+    #[error("Can't patch {0} without also patching {1}")]
+    IncompletePatch(Tag, Tag),
     #[error("Expected an anchor, got {0:?}")]
     ExpectedAnchor(FeWorkId),
     #[error("No glyph class '{0}'")]
@@ -93,10 +101,88 @@ pub enum Error {
     MissingGlyphId(GlyphName),
     #[error("Error making CMap: {0}")]
     CmapConflict(#[from] CmapConflict),
+    #[error("Unable to make the requested cmap: {0}")]
+    InvalidCmap(String),
+    #[error("Inconsistent style: {0}")]
+    InconsistentStyle(String),
     #[error("Progress stalled computing composite bbox: {0:?}")]
     CompositesStalled(Vec<GlyphName>),
     #[error("Inconsistent palette lengths observed: {0:?}")]
     InconsistentPaletteLength(Vec<usize>),
+    #[error("SVG documents for {0:?} and {1:?} overlap")]
+    OverlappingSvgDocuments(RangeInclusive<GlyphId16>, RangeInclusive<GlyphId16>),
+    #[error("Cancelled")]
+    Cancelled,
+}
+
+impl Error {
+    /// The broad kind of error, for callers that want to react without parsing messages
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Error::IoError(_) | Error::MissingDirectory(_) | Error::FileExpected(_) => {
+                ErrorCategory::Io
+            }
+            Error::FeaCompileError(_) | Error::MissingGlyphClass(_) => {
+                ErrorCategory::FeatureCompile
+            }
+            Error::GlyphError(_, problem) | Error::ComponentError { problem, .. } => {
+                problem.category()
+            }
+            Error::ComponentErrors { errors, .. } => errors
+                .first()
+                .map(Error::category)
+                .unwrap_or(ErrorCategory::Validation),
+            Error::GlyphDeltaError(..)
+            | Error::MvarDeltaError(..)
+            | Error::AvarModelError(_)
+            | Error::AvarDeltaError(_)
+            | Error::AnchorDeltaError(..)
+            | Error::ColrDeltaError(..)
+            | Error::KernDeltaError { .. }
+            | Error::GvarError(_)
+            | Error::IupError(..)
+            | Error::VariationModelError(..)
+            | Error::NoVariationModel(_)
+            | Error::DeltaError(_) => ErrorCategory::Interpolation,
+            Error::KurboError { .. }
+            | Error::OutOfBounds { .. }
+            | Error::PreviouslyAssignedMarkClass { .. }
+            | Error::CmapConflict(_)
+            | Error::InvalidCmap(_)
+            | Error::InconsistentStyle(_)
+            | Error::InconsistentPaletteLength(_)
+            | Error::OverlappingSvgDocuments(..) => ErrorCategory::Validation,
+            Error::ReadFontsReadError(_) | Error::InvalidTableBytes(_) => ErrorCategory::Parse,
+            Error::DumpTableError { .. }
+            | Error::MissingTable(_)
+            | Error::IncompletePatch(..)
+            | Error::ExpectedAnchor(_)
+            | Error::MissingGlyphId(_)
+            | Error::CompositesStalled(_) => ErrorCategory::Internal,
+            Error::Cancelled => ErrorCategory::Cancelled,
+        }
+    }
+
+    /// The glyph the error is about, if any
+    pub fn glyph(&self) -> Option<&GlyphName> {
+        match self {
+            Error::GlyphError(glyph, _)
+            | Error::KurboError {
+                glyph_name: glyph, ..
+            }
+            | Error::ComponentError { glyph, .. }
+            | Error::ComponentErrors { glyph, .. }
+            | Error::GlyphDeltaError(glyph, _)
+            | Error::AnchorDeltaError(glyph, _)
+            | Error::ColrDeltaError(glyph, _)
+            | Error::IupError(glyph, _)
+            | Error::VariationModelError(glyph, _)
+            | Error::MissingGlyphClass(glyph)
+            | Error::PreviouslyAssignedMarkClass { glyph, .. }
+            | Error::MissingGlyphId(glyph) => Some(glyph),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -109,6 +195,19 @@ pub enum GlyphProblem {
     NotInGlyphOrder,
 }
 
+impl GlyphProblem {
+    fn category(&self) -> ErrorCategory {
+        match self {
+            GlyphProblem::InconsistentComponents
+            | GlyphProblem::InconsistentPathElements
+            | GlyphProblem::MissingDefault => ErrorCategory::Interpolation,
+            GlyphProblem::HasComponentsAndPath
+            | GlyphProblem::NoComponents
+            | GlyphProblem::NotInGlyphOrder => ErrorCategory::Validation,
+        }
+    }
+}
+
 impl Display for GlyphProblem {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let message = match self {
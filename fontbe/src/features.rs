@@ -15,16 +15,15 @@ use ordered_float::OrderedFloat;
 
 use fea_rs::{
     compile::{
-        error::CompilerError, Compilation, FeatureBuilder, FeatureProvider, NopFeatureProvider,
-        PendingLookup, VariationInfo,
+        error::CompilerError, Compilation, FeatureProvider, NopFeatureProvider, VariationInfo,
     },
     parse::{FileSystemResolver, SourceLoadError, SourceResolver},
     typed::{AstNode, LanguageSystem},
-    DiagnosticSet, GlyphMap, Opts, ParseTree,
+    DiagnosticSet, GlyphIdent, GlyphMap, GlyphSet, Opts, ParseTree,
 };
 
 use fontir::{
-    ir::{FeaturesSource, GlyphOrder, StaticMetadata},
+    ir::{self, FeatureWriterMode, FeaturesSource, GlyphOrder, StaticMetadata},
     orchestration::{Flags, WorkId as FeWorkId},
     variations::{DeltaError, VariationModel},
 };
@@ -32,9 +31,11 @@ use fontir::{
 use fontdrasil::{
     coords::NormalizedLocation,
     orchestration::{Access, AccessBuilder, Work},
-    types::Axis,
+    types::{Axis, GlyphName},
+    warnings::codes,
 };
 use properties::UnicodeShortName;
+use substitutions::GeneratedSubstitutions;
 use write_fonts::{
     tables::{gdef::GlyphClassDef, layout::ClassDef, variations::VariationRegion},
     types::{GlyphId16, NameId, Tag},
@@ -54,9 +55,11 @@ mod kern;
 mod marks;
 mod ot_tags;
 mod properties;
+mod substitutions;
 #[cfg(test)]
 mod test_helpers;
 
+pub use fea_rs::compile::{FeatureBuilder, PendingLookup};
 pub use kern::{create_gather_ir_kerning_work, create_kern_segment_work, create_kerns_work};
 pub use marks::create_mark_work;
 
@@ -67,7 +70,23 @@ const DFLT_LANG: Tag = Tag::new(b"dflt");
 pub struct FeatureFirstPassWork {}
 
 #[derive(Debug)]
-pub struct FeatureCompilationWork {}
+pub struct FeatureCompilationWork {
+    custom_writers: Vec<Arc<dyn CustomFeatureWriter>>,
+}
+
+/// Writes features of its own alongside the built-in kern and mark writers.
+///
+/// For shaping logic the source can't express, e.g. a generated rand or calt.
+/// Lookups are added with [FeatureBuilder], like a fea-rs [FeatureProvider],
+/// and use the glyph ids of the final glyph order.
+pub trait CustomFeatureWriter: std::fmt::Debug + Send + Sync {
+    fn add_features(
+        &self,
+        static_metadata: &StaticMetadata,
+        glyph_order: &GlyphOrder,
+        builder: &mut FeatureBuilder,
+    );
+}
 
 // I did not want to make a struct
 // I did not want to clone the content
@@ -255,24 +274,42 @@ pub(crate) fn resolve_variable_metric<'a>(
 }
 
 struct FeatureWriter<'a> {
+    substitutions: GeneratedSubstitutions,
     kerning: &'a FeaRsKerns,
     marks: &'a FeaRsMarks,
     feature_variations: Option<FeatureVariationsProvider>,
+    custom: CustomFeatures<'a>,
+}
+
+/// Custom feature writers and what they need to know about the font
+struct CustomFeatures<'a> {
+    writers: &'a [Arc<dyn CustomFeatureWriter>],
+    static_metadata: &'a StaticMetadata,
+    glyph_order: &'a GlyphOrder,
 }
 
 impl<'a> FeatureWriter<'a> {
     fn new(
+        substitutions: GeneratedSubstitutions,
         kerning: &'a FeaRsKerns,
         marks: &'a FeaRsMarks,
         feature_variations: Option<FeatureVariationsProvider>,
+        custom: CustomFeatures<'a>,
     ) -> Self {
         FeatureWriter {
+            substitutions,
             marks,
             kerning,
             feature_variations,
+            custom,
         }
     }
 
+    /// Add the generated ccmp, locl and fraction features, if their writers are enabled
+    fn add_substitutions(&self, builder: &mut FeatureBuilder) {
+        self.substitutions.add_features(builder);
+    }
+
     /// We did most of the work in the kerning job, take the data and populate a builder
     fn add_kerning_features(&self, builder: &mut FeatureBuilder) {
         self.kerning.add_features(builder);
@@ -300,13 +337,26 @@ impl<'a> FeatureWriter<'a> {
             variations.add_features(builder);
         }
     }
+
+    /// Run the writers registered by the user, in the order they were registered
+    fn add_custom_features(&self, builder: &mut FeatureBuilder) {
+        for writer in self.custom.writers {
+            writer.add_features(
+                self.custom.static_metadata,
+                self.custom.glyph_order,
+                builder,
+            );
+        }
+    }
 }
 
 impl FeatureProvider for FeatureWriter<'_> {
     fn add_features(&self, builder: &mut FeatureBuilder) {
+        self.add_substitutions(builder);
         self.add_kerning_features(builder);
         self.add_marks(builder);
         self.add_feature_variations(builder);
+        self.add_custom_features(builder);
     }
 }
 
@@ -396,7 +446,12 @@ impl VariationInfo for FeaVariationInfo<'_> {
 
 impl FeatureCompilationWork {
     pub fn create() -> Box<BeWork> {
-        Box::new(FeatureCompilationWork {})
+        Self::with_custom_writers(Vec::new())
+    }
+
+    /// Feature compilation that also runs the given writers
+    pub fn with_custom_writers(custom_writers: Vec<Arc<dyn CustomFeatureWriter>>) -> Box<BeWork> {
+        Box::new(FeatureCompilationWork { custom_writers })
     }
 
     fn compile(
@@ -404,8 +459,10 @@ impl FeatureCompilationWork {
         static_metadata: &StaticMetadata,
         glyph_order: &GlyphOrder,
         ast: &FeaFirstPassOutput,
+        substitutions: GeneratedSubstitutions,
         kerns: &FeaRsKerns,
         marks: &FeaRsMarks,
+        optimize: bool,
     ) -> Result<Compilation, Error> {
         let feature_variations = static_metadata
             .variations
@@ -419,14 +476,22 @@ impl FeatureCompilationWork {
             })
             .transpose()?;
         let var_info = FeaVariationInfo::new(static_metadata);
-        let feature_writer = FeatureWriter::new(kerns, marks, feature_variations);
+        let custom = CustomFeatures {
+            writers: &self.custom_writers,
+            static_metadata,
+            glyph_order,
+        };
+        let feature_writer =
+            FeatureWriter::new(substitutions, kerns, marks, feature_variations, custom);
         // we've already validated the AST, so we only need to compile
         match fea_rs::compile::compile(
             &ast.ast,
             &marks.glyphmap,
             Some(&var_info),
             Some(&feature_writer),
-            Opts::new(),
+            Opts::new()
+                .dedup_lookups(optimize)
+                .optimize_layout(optimize),
         ) {
             Ok((result, warnings)) => {
                 log_fea_warnings("compilation", &warnings);
@@ -471,6 +536,7 @@ impl Work<Context, AnyWorkId, Error> for FeatureFirstPassWork {
 
     fn read_access(&self) -> Access<AnyWorkId> {
         AccessBuilder::new()
+            .variant(FeWorkId::PreliminaryGlyphOrder)
             .variant(FeWorkId::GlyphOrder)
             .variant(FeWorkId::StaticMetadata)
             .variant(FeWorkId::Features)
@@ -478,12 +544,16 @@ impl Work<Context, AnyWorkId, Error> for FeatureFirstPassWork {
     }
 
     fn exec(&self, context: &Context) -> Result<(), Error> {
-        let features = context.ir.features.get();
+        let mut features = context.ir.features.get();
         let glyph_order = context.ir.glyph_order.get();
         let static_metadata = context.ir.static_metadata.get();
         let glyph_map = glyph_order.names().cloned().collect();
 
-        let result = self.parse(&features, &glyph_map);
+        if context.flags.contains(Flags::SUBSET_FEATURES) {
+            let all_glyphs = context.ir.preliminary_glyph_order.get();
+            features = Arc::new(subset_features(&features, &all_glyphs, &glyph_order)?);
+        }
+        let result = parse(&features, &glyph_map);
 
         if context.flags.contains(Flags::EMIT_DEBUG) {
             write_debug_glyph_order(context, &glyph_order);
@@ -495,7 +565,12 @@ impl Work<Context, AnyWorkId, Error> for FeatureFirstPassWork {
         let ast = result?;
         // after parsing we validate; we only need to do this once, and future
         // work can trust the AST.
-        self.validate(&ast, &glyph_map, &static_metadata)?;
+        self.validate(
+            &ast,
+            &glyph_map,
+            &static_metadata,
+            context.flags.contains(Flags::LINT_FEATURES),
+        )?;
         let var_info = FeaVariationInfo::new(&static_metadata);
 
         let (compilation, _) = fea_rs::compile::compile::<_, NopFeatureProvider>(
@@ -520,22 +595,12 @@ impl FeatureFirstPassWork {
         Box::new(Self {})
     }
 
-    fn parse(&self, features: &FeaturesSource, glyph_map: &GlyphMap) -> Result<ParseTree, Error> {
-        let (resolver, root_path) = get_resolver_and_root_path(features);
-        let (tree, diagnostics) = fea_rs::parse::parse_root(root_path, Some(glyph_map), resolver)
-            .map_err(CompilerError::SourceLoad)?;
-        if diagnostics.has_errors() {
-            return Err(CompilerError::ParseFail(diagnostics).into());
-        }
-        log_fea_warnings("parsing", &diagnostics);
-        Ok(tree)
-    }
-
     fn validate(
         &self,
         ast: &ParseTree,
         glyph_map: &GlyphMap,
         static_metadata: &StaticMetadata,
+        lint: bool,
     ) -> Result<(), Error> {
         let var_info = FeaVariationInfo::new(static_metadata);
         let diagnostics = fea_rs::compile::validate(ast, glyph_map, Some(&var_info));
@@ -543,10 +608,78 @@ impl FeatureFirstPassWork {
             return Err(CompilerError::ValidationFail(diagnostics).into());
         }
         log_fea_warnings("validation", &diagnostics);
+        if lint {
+            log_fea_warnings("lint", &fea_rs::compile::lint(ast));
+        }
         Ok(())
     }
 }
 
+fn parse(features: &FeaturesSource, glyph_map: &GlyphMap) -> Result<ParseTree, Error> {
+    let (resolver, root_path) = get_resolver_and_root_path(features);
+    let (tree, diagnostics) = fea_rs::parse::parse_root(root_path, Some(glyph_map), resolver)
+        .map_err(CompilerError::SourceLoad)?;
+    if diagnostics.has_errors() {
+        return Err(CompilerError::ParseFail(diagnostics).into());
+    }
+    log_fea_warnings("parsing", &diagnostics);
+    Ok(tree)
+}
+
+/// Add the glyphs the feature file can substitute for `glyphs`, e.g. to keep them in a subset.
+pub fn substitution_closure(
+    features: &FeaturesSource,
+    glyph_order: &GlyphOrder,
+    glyphs: &mut HashSet<GlyphName>,
+) -> Result<(), Error> {
+    if matches!(features, FeaturesSource::Empty) {
+        return Ok(());
+    }
+    let glyph_map: GlyphMap = glyph_order.names().cloned().collect();
+    let ast = parse(features, &glyph_map)?;
+    let mut closure: GlyphSet = glyphs
+        .iter()
+        .filter_map(|name| glyph_map.get(name))
+        .collect();
+    fea_rs::compile::substitution_closure(&ast, &glyph_map, &mut closure);
+    let names = glyph_map.reverse_map();
+    glyphs.extend(closure.iter().filter_map(|gid| match names.get(&gid) {
+        Some(GlyphIdent::Name(name)) => Some(name.clone()),
+        _ => None,
+    }));
+    Ok(())
+}
+
+/// The features, without the rules for glyphs that were left out of the glyph order
+fn subset_features(
+    features: &FeaturesSource,
+    all_glyphs: &GlyphOrder,
+    glyph_order: &GlyphOrder,
+) -> Result<FeaturesSource, Error> {
+    if matches!(features, FeaturesSource::Empty) {
+        return Ok(FeaturesSource::Empty);
+    }
+    let glyph_map: GlyphMap = all_glyphs
+        .names()
+        .chain(
+            glyph_order
+                .names()
+                .filter(|name| !all_glyphs.contains(*name)),
+        )
+        .cloned()
+        .collect();
+    let ast = parse(features, &glyph_map)?;
+    let keep = glyph_order
+        .names()
+        .filter_map(|name| glyph_map.get(name))
+        .collect();
+    Ok(FeaturesSource::Memory {
+        fea_content: fea_rs::compile::subset(&ast, &glyph_map, &keep),
+        // the subset has its includes inlined
+        include_dir: None,
+    })
+}
+
 fn get_resolver_and_root_path(features: &FeaturesSource) -> (Box<dyn SourceResolver>, PathBuf) {
     match features {
         FeaturesSource::File {
@@ -585,6 +718,7 @@ impl Work<Context, AnyWorkId, Error> for FeatureCompilationWork {
     fn read_access(&self) -> Access<AnyWorkId> {
         AccessBuilder::new()
             .variant(FeWorkId::GlyphOrder)
+            .variant(FeWorkId::ALL_GLYPHS)
             .variant(WorkId::FeaturesAst)
             .variant(WorkId::GatherBeKerning)
             .variant(WorkId::Marks)
@@ -606,13 +740,28 @@ impl Work<Context, AnyWorkId, Error> for FeatureCompilationWork {
         let glyph_order = context.ir.glyph_order.get();
         let kerns = context.fea_rs_kerns.get();
         let marks = context.fea_rs_marks.get();
+        let glyphs = glyph_order
+            .names()
+            .map(|name| context.ir.get_glyph(name.clone()))
+            .collect::<Vec<_>>();
+        let substitutions = GeneratedSubstitutions::new(
+            &static_metadata,
+            &glyph_order,
+            &ast,
+            &glyphs
+                .iter()
+                .map(|glyph| glyph.as_ref())
+                .collect::<Vec<_>>(),
+        );
 
         let mut result = self.compile(
             &static_metadata,
             &glyph_order,
             &ast,
+            substitutions,
             kerns.as_ref(),
             marks.as_ref(),
+            context.flags.contains(Flags::OPTIMIZE_LAYOUT),
         )?;
         if result.gdef_classes.is_none() && !static_metadata.gdef_categories.categories.is_empty() {
             // the FEA did not contain an explicit GDEF block with glyph categories,
@@ -689,6 +838,7 @@ fn log_fea_warnings(stage: &str, warnings: &DiagnosticSet) {
     assert!(!warnings.has_errors(), "of course we checked this already");
     if !warnings.is_empty() {
         log::warn!(
+            code = codes::FEA_WARNING;
             "FEA {stage} produced {} warnings:\n{}",
             warnings.len(),
             warnings.display()
@@ -735,11 +885,24 @@ fn get_script_language_systems(ast: &ParseTree) -> HashMap<UnicodeShortName, Vec
 
 /// Return the set of features from the list that we need to generate.
 ///
-/// This ignores features that already exist in the FEA, and for which there
-/// is no insertion mark.
-fn feature_writer_todo_list(features: &[Tag], ast: &ParseTree) -> HashSet<Tag> {
+/// Features the writer isn't configured to generate are ignored. In skip mode
+/// this also ignores features that already exist in the FEA, and for which
+/// there is no insertion mark; in append mode the generated lookups are added
+/// to them instead.
+fn feature_writer_todo_list(
+    features: &[Tag],
+    writer: &ir::FeatureWriter,
+    ast: &ParseTree,
+) -> HashSet<Tag> {
     use fea_rs::typed;
-    let mut result = features.iter().copied().collect::<HashSet<_>>();
+    let mut result = features
+        .iter()
+        .copied()
+        .filter(|tag| writer.generates(*tag))
+        .collect::<HashSet<_>>();
+    if writer.mode == FeatureWriterMode::Append {
+        return result;
+    }
     let mut existing_features = HashMap::new();
     for feature in ast
         .typed_root()
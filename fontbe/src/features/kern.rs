@@ -432,7 +432,11 @@ fn finalize_kerning(
     char_map: HashMap<u32, GlyphId16>,
     non_spacing_glyphs: HashSet<GlyphId16>,
 ) -> Result<FeaRsKerns, Error> {
-    let todo = super::feature_writer_todo_list(&[KERN, DIST], &ast.ast);
+    let Some(writer) = meta.feature_writers.kern.as_ref() else {
+        log::info!("kern feature writer is disabled");
+        return Ok(Default::default());
+    };
+    let todo = super::feature_writer_todo_list(&[KERN, DIST], writer, &ast.ast);
     if pairs.is_empty() || todo.is_empty() {
         log::info!("no kerning work to do");
         return Ok(Default::default());
@@ -245,7 +245,9 @@ impl<'a> MarkLookupBuilder<'a> {
                     | AnchorKind::CursiveEntry
                     | AnchorKind::CursiveExit
                     | AnchorKind::Caret(_)
-                    | AnchorKind::VCaret(_) => (),
+                    | AnchorKind::VCaret(_)
+                    | AnchorKind::MathItalicCorrection
+                    | AnchorKind::MathTopAccent => (),
                 }
                 pruned.entry(gid).or_insert(Vec::new()).push(anchor);
             }
@@ -288,10 +290,16 @@ impl<'a> MarkLookupBuilder<'a> {
 
         let (abvm_glyphs, non_abvm_glyphs) = self.split_mark_and_abvm_blwm_glyphs()?;
 
-        let todo = super::feature_writer_todo_list(
-            &[MARK, MKMK, ABVM, BLWM, CURS],
-            &self.fea_first_pass.ast,
-        );
+        let writers = &self.static_metadata.feature_writers;
+        let ast = &self.fea_first_pass.ast;
+        let mut todo = writers
+            .mark
+            .as_ref()
+            .map(|writer| super::feature_writer_todo_list(&[MARK, MKMK, ABVM, BLWM], writer, ast))
+            .unwrap_or_default();
+        if let Some(writer) = writers.curs.as_ref() {
+            todo.extend(super::feature_writer_todo_list(&[CURS], writer, ast));
+        }
 
         let mut mark_mkmk = self.make_lookups(
             &mark_base_groups,
@@ -1037,7 +1045,9 @@ mod tests {
         coords::{Coord, CoordConverter, NormalizedLocation},
         types::Axis,
     };
-    use fontir::ir::{GdefCategories, NamedInstance};
+    use fontir::ir::{
+        FeatureWriter, FeatureWriterMode, FeatureWriters, GdefCategories, NamedInstance,
+    };
     use kurbo::Point;
 
     use write_fonts::{
@@ -1094,6 +1104,7 @@ mod tests {
         categories: BTreeMap<GlyphName, GlyphClassDef>,
         char_map: HashMap<u32, GlyphName>,
         user_fea: &'static str,
+        feature_writers: FeatureWriters,
     }
 
     struct AnchorBuilder<const N: usize> {
@@ -1176,6 +1187,7 @@ mod tests {
                 categories: Default::default(),
                 char_map: Default::default(),
                 prefer_gdef_categories_in_fea: false,
+                feature_writers: Default::default(),
             }
         }
 
@@ -1196,6 +1208,12 @@ mod tests {
             self
         }
 
+        /// Configure which features may be generated, and how
+        fn set_feature_writers(&mut self, feature_writers: FeatureWriters) -> &mut Self {
+            self.feature_writers = feature_writers;
+            self
+        }
+
         /// Add a glyph with an optional GDEF category.
         ///
         /// the `anchors_fn` argument is a closure where anchors can be added
@@ -1261,6 +1279,7 @@ mod tests {
                 .with_categories(categories)
                 .with_user_fea(self.user_fea)
                 .with_glyph_order(self.anchors.keys().cloned().collect())
+                .with_feature_writers(self.feature_writers.clone())
                 .build()
         }
 
@@ -1659,6 +1678,61 @@ mod tests {
         );
     }
 
+    #[test]
+    fn disabled_mark_writer() {
+        let out = simple_test_input()
+            .set_feature_writers(FeatureWriters {
+                mark: None,
+                ..Default::default()
+            })
+            .get_normalized_output();
+        assert!(out.is_empty(), "{out}");
+    }
+
+    #[test]
+    fn mark_writer_limited_to_some_features() {
+        let out = simple_test_input()
+            .set_feature_writers(FeatureWriters {
+                mark: Some(FeatureWriter {
+                    features: Some([Tag::new(b"mkmk")].into()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .get_normalized_output();
+        assert!(out.is_empty(), "{out}");
+    }
+
+    const EXISTING_MARK_FEA: &str = r#"
+        languagesystem DFLT dflt;
+        feature mark {
+            pos A 0;
+        } mark;
+        "#;
+
+    #[test]
+    fn skip_mode_leaves_existing_feature_alone() {
+        let out = simple_test_input()
+            .set_user_fea(EXISTING_MARK_FEA)
+            .get_normalized_output();
+        assert!(!out.contains("MarkToBase"), "{out}");
+    }
+
+    #[test]
+    fn append_mode_adds_to_existing_feature() {
+        let out = simple_test_input()
+            .set_user_fea(EXISTING_MARK_FEA)
+            .set_feature_writers(FeatureWriters {
+                mark: Some(FeatureWriter {
+                    mode: FeatureWriterMode::Append,
+                    features: None,
+                }),
+                ..Default::default()
+            })
+            .get_normalized_output();
+        assert!(out.contains("MarkToBase"), "{out}");
+    }
+
     // shared between two tests below
     fn gdef_test_input() -> MarksInput<1> {
         let mut out = simple_test_input();
@@ -0,0 +1,714 @@
+//! Generates substitutions, such as ccmp, locl or liga, from the glyphs in the font
+//!
+//! None are generated unless their writer is enabled, see
+//! [FeatureWriters](fontir::ir::FeatureWriters).
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use fea_rs::{
+    compile::{
+        ContextualSubBuilder, FeatureBuilder, FeatureKey, FeatureProvider, LookupId, PendingLookup,
+    },
+    GlyphSet,
+};
+use fontir::ir::{self, GlyphOrder, StaticMetadata};
+use log::warn;
+use write_fonts::{
+    tables::{
+        gdef::GlyphClassDef,
+        gsub::builders::{LigatureSubBuilder, MultipleSubBuilder, SingleSubBuilder},
+        layout::LookupFlag,
+    },
+    types::{GlyphId16, Tag},
+};
+
+use crate::orchestration::FeaFirstPassOutput;
+
+use super::properties::{self, COMMON_SCRIPT, INHERITED_SCRIPT};
+
+const CCMP: Tag = Tag::new(b"ccmp");
+const LOCL: Tag = Tag::new(b"locl");
+const FRAC: Tag = Tag::new(b"frac");
+const NUMR: Tag = Tag::new(b"numr");
+const DNOM: Tag = Tag::new(b"dnom");
+const LIGA: Tag = Tag::new(b"liga");
+const CASE: Tag = Tag::new(b"case");
+const ORDN: Tag = Tag::new(b"ordn");
+const SUPS: Tag = Tag::new(b"sups");
+const SUBS: Tag = Tag::new(b"subs");
+const SINF: Tag = Tag::new(b"sinf");
+
+/// Features that substitute glyphs with the form named with a suffix, e.g. one.sups
+const SUFFIX_FEATURES: &[(Tag, &str)] = &[
+    (NUMR, ".numr"),
+    (DNOM, ".dnom"),
+    (CASE, ".case"),
+    (ORDN, ".ordn"),
+    (SUPS, ".sups"),
+    (SUBS, ".subs"),
+    (SINF, ".sinf"),
+];
+
+/// The generated substitutions, ready to feed to fea-rs
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct GeneratedSubstitutions {
+    /// Precomposed glyphs to the base and marks they are made of
+    ccmp: BTreeMap<GlyphId16, Vec<GlyphId16>>,
+    /// For each language, the scripts it's registered under and its localized forms
+    locl: BTreeMap<Tag, (BTreeSet<Tag>, BTreeMap<GlyphId16, GlyphId16>)>,
+    /// For each of [SUFFIX_FEATURES], glyphs to their suffixed forms
+    suffixed: BTreeMap<Tag, BTreeMap<GlyphId16, GlyphId16>>,
+    frac: Option<Fractions>,
+    /// The glyphs ligatures named e.g. f_f_i are made of, to the ligature
+    liga: BTreeMap<Vec<GlyphId16>, GlyphId16>,
+    ordinals: Option<Ordinals>,
+}
+
+/// Letters after a figure become ordinal indicators, e.g. 1a to 1ª
+#[derive(Debug, Clone, PartialEq)]
+struct Ordinals {
+    figures: BTreeSet<GlyphId16>,
+    /// a and A to ordfeminine, o and O to ordmasculine
+    indicators: BTreeMap<GlyphId16, GlyphId16>,
+}
+
+/// What frac needs, as Glyphs.app writes it: numerators up to the fraction
+/// slash, denominators after it
+#[derive(Debug, Clone, PartialEq)]
+struct Fractions {
+    fraction: GlyphId16,
+    /// Becomes the fraction slash
+    slash: Option<GlyphId16>,
+    /// Glyphs with both forms, to their .numr form
+    numerators: BTreeMap<GlyphId16, GlyphId16>,
+    /// The .numr forms to the .dnom forms of the same glyph
+    denominators: BTreeMap<GlyphId16, GlyphId16>,
+}
+
+impl GeneratedSubstitutions {
+    pub(crate) fn new(
+        static_metadata: &StaticMetadata,
+        glyph_order: &GlyphOrder,
+        fea_first_pass: &FeaFirstPassOutput,
+        glyphs: &[&ir::Glyph],
+    ) -> Self {
+        let writers = &static_metadata.feature_writers;
+        let ast = &fea_first_pass.ast;
+        let mut todo = writers
+            .ccmp
+            .as_ref()
+            .map(|writer| super::feature_writer_todo_list(&[CCMP], writer, ast))
+            .unwrap_or_default();
+        if let Some(writer) = writers.locl.as_ref() {
+            todo.extend(super::feature_writer_todo_list(&[LOCL], writer, ast));
+        }
+        if let Some(writer) = writers.frac.as_ref() {
+            todo.extend(super::feature_writer_todo_list(
+                &[FRAC, NUMR, DNOM],
+                writer,
+                ast,
+            ));
+        }
+        if let Some(writer) = writers.automatic.as_ref() {
+            todo.extend(super::feature_writer_todo_list(
+                &[LIGA, CASE, ORDN, SUPS, SUBS, SINF],
+                writer,
+                ast,
+            ));
+        }
+
+        let mut result = GeneratedSubstitutions::default();
+        if todo.contains(&CCMP) {
+            let gdef_classes =
+                super::get_gdef_classes(static_metadata, fea_first_pass, glyph_order);
+            result.ccmp = decompositions(glyph_order, &gdef_classes, glyphs);
+        }
+        if todo.contains(&LOCL) {
+            let language_systems = super::get_fea_language_systems(ast);
+            result.locl = localized_forms(glyph_order, &language_systems, glyphs);
+        }
+        let mut suffixed: BTreeMap<_, _> = SUFFIX_FEATURES
+            .iter()
+            .map(|(tag, suffix)| (*tag, suffixed_forms(glyph_order, suffix)))
+            .collect();
+        if todo.contains(&FRAC) {
+            result.frac = fractions(glyphs, glyph_order, &suffixed[&NUMR], &suffixed[&DNOM]);
+        }
+        suffixed.retain(|tag, forms| todo.contains(tag) && !forms.is_empty());
+        result.suffixed = suffixed;
+        if todo.contains(&LIGA) {
+            result.liga = ligatures(glyph_order);
+        }
+        if todo.contains(&ORDN) {
+            result.ordinals = ordinals(glyphs, glyph_order);
+        }
+        result
+    }
+}
+
+/// The glyph encoded with the codepoint, if any
+fn glyph_for(glyphs: &[&ir::Glyph], glyph_order: &GlyphOrder, codepoint: u32) -> Option<GlyphId16> {
+    glyphs
+        .iter()
+        .find(|glyph| glyph.codepoints.contains(&codepoint))
+        .and_then(|glyph| glyph_order.glyph_id(&glyph.name))
+}
+
+/// Ligatures named for their parts, without a suffix or suffixed .liga
+fn ligatures(glyph_order: &GlyphOrder) -> BTreeMap<Vec<GlyphId16>, GlyphId16> {
+    glyph_order
+        .names()
+        .filter_map(|name| {
+            let name_str = name.as_str();
+            let parts = name_str.strip_suffix(".liga").unwrap_or(name_str);
+            if parts.starts_with('_') || parts.contains('.') || !parts.contains('_') {
+                return None;
+            }
+            let parts = parts
+                .split('_')
+                .map(|part| glyph_order.glyph_id(part))
+                .collect::<Option<Vec<_>>>()?;
+            Some((parts, glyph_order.glyph_id(name)?))
+        })
+        .collect()
+}
+
+fn ordinals(glyphs: &[&ir::Glyph], glyph_order: &GlyphOrder) -> Option<Ordinals> {
+    let glyph_for = |codepoint| glyph_for(glyphs, glyph_order, codepoint);
+    let figures: BTreeSet<_> = (0x30..=0x39).filter_map(glyph_for).collect();
+    let mut indicators = BTreeMap::new();
+    for (letters, indicator) in [([0x61, 0x41], 0xAA), ([0x6F, 0x4F], 0xBA)] {
+        let Some(indicator) = glyph_for(indicator) else {
+            continue;
+        };
+        indicators.extend(
+            letters
+                .into_iter()
+                .filter_map(glyph_for)
+                .map(|gid| (gid, indicator)),
+        );
+    }
+    (!figures.is_empty() && !indicators.is_empty()).then_some(Ordinals {
+        figures,
+        indicators,
+    })
+}
+
+/// Glyphs to their forms named with the suffix, e.g. one to one.numr
+fn suffixed_forms(glyph_order: &GlyphOrder, suffix: &str) -> BTreeMap<GlyphId16, GlyphId16> {
+    glyph_order
+        .names()
+        .filter_map(|name| {
+            let base = name.as_str().strip_suffix(suffix)?;
+            Some((glyph_order.glyph_id(base)?, glyph_order.glyph_id(name)?))
+        })
+        .collect()
+}
+
+fn fractions(
+    glyphs: &[&ir::Glyph],
+    glyph_order: &GlyphOrder,
+    numr: &BTreeMap<GlyphId16, GlyphId16>,
+    dnom: &BTreeMap<GlyphId16, GlyphId16>,
+) -> Option<Fractions> {
+    let glyph_for = |codepoint| glyph_for(glyphs, glyph_order, codepoint);
+    let fraction = glyph_for(0x2044)?;
+    let (numerators, denominators): (BTreeMap<_, _>, BTreeMap<_, _>) = numr
+        .iter()
+        .filter_map(|(base, numr)| {
+            let dnom = dnom.get(base)?;
+            Some(((*base, *numr), (*numr, *dnom)))
+        })
+        .unzip();
+    if numerators.is_empty() {
+        return None;
+    }
+    Some(Fractions {
+        fraction,
+        slash: glyph_for(0x2F),
+        numerators,
+        denominators,
+    })
+}
+
+/// Encoded glyphs made only of a base component followed by marks decompose into them
+fn decompositions(
+    glyph_order: &GlyphOrder,
+    gdef_classes: &HashMap<GlyphId16, GlyphClassDef>,
+    glyphs: &[&ir::Glyph],
+) -> BTreeMap<GlyphId16, Vec<GlyphId16>> {
+    let is_mark = |gid: &GlyphId16| gdef_classes.get(gid) == Some(&GlyphClassDef::Mark);
+    glyphs
+        .iter()
+        .filter(|glyph| !glyph.codepoints.is_empty())
+        .filter_map(|glyph| {
+            let gid = glyph_order.glyph_id(&glyph.name)?;
+            let instance = glyph.default_instance();
+            if !instance.contours.is_empty() || instance.components.len() < 2 {
+                return None;
+            }
+            let parts = instance
+                .components
+                .iter()
+                .map(|component| glyph_order.glyph_id(&component.base))
+                .collect::<Option<Vec<_>>>()?;
+            (!is_mark(&parts[0]) && parts[1..].iter().all(is_mark)).then_some((gid, parts))
+        })
+        .collect()
+}
+
+/// The language a glyph is the localized form of, if it's named e.g. a.loclNLD
+fn locl_language(name: &str) -> Option<(&str, Tag)> {
+    let (base, suffix) = name.rsplit_once('.')?;
+    let language = suffix.strip_prefix("locl")?;
+    if base.is_empty()
+        || !(3..=4).contains(&language.len())
+        || !language
+            .bytes()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+    {
+        return None;
+    }
+    let tag = Tag::new_checked(format!("{language:<4}").as_bytes()).ok()?;
+    Some((base, tag))
+}
+
+/// Glyphs named base.loclXXX replace base for language XXX.
+///
+/// The feature is registered under each script the FEA declares the language
+/// for or, failing that, the script of the base glyph.
+fn localized_forms(
+    glyph_order: &GlyphOrder,
+    language_systems: &BTreeMap<Tag, Vec<Tag>>,
+    glyphs: &[&ir::Glyph],
+) -> BTreeMap<Tag, (BTreeSet<Tag>, BTreeMap<GlyphId16, GlyphId16>)> {
+    let codepoints: HashMap<_, _> = glyphs
+        .iter()
+        .map(|glyph| (glyph.name.as_str(), &glyph.codepoints))
+        .collect();
+    let mut result = BTreeMap::new();
+    for name in glyph_order.names() {
+        let Some((base, language)) = locl_language(name.as_str()) else {
+            continue;
+        };
+        let (Some(base_gid), Some(gid)) = (glyph_order.glyph_id(base), glyph_order.glyph_id(name))
+        else {
+            continue;
+        };
+        let mut scripts: BTreeSet<_> = language_systems
+            .iter()
+            .filter(|(_, languages)| languages.contains(&language))
+            .map(|(script, _)| *script)
+            .collect();
+        if scripts.is_empty() {
+            scripts = codepoints
+                .get(base)
+                .into_iter()
+                .flat_map(|cps| cps.iter())
+                .flat_map(|cp| properties::scripts_for_codepoint(*cp))
+                .filter(|script| *script != COMMON_SCRIPT && *script != INHERITED_SCRIPT)
+                .flat_map(|script| properties::script_to_ot_tags(&script))
+                .collect();
+        }
+        if scripts.is_empty() {
+            warn!("Unable to tell the script of {name}, not adding it to locl");
+            continue;
+        }
+        let (all_scripts, substitutions) = result
+            .entry(language)
+            .or_insert_with(|| (BTreeSet::new(), BTreeMap::new()));
+        all_scripts.extend(scripts);
+        substitutions.insert(base_gid, gid);
+    }
+    result
+}
+
+impl FeatureProvider for GeneratedSubstitutions {
+    fn add_features(&self, builder: &mut FeatureBuilder) {
+        // both go ahead of the lookups in the FEA, ccmp first
+        if !self.ccmp.is_empty() {
+            let mut subtable = MultipleSubBuilder::default();
+            for (gid, parts) in self.ccmp.iter() {
+                subtable.insert(*gid, parts.clone());
+            }
+            let lookup = builder.add_lookup(
+                PendingLookup::new(vec![subtable], LookupFlag::empty(), None)
+                    .at_front_of_list(true),
+            );
+            builder.add_to_default_language_systems(CCMP, &[lookup]);
+        }
+        for (language, (scripts, substitutions)) in self.locl.iter() {
+            let mut subtable = SingleSubBuilder::default();
+            for (base, localized) in substitutions {
+                subtable.insert(*base, *localized);
+            }
+            let lookup = builder.add_lookup(
+                PendingLookup::new(vec![subtable], LookupFlag::empty(), None)
+                    .at_front_of_list(true),
+            );
+            for script in scripts {
+                builder.add_feature(FeatureKey::new(LOCL, *language, *script), vec![lookup]);
+            }
+        }
+        if let Some(fractions) = self.frac.as_ref() {
+            fractions.add_features(builder);
+        }
+        for (tag, substitutions) in self.suffixed.iter() {
+            let mut lookups = vec![add_single_sub(builder, substitutions)];
+            if *tag == ORDN {
+                if let Some(ordinals) = self.ordinals.as_ref() {
+                    lookups.push(ordinals.add_lookup(builder));
+                }
+            }
+            builder.add_to_default_language_systems(*tag, &lookups);
+        }
+        if !self.suffixed.contains_key(&ORDN) {
+            if let Some(ordinals) = self.ordinals.as_ref() {
+                let lookup = ordinals.add_lookup(builder);
+                builder.add_to_default_language_systems(ORDN, &[lookup]);
+            }
+        }
+        if !self.liga.is_empty() {
+            let mut subtable = LigatureSubBuilder::default();
+            // longest first, so f_f_i is matched before f_f
+            let mut ligatures = self.liga.iter().collect::<Vec<_>>();
+            ligatures.sort_by_key(|(parts, _)| std::cmp::Reverse(parts.len()));
+            for (parts, ligature) in ligatures {
+                subtable.insert(parts.clone(), *ligature);
+            }
+            let lookup = builder.add_lookup(PendingLookup::new(
+                vec![subtable],
+                LookupFlag::empty(),
+                None,
+            ));
+            builder.add_to_default_language_systems(LIGA, &[lookup]);
+        }
+    }
+}
+
+impl Ordinals {
+    // sub [zero one ...] [a A o O]' lookup INDICATORS;
+    fn add_lookup(&self, builder: &mut FeatureBuilder) -> LookupId {
+        let indicators = add_single_sub(builder, &self.indicators);
+        let mut after_figure = ContextualSubBuilder::default();
+        after_figure.add_rule(
+            vec![self.figures.iter().copied().collect::<GlyphSet>()],
+            vec![(
+                self.indicators.keys().copied().collect::<GlyphSet>(),
+                vec![indicators],
+            )],
+            Vec::new(),
+        );
+        builder.add_lookup(PendingLookup::new(
+            vec![after_figure],
+            LookupFlag::empty(),
+            None,
+        ))
+    }
+}
+
+impl Fractions {
+    // sub slash by fraction;
+    // sub [one two] by [one.numr two.numr];
+    // sub [fraction one.dnom two.dnom] [one.numr two.numr]' lookup DOWN;
+    fn add_features(&self, builder: &mut FeatureBuilder) {
+        let mut lookups = Vec::new();
+        if let Some(slash) = self.slash {
+            lookups.push(add_single_sub(
+                builder,
+                &BTreeMap::from([(slash, self.fraction)]),
+            ));
+        }
+        lookups.push(add_single_sub(builder, &self.numerators));
+
+        let down = add_single_sub(builder, &self.denominators);
+        let mut after_fraction = ContextualSubBuilder::default();
+        after_fraction.add_rule(
+            vec![std::iter::once(self.fraction)
+                .chain(self.denominators.values().copied())
+                .collect::<GlyphSet>()],
+            vec![(
+                self.denominators.keys().copied().collect::<GlyphSet>(),
+                vec![down],
+            )],
+            Vec::new(),
+        );
+        lookups.push(builder.add_lookup(PendingLookup::new(
+            vec![after_fraction],
+            LookupFlag::empty(),
+            None,
+        )));
+        builder.add_to_default_language_systems(FRAC, &lookups);
+    }
+}
+
+fn add_single_sub(
+    builder: &mut FeatureBuilder,
+    substitutions: &BTreeMap<GlyphId16, GlyphId16>,
+) -> LookupId {
+    let mut subtable = SingleSubBuilder::default();
+    for (target, replacement) in substitutions {
+        subtable.insert(*target, *replacement);
+    }
+    builder.add_lookup(PendingLookup::new(
+        vec![subtable],
+        LookupFlag::empty(),
+        None,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use fontdrasil::coords::NormalizedLocation;
+    use fontir::ir::{Component, FeatureWriter, GdefCategories, GlyphBuilder, GlyphInstance};
+    use kurbo::{Affine, Rect, Shape};
+
+    use crate::features::test_helpers::LayoutOutputBuilder;
+
+    use super::*;
+
+    fn glyph(name: &str, codepoint: Option<u32>, components: &[&str]) -> ir::Glyph {
+        let mut builder = GlyphBuilder::new(name.into());
+        builder.codepoints.extend(codepoint);
+        let instance = GlyphInstance {
+            contours: if components.is_empty() {
+                vec![Rect::new(0.0, 0.0, 10.0, 10.0).to_path(0.1)]
+            } else {
+                Vec::new()
+            },
+            components: components
+                .iter()
+                .map(|base| Component {
+                    base: (*base).into(),
+                    transform: Affine::IDENTITY,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        builder
+            .try_add_source(&NormalizedLocation::for_pos(&[("wght", 0.0)]), instance)
+            .unwrap();
+        builder.build().unwrap()
+    }
+
+    fn generate(fea: &str, glyphs: &[ir::Glyph]) -> (GlyphOrder, GeneratedSubstitutions) {
+        let glyph_order: GlyphOrder = glyphs.iter().map(|g| g.name.clone()).collect();
+        let mut feature_writers = ir::FeatureWriters::default();
+        feature_writers.ccmp = Some(FeatureWriter::default());
+        feature_writers.locl = Some(FeatureWriter::default());
+        feature_writers.frac = Some(FeatureWriter::default());
+        feature_writers.automatic = Some(FeatureWriter::default());
+        let layout = LayoutOutputBuilder::new()
+            .with_user_fea(fea)
+            .with_glyph_order(glyph_order.clone())
+            .with_categories(GdefCategories {
+                categories: [("acutecomb".into(), GlyphClassDef::Mark)].into(),
+                prefer_gdef_categories_in_fea: false,
+            })
+            .with_feature_writers(feature_writers)
+            .build();
+        let glyphs = glyphs.iter().collect::<Vec<_>>();
+        let generated = GeneratedSubstitutions::new(
+            &layout.static_metadata,
+            &layout.glyph_order,
+            &layout.first_pass_fea,
+            &glyphs,
+        );
+        (glyph_order, generated)
+    }
+
+    #[test]
+    fn locl_suffixes() {
+        assert_eq!(Some(("a", Tag::new(b"NLD "))), locl_language("a.loclNLD"));
+        assert_eq!(
+            Some(("a.ss01", Tag::new(b"TRK "))),
+            locl_language("a.ss01.loclTRK")
+        );
+        assert_eq!(None, locl_language("a.locl"));
+        assert_eq!(None, locl_language("a.loclnld"));
+        assert_eq!(None, locl_language("a.alt"));
+    }
+
+    #[test]
+    fn ccmp_decomposes_base_and_marks() {
+        let (glyph_order, generated) = generate(
+            "languagesystem DFLT dflt;",
+            &[
+                glyph("A", Some(0x41), &[]),
+                glyph("acutecomb", Some(0x301), &[]),
+                glyph("Aacute", Some(0xC1), &["A", "acutecomb"]),
+                // not a mark, so not decomposed
+                glyph("AA", Some(0xA733), &["A", "A"]),
+                // unencoded
+                glyph("A.acute", None, &["A", "acutecomb"]),
+            ],
+        );
+        let gid = |name: &str| glyph_order.glyph_id(name).unwrap();
+        assert_eq!(
+            BTreeMap::from([(gid("Aacute"), vec![gid("A"), gid("acutecomb")])]),
+            generated.ccmp
+        );
+    }
+
+    #[test]
+    fn locl_uses_fea_language_systems() {
+        let (glyph_order, generated) = generate(
+            "languagesystem DFLT dflt; languagesystem latn dflt; languagesystem latn TRK;",
+            &[
+                glyph("i", Some(0x69), &[]),
+                glyph("i.loclTRK", None, &[]),
+                glyph("ij", Some(0x133), &[]),
+                glyph("ij.loclNLD", None, &[]),
+                glyph("b.loclNLD", None, &[]),
+            ],
+        );
+        let gid = |name: &str| glyph_order.glyph_id(name).unwrap();
+        assert_eq!(
+            BTreeMap::from([
+                (
+                    Tag::new(b"NLD "),
+                    (
+                        BTreeSet::from([Tag::new(b"latn")]),
+                        BTreeMap::from([(gid("ij"), gid("ij.loclNLD"))])
+                    )
+                ),
+                (
+                    Tag::new(b"TRK "),
+                    (
+                        BTreeSet::from([Tag::new(b"latn")]),
+                        BTreeMap::from([(gid("i"), gid("i.loclTRK"))])
+                    )
+                ),
+            ]),
+            generated.locl
+        );
+    }
+
+    #[test]
+    fn nothing_generated_by_default() {
+        let glyphs = [glyph("i", Some(0x69), &[]), glyph("i.loclTRK", None, &[])];
+        let glyph_order: GlyphOrder = glyphs.iter().map(|g| g.name.clone()).collect();
+        let layout = LayoutOutputBuilder::new()
+            .with_glyph_order(glyph_order)
+            .build();
+        let glyphs = glyphs.iter().collect::<Vec<_>>();
+        assert_eq!(
+            GeneratedSubstitutions::default(),
+            GeneratedSubstitutions::new(
+                &layout.static_metadata,
+                &layout.glyph_order,
+                &layout.first_pass_fea,
+                &glyphs,
+            )
+        );
+    }
+
+    #[test]
+    fn fractions_from_numr_and_dnom() {
+        let (glyph_order, generated) = generate(
+            "languagesystem DFLT dflt;",
+            &[
+                glyph("one", Some(0x31), &[]),
+                glyph("two", Some(0x32), &[]),
+                glyph("slash", Some(0x2F), &[]),
+                glyph("fraction", Some(0x2044), &[]),
+                glyph("one.numr", None, &[]),
+                glyph("one.dnom", None, &[]),
+                // no dnom, so in numr but not frac
+                glyph("two.numr", None, &[]),
+            ],
+        );
+        let gid = |name: &str| glyph_order.glyph_id(name).unwrap();
+        assert_eq!(
+            BTreeMap::from([(gid("one"), gid("one.numr")), (gid("two"), gid("two.numr"))]),
+            generated.suffixed[&NUMR]
+        );
+        assert_eq!(
+            BTreeMap::from([(gid("one"), gid("one.dnom"))]),
+            generated.suffixed[&DNOM]
+        );
+        assert_eq!(
+            Some(Fractions {
+                fraction: gid("fraction"),
+                slash: Some(gid("slash")),
+                numerators: BTreeMap::from([(gid("one"), gid("one.numr"))]),
+                denominators: BTreeMap::from([(gid("one.numr"), gid("one.dnom"))]),
+            }),
+            generated.frac
+        );
+    }
+
+    #[test]
+    fn no_frac_without_fraction_slash() {
+        let (_, generated) = generate(
+            "languagesystem DFLT dflt;",
+            &[
+                glyph("one", Some(0x31), &[]),
+                glyph("one.numr", None, &[]),
+                glyph("one.dnom", None, &[]),
+            ],
+        );
+        assert_eq!(None, generated.frac);
+        assert_eq!(1, generated.suffixed[&NUMR].len());
+    }
+
+    #[test]
+    fn ligatures_from_names() {
+        let (glyph_order, generated) = generate(
+            "languagesystem DFLT dflt;",
+            &[
+                glyph("f", Some(0x66), &[]),
+                glyph("i", Some(0x69), &[]),
+                glyph("f_i", None, &[]),
+                glyph("f_f_i.liga", None, &[]),
+                // not ligatures
+                glyph("f_i.sc", None, &[]),
+                glyph("_part", None, &[]),
+                glyph("f_x", None, &[]),
+            ],
+        );
+        let gid = |name: &str| glyph_order.glyph_id(name).unwrap();
+        assert_eq!(
+            BTreeMap::from([
+                (vec![gid("f"), gid("i")], gid("f_i")),
+                (vec![gid("f"), gid("f"), gid("i")], gid("f_f_i.liga")),
+            ]),
+            generated.liga
+        );
+    }
+
+    #[test]
+    fn suffixed_forms_and_ordinals() {
+        let (glyph_order, generated) = generate(
+            "languagesystem DFLT dflt;",
+            &[
+                glyph("one", Some(0x31), &[]),
+                glyph("a", Some(0x61), &[]),
+                glyph("A", Some(0x41), &[]),
+                glyph("ordfeminine", Some(0xAA), &[]),
+                glyph("one.sups", None, &[]),
+                glyph("one.sinf", None, &[]),
+                glyph("A.case", None, &[]),
+            ],
+        );
+        let gid = |name: &str| glyph_order.glyph_id(name).unwrap();
+        assert_eq!(
+            BTreeMap::from([
+                (CASE, BTreeMap::from([(gid("A"), gid("A.case"))])),
+                (SUPS, BTreeMap::from([(gid("one"), gid("one.sups"))])),
+                (SINF, BTreeMap::from([(gid("one"), gid("one.sinf"))])),
+            ]),
+            generated.suffixed
+        );
+        assert_eq!(
+            Some(Ordinals {
+                figures: BTreeSet::from([gid("one")]),
+                indicators: BTreeMap::from([
+                    (gid("a"), gid("ordfeminine")),
+                    (gid("A"), gid("ordfeminine"))
+                ]),
+            }),
+            generated.ordinals
+        );
+    }
+}
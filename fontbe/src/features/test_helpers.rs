@@ -4,7 +4,7 @@ use std::{collections::HashSet, path::Path, sync::Arc};
 
 use fea_rs::compile::{Compilation, FeatureProvider};
 use fontdrasil::{coords::NormalizedLocation, types::Axes};
-use fontir::ir::{GdefCategories, GlyphOrder, NamedInstance, StaticMetadata};
+use fontir::ir::{FeatureWriters, GdefCategories, GlyphOrder, NamedInstance, StaticMetadata};
 
 use crate::orchestration::FeaFirstPassOutput;
 
@@ -18,6 +18,7 @@ pub(crate) struct LayoutOutputBuilder {
     glyph_locations: HashSet<NormalizedLocation>,
     user_fea: Arc<str>,
     glyph_order: GlyphOrder,
+    feature_writers: FeatureWriters,
 }
 
 /// A helper for compiling layout tables (including with user-provided features)
@@ -62,8 +63,13 @@ impl LayoutOutputBuilder {
         self
     }
 
+    pub(crate) fn with_feature_writers(&mut self, feature_writers: FeatureWriters) -> &mut Self {
+        self.feature_writers = feature_writers;
+        self
+    }
+
     pub(crate) fn build(&self) -> LayoutOutput {
-        let static_metadata = StaticMetadata::new(
+        let mut static_metadata = StaticMetadata::new(
             1000,
             Default::default(),
             self.axes.clone().into_inner(),
@@ -76,6 +82,7 @@ impl LayoutOutputBuilder {
             false,
         )
         .unwrap();
+        static_metadata.feature_writers = self.feature_writers.clone();
 
         let glyph_map = self.glyph_order.names().cloned().collect();
         // first get the AST, which we need to use as input
@@ -127,6 +134,7 @@ impl Default for LayoutOutputBuilder {
             glyph_locations: Default::default(),
             user_fea: "languagesystem DFLT dflt;".into(),
             glyph_order: Default::default(),
+            feature_writers: Default::default(),
         }
     }
 }
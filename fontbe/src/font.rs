@@ -1,17 +1,21 @@
 //! Merge tables into a font
 
 use fontdrasil::orchestration::{Access, AccessBuilder, Work};
-use fontir::orchestration::WorkId as FeWorkId;
+use fontir::orchestration::{Flags, WorkId as FeWorkId};
 use log::debug;
+use write_fonts::tables::{head::MacStyle, os2::SelectionFlags};
 use write_fonts::{
-    read::TopLevelTable,
+    read::{
+        tables::{cbdt::Cbdt, cblc::Cblc, kern::Kern, sbix::Sbix, svg::Svg},
+        FontRef, TopLevelTable,
+    },
     tables::{
         avar::Avar, cmap::Cmap, colr::Colr, cpal::Cpal, fvar::Fvar, gasp::Gasp, gdef::Gdef,
         glyf::Glyf, gpos::Gpos, gsub::Gsub, gvar::Gvar, head::Head, hhea::Hhea, hmtx::Hmtx,
         hvar::Hvar, loca::Loca, maxp::Maxp, meta::Meta, mvar::Mvar, name::Name, os2::Os2,
         post::Post, stat::Stat, vhea::Vhea, vmtx::Vmtx,
     },
-    types::Tag,
+    types::{NameId, Tag},
     FontBuilder,
 };
 
@@ -36,6 +40,8 @@ fn is_variable_only(workid: &WorkId) -> bool {
 
 const TABLES_TO_MERGE: &[(WorkId, Tag)] = &[
     (WorkId::Avar, Avar::TAG),
+    (WorkId::Cbdt, Cbdt::TAG),
+    (WorkId::Cblc, Cblc::TAG),
     (WorkId::Cmap, Cmap::TAG),
     (WorkId::Colr, Colr::TAG),
     (WorkId::Cpal, Cpal::TAG),
@@ -49,12 +55,16 @@ const TABLES_TO_MERGE: &[(WorkId, Tag)] = &[
     (WorkId::Gsub, Gsub::TAG),
     (WorkId::Gdef, Gdef::TAG),
     (WorkId::Gvar, Gvar::TAG),
+    (WorkId::Kern, Kern::TAG),
     (WorkId::Loca, Loca::TAG),
+    (WorkId::Math, Tag::new(b"MATH")),
     (WorkId::Maxp, Maxp::TAG),
     (WorkId::Name, Name::TAG),
     (WorkId::Os2, Os2::TAG),
     (WorkId::Post, Post::TAG),
+    (WorkId::Sbix, Sbix::TAG),
     (WorkId::Stat, Stat::TAG),
+    (WorkId::Svg, Svg::TAG),
     (WorkId::Hvar, Hvar::TAG),
     (WorkId::Mvar, Mvar::TAG),
     (WorkId::Meta, Meta::TAG),
@@ -78,7 +88,13 @@ fn has(context: &Context, id: WorkId) -> bool {
         WorkId::Gsub => context.gsub.try_get().is_some(),
         WorkId::Gdef => context.gdef.try_get().is_some(),
         WorkId::Gvar => context.gvar.try_get().is_some(),
+        WorkId::Kern => context.kern.try_get().is_some(),
+        WorkId::Cbdt => context.cbdt.try_get().is_some(),
+        WorkId::Cblc => context.cblc.try_get().is_some(),
+        WorkId::Sbix => context.sbix.try_get().is_some(),
+        WorkId::Svg => context.svg.try_get().is_some(),
         WorkId::Loca => context.loca.try_get().is_some(),
+        WorkId::Math => context.math.try_get().is_some(),
         WorkId::Maxp => context.maxp.try_get().is_some(),
         WorkId::Name => context.name.try_get().is_some(),
         WorkId::Os2 => context.os2.try_get().is_some(),
@@ -99,7 +115,7 @@ fn bytes_for(context: &Context, id: WorkId) -> Result<Option<Vec<u8>>, Error> {
         WorkId::Avar => context.avar.get().as_ref().as_ref().and_then(to_bytes),
         WorkId::Cmap => to_bytes(context.cmap.get().as_ref()),
         WorkId::Colr => to_bytes(context.colr.get().as_ref()),
-        WorkId::Cpal => to_bytes(context.cpal.get().as_ref()),
+        WorkId::Cpal => Some(context.cpal.get().as_ref().get().to_vec()),
         WorkId::Fvar => to_bytes(context.fvar.get().as_ref()),
         WorkId::Head => to_bytes(context.head.get().as_ref()),
         WorkId::Hhea => to_bytes(context.hhea.get().as_ref()),
@@ -110,7 +126,13 @@ fn bytes_for(context: &Context, id: WorkId) -> Result<Option<Vec<u8>>, Error> {
         WorkId::Gsub => to_bytes(context.gsub.get().as_ref()),
         WorkId::Gdef => to_bytes(context.gdef.get().as_ref()),
         WorkId::Gvar => Some(context.gvar.get().as_ref().get().to_vec()),
+        WorkId::Kern => Some(context.kern.get().as_ref().get().to_vec()),
+        WorkId::Cbdt => Some(context.cbdt.get().as_ref().get().to_vec()),
+        WorkId::Cblc => Some(context.cblc.get().as_ref().get().to_vec()),
+        WorkId::Sbix => Some(context.sbix.get().as_ref().get().to_vec()),
+        WorkId::Svg => Some(context.svg.get().as_ref().get().to_vec()),
         WorkId::Loca => Some(context.loca.get().as_ref().get().to_vec()),
+        WorkId::Math => Some(context.math.get().as_ref().get().to_vec()),
         WorkId::Maxp => to_bytes(context.maxp.get().as_ref()),
         WorkId::Name => to_bytes(context.name.get().as_ref()),
         WorkId::Os2 => to_bytes(context.os2.get().as_ref()),
@@ -126,6 +148,94 @@ fn bytes_for(context: &Context, id: WorkId) -> Result<Option<Vec<u8>>, Error> {
     Ok(bytes)
 }
 
+/// Cross-check the style bits of head and OS/2 against each other, the italic
+/// angle and the subfamily name, which are each derived by their own work.
+///
+/// fsSelection, which comes most directly from the source, wins: REGULAR is
+/// cleared if BOLD or ITALIC is set and macStyle is made to match. A
+/// disagreement with the italic angle or subfamily name can't be fixed,
+/// so is only reported. Returns a description of each problem found.
+fn reconcile_style(
+    head: &mut Head,
+    os2: &mut Os2,
+    italic_angle: f64,
+    subfamily: Option<&str>,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    let fs_selection = os2.fs_selection;
+    let bold = fs_selection.contains(SelectionFlags::BOLD);
+    let italic = fs_selection.contains(SelectionFlags::ITALIC);
+
+    if fs_selection.contains(SelectionFlags::REGULAR) && (bold || italic) {
+        problems.push("fsSelection sets REGULAR as well as BOLD or ITALIC".to_string());
+        os2.fs_selection.remove(SelectionFlags::REGULAR);
+    }
+
+    let mut mac_style = head.mac_style;
+    mac_style.remove(MacStyle::BOLD | MacStyle::ITALIC);
+    if bold {
+        mac_style.insert(MacStyle::BOLD);
+    }
+    if italic {
+        mac_style.insert(MacStyle::ITALIC);
+    }
+    if mac_style != head.mac_style {
+        problems.push(format!(
+            "macStyle {:?} doesn't match fsSelection {:?}",
+            head.mac_style, fs_selection
+        ));
+        head.mac_style = mac_style;
+    }
+
+    if italic_angle != 0.0 && !italic && !fs_selection.contains(SelectionFlags::OBLIQUE) {
+        problems.push(format!(
+            "italic angle is {italic_angle} but fsSelection is neither ITALIC nor OBLIQUE"
+        ));
+    }
+
+    if let Some(subfamily) = subfamily {
+        let words = subfamily.to_lowercase();
+        let words = words.split_whitespace().collect::<Vec<_>>();
+        if (words.contains(&"bold"), words.contains(&"italic")) != (bold, italic) {
+            problems.push(format!(
+                "subfamily name '{subfamily}' doesn't match fsSelection {fs_selection:?}"
+            ));
+        }
+    }
+
+    problems
+}
+
+/// head and OS/2 with their style bits made consistent, if both exist.
+fn consistent_style(context: &Context) -> Result<Option<(Head, Os2)>, Error> {
+    let (Some(head), Some(os2)) = (context.head.try_get(), context.os2.try_get()) else {
+        return Ok(None);
+    };
+    let mut head = (*head).clone();
+    let mut os2 = (*os2).clone();
+    let italic_angle = context
+        .post
+        .try_get()
+        .map(|post| post.italic_angle.to_f64())
+        .unwrap_or_default();
+    let name = context.name.try_get();
+    let subfamily = name.as_ref().and_then(|name| {
+        name.name_record
+            .iter()
+            .find(|record| record.name_id == NameId::SUBFAMILY_NAME)
+            .map(|record| record.string.as_str())
+    });
+
+    let problems = reconcile_style(&mut head, &mut os2, italic_angle, subfamily);
+    if !problems.is_empty() && context.flags.contains(Flags::STRICT_STYLE) {
+        return Err(Error::InconsistentStyle(problems.join("; ")));
+    }
+    for problem in problems {
+        log::warn!("{problem}");
+    }
+    Ok(Some((head, os2)))
+}
+
 impl Work<Context, AnyWorkId, Error> for FontWork {
     fn id(&self) -> AnyWorkId {
         WorkId::Font.into()
@@ -147,7 +257,13 @@ impl Work<Context, AnyWorkId, Error> for FontWork {
             .variant(WorkId::Gsub)
             .variant(WorkId::Gdef)
             .variant(WorkId::Gvar)
+            .variant(WorkId::Kern)
+            .variant(WorkId::Cbdt)
+            .variant(WorkId::Cblc)
+            .variant(WorkId::Sbix)
+            .variant(WorkId::Svg)
             .variant(WorkId::Loca)
+            .variant(WorkId::Math)
             .variant(WorkId::Maxp)
             .variant(WorkId::Name)
             .variant(WorkId::Os2)
@@ -156,6 +272,7 @@ impl Work<Context, AnyWorkId, Error> for FontWork {
             .variant(WorkId::Hvar)
             .variant(WorkId::Mvar)
             .variant(WorkId::Meta)
+            .variant(WorkId::Base)
             .variant(WorkId::LocaFormat)
             .variant(WorkId::Vhea)
             .variant(WorkId::Vmtx)
@@ -169,21 +286,29 @@ impl Work<Context, AnyWorkId, Error> for FontWork {
         // Lets go right ahead and believe those bytes are a font
         let mut builder = FontBuilder::default();
 
-        // currently this is a special case where we slap the table from FEA
-        // directly into the font, if it exists; if we ever start compiling
-        // this directly, we need to merge this at that point.
+        // BASE is a special case: a table from FEA is slapped directly into the
+        // font, taking precedence over one generated from the source's baselines.
         assert!(
             !TABLES_TO_MERGE
                 .iter()
                 .any(|(_, tag)| *tag == Tag::new(b"BASE")),
-            "if manually generating BASE, handle possible BASE table in FEA"
+            "BASE is merged here, so that one in FEA can take precedence"
         );
-        if let Some(base) = context
+        let fea_base = context
             .extra_fea_tables
             .try_get()
-            .and_then(|fea| fea.base.clone())
-        {
-            log::info!("using BASE table from fea");
+            .and_then(|fea| fea.base.clone());
+        let base = match (fea_base, context.base.try_get()) {
+            (Some(fea_base), generated) => {
+                if generated.is_some() {
+                    log::warn!("BASE table from fea replaces the baselines from the source");
+                }
+                log::info!("using BASE table from fea");
+                Some(fea_base)
+            }
+            (None, generated) => generated.map(|base| (*base).clone()),
+        };
+        if let Some(base) = base {
             builder
                 .add_table(&base)
                 .map_err(|e| Error::DumpTableError {
@@ -192,6 +317,8 @@ impl Work<Context, AnyWorkId, Error> for FontWork {
                 })?;
         }
 
+        let styled = consistent_style(context)?;
+
         // A fancier implementation would mmap the files. We basic.
         let is_static = context.ir.static_metadata.get().axes.is_empty();
         for (work_id, tag) in TABLES_TO_MERGE {
@@ -200,7 +327,12 @@ impl Work<Context, AnyWorkId, Error> for FontWork {
                 continue;
             }
             debug!("Grabbing {tag} for final font");
-            if let Some(bytes) = bytes_for(context, work_id.clone())? {
+            let bytes = match (work_id, &styled) {
+                (WorkId::Head, Some((head, _))) => to_bytes(head),
+                (WorkId::Os2, Some((_, os2))) => to_bytes(os2),
+                _ => bytes_for(context, work_id.clone())?,
+            };
+            if let Some(bytes) = bytes {
                 if is_variable_only(work_id) && is_static {
                     log::warn!("We generated {tag} for a static font, which seems weird but okay");
                 }
@@ -217,3 +349,192 @@ impl Work<Context, AnyWorkId, Error> for FontWork {
         Ok(())
     }
 }
+
+/// Tables whose content refers to each other, so one can't be patched without the other
+const PATCH_DEPENDENCIES: &[(Tag, Tag)] = &[
+    (Glyf::TAG, Loca::TAG),
+    (Loca::TAG, Glyf::TAG),
+    (Cbdt::TAG, Cblc::TAG),
+    (Cblc::TAG, Cbdt::TAG),
+];
+
+/// Replace, add or remove tables of an already built font
+///
+/// Each patch is a table tag and its new content, or None to remove the table.
+/// Tables that aren't patched are copied as-is. Table offsets and checksums, and
+/// head.checksumAdjustment, are recomputed so the result is a valid font.
+///
+/// Tables that refer to each other, such as glyf and loca, must be patched together.
+pub fn patch_tables(font: &[u8], patches: &[(Tag, Option<&[u8]>)]) -> Result<Vec<u8>, Error> {
+    let font = FontRef::new(font)?;
+    let patched = |tag: Tag| patches.iter().any(|(patch_tag, _)| *patch_tag == tag);
+    if let Some((tag, dependency)) = PATCH_DEPENDENCIES
+        .iter()
+        .find(|(tag, dependency)| patched(*tag) && !patched(*dependency))
+    {
+        return Err(Error::IncompletePatch(*tag, *dependency));
+    }
+
+    let mut builder = FontBuilder::default();
+    for (tag, data) in patches {
+        if let Some(data) = data {
+            builder.add_raw(*tag, *data);
+        }
+    }
+    for record in font.table_directory.table_records() {
+        let tag = record.tag();
+        if patched(tag) {
+            continue;
+        }
+        let data = font.table_data(tag).ok_or(Error::MissingTable(tag))?;
+        builder.add_raw(tag, data.as_bytes());
+    }
+    debug!(
+        "Patched {} table(s) of a font with {} tables",
+        patches.len(),
+        font.table_directory.num_tables()
+    );
+    Ok(builder.build())
+}
+
+#[cfg(test)]
+mod tests {
+    use write_fonts::{
+        dump_table,
+        read::{tables::compute_checksum, TableProvider},
+    };
+
+    use super::*;
+
+    #[test]
+    fn mac_style_follows_fs_selection() {
+        let mut head = Head::default();
+        let mut os2 = Os2 {
+            fs_selection: SelectionFlags::REGULAR | SelectionFlags::BOLD,
+            ..Default::default()
+        };
+        let problems = reconcile_style(&mut head, &mut os2, 0.0, Some("Bold"));
+        assert_eq!(2, problems.len(), "{problems:?}");
+        assert_eq!(MacStyle::BOLD, head.mac_style);
+        assert_eq!(SelectionFlags::BOLD, os2.fs_selection);
+    }
+
+    #[test]
+    fn consistent_style_is_left_alone() {
+        let mut head = Head {
+            mac_style: MacStyle::BOLD | MacStyle::ITALIC,
+            ..Default::default()
+        };
+        let mut os2 = Os2 {
+            fs_selection: SelectionFlags::BOLD | SelectionFlags::ITALIC,
+            ..Default::default()
+        };
+        let problems = reconcile_style(&mut head, &mut os2, -12.0, Some("Bold Italic"));
+        assert!(problems.is_empty(), "{problems:?}");
+    }
+
+    #[test]
+    fn report_what_cant_be_fixed() {
+        let mut head = Head::default();
+        let mut os2 = Os2 {
+            fs_selection: SelectionFlags::REGULAR,
+            ..Default::default()
+        };
+        let problems = reconcile_style(&mut head, &mut os2, -12.0, Some("Italic"));
+        assert_eq!(2, problems.len(), "{problems:?}");
+        assert_eq!(SelectionFlags::REGULAR, os2.fs_selection);
+    }
+
+    fn font_with(tables: &[(Tag, &[u8])]) -> Vec<u8> {
+        let mut builder = FontBuilder::default();
+        builder.add_table(&Head::default()).unwrap();
+        for (tag, data) in tables {
+            builder.add_raw(*tag, *data);
+        }
+        builder.build()
+    }
+
+    /// The checksum of every table, and of the font as a whole, must be right
+    fn assert_checksums(font: &[u8]) {
+        let font_ref = FontRef::new(font).unwrap();
+        for record in font_ref.table_directory.table_records() {
+            let mut data = font_ref
+                .table_data(record.tag())
+                .unwrap()
+                .as_bytes()
+                .to_vec();
+            if record.tag() == Head::TAG {
+                data[8..12].fill(0);
+            }
+            assert_eq!(
+                compute_checksum(&data),
+                record.checksum(),
+                "{}",
+                record.tag()
+            );
+        }
+        assert_eq!(0xB1B0AFBA, compute_checksum(font));
+    }
+
+    #[test]
+    fn patch_replaces_adds_and_removes() {
+        let font = font_with(&[
+            (Tag::new(b"abcd"), b"old".as_slice()),
+            (Tag::new(b"efgh"), b"gone".as_slice()),
+            (Tag::new(b"ijkl"), b"untouched".as_slice()),
+        ]);
+        let patched = patch_tables(
+            &font,
+            &[
+                (Tag::new(b"abcd"), Some(b"new content".as_slice())),
+                (Tag::new(b"efgh"), None),
+                (Tag::new(b"mnop"), Some(b"added".as_slice())),
+            ],
+        )
+        .unwrap();
+
+        assert_checksums(&patched);
+        let font_ref = FontRef::new(&patched).unwrap();
+        let table = |tag: &[u8; 4]| {
+            font_ref
+                .table_data(Tag::new(tag))
+                .map(|data| data.as_bytes().to_vec())
+        };
+        assert_eq!(Some(b"new content".to_vec()), table(b"abcd"));
+        assert_eq!(None, table(b"efgh"));
+        assert_eq!(Some(b"untouched".to_vec()), table(b"ijkl"));
+        assert_eq!(Some(b"added".to_vec()), table(b"mnop"));
+        assert!(font_ref.head().is_ok());
+    }
+
+    #[test]
+    fn patch_recomputes_checksum_adjustment() {
+        let font = font_with(&[(Tag::new(b"abcd"), b"old".as_slice())]);
+        let head = Head {
+            units_per_em: 2048,
+            ..Default::default()
+        };
+        let head_bytes = dump_table(&head).unwrap();
+
+        let patched = patch_tables(&font, &[(Head::TAG, Some(head_bytes.as_slice()))]).unwrap();
+
+        assert_checksums(&patched);
+        assert_eq!(
+            2048,
+            FontRef::new(&patched)
+                .unwrap()
+                .head()
+                .unwrap()
+                .units_per_em()
+        );
+    }
+
+    #[test]
+    fn patch_glyf_needs_loca() {
+        let font = font_with(&[]);
+        assert!(matches!(
+            patch_tables(&font, &[(Glyf::TAG, Some(b"".as_slice()))]),
+            Err(Error::IncompletePatch(tag, dependency)) if tag == Glyf::TAG && dependency == Loca::TAG
+        ));
+    }
+}
@@ -2,9 +2,12 @@
 
 use log::trace;
 
-use fontdrasil::coords::UserLocation;
+use fontdrasil::coords::{UserCoord, UserLocation};
 use fontdrasil::orchestration::{Access, Work};
-use fontir::{ir::StaticMetadata, orchestration::WorkId as FeWorkId};
+use fontir::{
+    ir::StaticMetadata,
+    orchestration::{Flags, WorkId as FeWorkId},
+};
 use write_fonts::{
     tables::fvar::{AxisInstanceArrays, Fvar, InstanceRecord, VariationAxisRecord},
     types::{Fixed, NameId},
@@ -28,7 +31,9 @@ pub fn create_fvar_work() -> Box<BeWork> {
     Box::new(FvarWork {})
 }
 
-fn generate_fvar(static_metadata: &StaticMetadata) -> Option<Fvar> {
+/// If `round_instances` is set the coordinates of named instances are rounded
+/// to whole numbers.
+fn generate_fvar(static_metadata: &StaticMetadata, round_instances: bool) -> Option<Fvar> {
     // Guard clause: don't produce fvar for a static font
     if static_metadata.axes.is_empty() {
         trace!("Skip fvar; this is not a variable font");
@@ -89,12 +94,25 @@ fn generate_fvar(static_metadata: &StaticMetadata) -> Option<Fvar> {
             .named_instances
             .iter()
             .map(|ni| {
+                let location: UserLocation = static_metadata
+                    .axes
+                    .iter()
+                    .map(|axis| {
+                        let coord = ni.location.get(axis.tag).unwrap_or(axis.default);
+                        if round_instances {
+                            (axis.tag, UserCoord::new(coord.to_f64().round()))
+                        } else {
+                            (axis.tag, coord)
+                        }
+                    })
+                    .collect();
+
                 // "The values 2 or 17 should only be used if the named instance corresponds
                 // to the font’s default instance."
                 // https://learn.microsoft.com/en-us/typography/opentype/spec/fvar#instancerecord
                 // https://github.com/fonttools/fonttools/blob/0bc8c028f/Lib/fontTools/varLib/__init__.py#L139-L150
                 let subfamily_name_id =
-                    reusable_name_id(ni.name.as_str(), ni.location == default_instance_location);
+                    reusable_name_id(ni.name.as_str(), location == default_instance_location);
 
                 // fonttools implicitly sets minNameID=256 when adding instance postscript names
                 // (even though the default named instance could in theory reuse nameID 6... but the
@@ -110,17 +128,11 @@ fn generate_fvar(static_metadata: &StaticMetadata) -> Option<Fvar> {
                 InstanceRecord {
                     subfamily_name_id,
                     post_script_name_id,
+                    // in fvar axis order, not the tag order of the location
                     coordinates: static_metadata
                         .axes
                         .iter()
-                        .map(|axis| {
-                            let loc = ni
-                                .location
-                                .get(axis.tag)
-                                .unwrap_or(axis.default)
-                                .into_inner();
-                            Fixed::from_f64(loc.into_inner())
-                        })
+                        .map(|axis| Fixed::from_f64(location.get(axis.tag).unwrap().to_f64()))
                         .collect(),
                     ..Default::default()
                 }
@@ -142,7 +154,8 @@ impl Work<Context, AnyWorkId, Error> for FvarWork {
 
     /// Generate [fvar](https://learn.microsoft.com/en-us/typography/opentype/spec/fvar)
     fn exec(&self, context: &Context) -> Result<(), Error> {
-        if let Some(fvar) = generate_fvar(&context.ir.static_metadata.get()) {
+        let round_instances = context.flags.contains(Flags::ROUND_INSTANCE_COORDINATES);
+        if let Some(fvar) = generate_fvar(&context.ir.static_metadata.get(), round_instances) {
             context.fvar.set(fvar);
         }
         Ok(())
@@ -151,8 +164,8 @@ impl Work<Context, AnyWorkId, Error> for FvarWork {
 
 #[cfg(test)]
 mod tests {
-    use fontdrasil::types::Axis;
-    use fontir::ir::StaticMetadata;
+    use fontdrasil::{coords::UserLocation, types::Axis};
+    use fontir::ir::{NamedInstance, StaticMetadata};
 
     use super::generate_fvar;
 
@@ -177,14 +190,14 @@ mod tests {
     #[test]
     fn no_fvar_for_no_axes() {
         let static_metadata = create_static_metadata(&[]);
-        let fvar = generate_fvar(&static_metadata);
+        let fvar = generate_fvar(&static_metadata, false);
         assert!(fvar.is_none());
     }
 
     #[test]
     fn no_fvar_for_point_axes() {
         let static_metadata = create_static_metadata(&[axis("wght", 400.0, 400.0, 400.0)]);
-        let fvar = generate_fvar(&static_metadata);
+        let fvar = generate_fvar(&static_metadata, false);
         assert!(fvar.is_none());
     }
 
@@ -194,7 +207,7 @@ mod tests {
             axis("wght", 400.0, 400.0, 700.0),
             axis("wdth", 400.0, 400.0, 400.0),
         ]);
-        let fvar = generate_fvar(&static_metadata).unwrap();
+        let fvar = generate_fvar(&static_metadata, false).unwrap();
         assert_eq!(
             vec![(400.0, 400.0, 700.0),],
             fvar.axis_instance_arrays
@@ -208,4 +221,36 @@ mod tests {
                 .collect::<Vec<_>>()
         );
     }
+
+    #[test]
+    fn round_instance_coordinates() {
+        let static_metadata = StaticMetadata::new(
+            1000,
+            Default::default(),
+            vec![axis("wght", 100.0, 400.0, 900.0)],
+            vec![NamedInstance {
+                name: "Regular".to_string(),
+                postscript_name: None,
+                location: UserLocation::for_pos(&[("wght", 399.996)]),
+            }],
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            None,
+            false,
+        )
+        .unwrap();
+        let coords = |round| {
+            generate_fvar(&static_metadata, round)
+                .unwrap()
+                .axis_instance_arrays
+                .instances
+                .iter()
+                .flat_map(|instance| instance.coordinates.iter().map(|c| c.to_f64()))
+                .collect::<Vec<_>>()
+        };
+        assert_ne!(vec![400.0], coords(false));
+        assert_eq!(vec![400.0], coords(true));
+    }
 }
@@ -53,6 +53,24 @@ pub fn create_glyf_work(glyph_name: GlyphName) -> Box<BeWork> {
     Box::new(GlyphWork { glyph_name })
 }
 
+/// Reuses the output of a previous build for a glyph whose inputs haven't changed.
+#[derive(Debug)]
+struct ReuseGlyphWork {
+    glyph: Arc<Glyph>,
+    gvar_fragment: Arc<GvarFragment>,
+}
+
+/// Create work that stores previously compiled glyf and gvar fragments for a glyph.
+///
+/// Intended for use when the IR glyph, and anything else the fragments were
+/// computed from, is known to be unchanged since they were built.
+pub fn create_reuse_glyf_work(glyph: Arc<Glyph>, gvar_fragment: Arc<GvarFragment>) -> Box<BeWork> {
+    Box::new(ReuseGlyphWork {
+        glyph,
+        gvar_fragment,
+    })
+}
+
 /// Can glyph instance reuse the metrics of other?
 ///
 /// To be safe the component should have:
@@ -255,10 +273,23 @@ fn point_seqs_for_composite_glyph(ir_glyph: &ir::Glyph) -> HashMap<NormalizedLoc
         .collect()
 }
 
+/// How the deltas of a glyph are stored in gvar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DeltaEncoding {
+    /// Zero deltas are omitted; IUP doesn't apply to composite glyphs
+    Composite,
+    /// Every delta is stored
+    Explicit,
+    /// Deltas IUP can infer are omitted
+    Iup,
+    /// As [`DeltaEncoding::Iup`], but a tuple stores every delta when that's smaller
+    IupSharedPoints,
+}
+
 fn compute_deltas(
     glyph_name: &GlyphName,
     var_model: &VariationModel,
-    should_iup: bool,
+    encoding: DeltaEncoding,
     point_seqs: &HashMap<NormalizedLocation, Vec<Point>>,
     coords: &Vec<Point>,
     contour_ends: &Vec<usize>,
@@ -272,10 +303,10 @@ fn compute_deltas(
         .deltas(point_seqs)
         .map_err(|e| Error::GlyphDeltaError(glyph_name.clone(), e))?
         .into_iter()
-        .map(|(region, deltas)| {
+        .map(|(region, deltas)| match encoding {
             // Spec: inferring of deltas for un-referenced points applies only
             // to simple glyphs, not to composite glyphs.
-            if should_iup {
+            DeltaEncoding::Iup | DeltaEncoding::IupSharedPoints => {
                 // Doing IUP optimization here conveniently means it threads
                 // per-glyph
                 if log_enabled!(log::Level::Trace) {
@@ -283,17 +314,99 @@ fn compute_deltas(
                     let deltas = deltas.iter().map(|d| d.to_point()).collect::<Vec<_>>();
                     trace!("IUP '{}', tolerance {tolerance}\n  {} contour ends {contour_ends:?}\n  {} deltas {deltas:?}\n  {} coords {coords:?}", glyph_name, contour_ends.len(), deltas.len(), coords.len());
                 }
-                iup_delta_optimize(deltas, coords.clone(), tolerance, contour_ends)
-                    .map(|iup_deltas| (region.clone(), iup_deltas))
-            } else {
-                let deltas = process_composite_deltas(deltas);
-                Ok((region, deltas))
+                iup_delta_optimize(deltas, coords.clone(), tolerance, contour_ends).map(
+                    |mut iup_deltas| {
+                        if encoding == DeltaEncoding::IupSharedPoints
+                            && all_points_is_smaller(&iup_deltas)
+                        {
+                            iup_deltas.iter_mut().for_each(|d| d.required = true);
+                        }
+                        (region.clone(), iup_deltas)
+                    },
+                )
             }
+            DeltaEncoding::Explicit => Ok((region, explicit_deltas(deltas))),
+            DeltaEncoding::Composite => Ok((region, process_composite_deltas(deltas))),
         })
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| Error::IupError(glyph_name.clone(), e))
 }
 
+/// Convert raw deltas to the write-fonts representation, all of them required
+fn explicit_deltas(deltas: Vec<Vec2>) -> Vec<GlyphDelta> {
+    deltas
+        .into_iter()
+        .map(|delta| {
+            let (x, y) = delta.to_point().ot_round();
+            GlyphDelta::required(x, y)
+        })
+        .collect()
+}
+
+/// Whether storing every delta of a tuple is smaller than storing only the required ones.
+///
+/// Only the required deltas need point numbers, but a tuple with every delta uses
+/// "all points", which takes a single byte or is shared by the other such tuples of
+/// the glyph. A rough take on
+/// <https://github.com/googlefonts/fontations/issues/634>; sizes are estimated per
+/// <https://learn.microsoft.com/en-us/typography/opentype/spec/otvarcommonformats#packed-point-numbers>.
+fn all_points_is_smaller(deltas: &[GlyphDelta]) -> bool {
+    let required: Vec<_> = deltas
+        .iter()
+        .enumerate()
+        .filter(|(_, d)| d.required)
+        .collect();
+    if required.len() == deltas.len() {
+        return false;
+    }
+    let points_size = {
+        let count_size = if required.len() < 128 { 1 } else { 2 };
+        let runs_size = required.len().div_ceil(128);
+        let mut prev = 0;
+        let numbers_size: usize = required
+            .iter()
+            .map(|(i, _)| {
+                let gap = *i - prev;
+                prev = *i;
+                if gap > u8::MAX as usize {
+                    2
+                } else {
+                    1
+                }
+            })
+            .sum();
+        count_size + runs_size + numbers_size
+    };
+    let some_size = points_size
+        + packed_deltas_size(required.iter().map(|(_, d)| d.x))
+        + packed_deltas_size(required.iter().map(|(_, d)| d.y));
+    let all_size = 1
+        + packed_deltas_size(deltas.iter().map(|d| d.x))
+        + packed_deltas_size(deltas.iter().map(|d| d.y));
+    all_size <= some_size
+}
+
+/// Estimate the size of packed deltas, which are stored in runs of up to 64
+/// zeros, bytes or words.
+///
+/// See <https://learn.microsoft.com/en-us/typography/opentype/spec/otvarcommonformats#packed-deltas>
+fn packed_deltas_size(deltas: impl Iterator<Item = i16>) -> usize {
+    // (bytes per value, length) of each run
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for delta in deltas {
+        let width = match delta {
+            0 => 0,
+            -128..=127 => 1,
+            _ => 2,
+        };
+        match runs.last_mut() {
+            Some((run_width, len)) if *run_width == width && *len < 64 => *len += 1,
+            _ => runs.push((width, 1)),
+        }
+    }
+    runs.iter().map(|(width, len)| 1 + width * len).sum()
+}
+
 /// convert raw deltas to the write-fonts representation (composite glyphs only)
 fn process_composite_deltas(deltas: Vec<Vec2>) -> Vec<GlyphDelta> {
     deltas
@@ -307,6 +420,32 @@ fn process_composite_deltas(deltas: Vec<Vec2>) -> Vec<GlyphDelta> {
         .collect()
 }
 
+impl Work<Context, AnyWorkId, Error> for ReuseGlyphWork {
+    fn id(&self) -> AnyWorkId {
+        WorkId::GlyfFragment(self.glyph.name.clone()).into()
+    }
+
+    fn write_access(&self) -> Access<AnyWorkId> {
+        AccessBuilder::new()
+            .specific_instance(WorkId::GlyfFragment(self.glyph.name.clone()))
+            .specific_instance(WorkId::GvarFragment(self.glyph.name.clone()))
+            .build()
+    }
+
+    fn also_completes(&self) -> Vec<AnyWorkId> {
+        vec![WorkId::GvarFragment(self.glyph.name.clone()).into()]
+    }
+
+    fn exec(&self, context: &Context) -> Result<(), Error> {
+        trace!("Reusing BE glyph work for '{}'", self.glyph.name);
+        context.glyphs.set_unconditionally((*self.glyph).clone());
+        context
+            .gvar_fragments
+            .set_unconditionally((*self.gvar_fragment).clone());
+        Ok(())
+    }
+}
+
 impl Work<Context, AnyWorkId, Error> for GlyphWork {
     fn id(&self) -> AnyWorkId {
         WorkId::GlyfFragment(self.glyph_name.clone()).into()
@@ -350,7 +489,16 @@ impl Work<Context, AnyWorkId, Error> for GlyphWork {
             glyph.reverse_contour_direction();
         }
 
-        let should_iup = glyph.should_iup(); // we partially borrow it later
+        // we partially borrow the glyph later
+        let encoding = if !glyph.should_iup() {
+            DeltaEncoding::Composite
+        } else if context.flags.contains(Flags::NO_IUP) {
+            DeltaEncoding::Explicit
+        } else if context.flags.contains(Flags::SHARE_GVAR_POINTS) {
+            DeltaEncoding::IupSharedPoints
+        } else {
+            DeltaEncoding::Iup
+        };
 
         let (name, point_seqs, contour_ends) = match glyph {
             CheckedGlyph::Composite { name, components } => {
@@ -421,19 +569,22 @@ impl Work<Context, AnyWorkId, Error> for GlyphWork {
             compute_deltas(
                 &self.glyph_name,
                 global_model,
-                should_iup,
+                encoding,
                 &point_seqs,
                 coords,
                 &contour_ends,
             )?
         } else {
-            let locations: HashSet<_> = ir_glyph.sources().keys().cloned().collect();
-            let sub_model = VariationModel::new(locations, static_metadata.axes.clone())
+            // glyphs with the same brace layers share a model, only the first one builds it
+            let sub_model = context
+                .ir
+                .variation_models
+                .get(ir_glyph.sources().keys().cloned(), &static_metadata.axes)
                 .map_err(|e| Error::VariationModelError(self.glyph_name.clone(), e))?;
             compute_deltas(
                 &self.glyph_name,
                 &sub_model,
-                should_iup,
+                encoding,
                 &point_seqs,
                 coords,
                 &contour_ends,
@@ -853,6 +1004,9 @@ impl Work<Context, AnyWorkId, Error> for GlyfLocaWork {
         let mut builder = GlyfLocaBuilder::new();
 
         for name in glyph_order.names() {
+            if context.ir.cancellation.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
             let glyph = context
                 .glyphs
                 .get(&WorkId::GlyfFragment(name.clone()).into());
@@ -1019,4 +1173,41 @@ mod tests {
         };
         assert_eq!((0, 1), (x, y));
     }
+
+    #[test]
+    fn component_flip_is_exact() {
+        let (c, _) = create_component_ref_gid(
+            GlyphId16::new(42),
+            &Affine::new([-1.0, 0.0, 0.0, 1.0, 500.0, 0.0]),
+        )
+        .unwrap();
+        assert_eq!(F2Dot14::from_f32(-1.0), c.transform.xx);
+        assert_eq!(F2Dot14::from_f32(1.0), c.transform.yy);
+        assert!(c.flags.round_xy_to_grid);
+    }
+
+    #[test]
+    fn packed_deltas_size_counts_runs() {
+        // a run of 3 zeros, a run of 2 bytes, a run of 1 word
+        assert_eq!(
+            1 + 3 + 3,
+            packed_deltas_size([0, 0, 0, 5, -5, 300].into_iter())
+        );
+        // runs hold at most 64 values
+        assert_eq!(2, packed_deltas_size([0; 65].into_iter()));
+    }
+
+    #[test]
+    fn all_points_when_nearly_all_are_required() {
+        let mut deltas = vec![GlyphDelta::required(1, 1); 20];
+        deltas[3] = GlyphDelta::optional(1, 1);
+        assert!(all_points_is_smaller(&deltas));
+    }
+
+    #[test]
+    fn some_points_when_few_are_required() {
+        let mut deltas = vec![GlyphDelta::optional(10, 10); 20];
+        deltas[3] = GlyphDelta::required(10, 10);
+        assert!(!all_points_is_smaller(&deltas));
+    }
 }
@@ -36,11 +36,15 @@ fn seconds_since_mac_epoch(datetime: DateTime<Utc>) -> i64 {
     datetime.signed_duration_since(mac_epoch).num_seconds()
 }
 
-/// The number of seconds since 00:00 1904-01-01 (GMT/UTC).
+/// The time to use for "now", e.g. for head.modified.
 ///
-/// If the [SOURCE_DATE_EPOCH](https://reproducible-builds.org/specs/source-date-epoch/)
-/// environment variable is set, use that instead of the current time.
-fn current_timestamp() -> i64 {
+/// An explicit timestamp wins, then the
+/// [SOURCE_DATE_EPOCH](https://reproducible-builds.org/specs/source-date-epoch/)
+/// environment variable, then the current time.
+pub(crate) fn build_time(timestamp: Option<DateTime<Utc>>) -> DateTime<Utc> {
+    if let Some(timestamp) = timestamp {
+        return timestamp;
+    }
     let mut src_date = None;
     if let Ok(src_date_var) = env::var("SOURCE_DATE_EPOCH") {
         if let Ok(timestamp) = src_date_var.parse::<i64>() {
@@ -50,7 +54,7 @@ fn current_timestamp() -> i64 {
             warn!("Invalid SOURCE_DATE_EPOCH value: {src_date_var:?}. Falling back to Utc::now().",);
         }
     }
-    seconds_since_mac_epoch(src_date.unwrap_or_else(Utc::now))
+    src_date.unwrap_or_else(Utc::now)
 }
 
 fn init_head(
@@ -98,8 +102,12 @@ fn apply_macstyle(head: &mut Head, selection_flags: SelectionFlags) {
     }
 }
 
-fn apply_created_modified(head: &mut Head, created: Option<DateTime<Utc>>) {
-    let now = current_timestamp();
+fn apply_created_modified(
+    head: &mut Head,
+    created: Option<DateTime<Utc>>,
+    timestamp: Option<DateTime<Utc>>,
+) {
+    let now = seconds_since_mac_epoch(build_time(timestamp));
     head.created = LongDateTime::new(created.map(seconds_since_mac_epoch).unwrap_or(now));
     head.modified = LongDateTime::new(now);
 }
@@ -132,7 +140,11 @@ impl Work<Context, AnyWorkId, Error> for HeadWork {
             static_metadata.misc.version_major,
             static_metadata.misc.version_minor,
         );
-        apply_created_modified(&mut head, static_metadata.misc.created);
+        apply_created_modified(
+            &mut head,
+            static_metadata.misc.created,
+            static_metadata.misc.timestamp,
+        );
         apply_macstyle(&mut head, static_metadata.misc.selection_flags);
         context.head.set(head);
 
@@ -168,7 +180,7 @@ mod tests {
         temp_env::with_var_unset("SOURCE_DATE_EPOCH", || {
             let now = seconds_since_mac_epoch(Utc::now());
             let mut head = init_head(1000, LocaFormat::Long, DEFAULT_HEAD_FLAGS, 42);
-            apply_created_modified(&mut head, None);
+            apply_created_modified(&mut head, None, None);
             assert_eq!(head.units_per_em, 1000);
             assert_eq!(head.index_to_loc_format, 1);
             assert_ge!(head.created.as_secs(), now);
@@ -186,12 +198,24 @@ mod tests {
             .timestamp();
         temp_env::with_var("SOURCE_DATE_EPOCH", Some(source_date.to_string()), || {
             let mut head = init_head(1000, LocaFormat::Short, DEFAULT_HEAD_FLAGS, 42);
-            apply_created_modified(&mut head, None);
+            apply_created_modified(&mut head, None, None);
             assert_eq!(head.created.as_secs(), 0);
             assert_eq!(head.modified.as_secs(), 0);
         });
     }
 
+    #[test]
+    fn explicit_timestamp_beats_source_date_epoch() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 2, 3, 4, 5, 6).unwrap();
+        let created = Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap();
+        temp_env::with_var("SOURCE_DATE_EPOCH", Some("0"), || {
+            let mut head = init_head(1000, LocaFormat::Short, DEFAULT_HEAD_FLAGS, 42);
+            apply_created_modified(&mut head, Some(created), Some(timestamp));
+            assert_eq!(head.created.as_secs(), seconds_since_mac_epoch(created));
+            assert_eq!(head.modified.as_secs(), seconds_since_mac_epoch(timestamp));
+        });
+    }
+
     #[test]
     fn init_head_with_invalid_source_date_epoch() {
         // if SOURCE_DATE_EPOCH is invalid, set the current time for created/modified
@@ -201,7 +225,7 @@ mod tests {
             Some("I am not a Unix timestamp!"),
             || {
                 let mut head = init_head(1000, LocaFormat::Short, DEFAULT_HEAD_FLAGS, 42);
-                apply_created_modified(&mut head, None);
+                apply_created_modified(&mut head, None, None);
                 assert_ge!(head.created.as_secs(), now);
                 assert_ge!(head.modified.as_secs(), now);
             },
@@ -1,7 +1,8 @@
 //! Generates an [HVAR](https://learn.microsoft.com/en-us/typography/opentype/spec/HVAR) table.
 
 use std::any::type_name;
-use std::collections::{BTreeSet, HashMap, HashSet};
+use std::collections::{hash_map::Entry, BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
 
 use fontdrasil::orchestration::AccessBuilder;
 
@@ -11,7 +12,11 @@ use fontdrasil::{
     orchestration::{Access, Work},
     types::GlyphName,
 };
-use fontir::{ir::Glyph, orchestration::WorkId as FeWorkId, variations::VariationModel};
+use fontir::{
+    ir::Glyph,
+    orchestration::WorkId as FeWorkId,
+    variations::{ModelCache, VariationModel},
+};
 use write_fonts::{
     dump_table,
     tables::{
@@ -50,8 +55,10 @@ where
 struct AdvanceWidthDeltas {
     /// Variation axes
     axes: Axes,
-    /// Sparse variation models, keyed by the set of locations they define
-    models: HashMap<BTreeSet<NormalizedLocation>, VariationModel>,
+    /// Models shared with the rest of the compiler
+    model_cache: Arc<ModelCache>,
+    /// The variation models used so far, keyed by the set of locations they define
+    models: HashMap<BTreeSet<NormalizedLocation>, Arc<VariationModel>>,
     /// Glyph's advance width deltas sorted by glyph order
     deltas: Vec<Vec<(VariationRegion, i16)>>,
     /// All the glyph locations that are defined in the font
@@ -61,6 +68,7 @@ struct AdvanceWidthDeltas {
 impl AdvanceWidthDeltas {
     fn new<'a>(
         global_model: VariationModel,
+        model_cache: Arc<ModelCache>,
         glyph_locations: impl IntoIterator<Item = &'a NormalizedLocation>,
     ) -> Self {
         let axes = global_model.axes().cloned().collect();
@@ -73,9 +81,10 @@ impl AdvanceWidthDeltas {
             .collect();
         let global_locations = global_model.locations().cloned().collect::<BTreeSet<_>>();
         let mut models = HashMap::new();
-        models.insert(global_locations, global_model);
+        models.insert(global_locations, model_cache.insert(global_model));
         AdvanceWidthDeltas {
             axes,
+            model_cache,
             models,
             deltas: Vec::new(),
             glyph_locations,
@@ -121,11 +130,17 @@ impl AdvanceWidthDeltas {
             }
         }
         let locations = advance_widths.keys().cloned().collect::<BTreeSet<_>>();
-        let model = self.models.entry(locations).or_insert_with(|| {
-            // this glyph defines its own set of locations, a new sparse model is needed
-            VariationModel::new(advance_widths.keys().cloned().collect(), self.axes.clone())
-                .unwrap()
-        });
+        let model = match self.models.entry(locations) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                // this glyph defines its own set of locations, a sparse model is needed
+                let model = self
+                    .model_cache
+                    .get(entry.key().iter().cloned(), &self.axes)
+                    .map_err(|e| Error::VariationModelError(name.clone(), e))?;
+                entry.insert(model)
+            }
+        };
         self.deltas.push(
             model
                 .deltas(&advance_widths)
@@ -185,7 +200,11 @@ impl Work<Context, AnyWorkId, Error> for HvarWork {
             .collect();
         let glyph_locations = glyphs.iter().flat_map(|glyph| glyph.sources().keys());
 
-        let mut glyph_width_deltas = AdvanceWidthDeltas::new(var_model.clone(), glyph_locations);
+        let mut glyph_width_deltas = AdvanceWidthDeltas::new(
+            var_model.clone(),
+            context.ir.variation_models.clone(),
+            glyph_locations,
+        );
         for glyph in glyphs.into_iter() {
             glyph_width_deltas.add(glyph.as_ref())?;
         }
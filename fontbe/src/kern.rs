@@ -0,0 +1,244 @@
+//! Generate a legacy [kern](https://learn.microsoft.com/en-us/typography/opentype/spec/kern) table
+//!
+//! Only written if [Flags::LEGACY_KERN] is set. The table can't vary, so the
+//! kerning at the default location is flattened into glyph pairs and written
+//! as format 0 subtables, alongside the GPOS kerning.
+
+use std::collections::BTreeMap;
+
+use fontdrasil::{
+    coords::NormalizedLocation,
+    orchestration::{Access, AccessBuilder, Work},
+};
+use fontir::{
+    ir::{self, GlyphOrder},
+    orchestration::{Flags, WorkId as FeWorkId},
+};
+use log::{debug, warn};
+use write_fonts::types::GlyphId16;
+
+use crate::{
+    error::Error,
+    orchestration::{AllKerningPairs, AnyWorkId, BeWork, Context, WorkId},
+};
+
+/// The size of the header of a format 0 subtable, including the search fields
+const SUBTABLE_HEADER_LEN: usize = 14;
+/// The size of one left, right, value record
+const PAIR_LEN: usize = 6;
+/// The most pairs that fit in a subtable, whose length is a u16
+const MAX_PAIRS_PER_SUBTABLE: usize = (u16::MAX as usize - SUBTABLE_HEADER_LEN) / PAIR_LEN;
+/// Horizontal kerning, with values that are kerning rather than minimums
+const COVERAGE_HORIZONTAL: u16 = 0x0001;
+
+#[derive(Debug)]
+struct KernWork;
+
+pub fn create_kern_work() -> Box<BeWork> {
+    Box::new(KernWork)
+}
+
+impl Work<Context, AnyWorkId, Error> for KernWork {
+    fn id(&self) -> AnyWorkId {
+        WorkId::Kern.into()
+    }
+
+    fn read_access(&self) -> Access<AnyWorkId> {
+        AccessBuilder::new()
+            .variant(FeWorkId::StaticMetadata)
+            .variant(FeWorkId::GlyphOrder)
+            .variant(WorkId::GatherIrKerning)
+            .build()
+    }
+
+    fn exec(&self, context: &Context) -> Result<(), Error> {
+        if !context.flags.contains(Flags::LEGACY_KERN) {
+            return Ok(());
+        }
+        let static_metadata = context.ir.static_metadata.get();
+        let glyph_order = context.ir.glyph_order.get();
+        let kerning = context.all_kerning_pairs.get();
+
+        let pairs = flatten_pairs(&kerning, &glyph_order, static_metadata.default_location());
+        if pairs.is_empty() {
+            debug!("No kerning at the default location, no kern table");
+            return Ok(());
+        }
+        let num_subtables = pairs.len().div_ceil(MAX_PAIRS_PER_SUBTABLE);
+        if num_subtables > 1 {
+            warn!(
+                "{} kern pairs need {num_subtables} kern subtables, some consumers only read the first",
+                pairs.len()
+            );
+        }
+        context.kern.set(to_table_bytes(&pairs).into());
+        Ok(())
+    }
+}
+
+/// Resolve kerning at `default_location` to pairs of glyphs.
+///
+/// As in the UFO kerning lookup algorithm, a pair of glyphs takes its value from
+/// the most specific rule that covers it: glyph, glyph first, then glyph, group,
+/// group, glyph and finally group, group. Zero values are left out, since that is
+/// what an absent pair means.
+fn flatten_pairs(
+    kerning: &AllKerningPairs,
+    glyph_order: &GlyphOrder,
+    default_location: &NormalizedLocation,
+) -> BTreeMap<(GlyphId16, GlyphId16), i16> {
+    let glyphs = |side: &ir::KernSide| -> Vec<GlyphId16> {
+        match side {
+            ir::KernSide::Glyph(name) => glyph_order.glyph_id(name).into_iter().collect(),
+            ir::KernSide::Group(group) => kerning
+                .groups
+                .get(group)
+                .map(|glyphs| glyphs.iter().collect())
+                .unwrap_or_default(),
+        }
+    };
+    let specificity = |(side1, side2): &ir::KernPair| match (side1, side2) {
+        (ir::KernSide::Group(_), ir::KernSide::Group(_)) => 0,
+        (ir::KernSide::Group(_), ir::KernSide::Glyph(_)) => 1,
+        (ir::KernSide::Glyph(_), ir::KernSide::Group(_)) => 2,
+        (ir::KernSide::Glyph(_), ir::KernSide::Glyph(_)) => 3,
+    };
+
+    let mut adjustments: Vec<_> = kerning.adjustments.iter().collect();
+    adjustments.sort_by_key(|(pair, _)| specificity(pair));
+
+    let mut pairs = BTreeMap::new();
+    for (pair, values) in adjustments {
+        let value = values
+            .get(default_location)
+            .map(|v| v.0.round() as i16)
+            .unwrap_or_default();
+        let rights = glyphs(&pair.1);
+        for left in glyphs(&pair.0) {
+            for right in rights.iter() {
+                pairs.insert((left, *right), value);
+            }
+        }
+    }
+    pairs.retain(|_, value| *value != 0);
+    pairs
+}
+
+/// A version 0 kern table, with as many format 0 subtables as the pairs need
+fn to_table_bytes(pairs: &BTreeMap<(GlyphId16, GlyphId16), i16>) -> Vec<u8> {
+    let pairs: Vec<_> = pairs.iter().collect();
+    let chunks: Vec<_> = pairs.chunks(MAX_PAIRS_PER_SUBTABLE).collect();
+
+    let mut buf = Vec::new();
+    buf.extend(0u16.to_be_bytes());
+    buf.extend((chunks.len() as u16).to_be_bytes());
+    for chunk in chunks {
+        let num_pairs = chunk.len() as u16;
+        let length = (SUBTABLE_HEADER_LEN + PAIR_LEN * chunk.len()) as u16;
+        // the largest power of two that fits in the pair count
+        let entry_selector = num_pairs.ilog2() as u16;
+        let search_range = (1u16 << entry_selector) * PAIR_LEN as u16;
+        let range_shift = num_pairs * PAIR_LEN as u16 - search_range;
+
+        buf.extend(0u16.to_be_bytes());
+        buf.extend(length.to_be_bytes());
+        buf.extend(COVERAGE_HORIZONTAL.to_be_bytes());
+        buf.extend(num_pairs.to_be_bytes());
+        buf.extend(search_range.to_be_bytes());
+        buf.extend(entry_selector.to_be_bytes());
+        buf.extend(range_shift.to_be_bytes());
+        for ((left, right), value) in chunk {
+            buf.extend(left.to_u16().to_be_bytes());
+            buf.extend(right.to_u16().to_be_bytes());
+            buf.extend(value.to_be_bytes());
+        }
+    }
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use fontdrasil::coords::NormalizedLocation;
+    use fontir::ir::{GlyphOrder, KernGroup, KernSide};
+    use ordered_float::OrderedFloat;
+    use write_fonts::{
+        read::{
+            tables::kern::{Kern, SubtableKind},
+            FontData, FontRead,
+        },
+        types::GlyphId16,
+    };
+
+    use crate::orchestration::AllKerningPairs;
+
+    use super::{flatten_pairs, to_table_bytes};
+
+    fn gid(gid: u16) -> GlyphId16 {
+        GlyphId16::new(gid)
+    }
+
+    #[test]
+    fn glyph_pairs_override_group_pairs() {
+        let glyph_order: GlyphOrder = [".notdef", "A", "Aacute", "V"]
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        let group_a = KernGroup::Side1("A".into());
+        let default = NormalizedLocation::new();
+        let value = |v: f64| BTreeMap::from([(default.clone(), OrderedFloat(v))]);
+        let kerning = AllKerningPairs {
+            groups: BTreeMap::from([(group_a.clone(), [gid(1), gid(2)].into_iter().collect())]),
+            adjustments: vec![
+                (
+                    (
+                        KernSide::Glyph("Aacute".into()),
+                        KernSide::Glyph("V".into()),
+                    ),
+                    value(0.0),
+                ),
+                (
+                    (KernSide::Group(group_a), KernSide::Glyph("V".into())),
+                    value(-80.4),
+                ),
+            ],
+        };
+
+        let pairs = flatten_pairs(&kerning, &glyph_order, &default);
+
+        assert_eq!(BTreeMap::from([((gid(1), gid(3)), -80)]), pairs);
+    }
+
+    #[test]
+    fn table_reads_back() {
+        let pairs = BTreeMap::from([
+            ((gid(1), gid(3)), -80),
+            ((gid(2), gid(3)), -60),
+            ((gid(3), gid(1)), 20),
+        ]);
+
+        let bytes = to_table_bytes(&pairs);
+        let kern = Kern::read(FontData::new(&bytes)).unwrap();
+        let Kern::Ot(kern) = kern else {
+            panic!("Should be an OpenType kern table");
+        };
+
+        assert_eq!(1, kern.n_tables());
+        let subtable = kern.subtables().next().unwrap().unwrap();
+        let SubtableKind::Format0(format0) = subtable.kind().unwrap() else {
+            panic!("Should be format 0");
+        };
+        assert_eq!(
+            vec![(1, 3, -80), (2, 3, -60), (3, 1, 20)],
+            format0
+                .pairs()
+                .iter()
+                .map(|p| (p.left().to_u16(), p.right().to_u16(), p.value()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(12, format0.search_range());
+        assert_eq!(1, format0.entry_selector());
+        assert_eq!(6, format0.range_shift());
+    }
+}
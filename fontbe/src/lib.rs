@@ -1,5 +1,7 @@
 //! Backend of the `fontc` font compiler.
 pub mod avar;
+pub mod base;
+pub mod cbdt;
 pub mod cmap;
 pub mod colr;
 pub mod cpal;
@@ -12,6 +14,8 @@ pub mod glyphs;
 pub mod gvar;
 pub mod head;
 pub mod hvar;
+pub mod kern;
+pub mod math;
 pub mod meta;
 pub mod metrics_and_limits;
 pub mod mvar;
@@ -20,7 +24,9 @@ pub mod orchestration;
 pub mod os2;
 pub mod paths;
 pub mod post;
+pub mod sbix;
 pub mod stat;
+pub mod svg;
 #[cfg(test)]
 mod test_util;
 pub mod vertical_metrics;
@@ -0,0 +1,367 @@
+//! Generate a [MATH](https://learn.microsoft.com/en-us/typography/opentype/spec/math) table
+//!
+//! Only written for sources that declare math constants. Italic corrections and
+//! top accent positions come from the `math.ic` and `math.ta` anchors at the
+//! default location, as the GlyphsMath plugin places them.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use fontdrasil::orchestration::{Access, AccessBuilder, Work};
+use fontir::{
+    ir::{AnchorKind, GlyphOrder, MathValues},
+    orchestration::WorkId as FeWorkId,
+};
+use log::warn;
+use smol_str::SmolStr;
+use write_fonts::types::GlyphId16;
+
+use crate::{
+    error::Error,
+    orchestration::{AnyWorkId, BeWork, Context, WorkId},
+};
+
+/// The int16 constants that open MathConstants, in table order
+const LEADING_CONSTANTS: [&str; 4] = [
+    "ScriptPercentScaleDown",
+    "ScriptScriptPercentScaleDown",
+    "DelimitedSubFormulaMinHeight",
+    "DisplayOperatorMinHeight",
+];
+
+/// The MathValueRecord constants of MathConstants, in table order
+const VALUE_RECORD_CONSTANTS: [&str; 51] = [
+    "MathLeading",
+    "AxisHeight",
+    "AccentBaseHeight",
+    "FlattenedAccentBaseHeight",
+    "SubscriptShiftDown",
+    "SubscriptTopMax",
+    "SubscriptBaselineDropMin",
+    "SuperscriptShiftUp",
+    "SuperscriptShiftUpCramped",
+    "SuperscriptBottomMin",
+    "SuperscriptBaselineDropMax",
+    "SubSuperscriptGapMin",
+    "SuperscriptBottomMaxWithSubscript",
+    "SpaceAfterScript",
+    "UpperLimitGapMin",
+    "UpperLimitBaselineRiseMin",
+    "LowerLimitGapMin",
+    "LowerLimitBaselineDropMin",
+    "StackTopShiftUp",
+    "StackTopDisplayStyleShiftUp",
+    "StackBottomShiftDown",
+    "StackBottomDisplayStyleShiftDown",
+    "StackGapMin",
+    "StackDisplayStyleGapMin",
+    "StretchStackTopShiftUp",
+    "StretchStackBottomShiftDown",
+    "StretchStackGapAboveMin",
+    "StretchStackGapBelowMin",
+    "FractionNumeratorShiftUp",
+    "FractionNumeratorDisplayStyleShiftUp",
+    "FractionDenominatorShiftDown",
+    "FractionDenominatorDisplayStyleShiftDown",
+    "FractionNumeratorGapMin",
+    "FractionNumDisplayStyleGapMin",
+    "FractionRuleThickness",
+    "FractionDenominatorGapMin",
+    "FractionDenomDisplayStyleGapMin",
+    "SkewedFractionHorizontalGap",
+    "SkewedFractionVerticalGap",
+    "OverbarVerticalGap",
+    "OverbarRuleThickness",
+    "OverbarExtraAscender",
+    "UnderbarVerticalGap",
+    "UnderbarRuleThickness",
+    "UnderbarExtraDescender",
+    "RadicalVerticalGap",
+    "RadicalDisplayStyleVerticalGap",
+    "RadicalRuleThickness",
+    "RadicalExtraAscender",
+    "RadicalKernBeforeDegree",
+    "RadicalKernAfterDegree",
+];
+
+/// The int16 constant that closes MathConstants
+const TRAILING_CONSTANT: &str = "RadicalDegreeBottomRaisePercent";
+
+/// The one constant that lives in MathVariants rather than MathConstants
+const MIN_CONNECTOR_OVERLAP: &str = "MinConnectorOverlap";
+
+/// The size of the MATH header: version and three offsets
+const HEADER_LEN: u16 = 10;
+
+#[derive(Debug)]
+struct MathWork;
+
+pub fn create_math_work() -> Box<BeWork> {
+    Box::new(MathWork)
+}
+
+impl Work<Context, AnyWorkId, Error> for MathWork {
+    fn id(&self) -> AnyWorkId {
+        WorkId::Math.into()
+    }
+
+    fn read_access(&self) -> Access<AnyWorkId> {
+        AccessBuilder::new()
+            .variant(FeWorkId::StaticMetadata)
+            .variant(FeWorkId::GlyphOrder)
+            .variant(FeWorkId::ALL_ANCHORS)
+            .build()
+    }
+
+    fn exec(&self, context: &Context) -> Result<(), Error> {
+        let static_metadata = context.ir.static_metadata.get();
+        let Some(math) = static_metadata.misc.math.as_ref() else {
+            return Ok(());
+        };
+        let glyph_order = context.ir.glyph_order.get();
+
+        let mut italic_corrections = BTreeMap::new();
+        let mut top_accents = BTreeMap::new();
+        for (_, glyph_anchors) in context.ir.anchors.all() {
+            let Some(gid) = glyph_order.glyph_id(&glyph_anchors.glyph_name) else {
+                continue;
+            };
+            for anchor in glyph_anchors.anchors.iter() {
+                let values = match anchor.kind {
+                    AnchorKind::MathItalicCorrection => &mut italic_corrections,
+                    AnchorKind::MathTopAccent => &mut top_accents,
+                    _ => continue,
+                };
+                values.insert(gid, anchor.default_pos().x.round() as i16);
+            }
+        }
+
+        let bytes = to_table_bytes(math, &glyph_order, &italic_corrections, &top_accents);
+        context.math.set(bytes.into());
+        Ok(())
+    }
+}
+
+/// A version 1.0 MATH table
+fn to_table_bytes(
+    math: &MathValues,
+    glyph_order: &GlyphOrder,
+    italic_corrections: &BTreeMap<GlyphId16, i16>,
+    top_accents: &BTreeMap<GlyphId16, i16>,
+) -> Vec<u8> {
+    for name in math.constants.keys() {
+        if !LEADING_CONSTANTS.contains(&name.as_str())
+            && !VALUE_RECORD_CONSTANTS.contains(&name.as_str())
+            && name != TRAILING_CONSTANT
+            && name != MIN_CONNECTOR_OVERLAP
+        {
+            warn!("Ignoring unknown MATH constant '{name}'");
+        }
+    }
+    // extended shapes are by name, coverage wants them by glyph id
+    let extended_shapes: Vec<_> = math
+        .extended_shapes
+        .iter()
+        .filter_map(|name| glyph_order.glyph_id(name))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    let constants = math_constants(&math.constants);
+    let glyph_info = math_glyph_info(italic_corrections, top_accents, &extended_shapes);
+    let variants = math_variants(&math.constants);
+
+    let mut buf = Vec::new();
+    buf.extend(1u16.to_be_bytes());
+    buf.extend(0u16.to_be_bytes());
+    buf.extend(HEADER_LEN.to_be_bytes());
+    buf.extend((HEADER_LEN + constants.len() as u16).to_be_bytes());
+    buf.extend((HEADER_LEN + (constants.len() + glyph_info.len()) as u16).to_be_bytes());
+    buf.extend(constants);
+    buf.extend(glyph_info);
+    buf.extend(variants);
+    buf
+}
+
+fn math_constants(constants: &BTreeMap<SmolStr, i16>) -> Vec<u8> {
+    let value = |name: &str| constants.get(name).copied().unwrap_or_default();
+    let mut buf = Vec::new();
+    for name in LEADING_CONSTANTS {
+        buf.extend(value(name).to_be_bytes());
+    }
+    for name in VALUE_RECORD_CONSTANTS {
+        push_value_record(&mut buf, value(name));
+    }
+    buf.extend(value(TRAILING_CONSTANT).to_be_bytes());
+    buf
+}
+
+/// MathGlyphInfo, with no MathKernInfo
+fn math_glyph_info(
+    italic_corrections: &BTreeMap<GlyphId16, i16>,
+    top_accents: &BTreeMap<GlyphId16, i16>,
+    extended_shapes: &[GlyphId16],
+) -> Vec<u8> {
+    let subtables = [
+        Some(values_by_glyph(italic_corrections)).filter(|_| !italic_corrections.is_empty()),
+        Some(values_by_glyph(top_accents)).filter(|_| !top_accents.is_empty()),
+        Some(coverage(extended_shapes)).filter(|_| !extended_shapes.is_empty()),
+    ];
+
+    let mut header = Vec::new();
+    let mut body = Vec::new();
+    let header_len = 8;
+    for subtable in subtables {
+        let offset = match subtable {
+            Some(subtable) => {
+                let offset = header_len + body.len() as u16;
+                body.extend(subtable);
+                offset
+            }
+            None => 0,
+        };
+        header.extend(offset.to_be_bytes());
+    }
+    // MathKernInfo
+    header.extend(0u16.to_be_bytes());
+    header.extend(body);
+    header
+}
+
+/// MathVariants with no glyph constructions, which only carries MinConnectorOverlap
+fn math_variants(constants: &BTreeMap<SmolStr, i16>) -> Vec<u8> {
+    let min_connector_overlap = constants
+        .get(MIN_CONNECTOR_OVERLAP)
+        .copied()
+        .unwrap_or_default();
+    let mut buf = Vec::new();
+    buf.extend(min_connector_overlap.to_be_bytes());
+    // vertical and horizontal coverage offsets and counts
+    buf.extend([0u8; 8]);
+    buf
+}
+
+/// The shape shared by MathItalicsCorrectionInfo and MathTopAccentAttachment:
+/// a coverage and a MathValueRecord for each glyph it covers.
+fn values_by_glyph(values: &BTreeMap<GlyphId16, i16>) -> Vec<u8> {
+    let count = values.len() as u16;
+    let mut buf = Vec::new();
+    buf.extend((4 + 4 * count).to_be_bytes());
+    buf.extend(count.to_be_bytes());
+    for value in values.values() {
+        push_value_record(&mut buf, *value);
+    }
+    buf.extend(coverage(&values.keys().copied().collect::<Vec<_>>()));
+    buf
+}
+
+/// A MathValueRecord without a device table
+fn push_value_record(buf: &mut Vec<u8>, value: i16) {
+    buf.extend(value.to_be_bytes());
+    buf.extend(0u16.to_be_bytes());
+}
+
+/// A format 1 coverage table, `glyphs` must be sorted
+fn coverage(glyphs: &[GlyphId16]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend(1u16.to_be_bytes());
+    buf.extend((glyphs.len() as u16).to_be_bytes());
+    for gid in glyphs {
+        buf.extend(gid.to_u16().to_be_bytes());
+    }
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use fontir::ir::{GlyphOrder, MathValues};
+    use write_fonts::types::GlyphId16;
+
+    use super::to_table_bytes;
+
+    fn read_u16(bytes: &[u8], pos: usize) -> u16 {
+        u16::from_be_bytes([bytes[pos], bytes[pos + 1]])
+    }
+
+    #[test]
+    fn constants_and_glyph_info_land_where_the_spec_says() {
+        let glyph_order: GlyphOrder = [".notdef", "f", "integral"]
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        let math = MathValues {
+            constants: BTreeMap::from([
+                ("ScriptPercentScaleDown".into(), 70),
+                ("AxisHeight".into(), 250),
+                ("RadicalDegreeBottomRaisePercent".into(), 60),
+                ("MinConnectorOverlap".into(), 20),
+            ]),
+            extended_shapes: ["integral".into()].into(),
+        };
+        let italic_corrections = BTreeMap::from([(GlyphId16::new(1), 45)]);
+
+        let bytes = to_table_bytes(&math, &glyph_order, &italic_corrections, &BTreeMap::new());
+
+        assert_eq!((1, 0), (read_u16(&bytes, 0), read_u16(&bytes, 2)));
+        let constants = read_u16(&bytes, 4) as usize;
+        let glyph_info = read_u16(&bytes, 6) as usize;
+        let variants = read_u16(&bytes, 8) as usize;
+        assert_eq!((10, 224, 252), (constants, glyph_info, variants));
+
+        assert_eq!(70, read_u16(&bytes, constants));
+        // AxisHeight is the second MathValueRecord
+        assert_eq!(250, read_u16(&bytes, constants + 8 + 4));
+        assert_eq!(60, read_u16(&bytes, glyph_info - 2));
+
+        // italics correction info, no top accents, extended shapes, no kerns
+        let italics = glyph_info + read_u16(&bytes, glyph_info) as usize;
+        assert_eq!(0, read_u16(&bytes, glyph_info + 2));
+        let extended = glyph_info + read_u16(&bytes, glyph_info + 4) as usize;
+        assert_eq!(0, read_u16(&bytes, glyph_info + 6));
+        assert_eq!(
+            (1, 45),
+            (read_u16(&bytes, italics + 2), read_u16(&bytes, italics + 4))
+        );
+        let italics_coverage = italics + read_u16(&bytes, italics) as usize;
+        assert_eq!(
+            vec![1, 1, 1],
+            (0..3)
+                .map(|i| read_u16(&bytes, italics_coverage + 2 * i))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![1, 1, 2],
+            (0..3)
+                .map(|i| read_u16(&bytes, extended + 2 * i))
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(20, read_u16(&bytes, variants));
+        assert_eq!(variants + 10, bytes.len());
+    }
+
+    #[test]
+    fn extended_shapes_are_covered_in_glyph_id_order() {
+        // alphabetically integral precedes summation, by glyph id it follows
+        let glyph_order: GlyphOrder = [".notdef", "summation", "integral"]
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        let math = MathValues {
+            constants: BTreeMap::new(),
+            extended_shapes: ["integral".into(), "summation".into()].into(),
+        };
+
+        let bytes = to_table_bytes(&math, &glyph_order, &BTreeMap::new(), &BTreeMap::new());
+
+        let glyph_info = read_u16(&bytes, 6) as usize;
+        let extended = glyph_info + read_u16(&bytes, glyph_info + 4) as usize;
+        assert_eq!(
+            vec![1, 2, 1, 2],
+            (0..4)
+                .map(|i| read_u16(&bytes, extended + 2 * i))
+                .collect::<Vec<_>>()
+        );
+    }
+}
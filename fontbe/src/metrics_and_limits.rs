@@ -12,6 +12,7 @@ use fontdrasil::orchestration::{Access, AccessBuilder, Work};
 use fontir::orchestration::WorkId as FeWorkId;
 use write_fonts::{
     dump_table,
+    read::{tables::hmtx::Hmtx as ReadHmtx, FontData, TopLevelTable},
     tables::{
         glyf::{Bbox, Contour, Glyph as RawGlyph},
         head,
@@ -29,6 +30,31 @@ use crate::{
     orchestration::{AnyWorkId, BeWork, Context, Glyph, WorkId},
 };
 
+/// True if every glyph that has an advance has the same one, i.e. the font is monospaced
+fn is_monospace(context: &Context) -> Result<bool, Error> {
+    let hhea = context.hhea.get();
+    let raw_hmtx = context.hmtx.get();
+    let hmtx = ReadHmtx::read(FontData::new(raw_hmtx.get()), hhea.number_of_h_metrics)
+        .map_err(|_| Error::InvalidTableBytes(ReadHmtx::TAG))?;
+    let mut advances = hmtx
+        .h_metrics()
+        .iter()
+        .map(|metric| metric.advance())
+        .filter(|advance| *advance != 0);
+    let first = advances.next();
+    Ok(first.is_some() && advances.all(|advance| Some(advance) == first))
+}
+
+/// Whether post.isFixedPitch is set; the source has the final say, otherwise look at the advances
+///
+/// OS/2 follows this too, see the PANOSE proportion in [crate::os2].
+pub(crate) fn is_fixed_pitch(context: &Context) -> Result<bool, Error> {
+    match context.ir.static_metadata.get().misc.is_fixed_pitch {
+        Some(is_fixed_pitch) => Ok(is_fixed_pitch),
+        None => is_monospace(context),
+    }
+}
+
 #[derive(Debug)]
 struct MetricAndLimitWork {}
 
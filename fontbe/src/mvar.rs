@@ -1,6 +1,7 @@
 //! Generates an [MVAR](https://learn.microsoft.com/en-us/typography/opentype/spec/MVAR) table.
 
-use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
 
 use fontdrasil::orchestration::AccessBuilder;
 
@@ -10,7 +11,9 @@ use fontdrasil::{
     types::Axes,
 };
 use fontir::{
-    ir::GlobalMetricValues, orchestration::WorkId as FeWorkId, variations::VariationModel,
+    ir::GlobalMetricValues,
+    orchestration::WorkId as FeWorkId,
+    variations::{ModelCache, VariationModel},
 };
 use write_fonts::types::MajorMinor;
 use write_fonts::{
@@ -38,20 +41,23 @@ pub fn create_mvar_work() -> Box<BeWork> {
 struct MvarBuilder {
     /// Variation axes
     axes: Axes,
-    /// Sparse variation models, keyed by the set of locations they define
-    models: HashMap<BTreeSet<NormalizedLocation>, VariationModel>,
+    /// Models shared with the rest of the compiler
+    model_cache: Arc<ModelCache>,
+    /// The variation models used so far, keyed by the set of locations they define
+    models: HashMap<BTreeSet<NormalizedLocation>, Arc<VariationModel>>,
     /// Metrics deltas keyed by MVAR tag
     deltas: BTreeMap<Tag, Vec<(VariationRegion, i16)>>,
 }
 
 impl MvarBuilder {
-    fn new(global_model: VariationModel) -> Self {
+    fn new(global_model: VariationModel, model_cache: Arc<ModelCache>) -> Self {
         let axes = global_model.axes().cloned().collect();
         let global_locations = global_model.locations().cloned().collect::<BTreeSet<_>>();
         let mut models = HashMap::new();
-        models.insert(global_locations, global_model);
+        models.insert(global_locations, model_cache.insert(global_model));
         MvarBuilder {
             axes,
+            model_cache,
             models,
             deltas: BTreeMap::new(),
         }
@@ -70,10 +76,17 @@ impl MvarBuilder {
             return Ok(());
         }
         let locations = sources.keys().cloned().collect::<BTreeSet<_>>();
-        let model = self.models.entry(locations).or_insert_with(|| {
-            // this glyph defines its own set of locations, a new sparse model is needed
-            VariationModel::new(sources.keys().cloned().collect(), self.axes.clone()).unwrap()
-        });
+        let model = match self.models.entry(locations) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                // this metric defines its own set of locations, a sparse model is needed
+                let model = self
+                    .model_cache
+                    .get(entry.key().iter().cloned(), &self.axes)
+                    .unwrap();
+                entry.insert(model)
+            }
+        };
         let deltas: Vec<_> = model
             .deltas(&sources)
             .map_err(|e| Error::MvarDeltaError(mvar_tag, e))?
@@ -144,7 +157,8 @@ impl Work<Context, AnyWorkId, Error> for MvarWork {
         let metrics = context.ir.global_metrics.get();
         let var_model = &static_metadata.variation_model;
 
-        let mut mvar_builder = MvarBuilder::new(var_model.clone());
+        let mut mvar_builder =
+            MvarBuilder::new(var_model.clone(), context.ir.variation_models.clone());
         for (metric, values) in metrics.iter() {
             // some of the GlobalMetric variants are not MVAR-relevant, e.g.
             // hhea ascender/descender/lineGap so we just skip those
@@ -180,7 +194,7 @@ mod tests {
     fn new_mvar_builder(locations: Vec<&NormalizedLocation>, axes: Vec<Axis>) -> MvarBuilder {
         let locations = locations.into_iter().cloned().collect();
         let model = VariationModel::new(locations, axes.into()).unwrap();
-        MvarBuilder::new(model)
+        MvarBuilder::new(model, Default::default())
     }
 
     fn add_sources(
@@ -2,8 +2,13 @@
 
 use std::collections::BTreeMap;
 
+use chrono::{DateTime, Utc};
 use fontdrasil::orchestration::{Access, AccessBuilder, Work};
-use fontir::orchestration::WorkId as FeWorkId;
+use fontir::{
+    ir::StaticMetadata,
+    orchestration::{Flags, WorkId as FeWorkId},
+};
+use log::warn;
 use write_fonts::{
     tables::name::{Name, NameRecord},
     types::NameId,
@@ -12,6 +17,7 @@ use write_fonts::{
 
 use crate::{
     error::Error,
+    head::build_time,
     orchestration::{AnyWorkId, BeWork, Context, WorkId},
 };
 
@@ -39,6 +45,15 @@ impl Work<Context, AnyWorkId, Error> for NameWork {
         let static_metadata = context.ir.static_metadata.get();
         let extra_tables = context.extra_fea_tables.try_get();
 
+        let template_values = TemplateValues::new(
+            &static_metadata,
+            static_metadata
+                .misc
+                .created
+                .unwrap_or_else(|| build_time(static_metadata.misc.timestamp)),
+            context.flags.contains(Flags::OMIT_NAME_DATES),
+        );
+
         let mut name_records = static_metadata
             .names
             .iter()
@@ -47,7 +62,11 @@ impl Work<Context, AnyWorkId, Error> for NameWork {
                 platform_id: key.platform_id,
                 encoding_id: key.encoding_id,
                 language_id: key.lang_id,
-                string: OffsetMarker::new(value.clone()),
+                string: OffsetMarker::new(if TEMPLATED_NAMES.contains(&key.name_id) {
+                    template_values.expand(value)
+                } else {
+                    value.clone()
+                }),
             })
             .collect::<Vec<_>>();
 
@@ -66,6 +85,86 @@ impl Work<Context, AnyWorkId, Error> for NameWork {
     }
 }
 
+/// The names that may contain placeholders, such as `{version}`
+const TEMPLATED_NAMES: [NameId; 2] = [NameId::UNIQUE_ID, NameId::VERSION_STRING];
+
+/// What the placeholders in a templated name expand to.
+///
+/// Supported placeholders are `{vendor}`, `{version}`, `{date}`, `{family}`,
+/// `{subfamily}` and `{postscriptName}`. Anything else is left as is.
+struct TemplateValues {
+    values: Vec<(&'static str, String)>,
+    omit_date: bool,
+}
+
+impl TemplateValues {
+    fn new(static_metadata: &StaticMetadata, date: DateTime<Utc>, omit_date: bool) -> Self {
+        let name = |name_ids: &[NameId]| {
+            name_ids
+                .iter()
+                .find_map(|name_id| {
+                    static_metadata
+                        .names
+                        .iter()
+                        .find(|(key, _)| key.name_id == *name_id)
+                        .map(|(_, value)| value.clone())
+                })
+                .unwrap_or_default()
+        };
+        let misc = &static_metadata.misc;
+        let values = vec![
+            ("vendor", misc.vendor_id.to_string().trim_end().to_string()),
+            (
+                "version",
+                format!("{}.{:0>3}", misc.version_major, misc.version_minor),
+            ),
+            ("date", date.format("%Y-%m-%d").to_string()),
+            (
+                "family",
+                name(&[NameId::TYPOGRAPHIC_FAMILY_NAME, NameId::FAMILY_NAME]),
+            ),
+            (
+                "subfamily",
+                name(&[NameId::TYPOGRAPHIC_SUBFAMILY_NAME, NameId::SUBFAMILY_NAME]),
+            ),
+            ("postscriptName", name(&[NameId::POSTSCRIPT_NAME])),
+        ];
+        TemplateValues { values, omit_date }
+    }
+
+    fn expand(&self, template: &str) -> String {
+        let mut result = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            let whole = &rest[start..=start + len];
+            let placeholder = &whole[1..len];
+            result.push_str(&rest[..start]);
+            rest = &rest[start + len + 1..];
+            if placeholder == "date" && self.omit_date {
+                // drop the separator too, so "1.000;2024-01-01" becomes "1.000"
+                if result.ends_with([';', ' ', '-']) {
+                    result.pop();
+                } else if rest.starts_with([';', ' ', '-']) {
+                    rest = &rest[1..];
+                }
+                continue;
+            }
+            match self.values.iter().find(|(key, _)| *key == placeholder) {
+                Some((_, value)) => result.push_str(value),
+                None => {
+                    warn!("Unknown placeholder '{{{placeholder}}}' in name '{template}'");
+                    result.push_str(whole);
+                }
+            }
+        }
+        result.push_str(rest);
+        result
+    }
+}
+
 // records from fea overwrite records derived elsewhere:
 // https://github.com/fonttools/fonttools/blob/b90ac3c29f6030ec/Lib/fontTools/feaLib/builder.py#L452
 fn merge_name_records(records: Vec<NameRecord>, fea_names: &Name) -> Vec<NameRecord> {
@@ -96,3 +195,42 @@ fn merge_name_records(records: Vec<NameRecord>, fea_names: &Name) -> Vec<NameRec
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template_values(omit_date: bool) -> TemplateValues {
+        TemplateValues {
+            values: vec![
+                ("vendor", "NONE".to_string()),
+                ("version", "1.002".to_string()),
+                ("date", "2024-05-06".to_string()),
+                ("postscriptName", "Family-Bold".to_string()),
+            ],
+            omit_date,
+        }
+    }
+
+    #[test]
+    fn expand_placeholders() {
+        assert_eq!(
+            "1.002;NONE;Family-Bold;2024-05-06",
+            template_values(false).expand("{version};{vendor};{postscriptName};{date}")
+        );
+    }
+
+    #[test]
+    fn leave_plain_and_unknown_text_alone() {
+        let values = template_values(false);
+        assert_eq!("Version 1.002", values.expand("Version 1.002"));
+        assert_eq!("{nope} 1.002 {", values.expand("{nope} {version} {"));
+    }
+
+    #[test]
+    fn omit_date() {
+        let values = template_values(true);
+        assert_eq!("1.002;NONE", values.expand("{version};{vendor};{date}"));
+        assert_eq!("1.002", values.expand("{date} {version}"));
+    }
+}
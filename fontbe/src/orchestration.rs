@@ -37,7 +37,6 @@ use write_fonts::{
         base::Base,
         cmap::Cmap,
         colr::Colr,
-        cpal::Cpal,
         fvar::Fvar,
         gasp::Gasp,
         gdef::{Gdef, GlyphClassDef},
@@ -83,6 +82,9 @@ pub enum WorkId {
     Features,
     FeaturesAst,
     Avar,
+    Base,
+    Cbdt,
+    Cblc,
     Cmap,
     Colr,
     Cpal,
@@ -100,6 +102,8 @@ pub enum WorkId {
     Hhea,
     Hmtx,
     Hvar,
+    Kern,
+    Math,
     Meta,
     Vhea,
     Vmtx,
@@ -114,7 +118,9 @@ pub enum WorkId {
     Name,
     Os2,
     Post,
+    Sbix,
     Stat,
+    Svg,
     ExtraFeaTables,
 }
 
@@ -133,6 +139,7 @@ impl Identifier for WorkId {
             WorkId::Meta => "BeMeta",
             WorkId::FeaturesAst => "BeFeaturesAst",
             WorkId::Avar => "BeAvar",
+            WorkId::Base => "BeBase",
             WorkId::Cmap => "BeCmap",
             WorkId::Colr => "BeColr",
             WorkId::Cpal => "BeCpal",
@@ -150,6 +157,12 @@ impl Identifier for WorkId {
             WorkId::Hhea => "BeHhea",
             WorkId::Hmtx => "BeHmtx",
             WorkId::Hvar => "BeHvar",
+            WorkId::Kern => "BeKern",
+            WorkId::Cbdt => "BeCbdt",
+            WorkId::Cblc => "BeCblc",
+            WorkId::Sbix => "BeSbix",
+            WorkId::Svg => "BeSvg",
+            WorkId::Math => "BeMath",
             WorkId::GatherIrKerning => "BeGatherIr",
             WorkId::KernFragment(..) => "BeKernFragment",
             WorkId::GatherBeKerning => "BeGatherKernFragments",
@@ -375,7 +388,7 @@ impl Persistable for Glyph {
 /// Unusually we store something other than the binary gvar per glyph.
 ///
 /// <https://learn.microsoft.com/en-us/typography/opentype/spec/gvar>
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GvarFragment {
     pub glyph_name: GlyphName,
     /// None entries are safe to omit per IUP
@@ -873,7 +886,7 @@ pub struct Context {
     pub avar: BeContextItem<PossiblyEmptyAvar>,
     pub cmap: BeContextItem<Cmap>,
     pub colr: BeContextItem<Colr>,
-    pub cpal: BeContextItem<Cpal>,
+    pub cpal: BeContextItem<Bytes>,
     pub fvar: BeContextItem<Fvar>,
     pub gasp: BeContextItem<Gasp>,
     pub glyf: BeContextItem<Bytes>,
@@ -881,8 +894,15 @@ pub struct Context {
     pub gpos: BeContextItem<Gpos>,
     pub gdef: BeContextItem<Gdef>,
     pub gvar: BeContextItem<Bytes>,
+    pub kern: BeContextItem<Bytes>,
+    pub cbdt: BeContextItem<Bytes>,
+    pub cblc: BeContextItem<Bytes>,
+    pub sbix: BeContextItem<Bytes>,
+    pub svg: BeContextItem<Bytes>,
+    pub math: BeContextItem<Bytes>,
     pub post: BeContextItem<Post>,
     pub meta: BeContextItem<Meta>,
+    pub base: BeContextItem<Base>,
     pub loca: BeContextItem<Bytes>,
     pub loca_format: BeContextItem<LocaFormatWrapper>,
     pub maxp: BeContextItem<Maxp>,
@@ -925,6 +945,12 @@ impl Context {
             gpos: self.gpos.clone_with_acl(acl.clone()),
             gdef: self.gdef.clone_with_acl(acl.clone()),
             gvar: self.gvar.clone_with_acl(acl.clone()),
+            kern: self.kern.clone_with_acl(acl.clone()),
+            cbdt: self.cbdt.clone_with_acl(acl.clone()),
+            cblc: self.cblc.clone_with_acl(acl.clone()),
+            sbix: self.sbix.clone_with_acl(acl.clone()),
+            svg: self.svg.clone_with_acl(acl.clone()),
+            math: self.math.clone_with_acl(acl.clone()),
             post: self.post.clone_with_acl(acl.clone()),
             loca: self.loca.clone_with_acl(acl.clone()),
             loca_format: self.loca_format.clone_with_acl(acl.clone()),
@@ -937,6 +963,7 @@ impl Context {
             hvar: self.hvar.clone_with_acl(acl.clone()),
             mvar: self.mvar.clone_with_acl(acl.clone()),
             meta: self.meta.clone_with_acl(acl.clone()),
+            base: self.base.clone_with_acl(acl.clone()),
             vhea: self.vhea.clone_with_acl(acl.clone()),
             vmtx: self.vmtx.clone_with_acl(acl.clone()),
             all_kerning_pairs: self.all_kerning_pairs.clone_with_acl(acl.clone()),
@@ -973,6 +1000,12 @@ impl Context {
             gsub: ContextItem::new(WorkId::Gsub.into(), acl.clone(), persistent_storage.clone()),
             gdef: ContextItem::new(WorkId::Gdef.into(), acl.clone(), persistent_storage.clone()),
             gvar: ContextItem::new(WorkId::Gvar.into(), acl.clone(), persistent_storage.clone()),
+            kern: ContextItem::new(WorkId::Kern.into(), acl.clone(), persistent_storage.clone()),
+            cbdt: ContextItem::new(WorkId::Cbdt.into(), acl.clone(), persistent_storage.clone()),
+            cblc: ContextItem::new(WorkId::Cblc.into(), acl.clone(), persistent_storage.clone()),
+            sbix: ContextItem::new(WorkId::Sbix.into(), acl.clone(), persistent_storage.clone()),
+            svg: ContextItem::new(WorkId::Svg.into(), acl.clone(), persistent_storage.clone()),
+            math: ContextItem::new(WorkId::Math.into(), acl.clone(), persistent_storage.clone()),
             post: ContextItem::new(WorkId::Post.into(), acl.clone(), persistent_storage.clone()),
             loca: ContextItem::new(WorkId::Loca.into(), acl.clone(), persistent_storage.clone()),
             loca_format: ContextItem::new(
@@ -989,6 +1022,7 @@ impl Context {
             hvar: ContextItem::new(WorkId::Hvar.into(), acl.clone(), persistent_storage.clone()),
             mvar: ContextItem::new(WorkId::Mvar.into(), acl.clone(), persistent_storage.clone()),
             meta: ContextItem::new(WorkId::Meta.into(), acl.clone(), persistent_storage.clone()),
+            base: ContextItem::new(WorkId::Base.into(), acl.clone(), persistent_storage.clone()),
             vhea: ContextItem::new(WorkId::Vhea.into(), acl.clone(), persistent_storage.clone()),
             vmtx: ContextItem::new(WorkId::Vmtx.into(), acl.clone(), persistent_storage.clone()),
             all_kerning_pairs: ContextItem::new(
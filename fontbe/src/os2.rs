@@ -7,18 +7,23 @@ use fontdrasil::{
     types::WidthClass,
 };
 use fontir::{
-    ir::{GlobalMetricsInstance, Panose},
-    orchestration::WorkId as FeWorkId,
+    ir::{GlobalMetric, GlobalMetricsInstance, Panose},
+    orchestration::{Flags, WorkId as FeWorkId},
 };
+use log::warn;
 use write_fonts::{
     read::{tables::hmtx::Hmtx, FontData, TopLevelTable},
-    tables::os2::Os2,
+    tables::{
+        head::Head,
+        os2::{Os2, SelectionFlags},
+    },
     types::Tag,
     OtRound,
 };
 
 use crate::{
     error::Error,
+    metrics_and_limits::is_fixed_pitch,
     orchestration::{AnyWorkId, BeWork, Context, WorkId},
 };
 
@@ -248,6 +253,56 @@ fn x_avg_char_width(context: &Context) -> Result<i16, Error> {
     Ok((total as f32 / count as f32).ot_round())
 }
 
+/// A PANOSE classification for sources that don't provide one.
+///
+/// Only the family kind, weight and proportion are filled in, the rest
+/// are 0 (any). See <https://monotype.github.io/panose/pan2.htm>.
+fn compute_panose(us_weight_class: u16, us_width_class: u16, monospace: bool) -> Panose {
+    // 100 => 2 (very light) .. 900 => 10 (black), 11 is extra black
+    let weight = (us_weight_class.clamp(100, 1000) as f64 / 100.0).round() as u8 + 1;
+    let proportion = if monospace {
+        9
+    } else {
+        match us_width_class {
+            0..=2 => 8, // very condensed
+            3 | 4 => 6, // condensed
+            5 => 3,     // modern
+            6 | 7 => 5, // expanded
+            _ => 7,     // very expanded
+        }
+    };
+    Panose {
+        family_type: 2, // Latin text
+        serif_style: 0,
+        weight,
+        proportion,
+        contrast: 0,
+        stroke_variation: 0,
+        arm_style: 0,
+        letterform: 0,
+        midline: 0,
+        x_height: 0,
+    }
+}
+
+/// `panose`, with the proportion of Latin text changed if it disagrees with `is_fixed_pitch`
+///
+/// Monospaced becomes 9, anything else claiming to be monospaced becomes 0 (any).
+fn agreeing_panose(panose: &Panose, is_fixed_pitch: bool) -> Panose {
+    const MONOSPACED: u8 = 9;
+    let mut panose = panose.clone();
+    if panose.family_type != 2 || is_fixed_pitch == (panose.proportion == MONOSPACED) {
+        return panose;
+    }
+    let proportion = if is_fixed_pitch { MONOSPACED } else { 0 };
+    warn!(
+        "PANOSE proportion {} doesn't agree with post.isFixedPitch, using {proportion}",
+        panose.proportion
+    );
+    panose.proportion = proportion;
+    panose
+}
+
 fn apply_panose(os2: &mut Os2, panose: Option<&Panose>) {
     let Some(panose) = panose else {
         return;
@@ -279,6 +334,26 @@ fn apply_metrics(os2: &mut Os2, metrics: &GlobalMetricsInstance) {
     os2.us_win_descent = metrics.os2_win_descent.ot_round();
 }
 
+/// The parts of the Google Fonts vertical metrics schema that need compiled glyphs.
+///
+/// Win metrics the source didn't set grow to cover the bounding box of every glyph,
+/// so nothing is clipped; those it did set are kept. Typo metrics are used for line
+/// spacing. See <https://googlefonts.github.io/gf-guide/metrics.html>.
+fn apply_google_fonts_metrics(
+    os2: &mut Os2,
+    head: &Head,
+    grow_win_ascent: bool,
+    grow_win_descent: bool,
+) {
+    if grow_win_ascent {
+        os2.us_win_ascent = os2.us_win_ascent.max(head.y_max.max(0) as u16);
+    }
+    if grow_win_descent {
+        os2.us_win_descent = os2.us_win_descent.max((-(head.y_min as i32)).max(0) as u16);
+    }
+    os2.fs_selection |= SelectionFlags::USE_TYPO_METRICS;
+}
+
 fn add_unicode_range_bits(add_to: &mut HashSet<u32>, codepoint: u32) {
     let maybe_idx = UNICODE_RANGES
         .binary_search_by(|(from, to, _)| match codepoint {
@@ -298,17 +373,37 @@ fn add_unicode_range_bits(add_to: &mut HashSet<u32>, codepoint: u32) {
     }
 }
 
+/// Like [`add_unicode_range_bits`] for every codepoint in `first..=last`
+fn add_unicode_range_bits_for_range(add_to: &mut HashSet<u32>, first: u32, last: u32) {
+    add_to.extend(
+        UNICODE_RANGES
+            .iter()
+            .filter(|(from, to, _)| *from <= last && *to >= first)
+            .map(|(_, _, bit)| *bit),
+    );
+    if last >= 0x10000 && first <= 0x10FFFF {
+        add_to.insert(57);
+    }
+}
+
 /// <https://github.com/fonttools/fonttools/blob/47813b217c1f8bc343c094776020b4f32fc024b0/Lib/fontTools/ttLib/tables/O_S_2f_2.py#L317-L334>
+///
+/// `ranges` are the inclusive ranges mapped by cmap format 13, which can be
+/// too large to list in `codepoints`.
 fn apply_unicode_range(
     os2: &mut Os2,
     assigned_bits: Option<HashSet<u32>>,
     codepoints: &HashSet<u32>,
+    ranges: &[(u32, u32)],
 ) {
     let bits = assigned_bits.unwrap_or_else(|| {
         let mut bits = HashSet::new();
         for codepoint in codepoints {
             add_unicode_range_bits(&mut bits, *codepoint);
         }
+        for (first, last) in ranges {
+            add_unicode_range_bits_for_range(&mut bits, *first, *last);
+        }
         bits
     });
 
@@ -497,16 +592,35 @@ fn apply_max_context(os2: &mut Os2, context: &Context) {
     os2.us_max_context = Some(max_context::compute_max_context_value(gpos, gsub));
 }
 
-fn codepoints(context: &Context) -> HashSet<u32> {
+/// The codepoints in cmap, with those mapped by format 13 ranges only included up to U+FFFF
+fn codepoints(context: &Context, ranges: &[(u32, u32)]) -> HashSet<u32> {
     let glyph_order = context.ir.glyph_order.get();
 
     let mut codepoints = HashSet::new();
     for glyph_name in glyph_order.names() {
         codepoints.extend(context.ir.get_glyph(glyph_name.clone()).codepoints.iter());
     }
+    // Beyond the BMP the set would get huge, and nothing but the unicode range,
+    // which looks at the ranges directly, cares
+    for (first, last) in ranges.iter().filter(|(first, _)| *first <= 0xFFFF) {
+        codepoints.extend(*first..=(*last).min(0xFFFF));
+    }
     codepoints
 }
 
+/// The codepoints cmap maps using format 13, see [`fontir::ir::MiscMetadata::cmap_ranges`]
+fn cmap_ranges(context: &Context) -> Vec<(u32, u32)> {
+    let static_metadata = context.ir.static_metadata.get();
+    let glyph_order = context.ir.glyph_order.get();
+    static_metadata
+        .misc
+        .cmap_ranges
+        .iter()
+        .filter(|range| glyph_order.contains(&range.glyph))
+        .map(|range| (range.first, range.last))
+        .collect()
+}
+
 impl Work<Context, AnyWorkId, Error> for Os2Work {
     fn id(&self) -> AnyWorkId {
         WorkId::Os2.into()
@@ -517,6 +631,7 @@ impl Work<Context, AnyWorkId, Error> for Os2Work {
             .variant(FeWorkId::StaticMetadata)
             .variant(FeWorkId::GlyphOrder)
             .variant(FeWorkId::GlobalMetrics)
+            .variant(WorkId::Head)
             .variant(WorkId::Hhea)
             .variant(WorkId::Hmtx)
             .variant(WorkId::Gpos)
@@ -549,12 +664,11 @@ impl Work<Context, AnyWorkId, Error> for Os2Work {
             WidthClass::nearest(v) as u16
         });
 
-        let metrics = context
-            .ir
-            .global_metrics
-            .get()
-            .at(static_metadata.default_location());
-        let codepoints = codepoints(context);
+        let global_metrics = context.ir.global_metrics.get();
+        let metrics = global_metrics.at(static_metadata.default_location());
+        // Like fontmake, anything the source doesn't set is computed from what cmap covers
+        let cmap_ranges = cmap_ranges(context);
+        let codepoints = codepoints(context, &cmap_ranges);
 
         let mut os2 = Os2 {
             us_weight_class,
@@ -575,12 +689,24 @@ impl Work<Context, AnyWorkId, Error> for Os2Work {
 
             ..Default::default()
         };
-        apply_panose(&mut os2, static_metadata.misc.panose.as_ref());
+        // post.isFixedPitch wins, a Latin text PANOSE is made to agree with it
+        let is_fixed_pitch = is_fixed_pitch(context)?;
+        let panose = match &static_metadata.misc.panose {
+            Some(panose) => Some(agreeing_panose(panose, is_fixed_pitch)),
+            None if context.flags.contains(Flags::COMPUTE_PANOSE) => Some(compute_panose(
+                us_weight_class,
+                us_width_class,
+                is_fixed_pitch,
+            )),
+            None => None,
+        };
+        apply_panose(&mut os2, panose.as_ref());
         apply_metrics(&mut os2, &metrics);
         apply_unicode_range(
             &mut os2,
             static_metadata.misc.unicode_range_bits.clone(),
             &codepoints,
+            &cmap_ranges,
         );
         apply_codepage_range(
             &mut os2,
@@ -588,6 +714,14 @@ impl Work<Context, AnyWorkId, Error> for Os2Work {
             &codepoints,
         );
         apply_min_max_char_index(&mut os2, &codepoints);
+        if context.flags.contains(Flags::GOOGLE_FONTS_METRICS) {
+            apply_google_fonts_metrics(
+                &mut os2,
+                &context.head.get(),
+                global_metrics.covers_bbox(GlobalMetric::Os2WinAscent),
+                global_metrics.covers_bbox(GlobalMetric::Os2WinDescent),
+            );
+        }
 
         apply_max_context(&mut os2, context);
 
@@ -636,6 +770,95 @@ mod tests {
         bits
     }
 
+    #[test]
+    fn computed_panose() {
+        let panose = compute_panose(400, 5, false);
+        assert_eq!(
+            (2, 5, 3),
+            (panose.family_type, panose.weight, panose.proportion)
+        );
+        let panose = compute_panose(900, 3, false);
+        assert_eq!((10, 6), (panose.weight, panose.proportion));
+        let panose = compute_panose(1000, 9, true);
+        assert_eq!((11, 9), (panose.weight, panose.proportion));
+        let panose = compute_panose(1, 1, false);
+        assert_eq!((2, 8), (panose.weight, panose.proportion));
+    }
+
+    #[test]
+    fn panose_proportion_agrees_with_fixed_pitch() {
+        let proportional = compute_panose(400, 5, false);
+        assert_eq!(9, agreeing_panose(&proportional, true).proportion);
+        let monospaced = compute_panose(400, 5, true);
+        assert_eq!(0, agreeing_panose(&monospaced, false).proportion);
+        assert_eq!(monospaced, agreeing_panose(&monospaced, true));
+        // only Latin text has a proportion
+        let script = Panose {
+            family_type: 3,
+            ..proportional
+        };
+        assert_eq!(script, agreeing_panose(&script, true));
+    }
+
+    #[test]
+    fn google_fonts_win_metrics_cover_bbox() {
+        let mut os2 = Os2 {
+            us_win_ascent: 900,
+            us_win_descent: 300,
+            ..Default::default()
+        };
+        let head = Head {
+            y_max: 1100,
+            y_min: -200,
+            ..Default::default()
+        };
+        apply_google_fonts_metrics(&mut os2, &head, true, true);
+        assert_eq!((1100, 300), (os2.us_win_ascent, os2.us_win_descent));
+        assert!(os2.fs_selection.contains(SelectionFlags::USE_TYPO_METRICS));
+    }
+
+    #[test]
+    fn google_fonts_metrics_keep_source_win_metrics() {
+        let mut os2 = Os2 {
+            us_win_ascent: 900,
+            us_win_descent: 100,
+            ..Default::default()
+        };
+        let head = Head {
+            y_max: 1100,
+            y_min: -200,
+            ..Default::default()
+        };
+        // the source set win ascent but not win descent
+        apply_google_fonts_metrics(&mut os2, &head, false, true);
+        assert_eq!((900, 200), (os2.us_win_ascent, os2.us_win_descent));
+    }
+
+    #[test]
+    fn unicode_range_bits_for_ranges() {
+        let mut bits = HashSet::new();
+        // Basic Latin and Latin-1 Supplement
+        add_unicode_range_bits_for_range(&mut bits, 0x41, 0xE9);
+        assert_eq!(HashSet::from([0, 1]), bits);
+
+        let mut bits = HashSet::new();
+        // Mahjong Tiles
+        add_unicode_range_bits_for_range(&mut bits, 0x1F000, 0x1F02F);
+        assert_eq!(HashSet::from([57, 122]), bits);
+    }
+
+    #[test]
+    fn unicode_range_bits_for_everything() {
+        let mut bits = HashSet::new();
+        add_unicode_range_bits_for_range(&mut bits, 0, 0x10FFFF);
+        let expected = UNICODE_RANGES
+            .iter()
+            .map(|(_, _, bit)| *bit)
+            .chain([57])
+            .collect::<HashSet<_>>();
+        assert_eq!(expected, bits);
+    }
+
     #[test]
     fn unicode_range_bit_lut_latin() {
         assert_eq!(HashSet::from([0]), unicode_range_bits(0x65));
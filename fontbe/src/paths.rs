@@ -97,7 +97,14 @@ impl Paths {
             WorkId::Os2 => self.build_dir.join("os2.table"),
             WorkId::Post => self.build_dir.join("post.table"),
             WorkId::Stat => self.build_dir.join("stat.table"),
+            WorkId::Kern => self.build_dir.join("kern.table"),
+            WorkId::Cbdt => self.build_dir.join("cbdt.table"),
+            WorkId::Cblc => self.build_dir.join("cblc.table"),
+            WorkId::Sbix => self.build_dir.join("sbix.table"),
+            WorkId::Svg => self.build_dir.join("svg.table"),
+            WorkId::Math => self.build_dir.join("math.table"),
             WorkId::Meta => self.build_dir.join("meta.table"),
+            WorkId::Base => self.build_dir.join("base.table"),
             WorkId::Vhea => self.build_dir.join("vhea.table"),
             WorkId::Vmtx => self.build_dir.join("vmtx.table"),
             WorkId::ExtraFeaTables => self.build_dir.join("extra_tables.bin"),
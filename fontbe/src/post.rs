@@ -11,6 +11,7 @@ use write_fonts::{
 
 use crate::{
     error::Error,
+    metrics_and_limits::is_fixed_pitch,
     orchestration::{AnyWorkId, BeWork, Context, WorkId},
 };
 
@@ -31,6 +32,8 @@ impl Work<Context, AnyWorkId, Error> for PostWork {
             .variant(FeWorkId::StaticMetadata)
             .variant(FeWorkId::GlyphOrder)
             .variant(FeWorkId::GlobalMetrics)
+            .variant(WorkId::Hhea)
+            .variant(WorkId::Hmtx)
             .build()
     }
 
@@ -81,7 +84,7 @@ impl Work<Context, AnyWorkId, Error> for PostWork {
             Post::new_v2(glyph_order.names().map(|g| g.as_str()))
         };
 
-        post.is_fixed_pitch = static_metadata.misc.is_fixed_pitch.unwrap_or_default() as u32;
+        post.is_fixed_pitch = is_fixed_pitch(context)? as u32;
         post.italic_angle = Fixed::from_f64(static_metadata.italic_angle.into_inner());
         post.underline_position = FWord::new(metrics.underline_position.ot_round());
         post.underline_thickness = FWord::new(metrics.underline_thickness.ot_round());
@@ -0,0 +1,162 @@
+//! Generate an [sbix](https://learn.microsoft.com/en-us/typography/opentype/spec/sbix) table
+//!
+//! Written when the source has bitmap strikes, unless [Flags::CBDT_BITMAPS] asks
+//! for CBDT and CBLC instead.
+
+use fontdrasil::orchestration::{Access, AccessBuilder, Work};
+use fontir::{
+    ir::{BitmapStrikes, GlyphOrder},
+    orchestration::{Flags, WorkId as FeWorkId},
+};
+
+use crate::{
+    error::Error,
+    orchestration::{AnyWorkId, BeWork, Context, WorkId},
+};
+
+/// Bit 0 of the header flags, which the spec requires to be set
+const FLAGS: u16 = 0x0001;
+/// The size of a glyph's origin and graphic type, before its data
+const GLYPH_HEADER_LEN: usize = 8;
+/// Bitmaps are drawn for a 72 dpi device, so a pixel is a point
+const PPI: u16 = 72;
+
+#[derive(Debug)]
+struct SbixWork;
+
+pub fn create_sbix_work() -> Box<BeWork> {
+    Box::new(SbixWork)
+}
+
+impl Work<Context, AnyWorkId, Error> for SbixWork {
+    fn id(&self) -> AnyWorkId {
+        WorkId::Sbix.into()
+    }
+
+    fn read_access(&self) -> Access<AnyWorkId> {
+        AccessBuilder::new()
+            .variant(FeWorkId::GlyphOrder)
+            .variant(FeWorkId::BitmapStrikes)
+            .build()
+    }
+
+    fn exec(&self, context: &Context) -> Result<(), Error> {
+        if context.flags.contains(Flags::CBDT_BITMAPS) {
+            return Ok(());
+        }
+        let Some(strikes) = context.ir.bitmap_strikes.try_get() else {
+            return Ok(());
+        };
+        let glyph_order = context.ir.glyph_order.get();
+        context
+            .sbix
+            .set(to_table_bytes(&strikes, &glyph_order).into());
+        Ok(())
+    }
+}
+
+/// A version 1 sbix table with a strike per ppem, all of PNG graphics
+fn to_table_bytes(strikes: &BitmapStrikes, glyph_order: &GlyphOrder) -> Vec<u8> {
+    let strikes: Vec<_> = strikes
+        .strikes
+        .iter()
+        .map(|(ppem, strike)| {
+            let mut offsets = Vec::with_capacity(glyph_order.len() + 1);
+            let mut data = Vec::new();
+            let header_len = 4 + 4 * (glyph_order.len() + 1);
+            for glyph_name in glyph_order.names() {
+                offsets.push((header_len + data.len()) as u32);
+                let Some(bitmap) = strike.glyphs.get(glyph_name) else {
+                    continue;
+                };
+                data.reserve(GLYPH_HEADER_LEN + bitmap.png.len());
+                data.extend(bitmap.left.to_be_bytes());
+                data.extend(bitmap.bottom.to_be_bytes());
+                data.extend(b"png ");
+                data.extend(&bitmap.png);
+            }
+            offsets.push((header_len + data.len()) as u32);
+
+            let mut buf = Vec::with_capacity(header_len + data.len());
+            buf.extend(ppem.to_be_bytes());
+            buf.extend(PPI.to_be_bytes());
+            for offset in offsets {
+                buf.extend(offset.to_be_bytes());
+            }
+            buf.extend(data);
+            buf
+        })
+        .collect();
+
+    let mut buf = Vec::new();
+    buf.extend(1u16.to_be_bytes());
+    buf.extend(FLAGS.to_be_bytes());
+    buf.extend((strikes.len() as u32).to_be_bytes());
+    let mut offset = 8 + 4 * strikes.len();
+    for strike in strikes.iter() {
+        buf.extend((offset as u32).to_be_bytes());
+        offset += strike.len();
+    }
+    for strike in strikes {
+        buf.extend(strike);
+    }
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use fontir::ir::{BitmapGlyph, BitmapStrike, BitmapStrikes, GlyphOrder};
+    use write_fonts::{
+        read::{tables::sbix::Sbix, FontData},
+        types::{GlyphId, Tag},
+    };
+
+    use super::to_table_bytes;
+
+    #[test]
+    fn strikes_read_back() {
+        let glyph_order: GlyphOrder = [".notdef", "smile", "space"]
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        let strike = |png: &[u8]| BitmapStrike {
+            glyphs: [(
+                "smile".into(),
+                BitmapGlyph {
+                    png: png.to_vec(),
+                    left: 1,
+                    bottom: -2,
+                },
+            )]
+            .into(),
+        };
+        let strikes = BitmapStrikes {
+            strikes: [(32, strike(b"small")), (64, strike(b"large"))].into(),
+        };
+
+        let bytes = to_table_bytes(&strikes, &glyph_order);
+        let sbix = Sbix::read(FontData::new(&bytes), glyph_order.len() as u16).unwrap();
+
+        assert_eq!(
+            vec![(32, b"small".as_slice()), (64, b"large".as_slice())],
+            sbix.strikes()
+                .iter()
+                .map(|strike| {
+                    let strike = strike.unwrap();
+                    assert!(strike.glyph_data(GlyphId::new(0)).unwrap().is_none());
+                    assert!(strike.glyph_data(GlyphId::new(2)).unwrap().is_none());
+                    let smile = strike.glyph_data(GlyphId::new(1)).unwrap().unwrap();
+                    assert_eq!(
+                        (1, -2, Tag::new(b"png ")),
+                        (
+                            smile.origin_offset_x(),
+                            smile.origin_offset_y(),
+                            smile.graphic_type()
+                        )
+                    );
+                    (strike.ppem(), smile.data())
+                })
+                .collect::<Vec<_>>()
+        );
+    }
+}
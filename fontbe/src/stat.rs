@@ -78,15 +78,24 @@ fn make_stat(static_metadata: &StaticMetadata) -> Stat {
         })
         .collect();
 
+    // Listed axes first, in the order listed, then the rest as they come
+    let stat_axis_order = &static_metadata.misc.stat_axis_order;
+    let mut ordered_tags: Vec<_> = static_metadata.axes.iter().map(|a| a.tag).collect();
+    ordered_tags.sort_by_key(|tag| {
+        stat_axis_order
+            .iter()
+            .position(|t| t == tag)
+            .unwrap_or(stat_axis_order.len())
+    });
+
     Stat {
         design_axes: static_metadata
             .axes
             .iter()
-            .enumerate()
-            .map(|(idx, a)| AxisRecord {
+            .map(|a| AxisRecord {
                 axis_tag: a.tag,
                 axis_name_id: *reusable_names.get(a.ui_label_name()).unwrap(),
-                axis_ordering: idx as u16,
+                axis_ordering: ordered_tags.iter().position(|t| *t == a.tag).unwrap() as u16,
             })
             .collect::<Vec<_>>()
             .into(),
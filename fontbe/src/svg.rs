@@ -0,0 +1,274 @@
+//! Generate an [SVG](https://learn.microsoft.com/en-us/typography/opentype/spec/svg) table
+//!
+//! Every glyph gets a document of its own, whose root `svg` element is given the
+//! `glyph<gid>` id the spec uses to find a glyph in its document. Documents are
+//! gzipped, which the spec allows and which shrinks typical documents severalfold.
+
+use std::io::Write;
+
+use flate2::{write::GzEncoder, Compression};
+use fontdrasil::orchestration::{Access, AccessBuilder, Work};
+use fontir::orchestration::WorkId as FeWorkId;
+use log::warn;
+use write_fonts::types::GlyphId16;
+
+use crate::{
+    error::Error,
+    orchestration::{AnyWorkId, BeWork, Context, WorkId},
+};
+
+/// The size of the SVG header: version, document list offset and reserved
+const HEADER_LEN: u32 = 10;
+/// The size of an SVGDocumentRecord
+const RECORD_LEN: usize = 12;
+
+#[derive(Debug)]
+struct SvgWork;
+
+pub fn create_svg_work() -> Box<BeWork> {
+    Box::new(SvgWork)
+}
+
+impl Work<Context, AnyWorkId, Error> for SvgWork {
+    fn id(&self) -> AnyWorkId {
+        WorkId::Svg.into()
+    }
+
+    fn read_access(&self) -> Access<AnyWorkId> {
+        AccessBuilder::new()
+            .variant(FeWorkId::GlyphOrder)
+            .variant(FeWorkId::SvgDocuments)
+            .build()
+    }
+
+    fn exec(&self, context: &Context) -> Result<(), Error> {
+        let Some(svg_documents) = context.ir.svg_documents.try_get() else {
+            return Ok(());
+        };
+        let glyph_order = context.ir.glyph_order.get();
+
+        let mut documents = Vec::new();
+        for (glyph_name, document) in svg_documents.documents.iter() {
+            let Some(gid) = glyph_order.glyph_id(glyph_name) else {
+                warn!("Dropping the SVG document of {glyph_name}, it's not in the glyph order");
+                continue;
+            };
+            let Some(document) = with_glyph_id(document, gid) else {
+                warn!("Dropping the SVG document of {glyph_name}, it has no <svg> element");
+                continue;
+            };
+            documents.push(SvgDocument {
+                start: gid,
+                end: gid,
+                data: gzip(document.as_bytes())?,
+            });
+        }
+        if documents.is_empty() {
+            return Ok(());
+        }
+        documents.sort_by_key(|document| document.start);
+        check_ranges(&documents, glyph_order.len())?;
+        context.svg.set(to_table_bytes(&documents).into());
+        Ok(())
+    }
+}
+
+/// A document and the range of glyphs it covers
+#[derive(Debug, Clone, PartialEq)]
+struct SvgDocument {
+    start: GlyphId16,
+    end: GlyphId16,
+    data: Vec<u8>,
+}
+
+/// The document with its root `svg` element's id set to `glyph<gid>`
+///
+/// Returns None if there is no `svg` element.
+fn with_glyph_id(document: &str, gid: GlyphId16) -> Option<String> {
+    let id = format!("glyph{}", gid.to_u16());
+    // skip the prolog, an xml declaration, comments and such all start with <? or <!
+    let tag_start = document.match_indices("<svg").map(|(i, _)| i).find(|i| {
+        document[i + 4..]
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_whitespace() || c == '>' || c == '/')
+    })?;
+    let tag_end = tag_start + document[tag_start..].find('>')?;
+    let tag = &document[tag_start..tag_end];
+
+    // replace an existing id, otherwise add one
+    let existing = tag.match_indices("id=").find(|(i, _)| {
+        tag[..*i]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_whitespace)
+    });
+    if let Some((i, _)) = existing {
+        let value_start = tag_start + i + 3;
+        let quote = document[value_start..].chars().next()?;
+        let value_end = value_start + 1 + document[value_start + 1..].find(quote)?;
+        return Some(format!(
+            "{}{quote}{id}{quote}{}",
+            &document[..value_start],
+            &document[value_end + 1..]
+        ));
+    }
+    let insert_at = tag_start + 4;
+    Some(format!(
+        "{} id=\"{id}\"{}",
+        &document[..insert_at],
+        &document[insert_at..]
+    ))
+}
+
+fn gzip(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+/// Document ranges must be in order, not overlap and only cover glyphs in the font
+fn check_ranges(documents: &[SvgDocument], num_glyphs: usize) -> Result<(), Error> {
+    for document in documents {
+        if document.start > document.end || document.end.to_u16() as usize >= num_glyphs {
+            return Err(Error::OutOfBounds {
+                what: format!("SVG document glyph range, for {num_glyphs} glyphs"),
+                value: format!("{}..={}", document.start, document.end),
+            });
+        }
+    }
+    for pair in documents.windows(2) {
+        if pair[0].end >= pair[1].start {
+            return Err(Error::OverlappingSvgDocuments(
+                pair[0].start..=pair[0].end,
+                pair[1].start..=pair[1].end,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// A version 0 SVG table, `documents` must be in glyph id order
+fn to_table_bytes(documents: &[SvgDocument]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend(0u16.to_be_bytes());
+    buf.extend(HEADER_LEN.to_be_bytes());
+    buf.extend(0u32.to_be_bytes());
+
+    // offsets are from the start of the document list
+    buf.extend((documents.len() as u16).to_be_bytes());
+    let mut offset = 2 + RECORD_LEN * documents.len();
+    for document in documents {
+        buf.extend(document.start.to_u16().to_be_bytes());
+        buf.extend(document.end.to_u16().to_be_bytes());
+        buf.extend((offset as u32).to_be_bytes());
+        buf.extend((document.data.len() as u32).to_be_bytes());
+        offset += document.data.len();
+    }
+    for document in documents {
+        buf.extend(&document.data);
+    }
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+    use write_fonts::{
+        read::{tables::svg::Svg, FontData, FontRead},
+        types::{GlyphId, GlyphId16},
+    };
+
+    use crate::error::Error;
+
+    use super::{check_ranges, gzip, to_table_bytes, with_glyph_id, SvgDocument};
+
+    fn document(start: u16, end: u16, data: &[u8]) -> SvgDocument {
+        SvgDocument {
+            start: GlyphId16::new(start),
+            end: GlyphId16::new(end),
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn adds_glyph_id_to_root() {
+        assert_eq!(
+            Some(
+                r#"<?xml version="1.0"?><svg id="glyph3" xmlns="http://www.w3.org/2000/svg"/>"#
+                    .to_string()
+            ),
+            with_glyph_id(
+                r#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg"/>"#,
+                GlyphId16::new(3)
+            )
+        );
+    }
+
+    #[test]
+    fn replaces_root_id() {
+        assert_eq!(
+            Some(r#"<svg width='10' id='glyph7'><g id="a"/></svg>"#.to_string()),
+            with_glyph_id(
+                r#"<svg width='10' id='smile'><g id="a"/></svg>"#,
+                GlyphId16::new(7)
+            )
+        );
+    }
+
+    #[test]
+    fn no_svg_element() {
+        assert_eq!(None, with_glyph_id("<svgx/>", GlyphId16::new(1)));
+    }
+
+    #[test]
+    fn overlapping_ranges_are_rejected() {
+        let documents = [document(1, 3, b"a"), document(3, 4, b"b")];
+        assert!(matches!(
+            check_ranges(&documents, 5),
+            Err(Error::OverlappingSvgDocuments(..))
+        ));
+    }
+
+    #[test]
+    fn ranges_past_the_last_glyph_are_rejected() {
+        let documents = [document(1, 1, b"a"), document(2, 5, b"b")];
+        assert!(matches!(
+            check_ranges(&documents, 5),
+            Err(Error::OutOfBounds { .. })
+        ));
+        assert!(check_ranges(&documents, 6).is_ok());
+    }
+
+    #[test]
+    fn documents_read_back() {
+        let documents = [
+            document(1, 1, &gzip(b"<svg id=\"glyph1\"/>").unwrap()),
+            document(3, 4, &gzip(b"<svg id=\"glyph3\"/>").unwrap()),
+        ];
+
+        let bytes = to_table_bytes(&documents);
+        let svg = Svg::read(FontData::new(&bytes)).unwrap();
+
+        assert_eq!(0, svg.version());
+        let mut read_back = Vec::new();
+        for gid in 0..6 {
+            let Some(data) = svg.glyph_data(GlyphId::new(gid)).unwrap() else {
+                continue;
+            };
+            let mut document = String::new();
+            GzDecoder::new(data).read_to_string(&mut document).unwrap();
+            read_back.push((gid, document));
+        }
+        assert_eq!(
+            vec![
+                (1, "<svg id=\"glyph1\"/>".to_string()),
+                (3, "<svg id=\"glyph3\"/>".to_string()),
+                (4, "<svg id=\"glyph3\"/>".to_string()),
+            ],
+            read_back
+        );
+    }
+}
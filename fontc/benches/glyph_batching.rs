@@ -0,0 +1,113 @@
+//! Compare compile times with and without batching of glyph IR work.
+//!
+//! Uses a synthetic source with many simple glyphs, roughly the shape of a CJK
+//! font where per-glyph scheduling overhead is most noticeable.
+
+use std::{fmt::Write, fs, path::Path};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use fontc::{Args, JobTimer};
+
+const N_GLYPHS: usize = 10_000;
+
+/// A single master .glyphs source with n glyphs, each a box.
+fn many_glyphs_source(n: usize) -> String {
+    let mut glyphs = String::new();
+    for i in 0..n {
+        let (x, y) = ((i % 50) as i32 * 10, (i / 50 % 50) as i32 * 10);
+        write!(
+            glyphs,
+            r#"{{
+glyphname = uni{:04X};
+layers = (
+{{
+layerId = m01;
+shapes = (
+{{
+closed = 1;
+nodes = (
+({x},{y},l),
+({},{y},l),
+({},{},l),
+({x},{},l)
+);
+}}
+);
+width = 600;
+}}
+);
+unicode = {};
+}},
+"#,
+            0x4E00 + i,
+            x + 100,
+            x + 100,
+            y + 100,
+            y + 100,
+            0x4E00 + i,
+        )
+        .unwrap();
+    }
+    format!(
+        r#"{{
+.appVersion = "3208";
+.formatVersion = 3;
+familyName = "Batching";
+fontMaster = (
+{{
+id = m01;
+metricValues = (
+{{
+pos = 800;
+}},
+{{
+pos = -200;
+}}
+);
+name = Regular;
+}}
+);
+glyphs = (
+{glyphs});
+metrics = (
+{{
+type = ascender;
+}},
+{{
+type = descender;
+}}
+);
+unitsPerEm = 1000;
+versionMajor = 1;
+versionMinor = 0;
+}}
+"#
+    )
+}
+
+fn compile(build_dir: &Path, source: &Path, glyph_batch_size: Option<usize>) {
+    let mut args = Args::new(build_dir, source.to_path_buf());
+    args.glyph_batch_size = glyph_batch_size;
+    fontc::run(args, JobTimer::new()).unwrap();
+}
+
+fn glyph_batching(c: &mut Criterion) {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let source = temp_dir.path().join("Batching.glyphs");
+    fs::write(&source, many_glyphs_source(N_GLYPHS)).unwrap();
+    let build_dir = temp_dir.path().join("build");
+
+    let mut group = c.benchmark_group("glyph_batching");
+    group.sample_size(10);
+    for (name, batch_size) in [("unbatched", Some(1)), ("automatic", None)] {
+        group.bench_with_input(
+            BenchmarkId::new(name, N_GLYPHS),
+            &batch_size,
+            |b, batch_size| b.iter(|| compile(&build_dir, &source, *batch_size)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, glyph_batching);
+criterion_main!(benches);
@@ -0,0 +1,153 @@
+//! Compare compile times, and report gvar sizes, for each --gvar-optimization.
+//!
+//! Uses a synthetic two master source whose glyphs are many-pointed outlines
+//! that vary unevenly, so that IUP has something to work with.
+
+use std::{f64::consts::TAU, fmt::Write, fs, path::Path};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use fontc::{Args, GvarOptimization, JobTimer};
+use skrifa::{raw::types::Tag, FontRef};
+
+const N_GLYPHS: usize = 500;
+const N_POINTS: usize = 48;
+
+/// The nodes of a star-ish outline, bolder the larger weight is
+fn nodes(glyph: usize, weight: f64) -> String {
+    let mut nodes = String::new();
+    for i in 0..N_POINTS {
+        let angle = TAU * i as f64 / N_POINTS as f64;
+        // a smooth swelling that IUP can infer, plus an occasional spike it can't
+        let swell = 1.0 + weight * (angle * (1 + glyph % 3) as f64).sin().abs();
+        let spike = if (i + glyph) % 11 == 0 {
+            40.0 * weight
+        } else {
+            0.0
+        };
+        let radius = 200.0 * swell + spike;
+        let (x, y) = (300.0 + radius * angle.cos(), 300.0 + radius * angle.sin());
+        writeln!(nodes, "({},{},l),", x.round(), y.round()).unwrap();
+    }
+    nodes
+}
+
+fn two_master_source(n: usize) -> String {
+    let mut glyphs = String::new();
+    for i in 0..n {
+        write!(
+            glyphs,
+            r#"{{
+glyphname = uni{:04X};
+layers = (
+{{
+layerId = m01;
+shapes = (
+{{
+closed = 1;
+nodes = (
+{});
+}}
+);
+width = 600;
+}},
+{{
+layerId = m02;
+shapes = (
+{{
+closed = 1;
+nodes = (
+{});
+}}
+);
+width = 600;
+}}
+);
+unicode = {};
+}},
+"#,
+            0x4E00 + i,
+            nodes(i, 0.0),
+            nodes(i, 0.25),
+            0x4E00 + i,
+        )
+        .unwrap();
+    }
+    format!(
+        r#"{{
+.appVersion = "3208";
+.formatVersion = 3;
+axes = (
+{{
+name = Weight;
+tag = wght;
+}}
+);
+familyName = "Gvar";
+fontMaster = (
+{{
+axesValues = (
+400
+);
+id = m01;
+name = Regular;
+}},
+{{
+axesValues = (
+700
+);
+id = m02;
+name = Bold;
+}}
+);
+glyphs = (
+{glyphs});
+unitsPerEm = 1000;
+versionMajor = 1;
+versionMinor = 0;
+}}
+"#
+    )
+}
+
+/// Returns the size of gvar
+fn compile(build_dir: &Path, source: &Path, optimization: GvarOptimization) -> usize {
+    let output_file = build_dir.join("font.ttf");
+    let mut args = Args::new(build_dir, source.to_path_buf());
+    args.output_file = Some(output_file.clone());
+    args.gvar_optimization = optimization;
+    fontc::run(args, JobTimer::new()).unwrap();
+    let bytes = fs::read(output_file).unwrap();
+    let font = FontRef::new(&bytes).unwrap();
+    font.table_data(Tag::new(b"gvar")).unwrap().len()
+}
+
+fn gvar_optimization(c: &mut Criterion) {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let source = temp_dir.path().join("Gvar.glyphs");
+    fs::write(&source, two_master_source(N_GLYPHS)).unwrap();
+    let build_dir = temp_dir.path().join("build");
+
+    let levels = [
+        ("none", GvarOptimization::None),
+        ("iup", GvarOptimization::Iup),
+        ("iup-shared-points", GvarOptimization::IupSharedPoints),
+    ];
+    for (name, optimization) in levels {
+        let size = compile(&build_dir, &source, optimization);
+        println!("gvar with --gvar-optimization {name}: {size} bytes");
+    }
+
+    let mut group = c.benchmark_group("gvar_optimization");
+    group.sample_size(10);
+    for (name, optimization) in levels {
+        group.bench_with_input(
+            BenchmarkId::new(name, N_GLYPHS),
+            &optimization,
+            |b, optimization| b.iter(|| compile(&build_dir, &source, *optimization)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, gvar_optimization);
+criterion_main!(benches);
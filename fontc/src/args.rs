@@ -1,11 +1,20 @@
 //! Command line arguments
 
-use std::path::PathBuf;
+use std::{
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
 
-use clap::{ArgAction, Parser};
-use fontir::orchestration::Flags;
+use chrono::{TimeZone, Utc};
+use clap::{builder::PossibleValuesParser, ArgAction, Parser, ValueEnum};
+use fontdrasil::{
+    coords::UserCoord,
+    warnings::{codes, WarningPolicy},
+};
+use fontir::{orchestration::Flags, source::AxisLimit};
 use regex::Regex;
 use serde::{Deserialize, Deserializer, Serialize};
+use write_fonts::types::Tag;
 
 use crate::{Error, Input};
 
@@ -13,19 +22,26 @@ use crate::{Error, Input};
 #[derive(Serialize, Deserialize, Parser, Debug, Clone, PartialEq)]
 #[command(version)]
 pub struct Args {
-    /// A designspace, ufo, or glyphs file
+    /// Designspace, ufo, or glyphs files, or build directories holding IR from --emit-ir
+    ///
+    /// If more than one source is given they are compiled concurrently, each to
+    /// a font named for the source in the build directory. Wildcards (* and ?)
+    /// in file names are expanded.
     #[arg(
         conflicts_with = "source",
         required_unless_present("source"),
-        required_unless_present("verbose_version")
+        required_unless_present("verbose_version"),
+        required_unless_present("config")
     )]
-    input_source: Option<PathBuf>,
+    input_source: Vec<PathBuf>,
 
     /// DEPRECATED: old name for positional input file
     #[arg(short, long)]
     source: Option<PathBuf>,
 
     /// Whether to write IR to disk.
+    ///
+    /// The build directory can then be compiled in place of the source.
     #[arg(short, long, default_value = "false")]
     pub emit_ir: bool,
 
@@ -44,8 +60,24 @@ pub struct Args {
 
     /// Eliminate component references to other glyphs using components (that is, nested components),
     /// emitting only component references to simple (contour) glyphs.
-    #[arg(long, default_value = "false")]
-    pub flatten_components: bool,
+    ///
+    /// Given a depth, e.g. --flatten-components=2, only flatten composites that nest deeper than
+    /// that; a composite of simple glyphs has depth 1. Useful for renderers that limit nesting.
+    #[arg(
+        long,
+        value_name = "DEPTH",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1",
+        value_parser = clap::value_parser!(u8).range(1..)
+    )]
+    pub flatten_components: Option<u8>,
+
+    /// Convert glyphs whose components, or their 2x2 transforms, vary across the
+    /// designspace to contours, as gvar can only vary offsets. If false such glyphs
+    /// are an error.
+    #[arg(long, default_value = "true", action = ArgAction::Set)]
+    pub flatten_variable_components: bool,
 
     /// Whether all components with a non-identity 2x2 transform will be converted to outlines.
     // Named to match the ufo2ft flag as suggested in <https://github.com/googlefonts/fontc/pull/480#discussion_r1343801553>
@@ -62,6 +94,71 @@ pub struct Args {
     #[arg(long, default_value = "false")]
     pub emit_timing: bool,
 
+    /// Write a trace of task execution, one lane per thread, to the named file.
+    ///
+    /// Uses the Chrome trace event format, viewable in chrome://tracing or <https://ui.perfetto.dev>.
+    #[arg(long)]
+    pub emit_trace: Option<PathBuf>,
+
+    /// Write per-glyph statistics, such as point count and gvar size, to this JSON file.
+    ///
+    /// Useful to track down what made a font grow between releases.
+    #[arg(long)]
+    pub report: Option<PathBuf>,
+
+    /// Draw each glyph to an svg in this directory, one per location the glyph has a source at.
+    ///
+    /// Drawn from IR with components resolved, to see what the compiler made of a glyph.
+    #[arg(long)]
+    pub emit_glyph_svgs: Option<PathBuf>,
+
+    /// Draw the glyph svgs at this user-space location, e.g. wght=550, rather than at each source.
+    ///
+    /// Glyphs, and the components they use, are interpolated where they have no source.
+    /// Axes left out are at their default.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_parser = parse_axis_value,
+        requires = "emit_glyph_svgs"
+    )]
+    pub glyph_svg_location: Vec<(Tag, UserCoord)>,
+
+    /// Check the font covers the character set in this file, warning about each missing codepoint.
+    ///
+    /// The file lists one hex codepoint per line, e.g. 0x0041 or U+0041; anything
+    /// after it on the line is ignored, so Google Fonts glyphset .nam files work.
+    #[arg(long)]
+    pub coverage: Option<PathBuf>,
+
+    /// Check the compiled font before writing it, failing the build if a check fails.
+    ///
+    /// Fast structural checks: cmap order, table alignment, hmtx and loca sizes,
+    /// head/OS/2 agreement, required names and zero-width marks. Each failure is
+    /// logged with a code, e.g. [cmap-order].
+    #[arg(long, default_value = "false")]
+    pub check: bool,
+
+    /// Write these basic tables, as comma-separated tags, as ttx next to the font, e.g. font.ttx.
+    ///
+    /// Only head, hhea, maxp, OS/2 and name are supported, the xml matching what
+    /// fonttools' ttx writes for them. Use fonttools for cmap, layout, glyph and
+    /// variation tables.
+    #[arg(long, value_delimiter = ',', value_parser = parse_basic_ttx_tag)]
+    pub emit_basic_ttx: Vec<Tag>,
+
+    /// Print the size of each table after writing the font.
+    #[arg(long, default_value = "false")]
+    pub table_sizes: bool,
+
+    /// Write the size of each table to this JSON file after writing the font.
+    #[arg(long)]
+    pub table_sizes_json: Option<PathBuf>,
+
+    /// Show --table-sizes as changes from this font, or the JSON from a previous --table-sizes-json.
+    #[arg(long, requires = "table_sizes")]
+    pub table_sizes_baseline: Option<PathBuf>,
+
     /// Working directory for the build process. If emit-ir is on, written here.
     #[arg(short, long, default_value = "build")]
     pub build_dir: PathBuf,
@@ -74,6 +171,12 @@ pub struct Args {
     #[arg(long, default_value = "false")]
     pub skip_features: bool,
 
+    /// Warn about feature code that is legal but probably a mistake
+    ///
+    /// Such as classes and lookups that are never used, or rules that can never apply.
+    #[arg(long, default_value = "false", conflicts_with = "skip_features")]
+    pub lint_features: bool,
+
     /// Whether to keep the original glyph contour direction (TTF only).
     ///
     /// TrueType contours are recommended to follow clockwise orientation;
@@ -90,6 +193,18 @@ pub struct Args {
     #[arg(long, default_value = "false")]
     pub no_production_names: bool,
 
+    /// Don't decompose glyphs whose sources mix components and outlines, report them as incompatible
+    #[arg(long, default_value = "false")]
+    pub no_compatibility_repair: bool,
+
+    /// Reorder contours and rotate start points to match the default master by geometry
+    #[arg(long, default_value = "false")]
+    pub match_contours: bool,
+
+    /// Don't merge identical lookups or disjoint GSUB subtables, to compare with fontmake
+    #[arg(long, default_value = "false")]
+    pub no_layout_optimization: bool,
+
     /// Print verbose version information for debugging
     // Includes fontc git commit, rustc host triple, rustc version and channel, llvm version,
     // cargo profile, and cargo optimization level.
@@ -101,6 +216,236 @@ pub struct Args {
     /// See <https://docs.rs/env_logger/latest/env_logger/#enabling-logging> for format.
     #[arg(long)]
     pub log: Option<String>,
+
+    /// Fail the build if anything is warned about, other than warnings allowed with --allow.
+    ///
+    /// Not supported when compiling more than one source.
+    #[arg(long, default_value = "false")]
+    pub deny_warnings: bool,
+
+    /// Fail the build on warnings with this code, e.g. interpolation-mismatch; may be repeated.
+    ///
+    /// Not supported when compiling more than one source.
+    #[arg(long, value_parser = PossibleValuesParser::new(codes::ALL))]
+    pub deny: Vec<String>,
+
+    /// Don't log warnings with this code, e.g. unknown-custom-parameter; may be repeated.
+    #[arg(long, value_parser = PossibleValuesParser::new(codes::ALL))]
+    pub allow: Vec<String>,
+
+    /// Keep running, recompiling whenever the source changes.
+    ///
+    /// Intended for a design-preview loop; the time taken by each rebuild is printed.
+    #[arg(long, default_value = "false")]
+    pub watch: bool,
+
+    /// Show a progress bar on stderr, if it's a terminal.
+    #[arg(long, default_value = "false")]
+    pub progress: bool,
+
+    /// How many glyphs to produce IR for in a single job.
+    ///
+    /// By default this is chosen based on the number of glyphs and threads. The glyf
+    /// and gvar data for each glyph is still built in a job of its own.
+    #[arg(long)]
+    pub glyph_batch_size: Option<usize>,
+
+    /// Reuse the font from a previous run if the source and options are unchanged.
+    ///
+    /// Compiled fonts are kept in a cache directory inside the build directory. Only
+    /// whole fonts are cached; any change compiles from scratch.
+    #[arg(long, default_value = "false")]
+    pub cache: bool,
+
+    /// Limit peak memory use by dropping glyph IR once the glyph has been compiled.
+    ///
+    /// Dropped glyphs are written to the build directory and re-read if needed again,
+    /// so this implies --emit-ir. Slower, but lets large fonts build on small machines.
+    #[arg(long, default_value = "false")]
+    pub low_memory: bool,
+
+    /// Compile twice, with one thread and with many, and fail if the fonts differ.
+    ///
+    /// Reports the first table that differs. Useful for hunting down nondeterminism.
+    #[arg(long, default_value = "false")]
+    pub verify_determinism: bool,
+
+    /// Only keep the named instance with this name; may be repeated. By default all are kept.
+    #[arg(long = "instance")]
+    pub instances: Vec<String>,
+
+    /// The order of axes in fvar, and so avar and gvar, as comma-separated tags.
+    ///
+    /// Unlisted axes come last, in source order. By default the source order is kept.
+    #[arg(long, value_delimiter = ',', value_parser = parse_tag)]
+    pub axis_order: Vec<Tag>,
+
+    /// The order of axes in STAT, as comma-separated tags. Unlisted axes come last.
+    #[arg(long, value_delimiter = ',', value_parser = parse_tag)]
+    pub stat_axis_order: Vec<Tag>,
+
+    /// Restrict axes to user-space ranges, e.g. wght=300:700, or pin them, e.g. wdth=100.
+    ///
+    /// Masters and static instances outside the ranges are dropped. Nothing is
+    /// interpolated, so each range must start and end on masters.
+    #[arg(long, value_delimiter = ',', value_parser = parse_axis_limit)]
+    pub axis_limit: Vec<AxisLimit>,
+
+    /// A file listing glyph names, one per line, to put first in the glyph order.
+    ///
+    /// Useful to match the glyph order of a font that has already shipped. Glyphs
+    /// not listed follow, ordered according to --glyph-order-mode.
+    #[arg(long)]
+    pub glyph_order: Option<PathBuf>,
+
+    /// How to order glyphs that aren't listed in the --glyph-order file.
+    #[arg(long, value_enum, default_value_t = GlyphOrderMode::Source)]
+    pub glyph_order_mode: GlyphOrderMode,
+
+    /// A previous build of this font whose glyph ids should be kept.
+    ///
+    /// Its glyphs keep their ids, and new glyphs are added at the end. Glyphs are
+    /// matched by the names in its post table.
+    #[arg(long)]
+    pub compat_glyph_order: Option<PathBuf>,
+
+    /// Allow glyphs in the --compat-glyph-order font to be missing, renumbering the glyphs after them.
+    #[arg(long, default_value = "false", requires = "compat_glyph_order")]
+    pub allow_removed_glyphs: bool,
+
+    /// A file listing glyph names, one per line, to keep; all other glyphs are dropped.
+    ///
+    /// Components of kept glyphs are kept, as is anything a substitution in the
+    /// feature file can produce from them. Rules that use dropped glyphs are
+    /// removed from the feature file. May be combined with --keep-unicodes.
+    #[arg(long)]
+    pub keep_glyphs: Option<PathBuf>,
+
+    /// Keep the glyphs for these codepoints, e.g. U+0020-00FF,U+2022; all other glyphs are dropped.
+    ///
+    /// Subsets the same way as --keep-glyphs, with which it may be combined.
+    #[arg(long, value_delimiter = ',', value_parser = parse_unicode_range)]
+    pub keep_unicodes: Vec<RangeInclusive<u32>>,
+
+    /// Map every codepoint that has no glyph of its own to this glyph, e.g. .notdef.
+    ///
+    /// Emits a cmap format 13 subtable, as used by last-resort fonts. Surrogates,
+    /// U+D800 to U+DFFF, aren't characters and stay unmapped.
+    #[arg(long)]
+    pub cmap_fallback_glyph: Option<String>,
+
+    /// Don't emit cmap format 4 subtables, map all codepoints with format 12 instead.
+    ///
+    /// Fonts built this way won't work on older systems; intended for experimental builds.
+    #[arg(long, default_value = "false")]
+    pub no_cmap_format_4: bool,
+
+    /// Add a Macintosh Roman cmap subtable, for compatibility with legacy systems
+    #[arg(long, default_value = "false")]
+    pub cmap_mac_roman: bool,
+
+    /// Drop the {date} placeholder from the unique ID and version names, for reproducible builds.
+    ///
+    /// Those names may use {vendor}, {version}, {date}, {family}, {subfamily} and
+    /// {postscriptName} placeholders.
+    #[arg(long, default_value = "false")]
+    pub no_name_dates: bool,
+
+    /// Derive vertical metrics the source doesn't set following the Google Fonts schema.
+    ///
+    /// Typo and hhea metrics match and span at least 120% of the em, win metrics
+    /// cover every glyph, and USE_TYPO_METRICS is set.
+    /// See <https://googlefonts.github.io/gf-guide/metrics.html>.
+    #[arg(long, default_value = "false")]
+    pub google_fonts_metrics: bool,
+
+    /// Compute a basic PANOSE classification, from weight, width and whether the
+    /// font is monospaced, if the source doesn't set one. Otherwise it's all zeros.
+    #[arg(long, default_value = "false")]
+    pub compute_panose: bool,
+
+    /// Fail if fsSelection, macStyle, the italic angle and the subfamily name disagree.
+    ///
+    /// By default fsSelection wins, macStyle is made to match, and a warning is logged.
+    #[arg(long, default_value = "false")]
+    pub strict_style: bool,
+
+    /// Round the coordinates of fvar named instances to whole numbers.
+    ///
+    /// Instance locations mapped from design space may land just off the intended
+    /// value, e.g. 399.99 rather than 400.
+    #[arg(long, default_value = "false")]
+    pub round_instance_coordinates: bool,
+
+    /// Also write a legacy kern table, with the kerning at the default location.
+    ///
+    /// For consumers that read kern but not GPOS. Groups are flattened into pairs of
+    /// glyphs, so the table may be large.
+    #[arg(long, default_value = "false")]
+    pub legacy_kern: bool,
+
+    /// A directory of bitmap strikes, e.g. emoji, to add to those of the source.
+    ///
+    /// Holds a directory per ppem of PNG files named for their glyph, e.g. 64/smile.png.
+    #[arg(long)]
+    pub bitmap_strikes: Option<PathBuf>,
+
+    /// Write bitmaps as CBDT and CBLC rather than sbix.
+    ///
+    /// sbix is what Apple platforms read, CBDT what Android and Chrome do.
+    #[arg(long, default_value = "false")]
+    pub cbdt_bitmaps: bool,
+
+    /// A directory of SVG documents to add to those of the source.
+    ///
+    /// Holds an SVG file named for its glyph, e.g. smile.svg.
+    #[arg(long)]
+    pub svg_documents: Option<PathBuf>,
+
+    /// How hard to work at making gvar small.
+    #[arg(long, value_enum, default_value_t = GvarOptimization::Iup)]
+    pub gvar_optimization: GvarOptimization,
+
+    /// Use this time, in seconds since the Unix epoch, instead of the current time.
+    ///
+    /// Sets head.modified, and head.created if the source doesn't. Takes precedence
+    /// over the SOURCE_DATE_EPOCH environment variable.
+    #[arg(long, value_parser = parse_timestamp)]
+    pub timestamp: Option<i64>,
+
+    /// Build the fonts described by a project file, typically fontc.toml, instead of a source.
+    ///
+    /// A gftools builder config.yaml may also be used.
+    ///
+    /// Settings in the file take precedence over those given on the command line.
+    #[arg(long, conflicts_with_all = ["input_source", "source"])]
+    pub config: Option<PathBuf>,
+}
+
+/// How glyphs are ordered in the compiled font.
+///
+/// .notdef is always the first glyph.
+#[derive(Serialize, Deserialize, ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GlyphOrderMode {
+    /// Keep the order from the source, e.g. the glyphOrder custom parameter or lib key
+    #[default]
+    Source,
+    /// Sort glyphs by name
+    Sorted,
+    /// Put glyphs with a codepoint first, in codepoint order, then the others in source order
+    CmapFirst,
+}
+
+/// How to shrink the deltas of simple glyphs in gvar
+#[derive(Serialize, Deserialize, ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GvarOptimization {
+    /// Store every delta
+    None,
+    /// Omit deltas that IUP can infer within half a unit, as fontmake does
+    #[default]
+    Iup,
+    /// As iup, but store every delta of a tuple when that costs less than its point numbers
+    IupSharedPoints,
 }
 
 /// A wrapper around a validated regex string
@@ -111,14 +456,23 @@ pub struct Args {
 pub struct ValidatedRegex(Regex);
 
 impl Args {
+    /// Which warnings to allow or deny, from --deny-warnings, --deny and --allow
+    pub fn warning_policy(&self) -> WarningPolicy {
+        WarningPolicy {
+            deny_all: self.deny_warnings,
+            deny: self.deny.iter().cloned().collect(),
+            allow: self.allow.iter().cloned().collect(),
+        }
+    }
+
     /// Collect various relevant flags into a [`Flags`] object.
     pub fn flags(&self) -> Flags {
         let mut flags = Flags::default();
 
-        flags.set(Flags::EMIT_IR, self.emit_ir);
+        flags.set(Flags::EMIT_IR, self.emit_ir || self.low_memory);
         flags.set(Flags::EMIT_DEBUG, self.emit_debug);
         flags.set(Flags::PREFER_SIMPLE_GLYPHS, self.prefer_simple_glyphs);
-        flags.set(Flags::FLATTEN_COMPONENTS, self.flatten_components);
+        flags.set(Flags::FLATTEN_COMPONENTS, self.flatten_components.is_some());
         flags.set(
             Flags::DECOMPOSE_TRANSFORMED_COMPONENTS,
             self.decompose_transformed_components,
@@ -127,6 +481,39 @@ impl Args {
         flags.set(Flags::EMIT_TIMING, self.emit_timing);
         flags.set(Flags::KEEP_DIRECTION, self.keep_direction);
         flags.set(Flags::PRODUCTION_NAMES, !self.no_production_names);
+        flags.set(Flags::REPAIR_COMPATIBILITY, !self.no_compatibility_repair);
+        flags.set(Flags::MATCH_CONTOURS, self.match_contours);
+        flags.set(Flags::OPTIMIZE_LAYOUT, !self.no_layout_optimization);
+        flags.set(Flags::LOW_MEMORY, self.low_memory);
+        flags.set(Flags::OMIT_CMAP_FORMAT_4, self.no_cmap_format_4);
+        flags.set(Flags::CMAP_MAC_ROMAN, self.cmap_mac_roman);
+        flags.set(Flags::OMIT_NAME_DATES, self.no_name_dates);
+        flags.set(Flags::GOOGLE_FONTS_METRICS, self.google_fonts_metrics);
+        flags.set(Flags::COMPUTE_PANOSE, self.compute_panose);
+        flags.set(Flags::STRICT_STYLE, self.strict_style);
+        flags.set(Flags::LEGACY_KERN, self.legacy_kern);
+        flags.set(Flags::CBDT_BITMAPS, self.cbdt_bitmaps);
+        flags.set(Flags::LINT_FEATURES, self.lint_features);
+        flags.set(
+            Flags::FLATTEN_VARIABLE_COMPONENTS,
+            self.flatten_variable_components,
+        );
+        flags.set(
+            Flags::SUBSET_FEATURES,
+            self.keep_glyphs.is_some() || !self.keep_unicodes.is_empty(),
+        );
+        flags.set(
+            Flags::ROUND_INSTANCE_COORDINATES,
+            self.round_instance_coordinates,
+        );
+        flags.set(
+            Flags::NO_IUP,
+            self.gvar_optimization == GvarOptimization::None,
+        );
+        flags.set(
+            Flags::SHARE_GVAR_POINTS,
+            self.gvar_optimization == GvarOptimization::IupSharedPoints,
+        );
 
         flags
     }
@@ -134,23 +521,75 @@ impl Args {
     pub fn new(build_dir: &std::path::Path, input_source: PathBuf) -> Args {
         Args {
             glyph_name_filter: None,
-            input_source: Some(input_source),
+            input_source: vec![input_source],
             source: None,
             emit_ir: false,
             output_file: None,
             emit_debug: false, // they get destroyed by test cleanup
             emit_timing: false,
+            emit_trace: None,
+            report: None,
+            emit_glyph_svgs: None,
+            glyph_svg_location: Vec::new(),
+            coverage: None,
+            check: false,
+            emit_basic_ttx: Vec::new(),
+            table_sizes: false,
+            table_sizes_json: None,
+            table_sizes_baseline: None,
             build_dir: build_dir.to_path_buf(),
             prefer_simple_glyphs: Flags::default().contains(Flags::PREFER_SIMPLE_GLYPHS),
-            flatten_components: Flags::default().contains(Flags::FLATTEN_COMPONENTS),
+            flatten_components: Flags::default()
+                .contains(Flags::FLATTEN_COMPONENTS)
+                .then_some(1),
+            flatten_variable_components: Flags::default()
+                .contains(Flags::FLATTEN_VARIABLE_COMPONENTS),
             decompose_transformed_components: Flags::default()
                 .contains(Flags::DECOMPOSE_TRANSFORMED_COMPONENTS),
             decompose_components: Flags::default().contains(Flags::DECOMPOSE_COMPONENTS),
             skip_features: false,
+            lint_features: false,
             keep_direction: false,
             no_production_names: false,
+            no_compatibility_repair: false,
+            match_contours: false,
+            no_layout_optimization: false,
             verbose_version: false,
             log: None,
+            deny_warnings: false,
+            deny: Vec::new(),
+            allow: Vec::new(),
+            watch: false,
+            progress: false,
+            glyph_batch_size: None,
+            cache: false,
+            low_memory: false,
+            verify_determinism: false,
+            instances: Vec::new(),
+            axis_order: Vec::new(),
+            stat_axis_order: Vec::new(),
+            axis_limit: Vec::new(),
+            glyph_order: None,
+            glyph_order_mode: GlyphOrderMode::Source,
+            compat_glyph_order: None,
+            allow_removed_glyphs: false,
+            keep_glyphs: None,
+            keep_unicodes: Vec::new(),
+            cmap_fallback_glyph: None,
+            no_cmap_format_4: false,
+            cmap_mac_roman: false,
+            no_name_dates: false,
+            google_fonts_metrics: false,
+            compute_panose: false,
+            strict_style: false,
+            round_instance_coordinates: false,
+            legacy_kern: false,
+            bitmap_strikes: None,
+            cbdt_bitmaps: false,
+            svg_documents: None,
+            gvar_optimization: GvarOptimization::Iup,
+            timestamp: None,
+            config: None,
         }
     }
 
@@ -165,16 +604,258 @@ impl Args {
         result
     }
 
-    /// The input source to compile.
-    pub fn source(&self) -> Result<Input, Error> {
-        // safe to unwrap because clap ensures that the input_source is
+    /// The path of the input source to compile.
+    ///
+    /// If there are several, the first.
+    pub fn source_path(&self) -> &Path {
+        // safe to index because clap ensures that the input_source is
         // required_unless_present("source")
-        let path = self
-            .source
+        self.source
             .as_ref()
-            .unwrap_or_else(|| self.input_source.as_ref().unwrap());
-        Input::try_from(path.as_path())
+            .unwrap_or_else(|| &self.input_source[0])
     }
+
+    /// The paths of all the input sources to compile, with wildcards expanded.
+    pub fn source_paths(&self) -> Result<Vec<PathBuf>, Error> {
+        if let Some(source) = &self.source {
+            return Ok(vec![source.clone()]);
+        }
+        let mut paths = Vec::new();
+        for input_source in self.input_source.iter() {
+            paths.extend(expand_wildcards(input_source)?);
+        }
+        Ok(paths)
+    }
+
+    /// The first option given that reports on or checks the compiled font, if any
+    ///
+    /// These need a compile to happen, so the build cache can't be used with them.
+    pub(crate) fn post_compile_option(&self) -> Option<&'static str> {
+        [
+            ("--report", self.report.is_some()),
+            ("--emit-glyph-svgs", self.emit_glyph_svgs.is_some()),
+            ("--coverage", self.coverage.is_some()),
+            ("--check", self.check),
+            ("--emit-basic-ttx", !self.emit_basic_ttx.is_empty()),
+            (
+                "--table-sizes",
+                self.table_sizes || self.table_sizes_json.is_some(),
+            ),
+        ]
+        .into_iter()
+        .find_map(|(option, used)| used.then_some(option))
+    }
+
+    /// The files and directories other than the source that the compile reads, by option
+    ///
+    /// Every option that names an input must be listed here, or the build cache
+    /// won't notice when the input changes.
+    pub(crate) fn input_files(&self) -> Vec<(&'static str, &Path)> {
+        [
+            ("--glyph-order", &self.glyph_order),
+            ("--compat-glyph-order", &self.compat_glyph_order),
+            ("--keep-glyphs", &self.keep_glyphs),
+            ("--bitmap-strikes", &self.bitmap_strikes),
+            ("--svg-documents", &self.svg_documents),
+        ]
+        .into_iter()
+        .filter_map(|(option, path)| path.as_deref().map(|path| (option, path)))
+        .collect()
+    }
+
+    /// A copy of these args without the options that can't change the compiled font
+    ///
+    /// Paths are cleared too; the build cache hashes the content of the source and
+    /// of [Args::input_files] instead, so that it survives moving the checkout.
+    pub(crate) fn for_cache_key(&self) -> Args {
+        Args {
+            input_source: Vec::new(),
+            source: None,
+            glyph_order: None,
+            compat_glyph_order: None,
+            keep_glyphs: None,
+            bitmap_strikes: None,
+            svg_documents: None,
+            config: None,
+            // where things are written
+            emit_ir: false,
+            low_memory: false,
+            output_file: None,
+            build_dir: PathBuf::new(),
+            emit_debug: false,
+            emit_timing: false,
+            emit_trace: None,
+            // reporting, which the cache refuses, see post_compile_option
+            report: None,
+            emit_glyph_svgs: None,
+            glyph_svg_location: Vec::new(),
+            coverage: None,
+            check: false,
+            emit_basic_ttx: Vec::new(),
+            table_sizes: false,
+            table_sizes_json: None,
+            table_sizes_baseline: None,
+            // how the compile is run
+            verbose_version: false,
+            log: None,
+            watch: false,
+            progress: false,
+            glyph_batch_size: None,
+            cache: false,
+            verify_determinism: false,
+            ..self.clone()
+        }
+    }
+
+    /// A copy of these args that compiles only source, for use when compiling several.
+    ///
+    /// Each source gets its own build directory, named for the source, to hold IR
+    /// and the like. The font is written alongside rather than inside it.
+    pub fn for_one_source(&self, source: &Path) -> Args {
+        let name = source
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut args = self.clone();
+        args.source = None;
+        args.input_source = vec![source.to_path_buf()];
+        args.build_dir = self.build_dir.join(&name);
+        args.output_file = Some(self.build_dir.join(format!("{name}.ttf")));
+        args
+    }
+
+    /// The input source to compile.
+    pub fn source(&self) -> Result<Input, Error> {
+        Input::try_from(self.source_path())
+    }
+}
+
+fn parse_tag(s: &str) -> Result<Tag, String> {
+    s.parse::<Tag>().map_err(|e| e.to_string())
+}
+
+pub(crate) fn parse_basic_ttx_tag(s: &str) -> Result<Tag, String> {
+    let tag = parse_tag(s)?;
+    if !crate::ttx::SUPPORTED.contains(&tag) {
+        return Err(format!(
+            "{tag} can't be written as ttx, only {} can",
+            crate::ttx::SUPPORTED
+                .iter()
+                .map(Tag::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    Ok(tag)
+}
+
+/// Parse tag=value
+pub(crate) fn parse_axis_value(s: &str) -> Result<(Tag, UserCoord), String> {
+    let Some((tag, value)) = s.split_once('=') else {
+        return Err(format!("expected tag=value, got {s}"));
+    };
+    Ok((parse_tag(tag)?, parse_user_coord(value)?))
+}
+
+fn parse_user_coord(v: &str) -> Result<UserCoord, String> {
+    v.parse::<f64>()
+        .map(UserCoord::new)
+        .map_err(|e| format!("{v}: {e}"))
+}
+
+/// Parse tag=min:max, or tag=value to pin an axis
+pub(crate) fn parse_axis_limit(s: &str) -> Result<AxisLimit, String> {
+    let Some((tag, range)) = s.split_once('=') else {
+        return Err(format!("expected tag=min:max or tag=value, got {s}"));
+    };
+    let (min, max) = match range.split_once(':') {
+        Some((min, max)) => (parse_user_coord(min)?, parse_user_coord(max)?),
+        None => {
+            let value = parse_user_coord(range)?;
+            (value, value)
+        }
+    };
+    if min > max {
+        return Err(format!("{s} has its minimum above its maximum"));
+    }
+    Ok(AxisLimit {
+        tag: parse_tag(tag)?,
+        min,
+        max,
+    })
+}
+
+/// Parse U+start-end, or U+codepoint; the U+ is optional and values are hex
+pub(crate) fn parse_unicode_range(s: &str) -> Result<RangeInclusive<u32>, String> {
+    let parse_value = |v: &str| {
+        let v = v.trim();
+        let v = v
+            .strip_prefix("U+")
+            .or_else(|| v.strip_prefix("u+"))
+            .unwrap_or(v);
+        u32::from_str_radix(v, 16).map_err(|e| format!("{v}: {e}"))
+    };
+    let (start, end) = match s.split_once('-') {
+        Some((start, end)) => (parse_value(start)?, parse_value(end)?),
+        None => {
+            let value = parse_value(s)?;
+            (value, value)
+        }
+    };
+    if start > end {
+        return Err(format!("{s} starts after it ends"));
+    }
+    Ok(start..=end)
+}
+
+fn parse_timestamp(s: &str) -> Result<i64, String> {
+    let secs = s.parse::<i64>().map_err(|e| e.to_string())?;
+    Utc.timestamp_opt(secs, 0)
+        .single()
+        .map(|_| secs)
+        .ok_or_else(|| format!("{secs} is out of range"))
+}
+
+/// Expand * and ? in the file name of path to the matching files, in name order.
+///
+/// Paths without wildcards are returned as-is.
+fn expand_wildcards(path: &Path) -> Result<Vec<PathBuf>, Error> {
+    let Some(pattern) = path.file_name().and_then(|n| n.to_str()) else {
+        return Ok(vec![path.to_path_buf()]);
+    };
+    if !pattern.contains(['*', '?']) {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let re = pattern
+        .chars()
+        .map(|c| match c {
+            '*' => ".*".to_string(),
+            '?' => ".".to_string(),
+            c => regex::escape(&c.to_string()),
+        })
+        .collect::<String>();
+    let re = Regex::new(&format!("^{re}$")).expect("escaped pattern must be a valid regex");
+
+    let dir = path
+        .parent()
+        .filter(|p| *p != Path::new(""))
+        .unwrap_or(Path::new("."));
+    let io_err = |source| Error::FileIo {
+        path: dir.to_path_buf(),
+        source,
+    };
+    let mut matches = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(io_err)? {
+        let entry = entry.map_err(io_err)?;
+        if re.is_match(&entry.file_name().to_string_lossy()) {
+            matches.push(dir.join(entry.file_name()));
+        }
+    }
+    if matches.is_empty() {
+        return Err(Error::FileExpected(path.to_path_buf()));
+    }
+    matches.sort();
+    Ok(matches)
 }
 
 impl ValidatedRegex {
@@ -220,8 +901,13 @@ impl Serialize for ValidatedRegex {
 }
 #[cfg(test)]
 mod tests {
+    use std::{fs, path::PathBuf};
+
     use clap::Parser;
-    use fontir::orchestration::Flags;
+    use fontdrasil::coords::UserCoord;
+    use fontir::{orchestration::Flags, source::AxisLimit};
+    use tempfile::tempdir;
+    use write_fonts::types::Tag;
 
     use crate::Args;
 
@@ -238,4 +924,138 @@ mod tests {
             arg_default.bits(),
         );
     }
+
+    #[test]
+    fn flatten_components_takes_an_optional_depth() {
+        let flatten = |argv: Vec<&str>| Args::parse_from(argv).flatten_components;
+        assert_eq!(None, flatten(vec!["program", "a.glyphs"]));
+        assert_eq!(
+            Some(1),
+            flatten(vec!["program", "--flatten-components", "a.glyphs"])
+        );
+        assert_eq!(
+            Some(3),
+            flatten(vec!["program", "--flatten-components=3", "a.glyphs"])
+        );
+    }
+
+    #[test]
+    fn warning_policy() {
+        let args = Args::parse_from(vec![
+            "program",
+            "--deny-warnings",
+            "--deny",
+            "interpolation-mismatch",
+            "--allow",
+            "unknown-custom-parameter",
+            "a.glyphs",
+        ]);
+        let policy = args.warning_policy();
+        assert!(policy.deny_all);
+        assert_eq!(
+            vec!["interpolation-mismatch"],
+            policy.deny.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["unknown-custom-parameter"],
+            policy.allow.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn unknown_warning_codes_are_rejected() {
+        assert!(Args::try_parse_from(vec!["program", "--deny", "nope", "a.glyphs"]).is_err());
+    }
+
+    #[test]
+    fn axis_limits() {
+        let args = Args::parse_from(vec![
+            "program",
+            "--axis-limit",
+            "wght=300:700,wdth=100",
+            "a.glyphs",
+        ]);
+        assert_eq!(
+            vec![
+                AxisLimit {
+                    tag: Tag::new(b"wght"),
+                    min: UserCoord::new(300.0),
+                    max: UserCoord::new(700.0),
+                },
+                AxisLimit {
+                    tag: Tag::new(b"wdth"),
+                    min: UserCoord::new(100.0),
+                    max: UserCoord::new(100.0),
+                },
+            ],
+            args.axis_limit
+        );
+        assert!(
+            Args::try_parse_from(vec!["program", "--axis-limit", "wght=700:300", "a.glyphs"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn keep_unicodes() {
+        let args = Args::parse_from(vec![
+            "program",
+            "--keep-unicodes",
+            "U+0020-00FF,U+2022,41",
+            "a.glyphs",
+        ]);
+        assert_eq!(
+            vec![0x20..=0xFF, 0x2022..=0x2022, 0x41..=0x41],
+            args.keep_unicodes
+        );
+        assert!(args.flags().contains(Flags::SUBSET_FEATURES));
+        assert!(Args::try_parse_from(vec![
+            "program",
+            "--keep-unicodes",
+            "U+00FF-0020",
+            "a.glyphs"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn multiple_sources() {
+        let args = Args::parse_from(vec!["program", "a.glyphs", "b.designspace"]);
+        assert_eq!(
+            vec![PathBuf::from("a.glyphs"), PathBuf::from("b.designspace")],
+            args.source_paths().unwrap()
+        );
+        assert_eq!(PathBuf::from("a.glyphs"), args.source_path());
+    }
+
+    #[test]
+    fn source_wildcards_are_expanded() {
+        let temp_dir = tempdir().unwrap();
+        for name in ["Sans.glyphs", "Serif.glyphs", "Serif.fea"] {
+            fs::write(temp_dir.path().join(name), "").unwrap();
+        }
+        let pattern = temp_dir.path().join("*.glyphs");
+        let args = Args::parse_from(vec![
+            PathBuf::from("program"),
+            pattern,
+            temp_dir.path().join("Serif.fea"),
+        ]);
+        assert_eq!(
+            vec![
+                temp_dir.path().join("Sans.glyphs"),
+                temp_dir.path().join("Serif.glyphs"),
+                temp_dir.path().join("Serif.fea"),
+            ],
+            args.source_paths().unwrap()
+        );
+    }
+
+    #[test]
+    fn one_of_many_sources_gets_its_own_output() {
+        let args = Args::parse_from(vec!["program", "a.glyphs", "src/b.designspace"]);
+        let b = args.for_one_source(&PathBuf::from("src/b.designspace"));
+        assert_eq!(PathBuf::from("src/b.designspace"), b.source_path());
+        assert_eq!(PathBuf::from("build/b"), b.build_dir);
+        assert_eq!(Some(PathBuf::from("build/b.ttf")), b.output_file);
+    }
 }
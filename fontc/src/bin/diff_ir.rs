@@ -0,0 +1,33 @@
+//! Summarize how the IR of two builds differs, e.g. to triage a change in output.
+//!
+//! Compares build directories written with --emit-ir.
+
+use std::path::PathBuf;
+
+use clap::Parser;
+use fontir::ir_diff::diff_ir;
+
+/// Compare the IR in two build directories
+#[derive(Parser, Debug)]
+struct Args {
+    /// A build directory from fontc --emit-ir
+    a: PathBuf,
+    /// Another build directory, compared to the first
+    b: PathBuf,
+}
+
+fn main() {
+    let args = Args::parse();
+    match diff_ir(&args.a, &args.b) {
+        Ok(diff) => {
+            print!("{diff}");
+            if !diff.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    }
+}
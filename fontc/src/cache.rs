@@ -0,0 +1,254 @@
+//! A cache of compiled fonts that persists between runs.
+//!
+//! Entries are keyed by a hash of everything that goes into a compile: the
+//! content of the source and every other input file, the options that affect the
+//! output, and the version of fontc. Repeated builds of unchanged inputs, such as
+//! in CI, can then copy the previous result instead of compiling.
+//!
+//! Only the final font is cached. A compile that misses the cache starts from
+//! scratch, reusing no intermediate results.
+//!
+//! Options that report on the compile, such as --report, need it to actually
+//! happen, so the cache isn't used with them.
+
+use std::{
+    fs,
+    hash::Hasher,
+    path::{Path, PathBuf},
+};
+
+use log::debug;
+
+use crate::{watch::source_files, Args, Error};
+
+/// The name of the cache directory within the build directory
+const CACHE_DIR: &str = "cache";
+
+/// Identifies the output of a compile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CacheKey(u64);
+
+impl CacheKey {
+    /// Compute the key for the compile described by args.
+    ///
+    /// Covers every option that affects the output and the content of every file
+    /// read, see [Args::for_cache_key] and [Args::input_files].
+    pub(crate) fn new(args: &Args) -> Result<Self, Error> {
+        let mut hasher = StableHasher::default();
+        hasher.write(env!("CARGO_PKG_VERSION").as_bytes());
+        hasher.write(env!("VERGEN_GIT_SHA").as_bytes());
+        hasher.write(&serde_json::to_vec(&args.for_cache_key())?);
+        // head.created and head.modified depend on this
+        if let Ok(epoch) = std::env::var("SOURCE_DATE_EPOCH") {
+            hasher.write(epoch.as_bytes());
+        }
+        hash_files(&mut hasher, "source", args.source_path())?;
+        for (option, path) in args.input_files() {
+            hash_files(&mut hasher, option, path)?;
+        }
+        Ok(CacheKey(hasher.finish()))
+    }
+}
+
+/// Hash the name and content of the files that make up the input at root
+fn hash_files(hasher: &mut StableHasher, label: &str, root: &Path) -> Result<(), Error> {
+    hasher.write(label.as_bytes());
+    for file in source_files(root)? {
+        let content = fs::read(&file).map_err(|source| Error::FileIo {
+            path: file.clone(),
+            source,
+        })?;
+        // Relative so the cache is valid regardless of where the source is checked out
+        let name = file
+            .strip_prefix(root.parent().unwrap_or(root))
+            .unwrap_or(&file);
+        hasher.write(name.to_string_lossy().as_bytes());
+        hasher.write_u64(content.len() as u64);
+        hasher.write(&content);
+    }
+    Ok(())
+}
+
+/// Compiled fonts stored in the build directory.
+#[derive(Debug)]
+pub(crate) struct BuildCache {
+    dir: PathBuf,
+}
+
+impl BuildCache {
+    pub(crate) fn new(build_dir: &Path) -> Self {
+        BuildCache {
+            dir: build_dir.join(CACHE_DIR),
+        }
+    }
+
+    fn entry_file(&self, key: CacheKey) -> PathBuf {
+        self.dir.join(format!("{:016x}.bin", key.0))
+    }
+
+    /// The font previously stored for key, if any
+    pub(crate) fn get(&self, key: CacheKey) -> Option<Vec<u8>> {
+        let file = self.entry_file(key);
+        let font = fs::read(&file).ok();
+        debug!(
+            "Build cache {} for {file:?}",
+            if font.is_some() { "hit" } else { "miss" }
+        );
+        font
+    }
+
+    /// Store the font compiled for key
+    pub(crate) fn put(&self, key: CacheKey, font: &[u8]) -> Result<(), Error> {
+        fs::create_dir_all(&self.dir).map_err(|source| Error::FileIo {
+            path: self.dir.clone(),
+            source,
+        })?;
+        let file = self.entry_file(key);
+        fs::write(&file, font).map_err(|source| Error::FileIo { path: file, source })
+    }
+}
+
+/// FNV-1a, chosen because unlike [`std::hash::DefaultHasher`] it is guaranteed
+/// to produce the same result in every build of fontc, which matters for a key
+/// that persists on disk.
+///
+/// See <http://www.isthe.com/chongo/tech/comp/fnv/index.html>
+struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        StableHasher(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn stable_hasher_matches_reference() {
+        // Test vectors from the FNV reference implementation
+        let hash = |s: &str| {
+            let mut hasher = StableHasher::default();
+            hasher.write(s.as_bytes());
+            hasher.finish()
+        };
+        assert_eq!(0xcbf29ce484222325, hash(""));
+        assert_eq!(0xaf63dc4c8601ec8c, hash("a"));
+        assert_eq!(0x85944171f73967e8, hash("foobar"));
+    }
+
+    #[test]
+    fn key_changes_with_source() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("Test.glyphs");
+        fs::write(&source, "{}").unwrap();
+        let args = Args::new(temp_dir.path(), source.clone());
+
+        let before = CacheKey::new(&args).unwrap();
+        assert_eq!(before, CacheKey::new(&args).unwrap());
+
+        fs::write(&source, "{ unitsPerEm = 2048; }").unwrap();
+        assert_ne!(before, CacheKey::new(&args).unwrap());
+    }
+
+    #[test]
+    fn key_changes_with_options() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("Test.glyphs");
+        fs::write(&source, "{}").unwrap();
+        let args = Args::new(temp_dir.path(), source.clone());
+        let before = CacheKey::new(&args).unwrap();
+
+        let mut limited = args.clone();
+        limited.axis_limit = vec![crate::args::parse_axis_limit("wght=400").unwrap()];
+        assert_ne!(before, CacheKey::new(&limited).unwrap());
+
+        let mut stamped = args.clone();
+        stamped.timestamp = Some(0);
+        assert_ne!(before, CacheKey::new(&stamped).unwrap());
+
+        // Where things are written and how chatty we are don't matter
+        let mut cosmetic = args.clone();
+        cosmetic.log = Some("debug".to_string());
+        cosmetic.progress = true;
+        cosmetic.build_dir = temp_dir.path().join("elsewhere");
+        assert_eq!(before, CacheKey::new(&cosmetic).unwrap());
+    }
+
+    #[test]
+    fn key_changes_with_input_files() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("Test.glyphs");
+        let glyph_order = temp_dir.path().join("order.txt");
+        fs::write(&source, "{}").unwrap();
+        fs::write(&glyph_order, "a\nb\n").unwrap();
+        let mut args = Args::new(temp_dir.path(), source.clone());
+        args.glyph_order = Some(glyph_order.clone());
+
+        let before = CacheKey::new(&args).unwrap();
+        fs::write(&glyph_order, "b\na\n").unwrap();
+        assert_ne!(before, CacheKey::new(&args).unwrap());
+    }
+
+    #[test]
+    fn key_includes_masters_outside_the_designspace_directory() {
+        let temp_dir = tempdir().unwrap();
+        let ds_dir = temp_dir.path().join("ds");
+        let master_dir = temp_dir.path().join("masters").join("Test.ufo");
+        fs::create_dir_all(&ds_dir).unwrap();
+        fs::create_dir_all(&master_dir).unwrap();
+        let designspace = ds_dir.join("Test.designspace");
+        fs::write(
+            &designspace,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<designspace format="5.0">
+  <axes>
+    <axis tag="wght" name="Weight" minimum="400" maximum="400" default="400"/>
+  </axes>
+  <sources>
+    <source filename="../masters/Test.ufo" name="Regular">
+      <location>
+        <dimension name="Weight" xvalue="400"/>
+      </location>
+    </source>
+  </sources>
+</designspace>
+"#,
+        )
+        .unwrap();
+        let fontinfo = master_dir.join("fontinfo.plist");
+        fs::write(&fontinfo, "<plist/>").unwrap();
+        let args = Args::new(temp_dir.path(), designspace);
+
+        let before = CacheKey::new(&args).unwrap();
+        fs::write(&fontinfo, "<plist><dict/></plist>").unwrap();
+        assert_ne!(before, CacheKey::new(&args).unwrap());
+    }
+
+    #[test]
+    fn get_what_was_put() {
+        let temp_dir = tempdir().unwrap();
+        let cache = BuildCache::new(temp_dir.path());
+        let key = CacheKey(42);
+
+        assert_eq!(None, cache.get(key));
+        cache.put(key, &[1, 2, 3]).unwrap();
+        assert_eq!(Some(vec![1, 2, 3]), cache.get(key));
+    }
+}
@@ -0,0 +1,274 @@
+//! Fast checks of the compiled font, run with --check before it is written.
+//!
+//! Each failure carries a short code, e.g. "cmap-order", so a build script or
+//! CI job can tell failures apart and allow the ones it expects.
+
+use std::fmt::Display;
+
+use log::error;
+use write_fonts::{
+    read::{
+        tables::{cmap::CmapSubtable, head::MacStyle, os2::SelectionFlags},
+        FontRef, TableProvider,
+    },
+    types::{GlyphId16, NameId, Tag},
+};
+
+use crate::Error;
+
+/// A check the font didn't pass
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CheckFailure {
+    pub(crate) code: &'static str,
+    pub(crate) message: String,
+}
+
+impl CheckFailure {
+    fn new(code: &'static str, message: impl Into<String>) -> Self {
+        CheckFailure {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for CheckFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.code, self.message)
+    }
+}
+
+/// Run every check, logging each failure; it's an error if any fail
+pub(crate) fn check_font(font_data: &[u8]) -> Result<(), Error> {
+    let failures = run_checks(font_data)?;
+    for failure in failures.iter() {
+        error!("{failure}");
+    }
+    if !failures.is_empty() {
+        return Err(Error::ChecksFailed(failures.len()));
+    }
+    Ok(())
+}
+
+pub(crate) fn run_checks(font_data: &[u8]) -> Result<Vec<CheckFailure>, Error> {
+    let font = FontRef::new(font_data)?;
+    let mut failures = Vec::new();
+    check_table_alignment(&font, font_data.len(), &mut failures);
+    check_cmap_order(&font, &mut failures)?;
+    check_hmtx_glyf(&font, &mut failures)?;
+    check_head_os2(&font, &mut failures)?;
+    check_name_ids(&font, &mut failures)?;
+    check_mark_widths(&font, &mut failures)?;
+    Ok(failures)
+}
+
+/// Tables start on a 4-byte boundary and lie within the file
+fn check_table_alignment(font: &FontRef, file_len: usize, failures: &mut Vec<CheckFailure>) {
+    for record in font.table_directory.table_records() {
+        let (tag, offset) = (record.tag(), record.offset() as usize);
+        if offset % 4 != 0 {
+            failures.push(CheckFailure::new(
+                "table-alignment",
+                format!("'{tag}' starts at {offset}, which isn't a multiple of 4"),
+            ));
+        }
+        if offset + record.length() as usize > file_len {
+            failures.push(CheckFailure::new(
+                "table-alignment",
+                format!("'{tag}' extends past the end of the file"),
+            ));
+        }
+    }
+}
+
+/// Every cmap subtable maps codepoints in increasing order, without overlaps
+fn check_cmap_order(font: &FontRef, failures: &mut Vec<CheckFailure>) -> Result<(), Error> {
+    let cmap = font.cmap()?;
+    for record in cmap.encoding_records() {
+        let ranges: Vec<(u32, u32)> = match record.subtable(cmap.offset_data())? {
+            CmapSubtable::Format4(subtable) => subtable
+                .start_code()
+                .iter()
+                .zip(subtable.end_code())
+                .map(|(start, end)| (start.get() as u32, end.get() as u32))
+                .collect(),
+            CmapSubtable::Format12(subtable) => subtable
+                .groups()
+                .iter()
+                .map(|g| (g.start_char_code(), g.end_char_code()))
+                .collect(),
+            CmapSubtable::Format13(subtable) => subtable
+                .groups()
+                .iter()
+                .map(|g| (g.start_char_code(), g.end_char_code()))
+                .collect(),
+            _ => continue,
+        };
+        let encoding = (record.platform_id(), record.encoding_id());
+        if ranges.iter().any(|(start, end)| start > end) {
+            failures.push(CheckFailure::new(
+                "cmap-order",
+                format!("cmap subtable {encoding:?} has a range that ends before it starts"),
+            ));
+        }
+        if ranges.windows(2).any(|w| w[0].1 >= w[1].0) {
+            failures.push(CheckFailure::new(
+                "cmap-order",
+                format!("cmap subtable {encoding:?} has ranges out of order or overlapping"),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// hmtx and loca are the size the glyph count says they should be
+fn check_hmtx_glyf(font: &FontRef, failures: &mut Vec<CheckFailure>) -> Result<(), Error> {
+    let num_glyphs = font.maxp()?.num_glyphs() as usize;
+    let num_long_metrics = font.hhea()?.number_of_h_metrics() as usize;
+    if num_long_metrics == 0 || num_long_metrics > num_glyphs {
+        failures.push(CheckFailure::new(
+            "hmtx-glyf",
+            format!("hhea has {num_long_metrics} long metrics for {num_glyphs} glyphs"),
+        ));
+    } else {
+        let expected = 4 * num_long_metrics + 2 * (num_glyphs - num_long_metrics);
+        let actual = table_len(font, Tag::new(b"hmtx"));
+        if actual != Some(expected) {
+            failures.push(CheckFailure::new(
+                "hmtx-glyf",
+                format!("hmtx is {actual:?} bytes, {num_glyphs} glyphs need {expected}"),
+            ));
+        }
+    }
+
+    if let Some(actual) = table_len(font, Tag::new(b"loca")) {
+        let entry_size = match font.head()?.index_to_loc_format() {
+            0 => 2,
+            _ => 4,
+        };
+        let expected = entry_size * (num_glyphs + 1);
+        if actual != expected {
+            failures.push(CheckFailure::new(
+                "hmtx-glyf",
+                format!("loca is {actual} bytes, {num_glyphs} glyphs need {expected}"),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn table_len(font: &FontRef, tag: Tag) -> Option<usize> {
+    font.table_directory
+        .table_records()
+        .iter()
+        .find(|record| record.tag() == tag)
+        .map(|record| record.length() as usize)
+}
+
+/// head and OS/2 agree on style, and hold sensible values
+fn check_head_os2(font: &FontRef, failures: &mut Vec<CheckFailure>) -> Result<(), Error> {
+    let head = font.head()?;
+    let upem = head.units_per_em();
+    if !(16..=16384).contains(&upem) {
+        failures.push(CheckFailure::new(
+            "head-os2",
+            format!("unitsPerEm {upem} is outside 16..=16384"),
+        ));
+    }
+    let Ok(os2) = font.os2() else {
+        failures.push(CheckFailure::new("head-os2", "there is no OS/2 table"));
+        return Ok(());
+    };
+    let weight = os2.us_weight_class();
+    if !(1..=1000).contains(&weight) {
+        failures.push(CheckFailure::new(
+            "head-os2",
+            format!("usWeightClass {weight} is outside 1..=1000"),
+        ));
+    }
+    let fs_selection = os2.fs_selection();
+    let mac_style = head.mac_style();
+    for (selection, style, name) in [
+        (SelectionFlags::BOLD, MacStyle::BOLD, "bold"),
+        (SelectionFlags::ITALIC, MacStyle::ITALIC, "italic"),
+    ] {
+        if fs_selection.contains(selection) != mac_style.contains(style) {
+            failures.push(CheckFailure::new(
+                "head-os2",
+                format!(
+                    "OS/2 fsSelection {fs_selection:?} and head macStyle {mac_style:?} \
+                    disagree on {name}"
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The names every font needs are present
+fn check_name_ids(font: &FontRef, failures: &mut Vec<CheckFailure>) -> Result<(), Error> {
+    let name = font.name()?;
+    for required in [
+        NameId::FAMILY_NAME,
+        NameId::SUBFAMILY_NAME,
+        NameId::FULL_NAME,
+        NameId::POSTSCRIPT_NAME,
+    ] {
+        if !name
+            .name_record()
+            .iter()
+            .any(|record| record.name_id() == required)
+        {
+            failures.push(CheckFailure::new(
+                "name-ids",
+                format!("there is no name {}", required.to_u16()),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Glyphs GDEF classes as marks have no advance
+fn check_mark_widths(font: &FontRef, failures: &mut Vec<CheckFailure>) -> Result<(), Error> {
+    let Ok(gdef) = font.gdef() else {
+        return Ok(());
+    };
+    let Some(class_def) = gdef.glyph_class_def().transpose()? else {
+        return Ok(());
+    };
+    let hmtx = font.hmtx()?;
+    let advance = |gid: GlyphId16| {
+        let metrics = hmtx.h_metrics();
+        metrics
+            .get(gid.to_u16() as usize)
+            .or(metrics.last())
+            .map(|m| m.advance.get())
+            .unwrap_or_default()
+    };
+    let mut spacing_marks: Vec<_> = class_def
+        .iter()
+        .filter(|(gid, class)| *class == 3 && advance(*gid) != 0)
+        .map(|(gid, _)| gid.to_u16())
+        .collect();
+    spacing_marks.sort();
+    if !spacing_marks.is_empty() {
+        failures.push(CheckFailure::new(
+            "mark-width",
+            format!("marks have an advance width, glyph ids {spacing_marks:?}"),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failures_display_their_code() {
+        assert_eq!(
+            "[cmap-order] out of order",
+            CheckFailure::new("cmap-order", "out of order").to_string()
+        );
+    }
+}
@@ -0,0 +1,516 @@
+//! Project files, typically named fontc.toml, that describe how to build a family.
+//!
+//! ```toml
+//! # Relative to this file; defaults to the build directory
+//! output-dir = "fonts"
+//!
+//! # Apply to every font
+//! [options]
+//! flatten-components = true
+//!
+//! [[font]]
+//! source = "sources/MyFamily.glyphs"
+//! output = "MyFamily[wght].ttf"
+//! # Only keep these named instances
+//! instances = ["Regular", "Bold"]
+//!
+//! [[font]]
+//! source = "sources/MyFamily-Italic.glyphs"
+//!
+//! # Apply to the preceding font only
+//! [font.options]
+//! keep-direction = true
+//! ```
+//!
+//! The `config.yaml` used by the gftools builder to build Google Fonts projects
+//! can be used instead, see [`ConfigFile::load_gftools`].
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::warn;
+
+use serde::Deserialize;
+
+use write_fonts::types::Tag;
+
+use crate::{args::ValidatedRegex, Args, Error};
+
+/// The conventional name of a project file
+pub const CONFIG_FILE: &str = "fontc.toml";
+
+/// The conventional name of a gftools builder config
+pub const GFTOOLS_CONFIG_FILE: &str = "config.yaml";
+
+/// A parsed project file
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ConfigFile {
+    /// Where to write fonts, relative to the project file
+    pub output_dir: Option<PathBuf>,
+    /// Options for every font
+    #[serde(default)]
+    pub options: Options,
+    #[serde(default, rename = "font")]
+    pub fonts: Vec<FontConfig>,
+}
+
+/// `flatten-components = true` flattens entirely, a number limits nesting to that depth
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum FlattenComponents {
+    Enabled(bool),
+    Depth(u8),
+}
+
+/// A font to build
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct FontConfig {
+    /// The source, relative to the project file
+    pub source: PathBuf,
+    /// The name of the output file; by default named for the source
+    pub output: Option<String>,
+    /// Named instances to keep; by default all are
+    pub instances: Option<Vec<String>>,
+    /// Options for this font only, these take precedence over project options
+    #[serde(default)]
+    pub options: Options,
+}
+
+/// Settings that change how a font is compiled.
+///
+/// Each corresponds to a command line option of the same name.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Options {
+    /// The kinds of font to produce
+    pub formats: Option<Vec<OutputFormat>>,
+    pub prefer_simple_glyphs: Option<bool>,
+    pub flatten_components: Option<FlattenComponents>,
+    pub decompose_transformed_components: Option<bool>,
+    pub decompose_components: Option<bool>,
+    pub keep_direction: Option<bool>,
+    pub production_names: Option<bool>,
+    pub skip_features: Option<bool>,
+    pub glyph_name_filter: Option<ValidatedRegex>,
+    pub axis_order: Option<Vec<String>>,
+    pub stat_axis_order: Option<Vec<String>>,
+}
+
+/// A kind of font fontc can produce
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// A TrueType font, variable if the source is
+    Ttf,
+}
+
+impl ConfigFile {
+    /// Read the project file at path
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let raw = fs::read_to_string(path).map_err(|source| Error::FileIo {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(&raw).map_err(|message| Error::InvalidConfig {
+            path: path.to_path_buf(),
+            message,
+        })
+    }
+
+    fn parse(raw: &str) -> Result<Self, String> {
+        let config: ConfigFile = toml::from_str(raw).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Read a gftools builder config.yaml at path
+    ///
+    /// Honors `sources`, `vfDir`, `axisOrder` (as the STAT axis order),
+    /// `flattenComponents`, `decomposeTransformedComponents`,
+    /// `reverseOutlineDirection`, and `instances` (as the named instances to keep).
+    /// Other settings are ignored.
+    ///
+    /// See <https://github.com/googlefonts/gftools/blob/main/Lib/gftools/builder/schema.py>
+    pub fn load_gftools(path: &Path) -> Result<Self, Error> {
+        let raw = fs::read_to_string(path).map_err(|source| Error::FileIo {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse_gftools(&raw).map_err(|message| Error::InvalidConfig {
+            path: path.to_path_buf(),
+            message,
+        })
+    }
+
+    fn parse_gftools(raw: &str) -> Result<Self, String> {
+        let gftools: GfToolsConfig = serde_yaml::from_str(raw).map_err(|e| e.to_string())?;
+        if gftools.build_variable == Some(false) {
+            warn!("buildVariable is false but fontc only builds variable fonts");
+        }
+        let config = ConfigFile {
+            output_dir: gftools.vf_dir,
+            options: Options {
+                flatten_components: gftools.flatten_components.map(FlattenComponents::Enabled),
+                decompose_transformed_components: gftools.decompose_transformed_components,
+                keep_direction: gftools.reverse_outline_direction.map(|b| !b),
+                stat_axis_order: gftools.axis_order,
+                ..Default::default()
+            },
+            fonts: gftools
+                .sources
+                .into_iter()
+                .map(|source| {
+                    let instances = source
+                        .file_name()
+                        .and_then(|name| gftools.instances.get(&*name.to_string_lossy()))
+                        .map(|instances| {
+                            instances
+                                .iter()
+                                .filter_map(|i| i.style_name.clone())
+                                .collect()
+                        });
+                    FontConfig {
+                        source,
+                        instances,
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.fonts.is_empty() {
+            return Err("there are no fonts to build".to_string());
+        }
+        for options in std::iter::once(&self.options).chain(self.fonts.iter().map(|f| &f.options)) {
+            for tag in options
+                .axis_order
+                .iter()
+                .chain(options.stat_axis_order.iter())
+                .flatten()
+            {
+                tag.parse::<Tag>()
+                    .map_err(|e| format!("invalid axis tag '{tag}': {e}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The arguments to compile each font, starting from those given on the command line.
+    ///
+    /// config_path is the location of this project file.
+    pub fn font_args(&self, config_path: &Path, args: &Args) -> Vec<Args> {
+        let project_dir = config_path.parent().unwrap_or(Path::new(""));
+        let output_dir = self
+            .output_dir
+            .as_ref()
+            .map(|d| project_dir.join(d))
+            .unwrap_or_else(|| args.build_dir.clone());
+        self.fonts
+            .iter()
+            .map(|font| {
+                let mut font_args = args.for_one_source(&project_dir.join(&font.source));
+                // Keep the name chosen for the source unless told otherwise, but not the location
+                let name = match &font.output {
+                    Some(output) => PathBuf::from(output),
+                    None => font_args
+                        .output_file
+                        .as_ref()
+                        .and_then(|f| f.file_name())
+                        .map(PathBuf::from)
+                        .unwrap_or_default(),
+                };
+                font_args.output_file = Some(output_dir.join(name));
+                if let Some(instances) = &font.instances {
+                    font_args.instances = instances.clone();
+                }
+                self.options.apply(&mut font_args);
+                font.options.apply(&mut font_args);
+                font_args
+            })
+            .collect()
+    }
+}
+
+impl Options {
+    fn apply(&self, args: &mut Args) {
+        let set = |value: Option<bool>, field: &mut bool| {
+            if let Some(value) = value {
+                *field = value;
+            }
+        };
+        set(self.prefer_simple_glyphs, &mut args.prefer_simple_glyphs);
+        match self.flatten_components {
+            Some(FlattenComponents::Enabled(true)) => args.flatten_components = Some(1),
+            Some(FlattenComponents::Enabled(false)) => args.flatten_components = None,
+            Some(FlattenComponents::Depth(depth)) => args.flatten_components = Some(depth.max(1)),
+            None => (),
+        }
+        set(
+            self.decompose_transformed_components,
+            &mut args.decompose_transformed_components,
+        );
+        set(self.decompose_components, &mut args.decompose_components);
+        set(self.keep_direction, &mut args.keep_direction);
+        set(
+            self.production_names.map(|b| !b),
+            &mut args.no_production_names,
+        );
+        set(self.skip_features, &mut args.skip_features);
+        if let Some(filter) = &self.glyph_name_filter {
+            args.glyph_name_filter = Some(filter.clone());
+        }
+        if let Some(axis_order) = &self.axis_order {
+            // Validated on load
+            args.axis_order = axis_order
+                .iter()
+                .filter_map(|tag| tag.parse().ok())
+                .collect();
+        }
+        if let Some(stat_axis_order) = &self.stat_axis_order {
+            // Validated on load
+            args.stat_axis_order = stat_axis_order
+                .iter()
+                .filter_map(|tag| tag.parse().ok())
+                .collect();
+        }
+    }
+}
+
+/// The parts of a gftools builder config.yaml we understand
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GfToolsConfig {
+    sources: Vec<PathBuf>,
+    vf_dir: Option<PathBuf>,
+    axis_order: Option<Vec<String>>,
+    flatten_components: Option<bool>,
+    decompose_transformed_components: Option<bool>,
+    reverse_outline_direction: Option<bool>,
+    build_variable: Option<bool>,
+    /// Source file name => instances to build from it
+    #[serde(default)]
+    instances: HashMap<String, Vec<GfToolsInstance>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GfToolsInstance {
+    style_name: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    const EXAMPLE: &str = r#"
+        output-dir = "fonts"
+
+        [options]
+        flatten-components = true
+        formats = ["ttf"]
+
+        [[font]]
+        source = "sources/Family.glyphs"
+        output = "Family[wght].ttf"
+        instances = ["Regular", "Bold"]
+
+        [[font]]
+        source = "sources/Family-Italic.glyphs"
+
+        [font.options]
+        flatten-components = false
+        production-names = false
+    "#;
+
+    #[test]
+    fn parse_example() {
+        let config = ConfigFile::parse(EXAMPLE).unwrap();
+        assert_eq!(Some(PathBuf::from("fonts")), config.output_dir);
+        assert_eq!(Some(vec![OutputFormat::Ttf]), config.options.formats);
+        assert_eq!(
+            vec![
+                PathBuf::from("sources/Family.glyphs"),
+                PathBuf::from("sources/Family-Italic.glyphs")
+            ],
+            config
+                .fonts
+                .iter()
+                .map(|f| f.source.clone())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Some(FlattenComponents::Enabled(false)),
+            config.fonts[1].options.flatten_components
+        );
+    }
+
+    #[test]
+    fn flatten_components_to_depth() {
+        let config = ConfigFile::parse(
+            r#"
+            [options]
+            flatten-components = 2
+
+            [[font]]
+            source = "Family.glyphs"
+            "#,
+        )
+        .unwrap();
+        let args = Args::parse_from(vec!["program", "--config", "fontc.toml"]);
+        let font_args = config.font_args(Path::new("fontc.toml"), &args);
+        assert_eq!(Some(2), font_args[0].flatten_components);
+    }
+
+    #[test]
+    fn font_args_apply_options() {
+        let config = ConfigFile::parse(EXAMPLE).unwrap();
+        let args = Args::parse_from(vec!["program", "--config", "project/fontc.toml"]);
+        let font_args = config.font_args(Path::new("project/fontc.toml"), &args);
+
+        assert_eq!(
+            vec![
+                PathBuf::from("project/sources/Family.glyphs"),
+                PathBuf::from("project/sources/Family-Italic.glyphs"),
+            ],
+            font_args
+                .iter()
+                .map(|a| a.source_path().to_path_buf())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                Some(PathBuf::from("project/fonts/Family[wght].ttf")),
+                Some(PathBuf::from("project/fonts/Family-Italic.ttf")),
+            ],
+            font_args
+                .iter()
+                .map(|a| a.output_file.clone())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(vec!["Regular", "Bold"], font_args[0].instances);
+        assert!(font_args[1].instances.is_empty());
+        assert_eq!(Some(1), font_args[0].flatten_components);
+        assert_eq!(None, font_args[1].flatten_components);
+        assert!(!font_args[0].no_production_names);
+        assert!(font_args[1].no_production_names);
+    }
+
+    #[test]
+    fn reject_unknown_settings() {
+        let err = ConfigFile::parse(
+            r#"
+            [[font]]
+            source = "Family.glyphs"
+            flaten-components = true
+            "#,
+        )
+        .unwrap_err();
+        assert!(err.contains("flaten-components"), "{err}");
+    }
+
+    #[test]
+    fn reject_unsupported_format() {
+        let err = ConfigFile::parse(
+            r#"
+            [options]
+            formats = ["woff2"]
+
+            [[font]]
+            source = "Family.glyphs"
+            "#,
+        )
+        .unwrap_err();
+        assert!(err.contains("woff2"), "{err}");
+    }
+
+    #[test]
+    fn parse_gftools_config() {
+        let config = ConfigFile::parse_gftools(
+            r#"
+            sources:
+              - Family.glyphs
+              - Family-Italic.glyphs
+            familyName: Family
+            axisOrder:
+              - wdth
+              - wght
+            vfDir: ../fonts/variable
+            flattenComponents: false
+            reverseOutlineDirection: false
+            instances:
+              Family.glyphs:
+                - familyName: Family
+                  styleName: Bold
+                  coordinates:
+                    wght: 700
+            "#,
+        )
+        .unwrap();
+        assert_eq!(Some(PathBuf::from("../fonts/variable")), config.output_dir);
+        assert_eq!(
+            Some(vec!["wdth".to_string(), "wght".to_string()]),
+            config.options.stat_axis_order
+        );
+        assert_eq!(
+            Some(FlattenComponents::Enabled(false)),
+            config.options.flatten_components
+        );
+        assert_eq!(Some(true), config.options.keep_direction);
+        assert_eq!(
+            vec![
+                (
+                    PathBuf::from("Family.glyphs"),
+                    Some(vec!["Bold".to_string()])
+                ),
+                (PathBuf::from("Family-Italic.glyphs"), None),
+            ],
+            config
+                .fonts
+                .iter()
+                .map(|f| (f.source.clone(), f.instances.clone()))
+                .collect::<Vec<_>>()
+        );
+
+        let args = Args::parse_from(vec!["program", "--config", "sources/config.yaml"]);
+        let font_args = config.font_args(Path::new("sources/config.yaml"), &args);
+        assert_eq!(
+            vec![Tag::new(b"wdth"), Tag::new(b"wght")],
+            font_args[0].stat_axis_order
+        );
+        assert_eq!(
+            Some(PathBuf::from("sources/../fonts/variable/Family.ttf")),
+            font_args[0].output_file
+        );
+    }
+
+    #[test]
+    fn reject_bad_axis_tag() {
+        let err = ConfigFile::parse(
+            r#"
+            [options]
+            stat-axis-order = ["weight"]
+
+            [[font]]
+            source = "Family.glyphs"
+            "#,
+        )
+        .unwrap_err();
+        assert!(err.contains("weight"), "{err}");
+    }
+
+    #[test]
+    fn reject_no_fonts() {
+        assert!(ConfigFile::parse("output-dir = \"fonts\"").is_err());
+    }
+}
@@ -0,0 +1,145 @@
+//! Check the compiled font covers a required character set.
+//!
+//! Requested with --coverage, e.g. with a Google Fonts glyphset such as
+//! GF_Latin_Core.nam, so a glyph dropped by mistake is noticed at build time
+//! rather than by users.
+
+use std::{collections::BTreeSet, fmt::Display, fs, path::Path};
+
+use fontir::orchestration::{Context as FeContext, WorkId as FeWorkIdentifier};
+use log::{info, warn};
+
+use crate::Error;
+
+/// How much of a character set a font covers
+#[derive(Debug, PartialEq)]
+pub(crate) struct Coverage {
+    required: usize,
+    /// Required codepoints the font has no glyph for, ascending
+    missing: Vec<u32>,
+}
+
+impl Coverage {
+    pub(crate) fn new(fe_root: &FeContext, required: &BTreeSet<u32>) -> Self {
+        let glyph_order = fe_root.glyph_order.get();
+        let mut mapped = BTreeSet::new();
+        for name in glyph_order.names() {
+            let glyph = fe_root.glyphs.get(&FeWorkIdentifier::Glyph(name.clone()));
+            mapped.extend(glyph.codepoints.iter().copied());
+        }
+        Coverage {
+            required: required.len(),
+            missing: required.difference(&mapped).copied().collect(),
+        }
+    }
+
+    /// The missing codepoints, consecutive ones collapsed into ranges
+    fn missing_ranges(&self) -> Vec<(u32, u32)> {
+        let mut ranges: Vec<(u32, u32)> = Vec::new();
+        for cp in self.missing.iter().copied() {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == cp => *end = cp,
+                _ => ranges.push((cp, cp)),
+            }
+        }
+        ranges
+    }
+}
+
+impl Display for Coverage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let covered = self.required - self.missing.len();
+        write!(f, "covers {covered} of {} codepoints", self.required)?;
+        if self.required > 0 {
+            write!(
+                f,
+                " ({:.1}%)",
+                100.0 * covered as f64 / self.required as f64
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Report how much of the character set in path the font covers, warning about what's missing
+pub(crate) fn check_coverage(fe_root: &FeContext, path: &Path) -> Result<Coverage, Error> {
+    let raw = fs::read_to_string(path).map_err(|source| Error::FileIo {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let required = parse_charset(&raw).map_err(|message| Error::InvalidCharset {
+        path: path.to_path_buf(),
+        message,
+    })?;
+    let coverage = Coverage::new(fe_root, &required);
+    if coverage.missing.is_empty() {
+        info!("The font {coverage} in {path:?}");
+    } else {
+        let missing = coverage
+            .missing_ranges()
+            .into_iter()
+            .map(|(start, end)| {
+                if start == end {
+                    format!("U+{start:04X}")
+                } else {
+                    format!("U+{start:04X}-{end:04X}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        warn!("The font {coverage} in {path:?}, missing {missing}");
+    }
+    Ok(coverage)
+}
+
+/// Read a character set, one codepoint per line.
+///
+/// Codepoints are hex, written 0x0041, U+0041 or 0041. Anything after the
+/// codepoint, such as a character name, is ignored, as are blank lines and
+/// anything after a '#'. That reads the .nam files of Google Fonts glyphsets.
+fn parse_charset(raw: &str) -> Result<BTreeSet<u32>, String> {
+    let mut codepoints = BTreeSet::new();
+    for (i, line) in raw.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let Some(token) = line.split_whitespace().next() else {
+            continue;
+        };
+        let hex = token
+            .strip_prefix("0x")
+            .or_else(|| token.strip_prefix("U+"))
+            .unwrap_or(token);
+        let cp = u32::from_str_radix(hex, 16)
+            .map_err(|e| format!("line {}: '{token}' is not a codepoint: {e}", i + 1))?;
+        codepoints.insert(cp);
+    }
+    Ok(codepoints)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_nam_file() {
+        let raw = "# GF Latin Core\n\
+            0x0020      SPACE\n\
+            U+0041 A\n\
+            \n\
+            0042  # B\n";
+        assert_eq!(
+            BTreeSet::from([0x20, 0x41, 0x42]),
+            parse_charset(raw).unwrap()
+        );
+        assert!(parse_charset("0x0020\nSPACE\n").is_err());
+    }
+
+    #[test]
+    fn missing_collapses_to_ranges() {
+        let coverage = Coverage {
+            required: 10,
+            missing: vec![0x41, 0x42, 0x43, 0x45],
+        };
+        assert_eq!(vec![(0x41, 0x43), (0x45, 0x45)], coverage.missing_ranges());
+        assert_eq!("covers 6 of 10 codepoints (60.0%)", coverage.to_string());
+    }
+}
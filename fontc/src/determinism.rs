@@ -0,0 +1,137 @@
+//! Check that the output doesn't depend on how work happens to be scheduled.
+//!
+//! Compiling with different numbers of threads changes the order in which work
+//! completes, which flushes out things like output that depends on hash map
+//! iteration order.
+
+use log::info;
+use write_fonts::{
+    read::{FontRef, TableProvider},
+    types::Tag,
+};
+
+use crate::{Args, Error, JobTimer};
+
+/// The byte range of head.checksumAdjustment
+const HEAD_CHECKSUM_ADJUSTMENT: std::ops::Range<usize> = 8..12;
+/// The byte range of head.created and head.modified
+const HEAD_TIMESTAMPS: std::ops::Range<usize> = 20..36;
+
+/// Compile single threaded and then multi-threaded, fail if the results differ.
+///
+/// The font from the multi-threaded compile is written as usual.
+pub(crate) fn verify_determinism(args: &Args, timer: JobTimer) -> Result<(), Error> {
+    let source = args.source()?;
+    let many_threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .max(2);
+
+    let mut timer = Some(timer);
+    let mut fonts = Vec::new();
+    for n_threads in [1, many_threads] {
+        let (_, be_root, _) = crate::_generate_font(
+            &source,
+            &args.build_dir,
+            args.output_file.as_ref(),
+            args.flags(),
+            args.skip_features,
+            &args.axis_limit,
+            timer.take().unwrap_or_default(),
+            |workload| {
+                crate::configure_workload(args, workload);
+                workload.set_threads(Some(n_threads));
+            },
+        )?;
+        fonts.push(be_root);
+    }
+
+    let (single, multi) = (fonts[0].font.get(), fonts[1].font.get());
+    if let Some(table) = first_difference(single.get(), multi.get()) {
+        return Err(Error::NondeterministicOutput {
+            n_threads: (1, many_threads),
+            table,
+        });
+    }
+    info!("Output is identical with 1 and {many_threads} threads");
+    crate::write_font_file(args, &fonts[1])
+}
+
+/// The tag of the first table, in tag order, whose content differs between two fonts.
+///
+/// head timestamps, and the checksum that depends on them, are ignored as they
+/// legitimately change between compiles unless SOURCE_DATE_EPOCH is set.
+pub(crate) fn first_difference(font_a: &[u8], font_b: &[u8]) -> Option<Tag> {
+    let (Ok(a), Ok(b)) = (FontRef::new(font_a), FontRef::new(font_b)) else {
+        // No tables to speak of, report the difference against a blank tag
+        return (font_a != font_b).then_some(Tag::new(b"    "));
+    };
+    let mut tags: Vec<_> = a
+        .table_directory
+        .table_records()
+        .iter()
+        .chain(b.table_directory.table_records())
+        .map(|r| r.tag())
+        .collect();
+    tags.sort();
+    tags.dedup();
+
+    tags.into_iter().find(|tag| {
+        let data_a = a.table_data(*tag).map(|d| comparable(*tag, d.as_bytes()));
+        let data_b = b.table_data(*tag).map(|d| comparable(*tag, d.as_bytes()));
+        data_a != data_b
+    })
+}
+
+fn comparable(tag: Tag, data: &[u8]) -> Vec<u8> {
+    let mut data = data.to_vec();
+    if tag == Tag::new(b"head") && data.len() >= HEAD_TIMESTAMPS.end {
+        data[HEAD_CHECKSUM_ADJUSTMENT].fill(0);
+        data[HEAD_TIMESTAMPS].fill(0);
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use write_fonts::{
+        tables::{head::Head, maxp::Maxp},
+        types::LongDateTime,
+        FontBuilder,
+    };
+
+    use super::*;
+
+    fn font(head: Head, num_glyphs: u16) -> Vec<u8> {
+        let mut builder = FontBuilder::default();
+        builder.add_table(&head).unwrap();
+        builder
+            .add_table(&Maxp {
+                num_glyphs,
+                ..Default::default()
+            })
+            .unwrap();
+        builder.build()
+    }
+
+    #[test]
+    fn head_timestamps_are_ignored() {
+        let a = font(Head::default(), 5);
+        let b = font(
+            Head {
+                created: LongDateTime::new(42),
+                modified: LongDateTime::new(43),
+                ..Default::default()
+            },
+            5,
+        );
+        assert_eq!(None, first_difference(&a, &b));
+    }
+
+    #[test]
+    fn reports_differing_table() {
+        let a = font(Head::default(), 5);
+        let b = font(Head::default(), 6);
+        assert_eq!(Some(Tag::new(b"maxp")), first_difference(&a, &b));
+    }
+}
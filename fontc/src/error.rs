@@ -1,31 +1,521 @@
-use std::{io, path::PathBuf};
+use std::{fmt::Display, io, ops::Range, path::PathBuf};
 
+use fontdrasil::{error::ErrorCategory, types::GlyphName};
+use serde::Serialize;
 use thiserror::Error;
+use write_fonts::{read::ReadError, types::Tag};
 
+use crate::messages::{ids, Message};
+
+/// An error from fontc, or from the FE or BE it runs
+///
+/// Displayed from its [Message], so in the language of the
+/// [catalog](crate::messages::catalog).
 #[derive(Debug, Error)]
 pub enum Error {
-    #[error("'{0}' exists but is not a directory")]
     ExpectedDirectory(PathBuf),
-    #[error("io failed for '{path}': '{source}'")]
     FileIo {
         path: PathBuf,
         #[source]
         source: io::Error,
     },
-    #[error("failed to write to stdout or stderr: '{0}'")]
     StdioWriteFail(#[source] io::Error),
-    #[error("Unrecognized source {0}")]
     UnrecognizedSource(PathBuf),
-    #[error(transparent)]
     YamlSerError(#[from] serde_yaml::Error),
-    #[error(transparent)]
     FontIrError(#[from] fontir::error::Error),
-    #[error(transparent)]
     Backend(#[from] fontbe::error::Error),
-    #[error("Missing file '{0}'")]
     FileExpected(PathBuf),
-    #[error("Unable to proceed; {0} jobs stuck pending")]
     UnableToProceed(usize),
-    #[error("A task panicked: '{0}'")]
     Panic(String),
+    NondeterministicOutput {
+        n_threads: (usize, usize),
+        table: Tag,
+    },
+    UnsupportedWithMultipleSources(&'static str),
+    DuplicateSourceName(PathBuf),
+    InvalidConfig {
+        path: PathBuf,
+        message: String,
+    },
+    InvalidCharset {
+        path: PathBuf,
+        message: String,
+    },
+    ReadFont(#[from] ReadError),
+    ChecksFailed(usize),
+    DeniedWarnings(usize),
+    JsonSerError(#[from] serde_json::Error),
+    Cancelled,
+}
+
+impl Error {
+    /// The broad kind of error, for callers that want to react without parsing messages
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Error::FontIrError(e) => e.category(),
+            Error::Backend(e) => e.category(),
+            Error::ExpectedDirectory(_)
+            | Error::FileIo { .. }
+            | Error::StdioWriteFail(_)
+            | Error::FileExpected(_) => ErrorCategory::Io,
+            Error::UnrecognizedSource(_)
+            | Error::InvalidConfig { .. }
+            | Error::InvalidCharset { .. } => ErrorCategory::Parse,
+            Error::UnsupportedWithMultipleSources(_)
+            | Error::DuplicateSourceName(_)
+            | Error::ChecksFailed(_)
+            | Error::DeniedWarnings(_) => ErrorCategory::Validation,
+            Error::YamlSerError(_)
+            | Error::JsonSerError(_)
+            | Error::UnableToProceed(_)
+            | Error::Panic(_)
+            | Error::NondeterministicOutput { .. }
+            | Error::ReadFont(_) => ErrorCategory::Internal,
+            Error::Cancelled => ErrorCategory::Cancelled,
+        }
+    }
+
+    /// The glyph the error is about, if any
+    pub fn glyph(&self) -> Option<&GlyphName> {
+        match self {
+            Error::FontIrError(e) => e.glyph(),
+            Error::Backend(e) => e.glyph(),
+            _ => None,
+        }
+    }
+
+    /// Where in the sources the error is, currently only known for feature errors
+    pub fn spans(&self) -> Vec<SourceSpan> {
+        let Error::Backend(fontbe::error::Error::FeaCompileError(e)) = self else {
+            return Vec::new();
+        };
+        let Some(diagnostics) = e.diagnostics() else {
+            return Vec::new();
+        };
+        diagnostics
+            .diagnostics()
+            .iter()
+            .filter(|diagnostic| diagnostic.is_error())
+            .filter_map(|diagnostic| {
+                let (path, line, column) = diagnostics.location(diagnostic)?;
+                Some(SourceSpan {
+                    path: path.to_path_buf(),
+                    line,
+                    column,
+                    range: diagnostic.span(),
+                    message: diagnostic.text().to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// The error as a [Message], to display it with a [MessageCatalog](crate::messages::MessageCatalog)
+    pub fn message(&self) -> Message {
+        match self {
+            Error::ExpectedDirectory(path) => {
+                Message::new(ids::EXPECTED_DIRECTORY).with("path", path.display())
+            }
+            Error::FileIo { path, source } => Message::new(ids::FILE_IO)
+                .with("path", path.display())
+                .with("source", source),
+            Error::StdioWriteFail(source) => {
+                Message::new(ids::STDIO_WRITE_FAILED).with("source", source)
+            }
+            Error::UnrecognizedSource(path) => {
+                Message::new(ids::UNRECOGNIZED_SOURCE).with("path", path.display())
+            }
+            Error::YamlSerError(e) => Message::new(ids::YAML_ERROR).with("detail", e),
+            Error::JsonSerError(e) => Message::new(ids::JSON_ERROR).with("detail", e),
+            Error::FontIrError(e) => fontir_message(e),
+            Error::Backend(e) => fontbe_message(e),
+            Error::FileExpected(path) => {
+                Message::new(ids::FILE_EXPECTED).with("path", path.display())
+            }
+            Error::UnableToProceed(count) => {
+                Message::new(ids::UNABLE_TO_PROCEED).with("count", count)
+            }
+            Error::Panic(detail) => Message::new(ids::PANIC).with("detail", detail),
+            Error::NondeterministicOutput { n_threads, table } => {
+                Message::new(ids::NONDETERMINISTIC_OUTPUT)
+                    .with("threads", n_threads.0)
+                    .with("other_threads", n_threads.1)
+                    .with("table", table)
+            }
+            Error::UnsupportedWithMultipleSources(option) => {
+                Message::new(ids::UNSUPPORTED_WITH_MULTIPLE_SOURCES).with("option", option)
+            }
+            Error::DuplicateSourceName(path) => {
+                Message::new(ids::DUPLICATE_SOURCE_NAME).with("path", path.display())
+            }
+            Error::InvalidConfig { path, message } => Message::new(ids::INVALID_CONFIG)
+                .with("path", path.display())
+                .with("detail", message),
+            Error::InvalidCharset { path, message } => Message::new(ids::INVALID_CHARSET)
+                .with("path", path.display())
+                .with("detail", message),
+            Error::ReadFont(e) => Message::new(ids::READ_FONT_FAILED).with("detail", e),
+            Error::ChecksFailed(count) => Message::new(ids::CHECKS_FAILED).with("count", count),
+            Error::DeniedWarnings(count) => Message::new(ids::DENIED_WARNINGS).with("count", count),
+            Error::Cancelled => Message::new(ids::CANCELLED),
+        }
+    }
+
+    /// Everything a programmatic consumer, such as an editor, might want to know
+    pub fn detail(&self) -> ErrorDetail {
+        ErrorDetail {
+            category: self.category(),
+            message_id: self.message().id(),
+            message: self.to_string(),
+            glyph: self.glyph().cloned(),
+            spans: self.spans(),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.message().fmt(f)
+    }
+}
+
+fn fontir_message(error: &fontir::error::Error) -> Message {
+    use fontir::error::Error;
+    match error {
+        Error::BadSource(e) => Message::new(ids::BAD_SOURCE)
+            .with("path", e.path().display())
+            .with("reason", &e.kind),
+        Error::NoSuchPath(path) => Message::new(ids::NO_SUCH_PATH).with("path", path.display()),
+        Error::BadGlyph(e) => Message::new(ids::BAD_GLYPH)
+            .with("glyph", e.name())
+            .with("reason", e.kind()),
+        Error::DeleteFailed { path, source } => Message::new(ids::DELETE_FAILED)
+            .with("path", path.display())
+            .with("source", source),
+        Error::InvalidUpem(upem) => Message::new(ids::INVALID_UPEM).with("upem", upem),
+        Error::InconsistentUpem(values) => {
+            Message::new(ids::INCONSISTENT_UPEM).with("values", format_args!("{values:?}"))
+        }
+        Error::VariationModelError(e) => {
+            Message::new(ids::VARIATION_MODEL_FAILED).with("detail", e)
+        }
+        Error::NonIdenticalFea(path, other_path) => Message::new(ids::NON_IDENTICAL_FEA)
+            .with("path", path.display())
+            .with("other_path", other_path.display()),
+        Error::MissingAxisMapping(axis) => {
+            Message::new(ids::MISSING_AXIS_MAPPING).with("axis", axis)
+        }
+        Error::NoGlyphForName(glyph) => Message::new(ids::NO_GLYPH_FOR_NAME).with("glyph", glyph),
+        Error::NoAxisDefinitions(what) => Message::new(ids::NO_AXIS_DEFINITIONS).with("what", what),
+        Error::NoEntryInAxes(axis) => Message::new(ids::NO_ENTRY_IN_AXES).with("axis", axis),
+        Error::InconsistentAxisDefinitions(detail) => {
+            Message::new(ids::INCONSISTENT_AXIS_DEFINITIONS).with("detail", detail)
+        }
+        Error::NoSuchLayer { ufo, layer } => Message::new(ids::NO_SUCH_LAYER)
+            .with("ufo", ufo)
+            .with("layer", layer),
+        Error::NoStateForGlyph(glyph) => Message::new(ids::NO_STATE_FOR_GLYPH).with("glyph", glyph),
+        Error::NoLocationsForGlyph(glyph) => {
+            Message::new(ids::NO_LOCATIONS_FOR_GLYPH).with("glyph", glyph)
+        }
+        Error::UnableToCreateGlyphIrWork => Message::new(ids::UNABLE_TO_CREATE_GLYPH_IR_WORK),
+        Error::UnexpectedState => Message::new(ids::UNEXPECTED_STATE),
+        Error::DuplicateUserLocation { what, loc } => Message::new(ids::DUPLICATE_USER_LOCATION)
+            .with("what", what)
+            .with("location", format_args!("{loc:?}")),
+        Error::InvalidGlobalMetadata => Message::new(ids::INVALID_GLOBAL_METADATA),
+        Error::NoDefaultMaster(path) => {
+            Message::new(ids::NO_DEFAULT_MASTER).with("path", path.display())
+        }
+        Error::MissingMappingForDesignCoord {
+            axis_name,
+            field,
+            mappings,
+            value,
+        } => Message::new(ids::MISSING_MAPPING_FOR_DESIGN_COORD)
+            .with("axis", axis_name)
+            .with("field", field)
+            .with("value", format_args!("{value:?}"))
+            .with("mappings", format_args!("{mappings:?}")),
+        Error::InvalidTag { raw_tag, cause } => Message::new(ids::INVALID_TAG)
+            .with("tag", raw_tag)
+            .with("detail", cause),
+        Error::UnsupportedConstruct(construct) => {
+            Message::new(ids::UNSUPPORTED_CONSTRUCT).with("construct", construct)
+        }
+        Error::InconsistentPaletteLength { size_0, n, size_n } => {
+            Message::new(ids::INCONSISTENT_PALETTE_SIZE)
+                .with("size", size_0)
+                .with("palette", n)
+                .with("other_size", size_n)
+        }
+        Error::UnknownEntry(what, value) => Message::new(ids::UNKNOWN_ENTRY)
+            .with("what", what)
+            .with("value", value),
+        Error::InvalidEntry(what, detail) => Message::new(ids::INVALID_ENTRY)
+            .with("what", what)
+            .with("detail", detail),
+        Error::IncompatibleSources(e) => Message::new(ids::INCOMPATIBLE_SOURCES).with("detail", e),
+        Error::OverlappingKerningGroups(glyphs) => {
+            Message::new(ids::OVERLAPPING_KERNING_GROUPS).with("glyphs", glyphs.join(", "))
+        }
+        Error::VariableComponentTransforms(glyphs) => {
+            Message::new(ids::VARIABLE_COMPONENT_TRANSFORMS)
+                .with("glyphs", format_args!("{glyphs:?}"))
+        }
+        Error::InconsistentComponents(glyphs) => {
+            Message::new(ids::INCONSISTENT_COMPONENTS).with("glyphs", format_args!("{glyphs:?}"))
+        }
+        Error::Cancelled => Message::new(ids::CANCELLED),
+    }
+}
+
+fn fontbe_message(error: &fontbe::error::Error) -> Message {
+    use fontbe::error::Error;
+    match error {
+        Error::IoError(_) => Message::new(ids::IO_FAILED),
+        Error::FeaCompileError(e) => Message::new(ids::FEATURE_COMPILE_FAILED).with("detail", e),
+        Error::GlyphError(glyph, problem) => Message::new(ids::GLYPH_PROBLEM)
+            .with("glyph", glyph)
+            .with("problem", problem),
+        Error::KurboError {
+            glyph_name,
+            kurbo_problem,
+            context,
+        } => Message::new(ids::MALFORMED_PATH)
+            .with("glyph", glyph_name)
+            .with("problem", format_args!("{kurbo_problem:?}"))
+            .with("context", context),
+        Error::ComponentError {
+            glyph,
+            referenced_glyph,
+            problem,
+        } => Message::new(ids::COMPONENT_PROBLEM)
+            .with("glyph", glyph)
+            .with("component", referenced_glyph)
+            .with("problem", problem),
+        Error::ComponentErrors { glyph, errors } => Message::new(ids::COMPONENT_PROBLEMS)
+            .with("glyph", glyph)
+            .with("problems", format_args!("{errors:?}")),
+        Error::DumpTableError { e, context } => Message::new(ids::DUMP_TABLE_FAILED)
+            .with("context", context)
+            .with("detail", e),
+        Error::OutOfBounds { what, value } => Message::new(ids::OUT_OF_BOUNDS)
+            .with("what", what)
+            .with("value", value),
+        Error::GlyphDeltaError(glyph, e) => Message::new(ids::GLYPH_DELTAS_FAILED)
+            .with("glyph", glyph)
+            .with("detail", e),
+        Error::MvarDeltaError(tag, e) => Message::new(ids::MVAR_DELTAS_FAILED)
+            .with("tag", tag)
+            .with("detail", e),
+        Error::AvarModelError(e) => Message::new(ids::AVAR_MODEL_FAILED).with("detail", e),
+        Error::AvarDeltaError(e) => Message::new(ids::AVAR_DELTAS_FAILED).with("detail", e),
+        Error::AnchorDeltaError(glyph, e) => Message::new(ids::ANCHOR_DELTAS_FAILED)
+            .with("glyph", glyph)
+            .with("detail", e),
+        Error::ColrDeltaError(glyph, e) => Message::new(ids::GRADIENT_DELTAS_FAILED)
+            .with("glyph", glyph)
+            .with("detail", e),
+        Error::KernDeltaError { pair, error } => Message::new(ids::KERN_DELTAS_FAILED)
+            .with("side1", &pair.0)
+            .with("side2", &pair.1)
+            .with("detail", error),
+        Error::GvarError(_) => Message::new(ids::GVAR_FAILED),
+        Error::ReadFontsReadError(_) => Message::new(ids::READ_FAILED),
+        Error::IupError(glyph, e) => Message::new(ids::IUP_FAILED)
+            .with("glyph", glyph)
+            .with("detail", format_args!("{e:?}")),
+        Error::InvalidTableBytes(tag) => Message::new(ids::INVALID_TABLE_BYTES).with("tag", tag),
+        Error::MissingDirectory(path) => {
+            Message::new(ids::MISSING_DIRECTORY).with("path", path.display())
+        }
+        Error::VariationModelError(glyph, e) => Message::new(ids::GLYPH_VARIATION_MODEL_FAILED)
+            .with("glyph", glyph)
+            .with("detail", e),
+        Error::FileExpected(path) => Message::new(ids::FILE_EXPECTED).with("path", path.display()),
+        Error::MissingTable(tag) => Message::new(ids::MISSING_TABLE).with("tag", tag),
+        Error::IncompletePatch(tag, other_tag) => Message::new(ids::INCOMPLETE_PATCH)
+            .with("tag", tag)
+            .with("other_tag", other_tag),
+        Error::ExpectedAnchor(work) => {
+            Message::new(ids::EXPECTED_ANCHOR).with("work", format_args!("{work:?}"))
+        }
+        Error::MissingGlyphClass(glyph) => {
+            Message::new(ids::MISSING_GLYPH_CLASS).with("glyph", glyph)
+        }
+        Error::PreviouslyAssignedMarkClass {
+            old_class,
+            new_class,
+            glyph,
+        } => Message::new(ids::CONFLICTING_MARK_CLASSES)
+            .with("glyph", glyph)
+            .with("class", old_class)
+            .with("other_class", new_class),
+        Error::NoVariationModel(location) => {
+            Message::new(ids::NO_VARIATION_MODEL).with("location", format_args!("{location:?}"))
+        }
+        Error::DeltaError(e) => {
+            Message::new(ids::DELTAS_FAILED).with("detail", format_args!("{e:?}"))
+        }
+        Error::MissingGlyphId(glyph) => Message::new(ids::MISSING_GLYPH_ID).with("glyph", glyph),
+        Error::CmapConflict(e) => Message::new(ids::CMAP_CONFLICT).with("detail", e),
+        Error::InvalidCmap(detail) => Message::new(ids::INVALID_CMAP).with("detail", detail),
+        Error::InconsistentStyle(detail) => {
+            Message::new(ids::INCONSISTENT_STYLE).with("detail", detail)
+        }
+        Error::CompositesStalled(glyphs) => {
+            Message::new(ids::COMPOSITES_STALLED).with("glyphs", format_args!("{glyphs:?}"))
+        }
+        Error::InconsistentPaletteLength(lengths) => {
+            Message::new(ids::INCONSISTENT_PALETTE_LENGTHS)
+                .with("lengths", format_args!("{lengths:?}"))
+        }
+        Error::OverlappingSvgDocuments(range, other_range) => {
+            Message::new(ids::OVERLAPPING_SVG_DOCUMENTS)
+                .with("range", format_args!("{range:?}"))
+                .with("other_range", format_args!("{other_range:?}"))
+        }
+        Error::Cancelled => Message::new(ids::CANCELLED),
+    }
+}
+
+/// A structured description of an [Error]
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ErrorDetail {
+    pub category: ErrorCategory,
+    /// The stable id of the message, see [crate::messages]
+    pub message_id: &'static str,
+    pub message: String,
+    pub glyph: Option<GlyphName>,
+    pub spans: Vec<SourceSpan>,
+}
+
+/// A location in a source file, with what went wrong there
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SourceSpan {
+    pub path: PathBuf,
+    /// 1-indexed
+    pub line: usize,
+    /// 0-indexed utf-8 offset into the line
+    pub column: usize,
+    /// The utf-8 byte range in the file
+    pub range: Range<usize>,
+    pub message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use fea_rs::compile::error::CompilerError;
+    use fontdrasil::coords::NormalizedLocation;
+    use fontir::error::{BadGlyph, BadGlyphKind};
+
+    use super::*;
+
+    /// The English templates of FE and BE errors read as those errors display
+    #[test]
+    fn english_messages_match_fe_and_be_display() {
+        use fontbe::error::{Error as BeError, GlyphProblem};
+        use fontir::error::Error as FeError;
+        use write_fonts::types::GlyphId16;
+
+        let fe_errors = [
+            FeError::NoSuchPath(PathBuf::from("a.glyphs")),
+            BadGlyph::new("A", BadGlyphKind::NoDefaultLocation).into(),
+            FeError::InconsistentUpem(vec![1000, 2048]),
+            FeError::NoSuchLayer {
+                ufo: "a.ufo".to_string(),
+                layer: "public.background".to_string(),
+            },
+            FeError::InconsistentPaletteLength {
+                size_0: 2,
+                n: 1,
+                size_n: 3,
+            },
+            FeError::InvalidEntry("axis limit", "wght=100:200".to_string()),
+            FeError::OverlappingKerningGroups(vec!["A".to_string(), "B".to_string()]),
+            FeError::VariableComponentTransforms(vec![GlyphName::new("A")]),
+            FeError::InconsistentComponents(vec![GlyphName::new("A")]),
+        ];
+        let be_errors = [
+            BeError::GlyphError(GlyphName::new("A"), GlyphProblem::NoComponents),
+            BeError::ComponentError {
+                glyph: GlyphName::new("Aacute"),
+                referenced_glyph: GlyphName::new("acute"),
+                problem: GlyphProblem::MissingDefault,
+            },
+            BeError::OutOfBounds {
+                what: "advance".to_string(),
+                value: "70000".to_string(),
+            },
+            BeError::MissingDirectory(PathBuf::from("build")),
+            BeError::IncompletePatch(Tag::new(b"GSUB"), Tag::new(b"GDEF")),
+            BeError::PreviouslyAssignedMarkClass {
+                old_class: "top".into(),
+                new_class: "bottom".into(),
+                glyph: GlyphName::new("acutecomb"),
+            },
+            BeError::NoVariationModel(NormalizedLocation::new()),
+            BeError::OverlappingSvgDocuments(
+                GlyphId16::new(1)..=GlyphId16::new(3),
+                GlyphId16::new(2)..=GlyphId16::new(4),
+            ),
+        ];
+        let expected = fe_errors
+            .iter()
+            .map(ToString::to_string)
+            .chain(be_errors.iter().map(ToString::to_string))
+            .collect::<Vec<_>>();
+        let english = fe_errors
+            .into_iter()
+            .map(Error::from)
+            .chain(be_errors.into_iter().map(Error::from))
+            .map(|error| error.message().render(&crate::messages::English))
+            .collect::<Vec<_>>();
+        assert_eq!(expected, english);
+    }
+
+    #[test]
+    fn interpolation_error_names_glyph() {
+        let error = Error::FontIrError(
+            BadGlyph::new(
+                "A",
+                BadGlyphKind::NotInterpolatable(NormalizedLocation::new()),
+            )
+            .into(),
+        );
+        let detail = error.detail();
+        assert_eq!(
+            (ErrorCategory::Interpolation, Some(GlyphName::new("A"))),
+            (detail.category, detail.glyph.clone())
+        );
+        assert!(detail.spans.is_empty());
+        assert_eq!(
+            "interpolation",
+            serde_json::to_value(&detail).unwrap()["category"]
+        );
+    }
+
+    #[test]
+    fn feature_error_has_spans() {
+        let (_, diagnostics) =
+            fea_rs::parse::parse_string("languagesystem DFLT dflt;\nbogus_statement;\n");
+        let error = Error::Backend(fontbe::error::Error::FeaCompileError(
+            CompilerError::ParseFail(diagnostics),
+        ));
+
+        assert_eq!(ErrorCategory::FeatureCompile, error.category());
+        let spans = error.spans();
+        assert!(!spans.is_empty());
+        assert_eq!(
+            (PathBuf::from("parse::parse_string"), 2),
+            (spans[0].path.clone(), spans[0].line)
+        );
+    }
+
+    #[test]
+    fn io_errors_are_io() {
+        let error = Error::FileExpected(PathBuf::from("nope.glyphs"));
+        assert_eq!(ErrorCategory::Io, error.category());
+        assert_eq!(None, error.glyph());
+    }
 }
@@ -0,0 +1,173 @@
+//! Draw compiled glyphs as svg.
+//!
+//! Written with --emit-glyph-svgs, one file per glyph per location it has a
+//! source at, or at the --glyph-svg-location only. Glyphs are drawn from IR once
+//! components are resolved, so a flipped component or a dropped contour looks the
+//! way it will in the font.
+
+use std::{fmt::Write, fs, path::Path};
+
+use fontdrasil::{
+    coords::{NormalizedLocation, UserCoord},
+    paths::string_to_filename,
+};
+use fontir::{
+    glyph::instance_at,
+    ir::GlyphInstance,
+    orchestration::{Context as FeContext, WorkId as FeWorkIdentifier},
+};
+use kurbo::{Affine, BezPath, Shape};
+use write_fonts::types::Tag;
+
+use crate::Error;
+
+/// Write an svg for every glyph source into dir, or for every glyph at `at` if it's not empty
+pub(crate) fn write_glyph_svgs(
+    fe_root: &FeContext,
+    dir: &Path,
+    at: &[(Tag, UserCoord)],
+) -> Result<(), Error> {
+    fs::create_dir_all(dir).map_err(|source| Error::FileIo {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+    let at = if at.is_empty() {
+        None
+    } else {
+        Some(normalized_location(fe_root, at)?)
+    };
+    let glyph_order = fe_root.glyph_order.get();
+    for name in glyph_order.names() {
+        let glyph = fe_root.glyphs.get(&FeWorkIdentifier::Glyph(name.clone()));
+        let locations: Vec<_> = match &at {
+            Some(at) => vec![at.clone()],
+            None => glyph.sources().keys().cloned().collect(),
+        };
+        for location in locations.iter() {
+            let instance =
+                instance_at(fe_root, &glyph, location).map_err(fontir::error::Error::from)?;
+            let mut outline = BezPath::new();
+            draw(fe_root, &instance, location, Affine::IDENTITY, &mut outline)?;
+            let path = dir.join(string_to_filename(
+                name.as_str(),
+                &format!("{}.svg", location_suffix(location)),
+            ));
+            fs::write(&path, to_svg(&outline, instance.width))
+                .map_err(|source| Error::FileIo { path, source })?;
+        }
+    }
+    Ok(())
+}
+
+/// The normalized location of `at`, the axes it leaves out being at their default
+fn normalized_location(
+    fe_root: &FeContext,
+    at: &[(Tag, UserCoord)],
+) -> Result<NormalizedLocation, Error> {
+    let static_metadata = fe_root.static_metadata.get();
+    let axes = &static_metadata.axes;
+    if let Some((tag, _)) = at.iter().find(|(tag, _)| axes.get(tag).is_none()) {
+        return Err(fontir::error::Error::InvalidEntry(
+            "glyph svg location",
+            format!("the font has no {tag} axis"),
+        )
+        .into());
+    }
+    Ok(axes
+        .iter()
+        .map(|axis| {
+            let value = at
+                .iter()
+                .find_map(|(tag, value)| (*tag == axis.tag).then_some(*value))
+                .unwrap_or(axis.default);
+            (axis.tag, value.to_normalized(&axis.converter))
+        })
+        .collect())
+}
+
+/// Add the outline of instance, and of its components, to outline
+fn draw(
+    fe_root: &FeContext,
+    instance: &GlyphInstance,
+    location: &NormalizedLocation,
+    transform: Affine,
+    outline: &mut BezPath,
+) -> Result<(), Error> {
+    for contour in instance.contours.iter() {
+        outline.extend((transform * contour.clone()).elements().iter().copied());
+    }
+    for component in instance.components.iter() {
+        let base = fe_root
+            .glyphs
+            .get(&FeWorkIdentifier::Glyph(component.base.clone()));
+        // a component may be sparser than the glyph that uses it, interpolate it if so
+        let base_instance =
+            instance_at(fe_root, &base, location).map_err(fontir::error::Error::from)?;
+        draw(
+            fe_root,
+            &base_instance,
+            location,
+            transform * component.transform,
+            outline,
+        )?;
+    }
+    Ok(())
+}
+
+/// e.g. "_wght_1.00", nothing at the default location
+fn location_suffix(location: &NormalizedLocation) -> String {
+    location
+        .iter()
+        .filter(|(_, pos)| pos.to_f64() != 0.0)
+        .map(|(tag, pos)| format!("_{tag}_{:.2}", pos.to_f64()))
+        .collect()
+}
+
+/// The outline, y up, with a box showing the advance
+fn to_svg(outline: &BezPath, advance: f64) -> String {
+    let bounds = outline
+        .bounding_box()
+        .union(kurbo::Rect::new(0.0, 0.0, advance, 0.0));
+    let margin = 0.05 * bounds.width().max(bounds.height()).max(1.0);
+    let view = bounds.inflate(margin, margin);
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#,
+        view.x0,
+        -view.y1,
+        view.width(),
+        view.height()
+    )
+    .unwrap();
+    writeln!(svg, r#"<g transform="scale(1 -1)">"#).unwrap();
+    writeln!(
+        svg,
+        r#"<rect x="0" y="{}" width="{advance}" height="{}" fill="none" stroke="#aaa" stroke-width="{}"/>"#,
+        view.y0,
+        view.height(),
+        margin / 10.0
+    )
+    .unwrap();
+    writeln!(svg, r#"<path d="{}"/>"#, outline.to_svg()).unwrap();
+    writeln!(svg, "</g>").unwrap();
+    writeln!(svg, "</svg>").unwrap();
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_location_has_no_suffix() {
+        assert_eq!(
+            "",
+            location_suffix(&NormalizedLocation::for_pos(&[("wght", 0.0)]))
+        );
+        assert_eq!(
+            "_wght_1.00",
+            location_suffix(&NormalizedLocation::for_pos(&[("wght", 1.0)]))
+        );
+    }
+}
@@ -0,0 +1,72 @@
+//! Carry results from one build over to the next.
+//!
+//! Used by watch mode so editing a single glyph doesn't recompile every glyph.
+
+use std::{collections::HashMap, sync::Arc};
+
+use fontbe::orchestration::{
+    AnyWorkId, Context as BeContext, Glyph, GvarFragment, WorkId as BeWorkIdentifier,
+};
+use fontdrasil::types::GlyphName;
+use fontir::{
+    ir::{self, StaticMetadata},
+    orchestration::Context as FeContext,
+};
+
+/// What a previous build produced, used to skip work whose inputs are unchanged.
+pub(crate) struct PreviousBuild {
+    static_metadata: Arc<StaticMetadata>,
+    glyph_hashes: HashMap<GlyphName, u64>,
+    be_root: BeContext,
+}
+
+impl PreviousBuild {
+    /// Capture the state of a successful build.
+    pub(crate) fn new(fe_root: &FeContext, be_root: BeContext) -> Self {
+        let glyph_hashes = fe_root
+            .glyphs
+            .all()
+            .into_iter()
+            .map(|(_, glyph)| (glyph.name.clone(), glyph.content_hash()))
+            .collect();
+        PreviousBuild {
+            static_metadata: fe_root.static_metadata.get(),
+            glyph_hashes,
+            be_root,
+        }
+    }
+
+    /// The fragments built for glyph last time, if they are still valid.
+    ///
+    /// Only glyphs without components are considered. Composites refer to
+    /// other glyphs by id, so they depend on the glyph order and on the glyphs
+    /// they reference; they are also cheap to rebuild.
+    pub(crate) fn reusable_glyph(
+        &self,
+        static_metadata: &StaticMetadata,
+        glyph: &ir::Glyph,
+    ) -> Option<(Arc<Glyph>, Arc<GvarFragment>)> {
+        if *self.static_metadata != *static_metadata
+            || glyph
+                .sources()
+                .values()
+                .any(|inst| !inst.components.is_empty())
+            || self.glyph_hashes.get(&glyph.name) != Some(&glyph.content_hash())
+        {
+            return None;
+        }
+        let be_glyph =
+            self.be_root
+                .glyphs
+                .try_get(&AnyWorkId::Be(BeWorkIdentifier::GlyfFragment(
+                    glyph.name.clone(),
+                )))?;
+        let gvar_fragment =
+            self.be_root
+                .gvar_fragments
+                .try_get(&AnyWorkId::Be(BeWorkIdentifier::GvarFragment(
+                    glyph.name.clone(),
+                )))?;
+        Some((be_glyph, gvar_fragment))
+    }
+}
@@ -2,14 +2,39 @@
 
 #[cfg(feature = "cli")]
 mod args;
+#[cfg(feature = "cli")]
+mod cache;
+#[cfg(feature = "cli")]
+mod checks;
+#[cfg(feature = "cli")]
+pub mod config_file;
+#[cfg(feature = "cli")]
+mod coverage;
+#[cfg(feature = "cli")]
+mod determinism;
 mod error;
+#[cfg(feature = "cli")]
+mod glyph_svg;
+mod incremental;
+pub mod messages;
+pub mod progress;
+#[cfg(feature = "cli")]
+mod report;
+#[cfg(feature = "cli")]
+mod table_sizes;
 mod timing;
+#[cfg(feature = "cli")]
+mod ttx;
+#[cfg(feature = "cli")]
+mod watch;
 pub mod work;
 mod workload;
 
 #[cfg(feature = "cli")]
-pub use args::Args;
-pub use error::Error;
+pub use args::{Args, GlyphOrderMode, GvarOptimization};
+pub use error::{Error, ErrorDetail, SourceSpan};
+pub use fontbe::features::{CustomFeatureWriter, FeatureBuilder, PendingLookup};
+pub use fontdrasil::{error::ErrorCategory, orchestration::CancellationToken};
 
 pub use fontir::orchestration::Flags; // Re-export for library users
 use fontra2fontir::source::FontraIrSource;
@@ -23,9 +48,14 @@ use std::{
     ffi::OsStr,
     fs,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
-use fontir::{orchestration::Context as FeContext, source::Source};
+use fontir::{
+    ir_source::IrSource,
+    orchestration::Context as FeContext,
+    source::{AxisLimit, Source},
+};
 
 use fontbe::{orchestration::Context as BeContext, paths::Paths as BePaths};
 use fontir::paths::Paths as IrPaths;
@@ -41,6 +71,8 @@ pub enum Input {
     GlyphsPath(PathBuf),
     FontraPath(PathBuf),
     GlyphsMemory(String),
+    /// A directory of IR written by a previous build with --emit-ir
+    IrPath(PathBuf),
 }
 
 impl Input {
@@ -48,6 +80,9 @@ impl Input {
         if !path.exists() {
             return Err(Error::FileExpected(path.to_path_buf()));
         }
+        if path.is_dir() && IrSource::is_ir_dir(path) {
+            return Ok(Input::IrPath(path.to_path_buf()));
+        }
         let ext = path
             .extension()
             .and_then(OsStr::to_str)
@@ -74,6 +109,7 @@ impl Input {
             Input::GlyphsPath(path) => Ok(Box::new(GlyphsIrSource::new(path)?)),
             Input::FontraPath(path) => Ok(Box::new(FontraIrSource::new(path)?)),
             Input::GlyphsMemory(source) => Ok(Box::new(GlyphsIrSource::new_from_memory(source)?)),
+            Input::IrPath(path) => Ok(Box::new(IrSource::new(path)?)),
         }
     }
 }
@@ -91,14 +127,185 @@ impl TryFrom<&Path> for Input {
 /// This is the main entry point for the fontc command line utility.
 #[cfg(feature = "cli")]
 pub fn run(args: Args, timer: JobTimer) -> Result<(), Error> {
+    if let Some(config) = &args.config {
+        return compile_config(&args, config, timer);
+    }
+    let sources = args.source_paths()?;
+    if sources.len() > 1 {
+        return compile_many(&args, &sources, timer);
+    }
+    if args.watch {
+        return watch::watch(&args, timer);
+    }
+    if args.verify_determinism {
+        return determinism::verify_determinism(&args, timer);
+    }
+    if args.cache {
+        return compile_with_cache(&args, timer, |_| ());
+    }
+    compile_once(&args, timer, |_| ()).map(|_| ())
+}
+
+/// Compile several sources concurrently
+#[cfg(feature = "cli")]
+fn compile_many(args: &Args, sources: &[PathBuf], timer: JobTimer) -> Result<(), Error> {
+    let per_source = sources.iter().map(|s| args.for_one_source(s)).collect();
+    compile_concurrently(args, per_source, timer)
+}
+
+/// Compile the fonts described by a project file or gftools config, see [`config_file`]
+#[cfg(feature = "cli")]
+fn compile_config(args: &Args, config: &Path, timer: JobTimer) -> Result<(), Error> {
+    let config = if config.is_dir() {
+        let fontc_config = config.join(config_file::CONFIG_FILE);
+        if fontc_config.is_file() {
+            fontc_config
+        } else {
+            config.join(config_file::GFTOOLS_CONFIG_FILE)
+        }
+    } else {
+        config.to_path_buf()
+    };
+    let config_file = match config.extension().and_then(OsStr::to_str) {
+        Some("yaml") | Some("yml") => config_file::ConfigFile::load_gftools(&config)?,
+        _ => config_file::ConfigFile::load(&config)?,
+    };
+    let per_font = config_file.font_args(&config, args);
+    compile_concurrently(args, per_font, timer)
+}
+
+/// Compile each of per_source at the same time, sharing a single thread pool
+#[cfg(feature = "cli")]
+fn compile_concurrently(args: &Args, per_source: Vec<Args>, timer: JobTimer) -> Result<(), Error> {
+    for (option, used) in [
+        ("--output-file", args.output_file.is_some()),
+        ("--watch", args.watch),
+        ("--verify-determinism", args.verify_determinism),
+        ("--progress", args.progress),
+        ("--report", args.report.is_some()),
+        ("--table-sizes-json", args.table_sizes_json.is_some()),
+        // the warnings of every compile go through the one logger, which can't tell
+        // which font they are about; each font would fail for the others' warnings
+        ("--deny-warnings", args.deny_warnings),
+        ("--deny", !args.deny.is_empty()),
+    ] {
+        if used {
+            return Err(Error::UnsupportedWithMultipleSources(option));
+        }
+    }
+    let mut outputs = std::collections::HashSet::new();
+    for args in per_source.iter() {
+        if !outputs.insert(&args.output_file) {
+            return Err(Error::DuplicateSourceName(args.source_path().to_path_buf()));
+        }
+    }
+    require_dir(&args.build_dir)?;
+
+    let thread_pool = std::sync::Arc::new(
+        rayon::ThreadPoolBuilder::new()
+            .build()
+            .expect("couldn't build threadpool"),
+    );
+    let mut timer = Some(timer);
+    let results: Vec<_> = std::thread::scope(|scope| {
+        let handles: Vec<_> = per_source
+            .iter()
+            .map(|args| {
+                let timer = timer.take().unwrap_or_default();
+                let thread_pool = thread_pool.clone();
+                let share_threads = |workload: &mut Workload| workload.set_thread_pool(thread_pool);
+                scope.spawn(move || {
+                    if args.cache {
+                        compile_with_cache(args, timer, share_threads)
+                    } else {
+                        compile_once(args, timer, share_threads).map(|_| ())
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| {
+                h.join()
+                    .unwrap_or_else(|e| Err(Error::Panic(workload::get_panic_message(e))))
+            })
+            .collect()
+    });
+
+    // Report every failure, not just the first, then return the first
+    let mut first_error = None;
+    for (args, result) in per_source.iter().zip(results) {
+        match result {
+            Ok(()) => debug!("Compiled {:?}", args.source_path()),
+            Err(e) => {
+                log::error!(
+                    "{}",
+                    messages::Message::new(messages::ids::COMPILE_FAILED)
+                        .with("path", args.source_path().display())
+                        .with("error", e.message())
+                );
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    first_error.map_or(Ok(()), Err)
+}
+
+/// Copy the font from the build cache if we can, otherwise compile and add it to the cache
+#[cfg(feature = "cli")]
+fn compile_with_cache(
+    args: &Args,
+    timer: JobTimer,
+    adjust_workload: impl FnOnce(&mut Workload),
+) -> Result<(), Error> {
+    if let Some(option) = args.post_compile_option() {
+        log::info!("{option} needs a compile, not using the build cache");
+        return compile_once(args, timer, adjust_workload).map(|_| ());
+    }
+    let cache = cache::BuildCache::new(&args.build_dir);
+    let key = cache::CacheKey::new(args)?;
+    if let Some(font) = cache.get(key) {
+        let (_, be_paths) = init_paths(args.output_file.as_ref(), &args.build_dir, args.flags())?;
+        let font_file = be_paths.target_file(&fontbe::orchestration::WorkId::Font);
+        log::info!("Source unchanged, using cached font for {font_file:?}");
+        return fs::write(&font_file, font).map_err(|source| Error::FileIo {
+            path: font_file,
+            source,
+        });
+    }
+    let (_, be_root) = compile_once(args, timer, adjust_workload)?;
+    cache.put(key, be_root.font.get().get())
+}
+
+/// Compile the font described by args a single time
+///
+/// adjust_workload can tweak the workload before it runs, e.g. to reuse the results
+/// of a previous build.
+#[cfg(feature = "cli")]
+fn compile_once(
+    args: &Args,
+    timer: JobTimer,
+    adjust_workload: impl FnOnce(&mut Workload),
+) -> Result<(FeContext, BeContext), Error> {
+    // only this compile's, not e.g. those of an earlier rebuild in watch mode
+    let denied_warnings = fontdrasil::warnings::count_denied_warnings();
     let source = args.source()?;
-    let (be_root, mut timing) = _generate_font(
+    let source_composites = std::sync::Arc::<std::sync::Mutex<_>>::default();
+    let (fe_root, be_root, mut timing) = _generate_font(
         &source,
         &args.build_dir,
         args.output_file.as_ref(),
         args.flags(),
         args.skip_features,
+        &args.axis_limit,
         timer,
+        |workload| {
+            configure_workload(args, workload);
+            if args.report.is_some() {
+                workload.record_source_composites(source_composites.clone());
+            }
+            adjust_workload(workload);
+        },
     )?;
 
     if args.flags().contains(Flags::EMIT_TIMING) {
@@ -118,8 +325,117 @@ pub fn run(args: Args, timer: JobTimer) -> Result<(), Error> {
             .map_err(|source| Error::FileIo { path, source })?;
     }
 
+    if let Some(path) = &args.emit_trace {
+        let out_file = std::fs::File::create(path).map_err(|source| Error::FileIo {
+            path: path.clone(),
+            source,
+        })?;
+        let mut buf = std::io::BufWriter::new(out_file);
+        timing
+            .write_chrome_trace(&mut buf)
+            .map_err(|source| Error::FileIo {
+                path: path.clone(),
+                source,
+            })?;
+    }
+
+    if let Some(path) = &args.report {
+        let source_composites = source_composites.lock().unwrap();
+        report::GlyphReport::new(&fe_root, &be_root, &source_composites)?.write(path)?;
+    }
+
+    if let Some(dir) = &args.emit_glyph_svgs {
+        glyph_svg::write_glyph_svgs(&fe_root, dir, &args.glyph_svg_location)?;
+    }
+
+    if let Some(path) = &args.coverage {
+        coverage::check_coverage(&fe_root, path)?;
+    }
+
+    if args.check {
+        checks::check_font(be_root.font.get().get())?;
+    }
+
+    let denied = denied_warnings.get();
+    if denied > 0 {
+        return Err(Error::DeniedWarnings(denied));
+    }
+
     // At long last!
-    write_font_file(&args, &be_root)
+    write_font_file(args, &be_root)?;
+
+    // next to the font, so only once the font is
+    if !args.emit_basic_ttx.is_empty() {
+        let path = be_root.font_file().with_extension("ttx");
+        ttx::write_ttx(be_root.font.get().get(), &args.emit_basic_ttx, &path)?;
+    }
+    Ok((fe_root, be_root))
+}
+
+/// Apply options from args that are implemented by adjusting the workload
+#[cfg(feature = "cli")]
+fn configure_workload(args: &Args, workload: &mut Workload) {
+    workload.set_glyph_batch_size(args.glyph_batch_size);
+    // must precede overriding the glyph order, which wraps the glyph order work
+    if let Some(depth) = args.flatten_components.filter(|depth| *depth > 1) {
+        workload.set_max_component_depth(depth.into());
+    }
+    let overrides = work::StaticMetadataOverrides {
+        keep_instances: args.instances.clone(),
+        axis_order: args.axis_order.clone(),
+        stat_axis_order: args.stat_axis_order.clone(),
+        cmap_fallback_glyph: args
+            .cmap_fallback_glyph
+            .as_deref()
+            .map(fontdrasil::types::GlyphName::new),
+        timestamp: args.timestamp.map(|secs| {
+            chrono::TimeZone::timestamp_opt(&chrono::Utc, secs, 0)
+                .single()
+                .expect("timestamp is validated by the argument parser")
+        }),
+    };
+    if !overrides.is_empty() {
+        workload.override_static_metadata(overrides);
+    }
+    let glyph_order = work::GlyphOrderOverrides {
+        file: args.glyph_order.clone(),
+        mode: args.glyph_order_mode,
+        compat_font: args.compat_glyph_order.clone(),
+        allow_removed_glyphs: args.allow_removed_glyphs,
+        keep_glyphs: args.keep_glyphs.clone(),
+        keep_unicodes: args.keep_unicodes.clone(),
+    };
+    if !glyph_order.is_empty() {
+        workload.override_glyph_order(glyph_order);
+    }
+    if let Some(dir) = &args.bitmap_strikes {
+        workload.add_bitmap_strike_dir(dir.clone());
+    }
+    if let Some(dir) = &args.svg_documents {
+        workload.add_svg_document_dir(dir.clone());
+    }
+    if args.progress && std::io::IsTerminal::is_terminal(&std::io::stderr()) {
+        workload.set_progress(Arc::new(progress::TerminalProgress::default()));
+    }
+}
+
+/// How [generate_font] should compile, beyond the source and build directory
+#[derive(Clone, Default)]
+pub struct GenerateOptions {
+    /// Where to write the font, if anywhere
+    pub output_file: Option<PathBuf>,
+    pub flags: Flags,
+    pub skip_features: bool,
+    /// Add features of their own after the built-in kern and mark writers have run
+    pub feature_writers: Vec<Arc<dyn CustomFeatureWriter>>,
+    /// Told about each job as it completes, e.g. to show how far along a long compile is
+    pub progress: Option<Arc<dyn progress::Progress>>,
+    /// Stops the compile early once cancelled
+    ///
+    /// For callers such as editors that may want to abandon a compile because the
+    /// source changed. The compile then fails with [Error::Cancelled], or an error
+    /// whose [category](Error::category) is [ErrorCategory::Cancelled].
+    pub cancellation: Option<CancellationToken>,
 }
 
 /// Run and return an OpenType font
@@ -128,19 +444,35 @@ pub fn run(args: Args, timer: JobTimer) -> Result<(), Error> {
 pub fn generate_font(
     source: &Input,
     build_dir: &Path,
-    output_file: Option<&PathBuf>,
-    flags: Flags,
-    skip_features: bool,
+    options: GenerateOptions,
 ) -> Result<Vec<u8>, Error> {
+    let GenerateOptions {
+        output_file,
+        flags,
+        skip_features,
+        feature_writers,
+        progress,
+        cancellation,
+    } = options;
     _generate_font(
         source,
         build_dir,
-        output_file,
+        output_file.as_ref(),
         flags,
         skip_features,
+        &[],
         JobTimer::default(),
+        |workload| {
+            workload.add_custom_feature_writers(feature_writers);
+            if let Some(progress) = progress {
+                workload.set_progress(progress);
+            }
+            if let Some(cancellation) = cancellation {
+                workload.set_cancellation(cancellation);
+            }
+        },
     )
-    .map(|(be_root, _timing)| be_root.font.get().get().to_vec())
+    .map(|(_fe_root, be_root, _timing)| be_root.font.get().get().to_vec())
 }
 
 fn _generate_font(
@@ -149,18 +481,22 @@ fn _generate_font(
     output_file: Option<&PathBuf>,
     flags: Flags,
     skip_features: bool,
+    axis_limits: &[AxisLimit],
     mut timer: JobTimer,
-) -> Result<(BeContext, JobTimer), Error> {
+    adjust_workload: impl FnOnce(&mut Workload),
+) -> Result<(FeContext, BeContext, JobTimer), Error> {
     let time = timer
         .create_timer(AnyWorkId::InternalTiming("Init config"), 0)
         .run();
     let (ir_paths, be_paths) = init_paths(output_file, build_dir, flags)?;
     timer.add(time.complete());
-    let workload = Workload::new(source, timer, skip_features)?;
-    let fe_root = FeContext::new_root(flags, ir_paths);
+    let mut workload = Workload::new(source, timer, skip_features, axis_limits)?;
+    adjust_workload(&mut workload);
+    let mut fe_root = FeContext::new_root(flags, ir_paths);
+    fe_root.cancellation = workload.cancellation().clone();
     let be_root = BeContext::new_root(flags, be_paths, &fe_root);
     let timing = workload.exec(&fe_root, &be_root)?;
-    Ok((be_root, timing))
+    Ok((fe_root, be_root, timing))
 }
 
 pub fn require_dir(dir: &Path) -> Result<(), Error> {
@@ -226,7 +562,7 @@ pub fn init_paths(
 pub fn write_font_file(args: &Args, be_context: &BeContext) -> Result<(), Error> {
     // if IR is off the font didn't get written yet (nothing did), otherwise it's done already
     let font_file = be_context.font_file();
-    if !args.emit_ir {
+    if !args.flags().contains(Flags::EMIT_IR) {
         fs::write(&font_file, be_context.font.get().get()).map_err(|source| Error::FileIo {
             path: font_file,
             source,
@@ -234,7 +570,7 @@ pub fn write_font_file(args: &Args, be_context: &BeContext) -> Result<(), Error>
     } else if !font_file.exists() {
         return Err(Error::FileExpected(font_file));
     }
-    Ok(())
+    table_sizes::report_table_sizes(args, be_context.font.get().get())
 }
 
 #[cfg(test)]
@@ -264,6 +600,7 @@ mod tests {
     use ordered_float::OrderedFloat;
 
     use chrono::{Duration, TimeZone, Utc};
+    use flate2::read::GzDecoder;
     use fontbe::orchestration::{
         AnyWorkId, Context as BeContext, Glyph, LocaFormatWrapper, WorkId as BeWorkIdentifier,
     };
@@ -288,13 +625,15 @@ mod tests {
         dump_table,
         read::{
             tables::{
-                cmap::{Cmap, CmapSubtable},
-                cpal::ColorRecord,
+                cmap::{Cmap, CmapSubtable, PlatformId},
+                colr::Paint,
+                cpal::{ColorRecord, PaletteType},
                 gasp::GaspRangeBehavior,
                 glyf::{self, CompositeGlyph, CurvePoint, Glyf},
                 gpos::{AnchorTable, Gpos, MarkBasePosFormat1Marker, PositionLookup},
                 gsub::{SingleSubst, SubstitutionLookup},
                 hmtx::Hmtx,
+                kern::SubtableKind,
                 layout::FeatureParams,
                 loca::Loca,
                 name::Name,
@@ -307,15 +646,17 @@ mod tests {
         tables::{
             gdef::GlyphClassDef,
             glyf::{Bbox, Glyph as RawGlyph},
+            gsub::builders::SingleSubBuilder,
             head,
+            layout::LookupFlag,
             loca::LocaFormat,
             meta::{DataMapRecord, Metadata, ScriptLangTag},
         },
-        types::{F2Dot14, GlyphId, GlyphId16, NameId, Tag},
+        types::{F2Dot14, GlyphId, GlyphId16, MajorMinor, NameId, Tag},
     };
 
     use super::*;
-    use crate::work::AnyAccess;
+    use crate::{incremental::PreviousBuild, work::AnyAccess};
 
     struct TestCompile {
         /// we need to hold onto this because when it goes out of scope,
@@ -350,7 +691,9 @@ mod tests {
             let fe_context = FeContext::new_root(flags, ir_paths);
             let be_context = BeContext::new_root(flags, be_paths, &fe_context.read_only());
             let source = args.source().unwrap();
-            let workload = Workload::new(&source, timer, args.skip_features).unwrap();
+            let mut workload =
+                Workload::new(&source, timer, args.skip_features, &args.axis_limit).unwrap();
+            configure_workload(&args, &mut workload);
 
             TestCompile {
                 _temp_dir: temp_dir,
@@ -452,8 +795,11 @@ mod tests {
         let mut expected = vec![
             AnyWorkId::Fe(FeWorkIdentifier::StaticMetadata),
             FeWorkIdentifier::ColorPalettes.into(),
+            FeWorkIdentifier::CompatibilityCheck.into(),
             FeWorkIdentifier::GlobalMetrics.into(),
             FeWorkIdentifier::PaintGraph.into(),
+            FeWorkIdentifier::BitmapStrikes.into(),
+            FeWorkIdentifier::SvgDocuments.into(),
             FeWorkIdentifier::PreliminaryGlyphOrder.into(),
             FeWorkIdentifier::GlyphOrder.into(),
             FeWorkIdentifier::Features.into(),
@@ -463,6 +809,9 @@ mod tests {
             BeWorkIdentifier::Features.into(),
             BeWorkIdentifier::FeaturesAst.into(),
             BeWorkIdentifier::Avar.into(),
+            BeWorkIdentifier::Base.into(),
+            BeWorkIdentifier::Cbdt.into(),
+            BeWorkIdentifier::Cblc.into(),
             BeWorkIdentifier::Cmap.into(),
             BeWorkIdentifier::Colr.into(),
             BeWorkIdentifier::Cpal.into(),
@@ -478,6 +827,7 @@ mod tests {
             BeWorkIdentifier::Hhea.into(),
             BeWorkIdentifier::Hmtx.into(),
             BeWorkIdentifier::Hvar.into(),
+            BeWorkIdentifier::Kern.into(),
             BeWorkIdentifier::GatherIrKerning.into(),
             BeWorkIdentifier::KernFragment(0).into(),
             BeWorkIdentifier::GatherBeKerning.into(),
@@ -485,6 +835,8 @@ mod tests {
             BeWorkIdentifier::Loca.into(),
             BeWorkIdentifier::LocaFormat.into(),
             BeWorkIdentifier::Marks.into(),
+            BeWorkIdentifier::Math.into(),
+            BeWorkIdentifier::Sbix.into(),
             BeWorkIdentifier::Maxp.into(),
             BeWorkIdentifier::Meta.into(),
             BeWorkIdentifier::Mvar.into(),
@@ -492,6 +844,7 @@ mod tests {
             BeWorkIdentifier::Os2.into(),
             BeWorkIdentifier::Post.into(),
             BeWorkIdentifier::Stat.into(),
+            BeWorkIdentifier::Svg.into(),
             BeWorkIdentifier::Vhea.into(),
             BeWorkIdentifier::Vmtx.into(),
         ];
@@ -1310,6 +1663,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn compile_designspace_with_avar2() {
+        let result = TestCompile::compile_source("avar2.designspace");
+        let font = result.font();
+        let avar = font.avar().unwrap();
+        assert_eq!(MajorMinor::VERSION_2_0, avar.version());
+        assert!(avar.axis_index_map().is_some());
+        assert!(avar.var_store().is_some());
+    }
+
     #[test]
     fn compile_without_ir() {
         let result = TestCompile::compile("glyphs2/WghtVar.glyphs", |mut args| {
@@ -1470,6 +1833,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn keep_only_some_named_instances() {
+        let result = TestCompile::compile("glyphs3/WghtVar_Instances.glyphs", |mut args| {
+            args.instances = vec!["Bold".to_string(), "Black".to_string()];
+            args
+        });
+        let font = result.font();
+        let instances = font.fvar().unwrap().instances().unwrap();
+        assert_eq!(1, instances.len());
+        assert_eq!(
+            vec!["Bold".to_string()],
+            result
+                .fe_context
+                .static_metadata
+                .get()
+                .named_instances
+                .iter()
+                .map(|ni| ni.name.clone())
+                .collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn compile_named_instances_from_designspace() {
         assert_named_instances(
@@ -1675,6 +2060,26 @@ mod tests {
         assert_created_set("wght_var.designspace");
     }
 
+    #[rstest]
+    #[case::proportional("wght_var.designspace", 0)]
+    #[case::from_source("FixedPitch.designspace", 1)]
+    fn post_is_fixed_pitch(#[case] source: &str, #[case] expected: u32) {
+        let result = TestCompile::compile_source(source);
+        assert_eq!(expected, result.font().post().unwrap().is_fixed_pitch());
+    }
+
+    #[test]
+    fn panose_agrees_with_post_is_fixed_pitch() {
+        let result = TestCompile::compile("FixedPitch.designspace", |mut args| {
+            args.compute_panose = true;
+            args
+        });
+        let font = result.font();
+        assert_eq!(1, font.post().unwrap().is_fixed_pitch());
+        // proportion 9 is monospaced
+        assert_eq!(9, font.os2().unwrap().panose_10()[3]);
+    }
+
     #[test]
     fn generates_stat() {
         let result = TestCompile::compile_source("wght_var.designspace");
@@ -1697,6 +2102,250 @@ mod tests {
         );
     }
 
+    #[test]
+    fn stat_axis_order_puts_listed_axes_first() {
+        let result = TestCompile::compile("mapping.designspace", |mut args| {
+            args.stat_axis_order = vec![Tag::new(b"ital")];
+            args
+        });
+        let font = result.font();
+        let stat = font.stat().unwrap();
+        let mut by_ordering = stat
+            .design_axes()
+            .unwrap()
+            .iter()
+            .map(|ar| (ar.axis_ordering(), ar.axis_tag()))
+            .collect::<Vec<_>>();
+        by_ordering.sort();
+        assert_eq!(
+            vec![
+                (0, Tag::new(b"ital")),
+                (1, Tag::new(b"wght")),
+                (2, Tag::new(b"wdth"))
+            ],
+            by_ordering
+        );
+    }
+
+    #[test]
+    fn axis_order_reorders_fvar() {
+        let fvar_tags = |adjust_args: fn(Args) -> Args| {
+            let result = TestCompile::compile("mapping.designspace", adjust_args);
+            let font = result.font();
+            // avar and gvar must agree with fvar on the number and order of axes
+            assert_eq!(
+                font.fvar().unwrap().axis_count(),
+                font.gvar().unwrap().axis_count()
+            );
+            font.fvar()
+                .unwrap()
+                .axes()
+                .unwrap()
+                .iter()
+                .map(|axis| axis.axis_tag())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![Tag::new(b"wght"), Tag::new(b"wdth"), Tag::new(b"ital")],
+            fvar_tags(|args| args)
+        );
+        assert_eq!(
+            vec![Tag::new(b"ital"), Tag::new(b"wght"), Tag::new(b"wdth")],
+            fvar_tags(|mut args| {
+                args.axis_order = vec![Tag::new(b"ital")];
+                args
+            })
+        );
+    }
+
+    #[test]
+    fn gvar_optimization_levels_never_grow_gvar() {
+        let gvar_size = |optimization: GvarOptimization| {
+            let result = TestCompile::compile("wght_var.designspace", |mut args| {
+                args.gvar_optimization = optimization;
+                args
+            });
+            result.font().gvar().unwrap().offset_data().len()
+        };
+        let none = gvar_size(GvarOptimization::None);
+        let iup = gvar_size(GvarOptimization::Iup);
+        let shared_points = gvar_size(GvarOptimization::IupSharedPoints);
+        assert!(iup <= none, "iup {iup} > none {none}");
+        assert!(
+            shared_points <= iup,
+            "iup-shared-points {shared_points} > iup {iup}"
+        );
+    }
+
+    fn compiled_glyph_order(adjust_args: impl Fn(Args) -> Args) -> Vec<String> {
+        compiled_glyph_order_of("wght_var.designspace", adjust_args)
+    }
+
+    fn compiled_glyph_order_of(source: &str, adjust_args: impl Fn(Args) -> Args) -> Vec<String> {
+        let result = TestCompile::compile(source, adjust_args);
+        let glyph_order = result.fe_context.glyph_order.get();
+        glyph_order
+            .names()
+            .map(|n| n.as_str().to_string())
+            .collect()
+    }
+
+    // Static-Regular.ufo lists space, bar and plus, in that order, then has
+    // element_of, so every mode gives a different order
+    #[rstest]
+    #[case(GlyphOrderMode::Source, &[".notdef", "space", "bar", "plus", "element_of"])]
+    #[case(GlyphOrderMode::Sorted, &[".notdef", "bar", "element_of", "plus", "space"])]
+    #[case(GlyphOrderMode::CmapFirst, &[".notdef", "space", "plus", "bar", "element_of"])]
+    fn glyph_order_mode(#[case] mode: GlyphOrderMode, #[case] expected: &[&str]) {
+        let glyph_order = compiled_glyph_order_of("static.designspace", |mut args| {
+            args.glyph_order_mode = mode;
+            args
+        });
+        assert_eq!(expected, glyph_order);
+    }
+
+    #[test]
+    fn glyph_order_file_goes_first() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("glyph_order.txt");
+        fs::write(&path, "# shipped order\nbar\nnot_in_font\n").unwrap();
+        let glyph_order = compiled_glyph_order(|mut args| {
+            args.glyph_order = Some(path.clone());
+            args
+        });
+        assert_eq!(vec![".notdef", "bar", "plus"], glyph_order);
+    }
+
+    #[test]
+    fn compat_glyph_order_keeps_glyph_ids() {
+        let previous = TestCompile::compile_source("wght_var.designspace");
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("previous.ttf");
+        fs::write(&path, &previous.raw_font).unwrap();
+
+        // sorting would normally move bar before plus
+        let glyph_order = compiled_glyph_order(|mut args| {
+            args.glyph_order_mode = GlyphOrderMode::Sorted;
+            args.compat_glyph_order = Some(path.clone());
+            args
+        });
+        assert_eq!(vec![".notdef", "plus", "bar"], glyph_order);
+    }
+
+    #[test]
+    fn keep_glyphs_follows_substitutions() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("keep.txt");
+        fs::write(&path, "bar\n").unwrap();
+        // salt substitutes plus for bar
+        let glyph_order = compiled_glyph_order(|mut args| {
+            args.keep_glyphs = Some(path.clone());
+            args
+        });
+        assert_eq!(vec![".notdef", "plus", "bar"], glyph_order);
+    }
+
+    #[test]
+    fn keep_unicodes_keeps_components() {
+        let result =
+            TestCompile::compile("designspace_from_glyphs/WghtVar.designspace", |mut args| {
+                args.keep_unicodes = vec![0x3D..=0x3D];
+                args
+            });
+        let glyph_order = result.fe_context.glyph_order.get();
+        assert_eq!(
+            vec![".notdef", "hyphen", "manual-component"],
+            glyph_order.names().map(|n| n.as_str()).collect::<Vec<_>>()
+        );
+        assert_eq!(3, result.font().maxp().unwrap().num_glyphs());
+    }
+
+    #[test]
+    fn cmap_fallback_glyph_adds_format_13() {
+        let result = TestCompile::compile("wght_var.designspace", |mut args| {
+            args.cmap_fallback_glyph = Some(".notdef".to_string());
+            args
+        });
+        let raw_cmap = dump_table(result.be_context.cmap.get().as_ref()).unwrap();
+        let font_data = FontData::new(&raw_cmap);
+        let cmap = Cmap::read(font_data).unwrap();
+        let record = cmap
+            .encoding_records()
+            .iter()
+            .find(|rec| rec.platform_id() == PlatformId::Unicode && rec.encoding_id() == 6)
+            .unwrap();
+        let CmapSubtable::Format13(subtable) = record.subtable(font_data).unwrap() else {
+            panic!("expected a format 13 subtable");
+        };
+        // everything but plus, bar and the surrogates
+        let groups = subtable
+            .groups()
+            .iter()
+            .map(|g| (g.start_char_code(), g.end_char_code(), g.glyph_id()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (0, 0x2A, 0),
+                (0x2C, 0x7B, 0),
+                (0x7D, 0xD7FF, 0),
+                (0xE000, 0x10FFFF, 0)
+            ],
+            groups
+        );
+    }
+
+    #[test]
+    fn rebuilds_are_byte_identical() {
+        // with a fixed timestamp, nothing should depend on when or where we build
+        let compile = || {
+            TestCompile::compile("wght_var.designspace", |mut args| {
+                args.timestamp = Some(1_700_000_000);
+                args
+            })
+            .raw_font
+        };
+        let first = compile();
+        assert!(first == compile(), "rebuild produced a different font");
+
+        let font = FontRef::new(&first).unwrap();
+        let mac_epoch = Utc.with_ymd_and_hms(1904, 1, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            1_700_000_000,
+            mac_epoch.timestamp() + font.head().unwrap().modified().as_secs()
+        );
+    }
+
+    #[test]
+    fn cmap_format_options() {
+        let result = TestCompile::compile("wght_var.designspace", |mut args| {
+            args.no_cmap_format_4 = true;
+            args.cmap_mac_roman = true;
+            args
+        });
+        let raw_cmap = dump_table(result.be_context.cmap.get().as_ref()).unwrap();
+        let cmap = Cmap::read(FontData::new(&raw_cmap)).unwrap();
+        let records = cmap
+            .encoding_records()
+            .iter()
+            .map(|rec| {
+                let format = match rec.subtable(FontData::new(&raw_cmap)).unwrap() {
+                    CmapSubtable::Format6(..) => 6,
+                    CmapSubtable::Format12(..) => 12,
+                    _ => 0,
+                };
+                (rec.platform_id(), rec.encoding_id(), format)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (PlatformId::Unicode, 4, 12),
+                (PlatformId::Macintosh, 0, 6),
+                (PlatformId::Windows, 10, 12),
+            ],
+            records
+        );
+    }
+
     fn assert_simple_kerning(source: &str) {
         let result = TestCompile::compile_source(source);
 
@@ -1820,22 +2469,168 @@ mod tests {
         assert_simple_kerning("designspace_from_glyphs/WghtVar.designspace");
     }
 
-    fn assert_intermediate_layer(src: &str) {
-        let result = TestCompile::compile_source(src);
+    #[test]
+    fn sbix_from_glyphs_layers() {
+        let result = TestCompile::compile_source("glyphs3/Bitmaps.glyphs");
         let font = result.font();
-
+        let sbix = font.sbix().unwrap();
+        let strikes: Vec<_> = sbix.strikes().iter().map(|s| s.unwrap()).collect();
         assert_eq!(
-            vec!["CPHT", "wght"],
-            font.fvar()
-                .unwrap()
-                .axes()
-                .unwrap()
-                .iter()
-                .map(|a| a.axis_tag().to_string())
-                .collect::<Vec<_>>()
+            vec![64],
+            strikes.iter().map(|s| s.ppem()).collect::<Vec<_>>()
         );
 
-        // Confirm movement on the wght axis is as expected:
+        let gid = |name: &str| GlyphId::new(result.get_glyph_index(name).unwrap());
+        assert!(strikes[0].glyph_data(gid("space")).unwrap().is_none());
+        let smile = strikes[0].glyph_data(gid("smile")).unwrap().unwrap();
+        // the image is 100 units below the baseline, at 64 ppem in a 1000 upem font
+        assert_eq!((0, -6), (smile.origin_offset_x(), smile.origin_offset_y()));
+        assert_eq!(
+            fs::read(testdata_dir().join("glyphs3/bitmaps/smile.png")).unwrap(),
+            smile.data()
+        );
+        assert!(font.cblc().is_err());
+    }
+
+    #[test]
+    fn cbdt_bitmaps_when_asked() {
+        let result = TestCompile::compile("glyphs3/Bitmaps.glyphs", |mut args| {
+            args.cbdt_bitmaps = true;
+            args
+        });
+        let font = result.font();
+        assert!(font.sbix().is_err());
+        let cblc = font.cblc().unwrap();
+        let cbdt = font.cbdt().unwrap();
+        let size = &cblc.bitmap_sizes()[0];
+        assert_eq!(64, size.ppem_y());
+        let smile = GlyphId::new(result.get_glyph_index("smile").unwrap());
+        let location = size.location(cblc.offset_data(), smile).unwrap();
+        let metrics = cbdt.data(&location).unwrap().extract_small_metrics();
+        // 64 pixels tall with the bottom 6 below the baseline, 1000 units wide
+        assert_eq!(
+            (64, 64, 58, 64),
+            (
+                metrics.height,
+                metrics.width,
+                metrics.bearing_y.get(),
+                metrics.advance
+            )
+        );
+    }
+
+    #[test]
+    fn bitmap_strike_dir_adds_strikes() {
+        let result = TestCompile::compile("glyphs3/Bitmaps.glyphs", |mut args| {
+            args.bitmap_strikes = Some(testdata_dir().join("bitmap_strikes"));
+            args
+        });
+        let font = result.font();
+        let sbix = font.sbix().unwrap();
+        assert_eq!(
+            vec![32, 64],
+            sbix.strikes()
+                .iter()
+                .map(|s| s.unwrap().ppem())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    fn svg_document(result: &TestCompile, glyph_name: &str) -> Option<String> {
+        let gid = GlyphId::new(result.get_glyph_index(glyph_name).unwrap());
+        let data = result.font().svg().unwrap().glyph_data(gid).unwrap()?;
+        let mut document = String::new();
+        GzDecoder::new(data).read_to_string(&mut document).unwrap();
+        Some(document)
+    }
+
+    #[test]
+    fn svg_from_glyphs_layers() {
+        let result = TestCompile::compile_source("glyphs3/Svg.glyphs");
+        assert_eq!(None, svg_document(&result, "space"));
+        assert_eq!(None, svg_document(&result, "frown"));
+        let smile = svg_document(&result, "smile").unwrap();
+        let gid = result.get_glyph_index("smile").unwrap();
+        assert!(
+            smile.contains(&format!(r#"<svg id="glyph{gid}" xmlns="#)),
+            "{smile}"
+        );
+        assert!(smile.contains(r#"<circle cx="500""#), "{smile}");
+    }
+
+    #[test]
+    fn svg_document_dir_adds_documents() {
+        let result = TestCompile::compile("glyphs3/Svg.glyphs", |mut args| {
+            args.svg_documents = Some(testdata_dir().join("svg_documents"));
+            args
+        });
+        assert!(svg_document(&result, "smile").is_some());
+        let frown = svg_document(&result, "frown").unwrap();
+        let gid = result.get_glyph_index("frown").unwrap();
+        assert!(
+            frown.contains(&format!(r#"id="glyph{gid}" viewBox="#)),
+            "{frown}"
+        );
+    }
+
+    #[test]
+    fn no_svg_table_without_documents() {
+        let result = TestCompile::compile_source("glyphs3/Bitmaps.glyphs");
+        assert!(result.font().svg().is_err());
+    }
+
+    #[test]
+    fn legacy_kern_only_when_asked() {
+        let result = TestCompile::compile_source("glyphs3/WghtVar.glyphs");
+        assert!(result.font().kern().is_err());
+
+        let result = TestCompile::compile("glyphs3/WghtVar.glyphs", |mut args| {
+            args.legacy_kern = true;
+            args
+        });
+        let font = result.font();
+        let kern = font.kern().unwrap();
+        let subtables: Vec<_> = kern.subtables().map(|s| s.unwrap()).collect();
+        assert_eq!(1, subtables.len());
+        let SubtableKind::Format0(pairs) = subtables[0].kind().unwrap() else {
+            panic!("Expected a format 0 subtable");
+        };
+        let gid = |name: &str| result.get_glyph_index(name).unwrap() as u16;
+        let mut expected = vec![
+            (gid("bracketleft"), gid("bracketright"), -300),
+            (gid("bracketleft"), gid("exclam"), -165),
+            (gid("exclam"), gid("bracketright"), -160),
+            (gid("exclam"), gid("exclam"), -360),
+            (gid("exclam"), gid("hyphen"), 20),
+            (gid("hyphen"), gid("hyphen"), -150),
+        ];
+        expected.sort();
+        assert_eq!(
+            expected,
+            pairs
+                .pairs()
+                .iter()
+                .map(|p| (p.left().to_u16(), p.right().to_u16(), p.value()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    fn assert_intermediate_layer(src: &str) {
+        let result = TestCompile::compile_source(src);
+        let font = result.font();
+
+        assert_eq!(
+            vec!["CPHT", "wght"],
+            font.fvar()
+                .unwrap()
+                .axes()
+                .unwrap()
+                .iter()
+                .map(|a| a.axis_tag().to_string())
+                .collect::<Vec<_>>()
+        );
+
+        // Confirm movement on the wght axis is as expected:
         // glyph "i" (char 0x69) defines three masters at wght=400, 700 and 900
         assert_eq!(
             vec![
@@ -2458,7 +3253,7 @@ mod tests {
     #[test]
     fn compile_do_not_decompose_nested_no_export_glyphs() {
         let result = TestCompile::compile("glyphs3/NestedNoExportComponent.glyphs", |mut args| {
-            args.flatten_components = true;
+            args.flatten_components = Some(1);
             args
         });
 
@@ -2516,6 +3311,291 @@ mod tests {
         assert_eq!(700, font.os2().unwrap().us_weight_class());
     }
 
+    #[test]
+    fn disabled_masters_trim_the_axes() {
+        // the same font, but the variable export disables the Thin master at 200
+        let compile = TestCompile::compile_source("glyphs3/WghtVar_DisableMasters.glyphs");
+        let font = compile.font();
+        assert_eq!(vec![(Tag::new(b"wght"), 400.0, 700.0, 700.0)], axes(&font),);
+    }
+
+    fn limit_axes(source: &str, limit: &str) -> Vec<(Tag, f32, f32, f32)> {
+        let compile = TestCompile::compile(source, |mut args| {
+            args.axis_limit = vec![crate::args::parse_axis_limit(limit).unwrap()];
+            args
+        });
+        axes(&compile.font())
+    }
+
+    #[test]
+    fn axis_limit_drops_glyphs_masters_outside_it() {
+        // masters at 200, 400 and 700
+        assert_eq!(
+            vec![(Tag::new(b"wght"), 400.0, 700.0, 700.0)],
+            limit_axes(
+                "glyphs3/WghtVar_3master_CustomOrigin.glyphs",
+                "wght=400:700"
+            )
+        );
+    }
+
+    #[test]
+    fn axis_limit_drops_designspace_masters_outside_it() {
+        // masters at 400, 600 and 700
+        assert_eq!(
+            vec![(Tag::new(b"wght"), 400.0, 400.0, 600.0)],
+            limit_axes("wght_var.designspace", "wght=400:600")
+        );
+    }
+
+    #[test]
+    fn axis_limit_must_end_on_masters() {
+        // masters at 400, 600 and 700, nothing is interpolated to reach 650
+        let temp_dir = tempdir().unwrap();
+        let args = Args::for_test(temp_dir.path(), "wght_var.designspace");
+        let limits = [crate::args::parse_axis_limit("wght=400:650").unwrap()];
+        let result = Workload::new(&args.source().unwrap(), JobTimer::default(), false, &limits);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn axis_limit_can_pin_an_axis() {
+        assert_eq!(
+            vec![(Tag::new(b"wght"), 400.0, 400.0, 400.0)],
+            limit_axes("wght_var.designspace", "wght=400")
+        );
+    }
+
+    #[test]
+    fn emit_glyph_svgs_resolves_components() {
+        let svg_dir = tempdir().unwrap();
+        let result = TestCompile::compile_source("designspace_from_glyphs/WghtVar.designspace");
+        glyph_svg::write_glyph_svgs(&result.fe_context, svg_dir.path(), &[]).unwrap();
+
+        for file in ["manual-component.svg", "manual-component_wght_1.00.svg"] {
+            let svg = fs::read_to_string(svg_dir.path().join(file)).unwrap();
+            // two hyphens, drawn as one path
+            assert_eq!(1, svg.matches("<path").count(), "{svg}");
+            assert_eq!(2, svg.matches('M').count(), "{svg}");
+        }
+    }
+
+    #[test]
+    fn emit_glyph_svgs_at_a_location_between_masters() {
+        let svg_dir = tempdir().unwrap();
+        let result = TestCompile::compile_source("designspace_from_glyphs/WghtVar.designspace");
+        let at = [crate::args::parse_axis_value("wght=550").unwrap()];
+        glyph_svg::write_glyph_svgs(&result.fe_context, svg_dir.path(), &at).unwrap();
+
+        // only at the location asked for, which no master is at
+        let files: Vec<_> = fs::read_dir(svg_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|file| file.starts_with("hyphen"))
+            .collect();
+        assert_eq!(vec!["hyphen_wght_0.50.svg".to_string()], files);
+
+        // halfway between the hyphens of Regular, x 131 y 330, and Bold, x 92 y 356,
+        // whether drawn directly or as a component
+        for file in ["hyphen_wght_0.50.svg", "manual-component_wght_0.50.svg"] {
+            let svg = fs::read_to_string(svg_dir.path().join(file)).unwrap();
+            assert!(svg.contains("111.5"), "{svg}");
+            assert!(svg.contains("343"), "{svg}");
+        }
+    }
+
+    #[test]
+    fn coverage_reports_missing_codepoints() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("charset.nam");
+        fs::write(&path, "0x002B PLUS SIGN\n0x0041 A\n0x007C VERTICAL LINE\n").unwrap();
+        let result = TestCompile::compile("wght_var.designspace", |mut args| {
+            args.coverage = Some(path.clone());
+            args
+        });
+        let coverage = coverage::check_coverage(&result.fe_context, &path).unwrap();
+        assert_eq!("covers 2 of 3 codepoints (66.7%)", coverage.to_string());
+    }
+
+    #[test]
+    fn compiled_font_passes_checks() {
+        let result = TestCompile::compile("wght_var.designspace", |mut args| {
+            args.check = true;
+            args
+        });
+        assert_eq!(
+            Vec::<checks::CheckFailure>::new(),
+            checks::run_checks(&result.raw_font).unwrap()
+        );
+    }
+
+    #[test]
+    fn checks_find_spacing_marks() {
+        // macroncomb and brevecomb are 600 units wide
+        let result = TestCompile::compile_source("glyphs3/WghtVar_Anchors.glyphs");
+        let codes = checks::run_checks(&result.raw_font)
+            .unwrap()
+            .into_iter()
+            .map(|failure| failure.code)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["mark-width"], codes);
+    }
+
+    #[test]
+    fn emit_basic_ttx_refuses_other_tables() {
+        assert!(crate::args::parse_basic_ttx_tag("OS/2").is_ok());
+        assert!(crate::args::parse_basic_ttx_tag("GSUB").is_err());
+    }
+
+    #[test]
+    fn emit_basic_ttx_writes_requested_tables() {
+        let result = TestCompile::compile_source("wght_var.designspace");
+        let path = result.build_dir.join("font.ttx");
+        ttx::write_ttx(
+            &result.raw_font,
+            &[Tag::new(b"head"), Tag::new(b"OS/2")],
+            &path,
+        )
+        .unwrap();
+        let ttx = fs::read_to_string(path).unwrap();
+        assert!(ttx.contains("<head>"), "{ttx}");
+        assert!(ttx.contains(r#"<unitsPerEm value="1000"/>"#), "{ttx}");
+        assert!(ttx.contains("<OS_2>"), "{ttx}");
+        assert!(!ttx.contains("<name>"), "{ttx}");
+    }
+
+    /// Substitutes bar for plus in ss01
+    #[derive(Debug)]
+    struct PlusToBar;
+
+    impl CustomFeatureWriter for PlusToBar {
+        fn add_features(
+            &self,
+            _: &ir::StaticMetadata,
+            glyph_order: &GlyphOrder,
+            builder: &mut FeatureBuilder,
+        ) {
+            let mut sub = SingleSubBuilder::default();
+            sub.insert(
+                glyph_order.glyph_id("plus").unwrap(),
+                glyph_order.glyph_id("bar").unwrap(),
+            );
+            let lookup =
+                builder.add_lookup(PendingLookup::new(vec![sub], LookupFlag::empty(), None));
+            builder.add_to_default_language_systems(Tag::new(b"ss01"), &[lookup]);
+        }
+    }
+
+    #[test]
+    fn custom_feature_writers_add_features() {
+        let build_dir = tempdir().unwrap();
+        let font = generate_font(
+            &Input::new(&testdata_dir().join("wght_var.designspace")).unwrap(),
+            build_dir.path(),
+            GenerateOptions {
+                feature_writers: vec![Arc::new(PlusToBar)],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let font = FontRef::new(&font).unwrap();
+        let tags = font
+            .gsub()
+            .unwrap()
+            .feature_list()
+            .unwrap()
+            .feature_records()
+            .iter()
+            .map(|rec| rec.feature_tag())
+            .collect::<HashSet<_>>();
+        assert!(tags.contains(&Tag::new(b"ss01")), "{tags:?}");
+        // the features in the source are still there
+        assert!(tags.contains(&Tag::new(b"salt")), "{tags:?}");
+    }
+
+    /// Remembers how many jobs were complete, and of how many, at each event
+    #[derive(Default)]
+    struct RecordProgress(std::sync::Mutex<Vec<(usize, usize)>>);
+
+    impl progress::Progress for RecordProgress {
+        fn job_completed(&self, event: &progress::ProgressEvent) {
+            self.0.lock().unwrap().push((event.completed, event.total));
+        }
+    }
+
+    #[test]
+    fn cancelled_compile_fails() {
+        let build_dir = tempdir().unwrap();
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+        let result = generate_font(
+            &Input::new(&testdata_dir().join("wght_var.designspace")).unwrap(),
+            build_dir.path(),
+            GenerateOptions {
+                cancellation: Some(cancellation),
+                ..Default::default()
+            },
+        );
+        let Err(error) = result else {
+            panic!("cancelled compile produced a font");
+        };
+        assert_eq!(ErrorCategory::Cancelled, error.category(), "{error}");
+    }
+
+    #[test]
+    fn cancel_during_compile() {
+        /// Cancels once a few jobs are done
+        struct CancelPartWay(CancellationToken);
+
+        impl progress::Progress for CancelPartWay {
+            fn job_completed(&self, event: &progress::ProgressEvent) {
+                if event.completed >= 3 {
+                    self.0.cancel();
+                }
+            }
+        }
+
+        let build_dir = tempdir().unwrap();
+        let cancellation = CancellationToken::new();
+        let result = generate_font(
+            &Input::new(&testdata_dir().join("wght_var.designspace")).unwrap(),
+            build_dir.path(),
+            GenerateOptions {
+                progress: Some(Arc::new(CancelPartWay(cancellation.clone()))),
+                cancellation: Some(cancellation),
+                ..Default::default()
+            },
+        );
+        let Err(error) = result else {
+            panic!("cancelled compile produced a font");
+        };
+        assert_eq!(ErrorCategory::Cancelled, error.category(), "{error}");
+    }
+
+    #[test]
+    fn progress_counts_up_to_every_job() {
+        let build_dir = tempdir().unwrap();
+        let progress = Arc::new(RecordProgress::default());
+        generate_font(
+            &Input::new(&testdata_dir().join("wght_var.designspace")).unwrap(),
+            build_dir.path(),
+            GenerateOptions {
+                progress: Some(progress.clone()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let events = progress.0.lock().unwrap();
+        assert!(!events.is_empty());
+        assert!(
+            events.windows(2).all(|pair| pair[0].0 < pair[1].0),
+            "{events:?}"
+        );
+        let (completed, total) = *events.last().unwrap();
+        assert_eq!(completed, total);
+    }
+
     #[test]
     fn os2_width_class_matches_default_wdth_glyphs2() {
         let compile = TestCompile::compile_source("glyphs2/WdthVar.glyphs");
@@ -2778,6 +3858,66 @@ mod tests {
         );
     }
 
+    #[test]
+    fn compile_from_emitted_ir() {
+        // TestCompile emits IR into its build directory
+        let from_source = TestCompile::compile_source("wght_var.designspace");
+        let ir_dir = from_source.args.build_dir.to_str().unwrap().to_string();
+        let from_ir = TestCompile::compile_source(&ir_dir);
+
+        let (from_source, from_ir) = (from_source.font(), from_ir.font());
+        for tag in [
+            b"glyf", b"gvar", b"hmtx", b"HVAR", b"fvar", b"cmap", b"name",
+        ] {
+            let tag = Tag::new(tag);
+            assert_eq!(
+                from_source.table_data(tag).map(|t| t.as_bytes().to_vec()),
+                from_ir.table_data(tag).map(|t| t.as_bytes().to_vec()),
+                "{tag}"
+            );
+        }
+    }
+
+    #[test]
+    fn emitted_ir_inlines_feature_includes() {
+        let from_source = TestCompile::compile_source("fea_include.designspace");
+        let features = fs::read_to_string(
+            IrPaths::new(&from_source.args.build_dir).target_file(&FeWorkIdentifier::Features),
+        )
+        .unwrap();
+        assert!(!features.contains("include"), "{features}");
+
+        let ir_dir = from_source.args.build_dir.to_str().unwrap().to_string();
+        let from_ir = TestCompile::compile_source(&ir_dir);
+        let (from_source, from_ir) = (from_source.font(), from_ir.font());
+        for tag in [b"GSUB", b"GPOS"] {
+            let tag = Tag::new(tag);
+            assert_eq!(
+                from_source.table_data(tag).map(|t| t.as_bytes().to_vec()),
+                from_ir.table_data(tag).map(|t| t.as_bytes().to_vec()),
+                "{tag}"
+            );
+        }
+    }
+
+    #[test]
+    fn emitted_ir_of_another_version_is_refused() {
+        let from_source = TestCompile::compile_source("wght_var.designspace");
+        let ir_paths = IrPaths::new(&from_source.args.build_dir);
+        fs::write(ir_paths.ir_version_file(), "0").unwrap();
+        assert!(IrSource::new(&from_source.args.build_dir).is_err());
+    }
+
+    #[test]
+    fn compile_ufo_is_static() {
+        let result = TestCompile::compile_source("WghtVar-Bold.ufo");
+        let font = result.font();
+        for tag in [b"fvar", b"gvar", b"avar", b"HVAR"] {
+            assert!(font.table_data(Tag::new(tag)).is_none(), "{tag:?}");
+        }
+        assert!(font.glyf().is_ok());
+    }
+
     #[test]
     fn compile_empty_gvar_with_correct_axis_count() {
         // The test font contains a 'wght' axis and only 1 UFO source with a variable
@@ -3185,6 +4325,71 @@ mod tests {
         );
     }
 
+    #[test]
+    fn generate_base_table() {
+        let result = TestCompile::compile_source("glyphs3/Baselines.glyphs");
+        let base = result.be_context.base.get();
+        assert!(base.vert_axis.is_none());
+        let horiz_axis = base.horiz_axis.as_ref().unwrap();
+        assert_eq!(
+            vec![Tag::new(b"ideo"), Tag::new(b"romn")],
+            horiz_axis.base_tag_list.as_ref().unwrap().baseline_tags
+        );
+        assert_eq!(
+            vec![(Tag::new(b"hani"), 0), (Tag::new(b"latn"), 1)],
+            horiz_axis
+                .base_script_list
+                .base_script_records
+                .iter()
+                .map(|record| (
+                    record.base_script_tag,
+                    record
+                        .base_script
+                        .base_values
+                        .as_ref()
+                        .unwrap()
+                        .default_baseline_index
+                ))
+                .collect::<Vec<_>>()
+        );
+        assert!(result.font().base().is_ok());
+    }
+
+    #[test]
+    fn generate_math_table() {
+        let result = TestCompile::compile_source("glyphs3/Math.glyphs");
+        let static_metadata = result.fe_context.static_metadata.get();
+        let math = static_metadata.misc.math.as_ref().unwrap();
+        assert_eq!(Some(&250), math.constants.get("AxisHeight"));
+        assert_eq!(
+            vec!["integral"],
+            math.extended_shapes
+                .iter()
+                .map(|n| n.as_str())
+                .collect::<Vec<_>>()
+        );
+
+        let font = result.font();
+        let math = font.table_data(Tag::new(b"MATH")).unwrap();
+        let math = math.as_bytes();
+        let read_u16 = |pos: usize| u16::from_be_bytes([math[pos], math[pos + 1]]) as usize;
+        // italics correction, top accent and extended shape coverage, no kerns
+        let glyph_info = read_u16(6);
+        assert_eq!(
+            vec![true, true, true, false],
+            (0..4)
+                .map(|i| read_u16(glyph_info + 2 * i) != 0)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(20, read_u16(read_u16(8)));
+    }
+
+    #[test]
+    fn no_math_table_without_constants() {
+        let result = TestCompile::compile_source("glyphs3/WghtVar.glyphs");
+        assert!(result.font().table_data(Tag::new(b"MATH")).is_none());
+    }
+
     #[test]
     fn dont_generate_meta_table_if_no_glyphs_param() {
         let result = TestCompile::compile_source("glyphs3/NoMetaTable.glyphs");
@@ -3261,6 +4466,51 @@ mod tests {
         );
     }
 
+    #[test]
+    fn cpal_themed_palettes() {
+        let result = TestCompile::compile_source("glyphs3/ColorPalettes.glyphs");
+        let cpal = result.font().cpal().unwrap();
+        let color = |red, green, blue| ColorRecord {
+            red,
+            green,
+            blue,
+            alpha: 255,
+        };
+        // the gradient's green isn't in the palettes, so is added to each of them
+        assert_eq!(
+            (
+                1,
+                2,
+                3,
+                [
+                    color(255, 0, 0),
+                    color(0, 0, 255),
+                    color(0, 255, 0),
+                    color(255, 128, 128),
+                    color(128, 128, 255),
+                    color(0, 255, 0),
+                ]
+                .as_slice(),
+                vec![
+                    PaletteType::USABLE_WITH_LIGHT_BACKGROUND,
+                    PaletteType::USABLE_WITH_DARK_BACKGROUND
+                ]
+            ),
+            (
+                cpal.version(),
+                cpal.num_palettes(),
+                cpal.num_palette_entries(),
+                cpal.color_records_array().unwrap().unwrap(),
+                cpal.palette_types_array()
+                    .unwrap()
+                    .unwrap()
+                    .iter()
+                    .map(|flags| flags.get())
+                    .collect::<Vec<_>>()
+            )
+        );
+    }
+
     #[test]
     fn color_me_not() {
         let compile = TestCompile::compile_source("glyphs3/WghtVar.glyphs");
@@ -3274,6 +4524,66 @@ mod tests {
         result.font().colr().unwrap(); // for now just check the table exists
     }
 
+    #[test]
+    fn colr_variable_gradient() {
+        let result = TestCompile::compile_source("glyphs3/COLRv1-variable.glyphs");
+        let font = result.font();
+        let colr = font.colr().unwrap();
+        let base_glyphs = colr.base_glyph_list().unwrap().unwrap();
+        let paints: Vec<_> = base_glyphs
+            .base_glyph_paint_records()
+            .iter()
+            .map(|record| {
+                let Paint::Glyph(glyph) = record.paint(base_glyphs.offset_data()).unwrap() else {
+                    panic!("Expected a PaintGlyph");
+                };
+                assert_eq!(record.glyph_id(), glyph.glyph_id());
+                glyph.paint().unwrap()
+            })
+            .collect();
+        assert_eq!(2, paints.len());
+
+        // A is wider when bold, so its gradient is too
+        let Paint::VarLinearGradient(gradient) = &paints[0] else {
+            panic!("Expected a PaintVarLinearGradient");
+        };
+        assert_eq!(
+            (63, 250, 542, 250, 63, 729),
+            (
+                gradient.x0().to_i16(),
+                gradient.y0().to_i16(),
+                gradient.x1().to_i16(),
+                gradient.y1().to_i16(),
+                gradient.x2().to_i16(),
+                gradient.y2().to_i16()
+            )
+        );
+        let var_index_map = colr.var_index_map().unwrap().unwrap();
+        let store = colr.item_variation_store().unwrap().unwrap();
+        let bold = [F2Dot14::from_f32(1.0)];
+        let deltas: Vec<_> = (0..6)
+            .map(|i| {
+                let index = var_index_map.get(gradient.var_index_base() + i).unwrap();
+                store.compute_delta(index, &bold).unwrap()
+            })
+            .collect();
+        assert_eq!(vec![-13, 0, 208, 0, -13, 221], deltas);
+
+        // B's gradient is the same in both masters
+        let Paint::RadialGradient(gradient) = &paints[1] else {
+            panic!("Expected a PaintRadialGradient");
+        };
+        assert_eq!(
+            (300, 200, 0, 200),
+            (
+                gradient.x0().to_i16(),
+                gradient.y0().to_i16(),
+                gradient.radius0().to_u16(),
+                gradient.radius1().to_u16()
+            )
+        );
+    }
+
     #[rstest]
     #[case("glyphs2/IncompatibleUnexported.glyphs")]
     #[case("glyphs3/IncompatibleUnexported.glyphs")]
@@ -3574,11 +4884,13 @@ mod tests {
 
     #[rstest]
     #[case("glyphs3/DontUseProductionNames.glyphs")]
+    #[case("glyphs3/DontUseProductionNames_instance.glyphs")]
     #[case("glyphs2/DontUseProductionNames.glyphs")]
     #[case("designspace_from_glyphs/DontuseProductionNames.designspace")]
     fn dont_use_production_names_custom_parameter(#[case] source: &str) {
-        // these sources have the "Don't use Production Names" custom parameter set to true
-        // (or the ufo2ft equivalent "useProductionNames" lib key set to false).
+        // these sources have the "Don't use Production Names" custom parameter set to true,
+        // on the font or its variable export settings (or the ufo2ft equivalent
+        // "useProductionNames" lib key set to false).
         let result = TestCompile::compile_source(source);
         let font = result.font();
         let post = font.post().unwrap();
@@ -3587,6 +4899,34 @@ mod tests {
         assert_eq!(post.glyph_name(result.get_gid("nbspace")), Some("nbspace"));
     }
 
+    #[test]
+    fn ufo_lib_names_and_orders_glyphs() {
+        // the default master's lib has public.glyphOrder and public.postscriptNames
+        let result = TestCompile::compile_source("designspace_from_glyphs/WghtVar.designspace");
+        let glyph_order = result.fe_context.glyph_order.get();
+        assert_eq!(
+            vec![
+                ".notdef",
+                "space",
+                "exclam",
+                "hyphen",
+                "bracketleft",
+                "bracketright",
+                "manual-component"
+            ],
+            glyph_order
+                .iter()
+                .map(|(_, name)| name.as_str())
+                .collect::<Vec<_>>()
+        );
+        let font = result.font();
+        let post = font.post().unwrap();
+        assert_eq!(
+            post.glyph_name(result.get_gid("manual-component")),
+            Some("manualcomponent")
+        );
+    }
+
     /// End-to-end test that `vhea` is built correctly.
     #[rstest]
     #[case("glyphs2/Vertical.glyphs")]
@@ -3717,6 +5057,92 @@ mod tests {
         assert_eq!(get_component_gids(yen_bracket), [peso_bracket_gid]);
     }
 
+    #[test]
+    fn gvar_interpolates_matched_contours() {
+        // the bold master lists the contours in the other order, the top one rotated
+        let result = TestCompile::compile("glyphs3/WghtVar_ContourOrder.glyphs", |mut args| {
+            args.match_contours = true;
+            args
+        });
+        let font = result.font();
+        let deltas = font
+            .gvar()
+            .unwrap()
+            .glyph_variation_data(result.get_gid("colon").into())
+            .unwrap()
+            .unwrap()
+            .tuples()
+            .flat_map(|tuple| {
+                tuple
+                    .deltas()
+                    .map(|delta| (delta.x_delta, delta.y_delta))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert!(!deltas.is_empty());
+        // each square grows by 20 units, rather than moving to where the other square is
+        assert!(
+            deltas.iter().all(|(x, y)| x.abs() <= 20 && y.abs() <= 20),
+            "{deltas:?}"
+        );
+    }
+
+    #[test]
+    fn deny_warnings_is_refused_for_concurrent_compiles() {
+        use clap::Parser;
+
+        let build_dir = tempdir().unwrap();
+        for option in ["--deny-warnings", "--deny=fea-warning"] {
+            let args = Args::try_parse_from([
+                "fontc".into(),
+                option.into(),
+                "--build-dir".into(),
+                build_dir.path().to_path_buf(),
+                testdata_dir().join("wght_var.designspace"),
+                testdata_dir().join("glyphs3/WghtVar.glyphs"),
+            ])
+            .unwrap();
+            let result = run(args, JobTimer::default());
+            assert!(
+                matches!(result, Err(Error::UnsupportedWithMultipleSources(_))),
+                "{option}: {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn glyph_work_waits_for_compatibility_check() {
+        let mut test = TestCompile::new("wght_var.designspace", |args| args);
+        let mut glyph = ir::GlyphBuilder::new("bar".into());
+        glyph
+            .try_add_source(
+                &NormalizedLocation::for_pos(&[("wght", 0.0)]),
+                ir::GlyphInstance::default(),
+            )
+            .unwrap();
+        test.fe_context
+            .copy_for_work(Access::All, Access::All)
+            .glyphs
+            .set(glyph.build().unwrap());
+
+        // as when the IR for bar completes
+        test.workload
+            .update_be_glyph_work(&test.fe_context, "bar".into());
+
+        let glyf_job = test
+            .workload
+            .jobs_pending
+            .get(&AnyWorkId::Be(BeWorkIdentifier::GlyfFragment("bar".into())))
+            .unwrap();
+        assert!(
+            glyf_job
+                .read_access
+                .check(&FeWorkIdentifier::CompatibilityCheck.into()),
+            "{:?}",
+            glyf_job.read_access
+        );
+    }
+
     #[test]
     fn glyf_loca_work_waits_for_dynamic_notdef() {
         // https://github.com/googlefonts/fontc/issues/1436
@@ -3743,4 +5169,75 @@ mod tests {
         assert!(completed.contains(&AnyWorkId::Be(BeWorkIdentifier::Glyf)));
         assert!(completed.contains(&AnyWorkId::Be(BeWorkIdentifier::Loca)));
     }
+
+    #[test]
+    fn unchanged_glyphs_reuse_previous_build() {
+        let first = TestCompile::compile_source("wght_var.designspace");
+        let previous = PreviousBuild::new(&first.fe_context, first.be_context);
+
+        let mut second = TestCompile::new("wght_var.designspace", |args| args);
+        second.workload.reuse_unchanged_glyphs(previous);
+        second.run();
+
+        assert!(second.workload.n_reused_glyphs > 0);
+        // The whole font differs, head.modified if nothing else, but glyphs shouldn't
+        let first_font = FontRef::new(&first.raw_font).unwrap();
+        let second_font = FontRef::new(&second.raw_font).unwrap();
+        for tag in [Tag::new(b"glyf"), Tag::new(b"loca"), Tag::new(b"gvar")] {
+            assert_eq!(
+                first_font.table_data(tag).map(|d| d.as_bytes().to_vec()),
+                second_font.table_data(tag).map(|d| d.as_bytes().to_vec()),
+                "{tag} differs"
+            );
+        }
+    }
+
+    #[test]
+    fn batched_glyph_ir_work_completes_every_glyph() {
+        let unbatched = TestCompile::compile_source("glyphs3/WghtVar.glyphs");
+
+        let mut batched = TestCompile::new("glyphs3/WghtVar.glyphs", |args| args);
+        batched.workload.batch_glyph_ir_work(2);
+        batched.run();
+
+        assert_eq!(unbatched.work_executed, batched.work_executed);
+        assert_eq!(
+            *unbatched.fe_context.glyph_order.get(),
+            *batched.fe_context.glyph_order.get()
+        );
+    }
+
+    #[test]
+    fn low_memory_evicts_compiled_glyph_ir() {
+        let normal = TestCompile::compile_source("wght_var.designspace");
+        let low_memory = TestCompile::compile("wght_var.designspace", |mut args| {
+            args.low_memory = true;
+            args
+        });
+
+        let glyph_id = FeWorkIdentifier::Glyph("bar".into());
+        assert!(low_memory.fe_context.glyphs.try_get(&glyph_id).is_none());
+        // Evicted, not lost
+        assert_eq!(
+            *normal.fe_context.glyphs.get(&glyph_id),
+            *low_memory.fe_context.glyphs.get(&glyph_id)
+        );
+        assert!(low_memory.fe_context.glyphs.try_get(&glyph_id).is_none());
+        assert_eq!(
+            normal.fe_context.glyphs.all().len(),
+            low_memory.fe_context.glyphs.all().len()
+        );
+
+        let normal_font = FontRef::new(&normal.raw_font).unwrap();
+        let low_memory_font = FontRef::new(&low_memory.raw_font).unwrap();
+        for tag in [Tag::new(b"glyf"), Tag::new(b"loca"), Tag::new(b"gvar")] {
+            assert_eq!(
+                normal_font.table_data(tag).map(|d| d.as_bytes().to_vec()),
+                low_memory_font
+                    .table_data(tag)
+                    .map(|d| d.as_bytes().to_vec()),
+                "{tag} differs"
+            );
+        }
+    }
 }
@@ -3,7 +3,10 @@ use std::io::Write;
 use clap::Parser;
 
 use fontbe::orchestration::AnyWorkId;
-use fontc::{Args, Error, JobTimer};
+use fontc::{
+    messages::{ids, Message},
+    Args, Error, JobTimer,
+};
 use log::{error, warn};
 
 fn main() {
@@ -13,7 +16,7 @@ fn main() {
     // The default log level is error so the user will see it unless they specifically turned off logging
     if let Err(e) = run(args) {
         let mut error_displayed = false;
-        let mut additional = "";
+        let mut additional = String::new();
         if let Error::Backend(fontbe::error::Error::FeaCompileError(e)) = &e {
             if log::log_enabled!(log::Level::Warn) {
                 error!("{e}");
@@ -22,11 +25,11 @@ fn main() {
                 }
                 error_displayed = true;
             } else {
-                additional = ", set log level to warn or higher (--log warn) for additional detail"
+                additional = format!(", {}", Message::new(ids::LOG_LEVEL_HINT));
             }
         }
         if !error_displayed {
-            error!("{e}{additional}");
+            error!("{}{additional}", e.message());
         }
         std::process::exit(1);
     }
@@ -59,7 +62,10 @@ fn run(args: Args) -> Result<(), Error> {
     if let Some(log_filters) = &args.log {
         log_cfg.parse_filters(log_filters);
     }
-    log_cfg.init();
+    let logger = log_cfg.build();
+    let max_level = logger.filter();
+    fontdrasil::warnings::install(Box::new(logger), max_level, args.warning_policy())
+        .expect("a logger was already set");
     timer.add(time.complete());
 
     fontc::run(args, timer)
@@ -0,0 +1,415 @@
+//! User-facing messages, with stable ids so they can be localized or reworded
+//!
+//! A [Message] is an id, such as `checks-failed`, and named arguments. A
+//! [MessageCatalog] maps each id to a template in which `{name}` is replaced by the
+//! argument of that name, `{{` and `}}` being literal braces. [English] is the
+//! catalog fontc ships; products embedding fontc can [set_catalog] their own,
+//! which need only cover the ids they care about.
+//!
+//! Errors from the FE and BE have ids too, their English templates reading as the
+//! errors of fontir and fontbe display. Where the FE or BE fails the same way as
+//! fontc, e.g. when cancelled, they share an id. Feature compilation errors appear
+//! as `feature-compile-failed`, with the diagnostics as `detail`.
+
+use std::{collections::HashMap, fmt::Display, sync::OnceLock};
+
+/// The ids of every message
+pub mod ids {
+    pub const EXPECTED_DIRECTORY: &str = "expected-directory";
+    pub const FILE_IO: &str = "file-io";
+    pub const STDIO_WRITE_FAILED: &str = "stdio-write-failed";
+    pub const UNRECOGNIZED_SOURCE: &str = "unrecognized-source";
+    pub const YAML_ERROR: &str = "yaml-error";
+    pub const JSON_ERROR: &str = "json-error";
+    pub const FEATURE_COMPILE_FAILED: &str = "feature-compile-failed";
+    pub const FILE_EXPECTED: &str = "file-expected";
+    pub const UNABLE_TO_PROCEED: &str = "unable-to-proceed";
+    pub const PANIC: &str = "panic";
+    pub const NONDETERMINISTIC_OUTPUT: &str = "nondeterministic-output";
+    pub const UNSUPPORTED_WITH_MULTIPLE_SOURCES: &str = "unsupported-with-multiple-sources";
+    pub const DUPLICATE_SOURCE_NAME: &str = "duplicate-source-name";
+    pub const INVALID_CONFIG: &str = "invalid-config";
+    pub const INVALID_CHARSET: &str = "invalid-charset";
+    pub const READ_FONT_FAILED: &str = "read-font-failed";
+    pub const CHECKS_FAILED: &str = "checks-failed";
+    pub const DENIED_WARNINGS: &str = "denied-warnings";
+    pub const CANCELLED: &str = "cancelled";
+    pub const COMPILE_FAILED: &str = "compile-failed";
+    pub const LOG_LEVEL_HINT: &str = "log-level-hint";
+
+    // from the FE
+    pub const BAD_SOURCE: &str = "bad-source";
+    pub const NO_SUCH_PATH: &str = "no-such-path";
+    pub const BAD_GLYPH: &str = "bad-glyph";
+    pub const DELETE_FAILED: &str = "delete-failed";
+    pub const INVALID_UPEM: &str = "invalid-upem";
+    pub const INCONSISTENT_UPEM: &str = "inconsistent-upem";
+    pub const VARIATION_MODEL_FAILED: &str = "variation-model-failed";
+    pub const NON_IDENTICAL_FEA: &str = "non-identical-fea";
+    pub const MISSING_AXIS_MAPPING: &str = "missing-axis-mapping";
+    pub const NO_GLYPH_FOR_NAME: &str = "no-glyph-for-name";
+    pub const NO_AXIS_DEFINITIONS: &str = "no-axis-definitions";
+    pub const NO_ENTRY_IN_AXES: &str = "no-entry-in-axes";
+    pub const INCONSISTENT_AXIS_DEFINITIONS: &str = "inconsistent-axis-definitions";
+    pub const NO_SUCH_LAYER: &str = "no-such-layer";
+    pub const NO_STATE_FOR_GLYPH: &str = "no-state-for-glyph";
+    pub const NO_LOCATIONS_FOR_GLYPH: &str = "no-locations-for-glyph";
+    pub const UNABLE_TO_CREATE_GLYPH_IR_WORK: &str = "unable-to-create-glyph-ir-work";
+    pub const UNEXPECTED_STATE: &str = "unexpected-state";
+    pub const DUPLICATE_USER_LOCATION: &str = "duplicate-user-location";
+    pub const INVALID_GLOBAL_METADATA: &str = "invalid-global-metadata";
+    pub const NO_DEFAULT_MASTER: &str = "no-default-master";
+    pub const MISSING_MAPPING_FOR_DESIGN_COORD: &str = "missing-mapping-for-design-coord";
+    pub const INVALID_TAG: &str = "invalid-tag";
+    pub const UNSUPPORTED_CONSTRUCT: &str = "unsupported-construct";
+    pub const INCONSISTENT_PALETTE_SIZE: &str = "inconsistent-palette-size";
+    pub const UNKNOWN_ENTRY: &str = "unknown-entry";
+    pub const INVALID_ENTRY: &str = "invalid-entry";
+    pub const INCOMPATIBLE_SOURCES: &str = "incompatible-sources";
+    pub const OVERLAPPING_KERNING_GROUPS: &str = "overlapping-kerning-groups";
+    pub const VARIABLE_COMPONENT_TRANSFORMS: &str = "variable-component-transforms";
+    pub const INCONSISTENT_COMPONENTS: &str = "inconsistent-components";
+
+    // from the BE
+    pub const IO_FAILED: &str = "io-failed";
+    pub const GLYPH_PROBLEM: &str = "glyph-problem";
+    pub const MALFORMED_PATH: &str = "malformed-path";
+    pub const COMPONENT_PROBLEM: &str = "component-problem";
+    pub const COMPONENT_PROBLEMS: &str = "component-problems";
+    pub const DUMP_TABLE_FAILED: &str = "dump-table-failed";
+    pub const OUT_OF_BOUNDS: &str = "out-of-bounds";
+    pub const GLYPH_DELTAS_FAILED: &str = "glyph-deltas-failed";
+    pub const MVAR_DELTAS_FAILED: &str = "mvar-deltas-failed";
+    pub const AVAR_MODEL_FAILED: &str = "avar-model-failed";
+    pub const AVAR_DELTAS_FAILED: &str = "avar-deltas-failed";
+    pub const ANCHOR_DELTAS_FAILED: &str = "anchor-deltas-failed";
+    pub const GRADIENT_DELTAS_FAILED: &str = "gradient-deltas-failed";
+    pub const KERN_DELTAS_FAILED: &str = "kern-deltas-failed";
+    pub const GVAR_FAILED: &str = "gvar-failed";
+    pub const READ_FAILED: &str = "read-failed";
+    pub const IUP_FAILED: &str = "iup-failed";
+    pub const INVALID_TABLE_BYTES: &str = "invalid-table-bytes";
+    pub const MISSING_DIRECTORY: &str = "missing-directory";
+    pub const GLYPH_VARIATION_MODEL_FAILED: &str = "glyph-variation-model-failed";
+    pub const MISSING_TABLE: &str = "missing-table";
+    pub const INCOMPLETE_PATCH: &str = "incomplete-patch";
+    pub const EXPECTED_ANCHOR: &str = "expected-anchor";
+    pub const MISSING_GLYPH_CLASS: &str = "missing-glyph-class";
+    pub const CONFLICTING_MARK_CLASSES: &str = "conflicting-mark-classes";
+    pub const NO_VARIATION_MODEL: &str = "no-variation-model";
+    pub const DELTAS_FAILED: &str = "deltas-failed";
+    pub const MISSING_GLYPH_ID: &str = "missing-glyph-id";
+    pub const CMAP_CONFLICT: &str = "cmap-conflict";
+    pub const INVALID_CMAP: &str = "invalid-cmap";
+    pub const INCONSISTENT_STYLE: &str = "inconsistent-style";
+    pub const COMPOSITES_STALLED: &str = "composites-stalled";
+    pub const INCONSISTENT_PALETTE_LENGTHS: &str = "inconsistent-palette-lengths";
+    pub const OVERLAPPING_SVG_DOCUMENTS: &str = "overlapping-svg-documents";
+}
+
+/// The templates of [English], by id
+const ENGLISH: &[(&str, &str)] = &[
+    (
+        ids::EXPECTED_DIRECTORY,
+        "'{path}' exists but is not a directory",
+    ),
+    (ids::FILE_IO, "io failed for '{path}': '{source}'"),
+    (
+        ids::STDIO_WRITE_FAILED,
+        "failed to write to stdout or stderr: '{source}'",
+    ),
+    (ids::UNRECOGNIZED_SOURCE, "Unrecognized source {path}"),
+    (ids::YAML_ERROR, "{detail}"),
+    (ids::JSON_ERROR, "{detail}"),
+    (ids::FEATURE_COMPILE_FAILED, "{detail}"),
+    (ids::FILE_EXPECTED, "Missing file '{path}'"),
+    (
+        ids::UNABLE_TO_PROCEED,
+        "Unable to proceed; {count} jobs stuck pending",
+    ),
+    (ids::PANIC, "A task panicked: '{detail}'"),
+    (
+        ids::NONDETERMINISTIC_OUTPUT,
+        "Output differs between {threads} and {other_threads} threads, first in '{table}'",
+    ),
+    (
+        ids::UNSUPPORTED_WITH_MULTIPLE_SOURCES,
+        "{option} can't be used when compiling more than one source or a config file",
+    ),
+    (
+        ids::DUPLICATE_SOURCE_NAME,
+        "More than one source would be written to the same output as {path}",
+    ),
+    (
+        ids::INVALID_CONFIG,
+        "Invalid config file '{path}': {detail}",
+    ),
+    (
+        ids::INVALID_CHARSET,
+        "Invalid character set file '{path}': {detail}",
+    ),
+    (
+        ids::READ_FONT_FAILED,
+        "Unable to read back the compiled font: {detail}",
+    ),
+    (
+        ids::CHECKS_FAILED,
+        "{count} checks failed, the font was not written",
+    ),
+    (
+        ids::DENIED_WARNINGS,
+        "{count} denied warnings, the font was not written",
+    ),
+    (ids::CANCELLED, "Compilation was cancelled"),
+    (ids::COMPILE_FAILED, "Failed to compile '{path}': {error}"),
+    (
+        ids::LOG_LEVEL_HINT,
+        "set log level to warn or higher (--log warn) for additional detail",
+    ),
+    // from the FE
+    (ids::BAD_SOURCE, "Reading source failed for '{path}': '{reason}'"),
+    (ids::NO_SUCH_PATH, "{path} does not exist"),
+    (ids::BAD_GLYPH, "Invalid source glyph '{glyph}': '{reason}'"),
+    (ids::DELETE_FAILED, "Failed to delete file {path}: '{source}'"),
+    (ids::INVALID_UPEM, "UPEM value {upem} outside valid range 16..=16384"),
+    (ids::INCONSISTENT_UPEM, "Inconsistent UPEM values: {values}"),
+    (ids::VARIATION_MODEL_FAILED, "Variation model error: '{detail}'"),
+    (ids::NON_IDENTICAL_FEA, "feature files are non-identical: {path}, {other_path}"),
+    (ids::MISSING_AXIS_MAPPING, "axis '{axis}' missing at least one of default/min/max mapping"),
+    (ids::NO_GLYPH_FOR_NAME, "no glyph for name '{glyph}'"),
+    (ids::NO_AXIS_DEFINITIONS, "Missing required axis values for {what}"),
+    (ids::NO_ENTRY_IN_AXES, "Axis {axis} has no entry in axes"),
+    (ids::INCONSISTENT_AXIS_DEFINITIONS, "Axis definitions are inconsistent: '{detail}'"),
+    (ids::NO_SUCH_LAYER, "No layer '{layer}' in {ufo}"),
+    (ids::NO_STATE_FOR_GLYPH, "No files associated with glyph {glyph}"),
+    (ids::NO_LOCATIONS_FOR_GLYPH, "No design space location(s) associated with glyph {glyph}"),
+    (ids::UNABLE_TO_CREATE_GLYPH_IR_WORK, "Asked to create work for something other than the last input we created"),
+    (ids::UNEXPECTED_STATE, "Unexpected state encountered in a state set"),
+    (ids::DUPLICATE_USER_LOCATION, "Duplicate location for {what}: {location}"),
+    (ids::INVALID_GLOBAL_METADATA, "Global metadata very bad, very very bad"),
+    (ids::NO_DEFAULT_MASTER, "No default master in {path}"),
+    (ids::MISSING_MAPPING_FOR_DESIGN_COORD, "Missing mapping on {axis} for {field} at {value}. Mappings {mappings}"),
+    (ids::INVALID_TAG, "Invalid tag '{tag}': {detail}"),
+    (ids::UNSUPPORTED_CONSTRUCT, "Source file contained a construct we don't yet support: {construct}"),
+    (ids::INCONSISTENT_PALETTE_SIZE, "Inconsistent palette size, [0] has {size}, [{palette}] has {other_size}"),
+    (ids::UNKNOWN_ENTRY, "Unknown {what}: {value}"),
+    (ids::INVALID_ENTRY, "Invalid {what}: {detail}"),
+    (ids::INCOMPATIBLE_SOURCES, "{detail}"),
+    (ids::OVERLAPPING_KERNING_GROUPS, "Glyphs in more than one kerning group on the same side: {glyphs}"),
+    (ids::VARIABLE_COMPONENT_TRANSFORMS, "Component 2x2 transforms vary across the designspace, which gvar can't express, in {glyphs}"),
+    (ids::INCONSISTENT_COMPONENTS, "Components differ across the designspace, which gvar can't express, in {glyphs}"),
+    // from the BE
+    (ids::IO_FAILED, "IO failure"),
+    (ids::GLYPH_PROBLEM, "'{glyph}' {problem}"),
+    (ids::MALFORMED_PATH, "'{glyph}' {problem} {context}"),
+    (ids::COMPONENT_PROBLEM, "'{glyph}' references {component}, {problem}"),
+    (ids::COMPONENT_PROBLEMS, "'{glyph}' {problems}"),
+    (ids::DUMP_TABLE_FAILED, "Generating bytes for {context} failed: {detail}"),
+    (ids::OUT_OF_BOUNDS, "{what} out of bounds: {value}"),
+    (ids::GLYPH_DELTAS_FAILED, "Unable to compute deltas for {glyph}: {detail}"),
+    (ids::MVAR_DELTAS_FAILED, "Unable to compute deltas for MVAR {tag}: {detail}"),
+    (ids::AVAR_MODEL_FAILED, "Unable to build a variation model for avar2 mappings: {detail}"),
+    (ids::AVAR_DELTAS_FAILED, "Unable to compute deltas for avar2 mappings: {detail}"),
+    (ids::ANCHOR_DELTAS_FAILED, "Unable to compute deltas for anchor on '{glyph}': '{detail}'"),
+    (ids::GRADIENT_DELTAS_FAILED, "Unable to compute deltas for the gradient of '{glyph}': '{detail}'"),
+    (ids::KERN_DELTAS_FAILED, "Unable to compute deltas for kern pair '{side1}/{side2}': '{detail}'"),
+    (ids::GVAR_FAILED, "Unable to assemble gvar"),
+    (ids::READ_FAILED, "Unable to read"),
+    (ids::IUP_FAILED, "IUP error for {glyph}: {detail}"),
+    (ids::INVALID_TABLE_BYTES, "Unable to interpret bytes as {tag}"),
+    (ids::MISSING_DIRECTORY, "Missing directory:{path}"),
+    (ids::GLYPH_VARIATION_MODEL_FAILED, "Variation model error in '{glyph}': {detail}"),
+    (ids::MISSING_TABLE, "Missing {tag}"),
+    (ids::INCOMPLETE_PATCH, "Can't patch {tag} without also patching {other_tag}"),
+    (ids::EXPECTED_ANCHOR, "Expected an anchor, got {work}"),
+    (ids::MISSING_GLYPH_CLASS, "No glyph class '{glyph}'"),
+    (ids::CONFLICTING_MARK_CLASSES, "Mark glyph '{glyph}' in conflicting classes '{class}' and '{other_class}'"),
+    (ids::NO_VARIATION_MODEL, "No variation model for '{location}'"),
+    (ids::DELTAS_FAILED, "Delta error '{detail}'"),
+    (ids::MISSING_GLYPH_ID, "No glyph id for '{glyph}'"),
+    (ids::CMAP_CONFLICT, "Error making CMap: {detail}"),
+    (ids::INVALID_CMAP, "Unable to make the requested cmap: {detail}"),
+    (ids::INCONSISTENT_STYLE, "Inconsistent style: {detail}"),
+    (ids::COMPOSITES_STALLED, "Progress stalled computing composite bbox: {glyphs}"),
+    (ids::INCONSISTENT_PALETTE_LENGTHS, "Inconsistent palette lengths observed: {lengths}"),
+    (ids::OVERLAPPING_SVG_DOCUMENTS, "SVG documents for {range} and {other_range} overlap"),
+];
+
+/// Templates for messages, by id
+pub trait MessageCatalog: Send + Sync {
+    /// The template for `id`, or None to fall back to [English]
+    fn template(&self, id: &str) -> Option<&str>;
+}
+
+/// The messages fontc writes by default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct English;
+
+impl English {
+    fn get(id: &str) -> Option<&'static str> {
+        ENGLISH
+            .iter()
+            .find_map(|(english_id, template)| (*english_id == id).then_some(*template))
+    }
+}
+
+impl MessageCatalog for English {
+    fn template(&self, id: &str) -> Option<&str> {
+        English::get(id)
+    }
+}
+
+/// A catalog loaded from elsewhere, e.g. a translation file
+impl MessageCatalog for HashMap<String, String> {
+    fn template(&self, id: &str) -> Option<&str> {
+        self.get(id).map(String::as_str)
+    }
+}
+
+static CATALOG: OnceLock<Box<dyn MessageCatalog>> = OnceLock::new();
+
+/// Use `catalog` for every message displayed from now on
+///
+/// May only be called once, returns the catalog back if one was already set.
+pub fn set_catalog(catalog: Box<dyn MessageCatalog>) -> Result<(), Box<dyn MessageCatalog>> {
+    CATALOG.set(catalog)
+}
+
+/// The catalog messages are displayed with, [English] unless [set_catalog] was called
+pub fn catalog() -> &'static dyn MessageCatalog {
+    CATALOG.get().map(Box::as_ref).unwrap_or(&English)
+}
+
+/// A user-facing message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    id: &'static str,
+    args: Vec<(&'static str, String)>,
+}
+
+impl Message {
+    pub fn new(id: &'static str) -> Self {
+        Message {
+            id,
+            args: Vec::new(),
+        }
+    }
+
+    /// Add the argument `name`, for `{name}` in the template
+    pub fn with(mut self, name: &'static str, value: impl Display) -> Self {
+        self.args.push((name, value.to_string()));
+        self
+    }
+
+    /// The stable id of the message, e.g. to match on
+    pub fn id(&self) -> &'static str {
+        self.id
+    }
+
+    /// The value of the argument `name`, if the message has one
+    pub fn arg(&self, name: &str) -> Option<&str> {
+        self.args
+            .iter()
+            .find_map(|(arg_name, value)| (*arg_name == name).then_some(value.as_str()))
+    }
+
+    /// The message, from the template in `catalog`, falling back to [English]
+    ///
+    /// Placeholders with no matching argument are left as they are.
+    pub fn render(&self, catalog: &dyn MessageCatalog) -> String {
+        let Some(template) = catalog.template(self.id).or_else(|| English::get(self.id)) else {
+            return self.id.to_string();
+        };
+        let mut rendered = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(i) = rest.find(['{', '}']) {
+            rendered.push_str(&rest[..i]);
+            rest = &rest[i..];
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                rendered.push_str(&rest[..1]);
+                rest = &rest[2..];
+                continue;
+            }
+            let placeholder = rest[1..]
+                .find('}')
+                .filter(|_| rest.starts_with('{'))
+                .and_then(|end| self.arg(&rest[1..end + 1]).map(|value| (end, value)));
+            match placeholder {
+                Some((end, value)) => {
+                    rendered.push_str(value);
+                    rest = &rest[end + 2..];
+                }
+                None => {
+                    rendered.push_str(&rest[..1]);
+                    rest = &rest[1..];
+                }
+            }
+        }
+        rendered.push_str(rest);
+        rendered
+    }
+}
+
+impl Display for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.render(catalog()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use super::{ids, English, Message, ENGLISH};
+
+    #[test]
+    fn english_ids_are_unique() {
+        let mut seen = HashSet::new();
+        for (id, _) in ENGLISH {
+            assert!(seen.insert(id), "{id} is in the catalog twice");
+        }
+    }
+
+    #[test]
+    fn render_english() {
+        let message = Message::new(ids::CHECKS_FAILED).with("count", 3);
+        assert_eq!(
+            "3 checks failed, the font was not written",
+            message.render(&English)
+        );
+    }
+
+    #[test]
+    fn render_from_catalog_falls_back_to_english() {
+        let catalog = HashMap::from([(
+            ids::CHECKS_FAILED.to_string(),
+            "{count} vérifications ont échoué".to_string(),
+        )]);
+        assert_eq!(
+            "2 vérifications ont échoué",
+            Message::new(ids::CHECKS_FAILED)
+                .with("count", 2)
+                .render(&catalog)
+        );
+        assert_eq!(
+            "Missing file 'a.glyphs'",
+            Message::new(ids::FILE_EXPECTED)
+                .with("path", "a.glyphs")
+                .render(&catalog)
+        );
+    }
+
+    #[test]
+    fn braces_and_unknown_placeholders() {
+        let catalog = HashMap::from([(
+            ids::PANIC.to_string(),
+            "{{literal}} {detail} {missing} }".to_string(),
+        )]);
+        assert_eq!(
+            "{literal} boom {missing} }",
+            Message::new(ids::PANIC)
+                .with("detail", "boom")
+                .render(&catalog)
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! Reporting how far along a compile is
+//!
+//! The workload tells a [Progress] each time a job completes, from the thread that
+//! schedules work, so implementations needn't be fast but mustn't block.
+//! [TerminalProgress] draws a bar on stderr, for `--progress`.
+
+use std::{
+    fmt::Display,
+    io::Write,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use fontbe::orchestration::AnyWorkId;
+
+/// Which half of the compiler a job belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Reading sources into IR
+    Frontend,
+    /// Compiling IR into font tables
+    Backend,
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stage::Frontend => f.write_str("frontend"),
+            Stage::Backend => f.write_str("backend"),
+        }
+    }
+}
+
+/// A job has completed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressEvent {
+    /// How many jobs are complete, including jobs found to be unnecessary
+    pub completed: usize,
+    /// How many jobs there are in all
+    pub total: usize,
+    pub stage: Stage,
+    /// The job that completed
+    pub work: AnyWorkId,
+}
+
+impl ProgressEvent {
+    pub(crate) fn new(completed: usize, total: usize, work: AnyWorkId) -> Self {
+        let stage = match work {
+            AnyWorkId::Fe(..) => Stage::Frontend,
+            AnyWorkId::Be(..) | AnyWorkId::InternalTiming(..) => Stage::Backend,
+        };
+        ProgressEvent {
+            completed,
+            total,
+            stage,
+            work,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.completed >= self.total
+    }
+}
+
+/// Told about each job as it completes
+pub trait Progress: Send + Sync {
+    fn job_completed(&self, event: &ProgressEvent);
+}
+
+/// How wide the bar itself is, in characters
+const BAR_WIDTH: usize = 30;
+/// How often to redraw; glyph jobs complete far faster than a terminal wants to update
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A progress bar on stderr, redrawn in place
+#[derive(Debug, Default)]
+pub struct TerminalProgress {
+    last_drawn: Mutex<Option<Instant>>,
+}
+
+impl Progress for TerminalProgress {
+    fn job_completed(&self, event: &ProgressEvent) {
+        let mut last_drawn = self.last_drawn.lock().unwrap();
+        if !event.is_done() && last_drawn.is_some_and(|last| last.elapsed() < REDRAW_INTERVAL) {
+            return;
+        }
+        *last_drawn = Some(Instant::now());
+
+        let mut stderr = std::io::stderr().lock();
+        // a progress bar that fails to draw isn't worth failing the build over
+        let _ = write!(stderr, "\r\x1b[2K{}", render_bar(event));
+        if event.is_done() {
+            let _ = writeln!(stderr);
+        }
+        let _ = stderr.flush();
+    }
+}
+
+/// e.g. `[##########--------------------] 120/360 backend`
+fn render_bar(event: &ProgressEvent) -> String {
+    let filled = (BAR_WIDTH * event.completed)
+        .checked_div(event.total)
+        .unwrap_or(BAR_WIDTH)
+        .min(BAR_WIDTH);
+    format!(
+        "[{}{}] {}/{} {}",
+        "#".repeat(filled),
+        "-".repeat(BAR_WIDTH - filled),
+        event.completed,
+        event.total,
+        event.stage
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use fontbe::orchestration::{AnyWorkId, WorkId};
+    use fontir::orchestration::WorkId as FeWorkId;
+
+    use super::{render_bar, ProgressEvent, Stage};
+
+    #[test]
+    fn stage_from_work() {
+        assert_eq!(
+            Stage::Frontend,
+            ProgressEvent::new(1, 2, AnyWorkId::Fe(FeWorkId::StaticMetadata)).stage
+        );
+        assert_eq!(
+            Stage::Backend,
+            ProgressEvent::new(1, 2, AnyWorkId::Be(WorkId::Font)).stage
+        );
+    }
+
+    #[test]
+    fn bar_fills_in_proportion() {
+        assert_eq!(
+            format!("[{}{}] 1/3 backend", "#".repeat(10), "-".repeat(20)),
+            render_bar(&ProgressEvent::new(1, 3, WorkId::Font.into()))
+        );
+        assert_eq!(
+            format!("[{}] 3/3 backend", "#".repeat(30)),
+            render_bar(&ProgressEvent::new(3, 3, WorkId::Font.into()))
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//! Per-glyph statistics about a compiled font.
+//!
+//! Written with --report, meant to be diffed between builds to find out which
+//! glyphs made a font grow.
+
+use std::{collections::HashSet, fs, path::Path};
+
+use fontbe::orchestration::{AnyWorkId, Context as BeContext, WorkId as BeWorkIdentifier};
+use fontdrasil::types::GlyphName;
+use fontir::{
+    compatibility::check_glyph,
+    orchestration::{Context as FeContext, WorkId as FeWorkIdentifier},
+};
+use serde::Serialize;
+use write_fonts::{
+    read::{FontRef, TableProvider},
+    tables::glyf::Glyph as RawGlyph,
+};
+
+use crate::Error;
+
+/// Statistics for every glyph in the font, in glyph order.
+#[derive(Debug, Serialize)]
+pub(crate) struct GlyphReport {
+    glyphs: Vec<GlyphStats>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct GlyphStats {
+    name: GlyphName,
+    contours: usize,
+    points: usize,
+    components: usize,
+    gvar_tuples: usize,
+    gvar_bytes: usize,
+    /// The source used components but the compiled glyph doesn't
+    decomposed: bool,
+    /// Ways in which the sources of the compiled glyph don't interpolate, such as
+    /// a missing anchor
+    ///
+    /// Found by checking the glyph IR again, not taken from the warnings of the
+    /// compile, so a glyph exported without variations has none.
+    incompatibilities: Vec<String>,
+}
+
+impl GlyphReport {
+    /// Collect statistics from a successful compilation.
+    ///
+    /// source_composites names the glyphs that had components as read from source.
+    pub(crate) fn new(
+        fe_root: &FeContext,
+        be_root: &BeContext,
+        source_composites: &HashSet<GlyphName>,
+    ) -> Result<Self, Error> {
+        let font_data = be_root.font.get();
+        let font = FontRef::new(font_data.get())?;
+        let gvar = font.gvar().ok();
+
+        let mut glyphs = Vec::new();
+        for (gid, name) in fe_root.glyph_order.get().iter() {
+            let be_glyph = be_root
+                .glyphs
+                .get(&AnyWorkId::Be(BeWorkIdentifier::GlyfFragment(name.clone())));
+            let (contours, points, components) = match &be_glyph.data {
+                RawGlyph::Empty => (0, 0, 0),
+                RawGlyph::Simple(simple) => (
+                    simple.contours.len(),
+                    simple.contours.iter().map(|c| c.len()).sum(),
+                    0,
+                ),
+                RawGlyph::Composite(composite) => (0, 0, composite.components().len()),
+            };
+
+            let (mut gvar_tuples, mut gvar_bytes) = (0, 0);
+            if let Some(gvar) = &gvar {
+                let offsets = gvar.glyph_variation_data_offsets();
+                let start = offsets.get(gid.to_u16() as usize)?.get();
+                let end = offsets.get(gid.to_u16() as usize + 1)?.get();
+                gvar_bytes = (end - start) as usize;
+                if let Some(data) = gvar.glyph_variation_data(gid.into())? {
+                    gvar_tuples = data.tuples().count();
+                }
+            }
+
+            let ir_glyph = fe_root.glyphs.get(&FeWorkIdentifier::Glyph(name.clone()));
+            let anchors = fe_root
+                .anchors
+                .try_get(&FeWorkIdentifier::Anchor(name.clone()));
+            let incompatibilities = check_glyph(&ir_glyph, anchors.as_deref())
+                .into_iter()
+                .map(|incompatibility| incompatibility.to_string())
+                .collect();
+
+            glyphs.push(GlyphStats {
+                name: name.clone(),
+                contours,
+                points,
+                components,
+                gvar_tuples,
+                gvar_bytes,
+                decomposed: source_composites.contains(name) && !be_glyph.is_composite(),
+                incompatibilities,
+            });
+        }
+        Ok(GlyphReport { glyphs })
+    }
+
+    /// Write the report to path as JSON.
+    pub(crate) fn write(&self, path: &Path) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json).map_err(|source| Error::FileIo {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::{testdata_dir, Args, JobTimer};
+
+    fn report_for(source: &str) -> serde_json::Value {
+        let temp_dir = tempdir().unwrap();
+        let mut args = Args::new(temp_dir.path(), testdata_dir().join(source));
+        let report = temp_dir.path().join("report.json");
+        args.report = Some(report.clone());
+        crate::compile_once(&args, JobTimer::default(), |_| ()).unwrap();
+        serde_json::from_str(&fs::read_to_string(report).unwrap()).unwrap()
+    }
+
+    fn stats<'a>(report: &'a serde_json::Value, name: &str) -> &'a serde_json::Value {
+        report["glyphs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|g| g["name"] == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn report_outline_and_variation_stats() {
+        let report = report_for("glyphs3/WghtVar.glyphs");
+        let hyphen = stats(&report, "hyphen");
+        assert_eq!(
+            (1, 4, 0, 1, false),
+            (
+                hyphen["contours"].as_u64().unwrap(),
+                hyphen["points"].as_u64().unwrap(),
+                hyphen["components"].as_u64().unwrap(),
+                hyphen["gvar_tuples"].as_u64().unwrap(),
+                hyphen["decomposed"].as_bool().unwrap(),
+            )
+        );
+        assert!(hyphen["gvar_bytes"].as_u64().unwrap() > 0);
+        assert_eq!(
+            Some(0),
+            hyphen["incompatibilities"].as_array().map(Vec::len)
+        );
+    }
+
+    #[test]
+    fn report_decomposed_glyph() {
+        // manual-component scales a component in only one master so it can't stay a composite
+        let report = report_for("glyphs3/WghtVar.glyphs");
+        let glyph = stats(&report, "manual-component");
+        assert_eq!(Some(true), glyph["decomposed"].as_bool());
+        assert_eq!(Some(0), glyph["components"].as_u64());
+    }
+}
@@ -0,0 +1,163 @@
+//! Report how big each table of the compiled font is.
+//!
+//! Optionally compared to a baseline, either a font or the JSON written by
+//! a previous build, to see where size changes came from.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use write_fonts::read::FontRef;
+
+use crate::{Args, Error};
+
+/// The size of each table in a font, in bytes.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct TableSizes {
+    /// Size of the whole font file, including the table directory and padding
+    pub(crate) total: usize,
+    /// Tag => size
+    pub(crate) tables: BTreeMap<String, usize>,
+}
+
+impl TableSizes {
+    pub(crate) fn new(font_data: &[u8]) -> Result<Self, Error> {
+        let font = FontRef::new(font_data)?;
+        let tables = font
+            .table_directory
+            .table_records()
+            .iter()
+            .map(|record| (record.tag().to_string(), record.length() as usize))
+            .collect();
+        Ok(TableSizes {
+            total: font_data.len(),
+            tables,
+        })
+    }
+
+    /// Load sizes from a font file or, if it has a .json extension, a previous report.
+    pub(crate) fn load(path: &Path) -> Result<Self, Error> {
+        let data = fs::read(path).map_err(|source| Error::FileIo {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            Ok(serde_json::from_slice(&data)?)
+        } else {
+            TableSizes::new(&data)
+        }
+    }
+
+    /// Human readable breakdown, largest table first, with changes relative to baseline if given.
+    pub(crate) fn to_table(&self, baseline: Option<&TableSizes>) -> String {
+        let mut tags: Vec<_> = self
+            .tables
+            .keys()
+            .chain(baseline.into_iter().flat_map(|b| b.tables.keys()))
+            .collect();
+        let size = |tag: &str| self.tables.get(tag).copied().unwrap_or_default();
+        tags.sort_by(|a, b| size(b).cmp(&size(a)).then(a.cmp(b)));
+        tags.dedup();
+
+        let mut lines = Vec::new();
+        let mut line = |name: &str, size: Option<usize>, before: Option<usize>| {
+            let size_str = size.map(|s| s.to_string()).unwrap_or("-".to_string());
+            let mut line = format!("{name:<6}{size_str:>12}");
+            if let Some(size) = size {
+                let percent = 100.0 * size as f64 / self.total.max(1) as f64;
+                line.push_str(&format!("{percent:>8.1}%"));
+            } else {
+                line.push_str(&format!("{:>9}", ""));
+            }
+            if baseline.is_some() {
+                let delta = size.unwrap_or_default() as i64 - before.unwrap_or_default() as i64;
+                line.push_str(&format!("{delta:>+12}"));
+            }
+            lines.push(line.trim_end().to_string());
+        };
+        for tag in tags {
+            line(
+                tag,
+                self.tables.get(tag).copied(),
+                baseline.and_then(|b| b.tables.get(tag).copied()),
+            );
+        }
+        line("total", Some(self.total), baseline.map(|b| b.total));
+        lines.join("\n")
+    }
+}
+
+/// Print and/or write table sizes of the font just written, as requested by args.
+pub(crate) fn report_table_sizes(args: &Args, font_data: &[u8]) -> Result<(), Error> {
+    if !args.table_sizes && args.table_sizes_json.is_none() {
+        return Ok(());
+    }
+    let sizes = TableSizes::new(font_data)?;
+    if args.table_sizes {
+        let baseline = args
+            .table_sizes_baseline
+            .as_deref()
+            .map(TableSizes::load)
+            .transpose()?;
+        println!("{}", sizes.to_table(baseline.as_ref()));
+    }
+    if let Some(path) = &args.table_sizes_json {
+        let json = serde_json::to_string_pretty(&sizes)?;
+        fs::write(path, json).map_err(|source| Error::FileIo {
+            path: path.clone(),
+            source,
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sizes(total: usize, tables: &[(&str, usize)]) -> TableSizes {
+        TableSizes {
+            total,
+            tables: tables
+                .iter()
+                .map(|(tag, size)| (tag.to_string(), *size))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn largest_table_first() {
+        let sizes = sizes(100, &[("head", 54), ("glyf", 30)]);
+        assert_eq!(
+            vec![
+                "head            54    54.0%",
+                "glyf            30    30.0%",
+                "total          100   100.0%",
+            ],
+            sizes.to_table(None).lines().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn deltas_from_baseline() {
+        let before = sizes(90, &[("glyf", 40), ("kern", 10)]);
+        let after = sizes(100, &[("glyf", 30), ("GPOS", 30)]);
+        assert_eq!(
+            vec![
+                "GPOS            30    30.0%         +30",
+                "glyf            30    30.0%         -10",
+                "kern             -                  -10",
+                "total          100   100.0%         +10",
+            ],
+            after.to_table(Some(&before)).lines().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("sizes.json");
+        let sizes = sizes(100, &[("glyf", 30)]);
+        fs::write(&path, serde_json::to_string(&sizes).unwrap()).unwrap();
+        assert_eq!(sizes, TableSizes::load(&path).unwrap());
+    }
+}
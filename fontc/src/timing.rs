@@ -199,10 +199,61 @@ impl JobTimer {
     }
 }
 
+#[cfg(feature = "cli")]
+impl JobTimer {
+    /// Write timing in the Chrome trace event format.
+    ///
+    /// Each thread gets a lane and each unit of work a span. The result can be
+    /// loaded into chrome://tracing or <https://ui.perfetto.dev> to look for
+    /// scheduling gaps and the critical path.
+    ///
+    /// See <https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU>
+    pub fn write_chrome_trace(&self, out: &mut impl std::io::Write) -> Result<(), std::io::Error> {
+        let micros = |instant: Instant| (instant - self.t0).as_secs_f64() * 1_000_000.0;
+
+        let mut thread_ids: Vec<_> = self.job_times.keys().collect();
+        // Lanes in the order threads started doing work
+        thread_ids.sort_by_key(|tid| self.job_times[*tid].iter().map(|t| t.run).min());
+
+        let mut events = Vec::new();
+        for (lane, tid) in thread_ids.into_iter().enumerate() {
+            events.push(serde_json::json!({
+                "name": "thread_name",
+                "ph": "M",
+                "pid": 1,
+                "tid": lane,
+                "args": { "name": format!("t{lane}") },
+            }));
+            for timing in self.job_times[tid].iter() {
+                events.push(serde_json::json!({
+                    "name": format!("{:?}", timing.id),
+                    "cat": short_name(&timing.id),
+                    "ph": "X",
+                    "pid": 1,
+                    "tid": lane,
+                    "ts": micros(timing.run),
+                    "dur": micros(timing.complete) - micros(timing.run),
+                    "args": {
+                        "queued_us": micros(timing.queued),
+                        "wave": timing.nth_wave,
+                    },
+                }));
+            }
+        }
+        let trace = serde_json::json!({
+            "traceEvents": events,
+            "displayTimeUnit": "ms",
+        });
+        serde_json::to_writer(&mut *out, &trace)?;
+        writeln!(out)
+    }
+}
+
 fn short_name(id: &AnyWorkId) -> &'static str {
     match id {
         AnyWorkId::Fe(FeWorkIdentifier::Anchor(..)) => "anchor",
         AnyWorkId::Fe(FeWorkIdentifier::ColorPalettes) => "cpal",
+        AnyWorkId::Fe(FeWorkIdentifier::CompatibilityCheck) => "compat",
         AnyWorkId::Fe(FeWorkIdentifier::Features) => "fea",
         AnyWorkId::Fe(FeWorkIdentifier::GlobalMetrics) => "metrics",
         AnyWorkId::Fe(FeWorkIdentifier::Glyph(..)) => "glyph",
@@ -210,6 +261,8 @@ fn short_name(id: &AnyWorkId) -> &'static str {
         AnyWorkId::Fe(FeWorkIdentifier::KerningGroups) => "kerngrps",
         AnyWorkId::Fe(FeWorkIdentifier::KernInstance(..)) => "kernat",
         AnyWorkId::Fe(FeWorkIdentifier::PaintGraph) => "colr",
+        AnyWorkId::Fe(FeWorkIdentifier::BitmapStrikes) => "bitmaps",
+        AnyWorkId::Fe(FeWorkIdentifier::SvgDocuments) => "svgs",
         AnyWorkId::Fe(FeWorkIdentifier::PreliminaryGlyphOrder) => "pre-go",
         AnyWorkId::Fe(FeWorkIdentifier::StaticMetadata) => "static-meta",
         AnyWorkId::Be(BeWorkIdentifier::Avar) => "avar",
@@ -243,7 +296,14 @@ fn short_name(id: &AnyWorkId) -> &'static str {
         AnyWorkId::Be(BeWorkIdentifier::Name) => "name",
         AnyWorkId::Be(BeWorkIdentifier::Os2) => "OS/2",
         AnyWorkId::Be(BeWorkIdentifier::Post) => "post",
+        AnyWorkId::Be(BeWorkIdentifier::Kern) => "kern",
+        AnyWorkId::Be(BeWorkIdentifier::Math) => "MATH",
+        AnyWorkId::Be(BeWorkIdentifier::Sbix) => "sbix",
+        AnyWorkId::Be(BeWorkIdentifier::Cbdt) => "CBDT",
+        AnyWorkId::Be(BeWorkIdentifier::Cblc) => "CBLC",
+        AnyWorkId::Be(BeWorkIdentifier::Svg) => "SVG",
         AnyWorkId::Be(BeWorkIdentifier::Meta) => "meta",
+        AnyWorkId::Be(BeWorkIdentifier::Base) => "BASE",
         AnyWorkId::Be(BeWorkIdentifier::Stat) => "STAT",
         AnyWorkId::Be(BeWorkIdentifier::Vhea) => "vhea",
         AnyWorkId::Be(BeWorkIdentifier::Vmtx) => "vmtx",
@@ -334,3 +394,38 @@ impl JobTime {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chrome_trace_has_a_span_per_job() {
+        let mut timer = JobTimer::new();
+        for name in ["a", "b"] {
+            let timing = timer
+                .create_timer(AnyWorkId::InternalTiming(name), 0)
+                .queued()
+                .run()
+                .complete();
+            timer.add(timing);
+        }
+
+        let mut buf = Vec::new();
+        timer.write_chrome_trace(&mut buf).unwrap();
+        let trace: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+
+        let events = trace["traceEvents"].as_array().unwrap();
+        let spans: Vec<_> = events
+            .iter()
+            .filter(|e| e["ph"] == "X")
+            .map(|e| e["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            vec!["InternalTiming(\"a\")", "InternalTiming(\"b\")"],
+            spans
+        );
+        // one thread, one lane
+        assert_eq!(1, events.iter().filter(|e| e["ph"] == "M").count());
+    }
+}
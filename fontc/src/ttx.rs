@@ -0,0 +1,346 @@
+//! Write tables of the compiled font as ttx, the xml of fonttools.
+//!
+//! Requested with --emit-basic-ttx; the output matches `ttx -t <tag>` closely
+//! enough to diff against it, without needing fonttools installed. Only the basic
+//! tables, simple enough to write field by field, are supported.
+
+use std::{fs, path::Path};
+
+use chrono::{TimeZone, Utc};
+use log::warn;
+use write_fonts::{
+    from_obj::ToOwnedTable,
+    read::{FontRef, TableProvider},
+    tables::{head::Head, hhea::Hhea, maxp::Maxp, name::Name, os2::Os2},
+    types::{Fixed, LongDateTime, Tag},
+};
+
+use crate::Error;
+
+/// Tables we can write as ttx
+pub(crate) const SUPPORTED: &[Tag] = &[
+    Tag::new(b"head"),
+    Tag::new(b"hhea"),
+    Tag::new(b"maxp"),
+    Tag::new(b"OS/2"),
+    Tag::new(b"name"),
+];
+
+// The TrueType epoch (1st January 1904) as a Unix timestamp
+const MACINTOSH_EPOCH: i64 = -2082844800;
+
+/// Write the tables of font_data named by tags to path, as a single ttx file
+pub(crate) fn write_ttx(font_data: &[u8], tags: &[Tag], path: &Path) -> Result<(), Error> {
+    let ttx = to_ttx(font_data, tags)?;
+    fs::write(path, ttx).map_err(|source| Error::FileIo {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn to_ttx(font_data: &[u8], tags: &[Tag]) -> Result<String, Error> {
+    let font = FontRef::new(font_data)?;
+    let mut ttx = Ttx::default();
+    ttx.line(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    ttx.line(r#"<ttFont sfntVersion="\x00\x01\x00\x00" ttLibVersion="4.0">"#);
+    for tag in tags {
+        if !SUPPORTED.contains(tag) {
+            warn!("Unable to write '{tag}' as ttx, only {SUPPORTED:?} are supported");
+            continue;
+        }
+        if font.table_data(*tag).is_none() {
+            warn!("Unable to write '{tag}' as ttx, the font doesn't have one");
+            continue;
+        }
+        ttx.line("");
+        ttx.indent += 1;
+        match &tag.to_be_bytes() {
+            b"head" => write_head(&mut ttx, &font.head()?.to_owned_table()),
+            b"hhea" => write_hhea(&mut ttx, &font.hhea()?.to_owned_table()),
+            b"maxp" => write_maxp(&mut ttx, &font.maxp()?.to_owned_table()),
+            b"OS/2" => {
+                let os2 = font.os2()?;
+                write_os2(&mut ttx, os2.version(), &os2.to_owned_table())
+            }
+            b"name" => write_name(&mut ttx, &font.name()?.to_owned_table()),
+            _ => unreachable!("{tag} is supported"),
+        }
+        ttx.indent -= 1;
+    }
+    ttx.line("");
+    ttx.line("</ttFont>");
+    Ok(ttx.text)
+}
+
+/// Text with two space indentation, as ttx does it
+#[derive(Default)]
+struct Ttx {
+    text: String,
+    indent: usize,
+}
+
+impl Ttx {
+    fn line(&mut self, line: &str) {
+        if !line.is_empty() {
+            self.text.push_str(&"  ".repeat(self.indent));
+        }
+        self.text.push_str(line);
+        self.text.push('\n');
+    }
+
+    fn open(&mut self, element: &str) {
+        self.line(&format!("<{element}>"));
+        self.indent += 1;
+    }
+
+    fn close(&mut self, element: &str) {
+        self.indent -= 1;
+        self.line(&format!("</{element}>"));
+    }
+
+    fn simple(&mut self, element: &str, value: impl std::fmt::Display) {
+        self.line(&format!(r#"<{element} value="{value}"/>"#));
+    }
+}
+
+fn write_head(ttx: &mut Ttx, head: &Head) {
+    ttx.open("head");
+    ttx.line("<!-- Most of this table will be recalculated by the compiler -->");
+    ttx.simple("tableVersion", "1.0");
+    ttx.simple("fontRevision", fixed(head.font_revision));
+    ttx.simple(
+        "checkSumAdjustment",
+        format!("{:#x}", head.checksum_adjustment),
+    );
+    ttx.simple("magicNumber", "0x5f0f3cf5");
+    ttx.simple("flags", binary(head.flags.bits() as u32, 16));
+    ttx.simple("unitsPerEm", head.units_per_em);
+    ttx.simple("created", timestamp(head.created));
+    ttx.simple("modified", timestamp(head.modified));
+    ttx.simple("xMin", head.x_min);
+    ttx.simple("yMin", head.y_min);
+    ttx.simple("xMax", head.x_max);
+    ttx.simple("yMax", head.y_max);
+    ttx.simple("macStyle", binary(head.mac_style.bits() as u32, 16));
+    ttx.simple("lowestRecPPEM", head.lowest_rec_ppem);
+    ttx.simple("fontDirectionHint", head.font_direction_hint);
+    ttx.simple("indexToLocFormat", head.index_to_loc_format);
+    ttx.simple("glyphDataFormat", 0);
+    ttx.close("head");
+}
+
+fn write_hhea(ttx: &mut Ttx, hhea: &Hhea) {
+    ttx.open("hhea");
+    ttx.simple("tableVersion", "0x00010000");
+    ttx.simple("ascent", hhea.ascender.to_i16());
+    ttx.simple("descent", hhea.descender.to_i16());
+    ttx.simple("lineGap", hhea.line_gap.to_i16());
+    ttx.simple("advanceWidthMax", hhea.advance_width_max.to_u16());
+    ttx.simple("minLeftSideBearing", hhea.min_left_side_bearing.to_i16());
+    ttx.simple("minRightSideBearing", hhea.min_right_side_bearing.to_i16());
+    ttx.simple("xMaxExtent", hhea.x_max_extent.to_i16());
+    ttx.simple("caretSlopeRise", hhea.caret_slope_rise);
+    ttx.simple("caretSlopeRun", hhea.caret_slope_run);
+    ttx.simple("caretOffset", hhea.caret_offset);
+    for i in 0..4 {
+        ttx.simple(&format!("reserved{i}"), 0);
+    }
+    ttx.simple("metricDataFormat", 0);
+    ttx.simple("numberOfHMetrics", hhea.number_of_h_metrics);
+    ttx.close("hhea");
+}
+
+fn write_maxp(ttx: &mut Ttx, maxp: &Maxp) {
+    ttx.open("maxp");
+    ttx.line("<!-- Most of this table will be recalculated by the compiler -->");
+    let Some(max_points) = maxp.max_points else {
+        ttx.simple("tableVersion", "0x5000");
+        ttx.simple("numGlyphs", maxp.num_glyphs);
+        ttx.close("maxp");
+        return;
+    };
+    ttx.simple("tableVersion", "0x10000");
+    ttx.simple("numGlyphs", maxp.num_glyphs);
+    ttx.simple("maxPoints", max_points);
+    for (element, value) in [
+        ("maxContours", maxp.max_contours),
+        ("maxCompositePoints", maxp.max_composite_points),
+        ("maxCompositeContours", maxp.max_composite_contours),
+        ("maxZones", maxp.max_zones),
+        ("maxTwilightPoints", maxp.max_twilight_points),
+        ("maxStorage", maxp.max_storage),
+        ("maxFunctionDefs", maxp.max_function_defs),
+        ("maxInstructionDefs", maxp.max_instruction_defs),
+        ("maxStackElements", maxp.max_stack_elements),
+        ("maxSizeOfInstructions", maxp.max_size_of_instructions),
+        ("maxComponentElements", maxp.max_component_elements),
+        ("maxComponentDepth", maxp.max_component_depth),
+    ] {
+        ttx.simple(element, value.unwrap_or_default());
+    }
+    ttx.close("maxp");
+}
+
+fn write_os2(ttx: &mut Ttx, version: u16, os2: &Os2) {
+    ttx.open("OS_2");
+    ttx.line("<!-- The fields 'usFirstCharIndex' and 'usLastCharIndex'");
+    ttx.line("     will be recalculated by the compiler -->");
+    ttx.simple("version", version);
+    ttx.simple("xAvgCharWidth", os2.x_avg_char_width);
+    ttx.simple("usWeightClass", os2.us_weight_class);
+    ttx.simple("usWidthClass", os2.us_width_class);
+    ttx.simple("fsType", binary(os2.fs_type as u32, 16));
+    ttx.simple("ySubscriptXSize", os2.y_subscript_x_size);
+    ttx.simple("ySubscriptYSize", os2.y_subscript_y_size);
+    ttx.simple("ySubscriptXOffset", os2.y_subscript_x_offset);
+    ttx.simple("ySubscriptYOffset", os2.y_subscript_y_offset);
+    ttx.simple("ySuperscriptXSize", os2.y_superscript_x_size);
+    ttx.simple("ySuperscriptYSize", os2.y_superscript_y_size);
+    ttx.simple("ySuperscriptXOffset", os2.y_superscript_x_offset);
+    ttx.simple("ySuperscriptYOffset", os2.y_superscript_y_offset);
+    ttx.simple("yStrikeoutSize", os2.y_strikeout_size);
+    ttx.simple("yStrikeoutPosition", os2.y_strikeout_position);
+    ttx.simple("sFamilyClass", os2.s_family_class);
+    ttx.open("panose");
+    for (element, value) in [
+        "bFamilyType",
+        "bSerifStyle",
+        "bWeight",
+        "bProportion",
+        "bContrast",
+        "bStrokeVariation",
+        "bArmStyle",
+        "bLetterForm",
+        "bMidline",
+        "bXHeight",
+    ]
+    .into_iter()
+    .zip(os2.panose_10)
+    {
+        ttx.simple(element, value);
+    }
+    ttx.close("panose");
+    for (i, range) in [
+        os2.ul_unicode_range_1,
+        os2.ul_unicode_range_2,
+        os2.ul_unicode_range_3,
+        os2.ul_unicode_range_4,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        ttx.simple(&format!("ulUnicodeRange{}", i + 1), binary(range, 32));
+    }
+    ttx.simple("achVendID", os2.ach_vend_id);
+    ttx.simple("fsSelection", binary(os2.fs_selection.bits() as u32, 16));
+    ttx.simple("usFirstCharIndex", os2.us_first_char_index);
+    ttx.simple("usLastCharIndex", os2.us_last_char_index);
+    ttx.simple("sTypoAscender", os2.s_typo_ascender);
+    ttx.simple("sTypoDescender", os2.s_typo_descender);
+    ttx.simple("sTypoLineGap", os2.s_typo_line_gap);
+    ttx.simple("usWinAscent", os2.us_win_ascent);
+    ttx.simple("usWinDescent", os2.us_win_descent);
+    if version >= 1 {
+        for (i, range) in [os2.ul_code_page_range_1, os2.ul_code_page_range_2]
+            .into_iter()
+            .enumerate()
+        {
+            ttx.simple(
+                &format!("ulCodePageRange{}", i + 1),
+                binary(range.unwrap_or_default(), 32),
+            );
+        }
+    }
+    if version >= 2 {
+        ttx.simple("sxHeight", os2.sx_height.unwrap_or_default());
+        ttx.simple("sCapHeight", os2.s_cap_height.unwrap_or_default());
+        ttx.simple("usDefaultChar", os2.us_default_char.unwrap_or_default());
+        ttx.simple("usBreakChar", os2.us_break_char.unwrap_or_default());
+        ttx.simple("usMaxContext", os2.us_max_context.unwrap_or_default());
+    }
+    if version >= 5 {
+        ttx.simple(
+            "usLowerOpticalPointSize",
+            os2.us_lower_optical_point_size.unwrap_or_default(),
+        );
+        ttx.simple(
+            "usUpperOpticalPointSize",
+            os2.us_upper_optical_point_size.unwrap_or_default(),
+        );
+    }
+    ttx.close("OS_2");
+}
+
+fn write_name(ttx: &mut Ttx, name: &Name) {
+    ttx.open("name");
+    for record in name.name_record.iter() {
+        ttx.open(&format!(
+            r#"namerecord nameID="{}" platformID="{}" platEncID="{}" langID="{:#x}""#,
+            record.name_id.to_u16(),
+            record.platform_id,
+            record.encoding_id,
+            record.language_id
+        ));
+        ttx.line(&escape(&record.string));
+        ttx.close("namerecord");
+    }
+    ttx.close("name");
+}
+
+/// The shortest decimal that is the same Fixed, as fonttools writes them
+fn fixed(value: Fixed) -> String {
+    let value = value.to_f64();
+    (1..=5)
+        .map(|digits| format!("{value:.digits$}"))
+        .find(|s| Fixed::from_f64(s.parse().unwrap()).to_f64() == value)
+        .unwrap_or_else(|| value.to_string())
+}
+
+/// e.g. "00000000 00000011", most significant bit first in groups of 8
+fn binary(value: u32, bits: u32) -> String {
+    (0..bits / 8)
+        .rev()
+        .map(|byte| format!("{:08b}", (value >> (8 * byte)) & 0xFF))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// e.g. "Thu Jan  1 00:00:00 1970", as python's time.asctime
+fn timestamp(value: LongDateTime) -> String {
+    match Utc
+        .timestamp_opt(value.as_secs() + MACINTOSH_EPOCH, 0)
+        .single()
+    {
+        Some(time) => time.format("%a %b %e %H:%M:%S %Y").to_string(),
+        None => value.as_secs().to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_format_as_fonttools_does() {
+        assert_eq!("1.0", fixed(Fixed::from_f64(1.0)));
+        assert_eq!("1.001", fixed(Fixed::from_f64(1.001)));
+        assert_eq!("00000000 00000011", binary(3, 16));
+        assert_eq!(
+            "Thu Jan  1 00:00:00 1970",
+            timestamp(LongDateTime::new(-MACINTOSH_EPOCH))
+        );
+    }
+}
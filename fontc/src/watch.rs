@@ -0,0 +1,210 @@
+//! Recompile whenever the source changes.
+//!
+//! Change detection is done by polling modification times, which avoids a dependency
+//! on platform specific file notification APIs and is plenty fast for a preview loop.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use log::{debug, error};
+use norad::designspace::DesignSpaceDocument;
+
+use crate::{incremental::PreviousBuild, Args, Error, JobTimer};
+
+/// How long to wait between checks for changes to the source
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The modification time of every file that contributes to a source.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct SourceSnapshot(BTreeMap<PathBuf, SystemTime>);
+
+impl SourceSnapshot {
+    /// Record the state of the files that make up the source at root.
+    pub(crate) fn capture(root: &Path) -> Result<Self, Error> {
+        let mut snapshot = SourceSnapshot::default();
+        for file in source_files(root)? {
+            let modified = fs::metadata(&file)
+                .and_then(|m| m.modified())
+                .map_err(|source| Error::FileIo {
+                    path: file.clone(),
+                    source,
+                })?;
+            snapshot.0.insert(file, modified);
+        }
+        Ok(snapshot)
+    }
+}
+
+/// The files that make up the source at root, in a stable order.
+///
+/// Directory sources (.ufo, .glyphspackage, .fontra) are walked recursively.
+/// A .designspace also picks up any .ufo that sits alongside it, which is
+/// where designspace sources typically keep their masters, as well as the masters
+/// it names wherever they are. Feature files alongside a .glyphs or .designspace
+/// are included as they are commonly pulled in with `include`.
+pub(crate) fn source_files(root: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    add_files(root, &mut files)?;
+    let ext = root.extension().and_then(|e| e.to_str());
+    if matches!(ext, Some("designspace") | Some("glyphs")) {
+        let parent = root
+            .parent()
+            .filter(|p| *p != Path::new(""))
+            .unwrap_or(Path::new("."));
+        for entry in read_dir(parent)? {
+            match entry.extension().and_then(|e| e.to_str()) {
+                Some("ufo") if ext == Some("designspace") => add_files(&entry, &mut files)?,
+                Some("fea") if entry.is_file() => files.push(entry),
+                _ => (),
+            }
+        }
+        if ext == Some("designspace") {
+            // A designspace that fails to load is reported by the compile itself
+            if let Ok(designspace) = DesignSpaceDocument::load(root) {
+                for source in designspace.sources.iter() {
+                    let master = parent.join(&source.filename);
+                    if master.exists() {
+                        add_files(&master, &mut files)?;
+                    }
+                }
+            }
+        }
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+fn add_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    if path.is_dir() {
+        for entry in read_dir(path)? {
+            add_files(&entry, files)?;
+        }
+    } else {
+        files.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+fn read_dir(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let io_err = |source| Error::FileIo {
+        path: dir.to_path_buf(),
+        source,
+    };
+    let mut entries = fs::read_dir(dir)
+        .map_err(io_err)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io_err)?;
+    entries.sort();
+    Ok(entries)
+}
+
+/// Compile, then recompile each time the source changes. Runs until killed.
+///
+/// Failed compiles are reported but do not end the loop; presumably the
+/// next edit will fix things.
+pub(crate) fn watch(args: &Args, timer: JobTimer) -> Result<(), Error> {
+    let root = args.source_path().to_path_buf();
+    let mut timer = Some(timer);
+    let mut previous = None;
+    let mut n_build = 0;
+    loop {
+        let snapshot = SourceSnapshot::capture(&root)?;
+
+        n_build += 1;
+        let start = Instant::now();
+        let previous_build = previous.take();
+        let result = crate::compile_once(args, timer.take().unwrap_or_default(), |workload| {
+            if let Some(previous_build) = previous_build {
+                workload.reuse_unchanged_glyphs(previous_build);
+            }
+        });
+        let elapsed = start.elapsed();
+        match result {
+            Ok((fe_root, be_root)) => {
+                println!(
+                    "build {n_build}: compiled {} in {:.0}ms",
+                    root.display(),
+                    elapsed.as_secs_f64() * 1000.0
+                );
+                // Glyphs that don't change before the next build can skip BE work
+                previous = Some(PreviousBuild::new(&fe_root, be_root));
+            }
+            Err(e) => error!(
+                "build {n_build}: failed after {:.0}ms: {e}",
+                elapsed.as_secs_f64() * 1000.0
+            ),
+        }
+
+        // Wait for something to change. A source that is briefly unreadable,
+        // e.g. mid-save, is treated as unchanged until it settles.
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            match SourceSnapshot::capture(&root) {
+                Ok(current) if current != snapshot => break,
+                Ok(_) => (),
+                Err(e) => debug!("Unable to check {root:?} for changes: {e}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn snapshot_detects_new_file() {
+        let temp_dir = tempdir().unwrap();
+        let ufo = temp_dir.path().join("Test.ufo");
+        fs::create_dir(&ufo).unwrap();
+        fs::write(ufo.join("fontinfo.plist"), "").unwrap();
+
+        let before = SourceSnapshot::capture(&ufo).unwrap();
+        assert_eq!(before, SourceSnapshot::capture(&ufo).unwrap());
+
+        fs::create_dir(ufo.join("glyphs")).unwrap();
+        fs::write(ufo.join("glyphs").join("a.glif"), "").unwrap();
+        assert_ne!(before, SourceSnapshot::capture(&ufo).unwrap());
+    }
+
+    #[test]
+    fn glyphs_source_files_include_sibling_fea() {
+        let temp_dir = tempdir().unwrap();
+        let glyphs = temp_dir.path().join("Test.glyphs");
+        fs::write(&glyphs, "").unwrap();
+        fs::write(temp_dir.path().join("Test.fea"), "").unwrap();
+        fs::write(temp_dir.path().join("README.md"), "").unwrap();
+
+        assert_eq!(
+            vec![temp_dir.path().join("Test.fea"), glyphs.clone()],
+            source_files(&glyphs).unwrap()
+        );
+    }
+
+    #[test]
+    fn designspace_snapshot_includes_sibling_ufos() {
+        let temp_dir = tempdir().unwrap();
+        let designspace = temp_dir.path().join("Test.designspace");
+        fs::write(&designspace, "").unwrap();
+        let ufo = temp_dir.path().join("Test-Regular.ufo");
+        fs::create_dir(&ufo).unwrap();
+        fs::write(ufo.join("fontinfo.plist"), "").unwrap();
+
+        let snapshot = SourceSnapshot::capture(&designspace).unwrap();
+        assert_eq!(
+            vec![ufo.join("fontinfo.plist"), designspace],
+            snapshot.0.into_keys().collect::<Vec<_>>()
+        );
+    }
+}
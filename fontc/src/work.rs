@@ -2,11 +2,32 @@
 //!
 //! Basically enums that can be a FeWhatever or a BeWhatever.
 
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use chrono::{DateTime, Utc};
 use fontbe::orchestration::{AnyWorkId, BeWork, Context as BeContext};
-use fontdrasil::orchestration::{Access, AccessType};
-use fontir::orchestration::{Context as FeContext, IrWork, WorkId};
+use fontdrasil::{
+    orchestration::{Access, AccessBuilder, AccessType, Work},
+    types::GlyphName,
+};
+use fontir::{
+    error::BadSource,
+    ir::{BitmapGlyph, BitmapStrikes, CodepointRange, GlyphOrder, StaticMetadata, SvgDocuments},
+    orchestration::{Context as FeContext, IrWork, WorkId},
+};
+use log::warn;
+use write_fonts::{
+    read::{FontRef, TableProvider},
+    types::{GlyphId16, Tag},
+};
 
-use crate::Error;
+use crate::{Error, GlyphOrderMode};
 
 // Work of any type, FE, BE, ... some future pass, w/e
 #[derive(Debug)]
@@ -77,6 +98,654 @@ impl AnyWork {
     }
 }
 
+/// Glyph IR work for several glyphs run as a single job.
+///
+/// Every job has scheduling overhead. For fonts with many glyphs, such as CJK,
+/// that overhead can exceed the cost of producing the IR for a single glyph.
+///
+/// The BE work for each glyph, see `Workload::update_be_glyph_work`, isn't batched.
+#[derive(Debug)]
+pub(crate) struct GlyphIrBatch(Vec<Box<IrWork>>);
+
+impl GlyphIrBatch {
+    /// Create a batch; panics if works is empty.
+    pub(crate) fn new(works: Vec<Box<IrWork>>) -> Box<IrWork> {
+        assert!(!works.is_empty(), "A batch must contain work");
+        Box::new(GlyphIrBatch(works))
+    }
+}
+
+impl Work<FeContext, WorkId, fontir::error::Error> for GlyphIrBatch {
+    fn id(&self) -> WorkId {
+        self.0[0].id()
+    }
+
+    fn also_completes(&self) -> Vec<WorkId> {
+        let mut ids = self.0[0].also_completes();
+        for work in self.0[1..].iter() {
+            ids.push(work.id());
+            ids.extend(work.also_completes());
+        }
+        ids
+    }
+
+    fn read_access(&self) -> Access<WorkId> {
+        self.0
+            .iter()
+            .fold(AccessBuilder::new(), |access, work| {
+                access.union(work.read_access())
+            })
+            .build()
+    }
+
+    fn write_access(&self) -> Access<WorkId> {
+        self.0
+            .iter()
+            .fold(AccessBuilder::new(), |access, work| {
+                access.union(work.write_access())
+            })
+            .build()
+    }
+
+    fn exec(&self, context: &FeContext) -> Result<(), fontir::error::Error> {
+        for work in self.0.iter() {
+            if context.cancellation.is_cancelled() {
+                return Err(fontir::error::Error::Cancelled);
+            }
+            work.exec(context)?;
+        }
+        Ok(())
+    }
+}
+
+/// Changes to static metadata requested by the user, rather than the source.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct StaticMetadataOverrides {
+    /// Named instances to keep; all of them if empty
+    pub(crate) keep_instances: Vec<String>,
+    /// Axes to put first, see [`StaticMetadata::reorder_axes`]
+    pub(crate) axis_order: Vec<Tag>,
+    /// See [`fontir::ir::MiscMetadata::stat_axis_order`]
+    pub(crate) stat_axis_order: Vec<Tag>,
+    /// A glyph for all the codepoints not otherwise mapped, see
+    /// [`fontir::ir::MiscMetadata::cmap_ranges`]
+    pub(crate) cmap_fallback_glyph: Option<GlyphName>,
+    /// See [`fontir::ir::MiscMetadata::timestamp`]
+    pub(crate) timestamp: Option<DateTime<Utc>>,
+}
+
+impl StaticMetadataOverrides {
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == Default::default()
+    }
+
+    fn apply(&self, static_metadata: &mut StaticMetadata) -> Result<(), fontir::error::Error> {
+        if !self.keep_instances.is_empty() {
+            for name in self.keep_instances.iter() {
+                if !static_metadata
+                    .named_instances
+                    .iter()
+                    .any(|ni| ni.name == *name)
+                {
+                    warn!("There is no named instance '{name}' to keep");
+                }
+            }
+            static_metadata
+                .named_instances
+                .retain(|ni| self.keep_instances.contains(&ni.name));
+        }
+        if !self.axis_order.is_empty() {
+            for tag in self.axis_order.iter() {
+                if !static_metadata.all_source_axes.contains(tag) {
+                    warn!("There is no axis '{tag}' to reorder");
+                }
+            }
+            static_metadata.reorder_axes(&self.axis_order)?;
+        }
+        if !self.stat_axis_order.is_empty() {
+            static_metadata.misc.stat_axis_order = self.stat_axis_order.clone();
+        }
+        if let Some(glyph) = &self.cmap_fallback_glyph {
+            // after any ranges from the source, so those take precedence
+            static_metadata.misc.cmap_ranges.push(CodepointRange {
+                first: 0,
+                last: 0x10FFFF,
+                glyph: glyph.clone(),
+            });
+        }
+        if self.timestamp.is_some() {
+            static_metadata.misc.timestamp = self.timestamp;
+        }
+        Ok(())
+    }
+}
+
+/// Static metadata work followed by the application of [`StaticMetadataOverrides`].
+#[derive(Debug)]
+pub(crate) struct OverrideStaticMetadata {
+    work: Box<IrWork>,
+    overrides: StaticMetadataOverrides,
+}
+
+impl OverrideStaticMetadata {
+    pub(crate) fn new(work: Box<IrWork>, overrides: StaticMetadataOverrides) -> Box<IrWork> {
+        Box::new(OverrideStaticMetadata { work, overrides })
+    }
+}
+
+impl Work<FeContext, WorkId, fontir::error::Error> for OverrideStaticMetadata {
+    fn id(&self) -> WorkId {
+        self.work.id()
+    }
+
+    fn also_completes(&self) -> Vec<WorkId> {
+        self.work.also_completes()
+    }
+
+    fn read_access(&self) -> Access<WorkId> {
+        self.work.read_access()
+    }
+
+    fn write_access(&self) -> Access<WorkId> {
+        self.work.write_access()
+    }
+
+    fn exec(&self, context: &FeContext) -> Result<(), fontir::error::Error> {
+        self.work.exec(context)?;
+
+        // We just wrote static metadata, which doesn't entitle us to read it back
+        let context = context.copy_for_work(
+            Access::Variant(WorkId::StaticMetadata),
+            Access::Variant(WorkId::StaticMetadata),
+        );
+        let mut static_metadata = (*context.static_metadata.get()).clone();
+        self.overrides.apply(&mut static_metadata)?;
+        context.static_metadata.set(static_metadata);
+        Ok(())
+    }
+}
+
+/// Changes to the glyph order requested by the user, rather than the source.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct GlyphOrderOverrides {
+    /// A file listing the glyphs to put first, one name per line
+    pub(crate) file: Option<PathBuf>,
+    /// How to order the remaining glyphs
+    pub(crate) mode: GlyphOrderMode,
+    /// A previously compiled font whose glyph ids must be kept
+    pub(crate) compat_font: Option<PathBuf>,
+    /// Drop glyphs of the compat font that no longer exist, instead of failing
+    pub(crate) allow_removed_glyphs: bool,
+    /// A file listing glyphs to keep, one name per line; the rest are subset away
+    pub(crate) keep_glyphs: Option<PathBuf>,
+    /// Keep the glyphs mapped to these codepoints; the rest are subset away
+    pub(crate) keep_unicodes: Vec<RangeInclusive<u32>>,
+}
+
+impl GlyphOrderOverrides {
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == Default::default()
+    }
+
+    fn is_subset(&self) -> bool {
+        self.keep_glyphs.is_some() || !self.keep_unicodes.is_empty()
+    }
+
+    /// The glyphs to keep when subsetting.
+    ///
+    /// Starts from the listed glyphs and codepoints then adds, until nothing
+    /// changes, the components of kept glyphs and anything a feature file
+    /// substitution can produce from them.
+    fn glyphs_to_keep(
+        &self,
+        context: &FeContext,
+        glyph_order: &GlyphOrder,
+    ) -> Result<HashSet<GlyphName>, fontir::error::Error> {
+        let mut keep = HashSet::new();
+        if glyph_order.contains(&GlyphName::NOTDEF) {
+            keep.insert(GlyphName::NOTDEF);
+        }
+        if let Some(path) = &self.keep_glyphs {
+            let raw = std::fs::read_to_string(path).map_err(|e| BadSource::new(path, e))?;
+            for name in parse_glyph_order_file(&raw) {
+                if glyph_order.contains(&name) {
+                    keep.insert(name);
+                } else {
+                    warn!("The keep glyphs file lists '{name}', which is not in the font");
+                }
+            }
+        }
+        for name in glyph_order.names() {
+            let glyph = context.get_glyph(name.clone());
+            if glyph
+                .codepoints
+                .iter()
+                .any(|cp| self.keep_unicodes.iter().any(|range| range.contains(cp)))
+            {
+                keep.insert(name.clone());
+            }
+        }
+
+        let features = context.features.get();
+        let mut follow_substitutions = true;
+        loop {
+            let before = keep.len();
+            for name in keep.clone() {
+                for instance in context.get_glyph(name).sources().values() {
+                    keep.extend(instance.components.iter().map(|c| c.base.clone()));
+                }
+            }
+            if follow_substitutions {
+                if let Err(e) =
+                    fontbe::features::substitution_closure(&features, glyph_order, &mut keep)
+                {
+                    warn!("Unable to follow feature substitutions when subsetting: {e}");
+                    follow_substitutions = false;
+                }
+            }
+            if keep.len() == before {
+                break;
+            }
+        }
+        Ok(keep)
+    }
+
+    fn apply(
+        &self,
+        glyph_order: &GlyphOrder,
+        codepoint: impl Fn(&GlyphName) -> Option<u32>,
+        postscript_name: impl Fn(&GlyphName) -> GlyphName,
+    ) -> Result<GlyphOrder, fontir::error::Error> {
+        let listed = match &self.file {
+            Some(path) => {
+                let raw = std::fs::read_to_string(path).map_err(|e| BadSource::new(path, e))?;
+                parse_glyph_order_file(&raw)
+            }
+            None => Vec::new(),
+        };
+        let new_order = reorder_glyphs(glyph_order, &listed, self.mode, codepoint);
+        let Some(path) = &self.compat_font else {
+            return Ok(new_order);
+        };
+        let previous = read_font_glyph_names(path)?;
+        keep_glyph_ids(
+            &new_order,
+            &previous,
+            postscript_name,
+            self.allow_removed_glyphs,
+        )
+        .map_err(|msg| BadSource::custom(path, msg).into())
+    }
+}
+
+/// The glyph names of a compiled font, in glyph id order.
+fn read_font_glyph_names(path: &Path) -> Result<Vec<GlyphName>, fontir::error::Error> {
+    let data = std::fs::read(path).map_err(|e| BadSource::new(path, e))?;
+    let font = FontRef::new(&data).map_err(|e| BadSource::custom(path, e))?;
+    let num_glyphs = font
+        .maxp()
+        .map_err(|e| BadSource::custom(path, e))?
+        .num_glyphs();
+    let post = font.post().map_err(|e| BadSource::custom(path, e))?;
+    (0..num_glyphs)
+        .map(|gid| {
+            post.glyph_name(GlyphId16::new(gid))
+                .map(GlyphName::new)
+                .ok_or_else(|| {
+                    fontir::error::Error::from(BadSource::custom(
+                        path,
+                        format!("the post table has no name for glyph {gid}"),
+                    ))
+                })
+        })
+        .collect()
+}
+
+/// Give the glyphs of `previous` (a list of postscript names) the same ids in
+/// the new glyph order, followed by any new glyphs.
+///
+/// It is an error if a previous glyph is missing, since that would change the
+/// ids of the glyphs after it, unless `allow_removed` is set.
+fn keep_glyph_ids(
+    glyph_order: &GlyphOrder,
+    previous: &[GlyphName],
+    postscript_name: impl Fn(&GlyphName) -> GlyphName,
+    allow_removed: bool,
+) -> Result<GlyphOrder, String> {
+    let by_postscript_name: HashMap<_, _> = glyph_order
+        .names()
+        .map(|name| (postscript_name(name), name))
+        .collect();
+    let mut new_order = GlyphOrder::new();
+    let mut removed = Vec::new();
+    for ps_name in previous {
+        match by_postscript_name.get(ps_name) {
+            Some(name) => {
+                new_order.insert((*name).clone());
+            }
+            None => removed.push(ps_name.as_str()),
+        }
+    }
+    if !removed.is_empty() {
+        let removed = removed.join(", ");
+        if !allow_removed {
+            return Err(format!(
+                "glyphs would change id because these were removed: {removed}"
+            ));
+        }
+        warn!("Glyph ids change because these glyphs were removed: {removed}");
+    }
+    new_order.extend(glyph_order.names().cloned());
+    Ok(new_order)
+}
+
+/// Read a list of glyph names, one per line.
+///
+/// Blank lines and anything after a '#' are ignored.
+fn parse_glyph_order_file(raw: &str) -> Vec<GlyphName> {
+    raw.lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|name| !name.is_empty())
+        .map(GlyphName::new)
+        .collect()
+}
+
+/// Put .notdef, then the `listed` glyphs, then the rest as directed by `mode`.
+///
+/// Listed glyphs that are not in the font are skipped.
+fn reorder_glyphs(
+    glyph_order: &GlyphOrder,
+    listed: &[GlyphName],
+    mode: GlyphOrderMode,
+    codepoint: impl Fn(&GlyphName) -> Option<u32>,
+) -> GlyphOrder {
+    let mut rest: Vec<_> = glyph_order
+        .names()
+        .filter(|name| **name != GlyphName::NOTDEF)
+        .collect();
+    match mode {
+        GlyphOrderMode::Source => (),
+        GlyphOrderMode::Sorted => rest.sort(),
+        // a stable sort, so unmapped glyphs keep their relative order
+        GlyphOrderMode::CmapFirst => rest.sort_by_key(|name| match codepoint(name) {
+            Some(cp) => (0, cp),
+            None => (1, 0),
+        }),
+    }
+
+    let mut new_order = GlyphOrder::new();
+    if glyph_order.contains(&GlyphName::NOTDEF) {
+        new_order.insert(GlyphName::NOTDEF);
+    }
+    for name in listed {
+        if glyph_order.contains(name) {
+            new_order.insert(name.clone());
+        } else {
+            warn!("The glyph order file lists '{name}', which is not in the font");
+        }
+    }
+    new_order.extend(rest.into_iter().cloned());
+    new_order
+}
+
+/// Glyph order work followed by the application of [`GlyphOrderOverrides`].
+#[derive(Debug)]
+pub(crate) struct OverrideGlyphOrder {
+    work: Box<IrWork>,
+    overrides: GlyphOrderOverrides,
+}
+
+impl OverrideGlyphOrder {
+    pub(crate) fn new(work: Box<IrWork>, overrides: GlyphOrderOverrides) -> Box<IrWork> {
+        Box::new(OverrideGlyphOrder { work, overrides })
+    }
+}
+
+impl Work<FeContext, WorkId, fontir::error::Error> for OverrideGlyphOrder {
+    fn id(&self) -> WorkId {
+        self.work.id()
+    }
+
+    fn also_completes(&self) -> Vec<WorkId> {
+        self.work.also_completes()
+    }
+
+    fn read_access(&self) -> Access<WorkId> {
+        let access = self.work.read_access();
+        if !self.overrides.is_subset() {
+            return access;
+        }
+        // subsetting follows the substitutions in the feature file
+        let Access::Set(mut ids) = access else {
+            unreachable!("glyph order work reads a set of ids, not {access:?}");
+        };
+        ids.insert(AccessType::Variant(WorkId::Features));
+        Access::Set(ids)
+    }
+
+    fn write_access(&self) -> Access<WorkId> {
+        self.work.write_access()
+    }
+
+    fn exec(&self, context: &FeContext) -> Result<(), fontir::error::Error> {
+        self.work.exec(context)?;
+
+        let context = context.copy_for_work(
+            AccessBuilder::new()
+                .variant(WorkId::StaticMetadata)
+                .variant(WorkId::GlyphOrder)
+                .variant(WorkId::ALL_GLYPHS)
+                .variant(WorkId::Features)
+                .build(),
+            Access::Variant(WorkId::GlyphOrder),
+        );
+        let mut glyph_order = context.glyph_order.get();
+        if self.overrides.is_subset() {
+            let keep = self.overrides.glyphs_to_keep(&context, &glyph_order)?;
+            glyph_order = Arc::new(
+                glyph_order
+                    .names()
+                    .filter(|name| keep.contains(*name))
+                    .cloned()
+                    .collect(),
+            );
+        }
+        let static_metadata = context.static_metadata.get();
+        let new_order = self.overrides.apply(
+            &glyph_order,
+            |name| {
+                context
+                    .get_glyph(name.clone())
+                    .codepoints
+                    .iter()
+                    .min()
+                    .copied()
+            },
+            // the names in a compiled font are production names, if we use those
+            |name| {
+                static_metadata
+                    .postscript_names
+                    .as_ref()
+                    .and_then(|names| names.get(name))
+                    .unwrap_or(name)
+                    .clone()
+            },
+        )?;
+        context.glyph_order.set(new_order);
+        Ok(())
+    }
+}
+
+/// Bitmap strike work followed by adding the bitmaps of a strike directory.
+///
+/// The directory has a directory per ppem, holding a PNG per glyph, e.g.
+/// `64/smile.png`. Each image sits on the baseline at the glyph origin, and
+/// replaces any bitmap the source has for that glyph and size.
+#[derive(Debug)]
+pub(crate) struct OverrideBitmapStrikes {
+    work: Box<IrWork>,
+    dir: PathBuf,
+}
+
+impl OverrideBitmapStrikes {
+    pub(crate) fn new(work: Box<IrWork>, dir: PathBuf) -> Box<IrWork> {
+        Box::new(OverrideBitmapStrikes { work, dir })
+    }
+}
+
+impl Work<FeContext, WorkId, fontir::error::Error> for OverrideBitmapStrikes {
+    fn id(&self) -> WorkId {
+        self.work.id()
+    }
+
+    fn also_completes(&self) -> Vec<WorkId> {
+        self.work.also_completes()
+    }
+
+    fn read_access(&self) -> Access<WorkId> {
+        self.work.read_access()
+    }
+
+    fn write_access(&self) -> Access<WorkId> {
+        self.work.write_access()
+    }
+
+    fn exec(&self, context: &FeContext) -> Result<(), fontir::error::Error> {
+        self.work.exec(context)?;
+
+        let context = context.copy_for_work(
+            Access::Variant(WorkId::BitmapStrikes),
+            Access::Variant(WorkId::BitmapStrikes),
+        );
+        let mut strikes = context
+            .bitmap_strikes
+            .try_get()
+            .map(|strikes| (*strikes).clone())
+            .unwrap_or_default();
+        read_strike_dir(&self.dir, &mut strikes)?;
+        if !strikes.is_empty() {
+            context.bitmap_strikes.set(strikes);
+        }
+        Ok(())
+    }
+}
+
+fn read_strike_dir(dir: &Path, strikes: &mut BitmapStrikes) -> Result<(), BadSource> {
+    let entries = |dir: &Path| {
+        fs::read_dir(dir)
+            .and_then(|entries| {
+                entries
+                    .map(|e| e.map(|e| e.path()))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .map_err(|e| BadSource::new(dir, e))
+    };
+    for strike_dir in entries(dir)? {
+        let Some(ppem) = strike_dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.parse::<u16>().ok())
+            .filter(|_| strike_dir.is_dir())
+        else {
+            warn!("Ignoring {strike_dir:?}, strikes are directories named for their ppem");
+            continue;
+        };
+        let strike = strikes.strikes.entry(ppem).or_default();
+        for png_file in entries(&strike_dir)? {
+            if png_file.extension().is_none_or(|ext| ext != "png") {
+                continue;
+            }
+            let Some(glyph_name) = png_file.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let png = fs::read(&png_file).map_err(|e| BadSource::new(&png_file, e))?;
+            strike.glyphs.insert(
+                glyph_name.into(),
+                BitmapGlyph {
+                    png,
+                    left: 0,
+                    bottom: 0,
+                },
+            );
+        }
+    }
+    Ok(())
+}
+
+/// SVG document work followed by adding the documents of a directory.
+///
+/// The directory holds an SVG file per glyph, e.g. `smile.svg`, which replaces
+/// any document the source has for that glyph.
+#[derive(Debug)]
+pub(crate) struct OverrideSvgDocuments {
+    work: Box<IrWork>,
+    dir: PathBuf,
+}
+
+impl OverrideSvgDocuments {
+    pub(crate) fn new(work: Box<IrWork>, dir: PathBuf) -> Box<IrWork> {
+        Box::new(OverrideSvgDocuments { work, dir })
+    }
+}
+
+impl Work<FeContext, WorkId, fontir::error::Error> for OverrideSvgDocuments {
+    fn id(&self) -> WorkId {
+        self.work.id()
+    }
+
+    fn also_completes(&self) -> Vec<WorkId> {
+        self.work.also_completes()
+    }
+
+    fn read_access(&self) -> Access<WorkId> {
+        self.work.read_access()
+    }
+
+    fn write_access(&self) -> Access<WorkId> {
+        self.work.write_access()
+    }
+
+    fn exec(&self, context: &FeContext) -> Result<(), fontir::error::Error> {
+        self.work.exec(context)?;
+
+        let context = context.copy_for_work(
+            Access::Variant(WorkId::SvgDocuments),
+            Access::Variant(WorkId::SvgDocuments),
+        );
+        let mut documents = context
+            .svg_documents
+            .try_get()
+            .map(|documents| (*documents).clone())
+            .unwrap_or_default();
+        read_svg_dir(&self.dir, &mut documents)?;
+        if !documents.documents.is_empty() {
+            context.svg_documents.set(documents);
+        }
+        Ok(())
+    }
+}
+
+fn read_svg_dir(dir: &Path, documents: &mut SvgDocuments) -> Result<(), BadSource> {
+    let entries = fs::read_dir(dir)
+        .and_then(|entries| {
+            entries
+                .map(|e| e.map(|e| e.path()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| BadSource::new(dir, e))?;
+    for svg_file in entries {
+        if svg_file.extension().is_none_or(|ext| ext != "svg") {
+            continue;
+        }
+        let Some(glyph_name) = svg_file.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let document = fs::read_to_string(&svg_file).map_err(|e| BadSource::new(&svg_file, e))?;
+        documents.documents.insert(glyph_name.into(), document);
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum AnyAccess {
     Be(Access<AnyWorkId>),
@@ -215,3 +884,42 @@ impl AnyContext {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<GlyphName> {
+        names.iter().map(GlyphName::new).collect()
+    }
+
+    fn glyph_order(names: &[&str]) -> GlyphOrder {
+        names.iter().map(GlyphName::new).collect()
+    }
+
+    #[test]
+    fn keep_ids_and_append_new_glyphs() {
+        let order = glyph_order(&[".notdef", "a", "b", "c"]);
+        let previous = names(&[".notdef", "c", "uni0061"]);
+        let kept = keep_glyph_ids(
+            &order,
+            &previous,
+            |name| match name.as_str() {
+                "a" => "uni0061".into(),
+                _ => name.clone(),
+            },
+            false,
+        )
+        .unwrap();
+        assert_eq!(glyph_order(&[".notdef", "c", "a", "b"]), kept);
+    }
+
+    #[test]
+    fn removed_glyphs_are_an_error_unless_allowed() {
+        let order = glyph_order(&[".notdef", "b"]);
+        let previous = names(&[".notdef", "a", "b"]);
+        assert!(keep_glyph_ids(&order, &previous, |name| name.clone(), false).is_err());
+        let kept = keep_glyph_ids(&order, &previous, |name| name.clone(), true).unwrap();
+        assert_eq!(order, kept);
+    }
+}
@@ -3,6 +3,7 @@
 use std::{
     collections::{HashMap, HashSet},
     panic::AssertUnwindSafe,
+    path::PathBuf,
     sync::{
         atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc, Mutex,
@@ -12,20 +13,24 @@ use std::{
 use crossbeam_channel::{Receiver, TryRecvError};
 use fontbe::{
     avar::create_avar_work,
+    base::create_base_work,
+    cbdt::create_cbdt_work,
     cmap::create_cmap_work,
     colr::create_colr_work,
     cpal::create_cpal_work,
     features::{
         create_gather_ir_kerning_work, create_kern_segment_work, create_kerns_work,
-        create_mark_work, FeatureCompilationWork, FeatureFirstPassWork,
+        create_mark_work, CustomFeatureWriter, FeatureCompilationWork, FeatureFirstPassWork,
     },
     font::create_font_work,
     fvar::create_fvar_work,
     gasp::create_gasp_work,
-    glyphs::{create_glyf_loca_work, create_glyf_work},
+    glyphs::{create_glyf_loca_work, create_glyf_work, create_reuse_glyf_work},
     gvar::create_gvar_work,
     head::create_head_work,
     hvar::create_hvar_work,
+    kern::create_kern_work,
+    math::create_math_work,
     meta::create_meta_work,
     metrics_and_limits::create_metric_and_limit_work,
     mvar::create_mvar_work,
@@ -33,27 +38,53 @@ use fontbe::{
     orchestration::{AnyWorkId, Context as BeContext, WorkId as BeWorkIdentifier},
     os2::create_os2_work,
     post::create_post_work,
+    sbix::create_sbix_work,
     stat::create_stat_work,
+    svg::create_svg_work,
     vertical_metrics::create_vertical_metrics_work,
 };
 use fontdrasil::{
     coords::NormalizedLocation,
-    orchestration::{Access, AccessBuilder, AccessType, Identifier, IdentifierDiscriminant},
+    error::ErrorCategory,
+    orchestration::{
+        Access, AccessBuilder, AccessType, CancellationToken, Identifier, IdentifierDiscriminant,
+    },
     types::GlyphName,
 };
 use fontir::{
-    glyph::create_glyph_order_work,
-    orchestration::{Context as FeContext, WorkId as FeWorkIdentifier},
-    source::Source,
+    compatibility::create_compatibility_check_work,
+    glyph::{create_glyph_order_work, create_glyph_order_work_with_max_component_depth},
+    orchestration::{Context as FeContext, Flags, WorkId as FeWorkIdentifier},
+    source::{AxisLimit, Source},
 };
-use log::{debug, trace, warn};
+use log::{debug, info, trace, warn};
 
 use crate::{
+    incremental::PreviousBuild,
+    progress::{Progress, ProgressEvent},
     timing::{JobTime, JobTimer},
-    work::{AnyAccess, AnyContext, AnyWork},
+    work::{
+        AnyAccess, AnyContext, AnyWork, GlyphIrBatch, GlyphOrderOverrides, OverrideBitmapStrikes,
+        OverrideGlyphOrder, OverrideStaticMetadata, OverrideSvgDocuments, StaticMetadataOverrides,
+    },
     Error, Input,
 };
 
+/// When batching glyph IR work aim for roughly this many batches per thread.
+///
+/// More batches balance load better, fewer reduce scheduling overhead.
+const GLYPH_BATCHES_PER_THREAD: usize = 8;
+
+/// The largest automatically chosen glyph IR batch
+const MAX_GLYPH_BATCH_SIZE: usize = 64;
+
+/// How many glyphs to produce IR for in a single job.
+///
+/// Small fonts get batches of one, that is no batching, as overhead is no concern.
+fn glyph_batch_size(n_glyphs: usize, n_threads: usize) -> usize {
+    (n_glyphs / (n_threads.max(1) * GLYPH_BATCHES_PER_THREAD)).clamp(1, MAX_GLYPH_BATCH_SIZE)
+}
+
 /// A set of interdependent jobs to execute.
 pub struct Workload {
     source: Box<dyn Source>,
@@ -69,6 +100,28 @@ pub struct Workload {
     pub(crate) jobs_pending: HashMap<AnyWorkId, Job>,
     pub(crate) count_pending: HashMap<IdentifierDiscriminant, Arc<AtomicUsize>>,
 
+    // If present, BE glyph work whose inputs are unchanged reuses these results
+    previous: Option<PreviousBuild>,
+    pub(crate) n_reused_glyphs: usize,
+
+    // If present, the names of glyphs whose IR had components as read from source
+    source_composites: Option<Arc<Mutex<HashSet<GlyphName>>>>,
+
+    // How many glyphs per glyph IR job, None to choose automatically
+    glyph_batch_size: Option<usize>,
+
+    // How many threads to execute on, None to let rayon decide
+    n_threads: Option<usize>,
+    // If set, execute on this pool rather than creating one; lets compiles share threads
+    #[cfg(not(target_family = "wasm"))]
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+
+    // If present, told about each job as it completes
+    progress: Option<Arc<dyn Progress>>,
+
+    // Once cancelled no more jobs start and exec returns Error::Cancelled
+    cancellation: CancellationToken,
+
     pub(crate) timer: JobTimer,
 }
 
@@ -102,6 +155,7 @@ fn priority(id: &AnyWorkId) -> u32 {
         AnyWorkId::Fe(FeWorkIdentifier::KerningGroups) => 99,
         AnyWorkId::Fe(FeWorkIdentifier::KernInstance(..)) => 99,
         AnyWorkId::Fe(FeWorkIdentifier::GlyphOrder) => 99,
+        AnyWorkId::Fe(FeWorkIdentifier::CompatibilityCheck) => 99,
         AnyWorkId::Fe(FeWorkIdentifier::PreliminaryGlyphOrder) => 99,
         AnyWorkId::Fe(FeWorkIdentifier::StaticMetadata) => 99,
         AnyWorkId::Fe(FeWorkIdentifier::GlobalMetrics) => 99,
@@ -116,12 +170,18 @@ fn priority(id: &AnyWorkId) -> u32 {
 
 impl Workload {
     // Pass in timer to enable t0 to be as early as possible
-    pub fn new(input: &Input, mut timer: JobTimer, skip_features: bool) -> Result<Self, Error> {
+    pub fn new(
+        input: &Input,
+        mut timer: JobTimer,
+        skip_features: bool,
+        axis_limits: &[AxisLimit],
+    ) -> Result<Self, Error> {
         let time = timer
             .create_timer(AnyWorkId::InternalTiming("create_source"), 0)
             .run();
 
-        let source = input.create_source()?;
+        let mut source = input.create_source()?;
+        source.limit_axes(axis_limits)?;
 
         timer.add(time.complete());
         let time = timer
@@ -138,6 +198,15 @@ impl Workload {
             jobs_pending: Default::default(),
             count_pending: Default::default(),
             skip_features,
+            previous: None,
+            n_reused_glyphs: 0,
+            source_composites: None,
+            glyph_batch_size: None,
+            n_threads: None,
+            #[cfg(not(target_family = "wasm"))]
+            thread_pool: None,
+            progress: None,
+            cancellation: Default::default(),
             timer,
         };
 
@@ -155,9 +224,12 @@ impl Workload {
             .create_glyph_ir_work()?
             .into_iter()
             .for_each(|w| workload.add(w));
+        workload.add(create_compatibility_check_work());
         workload.add(create_glyph_order_work());
         workload.add(workload.source.create_color_palette_work()?);
         workload.add(workload.source.create_paint_graph_work()?);
+        workload.add(workload.source.create_bitmap_strikes_work()?);
+        workload.add(workload.source.create_svg_documents_work()?);
 
         // BE: f(IR, maybe other BE work) => binary
         workload.add_skippable_feature_work(FeatureFirstPassWork::create());
@@ -178,6 +250,11 @@ impl Workload {
         workload.add(create_avar_work());
         workload.add(create_stat_work());
         workload.add(create_meta_work());
+        workload.add(create_base_work());
+        workload.add(create_math_work());
+        workload.add(create_sbix_work());
+        workload.add(create_cbdt_work());
+        workload.add(create_svg_work());
         workload.add(create_cmap_work());
         workload.add(create_colr_work());
         workload.add(create_cpal_work());
@@ -186,6 +263,7 @@ impl Workload {
         workload.add(create_head_work());
         workload.add_skippable_feature_work(create_gather_ir_kerning_work());
         workload.add_skippable_feature_work(create_kerns_work());
+        workload.add_skippable_feature_work(create_kern_work());
         workload.add_skippable_feature_work(create_mark_work());
         workload.add(create_metric_and_limit_work());
         workload.add(create_hvar_work());
@@ -203,6 +281,156 @@ impl Workload {
         Ok(workload)
     }
 
+    /// Report each completed job to progress
+    pub(crate) fn set_progress(&mut self, progress: Arc<dyn Progress>) {
+        self.progress = Some(progress);
+    }
+
+    /// Stop early, with [Error::Cancelled], once cancellation is cancelled
+    pub(crate) fn set_cancellation(&mut self, cancellation: CancellationToken) {
+        self.cancellation = cancellation;
+    }
+
+    pub(crate) fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// Reuse the results of a previous build for glyphs that haven't changed since.
+    pub(crate) fn reuse_unchanged_glyphs(&mut self, previous: PreviousBuild) {
+        self.previous = Some(previous);
+    }
+
+    /// Record the names of glyphs that have components as read from source into composites.
+    ///
+    /// Glyph order work may later decompose them, which is otherwise invisible once
+    /// compilation completes.
+    pub(crate) fn record_source_composites(&mut self, composites: Arc<Mutex<HashSet<GlyphName>>>) {
+        self.source_composites = Some(composites);
+    }
+
+    /// Set how many glyphs to produce IR for in a single job; None to choose automatically.
+    pub(crate) fn set_glyph_batch_size(&mut self, glyph_batch_size: Option<usize>) {
+        self.glyph_batch_size = glyph_batch_size;
+    }
+
+    /// Set how many threads to execute on; None to choose automatically.
+    pub(crate) fn set_threads(&mut self, n_threads: Option<usize>) {
+        self.n_threads = n_threads;
+    }
+
+    /// Execute on an existing thread pool, such as one shared by several compiles.
+    ///
+    /// Takes precedence over [`Workload::set_threads`].
+    #[cfg(not(target_family = "wasm"))]
+    pub(crate) fn set_thread_pool(&mut self, thread_pool: Arc<rayon::ThreadPool>) {
+        self.thread_pool = Some(thread_pool);
+    }
+
+    /// Replace pending glyph IR jobs with jobs that each process a batch of glyphs.
+    pub(crate) fn batch_glyph_ir_work(&mut self, batch_size: usize) {
+        if batch_size < 2 {
+            return;
+        }
+        let mut glyph_ids = self
+            .jobs_pending
+            .values()
+            .filter(|job| {
+                matches!(
+                    (&job.id, &job.work),
+                    (AnyWorkId::Fe(FeWorkIdentifier::Glyph(..)), AnyWork::Fe(..))
+                ) && !job.running
+            })
+            .map(|job| job.id.clone())
+            .collect::<Vec<_>>();
+        // Sort for a stable assignment of glyphs to batches
+        glyph_ids.sort();
+        debug!(
+            "Batching IR work for {} glyphs, {batch_size} glyphs per job",
+            glyph_ids.len()
+        );
+
+        for ids in glyph_ids.chunks(batch_size) {
+            let works = ids
+                .iter()
+                .map(|id| match self.remove(id).work {
+                    AnyWork::Fe(work) => work,
+                    _ => unreachable!("We only selected FE work"),
+                })
+                .collect();
+            self.add(GlyphIrBatch::new(works));
+        }
+    }
+
+    /// Adjust the static metadata produced from the source.
+    pub(crate) fn override_static_metadata(&mut self, overrides: StaticMetadataOverrides) {
+        let AnyWork::Fe(work) = self.remove(&FeWorkIdentifier::StaticMetadata.into()).work else {
+            unreachable!("Static metadata is FE work");
+        };
+        self.add(OverrideStaticMetadata::new(work, overrides));
+    }
+
+    /// Flatten composites only as far as needed for none to nest deeper than depth
+    ///
+    /// Only matters if [Flags::FLATTEN_COMPONENTS] is set.
+    pub(crate) fn set_max_component_depth(&mut self, depth: usize) {
+        self.remove(&FeWorkIdentifier::GlyphOrder.into());
+        self.add(create_glyph_order_work_with_max_component_depth(depth));
+    }
+
+    /// Adjust the final glyph order.
+    pub(crate) fn override_glyph_order(&mut self, overrides: GlyphOrderOverrides) {
+        let AnyWork::Fe(work) = self.remove(&FeWorkIdentifier::GlyphOrder.into()).work else {
+            unreachable!("Glyph order is FE work");
+        };
+        self.add(OverrideGlyphOrder::new(work, overrides));
+    }
+
+    /// Add the bitmaps of a strike directory to those of the source.
+    pub(crate) fn add_bitmap_strike_dir(&mut self, dir: PathBuf) {
+        let AnyWork::Fe(work) = self.remove(&FeWorkIdentifier::BitmapStrikes.into()).work else {
+            unreachable!("Bitmap strikes are FE work");
+        };
+        self.add(OverrideBitmapStrikes::new(work, dir));
+    }
+
+    /// Add the documents of an SVG directory to those of the source.
+    pub(crate) fn add_svg_document_dir(&mut self, dir: PathBuf) {
+        let AnyWork::Fe(work) = self.remove(&FeWorkIdentifier::SvgDocuments.into()).work else {
+            unreachable!("SVG documents are FE work");
+        };
+        self.add(OverrideSvgDocuments::new(work, dir));
+    }
+
+    /// Run these writers when compiling features, alongside the built-in ones.
+    pub(crate) fn add_custom_feature_writers(
+        &mut self,
+        writers: Vec<Arc<dyn CustomFeatureWriter>>,
+    ) {
+        if self.skip_features || writers.is_empty() {
+            return;
+        }
+        self.remove(&BeWorkIdentifier::Features.into());
+        self.add(FeatureCompilationWork::with_custom_writers(writers));
+    }
+
+    /// Remove a pending job, undoing the bookkeeping done when it was inserted.
+    fn remove(&mut self, id: &AnyWorkId) -> Job {
+        let job = self
+            .jobs_pending
+            .remove(id)
+            .unwrap_or_else(|| panic!("Cannot remove {id:?}, it isn't pending"));
+        let also_completes = self.also_completes.remove(id).unwrap_or_default();
+        for id in std::iter::once(id).chain(also_completes.iter()) {
+            self.jobs_pending.remove(id);
+            self.job_count -= 1;
+            self.count_pending
+                .get(id.discriminant())
+                .unwrap_or_else(|| panic!("No count of type for {id:?}"))
+                .fetch_sub(1, Ordering::AcqRel);
+        }
+        job
+    }
+
     fn add_skippable_feature_work(&mut self, work: impl Into<AnyWork>) {
         if !self.skip_features {
             self.add(work);
@@ -311,6 +539,15 @@ impl Workload {
         }
     }
 
+    /// Mark a pending job complete without running it
+    fn skip_pending(&mut self, id: &AnyWorkId) {
+        for counter in self.counters(id) {
+            counter.fetch_sub(1, Ordering::AcqRel);
+        }
+        self.complete_one(id.clone());
+        self.mark_also_completed(id);
+    }
+
     /// When BE glyph jobs are initially created they don't know enough to set fine grained dependencies
     /// so they depend on *all* IR glyphs. Once IR for a glyph completes we can refine that:
     ///
@@ -318,14 +555,29 @@ impl Workload {
     /// * If the glyph has no components the BE for it doesn't use glyph order and needn't block on it
     /// * If the glyph does have components we need to block on glyph order because that might alter them
     ///    * For example, flatten
+    /// * Either way we block on the compatibility check, which fails the build before any BE
+    ///   work is spent on incompatible sources and may rewrite the glyph, e.g. to match contours
     ///
     /// By minimizing dependencies we allow jobs to start earlier and execute with greater concurrency.
-    fn update_be_glyph_work(&mut self, fe_root: &FeContext, glyph_name: GlyphName) {
+    ///
+    /// If the glyph is unchanged since a previous build the BE work is replaced with work
+    /// that simply reuses the previous result.
+    pub(crate) fn update_be_glyph_work(&mut self, fe_root: &FeContext, glyph_name: GlyphName) {
         let glyph = fe_root
             .glyphs
             .get(&FeWorkIdentifier::Glyph(glyph_name.clone()));
         let be_id = AnyWorkId::Be(BeWorkIdentifier::GlyfFragment(glyph_name));
 
+        if let Some(composites) = &self.source_composites {
+            if glyph
+                .sources()
+                .values()
+                .any(|inst| !inst.components.is_empty())
+            {
+                composites.lock().unwrap().insert(glyph.name.clone());
+            }
+        }
+
         // If the inputs to the BE glyph didn't change it won't be pending
         let Some(be_job) = self.jobs_pending.get_mut(&be_id) else {
             return;
@@ -333,15 +585,25 @@ impl Workload {
 
         if !glyph.emit_to_binary {
             trace!("Skipping execution of {be_id:?}; it does not emit to binary");
-            for counter in self.counters(&be_id) {
-                counter.fetch_sub(1, Ordering::AcqRel);
-            }
-            self.complete_one(be_id.clone());
-            self.mark_also_completed(&be_id);
+            self.skip_pending(&be_id);
+            return;
+        }
+
+        if let Some((be_glyph, gvar_fragment)) = self
+            .previous
+            .as_ref()
+            .and_then(|previous| previous.reusable_glyph(&fe_root.static_metadata.get(), &glyph))
+        {
+            trace!("Reusing the previous result for {be_id:?}; its inputs are unchanged");
+            be_job.work = create_reuse_glyf_work(be_glyph, gvar_fragment).into();
+            be_job.read_access = AnyAccess::Be(Access::None);
+            self.n_reused_glyphs += 1;
             return;
         }
 
-        let mut deps = AccessBuilder::<AnyWorkId>::new().variant(FeWorkIdentifier::StaticMetadata);
+        let mut deps = AccessBuilder::<AnyWorkId>::new()
+            .variant(FeWorkIdentifier::StaticMetadata)
+            .variant(FeWorkIdentifier::CompatibilityCheck);
 
         let mut has_components = false;
         for inst in glyph.sources().values() {
@@ -378,6 +640,15 @@ impl Workload {
         self.complete_one(success.clone());
         self.mark_also_completed(&success);
 
+        // Once the glyph is compiled the IR is rarely needed, let it go unless asked to keep it
+        if let AnyWorkId::Be(BeWorkIdentifier::GlyfFragment(glyph_name)) = &success {
+            if fe_root.flags.contains(Flags::LOW_MEMORY) {
+                fe_root
+                    .glyphs
+                    .evict(&FeWorkIdentifier::Glyph(glyph_name.clone()));
+            }
+        }
+
         // When glyph order finalizes, add BE work for any new glyphs
         if let AnyWorkId::Fe(FeWorkIdentifier::GlyphOrder) = success {
             let preliminary_glyph_order = fe_root.preliminary_glyph_order.get();
@@ -390,6 +661,19 @@ impl Workload {
                 self.update_be_glyph_work(fe_root, glyph_name.clone());
             }
 
+            // Glyphs subset away may still be waiting, e.g. for their components
+            for glyph_name in preliminary_glyph_order.difference(&final_glyph_order) {
+                let be_id = AnyWorkId::Be(BeWorkIdentifier::GlyfFragment(glyph_name.clone()));
+                if self
+                    .jobs_pending
+                    .get(&be_id)
+                    .is_some_and(|job| !job.running)
+                {
+                    trace!("Skipping execution of {be_id:?}; it is not in the glyph order");
+                    self.skip_pending(&be_id);
+                }
+            }
+
             // Now that we have a final glyph order we can resolve the Access::Unknown for glyf/loca
             let mut glyf_loca_deps = AccessBuilder::<AnyWorkId>::new()
                 .variant(FeWorkIdentifier::StaticMetadata)
@@ -447,7 +731,15 @@ impl Workload {
                 .into();
         }
 
-        if let AnyWorkId::Fe(FeWorkIdentifier::Glyph(glyph_name)) = success {
+        // Batched glyph IR work completes many glyphs at once
+        let completed_glyphs = std::iter::once(&success)
+            .chain(self.also_completes.get(&success).into_iter().flatten())
+            .filter_map(|id| match id {
+                AnyWorkId::Fe(FeWorkIdentifier::Glyph(glyph_name)) => Some(glyph_name.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for glyph_name in completed_glyphs {
             self.update_be_glyph_work(fe_root, glyph_name);
         }
 
@@ -559,6 +851,26 @@ impl Workload {
     }
 
     pub fn exec(mut self, fe_root: &FeContext, be_root: &BeContext) -> Result<JobTimer, Error> {
+        let batch_size = self.glyph_batch_size.unwrap_or_else(|| {
+            let n_glyphs = self
+                .jobs_pending
+                .keys()
+                .filter(|id| matches!(id, AnyWorkId::Fe(FeWorkIdentifier::Glyph(..))))
+                .count();
+            let n_threads = self.n_threads.unwrap_or_else(|| {
+                std::thread::available_parallelism()
+                    .map(|n| n.get())
+                    .unwrap_or(1)
+            });
+            glyph_batch_size(n_glyphs, n_threads)
+        });
+        self.batch_glyph_ir_work(batch_size);
+        // 0 means rayon picks
+        #[cfg(not(target_family = "wasm"))]
+        let n_threads = self.n_threads.unwrap_or_default();
+        #[cfg(not(target_family = "wasm"))]
+        let thread_pool = self.thread_pool.take();
+
         // Async work will send us it's ID on completion
         let (send, recv) =
             crossbeam_channel::unbounded::<(AnyWorkId, Result<(), Error>, JobTime)>();
@@ -584,6 +896,9 @@ impl Workload {
             let mut nth_wave = 0;
 
             while self.success.len() < self.job_count {
+                if self.cancellation.is_cancelled() {
+                    return Err(Error::Cancelled);
+                }
                 // Spawn anything that is currently executable (has no unfulfilled dependencies)
                 self.update_launchable(&mut launchable);
                 if launchable.is_empty() && !self.jobs_pending.values().any(|j| j.running) {
@@ -646,6 +961,7 @@ impl Workload {
                         let send = send.clone();
                         let run_queue = run_queue.clone();
                         let abort = abort_queued_jobs.clone();
+                        let cancellation = self.cancellation.clone();
 
                         scope.spawn(move |_| {
                             let runnable = { run_queue.lock().unwrap().pop() };
@@ -658,6 +974,20 @@ impl Workload {
                                 log::trace!("Aborting {id:?}");
                                 return;
                             }
+                            // Report back, rather than just returning, so exec doesn't wait on us forever
+                            if cancellation.is_cancelled() {
+                                log::trace!("Cancelling {id:?}");
+                                if let Err(e) = send.send((
+                                    id.clone(),
+                                    Err(Error::Cancelled),
+                                    timing.complete(),
+                                )) {
+                                    log::error!(
+                                        "Unable to write {id:?} to completion channel: {e}"
+                                    );
+                                }
+                                return;
+                            }
                             // # Unwind Safety
                             //
                             // 'unwind safety' does not impact memory safety, but
@@ -716,6 +1046,13 @@ impl Workload {
                         .run();
                     for (success, timing) in successes.iter() {
                         self.handle_success(fe_root, be_root, success.clone(), timing.clone())?;
+                        if let Some(progress) = &self.progress {
+                            progress.job_completed(&ProgressEvent::new(
+                                self.success.len(),
+                                self.job_count,
+                                success.clone(),
+                            ));
+                        }
                     }
                     self.timer.add(timing.complete());
                 }
@@ -731,9 +1068,14 @@ impl Workload {
         // library we use is using the global threadpool
         #[cfg(not(target_family = "wasm"))]
         {
-            let tp = rayon::ThreadPoolBuilder::new()
-                .build()
-                .expect("couldn't build threadpool");
+            let tp = thread_pool.unwrap_or_else(|| {
+                Arc::new(
+                    rayon::ThreadPoolBuilder::new()
+                        .num_threads(n_threads)
+                        .build()
+                        .expect("couldn't build threadpool"),
+                )
+            });
             tp.in_place_scope(runner)?;
         }
         // WASM rayon uses a fall-back single threaded implementation
@@ -743,6 +1085,13 @@ impl Workload {
         // If ^ exited due to error the scope awaited any live tasks; capture their results
         self.read_completions(&mut Vec::new(), &recv, RecvType::NonBlocking)?;
 
+        if self.previous.is_some() {
+            info!(
+                "Reused the previous build of {} glyph(s)",
+                self.n_reused_glyphs
+            );
+        }
+
         if self.error.is_none() {
             if self.success.len() != self.job_count {
                 panic!(
@@ -800,7 +1149,7 @@ impl Workload {
                     self.n_failures += 1;
                     if self.error.is_none() {
                         self.error = Some(e);
-                    } else {
+                    } else if e.category() != ErrorCategory::Cancelled {
                         // the first error will be reported on exit, log the rest:
                         log::error!("task '{completed_id:?}' failed: '{e}'");
                     }
@@ -923,7 +1272,7 @@ impl Workload {
 
 // taken from std:
 // <https://github.com/rust-lang/rust/blob/d5a82bbd26e1ad8b7401f6a718a9c57c96905483/library/std/src/panicking.rs#L247-L253>
-fn get_panic_message(msg: Box<dyn std::any::Any + Send + 'static>) -> String {
+pub(crate) fn get_panic_message(msg: Box<dyn std::any::Any + Send + 'static>) -> String {
     match msg.downcast_ref::<&'static str>() {
         Some(s) => s.to_string(),
         None => match msg.downcast_ref::<String>() {
@@ -0,0 +1,66 @@
+//! Denying warnings across more than one compile
+//!
+//! A test binary of its own as it installs the global logger, which the unit tests
+//! leave to env_logger.
+#![cfg(feature = "cli")]
+
+use std::path::{Path, PathBuf};
+
+use fontc::{Args, Error, JobTimer};
+use fontdrasil::warnings::codes;
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Drops everything, the test only wants the counting
+struct Quiet;
+
+impl Log for Quiet {
+    fn enabled(&self, _: &Metadata) -> bool {
+        false
+    }
+
+    fn log(&self, _: &Record) {}
+
+    fn flush(&self) {}
+}
+
+fn testdata(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../resources/testdata")
+        .join(path)
+}
+
+fn args(build_dir: &Path, source: &str) -> Args {
+    let mut args = Args::new(build_dir, testdata(source));
+    args.deny = vec![codes::UNKNOWN_CUSTOM_PARAMETER.to_string()];
+    args
+}
+
+/// Compile `source` in a build directory of its own
+fn compile(source: &str) -> Result<(), Error> {
+    let temp_dir = tempfile::tempdir().unwrap();
+    fontc::run(args(temp_dir.path(), source), JobTimer::default())
+}
+
+fn denied(result: Result<(), Error>) -> usize {
+    match result {
+        Err(Error::DeniedWarnings(count)) => count,
+        other => panic!("expected denied warnings, got {other:?}"),
+    }
+}
+
+#[test]
+fn denied_warnings_are_counted_per_compile() {
+    // as fontc's main does
+    let policy = args(Path::new("unused"), "unused").warning_policy();
+    fontdrasil::warnings::install(Box::new(Quiet), LevelFilter::Off, policy).unwrap();
+
+    let first = denied(compile("glyphs3/UnknownCustomParameter.glyphs"));
+    assert!(first > 0);
+    // the first compile's warnings aren't counted again
+    assert_eq!(
+        first,
+        denied(compile("glyphs3/UnknownCustomParameter.glyphs"))
+    );
+    // nor do they fail a compile that warns about nothing
+    compile("glyphs3/NoMetaTable.glyphs").unwrap();
+}
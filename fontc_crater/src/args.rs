@@ -19,6 +19,73 @@ pub(super) struct Args {
 #[derive(Debug, Subcommand, PartialEq)]
 pub(super) enum Commands {
     Ci(CiArgs),
+    DiffReports(DiffReportsArgs),
+    Local(LocalArgs),
+    History(HistoryArgs),
+    Diff(DiffArgs),
+}
+
+/// Compare a font built by fontc with one built by fontmake.
+///
+/// This does not need python, unless --python is passed.
+#[derive(Debug, PartialEq, clap::Args)]
+pub(super) struct DiffArgs {
+    /// The font built by fontc
+    pub(super) fontc: PathBuf,
+    /// The font built by fontmake
+    pub(super) fontmake: PathBuf,
+    /// Print results as json
+    #[arg(long)]
+    pub(super) json: bool,
+    /// Compare with ttx_diff.py, which requires fonttools and fontmake.
+    ///
+    /// This compares tables as ttx, and is more precise.
+    #[arg(long)]
+    pub(super) python: bool,
+}
+
+/// Compare the results of two runs, failing if anything regressed.
+#[derive(Debug, PartialEq, clap::Args)]
+pub(super) struct DiffReportsArgs {
+    /// Results json from the earlier run
+    pub(super) old: PathBuf,
+    /// Results json from the later run
+    pub(super) new: PathBuf,
+}
+
+/// Show how results changed over the runs recorded in a history dir.
+#[derive(Debug, PartialEq, clap::Args)]
+pub(super) struct HistoryArgs {
+    /// Directory passed as --history-dir to previous runs
+    pub(super) history_dir: PathBuf,
+}
+
+/// Run against sources on the local filesystem.
+#[derive(Debug, PartialEq, clap::Args)]
+pub(super) struct LocalArgs {
+    /// Sources to run.
+    ///
+    /// Each may be a .glyphs, .glyphspackage or .designspace file, a directory
+    /// to search for them, a path with '*' or '?' in its file name, or a text
+    /// file listing any of these, one per line.
+    #[arg(required = true)]
+    pub(super) sources: Vec<PathBuf>,
+    /// Directory where results are written.
+    #[arg(short = 'o', long = "out")]
+    pub(super) out_dir: PathBuf,
+    /// Maximum number of targets to compile at once, defaults to the number of cpus.
+    #[arg(short, long)]
+    pub(super) jobs: Option<usize>,
+    /// Number of times to rerun a target that fails.
+    ///
+    /// Targets with a different outcome on some attempt are reported as flaky.
+    #[arg(long, default_value_t = 0)]
+    pub(super) retries: usize,
+    /// Directory where a summary of this run is appended to the run history.
+    ///
+    /// Use the history command to see the trend.
+    #[arg(long)]
+    pub(super) history_dir: Option<PathBuf>,
 }
 
 #[derive(Debug, PartialEq, clap::Args)]
@@ -47,6 +114,31 @@ pub(super) struct CiArgs {
     /// only generate html (for the provided out_dir)
     #[arg(long)]
     pub(super) html_only: bool,
+    /// Skip targets already completed by an interrupted run of the same fontc rev.
+    ///
+    /// Without this, any progress left by an interrupted run is discarded.
+    #[arg(long)]
+    pub(super) resume: bool,
+    /// Maximum number of targets to compile at once, defaults to the number of cpus.
+    ///
+    /// Compiling is cpu and memory bound.
+    #[arg(short, long)]
+    pub(super) jobs: Option<usize>,
+    /// Number of times to rerun a target that fails.
+    ///
+    /// Targets with a different outcome on some attempt are reported as flaky.
+    #[arg(long, default_value_t = 0)]
+    pub(super) retries: usize,
+    /// Maximum number of repos to clone or fetch at once.
+    ///
+    /// This is network bound, and hosts may throttle too many connections.
+    #[arg(long, default_value_t = 8)]
+    pub(super) git_jobs: usize,
+    /// Directory where a summary of this run is appended to the run history.
+    ///
+    /// Use the history command to see the trend.
+    #[arg(long)]
+    pub(super) history_dir: Option<PathBuf>,
 }
 
 impl CiArgs {
@@ -20,13 +20,16 @@ use serde::de::DeserializeOwned;
 use crate::{
     args::CiArgs,
     error::Error,
+    history::HistoryRecord,
     ttx_diff_runner::{DiffError, DiffOutput},
     BuildType, Results, Target,
 };
 
 mod html;
+mod progress;
 mod results_cache;
 
+use progress::RunProgress;
 pub(crate) use results_cache::ResultsCache;
 
 static SUMMARY_FILE: &str = "summary.json";
@@ -133,12 +136,23 @@ fn run_crater_and_save_results(args: &CiArgs) -> Result<(), Error> {
         mut targets,
         source_repos,
         failures,
-    } = make_targets(&cache_dir, &inputs.sources);
+    } = make_targets(&cache_dir, &inputs.sources, args.git_jobs);
 
     if !args.gftools {
         targets.retain(|t| t.build == BuildType::Default);
     }
 
+    let (progress, completed) = RunProgress::start(&args.out_dir, &fontc_rev, args.resume)?;
+    if args.resume {
+        let n_before = targets.len();
+        targets
+            .retain(|t| !completed.success.contains_key(t) && !completed.failure.contains_key(t));
+        log::info!(
+            "resuming, {} of {n_before} targets already complete",
+            n_before - targets.len()
+        );
+    }
+
     let n_targets = targets.len();
 
     let context = super::ttx_diff_runner::TtxContext {
@@ -149,9 +163,17 @@ fn run_crater_and_save_results(args: &CiArgs) -> Result<(), Error> {
     };
 
     let began = Utc::now();
-    let results = super::run_all(targets, &context, super::ttx_diff_runner::run_ttx_diff)?
+    let mut results: DiffResults =
+        super::run_all(targets, &context, args.jobs, |context, target| {
+            let result =
+                super::ttx_diff_runner::run_ttx_diff_with_retries(context, target, args.retries);
+            progress.record(target, &result);
+            result
+        })?
         .into_iter()
         .collect();
+    results.success.extend(completed.success);
+    results.failure.extend(completed.failure);
     let finished = Utc::now();
 
     let elapsed = format_elapsed_time(&began, &finished);
@@ -165,6 +187,15 @@ fn run_crater_and_save_results(args: &CiArgs) -> Result<(), Error> {
         _ => (out_file.into(), false),
     };
 
+    if let Some(history_dir) = &args.history_dir {
+        let record = HistoryRecord {
+            date: began,
+            fontc_rev: fontc_rev.clone(),
+            stats: summary.clone(),
+        };
+        crate::history::append(history_dir, &record)?;
+    }
+
     let summary = RunSummary {
         began,
         finished,
@@ -180,6 +211,7 @@ fn run_crater_and_save_results(args: &CiArgs) -> Result<(), Error> {
     if reuse_last_result {
         // we don't need to rewrite any of these other files if nothing
         // changed.
+        progress.finish();
         return Ok(());
     }
 
@@ -189,10 +221,12 @@ fn run_crater_and_save_results(args: &CiArgs) -> Result<(), Error> {
     let sources_file = args.out_dir.join(SOURCES_FILE);
     super::try_write_json(&source_repos, &sources_file)?;
     let failures_file = args.out_dir.join(FAILED_REPOS_FILE);
-    super::try_write_json(&failures, &failures_file)
+    super::try_write_json(&failures, &failures_file)?;
+    progress.finish();
+    Ok(())
 }
 
-fn result_path_for_current_date() -> String {
+pub(crate) fn result_path_for_current_date() -> String {
     let now = chrono::Utc::now();
     let timestamp = now.format("%Y-%m-%d-%H%M%S");
     format!("{timestamp}.json")
@@ -233,9 +267,9 @@ impl ResolvedTargets {
     }
 }
 
-fn make_targets(cache_dir: &Path, repos: &[FontSource]) -> ResolvedTargets {
+fn make_targets(cache_dir: &Path, repos: &[FontSource], git_jobs: usize) -> ResolvedTargets {
     // first instantiate every repo in parallel:
-    preflight_all_repos(cache_dir, repos);
+    preflight_all_repos(cache_dir, repos, git_jobs);
     let mut result = ResolvedTargets::default();
     for repo in repos {
         let config_path = match repo.config_path(cache_dir) {
@@ -308,19 +342,62 @@ fn make_targets(cache_dir: &Path, repos: &[FontSource]) -> ResolvedTargets {
 }
 
 // make sure all repos are fetched and up to date; do this in parallel
-fn preflight_all_repos(cache_dir: &Path, sources: &[FontSource]) {
+fn preflight_all_repos(cache_dir: &Path, sources: &[FontSource], git_jobs: usize) {
     let mut seen = HashSet::new();
     let has_unique_repo_and_sha = sources
         .iter()
         .filter(|src| seen.insert((src.repo_url.as_str(), src.git_rev())))
         .collect::<Vec<_>>();
 
-    has_unique_repo_and_sha.par_iter().for_each(|src| {
-        // we will handle errors later
-        let _ignore = src.instantiate(cache_dir);
+    // git is network bound, so it gets its own pool separate from compilation
+    let threadpool = rayon::ThreadPoolBuilder::new()
+        .num_threads(git_jobs)
+        .build()
+        .unwrap();
+    threadpool.install(|| {
+        has_unique_repo_and_sha.par_iter().for_each(|src| {
+            let repo_dir = src.repo_path(cache_dir);
+            if repo_dir.exists() {
+                fetch_rev_if_missing(&repo_dir, src.git_rev());
+            }
+            // we will handle errors later
+            let _ignore = src.instantiate(cache_dir);
+        });
     });
 }
 
+/// Shallow fetch rev into an existing checkout, if we don't already have it.
+///
+/// Failures are only logged; instantiating the repo will report them properly.
+fn fetch_rev_if_missing(repo_dir: &Path, rev: &str) {
+    if rev.is_empty() {
+        return;
+    }
+    let has_rev = Command::new("git")
+        .args(["cat-file", "-e"])
+        .arg(format!("{rev}^{{commit}}"))
+        .current_dir(repo_dir)
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if has_rev {
+        return;
+    }
+    log::debug!("fetching {rev} in {}", repo_dir.display());
+    match Command::new("git")
+        .args(["fetch", "--depth", "1", "origin", rev])
+        .current_dir(repo_dir)
+        .output()
+    {
+        Ok(output) if output.status.success() => (),
+        Ok(output) => log::warn!(
+            "failed to fetch {rev} in {}: '{}'",
+            repo_dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => log::warn!("failed to run git fetch: '{e}'"),
+    }
+}
+
 fn should_build_in_gftools_mode(src_path: &Path, config: &Config) -> bool {
     let file_stem = src_path
         .file_stem()
@@ -362,7 +439,7 @@ fn format_elapsed_time<Tmz: TimeZone>(start: &DateTime<Tmz>, end: &DateTime<Tmz>
     out
 }
 
-fn precompile_rust_binaries(temp_dir: &Path) -> (PathBuf, PathBuf) {
+pub(crate) fn precompile_rust_binaries(temp_dir: &Path) -> (PathBuf, PathBuf) {
     let fontc = compile_crate_or_die("fontc");
     let normalizer = compile_crate_or_die("otl-normalizer");
 
@@ -384,6 +384,7 @@ fn make_summary_report(current: &DiffResults) -> Markup {
         .into_iter()
         .collect::<Vec<_>>();
     results.sort_by_key(|(item, count)| (-*count, *item));
+    let scores = crate::ttx_diff_runner::table_scores(current.success.values());
 
     if results.is_empty() {
         return html!();
@@ -397,12 +398,18 @@ fn make_summary_report(current: &DiffResults) -> Markup {
                     tr {
                         th { "Tag" }
                         th { "Targets with diff" }
+                        th { "Mean similarity" }
                     }
                 }
                 @for (tag, count) in results {
                     tr {
                         td { (tag) }
                         td { (count) }
+                        td {
+                            @if let Some(score) = scores.get(tag) {
+                                (format!("{:.1}%", score * 100.))
+                            }
+                        }
                     }
                 }
             }
@@ -546,6 +553,8 @@ fn make_error_report(
 
     let current_other = get_other_failures(current);
     let prev_other = get_other_failures(prev);
+    let current_flaky = get_flaky_failures(current);
+    let prev_flaky = get_flaky_failures(prev);
 
     let fontc = if current_fontc.len() - current_both.len() > 0 {
         make_error_report_group(
@@ -642,11 +651,35 @@ fn make_error_report(
         Default::default()
     };
 
+    let flaky = if !current_flaky.is_empty() {
+        make_error_report_group(
+            "flaky",
+            current_flaky
+                .keys()
+                .copied()
+                .map(|k| (k, !prev_flaky.contains_key(k))),
+            |path| {
+                let attempts = current_flaky.get(path).copied().unwrap_or_default();
+                html! {
+                    ol {
+                        @for attempt in attempts {
+                            li { (attempt) }
+                        }
+                    }
+                }
+            },
+            sources,
+        )
+    } else {
+        Default::default()
+    };
+
     html! {
         (fontc)
         (fontmake)
         (both)
         (other)
+        (flaky)
     }
 }
 
@@ -825,12 +858,23 @@ fn get_other_failures(results: &DiffResults) -> BTreeMap<&Target, &str> {
         .failure
         .iter()
         .filter_map(|(id, r)| match r {
-            DiffError::CompileFailed(_) => None,
+            DiffError::CompileFailed(_) | DiffError::Flaky(_) => None,
             DiffError::Other(err) => Some((id, err.as_str())),
         })
         .collect()
 }
 
+fn get_flaky_failures(results: &DiffResults) -> BTreeMap<&Target, &[String]> {
+    results
+        .failure
+        .iter()
+        .filter_map(|(id, r)| match r {
+            DiffError::Flaky(attempts) => Some((id, attempts.as_slice())),
+            _ => None,
+        })
+        .collect()
+}
+
 // failures that result from us not being able to access a repo in the target list
 fn format_repo_failures(failures: &BTreeMap<String, String>) -> Markup {
     if failures.is_empty() {
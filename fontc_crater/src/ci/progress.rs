@@ -0,0 +1,221 @@
+//! Recording results as they complete, so an interrupted run can resume.
+//!
+//! Each finished target is appended to a jsonl file in the output directory,
+//! tagged with the fontc rev that produced it. The file is removed once the
+//! run completes and its results have been written out.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Error,
+    ttx_diff_runner::{DiffError, DiffOutput},
+    Results, RunResult, Target,
+};
+
+static PROGRESS_FILE: &str = "in_progress.jsonl";
+
+/// One line of the progress file
+#[derive(Serialize, Deserialize)]
+struct Entry<S, F> {
+    fontc_rev: String,
+    target: Target,
+    result: Outcome<S, F>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Outcome<S, F> {
+    Success(S),
+    Failure(F),
+}
+
+/// Appends results to the progress file as targets complete.
+pub(crate) struct RunProgress {
+    path: PathBuf,
+    fontc_rev: String,
+    file: Mutex<File>,
+}
+
+impl RunProgress {
+    /// Start recording progress for a run of fontc_rev in out_dir.
+    ///
+    /// If resume is set, also returns the results recorded by a previous,
+    /// unfinished, run of the same fontc_rev. Otherwise previous progress is discarded.
+    pub(crate) fn start(
+        out_dir: &Path,
+        fontc_rev: &str,
+        resume: bool,
+    ) -> Result<(Self, Results<DiffOutput, DiffError>), Error> {
+        let path = out_dir.join(PROGRESS_FILE);
+        let (completed, complete_len) = if resume && path.exists() {
+            read_completed(&path, fontc_rev)?
+        } else {
+            Default::default()
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(resume)
+            .write(true)
+            .truncate(!resume)
+            .open(&path)
+            // drop any partial last line, which would otherwise run into the next one
+            .and_then(|file| file.set_len(complete_len).map(|_| file))
+            .map_err(|error| Error::WriteFile {
+                path: path.clone(),
+                error,
+            })?;
+        let progress = RunProgress {
+            path,
+            fontc_rev: fontc_rev.to_owned(),
+            file: Mutex::new(file),
+        };
+        Ok((progress, completed))
+    }
+
+    /// Record the result for a target that just finished.
+    ///
+    /// Failure to record is logged rather than fatal; at worst the target reruns.
+    pub(crate) fn record(&self, target: &Target, result: &RunResult<DiffOutput, DiffError>) {
+        let entry = Entry {
+            fontc_rev: self.fontc_rev.clone(),
+            target: target.clone(),
+            result: match result {
+                RunResult::Success(output) => Outcome::Success(output),
+                RunResult::Fail(error) => Outcome::Failure(error),
+            },
+        };
+        let mut line = serde_json::to_string(&entry).expect("results always serialize");
+        line.push('\n');
+        // one write per line so a crash leaves at most one partial line
+        let mut file = self.file.lock().unwrap();
+        if let Err(e) = file.write_all(line.as_bytes()).and_then(|_| file.flush()) {
+            log::warn!("failed to record progress for {target}: '{e}'");
+        }
+    }
+
+    /// The run completed and its results are saved, progress is no longer needed.
+    pub(crate) fn finish(self) {
+        drop(self.file);
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            log::warn!("failed to remove {}: '{e}'", self.path.display());
+        }
+    }
+}
+
+/// The results recorded for fontc_rev, and the length in bytes of the complete lines
+fn read_completed(
+    path: &Path,
+    fontc_rev: &str,
+) -> Result<(Results<DiffOutput, DiffError>, u64), Error> {
+    let contents = crate::try_read_string(path)?;
+    let complete_len = contents.rfind('\n').map(|i| i + 1).unwrap_or(0) as u64;
+    let mut completed = Results::default();
+    for line in contents.lines() {
+        // The last line may be incomplete if the previous run died mid-write
+        let Ok(entry) = serde_json::from_str::<Entry<DiffOutput, DiffError>>(line) else {
+            log::warn!("ignoring malformed progress entry '{line}'");
+            continue;
+        };
+        if entry.fontc_rev != fontc_rev {
+            continue;
+        }
+        match entry.result {
+            Outcome::Success(output) => {
+                completed.success.insert(entry.target, output);
+            }
+            Outcome::Failure(error) => {
+                completed.failure.insert(entry.target, error);
+            }
+        }
+    }
+    Ok((completed, complete_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(name: &str) -> Target {
+        Target::new(
+            "org/repo",
+            "123456789a",
+            "sources/config.yaml",
+            false,
+            format!("sources/{name}.glyphs"),
+        )
+    }
+
+    #[test]
+    fn resume_same_rev() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let (progress, completed) = RunProgress::start(temp_dir.path(), "abc", true).unwrap();
+        assert!(completed.success.is_empty() && completed.failure.is_empty());
+        progress.record(&target("a"), &RunResult::Success(DiffOutput::Identical));
+        progress.record(
+            &target("b"),
+            &RunResult::Fail(DiffError::Other("oops".to_string())),
+        );
+        drop(progress);
+
+        let (_, completed) = RunProgress::start(temp_dir.path(), "abc", true).unwrap();
+        assert_eq!(
+            vec![&target("a"), &target("b")],
+            completed.targets().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn ignore_other_rev() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let (progress, _) = RunProgress::start(temp_dir.path(), "abc", true).unwrap();
+        progress.record(&target("a"), &RunResult::Success(DiffOutput::Identical));
+        drop(progress);
+
+        let (_, completed) = RunProgress::start(temp_dir.path(), "def", true).unwrap();
+        assert_eq!(0, completed.targets().count());
+    }
+
+    #[test]
+    fn ignore_partial_line() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let (progress, _) = RunProgress::start(temp_dir.path(), "abc", true).unwrap();
+        progress.record(&target("a"), &RunResult::Success(DiffOutput::Identical));
+        drop(progress);
+        let path = temp_dir.path().join(PROGRESS_FILE);
+        let mut contents = std::fs::read_to_string(&path).unwrap();
+        contents.push_str("{\"fontc_rev\":\"abc\",\"tar");
+        std::fs::write(&path, contents).unwrap();
+
+        let (progress, completed) = RunProgress::start(temp_dir.path(), "abc", true).unwrap();
+        assert_eq!(vec![&target("a")], completed.targets().collect::<Vec<_>>());
+
+        // what we append after resuming mustn't be lost to the partial line
+        progress.record(&target("b"), &RunResult::Success(DiffOutput::Identical));
+        drop(progress);
+        let (_, completed) = RunProgress::start(temp_dir.path(), "abc", true).unwrap();
+        assert_eq!(
+            vec![&target("a"), &target("b")],
+            completed.targets().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn without_resume_progress_is_discarded() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let (progress, _) = RunProgress::start(temp_dir.path(), "abc", true).unwrap();
+        progress.record(&target("a"), &RunResult::Success(DiffOutput::Identical));
+        drop(progress);
+
+        let (progress, _) = RunProgress::start(temp_dir.path(), "abc", false).unwrap();
+        drop(progress);
+        let (_, completed) = RunProgress::start(temp_dir.path(), "abc", true).unwrap();
+        assert_eq!(0, completed.targets().count());
+    }
+}
@@ -0,0 +1,232 @@
+//! Compare the results of two crater runs.
+//!
+//! Lists targets that newly fail, newly pass, or whose diff score changed, so
+//! a change can be gated on "no new regressions" rather than on totals.
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::{
+    args::DiffReportsArgs,
+    error::Error,
+    ttx_diff_runner::{DiffError, DiffOutput},
+    Results, Target,
+};
+
+type DiffResults = Results<DiffOutput, DiffError>;
+
+/// Score changes smaller than this are noise
+const SCORE_EPSILON: f32 = 1e-4;
+
+pub(super) fn run_diff_reports(args: &DiffReportsArgs) -> Result<(), Error> {
+    let old: DiffResults = super::try_read_json(&args.old)?;
+    let new: DiffResults = super::try_read_json(&args.new)?;
+    let comparison = Comparison::new(&old, &new);
+    print!("{}", comparison.to_text());
+    match comparison.n_regressions() {
+        0 => Ok(()),
+        n => Err(Error::Regressions(n)),
+    }
+}
+
+/// How one run differs from another, by target.
+#[derive(Debug, Default, PartialEq)]
+struct Comparison<'a> {
+    newly_failing: Vec<&'a Target>,
+    newly_passing: Vec<&'a Target>,
+    /// target => (old score, new score)
+    score_changed: BTreeMap<&'a Target, (f32, f32)>,
+    added: Vec<&'a Target>,
+    removed: Vec<&'a Target>,
+}
+
+/// How similar the fontc and fontmake outputs are, 1.0 if they're identical.
+fn score(output: &DiffOutput) -> f32 {
+    match output {
+        DiffOutput::Identical => 1.0,
+        DiffOutput::Diffs(diffs) => diffs
+            .get("total")
+            .and_then(|total| total.ratio())
+            .unwrap_or_default(),
+    }
+}
+
+impl<'a> Comparison<'a> {
+    fn new(old: &'a DiffResults, new: &'a DiffResults) -> Self {
+        let mut comparison = Comparison::default();
+        for (target, output) in new.success.iter() {
+            if old.failure.contains_key(target) {
+                comparison.newly_passing.push(target);
+            } else if let Some(old_output) = old.success.get(target) {
+                let (before, after) = (score(old_output), score(output));
+                if (after - before).abs() > SCORE_EPSILON {
+                    comparison.score_changed.insert(target, (before, after));
+                }
+            } else {
+                comparison.added.push(target);
+            }
+        }
+        for target in new.failure.keys() {
+            if old.success.contains_key(target) {
+                comparison.newly_failing.push(target);
+            } else if !old.failure.contains_key(target) {
+                comparison.added.push(target);
+            }
+        }
+        comparison.added.sort();
+        comparison.removed = old
+            .targets()
+            .filter(|target| {
+                !new.success.contains_key(*target) && !new.failure.contains_key(*target)
+            })
+            .collect();
+        comparison.removed.sort();
+        comparison
+    }
+
+    /// Targets that got worse: they fail where they used to pass, or their score dropped.
+    fn n_regressions(&self) -> usize {
+        self.newly_failing.len()
+            + self
+                .score_changed
+                .values()
+                .filter(|(before, after)| after < before)
+                .count()
+    }
+
+    fn to_text(&self) -> String {
+        let mut out = String::new();
+        let mut section = |title: &str, targets: &[&Target]| {
+            if targets.is_empty() {
+                return;
+            }
+            writeln!(&mut out, "{title} ({}):", targets.len()).unwrap();
+            for target in targets {
+                writeln!(&mut out, "  {target}").unwrap();
+            }
+        };
+        section("newly failing", &self.newly_failing);
+        section("newly passing", &self.newly_passing);
+        section("added", &self.added);
+        section("removed", &self.removed);
+
+        if !self.score_changed.is_empty() {
+            writeln!(
+                &mut out,
+                "diff score changed ({}):",
+                self.score_changed.len()
+            )
+            .unwrap();
+            for (target, (before, after)) in self.score_changed.iter() {
+                writeln!(
+                    &mut out,
+                    "  {target} {:.3}% => {:.3}%",
+                    before * 100.,
+                    after * 100.
+                )
+                .unwrap();
+            }
+        }
+        if out.is_empty() {
+            out.push_str("no changes\n");
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ttx_diff_runner::{CompileFailed, CompilerFailure, DiffValue};
+
+    fn target(name: &str) -> Target {
+        Target::new(
+            "org/repo",
+            "123456789a",
+            "sources/config.yaml",
+            false,
+            format!("sources/{name}.glyphs"),
+        )
+    }
+
+    fn diffs(total: f32) -> DiffOutput {
+        DiffOutput::Diffs(BTreeMap::from([(
+            "total".to_string(),
+            DiffValue::Ratio(total),
+        )]))
+    }
+
+    fn fontc_failed() -> DiffError {
+        DiffError::CompileFailed(CompileFailed {
+            fontc: Some(CompilerFailure {
+                command: "fontc".to_string(),
+                stderr: "oh no".to_string(),
+            }),
+            fontmake: None,
+        })
+    }
+
+    fn results(success: Vec<(&str, DiffOutput)>, failure: Vec<&str>) -> DiffResults {
+        Results {
+            success: success
+                .into_iter()
+                .map(|(name, output)| (target(name), output))
+                .collect(),
+            failure: failure
+                .into_iter()
+                .map(|name| (target(name), fontc_failed()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn no_changes() {
+        let old = results(
+            vec![("a", DiffOutput::Identical), ("b", diffs(0.5))],
+            vec!["c"],
+        );
+        let new = results(
+            vec![("a", DiffOutput::Identical), ("b", diffs(0.5))],
+            vec!["c"],
+        );
+        let comparison = Comparison::new(&old, &new);
+        assert_eq!(Comparison::default(), comparison);
+        assert_eq!("no changes\n", comparison.to_text());
+    }
+
+    #[test]
+    fn pass_and_fail_changes() {
+        let old = results(vec![("a", DiffOutput::Identical)], vec!["b"]);
+        let new = results(vec![("b", DiffOutput::Identical)], vec!["a"]);
+        let (a, b) = (target("a"), target("b"));
+        let comparison = Comparison::new(&old, &new);
+        assert_eq!(vec![&a], comparison.newly_failing);
+        assert_eq!(vec![&b], comparison.newly_passing);
+        assert_eq!(1, comparison.n_regressions());
+    }
+
+    #[test]
+    fn score_changes() {
+        let old = results(vec![("a", diffs(0.5)), ("b", diffs(0.9))], vec![]);
+        let new = results(
+            vec![("a", DiffOutput::Identical), ("b", diffs(0.8))],
+            vec![],
+        );
+        let comparison = Comparison::new(&old, &new);
+        assert_eq!(
+            BTreeMap::from([(&target("a"), (0.5, 1.0)), (&target("b"), (0.9, 0.8))]),
+            comparison.score_changed
+        );
+        // Only b got worse
+        assert_eq!(1, comparison.n_regressions());
+    }
+
+    #[test]
+    fn added_and_removed_are_not_regressions() {
+        let old = results(vec![("a", DiffOutput::Identical)], vec![]);
+        let new = results(vec![], vec!["b"]);
+        let comparison = Comparison::new(&old, &new);
+        assert_eq!(vec![&target("b")], comparison.added);
+        assert_eq!(vec![&target("a")], comparison.removed);
+        assert_eq!(0, comparison.n_regressions());
+    }
+}
@@ -37,6 +37,12 @@ pub(super) enum Error {
         error: std::io::Error,
     },
 
+    #[error("Failed to read font: {0}")]
+    ReadFont(#[from] otl_normalizer::Error),
+    #[error("Failed to diff fonts: {0}")]
+    Diff(String),
     #[error("Failed to tidy html: '{0}")]
     TidyHtml(#[from] tidier::Error),
+    #[error("{0} target(s) regressed")]
+    Regressions(usize),
 }
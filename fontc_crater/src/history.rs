@@ -0,0 +1,192 @@
+//! Tracking results across runs.
+//!
+//! Each run given a history dir appends a summary line to a jsonl file there,
+//! and the `history` command prints them as a table, so we can see whether
+//! failures are trending down from one fontc release to the next.
+
+use std::{
+    fmt::Write as _,
+    fs::OpenOptions,
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{args::HistoryArgs, error::Error, ttx_diff_runner::Summary};
+
+static HISTORY_FILE: &str = "history.jsonl";
+
+/// The summary of one run, as recorded in the history file
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct HistoryRecord {
+    pub(crate) date: DateTime<Utc>,
+    pub(crate) fontc_rev: String,
+    pub(crate) stats: Summary,
+}
+
+pub(super) fn run_history(args: &HistoryArgs) -> Result<(), Error> {
+    let records = load(&args.history_dir)?;
+    print!("{}", render_trend(&records));
+    Ok(())
+}
+
+fn history_path(history_dir: &Path) -> PathBuf {
+    history_dir.join(HISTORY_FILE)
+}
+
+/// Append a record for a run to the history in history_dir.
+pub(crate) fn append(history_dir: &Path, record: &HistoryRecord) -> Result<(), Error> {
+    super::try_create_dir(history_dir)?;
+    let path = history_path(history_dir);
+    let mut line = serde_json::to_string(record).map_err(|error| Error::WriteJson {
+        path: path.clone(),
+        error,
+    })?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|error| Error::WriteFile { path, error })
+}
+
+/// All the records in history_dir, oldest first.
+fn load(history_dir: &Path) -> Result<Vec<HistoryRecord>, Error> {
+    let path = history_path(history_dir);
+    let mut records = super::try_read_string(&path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str::<HistoryRecord>(line).map_err(|error| Error::ParseJson {
+                path: path.clone(),
+                error,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    records.sort_by_key(|record| record.date);
+    Ok(records)
+}
+
+/// A table with one row per run, each value followed by its change from the previous run.
+fn render_trend(records: &[HistoryRecord]) -> String {
+    if records.is_empty() {
+        return "no runs recorded\n".to_string();
+    }
+    let mut out = String::new();
+    writeln!(
+        &mut out,
+        "{:<12}{:<12}{:>14}{:>14}{:>14}{:>14}{:>14}{:>14}{:>18}",
+        "date", "rev", "targets", "identical", "fontc", "fontmake", "both", "other", "similarity %"
+    )
+    .unwrap();
+
+    let mut prev: Option<&Summary> = None;
+    for record in records {
+        let stats = &record.stats;
+        let count = |get: fn(&Summary) -> u32| {
+            let value = get(stats);
+            match prev.map(|prev| value as i64 - get(prev) as i64) {
+                Some(delta) if delta != 0 => format!("{value} ({delta:+})"),
+                _ => value.to_string(),
+            }
+        };
+        let similarity = match prev
+            .map(|p| stats.diff_perc_including_failures - p.diff_perc_including_failures)
+        {
+            Some(delta) if delta.abs() >= 0.001 => {
+                format!("{:.3} ({delta:+.3})", stats.diff_perc_including_failures)
+            }
+            _ => format!("{:.3}", stats.diff_perc_including_failures),
+        };
+        writeln!(
+            &mut out,
+            "{:<12}{:<12}{:>14}{:>14}{:>14}{:>14}{:>14}{:>14}{:>18}",
+            record.date.format("%Y-%m-%d"),
+            record.fontc_rev,
+            count(|s| s.total_targets),
+            count(|s| s.identical),
+            count(|s| s.fontc_failed),
+            count(|s| s.fontmake_failed),
+            count(|s| s.both_failed),
+            count(|s| s.other_failure),
+            similarity,
+        )
+        .unwrap();
+        prev = Some(stats);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn record(day: u32, rev: &str, identical: u32, fontc_failed: u32) -> HistoryRecord {
+        HistoryRecord {
+            date: Utc.with_ymd_and_hms(2025, 1, day, 0, 0, 0).unwrap(),
+            fontc_rev: rev.to_string(),
+            stats: Summary {
+                total_targets: identical + fontc_failed,
+                identical,
+                produced_diff: 0,
+                fontc_failed,
+                fontmake_failed: 0,
+                both_failed: 0,
+                other_failure: 0,
+                flaky: 0,
+                diff_perc_including_failures: 100.0 * identical as f32
+                    / (identical + fontc_failed) as f32,
+                diff_perc_excluding_failures: 100.0,
+                table_scores: Default::default(),
+            },
+        }
+    }
+
+    #[test]
+    fn append_and_load() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let history_dir = temp_dir.path().join("history");
+        // appended out of order, loaded oldest first
+        append(&history_dir, &record(2, "def", 8, 2)).unwrap();
+        append(&history_dir, &record(1, "abc", 5, 5)).unwrap();
+        assert_eq!(
+            vec![record(1, "abc", 5, 5), record(2, "def", 8, 2)],
+            load(&history_dir).unwrap()
+        );
+    }
+
+    #[test]
+    fn trend_shows_changes() {
+        let trend = render_trend(&[record(1, "abc", 5, 5), record(2, "def", 8, 2)]);
+        let rows: Vec<Vec<_>> = trend
+            .lines()
+            .map(|line| line.split_whitespace().collect())
+            .collect();
+        assert_eq!(
+            vec!["2025-01-01", "abc", "10", "5", "5", "0", "0", "0", "50.000"],
+            rows[1]
+        );
+        assert_eq!(
+            vec![
+                "2025-01-02",
+                "def",
+                "10",
+                "8",
+                "(+3)",
+                "2",
+                "(-3)",
+                "0",
+                "0",
+                "0",
+                "80.000",
+                "(+30.000)"
+            ],
+            rows[2]
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Running against sources on the local filesystem.
+//!
+//! This is useful for checking a change against a set of fonts you have
+//! checked out already, without needing a google/fonts style repo list.
+
+use std::path::{Path, PathBuf};
+
+use crate::{
+    args::LocalArgs,
+    ci::ResultsCache,
+    error::Error,
+    history::HistoryRecord,
+    ttx_diff_runner::{self, DiffError, DiffOutput, Summary, TtxContext},
+    Results, Target,
+};
+
+static SOURCE_EXTENSIONS: &[&str] = &["glyphs", "glyphspackage", "designspace"];
+
+pub(super) fn run_local(args: &LocalArgs) -> Result<(), Error> {
+    ttx_diff_runner::assert_can_run_script();
+    let sources = find_sources(&args.sources)?;
+    if sources.is_empty() {
+        log::warn!("no sources found");
+        return Ok(());
+    }
+    super::try_create_dir(&args.out_dir)?;
+
+    let temp_dir = tempfile::tempdir().expect("couldn't create tempdir");
+    let (fontc_path, normalizer_path) = super::ci::precompile_rust_binaries(temp_dir.path());
+    let context = TtxContext {
+        fontc_path,
+        normalizer_path,
+        // local targets have absolute paths
+        source_cache: PathBuf::new(),
+        // local sources may be edited between runs, so fontmake output is
+        // only reused within this run
+        results_cache: ResultsCache::in_dir(temp_dir.path()),
+    };
+
+    let began = chrono::Utc::now();
+    let targets = sources.iter().map(|path| Target::local(path)).collect();
+    let results: Results<DiffOutput, DiffError> =
+        super::run_all(targets, &context, args.jobs, |context, target| {
+            ttx_diff_runner::run_ttx_diff_with_retries(context, target, args.retries)
+        })?
+        .into_iter()
+        .collect();
+
+    let out_path = args.out_dir.join(super::ci::result_path_for_current_date());
+    super::try_write_json(&results, &out_path)?;
+
+    let summary = Summary::new(&results);
+    if let Some(history_dir) = &args.history_dir {
+        let record = HistoryRecord {
+            date: began,
+            fontc_rev: super::get_git_rev(None).unwrap_or_default(),
+            stats: summary.clone(),
+        };
+        crate::history::append(history_dir, &record)?;
+    }
+    println!(
+        "{} targets: {} identical, {} with diffs, {} fontc failed, {} fontmake failed, {} both failed, {} other failures, {} flaky",
+        summary.total_targets,
+        summary.identical,
+        summary.produced_diff,
+        summary.fontc_failed,
+        summary.fontmake_failed,
+        summary.both_failed,
+        summary.other_failure,
+        summary.flaky,
+    );
+    for (tag, score) in &summary.table_scores {
+        println!("  {tag} matches {:.1}%", score * 100.);
+    }
+    println!("wrote results to {}", out_path.display());
+    Ok(())
+}
+
+/// Resolve the inputs to a sorted list of source files.
+///
+/// Each input may be a source, a directory to search for sources, a pattern
+/// with '*' or '?' in its file name, or a text file listing one input per
+/// line. Relative paths in a list are relative to the list.
+fn find_sources(inputs: &[PathBuf]) -> Result<Vec<PathBuf>, Error> {
+    let mut sources = Vec::new();
+    for input in inputs {
+        add_sources(input, &mut sources)?;
+    }
+    sources.sort();
+    sources.dedup();
+    Ok(sources)
+}
+
+fn is_source(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| SOURCE_EXTENSIONS.contains(&ext))
+}
+
+fn add_sources(input: &Path, sources: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let file_name = input.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    if file_name.contains(['*', '?']) {
+        let dir = input.parent().unwrap_or(Path::new("."));
+        for path in read_dir(dir)? {
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if wildcard_match(file_name, name) && is_source(&path) {
+                sources.push(path);
+            }
+        }
+    } else if is_source(input) {
+        sources.push(input.to_path_buf());
+    } else if input.is_dir() {
+        search_dir(input, sources)?;
+    } else {
+        let list_dir = input.parent().unwrap_or(Path::new("."));
+        for line in super::try_read_string(input)?.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            add_sources(&list_dir.join(line), sources)?;
+        }
+    }
+    Ok(())
+}
+
+/// Recursively add the sources in dir.
+fn search_dir(dir: &Path, sources: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for path in read_dir(dir)? {
+        if is_source(&path) {
+            sources.push(path);
+        // .glyphspackage is handled above, and we don't want anything inside a .ufo
+        } else if path.is_dir() && !path.extension().is_some_and(|ext| ext == "ufo") {
+            search_dir(&path, sources)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_dir(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let read_err = |error| Error::ReadFile {
+        path: dir.to_owned(),
+        error,
+    };
+    std::fs::read_dir(dir)
+        .map_err(read_err)?
+        .map(|entry| entry.map(|e| e.path()).map_err(read_err))
+        .collect()
+}
+
+/// Whether name matches pattern, where '*' matches any run of characters and '?' any one.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<_> = pattern.chars().collect();
+    let name: Vec<_> = name.chars().collect();
+    // matched[j]: whether the pattern so far matches the first j chars of name
+    let mut matched = vec![false; name.len() + 1];
+    matched[0] = true;
+    for p in pattern {
+        let mut next = vec![false; name.len() + 1];
+        for j in 0..=name.len() {
+            next[j] = match p {
+                '*' => matched[j] || (j > 0 && next[j - 1]),
+                '?' => j > 0 && matched[j - 1],
+                c => j > 0 && matched[j - 1] && name[j - 1] == c,
+            };
+        }
+        matched = next;
+    }
+    matched[name.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        assert!(wildcard_match("*.glyphs", "Foo.glyphs"));
+        assert!(wildcard_match("Foo-?.designspace", "Foo-1.designspace"));
+        assert!(!wildcard_match("*.glyphs", "Foo.glyphspackage"));
+        assert!(!wildcard_match("Foo-?.designspace", "Foo-12.designspace"));
+    }
+
+    #[test]
+    fn find_sources_from_dirs_lists_and_patterns() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        for dir in ["a/sources", "b", "b/Foo.ufo", "c/Bar.glyphspackage"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "a/sources/A.glyphs",
+            "a/sources/config.yaml",
+            "b/B.designspace",
+            "b/B-Bold.designspace",
+            "b/Foo.ufo/fontinfo.plist",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        fs::write(
+            root.join("list.txt"),
+            "# some sources\nb/*-Bold.designspace\n\nc/Bar.glyphspackage\n",
+        )
+        .unwrap();
+
+        let sources = find_sources(&[root.join("a"), root.join("list.txt")]).unwrap();
+        assert_eq!(
+            vec![
+                root.join("a/sources/A.glyphs"),
+                root.join("b/B-Bold.designspace"),
+                root.join("c/Bar.glyphspackage"),
+            ],
+            sources
+        );
+
+        // searching a dir skips the contents of .ufo dirs
+        let sources = find_sources(&[root.join("b"), root.join("b/B.designspace")]).unwrap();
+        assert_eq!(
+            vec![
+                root.join("b/B-Bold.designspace"),
+                root.join("b/B.designspace")
+            ],
+            sources
+        );
+    }
+}
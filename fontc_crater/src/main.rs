@@ -13,7 +13,11 @@ use rayon::{prelude::*, ThreadPoolBuilder};
 
 mod args;
 mod ci;
+mod diff_reports;
 mod error;
+mod history;
+mod local;
+mod native_diff;
 mod target;
 mod ttx_diff_runner;
 
@@ -28,12 +32,17 @@ fn main() {
     let args = Args::parse();
     if let Err(e) = run(&args) {
         eprintln!("{e}");
+        std::process::exit(1);
     }
 }
 
 fn run(args: &Args) -> Result<(), Error> {
     match &args.command {
         Commands::Ci(args) => ci::run_ci(args),
+        Commands::DiffReports(args) => diff_reports::run_diff_reports(args),
+        Commands::Local(args) => local::run_local(args),
+        Commands::Diff(args) => native_diff::run_diff(args),
+        Commands::History(args) => history::run_history(args),
     }
 }
 
@@ -66,12 +75,17 @@ enum SkipReason {
 fn run_all<T: Send, E: Send, Cx: Sync>(
     targets: Vec<Target>,
     context: &Cx,
+    max_jobs: Option<usize>,
     runner: impl Fn(&Cx, &Target) -> RunResult<T, E> + Send + Sync,
 ) -> Result<Vec<(Target, RunResult<T, E>)>, Error> {
     let total_targets = targets.len();
     let counter = AtomicUsize::new(0);
     let currently_running = AtomicUsize::new(0);
-    let threadpool = ThreadPoolBuilder::new().build().unwrap();
+    // zero means rayon's default, one thread per cpu
+    let threadpool = ThreadPoolBuilder::new()
+        .num_threads(max_jobs.unwrap_or(0))
+        .build()
+        .unwrap();
 
     let results = threadpool.install(|| {
         targets
@@ -203,6 +217,13 @@ fn try_read_string(path: &Path) -> Result<String, Error> {
     })
 }
 
+fn try_read_bytes(path: &Path) -> Result<Vec<u8>, Error> {
+    std::fs::read(path).map_err(|error| Error::ReadFile {
+        path: path.to_owned(),
+        error,
+    })
+}
+
 fn try_read_json<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, Error> {
     let path = path.as_ref();
     try_read_string(path).and_then(|content| {
@@ -0,0 +1,468 @@
+//! Comparing fontc and fontmake output without python
+//!
+//! This produces the same shape of results as ttx_diff.py, so they can be
+//! summarized the same way, but compares binary tables directly instead of
+//! going through ttx. Layout tables are instead compared as the text printed by
+//! otl-normalizer, under the same names the script uses.
+//!
+//! Binary tables are split into rows at boundaries chosen by their content, so
+//! that inserting or removing a byte changes only the rows around it, and rows
+//! are matched in order as with a text diff. This is still coarser than
+//! comparing ttx; the python script remains available for the more precise
+//! comparison.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+    process::Command,
+};
+
+use otl_normalizer::NameMap;
+use write_fonts::{
+    read::{FontRef, TableProvider},
+    types::Tag,
+};
+
+use crate::{
+    args::DiffArgs,
+    error::Error,
+    try_read_bytes,
+    ttx_diff_runner::{self, DiffError, DiffOutput, DiffValue},
+    RunResult,
+};
+
+// the names ttx_diff.py uses for the output of otl-normalizer
+static MARK_KERN_NAME: &str = "(mark/kern)";
+static LIG_CARET_NAME: &str = "ligcaret";
+static SUBST_NAME: &str = "subst";
+
+/// The shortest and longest rows binary tables are split into.
+const MIN_ROW_LEN: usize = 4;
+const MAX_ROW_LEN: usize = 64;
+
+/// How many bytes before a potential row boundary decide whether it is one.
+const BOUNDARY_WINDOW: usize = 4;
+
+/// Give up matching rows in order once this many have to be inserted or removed,
+/// and match them ignoring order instead, which is much cheaper.
+const MAX_EDITS: usize = 2000;
+
+/// Tables compared as the text printed by otl-normalizer rather than as bytes
+const LAYOUT_TABLES: [Tag; 3] = [Tag::new(b"GDEF"), Tag::new(b"GPOS"), Tag::new(b"GSUB")];
+
+/// Tables larger in fontc by more than this fraction are reported.
+const SIZE_THRESHOLD: f32 = 0.1;
+
+pub(super) fn run_diff(args: &DiffArgs) -> Result<(), Error> {
+    let output = if args.python {
+        diff_with_script(&args.fontc, &args.fontmake)?
+    } else {
+        let fontc = try_read_bytes(&args.fontc)?;
+        let fontmake = try_read_bytes(&args.fontmake)?;
+        diff_fonts(&fontc, &fontmake)?
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&output).unwrap());
+        return Ok(());
+    }
+    match output {
+        DiffOutput::Identical => println!("output is identical"),
+        DiffOutput::Diffs(diffs) => {
+            for (tag, value) in diffs {
+                if tag.starts_with("sizeof(") {
+                    println!("{tag}: {}B", value.as_n_of_bytes());
+                } else {
+                    println!("{tag}: {value}");
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Compare the tables of two fonts, the first built by fontc.
+pub(crate) fn diff_fonts(fontc: &[u8], fontmake: &[u8]) -> Result<DiffOutput, Error> {
+    let fontc = FontRef::new(fontc).map_err(otl_normalizer::Error::FontRead)?;
+    let fontmake = FontRef::new(fontmake).map_err(otl_normalizer::Error::FontRead)?;
+    let fontc_tables = comparables(&fontc)?;
+    let fontmake_tables = comparables(&fontmake)?;
+    if fontc_tables == fontmake_tables {
+        return Ok(DiffOutput::Identical);
+    }
+
+    let mut out = BTreeMap::new();
+    let mut same_lines = 0;
+    let mut different_lines = 0;
+    for (tag, fontc_lines) in &fontc_tables {
+        match fontmake_tables.get(tag) {
+            None => {
+                different_lines += fontc_lines.len();
+                out.insert(tag.clone(), DiffValue::Only("fontc".into()));
+            }
+            Some(fontmake_lines) if fontmake_lines != fontc_lines => {
+                let ratio = diff_ratio(fontc_lines, fontmake_lines);
+                let n_lines = fontc_lines.len().max(fontmake_lines.len()) as f32;
+                same_lines += (n_lines * ratio) as usize;
+                different_lines += (n_lines * (1.0 - ratio)) as usize;
+                out.insert(tag.clone(), DiffValue::Ratio(ratio));
+            }
+            Some(_) => same_lines += fontc_lines.len(),
+        }
+    }
+    for (tag, fontmake_lines) in &fontmake_tables {
+        if !fontc_tables.contains_key(tag) {
+            different_lines += fontmake_lines.len();
+            out.insert(tag.clone(), DiffValue::Only("fontmake".into()));
+        }
+    }
+
+    for (tag, size_diff) in size_diffs(&fontc, &fontmake) {
+        out.insert(format!("sizeof({tag})"), DiffValue::Ratio(size_diff as f32));
+        // like the script, treat a size diff as a fictional 100 line table
+        different_lines += 100;
+    }
+
+    let total = same_lines as f32 / (same_lines + different_lines) as f32;
+    out.insert("total".into(), DiffValue::Ratio(total));
+    Ok(DiffOutput::Diffs(out))
+}
+
+/// Everything we compare in a font, as lines of text, by name.
+fn comparables(font: &FontRef) -> Result<BTreeMap<String, Vec<String>>, Error> {
+    let mut result = BTreeMap::new();
+    let gdef = font.gdef().ok();
+    let gpos = font.gpos().ok();
+    let gsub = font.gsub().ok();
+    let has_layout = gdef.is_some() || gpos.is_some() || gsub.is_some();
+    for record in font.table_directory.table_records() {
+        let tag = record.tag();
+        // counting them as both bytes and text would weigh them double
+        if has_layout && LAYOUT_TABLES.contains(&tag) {
+            continue;
+        }
+        let Some(data) = font.table_data(tag) else {
+            continue;
+        };
+        let mut bytes = data.as_bytes().to_vec();
+        if tag == Tag::new(b"head") {
+            normalize_head(&mut bytes);
+        }
+        result.insert(tag.to_string(), hex_rows(&bytes));
+    }
+
+    if !has_layout {
+        return Ok(result);
+    }
+    let names = NameMap::from_font(font)?;
+    let mut text = Vec::new();
+    if let Some(gpos) = gpos {
+        otl_normalizer::print_gpos(&mut text, &gpos, gdef.as_ref(), &names)?;
+        result.insert(MARK_KERN_NAME.into(), text_lines(&text));
+        text.clear();
+    }
+    if let Some(gdef) = gdef.as_ref() {
+        otl_normalizer::print_gdef(&mut text, gdef, &names)?;
+        result.insert(LIG_CARET_NAME.into(), text_lines(&text));
+        text.clear();
+    }
+    if let Some(gsub) = gsub {
+        otl_normalizer::print_gsub(&mut text, &gsub, gdef.as_ref(), &names)?;
+        result.insert(SUBST_NAME.into(), text_lines(&text));
+    }
+    Ok(result)
+}
+
+/// Zero the checksum adjustment and timestamps, which are expected to differ
+fn normalize_head(bytes: &mut [u8]) {
+    const CHECKSUM_ADJUSTMENT: std::ops::Range<usize> = 8..12;
+    const CREATED_AND_MODIFIED: std::ops::Range<usize> = 20..36;
+    for range in [CHECKSUM_ADJUSTMENT, CREATED_AND_MODIFIED] {
+        if let Some(bytes) = bytes.get_mut(range) {
+            bytes.fill(0);
+        }
+    }
+}
+
+/// Split bytes into rows of hex, ending a row wherever the bytes before it say so
+///
+/// Fixed size rows would make an inserted byte shift, and so change, every row
+/// that follows. Boundaries that depend only on nearby content line up again
+/// shortly after the insertion.
+fn hex_rows(bytes: &[u8]) -> Vec<String> {
+    let mut rows = Vec::new();
+    let mut start = 0;
+    for end in 1..=bytes.len() {
+        let len = end - start;
+        if end == bytes.len()
+            || len >= MAX_ROW_LEN
+            || (len >= MIN_ROW_LEN && is_row_boundary(&bytes[..end]))
+        {
+            rows.push(
+                bytes[start..end]
+                    .iter()
+                    .map(|b| format!("{b:02x}"))
+                    .collect(),
+            );
+            start = end;
+        }
+    }
+    rows
+}
+
+/// Whether a row should end after `bytes`, on average every 16 bytes
+fn is_row_boundary(bytes: &[u8]) -> bool {
+    let window = &bytes[bytes.len().saturating_sub(BOUNDARY_WINDOW)..];
+    let hash = window.iter().fold(0u32, |hash, b| {
+        hash.wrapping_mul(31).wrapping_add(*b as u32)
+    });
+    hash.wrapping_mul(0x9e3779b1) >> 28 == 0
+}
+
+fn text_lines(text: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(text)
+        .lines()
+        .map(str::to_owned)
+        .collect()
+}
+
+/// The fraction of lines the two inputs have in common, in order.
+///
+/// Falls back to [quick_ratio] if the inputs are so different that finding the
+/// lines in common would take too long.
+fn diff_ratio(lines1: &[String], lines2: &[String]) -> f32 {
+    let total = lines1.len() + lines2.len();
+    if total == 0 {
+        return 1.0;
+    }
+    match longest_common_subsequence(lines1, lines2, MAX_EDITS) {
+        Some(common) => 2.0 * common as f32 / total as f32,
+        None => quick_ratio(lines1, lines2),
+    }
+}
+
+/// The length of the longest common subsequence of a and b, or None if more than
+/// max_edits insertions and deletions are needed to turn one into the other
+///
+/// This is the greedy algorithm from Myers' "An O(ND) Difference Algorithm and Its
+/// Variations", which only needs to remember the furthest point reached on each
+/// diagonal.
+fn longest_common_subsequence<T: PartialEq>(a: &[T], b: &[T], max_edits: usize) -> Option<usize> {
+    let prefix = a.iter().zip(b).take_while(|(a, b)| a == b).count();
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    let suffix = a
+        .iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (&a[..a.len() - suffix], &b[..b.len() - suffix]);
+
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m).min(max_edits as isize);
+    let offset = max + 1;
+    // the furthest x reached on each diagonal k = x - y, indexed by k + offset
+    let mut furthest = vec![0isize; 2 * max as usize + 3];
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let i = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && furthest[i - 1] < furthest[i + 1]) {
+                furthest[i + 1]
+            } else {
+                furthest[i - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            furthest[i] = x;
+            if x >= n && y >= m {
+                return Some(prefix + suffix + ((n + m - d) / 2) as usize);
+            }
+        }
+    }
+    None
+}
+
+/// The fraction of lines the two inputs have in common, ignoring order.
+///
+/// This matches python's `SequenceMatcher.quick_ratio`.
+fn quick_ratio(lines1: &[String], lines2: &[String]) -> f32 {
+    let total = lines1.len() + lines2.len();
+    if total == 0 {
+        return 1.0;
+    }
+    let mut counts = HashMap::<&str, usize>::new();
+    for line in lines2 {
+        *counts.entry(line.as_str()).or_default() += 1;
+    }
+    let mut matches = 0;
+    for line in lines1 {
+        if let Some(count) = counts.get_mut(line.as_str()).filter(|count| **count > 0) {
+            *count -= 1;
+            matches += 1;
+        }
+    }
+    2.0 * matches as f32 / total as f32
+}
+
+/// Tables that are significantly larger in fontc, and by how many bytes
+fn size_diffs(fontc: &FontRef, fontmake: &FontRef) -> Vec<(Tag, i64)> {
+    let fontmake_sizes = fontmake
+        .table_directory
+        .table_records()
+        .iter()
+        .map(|record| (record.tag(), record.length()))
+        .collect::<HashMap<_, _>>();
+    fontc
+        .table_directory
+        .table_records()
+        .iter()
+        .filter_map(|record| {
+            let fontc_len = record.length();
+            let fontmake_len = *fontmake_sizes.get(&record.tag())?;
+            if fontc_len <= fontmake_len {
+                return None;
+            }
+            let len_ratio = fontmake_len as f32 / fontc_len as f32;
+            (1.0 - len_ratio > SIZE_THRESHOLD)
+                .then(|| (record.tag(), fontc_len as i64 - fontmake_len as i64))
+        })
+        .collect()
+}
+
+/// Compare two fonts with ttx_diff.py, for when we want the precise comparison.
+fn diff_with_script(fontc: &Path, fontmake: &Path) -> Result<DiffOutput, Error> {
+    ttx_diff_runner::assert_can_run_script();
+    let tempdir = tempfile::tempdir().expect("couldn't create tempdir");
+    let build_dir = tempdir.path().join("default");
+    crate::try_create_dir(&build_dir)?;
+    for (from, name) in [(fontc, "fontc.ttf"), (fontmake, "fontmake.ttf")] {
+        std::fs::copy(from, build_dir.join(name)).map_err(|error| Error::ReadFile {
+            path: from.to_owned(),
+            error,
+        })?;
+    }
+    // the script reuses fonts that are already built, so the source is never read
+    let mut cmd = Command::new("python3");
+    cmd.args([ttx_diff_runner::SCRIPT_PATH, "--json", "--rebuild", "none"])
+        .arg("--outdir")
+        .arg(tempdir.path())
+        .arg(fontc);
+    let output = cmd.output().map_err(|e| Error::Diff(e.to_string()))?;
+    match ttx_diff_runner::read_script_output(&output) {
+        RunResult::Success(output) => Ok(output),
+        RunResult::Fail(DiffError::Other(e)) => Err(Error::Diff(e)),
+        RunResult::Fail(e) => Err(Error::Diff(format!("{e:?}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use write_fonts::{
+        tables::{head::Head, maxp::Maxp},
+        types::LongDateTime,
+        FontBuilder,
+    };
+
+    use super::*;
+
+    fn lines(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn make_font(units_per_em: u16, modified: i64, num_glyphs: u16) -> Vec<u8> {
+        let head = Head {
+            units_per_em,
+            modified: LongDateTime::new(modified),
+            ..Default::default()
+        };
+        let mut builder = FontBuilder::new();
+        builder.add_table(&head).unwrap();
+        builder.add_table(&Maxp::new(num_glyphs)).unwrap();
+        builder.build()
+    }
+
+    #[test]
+    fn quick_ratio_ignores_order() {
+        let a = lines(&["a", "b", "c", "d"]);
+        let b = lines(&["d", "c", "b", "x"]);
+        assert_eq!(0.75, quick_ratio(&a, &b));
+        assert_eq!(1.0, quick_ratio(&a, &a));
+        assert_eq!(0.0, quick_ratio(&a, &[]));
+    }
+
+    #[test]
+    fn ratio_respects_order() {
+        let a = lines(&["a", "b", "c", "d"]);
+        let b = lines(&["d", "c", "b", "x"]);
+        // only one of b, c and d can be matched in order
+        assert_eq!(0.25, diff_ratio(&a, &b));
+        assert_eq!(1.0, diff_ratio(&a, &a));
+        assert_eq!(0.0, diff_ratio(&a, &[]));
+    }
+
+    #[test]
+    fn longest_common_subsequence_lengths() {
+        let lcs = |a: &str, b: &str| {
+            let a = a.chars().collect::<Vec<_>>();
+            let b = b.chars().collect::<Vec<_>>();
+            longest_common_subsequence(&a, &b, 100)
+        };
+        assert_eq!(Some(0), lcs("", ""));
+        assert_eq!(Some(0), lcs("abc", ""));
+        assert_eq!(Some(3), lcs("abc", "abc"));
+        assert_eq!(Some(4), lcs("abcabba", "cbabac"));
+        assert_eq!(Some(3), lcs("xaybzc", "abc"));
+        assert_eq!(
+            None,
+            longest_common_subsequence(&[1, 2, 3, 4], &[5, 6, 7, 8], 3)
+        );
+    }
+
+    #[test]
+    fn inserted_byte_changes_few_rows() {
+        // bytes that look random, so that row boundaries fall at varied places
+        let mut state = 1u32;
+        let bytes = (0..4000)
+            .map(|_| {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect::<Vec<_>>();
+        let mut inserted = bytes.clone();
+        inserted.insert(100, 0xff);
+
+        let (before, after) = (hex_rows(&bytes), hex_rows(&inserted));
+        assert!(diff_ratio(&before, &after) > 0.95);
+        assert_eq!(
+            bytes.len() * 2,
+            before.iter().map(String::len).sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn timestamps_are_ignored() {
+        let fontc = make_font(1000, 1, 5);
+        let fontmake = make_font(1000, 2, 5);
+        assert!(matches!(
+            diff_fonts(&fontc, &fontmake).unwrap(),
+            DiffOutput::Identical
+        ));
+    }
+
+    #[test]
+    fn differing_tables() {
+        let fontc = make_font(1000, 1, 5);
+        let fontmake = make_font(2048, 1, 6);
+        let DiffOutput::Diffs(diffs) = diff_fonts(&fontc, &fontmake).unwrap() else {
+            panic!("fonts should differ");
+        };
+        assert_eq!(
+            vec!["head", "maxp", "total"],
+            diffs.keys().map(String::as_str).collect::<Vec<_>>()
+        );
+        assert!(diffs["total"].ratio().unwrap() < 1.0);
+    }
+}
@@ -3,13 +3,15 @@
 use std::{
     ffi::OsStr,
     fmt::{Display, Write},
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
     str::FromStr,
 };
 
 use serde::{Deserialize, Serialize};
 
 static VIRTUAL_CONFIG_DIR: &str = "sources";
+/// Stands in for the config file of a local source, which has none
+static LOCAL_CONFIG: &str = "$LOCAL";
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub(crate) struct Target {
@@ -61,6 +63,17 @@ impl Target {
         }
     }
 
+    /// A source on the local filesystem, rather than in a git repo.
+    ///
+    /// Local targets are identified by their absolute path, so they can be
+    /// used with any git cache dir.
+    pub(crate) fn local(source_path: &Path) -> Self {
+        let source_path = std::path::absolute(source_path).unwrap_or(source_path.to_path_buf());
+        let dir = source_path.parent().unwrap_or(Path::new("/"));
+        let file_name = source_path.file_name().unwrap_or_default();
+        Self::new(dir, "", LOCAL_CONFIG, false, file_name)
+    }
+
     pub(crate) fn to_gftools_target(&self) -> Self {
         Self {
             build: BuildType::GfTools,
@@ -128,7 +141,13 @@ impl Target {
     /// target, relative to the root git cache.
     pub(crate) fn cache_dir(&self, in_dir: &Path) -> PathBuf {
         let config = self.config.file_stem().unwrap_or(OsStr::new("config"));
-        let mut result = in_dir.join(self.source_dir());
+        // local targets have an absolute source_dir, which must not replace in_dir
+        let source_dir = self.source_dir();
+        let source_dir = source_dir
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)));
+        let mut result = in_dir.to_path_buf();
+        result.extend(source_dir);
         result.push(config);
         result.push(self.source.file_stem().unwrap());
         result.push(self.build.name());
@@ -243,6 +262,13 @@ impl FromStr for Target {
             .trim()
             .split_once('?')
             .ok_or_else(|| "missing '?'".to_string())?;
+
+        if let Some(dir) = config_part.strip_suffix(LOCAL_CONFIG) {
+            return match type_.trim_end_matches(')') {
+                "default" => Ok(Self::local(&Path::new(dir).join(source))),
+                other => Err(format!("unknown build type '{other}' for local source")),
+            };
+        }
         let (split_at, _) = config_part
             .match_indices('/')
             .nth(1)
@@ -346,6 +372,26 @@ mod tests {
         assert_eq!(target, der)
     }
 
+    #[test]
+    fn string_repr_local() {
+        let target = Target::local(Path::new("/home/me/fonts/derp.glyphs"));
+
+        let asstr = target.to_string();
+
+        assert_eq!(asstr, "/home/me/fonts/$LOCAL derp.glyphs? (default)");
+
+        let der = Target::from_str(&asstr).unwrap();
+        assert_eq!(target, der);
+        assert_eq!(
+            Path::new("/home/me/fonts/derp.glyphs"),
+            target.source_path(Path::new("/cache"))
+        );
+        assert_eq!(
+            Path::new("/cache/home/me/fonts/$LOCAL/derp/default"),
+            target.cache_dir(Path::new("/cache"))
+        );
+    }
+
     #[test]
     fn target_for_disambiguated_source() {
         let target = Target::new(
@@ -1,12 +1,12 @@
 use std::{
     collections::BTreeMap,
     path::{Path, PathBuf},
-    process::Command,
+    process::{Command, Output},
 };
 
 use crate::{ci::ResultsCache, BuildType, Results, RunResult, Target};
 
-static SCRIPT_PATH: &str = "./resources/scripts/ttx_diff.py";
+pub(crate) static SCRIPT_PATH: &str = "./resources/scripts/ttx_diff.py";
 
 pub(super) struct TtxContext {
     pub fontc_path: PathBuf,
@@ -43,8 +43,25 @@ pub(super) fn run_ttx_diff(ctx: &TtxContext, target: &Target) -> RunResult<DiffO
         Ok(val) => val,
     };
 
+    let result = read_script_output(&output);
+
+    if let RunResult::Fail(DiffError::Other(err)) = &result {
+        // these errors indicate something unexpected happening at runtime,
+        // so it is useful to see them in our logs.
+        log::warn!("error running {target} '{err}'");
+    }
+
+    if fontmake_finished(&result) {
+        ctx.results_cache
+            .save_built_files_to_cache(target, &build_dir);
+    }
+    result
+}
+
+/// Interpret the exit status and output of a ttx_diff.py run with `--json`
+pub(crate) fn read_script_output(output: &Output) -> RunResult<DiffOutput, DiffError> {
     let stderr = String::from_utf8_lossy(&output.stderr);
-    let result = match output.status.code() {
+    match output.status.code() {
         // success, diffs are identical
         Some(0) => RunResult::Success(DiffOutput::Identical),
         // there are diffs, or one or more compilers did not finish
@@ -78,26 +95,58 @@ pub(super) fn run_ttx_diff(ctx: &TtxContext, target: &Target) -> RunResult<DiffO
                 "unknown error (signal {signal}): '{stderr}'"
             )))
         }
-    };
-
-    if let RunResult::Fail(DiffError::Other(err)) = &result {
-        // these errors indicate something unexpected happening at runtime,
-        // so it is useful to see them in our logs.
-        log::warn!("error running {target} '{err}'");
     }
+}
 
-    if fontmake_finished(&result) {
-        ctx.results_cache
-            .save_built_files_to_cache(target, &build_dir);
+/// Run ttx_diff, rerunning failures up to `retries` times.
+///
+/// If the attempts don't all have the same outcome the result is
+/// [`DiffError::Flaky`], so nondeterminism isn't mistaken for a hard failure.
+pub(super) fn run_ttx_diff_with_retries(
+    ctx: &TtxContext,
+    target: &Target,
+    retries: usize,
+) -> RunResult<DiffOutput, DiffError> {
+    let mut result = run_ttx_diff(ctx, target);
+    let mut attempts = vec![describe_outcome(&result)];
+    while matches!(result, RunResult::Fail(_)) && attempts.len() <= retries {
+        log::debug!("retrying {target} (attempt {})", attempts.len() + 1);
+        result = run_ttx_diff(ctx, target);
+        attempts.push(describe_outcome(&result));
+    }
+    if attempts.iter().any(|attempt| *attempt != attempts[0]) {
+        log::warn!("{target} is flaky: {}", attempts.join(", "));
+        return RunResult::Fail(DiffError::Flaky(attempts));
     }
     result
 }
 
+/// A short description of a result, equal for results we consider the same.
+fn describe_outcome(result: &RunResult<DiffOutput, DiffError>) -> String {
+    match result {
+        RunResult::Success(DiffOutput::Identical) => "identical".to_string(),
+        RunResult::Success(DiffOutput::Diffs(diffs)) => match diffs.get("total") {
+            Some(total) => format!("diff {total}"),
+            None => "diff".to_string(),
+        },
+        RunResult::Fail(DiffError::CompileFailed(failed)) => {
+            match (&failed.fontc, &failed.fontmake) {
+                (Some(_), Some(_)) => "both failed",
+                (Some(_), None) => "fontc failed",
+                _ => "fontmake failed",
+            }
+            .to_string()
+        }
+        RunResult::Fail(DiffError::Other(_)) => "other failure".to_string(),
+        RunResult::Fail(DiffError::Flaky(_)) => "flaky".to_string(),
+    }
+}
+
 fn fontmake_finished(result: &RunResult<DiffOutput, DiffError>) -> bool {
     match result {
         RunResult::Success(_) => true,
         RunResult::Fail(DiffError::CompileFailed(diff)) => diff.fontmake.is_none(),
-        RunResult::Fail(DiffError::Other(_)) => false,
+        RunResult::Fail(DiffError::Other(_) | DiffError::Flaky(_)) => false,
     }
 }
 
@@ -118,8 +167,16 @@ pub(crate) struct Summary {
     pub(crate) fontmake_failed: u32,
     pub(crate) both_failed: u32,
     pub(crate) other_failure: u32,
+    /// Targets whose outcome changed when retried
+    #[serde(default)]
+    pub(crate) flaky: u32,
     pub(crate) diff_perc_including_failures: f32,
     pub(crate) diff_perc_excluding_failures: f32,
+    /// Mean similarity of each table across targets both compilers built.
+    ///
+    /// Tables that were identical for every target are not listed.
+    #[serde(default)]
+    pub(crate) table_scores: BTreeMap<String, f32>,
 }
 
 impl Summary {
@@ -150,6 +207,7 @@ impl Summary {
         let diff_perc_excluding_failures = non_nan(total_diff / success.len() as f32) * 100.;
         let (mut fontc_failed, mut fontmake_failed, mut both_failed, mut other_failure) =
             (0, 0, 0, 0);
+        let mut flaky = 0;
         for fail in failure.values() {
             match fail {
                 DiffError::CompileFailed(err) if err.fontc.is_some() && err.fontmake.is_some() => {
@@ -159,6 +217,7 @@ impl Summary {
                 DiffError::CompileFailed(err) if err.fontmake.is_some() => fontmake_failed += 1,
                 DiffError::CompileFailed(_) => unreachable!(),
                 DiffError::Other(_) => other_failure += 1,
+                DiffError::Flaky(_) => flaky += 1,
             }
         }
 
@@ -170,10 +229,45 @@ impl Summary {
             fontmake_failed,
             both_failed,
             other_failure,
+            flaky,
             diff_perc_including_failures,
             diff_perc_excluding_failures,
+            table_scores: table_scores(success.values()),
+        }
+    }
+}
+
+/// Average the similarity of each table over a set of results.
+///
+/// A table missing from a result matched exactly, and a table only one
+/// compiler produced scores zero.
+pub(crate) fn table_scores<'a>(
+    results: impl IntoIterator<Item = &'a DiffOutput>,
+) -> BTreeMap<String, f32> {
+    let mut n_results = 0;
+    // tag => (sum of scores, number of results with a diff)
+    let mut diffs = BTreeMap::<&str, (f32, usize)>::new();
+    for result in results {
+        n_results += 1;
+        let DiffOutput::Diffs(tables) = result else {
+            continue;
+        };
+        for (tag, value) in tables {
+            if tag == "total" || tag.starts_with("sizeof(") {
+                continue;
+            }
+            let (sum, count) = diffs.entry(tag).or_default();
+            *sum += value.ratio().unwrap_or_default();
+            *count += 1;
         }
     }
+    diffs
+        .into_iter()
+        .map(|(tag, (sum, count))| {
+            let identical = (n_results - count) as f32;
+            (tag.to_string(), (sum + identical) / n_results as f32)
+        })
+        .collect()
 }
 
 fn assert_has_timeout_coreutil() {
@@ -243,6 +337,8 @@ enum RawDiffOutput {
 pub(crate) enum DiffError {
     CompileFailed(CompileFailed),
     Other(String),
+    /// Retrying changed the outcome, one description per attempt
+    Flaky(Vec<String>),
 }
 
 /// One or both compilers failed to run
@@ -316,6 +412,54 @@ impl std::fmt::Display for DiffValue {
 mod tests {
     use super::*;
 
+    #[test]
+    fn flaky_is_its_own_category() {
+        let flaky = DiffError::Flaky(vec!["fontc failed".to_string(), "identical".to_string()]);
+        let json = serde_json::to_string(&flaky).unwrap();
+        assert_eq!(r#"{"flaky":["fontc failed","identical"]}"#, json);
+
+        let mut results = Results::default();
+        results.failure.insert(
+            Target::new("org/repo", "123", "sources/config.yaml", false, "a.glyphs"),
+            serde_json::from_str(&json).unwrap(),
+        );
+        let summary = Summary::new(&results);
+        assert_eq!(
+            (1, 0, 0),
+            (summary.flaky, summary.fontc_failed, summary.other_failure)
+        );
+    }
+
+    #[test]
+    fn per_table_scores() {
+        let diffs = |entries: &[(&str, DiffValue)]| {
+            DiffOutput::Diffs(
+                entries
+                    .iter()
+                    .map(|(tag, value)| (tag.to_string(), value.clone()))
+                    .collect(),
+            )
+        };
+        let results = [
+            DiffOutput::Identical,
+            diffs(&[
+                ("GPOS", DiffValue::Ratio(0.5)),
+                ("sizeof(GPOS)", DiffValue::Ratio(120.0)),
+                ("total", DiffValue::Ratio(0.9)),
+            ]),
+            diffs(&[
+                ("GPOS", DiffValue::Ratio(0.75)),
+                ("kern", DiffValue::Only("fontmake".to_string())),
+                ("total", DiffValue::Ratio(0.8)),
+            ]),
+            diffs(&[("total", DiffValue::Ratio(1.0))]),
+        ];
+        assert_eq!(
+            BTreeMap::from([("GPOS".to_string(), 0.8125), ("kern".to_string(), 0.75)]),
+            table_scores(&results)
+        );
+    }
+
     #[test]
     fn basic_serde() {
         fn expect_success(s: &str, expected: &[(&str, f32)]) -> bool {
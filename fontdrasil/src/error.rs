@@ -0,0 +1,49 @@
+//! Error categories shared by the FE and BE, for callers that react to kinds of errors
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// The broad kind of a compilation error
+///
+/// Lets programmatic consumers, such as editors, decide how to present an error
+/// without parsing its message.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// A source, or something it refers to, could not be read or understood
+    Parse,
+    /// Masters, or the glyphs in them, can't be combined into a variable font
+    Interpolation,
+    /// Features failed to parse, validate or compile
+    FeatureCompile,
+    /// The source was read but contains something invalid or unsupported
+    Validation,
+    /// Reading or writing files failed
+    Io,
+    /// The compiler got itself into a state it should not have; a bug
+    Internal,
+    /// Whoever started the compile asked for it to stop
+    Cancelled,
+}
+
+impl ErrorCategory {
+    /// A stable identifier for the category, e.g. for matching in scripts
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorCategory::Parse => "parse",
+            ErrorCategory::Interpolation => "interpolation",
+            ErrorCategory::FeatureCompile => "feature_compile",
+            ErrorCategory::Validation => "validation",
+            ErrorCategory::Io => "io",
+            ErrorCategory::Internal => "internal",
+            ErrorCategory::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl Display for ErrorCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
@@ -2,8 +2,10 @@
 
 pub mod agl;
 pub mod coords;
+pub mod error;
 pub mod orchestration;
 pub mod paths;
 mod piecewise_linear_map;
 pub mod types;
 pub mod util;
+pub mod warnings;
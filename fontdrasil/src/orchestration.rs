@@ -4,6 +4,10 @@ use std::{
     collections::HashSet,
     fmt::{Debug, Display},
     hash::Hash,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
 pub const MISSING_DATA: &str = "Missing data, dependency management failed us?";
@@ -79,6 +83,22 @@ impl<I: Identifier> AccessBuilder<I> {
         self.add_access(AccessType::SpecificInstanceOfVariant(id.into()))
     }
 
+    /// Access to everything the provided access permits is required.
+    ///
+    /// Panics if access is [`Access::Unknown`], there's no way to combine that.
+    pub fn union(self, access: Access<I>) -> Self {
+        match access {
+            Access::None => self,
+            Access::All => Self {
+                access: Access::All,
+            },
+            Access::SpecificInstanceOfVariant(id) => self.specific_instance(id),
+            Access::Variant(id) => self.variant(id),
+            Access::Set(ids) => ids.into_iter().fold(self, |b, id| b.add_access(id)),
+            Access::Unknown => panic!("Cannot combine {:?} and Unknown", self.access),
+        }
+    }
+
     pub fn build(self) -> Access<I> {
         self.access
     }
@@ -304,3 +324,26 @@ impl<I: Identifier> AccessControlList<I> {
         assert_access_one(&self.write_access, id, "write");
     }
 }
+
+/// Lets whoever started a compile stop it early
+///
+/// Cancellation is cooperative: the orchestrator checks the token between jobs and
+/// long-running work, such as loops over every glyph, checks it as it goes. Clones
+/// share state, so keep one and hand a clone to the compile.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the compile to stop; it may take a moment to notice
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,343 @@
+//! Warnings with stable codes, and a policy to allow or deny them by code
+//!
+//! Warnings that users may want to act on are logged with a `code` key, e.g.
+//! `warn!(code = codes::INTERPOLATION_MISMATCH; "...")`. Every crate logs through
+//! the same logger, so a [WarningSink] installed as that logger sees the warnings of
+//! the FE, the BE and feature compilation alike. It drops allowed warnings, logs
+//! denied warnings as errors and counts them so the build can fail.
+
+use std::{
+    collections::BTreeSet,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        OnceLock,
+    },
+};
+
+use log::{kv::Key, Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+/// The log key holding a warning's code
+pub const CODE_KEY: &str = "code";
+
+/// The codes of warnings that can be allowed or denied individually
+pub mod codes {
+    /// A glyph's masters differ in a way that is repaired or ignored
+    pub const INTERPOLATION_MISMATCH: &str = "interpolation-mismatch";
+    /// A source has a custom parameter we don't know what to do with
+    pub const UNKNOWN_CUSTOM_PARAMETER: &str = "unknown-custom-parameter";
+    /// Feature parsing, validation or compilation produced warnings
+    pub const FEA_WARNING: &str = "fea-warning";
+
+    /// Every code, e.g. to validate command line arguments
+    pub const ALL: &[&str] = &[
+        INTERPOLATION_MISMATCH,
+        UNKNOWN_CUSTOM_PARAMETER,
+        FEA_WARNING,
+    ];
+}
+
+/// What to do with a warning
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningAction {
+    /// Drop it
+    Allow,
+    /// Log it as a warning
+    Warn,
+    /// Log it as an error and fail the build
+    Deny,
+}
+
+/// Which warnings to allow or deny
+///
+/// An explicit allow wins over both an explicit deny and `deny_all`, so
+/// `--deny-warnings --allow <code>` denies everything but that code.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WarningPolicy {
+    /// Deny every warning that isn't explicitly allowed, coded or not
+    pub deny_all: bool,
+    /// Codes of warnings to deny
+    pub deny: BTreeSet<String>,
+    /// Codes of warnings to drop
+    pub allow: BTreeSet<String>,
+}
+
+impl WarningPolicy {
+    /// What to do with a warning that has the given code, if any
+    pub fn action(&self, code: Option<&str>) -> WarningAction {
+        match code {
+            Some(code) if self.allow.contains(code) => WarningAction::Allow,
+            Some(code) if self.deny.contains(code) => WarningAction::Deny,
+            _ if self.deny_all => WarningAction::Deny,
+            _ => WarningAction::Warn,
+        }
+    }
+
+    /// Whether any warning could be denied
+    fn denies_any(&self) -> bool {
+        self.deny_all || !self.deny.is_empty()
+    }
+}
+
+/// A logger that applies a [WarningPolicy] to warnings before passing them on
+pub struct WarningSink {
+    inner: Box<dyn Log>,
+    policy: WarningPolicy,
+    denied: AtomicUsize,
+}
+
+impl WarningSink {
+    pub fn new(inner: Box<dyn Log>, policy: WarningPolicy) -> Self {
+        WarningSink {
+            inner,
+            policy,
+            denied: AtomicUsize::new(0),
+        }
+    }
+
+    /// The number of denied warnings logged so far
+    pub fn denied(&self) -> usize {
+        self.denied.load(Ordering::Relaxed)
+    }
+
+    /// Start counting the denied warnings logged from now on
+    pub fn count_denied(&self) -> DeniedCount<'_> {
+        DeniedCount {
+            sink: Some(self),
+            start: self.denied(),
+        }
+    }
+}
+
+/// The denied warnings logged through a [WarningSink] since [WarningSink::count_denied]
+///
+/// The sink counts for as long as the process runs, so each compile, e.g. each
+/// rebuild in watch mode, counts its own. The sink can't tell which compile a
+/// warning is from, so denying warnings is only meaningful while a single compile
+/// runs at a time.
+#[derive(Clone, Copy)]
+pub struct DeniedCount<'a> {
+    sink: Option<&'a WarningSink>,
+    start: usize,
+}
+
+impl DeniedCount<'_> {
+    /// The number of denied warnings logged since counting started
+    pub fn get(&self) -> usize {
+        self.sink
+            .map(|sink| sink.denied().saturating_sub(self.start))
+            .unwrap_or_default()
+    }
+}
+
+impl Log for WarningSink {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // denied warnings must be seen even if the inner logger doesn't want warnings
+        (metadata.level() <= Level::Warn && self.policy.denies_any())
+            || self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if record.level() != Level::Warn {
+            self.inner.log(record);
+            return;
+        }
+        let code = record
+            .key_values()
+            .get(Key::from_str(CODE_KEY))
+            .map(|value| value.to_string());
+        match self.policy.action(code.as_deref()) {
+            WarningAction::Allow => (),
+            WarningAction::Warn => self.inner.log(record),
+            WarningAction::Deny => {
+                self.denied.fetch_add(1, Ordering::Relaxed);
+                self.inner.log(
+                    &Record::builder()
+                        .args(*record.args())
+                        .level(Level::Error)
+                        .target(record.target())
+                        .module_path(record.module_path())
+                        .file(record.file())
+                        .line(record.line())
+                        .key_values(record.key_values())
+                        .build(),
+                );
+            }
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush()
+    }
+}
+
+static SINK: OnceLock<WarningSink> = OnceLock::new();
+
+/// Install a [WarningSink] wrapping `logger` as the global logger
+///
+/// `max_level` is the most verbose level `logger` wants; it is raised to include
+/// warnings if the policy denies any, so that they can be counted.
+pub fn install(
+    logger: Box<dyn Log>,
+    max_level: LevelFilter,
+    policy: WarningPolicy,
+) -> Result<(), SetLoggerError> {
+    let max_level = if policy.denies_any() {
+        max_level.max(LevelFilter::Warn)
+    } else {
+        max_level
+    };
+    let sink = SINK.get_or_init(|| WarningSink::new(logger, policy));
+    log::set_logger(sink)?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+/// The number of denied warnings logged through the installed [WarningSink]
+///
+/// Zero if none is installed. This is the total for the process, use
+/// [count_denied_warnings] for those of a single compile.
+pub fn denied_warnings() -> usize {
+    SINK.get().map(WarningSink::denied).unwrap_or_default()
+}
+
+/// Start counting the denied warnings logged through the installed [WarningSink]
+///
+/// The count stays zero if none is installed.
+pub fn count_denied_warnings() -> DeniedCount<'static> {
+    match SINK.get() {
+        Some(sink) => sink.count_denied(),
+        None => DeniedCount {
+            sink: None,
+            start: 0,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use log::{Level, Log, Metadata, Record};
+
+    use super::{codes, WarningAction, WarningPolicy, WarningSink, CODE_KEY};
+
+    /// Remembers the level and message of everything logged
+    #[derive(Default, Clone)]
+    struct Recorder(Arc<Mutex<Vec<(Level, String)>>>);
+
+    impl Log for Recorder {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.level() <= Level::Error
+        }
+
+        fn log(&self, record: &Record) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((record.level(), record.args().to_string()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    fn policy(deny_all: bool, deny: &[&str], allow: &[&str]) -> WarningPolicy {
+        WarningPolicy {
+            deny_all,
+            deny: deny.iter().map(|c| c.to_string()).collect(),
+            allow: allow.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    fn log_warning(sink: &WarningSink, code: Option<&str>, message: &str) {
+        let key_values = code.map(|code| [(CODE_KEY, code)]);
+        let mut builder = Record::builder();
+        builder.level(Level::Warn);
+        if let Some(key_values) = &key_values {
+            builder.key_values(key_values);
+        }
+        sink.log(&builder.args(format_args!("{message}")).build());
+    }
+
+    #[test]
+    fn warn_by_default() {
+        let policy = WarningPolicy::default();
+        assert_eq!(WarningAction::Warn, policy.action(Some(codes::FEA_WARNING)));
+        assert_eq!(WarningAction::Warn, policy.action(None));
+    }
+
+    #[test]
+    fn allow_beats_deny_all() {
+        let policy = policy(true, &[], &[codes::UNKNOWN_CUSTOM_PARAMETER]);
+        assert_eq!(
+            WarningAction::Allow,
+            policy.action(Some(codes::UNKNOWN_CUSTOM_PARAMETER))
+        );
+        assert_eq!(
+            WarningAction::Deny,
+            policy.action(Some(codes::INTERPOLATION_MISMATCH))
+        );
+        assert_eq!(WarningAction::Deny, policy.action(None));
+    }
+
+    #[test]
+    fn deny_by_code() {
+        let policy = policy(false, &[codes::INTERPOLATION_MISMATCH], &[]);
+        assert_eq!(
+            WarningAction::Deny,
+            policy.action(Some(codes::INTERPOLATION_MISMATCH))
+        );
+        assert_eq!(WarningAction::Warn, policy.action(Some(codes::FEA_WARNING)));
+        assert_eq!(WarningAction::Warn, policy.action(None));
+    }
+
+    #[test]
+    fn sink_applies_policy() {
+        let recorder = Recorder::default();
+        let sink = WarningSink::new(
+            Box::new(recorder.clone()),
+            policy(
+                false,
+                &[codes::INTERPOLATION_MISMATCH],
+                &[codes::UNKNOWN_CUSTOM_PARAMETER],
+            ),
+        );
+
+        log_warning(&sink, Some(codes::INTERPOLATION_MISMATCH), "denied");
+        log_warning(&sink, Some(codes::UNKNOWN_CUSTOM_PARAMETER), "allowed");
+        log_warning(&sink, None, "uncoded");
+
+        assert_eq!(1, sink.denied());
+        assert_eq!(
+            vec![
+                (Level::Error, "denied".to_string()),
+                (Level::Warn, "uncoded".to_string()),
+            ],
+            *recorder.0.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn count_denied_from_when_counting_started() {
+        let sink = WarningSink::new(Box::new(Recorder::default()), policy(true, &[], &[]));
+        log_warning(&sink, None, "earlier");
+
+        let first = sink.count_denied();
+        log_warning(&sink, None, "first");
+        assert_eq!(1, first.get());
+
+        let second = sink.count_denied();
+        log_warning(&sink, None, "second");
+        log_warning(&sink, None, "second again");
+        assert_eq!((3, 2), (first.get(), second.get()));
+        assert_eq!(4, sink.denied());
+    }
+
+    #[test]
+    fn sink_enables_warnings_it_might_deny() {
+        let warn = Metadata::builder().level(Level::Warn).build();
+        let permissive = WarningSink::new(Box::new(Recorder::default()), Default::default());
+        let strict = WarningSink::new(Box::new(Recorder::default()), policy(true, &[], &[]));
+        assert!(!permissive.enabled(&warn));
+        assert!(strict.enabled(&warn));
+    }
+}
@@ -0,0 +1,572 @@
+//! Check that glyph sources are compatible before anything tries to interpolate them.
+//!
+//! Incompatible sources otherwise fail one glyph at a time, deep in backend work
+//! such as gvar construction. Checking all the IR up front lets us report every
+//! problem at once, with the glyph and location responsible.
+//!
+//! Glyphs sources may ask, by leaving 'Enforce Compatibility Check' off, that glyphs
+//! that still don't interpolate be exported without variations rather than fail
+//! the build, see [MiscMetadata::enforce_compatibility](crate::ir::MiscMetadata).
+//!
+//! Some incompatibilities have a well known fix which, unless
+//! [Flags::REPAIR_COMPATIBILITY] is cleared, we apply before checking. Contours
+//! are also matched by geometry, see [crate::contour_order], if
+//! [Flags::MATCH_CONTOURS] is set.
+
+use std::fmt::Display;
+
+use fontdrasil::{
+    coords::NormalizedLocation,
+    orchestration::{Access, AccessBuilder, Work},
+    types::GlyphName,
+    warnings::codes,
+};
+use kurbo::{BezPath, PathEl};
+use log::{debug, info, warn};
+
+use crate::{
+    contour_order::match_contours,
+    error::Error,
+    glyph::{convert_components_to_contours, distinct_component_glyph_seqs},
+    ir::{AnchorKind, Glyph, GlyphAnchors, GlyphBuilder, GlyphInstance},
+    orchestration::{Context, Flags, IrWork, WorkId},
+};
+
+pub fn create_compatibility_check_work() -> Box<IrWork> {
+    Box::new(CompatibilityCheckWork {})
+}
+
+#[derive(Debug)]
+struct CompatibilityCheckWork {}
+
+/// A way in which a glyph source differs from the default source of the same glyph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incompatibility {
+    pub glyph_name: GlyphName,
+    pub location: NormalizedLocation,
+    pub kind: IncompatibilityKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IncompatibilityKind {
+    ContourCount {
+        expected: usize,
+        actual: usize,
+    },
+    SegmentCount {
+        contour: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// A different kind of segment than the default, e.g. a line where the default
+    /// has a curve, or a quadratic curve where the default has a cubic
+    SegmentType {
+        contour: usize,
+        segment: usize,
+    },
+    Components {
+        expected: Vec<GlyphName>,
+        actual: Vec<GlyphName>,
+    },
+    /// The anchor exists at other locations but not this one.
+    ///
+    /// Not fatal, the position is interpolated from the locations that do
+    /// define it, but frequently unintended.
+    MissingAnchor(AnchorKind),
+}
+
+impl IncompatibilityKind {
+    /// Whether this prevents us from building the font
+    pub fn is_fatal(&self) -> bool {
+        !matches!(self, IncompatibilityKind::MissingAnchor(..))
+    }
+}
+
+impl Display for Incompatibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' at {:?}: ", self.glyph_name, self.location)?;
+        match &self.kind {
+            IncompatibilityKind::ContourCount { expected, actual } => {
+                write!(f, "{actual} contours, default has {expected}")
+            }
+            IncompatibilityKind::SegmentCount {
+                contour,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "contour {contour} has {actual} segments, default has {expected}"
+            ),
+            IncompatibilityKind::SegmentType { contour, segment } => write!(
+                f,
+                "contour {contour} segment {segment} is not the same kind of segment (line, quadratic or cubic) as in the default"
+            ),
+            IncompatibilityKind::Components { expected, actual } => {
+                write!(f, "components {actual:?}, default has {expected:?}")
+            }
+            IncompatibilityKind::MissingAnchor(kind) => write!(f, "no {kind:?} anchor"),
+        }
+    }
+}
+
+/// Every fatal incompatibility in a font, reported as a single error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incompatibilities(pub Vec<Incompatibility>);
+
+impl Display for Incompatibilities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} incompatibilities between sources", self.0.len())?;
+        for incompatibility in self.0.iter() {
+            write!(f, "\n  {incompatibility}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Interpolating a quadratic with a cubic curve, or a curve with a line, would need
+/// the segment converted first, so only the same kind of element is compatible
+fn same_kind(e: PathEl, a: PathEl) -> bool {
+    std::mem::discriminant(&e) == std::mem::discriminant(&a)
+}
+
+fn check_contour(
+    contour: usize,
+    expected: &BezPath,
+    actual: &BezPath,
+) -> Option<IncompatibilityKind> {
+    if expected.elements().len() != actual.elements().len() {
+        return Some(IncompatibilityKind::SegmentCount {
+            contour,
+            expected: expected.elements().len(),
+            actual: actual.elements().len(),
+        });
+    }
+    expected
+        .iter()
+        .zip(actual.iter())
+        .position(|(e, a)| !same_kind(e, a))
+        .map(|segment| IncompatibilityKind::SegmentType { contour, segment })
+}
+
+/// Compare an instance to the default instance of the same glyph
+fn check_instance(default: &GlyphInstance, instance: &GlyphInstance) -> Vec<IncompatibilityKind> {
+    let mut problems = Vec::new();
+    if default.contours.len() != instance.contours.len() {
+        problems.push(IncompatibilityKind::ContourCount {
+            expected: default.contours.len(),
+            actual: instance.contours.len(),
+        });
+    } else {
+        problems.extend(
+            default
+                .contours
+                .iter()
+                .zip(instance.contours.iter())
+                .enumerate()
+                .filter_map(|(i, (expected, actual))| check_contour(i, expected, actual)),
+        );
+    }
+    let component_names = |instance: &GlyphInstance| {
+        instance
+            .components
+            .iter()
+            .map(|c| c.base.clone())
+            .collect::<Vec<_>>()
+    };
+    let (expected, actual) = (component_names(default), component_names(instance));
+    if expected != actual {
+        problems.push(IncompatibilityKind::Components { expected, actual });
+    }
+    problems
+}
+
+/// Find every way in which the sources of glyph are incompatible.
+pub fn check_glyph(glyph: &Glyph, anchors: Option<&GlyphAnchors>) -> Vec<Incompatibility> {
+    let default = glyph.default_instance();
+    let mut locations: Vec<_> = glyph.sources().keys().collect();
+    locations.sort();
+
+    let mut incompatibilities = Vec::new();
+    for location in locations {
+        let instance = glyph.sources().get(location).unwrap();
+        let anchor_problems = anchors
+            .into_iter()
+            .flat_map(|anchors| anchors.anchors.iter())
+            .filter(|anchor| !anchor.positions.contains_key(location))
+            .map(|anchor| IncompatibilityKind::MissingAnchor(anchor.kind.clone()));
+        incompatibilities.extend(
+            check_instance(default, instance)
+                .into_iter()
+                .chain(anchor_problems)
+                .map(|kind| Incompatibility {
+                    glyph_name: glyph.name.clone(),
+                    location: location.clone(),
+                    kind,
+                }),
+        );
+    }
+    incompatibilities
+}
+
+/// Decompose glyphs where some sources use components and others don't.
+///
+/// Typically one master draws a component while another draws the same shape
+/// as outlines. Decomposing every source, as fontmake does, usually leaves
+/// outlines that interpolate.
+fn repair_mixed_components(context: &Context) -> Result<(), Error> {
+    let mut glyphs: Vec<_> = context
+        .glyphs
+        .all()
+        .into_iter()
+        .map(|(_, glyph)| glyph)
+        .filter(|glyph| distinct_component_glyph_seqs(glyph).len() > 1)
+        .collect();
+    glyphs.sort_by(|a, b| a.name.cmp(&b.name));
+    for glyph in glyphs {
+        info!(
+            "Decomposing '{}', its sources don't all use the same components",
+            glyph.name
+        );
+        convert_components_to_contours(context, &glyph)?;
+    }
+    Ok(())
+}
+
+/// Reorder contours and rotate start points so every source matches the default.
+///
+/// BE glyph work waits for the compatibility check, so only sees matched glyphs.
+fn match_all_contours(context: &Context) {
+    let mut glyphs: Vec<_> = context
+        .glyphs
+        .all()
+        .into_iter()
+        .filter_map(|(_, glyph)| match_contours(&glyph))
+        .collect();
+    glyphs.sort_by(|a, b| a.name.cmp(&b.name));
+    for glyph in glyphs {
+        debug!("Matched contours of '{}' to the default master", glyph.name);
+        context.glyphs.set(glyph);
+    }
+}
+
+/// A copy of glyph with only its default source, so it doesn't vary
+fn default_only(glyph: &Glyph, default_location: &NormalizedLocation) -> Result<Glyph, Error> {
+    let mut builder = GlyphBuilder::from(glyph.clone());
+    builder
+        .sources
+        .retain(|location, _| location == default_location);
+    Ok(builder.build()?)
+}
+
+impl Work<Context, WorkId, Error> for CompatibilityCheckWork {
+    fn id(&self) -> WorkId {
+        WorkId::CompatibilityCheck
+    }
+
+    fn read_access(&self) -> Access<WorkId> {
+        AccessBuilder::new()
+            .variant(WorkId::StaticMetadata)
+            .variant(WorkId::ALL_GLYPHS)
+            .variant(WorkId::ALL_ANCHORS)
+            .build()
+    }
+
+    fn write_access(&self) -> Access<WorkId> {
+        // Repair and contour matching may replace glyphs
+        Access::Variant(WorkId::ALL_GLYPHS)
+    }
+
+    fn exec(&self, context: &Context) -> Result<(), Error> {
+        if context.flags.contains(Flags::REPAIR_COMPATIBILITY) {
+            repair_mixed_components(context)?;
+        }
+        if context.flags.contains(Flags::MATCH_CONTOURS) {
+            match_all_contours(context);
+        }
+
+        let mut glyphs: Vec<_> = context
+            .glyphs
+            .all()
+            .into_iter()
+            .map(|(_, glyph)| glyph)
+            // Glyphs that aren't exported may well be works in progress
+            .filter(|glyph| glyph.emit_to_binary)
+            .collect();
+        glyphs.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let static_metadata = context.static_metadata.get();
+        let enforce = static_metadata.misc.enforce_compatibility;
+        let mut fatal = Vec::new();
+        for glyph in glyphs {
+            if context.cancellation.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            let anchors = context.anchors.try_get(&WorkId::Anchor(glyph.name.clone()));
+            let mut incompatible = false;
+            for incompatibility in check_glyph(&glyph, anchors.as_deref()) {
+                if incompatibility.kind.is_fatal() && enforce {
+                    fatal.push(incompatibility);
+                } else {
+                    incompatible |= incompatibility.kind.is_fatal();
+                    warn!(code = codes::INTERPOLATION_MISMATCH; "{incompatibility}");
+                }
+            }
+            if incompatible {
+                warn!(
+                    code = codes::INTERPOLATION_MISMATCH;
+                    "'{}' doesn't interpolate, dropping its variation data",
+                    glyph.name
+                );
+                context
+                    .glyphs
+                    .set(default_only(&glyph, static_metadata.default_location())?);
+            }
+        }
+        if !fatal.is_empty() {
+            return Err(Error::IncompatibleSources(Incompatibilities(fatal)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use fontdrasil::types::Axis;
+    use kurbo::{Rect, Shape};
+
+    use crate::{
+        ir::{Component, StaticMetadata},
+        paths::Paths,
+    };
+
+    use super::*;
+
+    fn loc(wght: f64) -> NormalizedLocation {
+        NormalizedLocation::for_pos(&[("wght", wght)])
+    }
+
+    fn glyph(instances: Vec<(f64, GlyphInstance)>) -> Glyph {
+        named_glyph("test", instances)
+    }
+
+    fn named_glyph(name: &str, instances: Vec<(f64, GlyphInstance)>) -> Glyph {
+        let mut builder = GlyphBuilder::new(name.into());
+        for (pos, instance) in instances {
+            builder.try_add_source(&loc(pos), instance).unwrap();
+        }
+        builder.build().unwrap()
+    }
+
+    fn squares(n: usize) -> GlyphInstance {
+        GlyphInstance {
+            contours: (0..n)
+                .map(|_| Rect::new(0.0, 0.0, 10.0, 10.0).to_path(0.1))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn compatible_glyph() {
+        assert_eq!(
+            Vec::<Incompatibility>::new(),
+            check_glyph(&glyph(vec![(0.0, squares(2)), (1.0, squares(2))]), None)
+        );
+    }
+
+    #[test]
+    fn report_every_location() {
+        let glyph = glyph(vec![
+            (-1.0, squares(1)),
+            (0.0, squares(2)),
+            (1.0, squares(3)),
+        ]);
+        assert_eq!(
+            vec![
+                Incompatibility {
+                    glyph_name: "test".into(),
+                    location: loc(-1.0),
+                    kind: IncompatibilityKind::ContourCount {
+                        expected: 2,
+                        actual: 1
+                    },
+                },
+                Incompatibility {
+                    glyph_name: "test".into(),
+                    location: loc(1.0),
+                    kind: IncompatibilityKind::ContourCount {
+                        expected: 2,
+                        actual: 3
+                    },
+                },
+            ],
+            check_glyph(&glyph, None)
+        );
+    }
+
+    #[test]
+    fn line_versus_curve() {
+        let mut curved = BezPath::new();
+        curved.move_to((0.0, 0.0));
+        curved.quad_to((5.0, 10.0), (10.0, 0.0));
+        curved.close_path();
+        let mut straight = BezPath::new();
+        straight.move_to((0.0, 0.0));
+        straight.line_to((10.0, 0.0));
+        straight.close_path();
+        let instance = |contour: BezPath| GlyphInstance {
+            contours: vec![contour],
+            ..Default::default()
+        };
+
+        let glyph = glyph(vec![(0.0, instance(curved)), (1.0, instance(straight))]);
+        assert_eq!(
+            vec![IncompatibilityKind::SegmentType {
+                contour: 0,
+                segment: 1
+            }],
+            check_glyph(&glyph, None)
+                .into_iter()
+                .map(|i| i.kind)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn quad_versus_cubic() {
+        let mut quadratic = BezPath::new();
+        quadratic.move_to((0.0, 0.0));
+        quadratic.quad_to((5.0, 10.0), (10.0, 0.0));
+        quadratic.close_path();
+        let mut cubic = BezPath::new();
+        cubic.move_to((0.0, 0.0));
+        cubic.curve_to((3.0, 10.0), (7.0, 10.0), (10.0, 0.0));
+        cubic.close_path();
+        let instance = |contour: BezPath| GlyphInstance {
+            contours: vec![contour],
+            ..Default::default()
+        };
+
+        let glyph = glyph(vec![(0.0, instance(quadratic)), (1.0, instance(cubic))]);
+        assert_eq!(
+            vec![IncompatibilityKind::SegmentType {
+                contour: 0,
+                segment: 1
+            }],
+            check_glyph(&glyph, None)
+                .into_iter()
+                .map(|i| i.kind)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn component_order() {
+        let instance = |bases: [&str; 2]| GlyphInstance {
+            components: bases
+                .into_iter()
+                .map(|base| Component {
+                    base: base.into(),
+                    transform: Default::default(),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let glyph = glyph(vec![
+            (0.0, instance(["a", "b"])),
+            (1.0, instance(["b", "a"])),
+        ]);
+        let problems = check_glyph(&glyph, None);
+        assert_eq!(1, problems.len(), "{problems:?}");
+        assert!(matches!(
+            problems[0].kind,
+            IncompatibilityKind::Components { .. }
+        ));
+    }
+
+    fn context_with_mixed_glyph(flags: Flags, enforce_compatibility: bool) -> Context {
+        let context = Context::new_root(flags, Paths::new(Path::new("/fake/path")))
+            .copy_for_work(Access::All, Access::All);
+        let mut static_metadata = StaticMetadata::new(
+            1000,
+            Default::default(),
+            vec![Axis::for_test("wght")],
+            Default::default(),
+            [loc(0.0), loc(1.0)].into(),
+            Default::default(),
+            0.0,
+            Default::default(),
+            None,
+            false,
+        )
+        .unwrap();
+        static_metadata.misc.enforce_compatibility = enforce_compatibility;
+        context.static_metadata.set(static_metadata);
+        context
+            .glyphs
+            .set(named_glyph("b", vec![(0.0, squares(1)), (1.0, squares(1))]));
+        let component = GlyphInstance {
+            components: vec![Component {
+                base: "b".into(),
+                transform: Default::default(),
+            }],
+            ..Default::default()
+        };
+        // A component in one master, outlines in the other
+        context
+            .glyphs
+            .set(named_glyph("a", vec![(0.0, component), (1.0, squares(1))]));
+        context
+    }
+
+    #[test]
+    fn repair_mixed_components_and_outlines() {
+        let context = context_with_mixed_glyph(Flags::default() - Flags::EMIT_IR, true);
+        CompatibilityCheckWork {}.exec(&context).unwrap();
+
+        let repaired = context.get_glyph("a");
+        assert!(repaired
+            .sources()
+            .values()
+            .all(|instance| instance.components.is_empty() && instance.contours.len() == 1));
+    }
+
+    #[test]
+    fn report_mixed_components_and_outlines_without_repair() {
+        let context = context_with_mixed_glyph(
+            Flags::default() - Flags::EMIT_IR - Flags::REPAIR_COMPATIBILITY,
+            true,
+        );
+        let Err(Error::IncompatibleSources(Incompatibilities(problems))) =
+            CompatibilityCheckWork {}.exec(&context)
+        else {
+            panic!("Should have failed");
+        };
+        assert_eq!(
+            vec![GlyphName::from("a"), GlyphName::from("a")],
+            problems
+                .into_iter()
+                .map(|p| p.glyph_name)
+                .collect::<Vec<_>>(),
+            "expected contour count and components to differ"
+        );
+    }
+
+    #[test]
+    fn drop_variations_of_mixed_components_and_outlines_unless_enforced() {
+        let context = context_with_mixed_glyph(
+            Flags::default() - Flags::EMIT_IR - Flags::REPAIR_COMPATIBILITY,
+            false,
+        );
+        CompatibilityCheckWork {}.exec(&context).unwrap();
+
+        assert_eq!(
+            vec![&loc(0.0)],
+            context.get_glyph("a").sources().keys().collect::<Vec<_>>()
+        );
+        // compatible glyphs keep their variations
+        assert_eq!(2, context.get_glyph("b").sources().len());
+    }
+}
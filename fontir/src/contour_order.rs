@@ -0,0 +1,246 @@
+//! Match contours across sources by geometry.
+//!
+//! Masters drawn independently may list the same contours in a different order,
+//! or start the same contour at a different point. Such sources are structurally
+//! compatible but interpolate into a mess. We reorder contours and rotate start
+//! points to be as close as possible to the default master, much like the
+//! "match by geometry" option of common font editors.
+//!
+//! Only closed contours are rotated; the point an open contour starts at is meaningful.
+
+use kurbo::{BezPath, ParamCurve, PathEl, PathSeg, Point};
+
+use crate::ir::{Glyph, GlyphBuilder, GlyphInstance};
+
+/// A copy of glyph with contours matched to the default, or None if nothing needed to change.
+pub fn match_contours(glyph: &Glyph) -> Option<Glyph> {
+    let default = glyph.default_instance();
+    let mut builder = GlyphBuilder::from(glyph.clone());
+    let mut changed = false;
+    for instance in builder.sources.values_mut() {
+        changed |= match_instance(default, instance);
+    }
+    // The locations are unchanged so building cannot fail
+    changed.then(|| builder.build().unwrap())
+}
+
+/// Update instance to match reference, returning whether anything changed.
+fn match_instance(reference: &GlyphInstance, instance: &mut GlyphInstance) -> bool {
+    if reference.contours.len() != instance.contours.len() {
+        return false;
+    }
+    let mut changed = false;
+    if let Some(order) = contour_order(&reference.contours, &instance.contours) {
+        let mut contours = std::mem::take(&mut instance.contours);
+        instance.contours = order
+            .into_iter()
+            .map(|i| std::mem::take(&mut contours[i]))
+            .collect();
+        changed = true;
+    }
+    for (reference, contour) in reference.contours.iter().zip(instance.contours.iter_mut()) {
+        if let Some(rotated) = rotate_start(reference, contour) {
+            *contour = rotated;
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// The segments of a closed contour, or None if it's open.
+///
+/// The closing line, if any, is made explicit.
+fn closed_segments(contour: &BezPath) -> Option<Vec<PathSeg>> {
+    if !matches!(contour.elements().last(), Some(PathEl::ClosePath)) {
+        return None;
+    }
+    Some(contour.segments().collect())
+}
+
+fn is_line(seg: &PathSeg) -> bool {
+    matches!(seg, PathSeg::Line(..))
+}
+
+/// Two contours that could be made to interpolate by rotating one of them
+fn similar(a: &[PathSeg], b: &[PathSeg]) -> bool {
+    a.len() == b.len()
+        && a.iter().filter(|s| is_line(s)).count() == b.iter().filter(|s| is_line(s)).count()
+}
+
+fn centroid(segments: &[PathSeg]) -> Point {
+    let n = segments.len().max(1) as f64;
+    let sum = segments
+        .iter()
+        .fold(Point::ZERO, |acc, seg| acc + seg.start().to_vec2());
+    Point::new(sum.x / n, sum.y / n)
+}
+
+/// The order of contours that best matches the reference, if not the current order.
+///
+/// Contours are matched greedily by the distance between their centroids.
+fn contour_order(reference: &[BezPath], contours: &[BezPath]) -> Option<Vec<usize>> {
+    let reference: Vec<_> = reference.iter().map(closed_segments).collect();
+    let contours: Vec<_> = contours.iter().map(closed_segments).collect();
+    // Only reorder if every contour is closed, open contours have an identity of their own
+    let reference: Vec<_> = reference.into_iter().collect::<Option<_>>()?;
+    let contours: Vec<_> = contours.into_iter().collect::<Option<_>>()?;
+
+    let cost =
+        |r: &[PathSeg], c: &[PathSeg]| similar(r, c).then(|| (centroid(r) - centroid(c)).hypot2());
+    let identity_cost: Option<f64> = reference
+        .iter()
+        .zip(contours.iter())
+        .map(|(r, c)| cost(r, c))
+        .sum();
+
+    let mut order = Vec::with_capacity(contours.len());
+    let mut total = 0.0;
+    for r in reference.iter() {
+        let (best, best_cost) = contours
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !order.contains(i))
+            .filter_map(|(i, c)| cost(r, c).map(|cost| (i, cost)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+        order.push(best);
+        total += best_cost;
+    }
+    let is_identity = order.iter().enumerate().all(|(i, j)| i == *j);
+    let better = identity_cost
+        .map(|identity| total < identity)
+        .unwrap_or(true);
+    (!is_identity && better).then_some(order)
+}
+
+fn rotated(segments: &[PathSeg], start: usize) -> impl Iterator<Item = &PathSeg> {
+    segments[start..].iter().chain(segments[..start].iter())
+}
+
+/// The contour, rotated to start at the point that best matches reference, if not the current start.
+fn rotate_start(reference: &BezPath, contour: &BezPath) -> Option<BezPath> {
+    let reference = closed_segments(reference)?;
+    let segments = closed_segments(contour)?;
+    if reference.len() != segments.len() || segments.len() < 2 {
+        return None;
+    }
+    let cost = |start: usize| {
+        let same_types = rotated(&segments, start)
+            .zip(reference.iter())
+            .all(|(s, r)| is_line(s) == is_line(r));
+        same_types.then(|| {
+            rotated(&segments, start)
+                .zip(reference.iter())
+                .map(|(s, r)| (s.start() - r.start()).hypot2())
+                .sum::<f64>()
+        })
+    };
+    let (best, best_cost) = (0..segments.len())
+        .filter_map(|start| cost(start).map(|c| (start, c)))
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+    if best == 0 || cost(0).is_some_and(|identity| identity <= best_cost) {
+        return None;
+    }
+    Some(to_contour(rotated(&segments, best)))
+}
+
+/// A closed contour from segments, the closing line is left implicit.
+fn to_contour<'a>(segments: impl Iterator<Item = &'a PathSeg>) -> BezPath {
+    let segments: Vec<_> = segments.collect();
+    let start = segments[0].start();
+    let mut path = BezPath::new();
+    path.move_to(start);
+    for (i, seg) in segments.iter().enumerate() {
+        match seg {
+            PathSeg::Line(line) if i == segments.len() - 1 && line.p1 == start => (),
+            PathSeg::Line(line) => path.line_to(line.p1),
+            PathSeg::Quad(quad) => path.quad_to(quad.p1, quad.p2),
+            PathSeg::Cubic(cubic) => path.curve_to(cubic.p1, cubic.p2, cubic.p3),
+        }
+    }
+    path.close_path();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use fontdrasil::coords::NormalizedLocation;
+
+    use super::*;
+
+    fn square(x: f64, y: f64, size: f64) -> BezPath {
+        let mut path = BezPath::new();
+        path.move_to((x, y));
+        path.line_to((x + size, y));
+        path.line_to((x + size, y + size));
+        path.line_to((x, y + size));
+        path.close_path();
+        path
+    }
+
+    fn glyph(default: Vec<BezPath>, other: Vec<BezPath>) -> Glyph {
+        let mut builder = GlyphBuilder::new("test".into());
+        for (pos, contours) in [(0.0, default), (1.0, other)] {
+            builder
+                .try_add_source(
+                    &NormalizedLocation::for_pos(&[("wght", pos)]),
+                    GlyphInstance {
+                        contours,
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+        }
+        builder.build().unwrap()
+    }
+
+    fn contours_at_one(glyph: &Glyph) -> Vec<String> {
+        glyph
+            .sources()
+            .get(&NormalizedLocation::for_pos(&[("wght", 1.0)]))
+            .unwrap()
+            .contours
+            .iter()
+            .map(|c| c.to_svg())
+            .collect()
+    }
+
+    #[test]
+    fn matched_contours_are_unchanged() {
+        let glyph = glyph(
+            vec![square(0.0, 0.0, 10.0), square(100.0, 0.0, 10.0)],
+            vec![square(0.0, 0.0, 20.0), square(100.0, 0.0, 20.0)],
+        );
+        assert!(match_contours(&glyph).is_none());
+    }
+
+    #[test]
+    fn reorder_contours() {
+        let glyph = glyph(
+            vec![square(0.0, 0.0, 10.0), square(100.0, 0.0, 10.0)],
+            vec![square(100.0, 0.0, 20.0), square(0.0, 0.0, 20.0)],
+        );
+        let matched = match_contours(&glyph).unwrap();
+        assert_eq!(
+            vec![
+                square(0.0, 0.0, 20.0).to_svg(),
+                square(100.0, 0.0, 20.0).to_svg()
+            ],
+            contours_at_one(&matched)
+        );
+    }
+
+    #[test]
+    fn rotate_start_point() {
+        // Same square, starting at the top right rather than the bottom left
+        let mut rotated = BezPath::new();
+        rotated.move_to((12.0, 12.0));
+        rotated.line_to((0.0, 12.0));
+        rotated.line_to((0.0, 0.0));
+        rotated.line_to((12.0, 0.0));
+        rotated.close_path();
+        let glyph = glyph(vec![square(0.0, 0.0, 10.0)], vec![rotated]);
+
+        let matched = match_contours(&glyph).unwrap();
+        assert_eq!(vec!["M0,0 L12,0 L12,12 L0,12 Z"], contours_at_one(&matched));
+    }
+}
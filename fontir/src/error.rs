@@ -1,12 +1,19 @@
-use std::{fmt::Display, io, path::PathBuf};
+use std::{
+    fmt::Display,
+    io,
+    path::{Path, PathBuf},
+};
 
 use fontdrasil::{
     coords::{DesignCoord, NormalizedCoord, NormalizedLocation, UserCoord, UserLocation},
+    error::ErrorCategory,
     types::GlyphName,
 };
 use kurbo::Point;
 use smol_str::SmolStr;
 use thiserror::Error;
+
+use crate::compatibility::Incompatibilities;
 use write_fonts::types::{InvalidTag, Tag};
 
 #[derive(Debug, Error)]
@@ -48,8 +55,8 @@ pub enum Error {
     NoEntryInAxes(String),
     #[error("Axis definitions are inconsistent: '{0}'")]
     InconsistentAxisDefinitions(String),
-    #[error("Missing layer '{0}'")]
-    NoSuchLayer(String),
+    #[error("No layer '{layer}' in {ufo}")]
+    NoSuchLayer { ufo: String, layer: String },
     #[error("No files associated with glyph {0}")]
     NoStateForGlyph(GlyphName),
     #[error("No design space location(s) associated with glyph {0}")]
@@ -86,6 +93,76 @@ pub enum Error {
     UnknownEntry(&'static str, String),
     #[error("Invalid {0}: {1}")]
     InvalidEntry(&'static str, String),
+    #[error("{0}")]
+    IncompatibleSources(Incompatibilities),
+    #[error("Glyphs in more than one kerning group on the same side: {}", .0.join(", "))]
+    OverlappingKerningGroups(Vec<String>),
+    #[error(
+        "Component 2x2 transforms vary across the designspace, which gvar can't express, in {0:?}"
+    )]
+    VariableComponentTransforms(Vec<GlyphName>),
+    #[error("Components differ across the designspace, which gvar can't express, in {0:?}")]
+    InconsistentComponents(Vec<GlyphName>),
+    #[error("Cancelled")]
+    Cancelled,
+}
+
+impl Error {
+    /// The broad kind of error, for callers that want to react without parsing messages
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Error::BadSource(e) => match &e.kind {
+                BadSourceKind::ExpectedDirectory
+                | BadSourceKind::ExpectedFile
+                | BadSourceKind::ExpectedParent
+                | BadSourceKind::Io(_) => ErrorCategory::Io,
+                BadSourceKind::UnrecognizedExtension | BadSourceKind::Custom(_) => {
+                    ErrorCategory::Parse
+                }
+            },
+            Error::BadGlyph(e) => e.kind.category(),
+            Error::NoSuchPath(_) | Error::DeleteFailed { .. } => ErrorCategory::Io,
+            Error::VariationModelError(_)
+            | Error::IncompatibleSources(_)
+            | Error::VariableComponentTransforms(_)
+            | Error::InconsistentComponents(_) => ErrorCategory::Interpolation,
+            Error::InvalidUpem(_)
+            | Error::InconsistentUpem(_)
+            | Error::NonIdenticalFea(..)
+            | Error::MissingAxisMapping(_)
+            | Error::NoGlyphForName(_)
+            | Error::NoAxisDefinitions(_)
+            | Error::NoEntryInAxes(_)
+            | Error::InconsistentAxisDefinitions(_)
+            | Error::NoSuchLayer { .. }
+            | Error::DuplicateUserLocation { .. }
+            | Error::NoDefaultMaster(_)
+            | Error::MissingMappingForDesignCoord { .. }
+            | Error::InvalidTag { .. }
+            | Error::UnsupportedConstruct(_)
+            | Error::InconsistentPaletteLength { .. }
+            | Error::UnknownEntry(..)
+            | Error::InvalidEntry(..)
+            | Error::OverlappingKerningGroups(_) => ErrorCategory::Validation,
+            Error::NoStateForGlyph(_)
+            | Error::NoLocationsForGlyph(_)
+            | Error::UnableToCreateGlyphIrWork
+            | Error::UnexpectedState
+            | Error::InvalidGlobalMetadata => ErrorCategory::Internal,
+            Error::Cancelled => ErrorCategory::Cancelled,
+        }
+    }
+
+    /// The glyph the error is about, if any
+    pub fn glyph(&self) -> Option<&GlyphName> {
+        match self {
+            Error::BadGlyph(e) => Some(&e.name),
+            Error::NoGlyphForName(name)
+            | Error::NoStateForGlyph(name)
+            | Error::NoLocationsForGlyph(name) => Some(name),
+            _ => None,
+        }
+    }
 }
 
 /// An error related to loading source input files
@@ -131,6 +208,7 @@ pub enum BadGlyphKind {
     MissingMaster(String),
     MultipleDefaultLocations,
     UndefinedAtNormalizedLocation(NormalizedLocation),
+    NotInterpolatable(NormalizedLocation),
     UndefinedAtNormalizedPosition { axis: Tag, pos: NormalizedCoord },
     NoAxisPosition(Tag),
     PathConversion(PathConversionError),
@@ -212,6 +290,11 @@ impl BadSource {
     pub fn custom(path: impl Into<PathBuf>, msg: impl Display) -> Self {
         Self::new(path, BadSourceKind::Custom(msg.to_string()))
     }
+
+    /// The path to the file where the error occured
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
 }
 
 impl BadGlyph {
@@ -222,6 +305,14 @@ impl BadGlyph {
             kind: kind.into(),
         }
     }
+
+    pub fn name(&self) -> &GlyphName {
+        &self.name
+    }
+
+    pub fn kind(&self) -> &BadGlyphKind {
+        &self.kind
+    }
 }
 
 impl BadAnchor {
@@ -239,6 +330,25 @@ impl From<std::io::Error> for BadSourceKind {
     }
 }
 
+impl BadGlyphKind {
+    fn category(&self) -> ErrorCategory {
+        match self {
+            BadGlyphKind::NoInstances
+            | BadGlyphKind::DuplicateLocation(_)
+            | BadGlyphKind::NoDefaultLocation
+            | BadGlyphKind::MultipleDefaultLocations
+            | BadGlyphKind::UndefinedAtNormalizedLocation(_)
+            | BadGlyphKind::NotInterpolatable(_)
+            | BadGlyphKind::UndefinedAtNormalizedPosition { .. } => ErrorCategory::Interpolation,
+            BadGlyphKind::MissingLayer(_)
+            | BadGlyphKind::MissingMaster(_)
+            | BadGlyphKind::NoAxisPosition(_)
+            | BadGlyphKind::PathConversion(_)
+            | BadGlyphKind::Anchor(_) => ErrorCategory::Validation,
+        }
+    }
+}
+
 impl From<PathConversionError> for BadGlyphKind {
     fn from(src: PathConversionError) -> BadGlyphKind {
         BadGlyphKind::PathConversion(src)
@@ -274,6 +384,9 @@ impl std::fmt::Display for BadGlyphKind {
             BadGlyphKind::UndefinedAtNormalizedLocation(loc) => {
                 write!(f, "undefined at required location {loc:?}")
             }
+            BadGlyphKind::NotInterpolatable(loc) => {
+                write!(f, "incompatible sources, cannot interpolate at {loc:?}")
+            }
             BadGlyphKind::UndefinedAtNormalizedPosition { axis, pos } => {
                 write!(f, "undefined on {axis} at required position {pos:?}")
             }
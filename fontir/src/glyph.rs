@@ -4,7 +4,9 @@
 //! the contours and one updated glyph with no contours that references the new gyph as a component.
 
 use std::{
+    borrow::Cow,
     collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    ops::{Add, Mul, Sub},
     sync::Arc,
 };
 
@@ -13,24 +15,37 @@ use fontdrasil::{
     orchestration::{Access, AccessBuilder, Work},
     types::GlyphName,
 };
-use kurbo::Affine;
-use log::{debug, log_enabled, trace};
+use kurbo::{Affine, BezPath, PathEl, Point};
+use log::{debug, log_enabled, trace, warn};
 use ordered_float::OrderedFloat;
 use smol_str::SmolStr;
 use write_fonts::types::GlyphId16;
 
 use crate::{
     error::{BadGlyph, BadGlyphKind, Error},
-    ir::{Component, Glyph, GlyphBuilder, GlyphOrder},
+    ir::{Component, Glyph, GlyphBuilder, GlyphInstance, GlyphOrder},
     orchestration::{Context, Flags, IrWork, WorkId},
+    variations::{RoundTiesEven, VariationModel},
 };
 
 pub fn create_glyph_order_work() -> Box<IrWork> {
-    Box::new(GlyphOrderWork {})
+    create_glyph_order_work_with_max_component_depth(1)
+}
+
+/// As [create_glyph_order_work] but if [Flags::FLATTEN_COMPONENTS] is set composites are
+/// only flattened as far as needed for none to nest deeper than `max_component_depth`.
+///
+/// A composite of simple glyphs has depth 1, so 1 flattens entirely.
+pub fn create_glyph_order_work_with_max_component_depth(max_component_depth: usize) -> Box<IrWork> {
+    Box::new(GlyphOrderWork {
+        max_component_depth: max_component_depth.max(1),
+    })
 }
 
 #[derive(Debug)]
-struct GlyphOrderWork {}
+struct GlyphOrderWork {
+    max_component_depth: usize,
+}
 
 /// Glyph should split if it has components *and* contours.
 ///
@@ -45,6 +60,15 @@ fn has_components_and_contours(glyph: &Glyph) -> bool {
         .any(|inst| !inst.components.is_empty() && !inst.contours.is_empty())
 }
 
+/// Does every source use the same components, in the same order, whatever their transforms?
+fn has_same_component_bases(glyph: &Glyph) -> bool {
+    let default = glyph.default_instance();
+    glyph.sources().values().all(|inst| {
+        let bases = inst.components.iter().map(|c| &c.base);
+        bases.eq(default.components.iter().map(|c| &c.base))
+    })
+}
+
 fn name_for_derivative(base_name: &GlyphName, names_in_use: &GlyphOrder) -> GlyphName {
     let mut i = 0;
     let base_name = base_name.as_str();
@@ -93,6 +117,15 @@ struct HashableComponent {
 }
 
 impl HashableComponent {
+    fn new(component: &Component, transform: Affine, index: usize) -> Self {
+        let coeffs = (transform * component.transform).as_coeffs();
+        HashableComponent {
+            base: component.base.clone(),
+            transform: coeffs.map(OrderedFloat),
+            index,
+        }
+    }
+
     fn affine(&self) -> Affine {
         Affine::new([
             self.transform[0].0,
@@ -110,7 +143,7 @@ impl HashableComponent {
 /// Only the glyph name is considered for uniqueness.
 ///
 /// Primary use is expected to be checking if there is >1 or not.
-fn distinct_component_glyph_seqs(glyph: &Glyph) -> HashSet<Vec<GlyphName>> {
+pub(crate) fn distinct_component_glyph_seqs(glyph: &Glyph) -> HashSet<Vec<GlyphName>> {
     glyph
         .sources()
         .values()
@@ -125,48 +158,18 @@ fn distinct_component_glyph_seqs(glyph: &Glyph) -> HashSet<Vec<GlyphName>> {
 
 /// Returns components transformed by the input transform
 ///
-/// Panics if the sequence of glyphs used as components (ignoring transform) is
-/// different at any point in design space.
+/// The sequence of glyphs used as components may differ across design space,
+/// each component is paired with the location of the source that uses it.
 fn components(
     glyph: &Glyph,
     transform: Affine,
 ) -> VecDeque<(NormalizedLocation, HashableComponent)> {
-    if glyph.sources().is_empty() {
-        return Default::default();
-    }
-
-    // Kerplode if the set of unique components is inconsistent across sources
-    let component_glyph_seqs = distinct_component_glyph_seqs(glyph);
-    if component_glyph_seqs.len() != 1 {
-        panic!(
-            "'{}' has {} unique sets of components; must have exactly 1\n{:?}",
-            glyph.name,
-            component_glyph_seqs.len(),
-            component_glyph_seqs
-        );
-    }
-
     glyph
         .sources()
         .iter()
         .flat_map(|(loc, inst)| inst.components.iter().map(|c| (loc.clone(), c)))
         .enumerate()
-        .map(|(index, (loc, component))| {
-            let coeffs = (transform * component.transform).as_coeffs();
-            let mut transform = [OrderedFloat(0f64); 6];
-            transform
-                .iter_mut()
-                .zip(coeffs)
-                .for_each(|(t, c)| *t = OrderedFloat(c));
-            (
-                loc,
-                HashableComponent {
-                    base: component.base.clone(),
-                    transform,
-                    index,
-                },
-            )
-        })
+        .map(|(index, (loc, component))| (loc, HashableComponent::new(component, transform, index)))
         .collect()
 }
 
@@ -241,7 +244,7 @@ impl fontdrasil::util::CompositeLike for &Glyph {
 ///
 /// It seemed simpler to me to just have this be a little standalone fn with
 /// limited scope.
-fn flatten_all_non_export_components(context: &Context) {
+fn flatten_all_non_export_components(context: &Context) -> Result<(), BadGlyph> {
     let glyphs = context.glyphs.all();
     let glyphs = glyphs
         .iter()
@@ -255,14 +258,18 @@ fn flatten_all_non_export_components(context: &Context) {
     for glyph_name in depth_first {
         let glyph = glyphs.get(&glyph_name).unwrap();
         if glyph_has_non_export_components(glyph, &glyphs) {
-            let new_glyph = flatten_non_export_components_for_glyph(context, glyph);
+            let new_glyph = flatten_non_export_components_for_glyph(context, glyph)?;
             context.glyphs.set(new_glyph);
         }
     }
+    Ok(())
 }
 
 /// Return a new glyph with any non-export components inlined.
-fn flatten_non_export_components_for_glyph(context: &Context, glyph: &Glyph) -> Glyph {
+fn flatten_non_export_components_for_glyph(
+    context: &Context,
+    glyph: &Glyph,
+) -> Result<Glyph, BadGlyph> {
     let mut builder = GlyphBuilder::from(glyph.clone());
     builder.clear_components();
     for (loc, instance) in glyph.sources() {
@@ -281,9 +288,7 @@ fn flatten_non_export_components_for_glyph(context: &Context, glyph: &Glyph) ->
             // okay so now we have a component that is not going to be exported,
             // and we need to flatten.
             let xform = component.transform;
-            let Some(referenced_instance) = referenced_glyph.sources().get(loc) else {
-                continue;
-            };
+            let referenced_instance = instance_at(context, &referenced_glyph, loc)?;
 
             for mut referenced_component in referenced_instance.components.iter().cloned() {
                 referenced_component.transform = xform * referenced_component.transform;
@@ -303,7 +308,7 @@ fn flatten_non_export_components_for_glyph(context: &Context, glyph: &Glyph) ->
         builder.sources.insert(loc.clone(), new_instance);
     }
     // unwrap is okay because all used locations are from previously validated glyph
-    builder.build().unwrap()
+    Ok(builder.build().unwrap())
 }
 
 fn glyph_has_non_export_components(glyph: &Glyph, glyphs: &BTreeMap<SmolStr, &Glyph>) -> bool {
@@ -314,10 +319,13 @@ fn glyph_has_non_export_components(glyph: &Glyph, glyphs: &BTreeMap<SmolStr, &Gl
 
 /// Convert a glyph with contours and components to a contour-only, aka simple, glyph
 ///
-/// At time of writing we only support this if every instance uses the same set of components.
+/// Each instance is decomposed independently so they need not use the same set of components.
 ///
 /// <https://github.com/googlefonts/ufo2ft/blob/dd738cdcd/Lib/ufo2ft/util.py#L165>
-fn convert_components_to_contours(context: &Context, original: &Glyph) -> Result<(), BadGlyph> {
+pub(crate) fn convert_components_to_contours(
+    context: &Context,
+    original: &Glyph,
+) -> Result<(), BadGlyph> {
     let mut simple = GlyphBuilder::from(original.clone());
     simple.clear_components();
 
@@ -333,15 +341,22 @@ fn convert_components_to_contours(context: &Context, original: &Glyph) -> Result
         }
 
         let referenced_glyph = context.get_glyph(component_base.clone());
+        // A sparse component may have no source here, in which case it's interpolated
+        let ref_inst = instance_at(context, &referenced_glyph, &loc)?;
         frontier.extend(
-            components(&referenced_glyph, component_affine)
+            ref_inst
+                .components
                 .iter()
-                .filter(|(component_loc, _)| *component_loc == loc)
-                .cloned(),
+                .enumerate()
+                .map(|(index, component)| {
+                    (
+                        loc.clone(),
+                        HashableComponent::new(component, component_affine, index),
+                    )
+                }),
         );
 
         // Any contours of the referenced glyph at this location should be kept
-        // For now just fail if the component source locations don't match ours, don't try to interpolate
         trace!(
             "'{}' retains {} {component_affine:?} at {loc:?}",
             original.name,
@@ -353,12 +368,6 @@ fn convert_components_to_contours(context: &Context, original: &Glyph) -> Result
                 BadGlyphKind::UndefinedAtNormalizedLocation(loc.clone()),
             ));
         };
-        let Some(ref_inst) = referenced_glyph.sources().get(&loc) else {
-            return Err(BadGlyph::new(
-                referenced_glyph.name.clone(),
-                BadGlyphKind::UndefinedAtNormalizedLocation(loc.clone()),
-            ));
-        };
 
         for contour in ref_inst.contours.iter() {
             let mut contour = contour.clone();
@@ -384,6 +393,183 @@ fn convert_components_to_contours(context: &Context, original: &Glyph) -> Result
     Ok(())
 }
 
+/// The instance of glyph at loc, interpolated if the glyph has no source there.
+///
+/// Sparse sources, such as designspace sources that only define some glyphs,
+/// mean a component need not have a source everywhere the glyph that uses it
+/// does. In that case we build a variation model from just the locations the
+/// component does define, as fontTools does when instantiating a sparse glyph.
+pub fn instance_at<'a>(
+    context: &Context,
+    glyph: &'a Glyph,
+    loc: &NormalizedLocation,
+) -> Result<Cow<'a, GlyphInstance>, BadGlyph> {
+    if let Some(instance) = glyph.sources().get(loc) {
+        return Ok(Cow::Borrowed(instance));
+    }
+    let not_interpolatable = || {
+        BadGlyph::new(
+            glyph.name.clone(),
+            BadGlyphKind::NotInterpolatable(loc.clone()),
+        )
+    };
+
+    let template = glyph.default_instance();
+    if glyph
+        .sources()
+        .values()
+        .any(|instance| !same_structure(template, instance))
+    {
+        return Err(not_interpolatable());
+    }
+    let model = VariationModel::new(
+        glyph.sources().keys().cloned().collect(),
+        context.static_metadata.get().axes.clone(),
+    )
+    .map_err(|_| not_interpolatable())?;
+    let values = glyph
+        .sources()
+        .iter()
+        .map(|(loc, instance)| (loc.clone(), interpolatable_values(instance)))
+        .collect();
+    let deltas = model.deltas(&values).map_err(|_| not_interpolatable())?;
+    let values = VariationModel::interpolate_from_deltas(loc, &deltas);
+    Ok(Cow::Owned(from_interpolatable_values(template, values)))
+}
+
+/// A value that, unlike an f64, isn't rounded when turned into a delta.
+///
+/// Deltas destined for a font must be integers but when we interpolate
+/// an instance we want the exact result.
+#[derive(Debug, Default, Clone, Copy)]
+struct Exact(f64);
+
+impl Add for Exact {
+    type Output = Exact;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Exact(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Exact {
+    type Output = Exact;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Exact(self.0 - rhs.0)
+    }
+}
+
+impl Mul<f64> for Exact {
+    type Output = Exact;
+
+    fn mul(self, rhs: f64) -> Self::Output {
+        Exact(self.0 * rhs)
+    }
+}
+
+impl RoundTiesEven for Exact {
+    fn round_ties_even(self) -> Self {
+        self
+    }
+}
+
+/// Whether two instances have the same contour and component structure, and so can be interpolated
+fn same_structure(a: &GlyphInstance, b: &GlyphInstance) -> bool {
+    let same_el =
+        |(a, b): (PathEl, PathEl)| std::mem::discriminant(&a) == std::mem::discriminant(&b);
+    a.contours.len() == b.contours.len()
+        && a.contours.iter().zip(b.contours.iter()).all(|(a, b)| {
+            a.elements().len() == b.elements().len() && a.iter().zip(b.iter()).all(same_el)
+        })
+        && a.components.len() == b.components.len()
+        && a.components
+            .iter()
+            .zip(b.components.iter())
+            .all(|(a, b)| a.base == b.base)
+        && a.height.is_some() == b.height.is_some()
+        && a.vertical_origin.is_some() == b.vertical_origin.is_some()
+}
+
+/// Every number in an instance that varies, in a stable order.
+fn interpolatable_values(instance: &GlyphInstance) -> Vec<Exact> {
+    let mut values = vec![Exact(instance.width)];
+    values.extend(instance.height.map(Exact));
+    values.extend(instance.vertical_origin.map(Exact));
+    for contour in instance.contours.iter() {
+        for el in contour.iter() {
+            values.extend(
+                el_points(el)
+                    .iter()
+                    .flat_map(|pt| [Exact(pt.x), Exact(pt.y)]),
+            );
+        }
+    }
+    for component in instance.components.iter() {
+        values.extend(component.transform.as_coeffs().map(Exact));
+    }
+    values
+}
+
+/// The inverse of [interpolatable_values], using template for the structure of the result.
+fn from_interpolatable_values(template: &GlyphInstance, values: Vec<Exact>) -> GlyphInstance {
+    let mut values = values.into_iter().map(|v| v.0);
+    let values = &mut values;
+    let width = next_value(values);
+    let height = template.height.map(|_| next_value(values));
+    let vertical_origin = template.vertical_origin.map(|_| next_value(values));
+    let contours = template
+        .contours
+        .iter()
+        .map(|contour| {
+            contour
+                .iter()
+                .map(|el| match el {
+                    PathEl::MoveTo(_) => PathEl::MoveTo(next_point(values)),
+                    PathEl::LineTo(_) => PathEl::LineTo(next_point(values)),
+                    PathEl::QuadTo(..) => PathEl::QuadTo(next_point(values), next_point(values)),
+                    PathEl::CurveTo(..) => {
+                        PathEl::CurveTo(next_point(values), next_point(values), next_point(values))
+                    }
+                    PathEl::ClosePath => PathEl::ClosePath,
+                })
+                .collect::<BezPath>()
+        })
+        .collect();
+    let components = template
+        .components
+        .iter()
+        .map(|component| Component {
+            base: component.base.clone(),
+            transform: Affine::new([(); 6].map(|_| next_value(values))),
+        })
+        .collect();
+    GlyphInstance {
+        width,
+        height,
+        vertical_origin,
+        contours,
+        components,
+    }
+}
+
+fn next_value(values: &mut impl Iterator<Item = f64>) -> f64 {
+    values.next().unwrap_or_default()
+}
+
+fn next_point(values: &mut impl Iterator<Item = f64>) -> Point {
+    Point::new(next_value(values), next_value(values))
+}
+
+fn el_points(el: PathEl) -> Vec<Point> {
+    match el {
+        PathEl::MoveTo(p) | PathEl::LineTo(p) => vec![p],
+        PathEl::QuadTo(p0, p1) => vec![p0, p1],
+        PathEl::CurveTo(p0, p1, p2) => vec![p0, p1, p2],
+        PathEl::ClosePath => Vec::new(),
+    }
+}
+
 fn move_contours_to_new_component(
     context: &Context,
     new_glyph_order: &mut GlyphOrder,
@@ -410,8 +596,11 @@ fn move_contours_to_new_component(
 /// Assumed to run after component consistency is checked/fixed so we can assume
 /// that no mixed contour+component glyphs exist.
 ///
+/// Components referencing glyphs nested less than `max_depth` deep are kept, so 1 leaves
+/// only references to simple glyphs.
+///
 /// See <https://github.com/googlefonts/ufo2ft/blob/main/Lib/ufo2ft/filters/flattenComponents.py>
-fn flatten_glyph(context: &Context, glyph: &Glyph) -> Result<(), BadGlyph> {
+fn flatten_glyph(context: &Context, glyph: &Glyph, max_depth: usize) -> Result<(), BadGlyph> {
     // Guard: nothing to see here folks
     if glyph.default_instance().components.is_empty() {
         return Ok(());
@@ -423,7 +612,7 @@ fn flatten_glyph(context: &Context, glyph: &Glyph) -> Result<(), BadGlyph> {
     );
     let mut glyph = glyph.clone();
     for (loc, inst) in glyph.sources_mut() {
-        let mut simple = Vec::new();
+        let mut kept = Vec::new();
         let mut frontier = VecDeque::new();
         frontier.extend(inst.components.split_off(0));
         while let Some(component) = frontier.pop_front() {
@@ -434,8 +623,8 @@ fn flatten_glyph(context: &Context, glyph: &Glyph) -> Result<(), BadGlyph> {
                     BadGlyphKind::UndefinedAtNormalizedLocation(loc.clone()),
                 )
             })?;
-            if ref_inst.components.is_empty() {
-                simple.push(component.clone());
+            if component_depth(context, ref_inst, loc) < max_depth {
+                kept.push(component.clone());
             } else {
                 for ref_component in ref_inst.components.iter().rev() {
                     frontier.push_front(Component {
@@ -445,7 +634,7 @@ fn flatten_glyph(context: &Context, glyph: &Glyph) -> Result<(), BadGlyph> {
                 }
             }
         }
-        inst.components = simple;
+        inst.components = kept;
     }
     trace!(
         "Flattened {} to {:?}",
@@ -456,14 +645,34 @@ fn flatten_glyph(context: &Context, glyph: &Glyph) -> Result<(), BadGlyph> {
     Ok(())
 }
 
+/// How deeply an instance nests components: 0 if simple, 1 for a composite of
+/// simple glyphs, and so on.
+///
+/// Components undefined at loc don't count, flattening reports them.
+fn component_depth(context: &Context, inst: &GlyphInstance, loc: &NormalizedLocation) -> usize {
+    inst.components
+        .iter()
+        .map(|component| {
+            let ref_glyph = context.get_glyph(component.base.clone());
+            ref_glyph
+                .sources()
+                .get(loc)
+                .map(|ref_inst| 1 + component_depth(context, ref_inst, loc))
+                .unwrap_or(1)
+        })
+        .max()
+        .unwrap_or(0)
+}
+
 /// Run some optional transformations on the glyphs listed.
 ///
 /// This includes decomposing all components, or only those with non-identity
-/// 2x2 transforms, and flattening nested composite glyphs so that they all
-/// have depth 1 (no components that reference components).
+/// 2x2 transforms, and flattening nested composite glyphs so that none are
+/// deeper than `max_component_depth` (1 meaning no components that reference components).
 fn apply_optional_transformations(
     context: &Context,
     glyph_order: &GlyphOrder,
+    max_component_depth: usize,
 ) -> Result<(), BadGlyph> {
     // If we are decomposing all components, the rest of the flags can be ignored
     if context.flags.contains(Flags::DECOMPOSE_COMPONENTS) {
@@ -496,7 +705,7 @@ fn apply_optional_transformations(
     if context.flags.contains(Flags::FLATTEN_COMPONENTS) {
         for glyph_name in glyph_order.names() {
             let glyph = context.get_glyph(glyph_name.clone());
-            flatten_glyph(context, &glyph)?;
+            flatten_glyph(context, &glyph, max_component_depth)?;
         }
     }
 
@@ -545,6 +754,8 @@ impl Work<Context, WorkId, Error> for GlyphOrderWork {
             .variant(WorkId::PreliminaryGlyphOrder)
             .variant(WorkId::GlobalMetrics)
             .variant(WorkId::ALL_GLYPHS)
+            // Not read, but incompatible sources should be reported before we start changing glyphs
+            .variant(WorkId::CompatibilityCheck)
             .build()
     }
 
@@ -564,7 +775,7 @@ impl Work<Context, WorkId, Error> for GlyphOrderWork {
         // (https://github.com/googlefonts/ufo2ft/blob/98e8916a8/Lib/ufo2ft/preProcessor.py#L92)
         // (https://github.com/googlefonts/ufo2ft/blob/98e8916a8/Lib/ufo2ft/util.py#L112)
 
-        flatten_all_non_export_components(context);
+        flatten_all_non_export_components(context)?;
 
         // then generate the final glyph order and do final glyph processing
         let arc_current = context.preliminary_glyph_order.get();
@@ -603,12 +814,25 @@ impl Work<Context, WorkId, Error> for GlyphOrderWork {
         // 2) collapse such glyphs into a simple (contour-only) glyph
         //    fontmake (Python) prefers option 2.
         let mut todo = VecDeque::new();
+        let mut variable_components = Vec::new();
+        let mut inconsistent_components = Vec::new();
         for glyph_name in new_glyph_order.names() {
             let glyph = original_glyphs.get(glyph_name).unwrap();
             if !glyph.has_consistent_components() {
                 debug!(
                     "Coalescing '{glyph_name}' into a simple glyph because \
-                        component 2x2s vary across the designspace"
+                        its components or their 2x2s vary across the designspace"
+                );
+                if has_same_component_bases(glyph) {
+                    variable_components.push(glyph_name.clone());
+                } else {
+                    inconsistent_components.push(glyph_name.clone());
+                }
+                todo.push_back((GlyphOp::ConvertToContour, glyph.clone()));
+            } else if glyph.has_overflowing_2x2() {
+                warn!(
+                    "Coalescing '{glyph_name}' into a simple glyph because \
+                        a component 2x2 is out of range for glyf"
                 );
                 todo.push_back((GlyphOp::ConvertToContour, glyph.clone()));
             } else if has_components_and_contours(glyph) {
@@ -623,6 +847,30 @@ impl Work<Context, WorkId, Error> for GlyphOrderWork {
                 }
             }
         }
+        // gvar can't vary a 2x2, nor which components there are, so these lose their
+        // components; make sure someone notices
+        if !context.flags.contains(Flags::FLATTEN_VARIABLE_COMPONENTS) {
+            if !inconsistent_components.is_empty() {
+                return Err(Error::InconsistentComponents(inconsistent_components));
+            }
+            if !variable_components.is_empty() {
+                return Err(Error::VariableComponentTransforms(variable_components));
+            }
+        }
+        if !variable_components.is_empty() {
+            warn!(
+                "{} glyphs have components whose 2x2 transform varies across the designspace \
+                and were converted to contours: {variable_components:?}",
+                variable_components.len()
+            );
+        }
+        if !inconsistent_components.is_empty() {
+            warn!(
+                "{} glyphs have components that differ across the designspace \
+                and were converted to contours: {inconsistent_components:?}",
+                inconsistent_components.len()
+            );
+        }
         resolve_inconsistencies(
             context,
             todo,
@@ -636,7 +884,7 @@ impl Work<Context, WorkId, Error> for GlyphOrderWork {
         )?;
         drop(original_glyphs); // lets not accidentally use that from here on
 
-        apply_optional_transformations(context, &new_glyph_order)?;
+        apply_optional_transformations(context, &new_glyph_order, self.max_component_depth)?;
 
         ensure_notdef_exists_and_is_gid_0(context, &mut new_glyph_order)?;
 
@@ -665,11 +913,14 @@ impl Work<Context, WorkId, Error> for GlyphOrderWork {
 mod tests {
     use std::{collections::HashSet, path::Path};
 
-    use fontdrasil::{orchestration::Access, types::GlyphName};
+    use fontdrasil::{
+        orchestration::Access,
+        types::{Axis, GlyphName},
+    };
     use kurbo::{Affine, BezPath, Rect, Shape};
 
     use crate::{
-        ir::{Component, Glyph, GlyphBuilder, GlyphInstance, GlyphOrder},
+        ir::{Component, Glyph, GlyphBuilder, GlyphInstance, GlyphOrder, StaticMetadata},
         orchestration::{Context, Flags, WorkId},
         paths::Paths,
     };
@@ -919,6 +1170,109 @@ mod tests {
         }
     }
 
+    fn wght_context() -> Context {
+        let context = test_context();
+        let static_metadata = StaticMetadata::new(
+            1000,
+            Default::default(),
+            vec![Axis::for_test("wght")],
+            Default::default(),
+            [0.0, 1.0]
+                .map(|pos| NormalizedLocation::for_pos(&[("wght", pos)]))
+                .into(),
+            Default::default(),
+            0.0,
+            Default::default(),
+            None,
+            false,
+        )
+        .unwrap();
+        context.static_metadata.set(static_metadata);
+        context
+    }
+
+    fn glyph_at(name: &str, instances: &[(f64, GlyphInstance)]) -> Glyph {
+        let mut glyph = GlyphBuilder::new(name.into());
+        for (pos, instance) in instances {
+            glyph
+                .try_add_source(
+                    &NormalizedLocation::for_pos(&[("wght", *pos)]),
+                    instance.clone(),
+                )
+                .unwrap();
+        }
+        glyph.build().unwrap()
+    }
+
+    fn square_instance(size: f64) -> GlyphInstance {
+        GlyphInstance {
+            width: size,
+            contours: vec![Rect::new(0.0, 0.0, size, size).to_path(0.1)],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn components_to_contours_interpolates_sparse_component() {
+        let context = wght_context();
+        // The component has no source at wght 0.5, it must be interpolated
+        context.glyphs.set(glyph_at(
+            "sparse",
+            &[(0.0, square_instance(10.0)), (1.0, square_instance(20.0))],
+        ));
+        let instance = GlyphInstance {
+            components: vec![Component {
+                base: "sparse".into(),
+                transform: Affine::IDENTITY,
+            }],
+            ..Default::default()
+        };
+        let composite = glyph_at(
+            "composite",
+            &[
+                (0.0, instance.clone()),
+                (0.5, instance.clone()),
+                (1.0, instance),
+            ],
+        );
+
+        convert_components_to_contours(&context, &composite).unwrap();
+        let simple = context.get_glyph(composite.name.clone());
+        assert_simple(&simple);
+        let mid = simple
+            .sources()
+            .get(&NormalizedLocation::for_pos(&[("wght", 0.5)]))
+            .unwrap();
+        assert_eq!(
+            vec!["M0,0 L15,0 L15,15 L0,15 Z"],
+            mid.contours
+                .iter()
+                .map(|bez| bez.to_svg())
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    #[test]
+    fn incompatible_sparse_component_is_an_error() {
+        let context = wght_context();
+        let mut two_squares = square_instance(20.0);
+        two_squares.contours.extend(square_instance(5.0).contours);
+        context.glyphs.set(glyph_at(
+            "sparse",
+            &[(0.0, square_instance(10.0)), (1.0, two_squares)],
+        ));
+        let instance = GlyphInstance {
+            components: vec![Component {
+                base: "sparse".into(),
+                transform: Affine::IDENTITY,
+            }],
+            ..Default::default()
+        };
+        let composite = glyph_at("composite", &[(0.0, instance.clone()), (0.5, instance)]);
+
+        assert!(convert_components_to_contours(&context, &composite).is_err());
+    }
+
     #[test]
     fn components_to_contours_deep() {
         let test_data = deep_component();
@@ -1078,6 +1432,90 @@ mod tests {
         assert_simple(&simple);
     }
 
+    #[test]
+    fn varied_transform_is_an_error_unless_flattened() {
+        let glyph = contour_and_component_weight_glyph("nameless");
+        let mut glyph: GlyphBuilder = glyph.into();
+        glyph
+            .sources
+            .values_mut()
+            .for_each(|inst| inst.contours.clear());
+        let glyph = adjust_transform_for_each_instance(&glyph.build().unwrap(), |i| {
+            Affine::scale(i as f64 + 1.0)
+        });
+
+        let mut flags = Flags::default();
+        flags.set(Flags::EMIT_IR, false);
+        flags.set(Flags::FLATTEN_VARIABLE_COMPONENTS, false);
+        let context = Context::new_root(flags, Paths::new(Path::new("/fake/path")))
+            .copy_for_work(Access::All, Access::All);
+        context.glyphs.set(contour_glyph("component"));
+        context.glyphs.set(glyph);
+        context.preliminary_glyph_order.set(
+            ["component", "nameless"]
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+        );
+
+        let result = create_glyph_order_work().exec(&context);
+        assert!(
+            matches!(&result, Err(Error::VariableComponentTransforms(names)) if names == &[GlyphName::from("nameless")]),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn different_components_are_an_error_unless_flattened() {
+        let mut glyph = GlyphBuilder::new("nameless".into());
+        glyph
+            .try_add_source(
+                &NormalizedLocation::for_pos(&[("wght", 0.0)]),
+                component_instance(),
+            )
+            .unwrap();
+        let mut other = component_instance();
+        other.components[0].base = "other".into();
+        glyph
+            .try_add_source(&NormalizedLocation::for_pos(&[("wght", 1.0)]), other)
+            .unwrap();
+        let glyph = glyph.build().unwrap();
+
+        for flatten in [false, true] {
+            let mut flags = Flags::default();
+            flags.set(Flags::EMIT_IR, false);
+            flags.set(Flags::FLATTEN_VARIABLE_COMPONENTS, flatten);
+            let context = Context::new_root(flags, Paths::new(Path::new("/fake/path")))
+                .copy_for_work(Access::All, Access::All);
+            // an existing .notdef spares us the metadata to make one
+            context.glyphs.set(contour_glyph(".notdef"));
+            context.glyphs.set(contour_glyph("component"));
+            context.glyphs.set(contour_glyph("other"));
+            context.glyphs.set(glyph.clone());
+            context.preliminary_glyph_order.set(
+                [".notdef", "component", "other", "nameless"]
+                    .into_iter()
+                    .map(Into::into)
+                    .collect(),
+            );
+
+            let result = create_glyph_order_work().exec(&context);
+            if flatten {
+                assert!(result.is_ok(), "{result:?}");
+                assert!(context
+                    .get_glyph("nameless")
+                    .sources()
+                    .values()
+                    .all(|inst| inst.components.is_empty()));
+            } else {
+                assert!(
+                    matches!(&result, Err(Error::InconsistentComponents(names)) if names == &[GlyphName::from("nameless")]),
+                    "{result:?}"
+                );
+            }
+        }
+    }
+
     #[test]
     fn varied_translation_is_ok() {
         let glyph = contour_and_component_weight_glyph("nameless");
@@ -1086,6 +1524,21 @@ mod tests {
         assert!(glyph.has_consistent_components());
     }
 
+    #[test]
+    fn overflowing_transform() {
+        let glyph = contour_and_component_weight_glyph("nameless");
+        let scaled = |scale: f64| {
+            adjust_transform_for_each_instance(&glyph, |_| Affine::scale(scale))
+                .has_overflowing_2x2()
+        };
+        // flips and scales up to 2 survive as F2Dot14
+        assert!(!scaled(-1.0));
+        assert!(!scaled(-2.0));
+        assert!(!scaled(2.0));
+        assert!(scaled(2.5));
+        assert!(scaled(-3.0));
+    }
+
     fn assert_is_simple_glyph(context: &Context, glyph_name: GlyphName) {
         let glyph = context.get_glyph(glyph_name);
         assert!(glyph
@@ -1116,7 +1569,7 @@ mod tests {
         let test_data = deep_component();
         let context = test_context();
         test_data.write_to(&context);
-        flatten_glyph(&context, &test_data.shallow_component).unwrap();
+        flatten_glyph(&context, &test_data.shallow_component, 1).unwrap();
         assert_is_flattened_component(&context, test_data.shallow_component.name);
     }
 
@@ -1125,10 +1578,48 @@ mod tests {
         let test_data = deep_component();
         let context = test_context();
         test_data.write_to(&context);
-        flatten_glyph(&context, &test_data.deep_component).unwrap();
+        flatten_glyph(&context, &test_data.deep_component, 1).unwrap();
         assert_is_flattened_component(&context, test_data.deep_component.name);
     }
 
+    #[test]
+    fn flatten_to_max_depth() {
+        let test_data = deep_component();
+        let context = test_context();
+        test_data.write_to(&context);
+        // c3 => c2 => c1 => shape is 3 deep
+        let deeper = component_glyph("c3", "c2".into(), Affine::translate((0.0, 7.0)));
+        context.glyphs.set(deeper.clone());
+
+        flatten_glyph(&context, &deeper, 2).unwrap();
+
+        // c2 is 2 deep so c3 must skip it and reference c1 instead
+        let flattened = context.get_glyph("c3");
+        let components = &flattened.default_instance().components;
+        assert_eq!(
+            vec![(
+                GlyphName::new("c1"),
+                Affine::translate((5.0, 7.0)).as_coeffs()
+            )],
+            components
+                .iter()
+                .map(|c| (c.base.clone(), c.transform.as_coeffs()))
+                .collect::<Vec<_>>()
+        );
+        // while c2 is left alone
+        flatten_glyph(&context, &test_data.deep_component, 2).unwrap();
+        assert_eq!(
+            vec![GlyphName::new("c1")],
+            context
+                .get_glyph("c2")
+                .default_instance()
+                .components
+                .iter()
+                .map(|c| c.base.clone())
+                .collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn decompose_transformed_and_flatten_components() {
         // when both flags are set, the flattening should happen last, after
@@ -1142,7 +1633,7 @@ mod tests {
         context.flags.set(Flags::FLATTEN_COMPONENTS, true);
         test_data.write_to(&context);
 
-        apply_optional_transformations(&context, &test_data.glyph_order()).unwrap();
+        apply_optional_transformations(&context, &test_data.glyph_order(), 1).unwrap();
 
         // the shallow_component had a non-identity 2x2 transform so it was
         // converted to a simple glyph
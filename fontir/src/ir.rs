@@ -1,9 +1,14 @@
 //! Font IR types.
 use std::{
-    collections::{hash_map::RandomState, BTreeMap, BTreeSet, HashMap, HashSet},
+    collections::{
+        hash_map::{DefaultHasher, RandomState},
+        BTreeMap, BTreeSet, HashMap, HashSet,
+    },
     fmt::{Debug, Display},
+    fs,
+    hash::{Hash, Hasher},
     io::Read,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use indexmap::IndexSet;
@@ -20,7 +25,7 @@ use write_fonts::{
 use fontdrasil::{coords::NormalizedLocation, types::GlyphName};
 
 use crate::{
-    error::{BadAnchor, BadAnchorReason, BadGlyph, BadGlyphKind, Error},
+    error::{BadAnchor, BadAnchorReason, BadGlyph, BadGlyphKind, BadSource, BadSourceKind, Error},
     orchestration::{IdAware, Persistable, WorkId},
 };
 
@@ -29,8 +34,10 @@ mod static_metadata;
 
 pub use path_builder::GlyphPathBuilder;
 pub use static_metadata::{
-    Condition, ConditionSet, GdefCategories, MetaTableValues, MiscMetadata, NameKey, NamedInstance,
-    Panose, PostscriptNames, Rule, StaticMetadata, Substitution, VariableFeature,
+    AxisMapping, BaseTableValues, CodepointRange, Condition, ConditionSet, FeatureWriter,
+    FeatureWriterMode, FeatureWriters, GdefCategories, MathValues, MetaTableValues, MiscMetadata,
+    NameKey, NamedInstance, Panose, PostscriptHints, PostscriptNames, Rule, ScriptBaselines,
+    StaticMetadata, Substitution, VariableFeature,
 };
 
 pub const DEFAULT_VENDOR_ID: &str = "NONE";
@@ -291,6 +298,8 @@ impl From<KernGroup> for KernSide {
 #[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
 pub struct GlobalMetrics(
     pub(crate) HashMap<GlobalMetric, HashMap<NormalizedLocation, OrderedFloat<f64>>>,
+    /// Metrics the source left unset that should cover the bounding box of every glyph
+    pub(crate) HashSet<GlobalMetric>,
 );
 
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -396,6 +405,62 @@ impl GlobalMetrics {
         Default::default()
     }
 
+    /// Fill in vertical metrics the source doesn't set following the Google Fonts schema.
+    ///
+    /// Typo metrics span at least 120% of the em, with no line gap, and hhea matches
+    /// them. Win metrics the source doesn't set are marked to grow to the bounding box
+    /// of the font once it's known, see [`GlobalMetrics::covers_bbox`]. Call before
+    /// [`GlobalMetrics::populate_defaults`], which fills in the rest.
+    ///
+    /// See <https://googlefonts.github.io/gf-guide/metrics.html>.
+    pub fn populate_google_fonts_defaults(
+        &mut self,
+        pos: &NormalizedLocation,
+        units_per_em: u16,
+        ascender: Option<f64>,
+        descender: Option<f64>,
+    ) {
+        let units_per_em = units_per_em as f64;
+        let ascender = ascender.unwrap_or(0.8 * units_per_em);
+        let descender = descender.unwrap_or(-0.2 * units_per_em);
+
+        // grow short spans on both sides, in proportion
+        let span = ascender - descender;
+        let extra = (1.2 * units_per_em - span).max(0.0);
+        let (typo_ascender, typo_descender) = if span > 0.0 {
+            (
+                ascender + extra * ascender / span,
+                descender + extra * descender / span,
+            )
+        } else {
+            (ascender, descender)
+        };
+
+        let typo_ascender =
+            self.set_if_absent(GlobalMetric::Os2TypoAscender, pos.clone(), typo_ascender);
+        let typo_descender =
+            self.set_if_absent(GlobalMetric::Os2TypoDescender, pos.clone(), typo_descender);
+        let typo_line_gap = self.set_if_absent(GlobalMetric::Os2TypoLineGap, pos.clone(), 0.0);
+
+        self.set_if_absent(GlobalMetric::HheaAscender, pos.clone(), typo_ascender);
+        self.set_if_absent(GlobalMetric::HheaDescender, pos.clone(), typo_descender);
+        self.set_if_absent(GlobalMetric::HheaLineGap, pos.clone(), typo_line_gap);
+
+        for metric in [GlobalMetric::Os2WinAscent, GlobalMetric::Os2WinDescent] {
+            if !self.values_mut(metric).contains_key(pos) {
+                self.1.insert(metric);
+            }
+        }
+    }
+
+    /// Whether `metric` should grow to cover the bounding box of every glyph
+    ///
+    /// Only true of win metrics the source didn't set, when following the Google
+    /// Fonts schema.
+    pub fn covers_bbox(&self, metric: GlobalMetric) -> bool {
+        self.1.contains(&metric)
+    }
+
     /// Populate default values for all metrics at the given location.
     ///
     /// This is used to populate the default values for the default location whenever a
@@ -917,6 +982,34 @@ impl NameBuilder {
         }
     }
 
+    /// Make the WWS names agree with fsSelection bit 8.
+    ///
+    /// If the family is already WWS-conformant the spec says name IDs 21 and 22
+    /// should be absent. Otherwise, if only one of the pair is set, the other
+    /// falls back to the typographic, then legacy, name.
+    ///
+    /// See <https://learn.microsoft.com/en-us/typography/opentype/spec/name#name-ids>.
+    pub fn apply_wws_names(&mut self, has_wws_names: bool) {
+        if has_wws_names {
+            self.remove(NameId::WWS_FAMILY_NAME);
+            self.remove(NameId::WWS_SUBFAMILY_NAME);
+            return;
+        }
+        if !self.contains_key(NameId::WWS_FAMILY_NAME)
+            && !self.contains_key(NameId::WWS_SUBFAMILY_NAME)
+        {
+            return;
+        }
+        self.apply_fallback(
+            NameId::WWS_FAMILY_NAME,
+            &[NameId::TYPOGRAPHIC_FAMILY_NAME, NameId::FAMILY_NAME],
+        );
+        self.apply_fallback(
+            NameId::WWS_SUBFAMILY_NAME,
+            &[NameId::TYPOGRAPHIC_SUBFAMILY_NAME, NameId::SUBFAMILY_NAME],
+        );
+    }
+
     pub fn set_version(&mut self, major: i32, minor: u32) {
         self.version_major = major;
         self.version_minor = minor;
@@ -986,6 +1079,111 @@ impl FeaturesSource {
             include_dir: None,
         }
     }
+
+    /// The feature code in memory, with every include replaced by what it includes
+    ///
+    /// Feature code in memory with no include dir is returned as is; its includes
+    /// can't be resolved when it is compiled either.
+    pub fn inlined(&self) -> Result<FeaturesSource, Error> {
+        let (fea_content, root, fea_file) = match self {
+            FeaturesSource::Empty
+            | FeaturesSource::Memory {
+                include_dir: None, ..
+            } => return Ok(self.clone()),
+            FeaturesSource::Memory {
+                fea_content,
+                include_dir: Some(include_dir),
+            } => (fea_content.clone(), include_dir.clone(), None),
+            FeaturesSource::File {
+                fea_file,
+                include_dir,
+            } => {
+                let fea_content = fs::read_to_string(fea_file)
+                    .map_err(|e| BadSource::new(fea_file, BadSourceKind::Io(e)))?;
+                // same as the project root fontbe gives fea-rs
+                let root = include_dir
+                    .clone()
+                    .or_else(|| fea_file.parent().map(PathBuf::from))
+                    .unwrap_or_default();
+                (fea_content, root, Some(fea_file.as_path()))
+            }
+        };
+        Ok(FeaturesSource::Memory {
+            fea_content: expand_includes(&fea_content, &root, fea_file, 0)?,
+            include_dir: None,
+        })
+    }
+}
+
+/// How deeply includes may nest, the same limit as fea-rs
+const MAX_INCLUDE_DEPTH: usize = 50;
+
+fn expand_includes(
+    fea: &str,
+    root: &Path,
+    including_file: Option<&Path>,
+    depth: usize,
+) -> Result<String, Error> {
+    let mut expanded = String::with_capacity(fea.len());
+    let mut rest = fea;
+    while let Some((before, include, after)) = split_at_include(rest) {
+        let file = resolve_include(Path::new(include), root, including_file);
+        if depth + 1 >= MAX_INCLUDE_DEPTH {
+            return Err(BadSource::custom(file, "includes are nested too deeply").into());
+        }
+        let included =
+            fs::read_to_string(&file).map_err(|e| BadSource::new(&file, BadSourceKind::Io(e)))?;
+        expanded.push_str(before);
+        expanded.push_str(&expand_includes(&included, root, Some(&file), depth + 1)?);
+        // the included file may end in a comment
+        if !expanded.ends_with('\n') {
+            expanded.push('\n');
+        }
+        rest = after;
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Split at the first include outside comments and strings, into the text
+/// before it, the path it includes and the text after it
+fn split_at_include(fea: &str) -> Option<(&str, &str, &str)> {
+    let is_name_byte = |b: u8| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b'-' | b'\\');
+    let bytes = fea.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'#' => i = fea[i..].find('\n').map_or(fea.len(), |end| i + end),
+            b'"' => i = fea[i + 1..].find('"').map_or(fea.len(), |end| i + end + 2),
+            b'i' if fea[i..].starts_with("include") && (i == 0 || !is_name_byte(bytes[i - 1])) => {
+                let parenthesized = fea[i + "include".len()..].trim_start().strip_prefix('(');
+                if let Some((path, after)) = parenthesized.and_then(|p| p.split_once(')')) {
+                    let after = after.trim_start().strip_prefix(';').unwrap_or(after);
+                    return Some((&fea[..i], path.trim(), after));
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Resolve an include relative to the project root, else to the including file,
+/// as the [FEA spec](http://adobe-type-tools.github.io/afdko/OpenTypeFeatureFileSpecification.html#3-including-files) says
+fn resolve_include(path: &Path, root: &Path, including_file: Option<&Path>) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    let from_root = root.join(path);
+    if from_root.exists() {
+        return from_root;
+    }
+    including_file
+        .and_then(Path::parent)
+        .map(|dir| dir.join(path))
+        .filter(|file| file.exists())
+        .unwrap_or(from_root)
 }
 
 /// The anchors for a [Glyph]
@@ -1047,6 +1245,12 @@ pub enum AnchorKind {
     VCaret(usize),
     CursiveEntry,
     CursiveExit,
+    /// The italic correction of a glyph for the MATH table, named `math.ic`
+    ///
+    /// Only the x position is used. This is the convention of the GlyphsMath plugin.
+    MathItalicCorrection,
+    /// The top accent attachment of a glyph for the MATH table, named `math.ta`
+    MathTopAccent,
 }
 
 impl AnchorKind {
@@ -1061,6 +1265,12 @@ impl AnchorKind {
         if name == "exit" {
             return Ok(AnchorKind::CursiveExit);
         }
+        if name == "math.ic" {
+            return Ok(AnchorKind::MathItalicCorrection);
+        }
+        if name == "math.ta" {
+            return Ok(AnchorKind::MathTopAccent);
+        }
 
         if let Some(suffix) = name
             .strip_prefix("caret_")
@@ -1190,6 +1400,9 @@ impl Anchor {
     }
 }
 
+/// The anchor used to move a glyph's origin
+const ORIGIN_ANCHOR: &str = "*origin";
+
 /// A type for building glyph anchors, reused by different backends
 #[derive(Debug, Clone)]
 pub struct AnchorBuilder {
@@ -1226,7 +1439,20 @@ impl AnchorBuilder {
         Ok(())
     }
 
-    pub fn build(self) -> Result<GlyphAnchors, BadGlyph> {
+    pub fn build(mut self) -> Result<GlyphAnchors, BadGlyph> {
+        // "*origin" shifts the glyph's origin, and so every other anchor. It isn't
+        // an anchor in its own right. Glyphs sources apply it when propagating anchors,
+        // this catches sources, like UFOs, that carry it through.
+        if let Some(origins) = self.anchors.remove(ORIGIN_ANCHOR) {
+            for positions in self.anchors.values_mut() {
+                for (loc, pos) in positions.iter_mut() {
+                    if let Some(origin) = origins.get(loc) {
+                        *pos -= origin.to_vec2();
+                    }
+                }
+            }
+        }
+
         // It would be nice if everyone was defined at default
         for (anchor, positions) in &self.anchors {
             if !positions.keys().any(|loc| !loc.has_any_non_zero()) {
@@ -1353,6 +1579,28 @@ impl Glyph {
         self.sources.get_mut(loc)
     }
 
+    /// A hash of everything about this glyph that affects compilation.
+    ///
+    /// Independent of the order of the (unordered) codepoints and sources so
+    /// equal glyphs hash equal. Used to detect which glyphs changed between builds.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.name.hash(&mut hasher);
+        self.emit_to_binary.hash(&mut hasher);
+
+        let mut codepoints: Vec<_> = self.codepoints.iter().collect();
+        codepoints.sort();
+        codepoints.hash(&mut hasher);
+
+        let mut sources: Vec<_> = self.sources.iter().collect();
+        sources.sort_by_key(|(loc, _)| *loc);
+        for (loc, instance) in sources {
+            loc.hash(&mut hasher);
+            instance.hash_content(&mut hasher);
+        }
+        hasher.finish()
+    }
+
     /// Iterate over the names of all components in all instances.
     ///
     /// This will return duplicates if multiple instances have identical
@@ -1382,6 +1630,22 @@ impl Glyph {
             .flat_map(|inst| inst.components.iter())
             .any(|c| c.transform.as_coeffs()[..4] != [1.0, 0.0, 0.0, 1.0])
     }
+
+    /// Does the glyph have any component with a 2x2 glyf can't store
+    ///
+    /// The coefficients are F2Dot14, good for [-2, 2). Like fontTools we let values
+    /// just shy of +2 saturate, so flips and scales up to 2 are fine, but anything
+    /// larger would silently distort.
+    pub(crate) fn has_overflowing_2x2(&self) -> bool {
+        self.sources
+            .values()
+            .flat_map(|inst| inst.components.iter())
+            .any(|c| {
+                c.transform.as_coeffs()[..4]
+                    .iter()
+                    .any(|v| !(-2.0..=2.0).contains(v))
+            })
+    }
 }
 
 impl IdAware<WorkId> for Glyph {
@@ -1425,8 +1689,13 @@ impl Persistable for FeaturesSource {
         serde_yaml::from_reader(from).unwrap()
     }
 
+    /// Writes the feature code inlined, so the IR doesn't depend on the source's files
     fn write(&self, to: &mut dyn std::io::Write) {
-        serde_yaml::to_writer(to, self).unwrap();
+        let inlined = self.inlined().unwrap_or_else(|e| {
+            warn!("Unable to inline the feature includes, writing them as they are: {e}");
+            self.clone()
+        });
+        serde_yaml::to_writer(to, &inlined).unwrap();
     }
 }
 
@@ -1492,6 +1761,26 @@ impl Persistable for PaintGraph {
     }
 }
 
+impl Persistable for BitmapStrikes {
+    fn read(from: &mut dyn Read) -> Self {
+        serde_yaml::from_reader(from).unwrap()
+    }
+
+    fn write(&self, to: &mut dyn std::io::Write) {
+        serde_yaml::to_writer(to, self).unwrap();
+    }
+}
+
+impl Persistable for SvgDocuments {
+    fn read(from: &mut dyn Read) -> Self {
+        serde_yaml::from_reader(from).unwrap()
+    }
+
+    fn write(&self, to: &mut dyn std::io::Write) {
+        serde_yaml::to_writer(to, self).unwrap();
+    }
+}
+
 /// A variable definition of a single glyph.
 ///
 /// If defined in many locations, presumed to vary continuously
@@ -1631,6 +1920,54 @@ pub struct GlyphInstance {
 }
 
 impl GlyphInstance {
+    /// Feed the content of this instance into a hasher.
+    ///
+    /// f64 doesn't implement Hash so values are hashed by their bits.
+    fn hash_content(&self, state: &mut impl Hasher) {
+        fn hash_point(pt: Point, state: &mut impl Hasher) {
+            pt.x.to_bits().hash(state);
+            pt.y.to_bits().hash(state);
+        }
+
+        self.width.to_bits().hash(state);
+        self.height.map(f64::to_bits).hash(state);
+        self.vertical_origin.map(f64::to_bits).hash(state);
+        self.contours.len().hash(state);
+        for contour in self.contours.iter() {
+            for el in contour.elements() {
+                match el {
+                    PathEl::MoveTo(p) => {
+                        0u8.hash(state);
+                        hash_point(*p, state);
+                    }
+                    PathEl::LineTo(p) => {
+                        1u8.hash(state);
+                        hash_point(*p, state);
+                    }
+                    PathEl::QuadTo(p0, p1) => {
+                        2u8.hash(state);
+                        hash_point(*p0, state);
+                        hash_point(*p1, state);
+                    }
+                    PathEl::CurveTo(p0, p1, p2) => {
+                        3u8.hash(state);
+                        hash_point(*p0, state);
+                        hash_point(*p1, state);
+                        hash_point(*p2, state);
+                    }
+                    PathEl::ClosePath => 4u8.hash(state),
+                }
+            }
+        }
+        self.components.len().hash(state);
+        for component in self.components.iter() {
+            component.base.hash(state);
+            for coeff in component.transform.as_coeffs() {
+                coeff.to_bits().hash(state);
+            }
+        }
+    }
+
     /// Returns the concatenation of the element types for each outline.
     ///
     /// These are 'M' for moveto, 'L' for lineto, 'Q' for quadto, 'C' for
@@ -1691,10 +2028,14 @@ pub struct Component {
 pub struct ColorPalettes {
     // All palettes have the same number of colors; there is at least one palette with at least one color
     pub palettes: Vec<Vec<Color>>,
+    /// CPAL palette type flags by palette, e.g. for dark mode; empty if no palette has a type
+    #[serde(default)]
+    pub palette_types: Vec<u32>,
 }
 
 impl ColorPalettes {
-    pub fn new(mut palettes: Vec<Vec<Color>>) -> Result<Option<Self>, Error> {
+    /// Palettes as given, entries correspond across palettes so none are reordered
+    pub fn new(palettes: Vec<Vec<Color>>) -> Result<Option<Self>, Error> {
         // Fail if boring
         if !palettes.iter().any(|p| !p.is_empty()) {
             return Ok(None);
@@ -1709,17 +2050,19 @@ impl ColorPalettes {
             });
         }
 
-        // Trivial optimization: if there is only one palette we can deduplicate it
-        if let [one_palette] = palettes.as_mut_slice() {
-            one_palette.sort();
-            one_palette.dedup();
-        }
-        // Sort for stability in output
-        for p in palettes.iter_mut() {
-            p.sort();
-        }
+        Ok(Some(Self {
+            palettes,
+            palette_types: Vec::new(),
+        }))
+    }
 
-        Ok(Some(Self { palettes }))
+    /// Set the type flags of each palette, there must be one per palette
+    pub fn with_palette_types(mut self, palette_types: Vec<u32>) -> Self {
+        assert_eq!(self.palettes.len(), palette_types.len());
+        if palette_types.iter().any(|flags| *flags != 0) {
+            self.palette_types = palette_types;
+        }
+        self
     }
 }
 
@@ -1733,8 +2076,100 @@ pub struct Color {
 }
 
 /// Data to inform construction of [COLR](https://learn.microsoft.com/en-us/typography/opentype/spec/colr)
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct PaintGraph {
+    /// The gradient that fills the outline of each color glyph
+    pub base_glyphs: BTreeMap<GlyphName, GradientPaint>,
+}
+
+/// A gradient, possibly varying across the designspace
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
-pub struct PaintGraph {}
+pub struct GradientPaint {
+    pub kind: GradientKind,
+    /// In order of increasing offset
+    pub stops: Vec<ColorStop>,
+    /// The points and radii of the gradient at each location that defines it
+    ///
+    /// Values are in font units, in the order of the fields of the COLR paint:
+    /// `[x0, y0, x1, y1, x2, y2]` for a linear gradient and `[x0, y0, r0, x1, y1, r1]`
+    /// for a radial one. There is always a value for the default location.
+    pub geometry: Vec<(NormalizedLocation, Vec<OrderedFloat<f64>>)>,
+}
+
+impl GradientPaint {
+    /// Whether the geometry differs between locations, requiring COLR variations
+    pub fn is_variable(&self) -> bool {
+        self.geometry.windows(2).any(|pair| pair[0].1 != pair[1].1)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GradientKind {
+    Linear,
+    Radial,
+}
+
+/// A color at a position along a gradient's color line
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ColorStop {
+    /// The position on the color line, 0 at the start and 1 at the end
+    pub offset: OrderedFloat<f64>,
+    pub color: Color,
+}
+
+/// Bitmap glyphs, such as emoji, by the pixels per em of their strike
+///
+/// Used to build [sbix](https://learn.microsoft.com/en-us/typography/opentype/spec/sbix)
+/// or [CBDT](https://learn.microsoft.com/en-us/typography/opentype/spec/cbdt).
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct BitmapStrikes {
+    pub strikes: BTreeMap<u16, BitmapStrike>,
+}
+
+impl BitmapStrikes {
+    pub fn is_empty(&self) -> bool {
+        self.strikes.values().all(|strike| strike.glyphs.is_empty())
+    }
+}
+
+/// The bitmaps of one size
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct BitmapStrike {
+    pub glyphs: BTreeMap<GlyphName, BitmapGlyph>,
+}
+
+/// A PNG image and where it sits relative to the glyph origin
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BitmapGlyph {
+    pub png: Vec<u8>,
+    /// Pixels from the glyph origin to the left edge of the image
+    pub left: i16,
+    /// Pixels from the baseline to the bottom edge of the image
+    pub bottom: i16,
+}
+
+impl BitmapGlyph {
+    /// The width and height in pixels, from the PNG header
+    pub fn size(&self) -> Option<(u32, u32)> {
+        // the signature, then the IHDR chunk, which starts with width and height
+        if self.png.len() < 24 || &self.png[12..16] != b"IHDR" {
+            return None;
+        }
+        let width = u32::from_be_bytes(self.png[16..20].try_into().unwrap());
+        let height = u32::from_be_bytes(self.png[20..24].try_into().unwrap());
+        Some((width, height))
+    }
+}
+
+/// An SVG document per glyph, for an
+/// [SVG](https://learn.microsoft.com/en-us/typography/opentype/spec/svg) table
+///
+/// Documents are as the source has them; the id that ties a document to its
+/// glyph id is only added once glyph ids are known.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct SvgDocuments {
+    pub documents: BTreeMap<GlyphName, String>,
+}
 
 #[cfg(test)]
 mod tests {
@@ -1774,6 +2209,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn glyph_content_hash_ignores_insertion_order() {
+        let regular = NormalizedLocation::default();
+        let bold = NormalizedLocation::for_pos(&[("wght", 1.0)]);
+        let instance = |width| GlyphInstance {
+            width,
+            contours: vec![BezPath::from_svg("M0,0 L10,0 L10,10 Z").unwrap()],
+            ..Default::default()
+        };
+        let glyph = |order: &[(&NormalizedLocation, f64)], codepoints: &[u32]| {
+            Glyph::new(
+                "a".into(),
+                true,
+                codepoints.iter().copied().collect(),
+                order
+                    .iter()
+                    .map(|(loc, width)| ((*loc).clone(), instance(*width)))
+                    .collect(),
+            )
+            .unwrap()
+        };
+
+        let g1 = glyph(&[(&regular, 500.0), (&bold, 600.0)], &[0x61, 0x62]);
+        let g2 = glyph(&[(&bold, 600.0), (&regular, 500.0)], &[0x62, 0x61]);
+        let g3 = glyph(&[(&regular, 500.0), (&bold, 601.0)], &[0x61, 0x62]);
+        assert_eq!(g1.content_hash(), g2.content_hash());
+        assert_ne!(g1.content_hash(), g3.content_hash());
+    }
+
     #[test]
     fn caret_anchor_names() {
         assert_eq!(AnchorKind::new("caret_1"), Ok(AnchorKind::Caret(1)));
@@ -1784,6 +2248,28 @@ mod tests {
         assert_eq!(AnchorKind::new("vcaret_"), Ok(AnchorKind::VCaret(1)));
     }
 
+    #[test]
+    fn math_anchor_names() {
+        assert_eq!(
+            AnchorKind::new("math.ic"),
+            Ok(AnchorKind::MathItalicCorrection)
+        );
+        assert_eq!(AnchorKind::new("math.ta"), Ok(AnchorKind::MathTopAccent));
+    }
+
+    #[test]
+    fn bitmap_size_from_png_header() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(136u32.to_be_bytes());
+        png.extend(128u32.to_be_bytes());
+        let glyph = BitmapGlyph {
+            png,
+            left: 0,
+            bottom: 0,
+        };
+        assert_eq!(Some((136, 128)), glyph.size());
+    }
+
     #[test]
     fn ligature_empty_component_anchor_name() {
         assert_eq!(AnchorKind::new("_3"), Ok(AnchorKind::ComponentMarker(3)));
@@ -1802,6 +2288,50 @@ mod tests {
         assert_eq!(AnchorKind::new("top_0"), Err(BadAnchorReason::ZeroIndex));
     }
 
+    #[test]
+    fn origin_anchor_moves_other_anchors() {
+        let regular = NormalizedLocation::default();
+        let bold = NormalizedLocation::for_pos(&[("wght", 1.0)]);
+        let mut builder = AnchorBuilder::new("a".into());
+        for (name, loc, pos) in [
+            ("top", &regular, (250.0, 700.0)),
+            ("top", &bold, (270.0, 700.0)),
+            ("*origin", &regular, (50.0, 0.0)),
+            ("entry", &regular, (0.0, 100.0)),
+        ] {
+            builder
+                .add(name.into(), loc.clone(), Point::from(pos))
+                .unwrap();
+        }
+        let anchors = builder.build().unwrap();
+
+        let mut anchors: Vec<_> = anchors
+            .anchors
+            .iter()
+            .map(|a| {
+                let mut positions: Vec<_> = a
+                    .positions
+                    .iter()
+                    .map(|(loc, pos)| (loc == &bold, pos.x, pos.y))
+                    .collect();
+                positions.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                (format!("{:?}", a.kind), positions)
+            })
+            .collect();
+        anchors.sort_by(|a, b| a.0.cmp(&b.0));
+        // the origin is only defined at regular, so only moves anchors there
+        assert_eq!(
+            vec![
+                (
+                    "Base(\"top\")".to_string(),
+                    vec![(false, 200.0, 700.0), (true, 270.0, 700.0)]
+                ),
+                ("CursiveEntry".to_string(), vec![(false, -50.0, 100.0)]),
+            ],
+            anchors
+        );
+    }
+
     fn assert_names(expected: &[(NameId, &str)], actual: HashMap<NameKey, String>) {
         let mut actual: Vec<_> = actual
             .iter()
@@ -1839,6 +2369,43 @@ mod tests {
         )
     }
 
+    #[test]
+    fn wws_names_dropped_if_family_is_wws() {
+        let mut builder = NameBuilder::default();
+        builder.add(NameId::WWS_FAMILY_NAME, "Family".into());
+        builder.add(NameId::WWS_SUBFAMILY_NAME, "Italic".into());
+        builder.apply_default_fallbacks(DEFAULT_VENDOR_ID);
+        builder.apply_wws_names(true);
+
+        assert!(!builder.contains_key(NameId::WWS_FAMILY_NAME));
+        assert!(!builder.contains_key(NameId::WWS_SUBFAMILY_NAME));
+    }
+
+    #[test]
+    fn wws_subfamily_falls_back_to_typographic() {
+        let mut builder = NameBuilder::default();
+        builder.add(NameId::TYPOGRAPHIC_FAMILY_NAME, "Family".into());
+        builder.add(NameId::TYPOGRAPHIC_SUBFAMILY_NAME, "Condensed Bold".into());
+        builder.add(NameId::WWS_FAMILY_NAME, "Family Condensed".into());
+        builder.apply_default_fallbacks(DEFAULT_VENDOR_ID);
+        builder.apply_wws_names(false);
+
+        assert_eq!(
+            Some("Condensed Bold"),
+            builder.get(NameId::WWS_SUBFAMILY_NAME)
+        );
+    }
+
+    #[test]
+    fn no_wws_names_unless_asked_for() {
+        let mut builder = NameBuilder::default();
+        builder.apply_default_fallbacks(DEFAULT_VENDOR_ID);
+        builder.apply_wws_names(false);
+
+        assert!(!builder.contains_key(NameId::WWS_FAMILY_NAME));
+        assert!(!builder.contains_key(NameId::WWS_SUBFAMILY_NAME));
+    }
+
     #[test]
     fn fallback_version_and_unique_id() {
         let mut builder = NameBuilder::default();
@@ -2044,6 +2611,59 @@ mod tests {
         assert_eq!(default.into_inner(), 220.);
     }
 
+    #[test]
+    fn google_fonts_metrics_span_120_percent() {
+        let pos = NormalizedLocation::for_pos(&[("wght", 0.0)]);
+
+        let mut metrics = GlobalMetrics::new();
+        metrics.populate_google_fonts_defaults(&pos, 1000, Some(750.), Some(-250.));
+        metrics.populate_defaults(&pos, 1000, None, Some(750.), Some(-250.), None);
+
+        let get = |metric| metrics.get(metric, &pos).into_inner();
+        assert_eq!(
+            (900., -300., 0.),
+            (
+                get(GlobalMetric::Os2TypoAscender),
+                get(GlobalMetric::Os2TypoDescender),
+                get(GlobalMetric::Os2TypoLineGap)
+            )
+        );
+        assert_eq!(
+            (900., -300., 0.),
+            (
+                get(GlobalMetric::HheaAscender),
+                get(GlobalMetric::HheaDescender),
+                get(GlobalMetric::HheaLineGap)
+            )
+        );
+    }
+
+    #[test]
+    fn google_fonts_metrics_keep_source_values() {
+        let pos = NormalizedLocation::for_pos(&[("wght", 0.0)]);
+
+        let mut metrics = GlobalMetrics::new();
+        metrics.set(GlobalMetric::Os2TypoAscender, pos.clone(), 1000.);
+        metrics.populate_google_fonts_defaults(&pos, 1000, Some(750.), Some(-250.));
+
+        assert_eq!(
+            1000.,
+            metrics.get(GlobalMetric::HheaAscender, &pos).into_inner()
+        );
+    }
+
+    #[test]
+    fn google_fonts_metrics_only_grow_unset_win_metrics() {
+        let pos = NormalizedLocation::for_pos(&[("wght", 0.0)]);
+
+        let mut metrics = GlobalMetrics::new();
+        metrics.set(GlobalMetric::Os2WinAscent, pos.clone(), 1000.);
+        metrics.populate_google_fonts_defaults(&pos, 1000, Some(750.), Some(-250.));
+
+        assert!(!metrics.covers_bbox(GlobalMetric::Os2WinAscent));
+        assert!(metrics.covers_bbox(GlobalMetric::Os2WinDescent));
+    }
+
     fn make_glyph_order<'a>(raw: impl IntoIterator<Item = &'a str>) -> GlyphOrder {
         raw.into_iter().map(GlyphName::from).collect()
     }
@@ -2057,4 +2677,34 @@ mod tests {
     fn glyphorder_non_equality() {
         assert_ne!(make_glyph_order(["a", "b"]), make_glyph_order(["b", "a"]));
     }
+
+    #[test]
+    fn inline_feature_includes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir(root.join("sub")).unwrap();
+        fs::write(
+            root.join("features.fea"),
+            "# include(commented.fea);\ninclude( sub/a.fea );\nfeature liga { sub f i by f_i; } liga;\n",
+        )
+        .unwrap();
+        // no include dir, includes resolve relative to features.fea or the including file
+        fs::write(root.join("sub/a.fea"), "include(b.fea)").unwrap();
+        fs::write(
+            root.join("sub/b.fea"),
+            "languagesystem DFLT dflt; # last line",
+        )
+        .unwrap();
+
+        let inlined = FeaturesSource::from_file(root.join("features.fea"), None)
+            .inlined()
+            .unwrap();
+        assert_eq!(
+            FeaturesSource::from_string(
+                "# include(commented.fea);\nlanguagesystem DFLT dflt; # last line\n\nfeature liga { sub f i by f_i; } liga;\n"
+                    .to_string()
+            ),
+            inlined
+        );
+    }
 }
@@ -80,6 +80,68 @@ pub struct StaticMetadata {
     pub gdef_categories: GdefCategories,
     /// Feature variation rules
     pub variations: Option<VariableFeature>,
+    /// Which features the compiler may generate, and how
+    pub feature_writers: FeatureWriters,
+    /// Hinting data for PostScript outlines, by master location
+    ///
+    /// Empty if the source doesn't define any.
+    pub postscript_hints: HashMap<NormalizedLocation, PostscriptHints>,
+    /// Mappings between locations, used to build an avar version 2 table
+    ///
+    /// Empty if the source doesn't define any.
+    pub axis_mappings: Vec<AxisMapping>,
+}
+
+/// Maps one location in variation space to another, as in avar version 2.
+///
+/// Both locations are normalized after avar version 1 has been applied; that is,
+/// linearly between the design space min, default and max of each axis. An axis
+/// missing from the output keeps its input value. See
+/// <https://github.com/harfbuzz/boring-expansion-spec/blob/main/avar2.md>
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AxisMapping {
+    pub input: NormalizedLocation,
+    pub output: NormalizedLocation,
+}
+
+/// The font-wide hinting data of a master, as stored in a CFF private dict
+///
+/// Zones are (bottom, top) pairs, sorted by position. See
+/// <https://adobe-type-tools.github.io/font-tech-notes/pdfs/5176.CFF.pdf#page=23>
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct PostscriptHints {
+    /// Alignment zones at the baseline and above, such as x-height overshoot
+    pub blue_values: Vec<(OrderedFloat<f64>, OrderedFloat<f64>)>,
+    /// Alignment zones below the baseline, such as descender overshoot
+    pub other_blues: Vec<(OrderedFloat<f64>, OrderedFloat<f64>)>,
+    /// The widths of horizontal stems, in the order the source lists them
+    ///
+    /// The first is taken to be the dominant width, see [PostscriptHints::std_hw].
+    /// The private dict wants StemSnapH in increasing order, so a writer must sort
+    /// them.
+    pub stem_snap_h: Vec<OrderedFloat<f64>>,
+    /// The widths of vertical stems, in the order the source lists them
+    ///
+    /// As for [PostscriptHints::stem_snap_h], the first is the dominant width.
+    pub stem_snap_v: Vec<OrderedFloat<f64>>,
+}
+
+impl PostscriptHints {
+    pub fn is_empty(&self) -> bool {
+        *self == Default::default()
+    }
+
+    /// The dominant horizontal stem width, StdHW in the private dict
+    ///
+    /// This is the first listed, as Glyphs users list the dominant stem first.
+    pub fn std_hw(&self) -> Option<OrderedFloat<f64>> {
+        self.stem_snap_h.first().copied()
+    }
+
+    /// The dominant vertical stem width, StdVW in the private dict
+    pub fn std_vw(&self) -> Option<OrderedFloat<f64>> {
+        self.stem_snap_v.first().copied()
+    }
 }
 
 /// IR for a named position in variation space
@@ -132,6 +194,89 @@ impl NameKey {
     }
 }
 
+/// Configuration of the features the compiler generates from source data.
+///
+/// Corresponds to the ufo2ft feature writers, configured by the
+/// `com.github.googlei18n.ufo2ft.featureWriters` lib key. A writer that is
+/// `None` is disabled. See
+/// <https://github.com/googlefonts/ufo2ft/blob/main/Lib/ufo2ft/featureWriters/__init__.py>
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FeatureWriters {
+    /// Generates kern and dist from kerning
+    pub kern: Option<FeatureWriter>,
+    /// Generates mark, mkmk, abvm and blwm from anchors
+    pub mark: Option<FeatureWriter>,
+    /// Generates curs from entry and exit anchors
+    pub curs: Option<FeatureWriter>,
+    /// Generates ccmp, decomposing encoded glyphs made of a base and marks
+    pub ccmp: Option<FeatureWriter>,
+    /// Generates locl from glyphs named for a language, e.g. a.loclNLD
+    pub locl: Option<FeatureWriter>,
+    /// Generates frac, numr and dnom from glyphs named e.g. one.numr and one.dnom
+    pub frac: Option<FeatureWriter>,
+    /// Generates liga, case, ordn, sups, subs and sinf from glyph names, the
+    /// way Glyphs.app generates them automatically
+    pub automatic: Option<FeatureWriter>,
+}
+
+impl Default for FeatureWriters {
+    fn default() -> Self {
+        FeatureWriters {
+            kern: Some(Default::default()),
+            mark: Some(Default::default()),
+            curs: Some(Default::default()),
+            // not among the ufo2ft defaults
+            ccmp: None,
+            locl: None,
+            frac: None,
+            automatic: None,
+        }
+    }
+}
+
+impl FeatureWriters {
+    /// Every writer disabled
+    pub fn none() -> Self {
+        FeatureWriters {
+            kern: None,
+            mark: None,
+            curs: None,
+            ccmp: None,
+            locl: None,
+            frac: None,
+            automatic: None,
+        }
+    }
+}
+
+/// The options of a single feature writer
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct FeatureWriter {
+    pub mode: FeatureWriterMode,
+    /// If set, only these of the features the writer supports are generated
+    pub features: Option<BTreeSet<Tag>>,
+}
+
+impl FeatureWriter {
+    /// Whether the writer is configured to generate the feature
+    pub fn generates(&self, tag: Tag) -> bool {
+        self.features
+            .as_ref()
+            .map(|features| features.contains(&tag))
+            .unwrap_or(true)
+    }
+}
+
+/// What to do when a feature to be generated is already present in the FEA
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureWriterMode {
+    /// Leave the existing feature alone, unless it has an insertion marker
+    #[default]
+    Skip,
+    /// Add the generated lookups to the existing feature
+    Append,
+}
+
 #[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
 pub struct GdefCategories {
     /// A map of glyphs to categories.
@@ -174,6 +319,12 @@ pub struct MiscMetadata {
 
     pub created: Option<DateTime<Utc>>,
 
+    /// The time of the build, used in place of the current time where a date is
+    /// needed, such as head.modified. Set for reproducible builds.
+    ///
+    /// If None, the SOURCE_DATE_EPOCH environment variable or the current time is used.
+    pub timestamp: Option<DateTime<Utc>>,
+
     // <https://learn.microsoft.com/en-us/typography/opentype/spec/os2#sfamilyclass>
     pub family_class: Option<i16>,
 
@@ -186,6 +337,16 @@ pub struct MiscMetadata {
     pub codepage_range_bits: Option<HashSet<u32>>,
     pub meta_table: Option<MetaTableValues>,
 
+    /// Baseline positions for the '[BASE]' table, from the source
+    ///
+    /// [BASE]: https://learn.microsoft.com/en-us/typography/opentype/spec/base
+    pub base_table: Option<BaseTableValues>,
+
+    /// Values for the '[MATH]' table that aren't anchors of the glyphs
+    ///
+    /// [MATH]: https://learn.microsoft.com/en-us/typography/opentype/spec/math
+    pub math: Option<MathValues>,
+
     /// <https://learn.microsoft.com/en-us/typography/opentype/spec/os2#usweightclass>
     ///
     /// If empty and there is a weight axis OS/2 will use the weight default
@@ -197,6 +358,34 @@ pub struct MiscMetadata {
 
     // <https://learn.microsoft.com/en-us/typography/opentype/spec/gasp>
     pub gasp: Vec<GaspRange>,
+
+    /// The order, by tag, to record for axes in STAT.
+    ///
+    /// Axes that aren't listed follow those that are. If empty, the order of
+    /// [`StaticMetadata::axes`] is used.
+    pub stat_axis_order: Vec<Tag>,
+
+    /// Ranges of codepoints that each map to a single glyph, as in a
+    /// [cmap format 13](https://learn.microsoft.com/en-us/typography/opentype/spec/cmap#format-13-many-to-one-range-mappings)
+    /// subtable.
+    ///
+    /// Used by last-resort style fonts. Where ranges overlap the earlier one wins,
+    /// and the codepoints of glyphs take precedence over all ranges.
+    pub cmap_ranges: Vec<CodepointRange>,
+
+    /// Whether glyphs whose sources don't interpolate fail the build
+    ///
+    /// If not, such glyphs keep only their default source, with a warning. Glyphs
+    /// sources choose with 'Enforce Compatibility Check', other sources always enforce it.
+    pub enforce_compatibility: bool,
+}
+
+/// An inclusive range of codepoints that all map to the same glyph.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CodepointRange {
+    pub first: u32,
+    pub last: u32,
+    pub glyph: GlyphName,
 }
 
 /// Records that will go in the '[meta]' table.
@@ -216,6 +405,51 @@ pub struct MetaTableValues {
     pub slng: Vec<SmolStr>,
 }
 
+/// Baselines that will go in the '[BASE]' table.
+///
+/// Lets text in scripts with different baselines, such as Han ideographs and
+/// Latin, be aligned when mixed on a line.
+///
+/// [BASE]: https://learn.microsoft.com/en-us/typography/opentype/spec/base
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct BaseTableValues {
+    /// Baselines of each script, by script tag, for horizontal text
+    pub horizontal: BTreeMap<Tag, ScriptBaselines>,
+    /// Baselines of each script, by script tag, for vertical text
+    pub vertical: BTreeMap<Tag, ScriptBaselines>,
+}
+
+impl BaseTableValues {
+    pub fn is_empty(&self) -> bool {
+        self.horizontal.is_empty() && self.vertical.is_empty()
+    }
+}
+
+/// The baselines of one script along one axis
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ScriptBaselines {
+    /// The baseline the script is aligned to by default, e.g. 'romn' for Latin
+    pub default: Tag,
+    /// The position of each baseline, by baseline tag such as 'ideo' or 'hang'
+    pub positions: BTreeMap<Tag, i16>,
+}
+
+/// Values for the '[MATH]' table, in the form of the GlyphsMath plugin.
+///
+/// The italic correction and top accent attachment of glyphs come from
+/// their `math.ic` and `math.ta` anchors.
+///
+/// [MATH]: https://learn.microsoft.com/en-us/typography/opentype/spec/math
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct MathValues {
+    /// MathConstants at the default location, by their name in the spec, e.g. AxisHeight
+    ///
+    /// MinConnectorOverlap, which goes in MathVariants, is accepted here too.
+    pub constants: BTreeMap<SmolStr, i16>,
+    /// Glyphs that are extended shapes, such as large operators
+    pub extended_shapes: BTreeSet<GlyphName>,
+}
+
 /// PANOSE bytes
 ///
 /// <https://learn.microsoft.com/en-us/typography/opentype/spec/os2#panose>
@@ -464,19 +698,52 @@ impl StaticMetadata {
                 // <https://github.com/googlefonts/ufo2ft/blob/0d2688cd847/Lib/ufo2ft/fontInfoData.py#L365>
                 head_flags: head::Flags::LSB_AT_X_0 | head::Flags::BASELINE_AT_Y_0,
                 created: None,
+                timestamp: None,
                 family_class: None,
                 panose: None,
                 unicode_range_bits: None,
                 codepage_range_bits: None,
                 meta_table: None,
+                base_table: None,
+                math: None,
                 us_weight_class: None,
                 us_width_class: None,
                 gasp: Vec::new(),
+                stat_axis_order: Vec::new(),
+                cmap_ranges: Vec::new(),
+                enforce_compatibility: true,
             },
             variations: None,
+            feature_writers: Default::default(),
+            postscript_hints: Default::default(),
+            axis_mappings: Default::default(),
         })
     }
 
+    /// Put the axes with the given tags first, in that order; the rest keep their order.
+    ///
+    /// The axis order is that of fvar, and hence of everything that follows it such as
+    /// avar and gvar, so the variation model is rebuilt to match.
+    pub fn reorder_axes(&mut self, order: &[Tag]) -> Result<(), VariationModelError> {
+        let reorder = |axes: &Axes| {
+            let mut axes = axes.iter().cloned().collect::<Vec<_>>();
+            axes.sort_by_key(|axis| {
+                order
+                    .iter()
+                    .position(|tag| *tag == axis.tag)
+                    .unwrap_or(order.len())
+            });
+            Axes::new(axes)
+        };
+        self.all_source_axes = reorder(&self.all_source_axes);
+        self.axes = reorder(&self.axes);
+        self.variation_model = VariationModel::new(
+            self.variation_model.locations().cloned().collect(),
+            self.axes.clone(),
+        )?;
+        Ok(())
+    }
+
     /// The default on all variable axes.
     pub fn default_location(&self) -> &NormalizedLocation {
         &self.default_location
@@ -614,18 +881,27 @@ mod tests {
                 head_flags: head::Flags::empty(),
                 lowest_rec_ppm: 42,
                 created: None,
+                timestamp: None,
                 family_class: None,
                 panose: None,
                 unicode_range_bits: None,
                 codepage_range_bits: None,
                 meta_table: None,
+                base_table: None,
+                math: None,
                 us_weight_class: None,
                 us_width_class: None,
                 gasp: Vec::new(),
+                stat_axis_order: Vec::new(),
+                cmap_ranges: Vec::new(),
+                enforce_compatibility: true,
             },
             number_values: Default::default(),
             variations: None,
             build_vertical: false,
+            feature_writers: Default::default(),
+            postscript_hints: Default::default(),
+            axis_mappings: Default::default(),
         }
     }
 
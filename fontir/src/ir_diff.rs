@@ -0,0 +1,223 @@
+//! Compare two directories of IR, e.g. as written by --emit-ir.
+//!
+//! Items are compared as yaml, so the summary says which fields of which items
+//! differ rather than how; the files themselves are the place to look for that.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    fs::File,
+    io::BufReader,
+    path::Path,
+};
+
+use serde_yaml::Value;
+
+use crate::{
+    error::{BadSource, BadSourceKind, Error},
+    paths::Paths,
+};
+
+/// Files in a build directory that aren't IR
+const NOT_IR: &[&str] = &["irinput.yml"];
+
+/// How two directories of IR differ
+#[derive(Debug, Default, PartialEq)]
+pub struct IrDiff {
+    pub only_in_a: Vec<String>,
+    pub only_in_b: Vec<String>,
+    /// Items in both, with a summary of what differs for each
+    pub changed: Vec<(String, Vec<String>)>,
+}
+
+impl IrDiff {
+    pub fn is_empty(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl Display for IrDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "IR is identical");
+        }
+        for item in self.only_in_a.iter() {
+            writeln!(f, "only in a: {item}")?;
+        }
+        for item in self.only_in_b.iter() {
+            writeln!(f, "only in b: {item}")?;
+        }
+        for (item, changes) in self.changed.iter() {
+            writeln!(f, "{item} differs: {}", changes.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare the IR in two build directories
+pub fn diff_ir(a: &Path, b: &Path) -> Result<IrDiff, Error> {
+    let (a, b) = (load_ir(a)?, load_ir(b)?);
+    let mut diff = IrDiff {
+        only_in_a: a.keys().filter(|k| !b.contains_key(*k)).cloned().collect(),
+        only_in_b: b.keys().filter(|k| !a.contains_key(*k)).cloned().collect(),
+        ..Default::default()
+    };
+    for (item, value_a) in a.iter() {
+        let Some(value_b) = b.get(item) else {
+            continue;
+        };
+        let changes = changes(value_a, value_b);
+        if !changes.is_empty() {
+            diff.changed.push((item.clone(), changes));
+        }
+    }
+    Ok(diff)
+}
+
+/// IR items by a name fit for humans, e.g. "glyph A"
+fn load_ir(dir: &Path) -> Result<BTreeMap<String, Value>, Error> {
+    if !dir.is_dir() {
+        return Err(BadSource::new(dir, BadSourceKind::ExpectedDirectory).into());
+    }
+    let paths = Paths::new(dir);
+    let mut items = BTreeMap::new();
+    for (file, value) in load_dir(paths.build_dir())? {
+        if !NOT_IR.contains(&file.as_str()) {
+            items.insert(file.trim_end_matches(".yml").to_string(), value);
+        }
+    }
+    // name glyphs and anchors by their glyph, filenames are escaped
+    for (kind, dir, name_key) in [
+        ("glyph", paths.glyph_ir_dir(), "name"),
+        ("anchors", paths.anchor_ir_dir(), "glyph_name"),
+    ] {
+        if !dir.is_dir() {
+            continue;
+        }
+        for (file, value) in load_dir(dir)? {
+            let name = value
+                .get(name_key)
+                .and_then(Value::as_str)
+                .unwrap_or(file.as_str())
+                .to_string();
+            items.insert(format!("{kind} {name}"), value);
+        }
+    }
+    Ok(items)
+}
+
+fn load_dir(dir: &Path) -> Result<Vec<(String, Value)>, Error> {
+    let io_err = |e| BadSource::new(dir, BadSourceKind::Io(e));
+    let mut result = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(io_err)? {
+        let path = entry.map_err(io_err)?.path();
+        let Some(file) = path.file_name().and_then(|f| f.to_str()) else {
+            continue;
+        };
+        if !path.is_file() || !file.ends_with(".yml") {
+            continue;
+        }
+        let raw = File::open(&path).map_err(|e| BadSource::new(&path, BadSourceKind::Io(e)))?;
+        let value = serde_yaml::from_reader(BufReader::new(raw))
+            .map_err(|e| BadSource::custom(&path, e))?;
+        result.push((file.to_string(), value));
+    }
+    Ok(result)
+}
+
+/// Summarize how two values differ; empty if they don't
+///
+/// Mappings report the keys whose values differ, lists of names report what
+/// was added or removed, anything else just that it changed.
+fn changes(a: &Value, b: &Value) -> Vec<String> {
+    if a == b {
+        return Vec::new();
+    }
+    match (a, b) {
+        (Value::Mapping(a), Value::Mapping(b)) => {
+            let keys: BTreeSet<_> = a
+                .keys()
+                .chain(b.keys())
+                .filter(|key| a.get(*key) != b.get(*key))
+                .map(|key| match key.as_str() {
+                    Some(key) => key.to_string(),
+                    None => serde_yaml::to_string(key).unwrap().trim().to_string(),
+                })
+                .collect();
+            keys.into_iter().collect()
+        }
+        (Value::Sequence(a), Value::Sequence(b)) if is_names(a) && is_names(b) => {
+            let (set_a, set_b): (BTreeSet<_>, BTreeSet<_>) = (
+                a.iter().filter_map(Value::as_str).collect(),
+                b.iter().filter_map(Value::as_str).collect(),
+            );
+            let mut changes: Vec<_> = set_a
+                .difference(&set_b)
+                .map(|name| format!("-{name}"))
+                .chain(set_b.difference(&set_a).map(|name| format!("+{name}")))
+                .collect();
+            if changes.is_empty() {
+                changes.push("order".to_string());
+            }
+            changes
+        }
+        _ => vec!["value".to_string()],
+    }
+}
+
+fn is_names(values: &[Value]) -> bool {
+    values.iter().all(Value::is_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml(raw: &str) -> Value {
+        serde_yaml::from_str(raw).unwrap()
+    }
+
+    #[test]
+    fn mappings_report_keys() {
+        assert_eq!(
+            vec!["b", "c"],
+            changes(&yaml("{a: 1, b: 2}"), &yaml("{a: 1, b: 3, c: 4}"))
+        );
+    }
+
+    #[test]
+    fn names_report_additions_and_removals() {
+        assert_eq!(
+            vec!["-b", "+d"],
+            changes(&yaml("[a, b, c]"), &yaml("[a, c, d]"))
+        );
+        assert_eq!(vec!["order"], changes(&yaml("[a, b]"), &yaml("[b, a]")));
+        assert!(changes(&yaml("[a, b]"), &yaml("[a, b]")).is_empty());
+    }
+
+    #[test]
+    fn diff_dirs() {
+        let (a, b) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        for dir in [a.path(), b.path()] {
+            std::fs::create_dir(dir.join("glyph_ir")).unwrap();
+            std::fs::write(dir.join("irinput.yml"), dir.to_str().unwrap()).unwrap();
+        }
+        std::fs::write(a.path().join("glyph_order.yml"), "[a, b]").unwrap();
+        std::fs::write(b.path().join("glyph_order.yml"), "[a]").unwrap();
+        std::fs::write(a.path().join("glyph_ir/a.yml"), "{name: a, width: 1}").unwrap();
+        std::fs::write(b.path().join("glyph_ir/a.yml"), "{name: a, width: 2}").unwrap();
+        std::fs::write(a.path().join("glyph_ir/b.yml"), "{name: b}").unwrap();
+
+        assert_eq!(
+            IrDiff {
+                only_in_a: vec!["glyph b".to_string()],
+                only_in_b: vec![],
+                changed: vec![
+                    ("glyph a".to_string(), vec!["width".to_string()]),
+                    ("glyph_order".to_string(), vec!["-b".to_string()]),
+                ],
+            },
+            diff_ir(a.path(), b.path()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Compile from IR written down by a previous build, e.g. with --emit-ir.
+//!
+//! Every item is restored from the file [Paths::target_file] names for it, so
+//! the build directory of one compilation is a source for another. That allows
+//! the frontend and backend to run separately and IR to be edited by hand to
+//! reduce a test case. Feature includes are inlined when IR is written, so it
+//! doesn't depend on the files of the source it came from, and the IR must be of
+//! the [IR_VERSION] this build writes.
+
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use fontdrasil::{
+    coords::NormalizedLocation,
+    orchestration::{Access, Work},
+    types::GlyphName,
+};
+use log::debug;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    error::{BadSource, BadSourceKind, Error},
+    ir::{GlyphAnchors, GlyphOrder},
+    orchestration::{Context, IrWork, WorkId, IR_VERSION},
+    paths::Paths,
+    source::Source,
+};
+
+/// A directory of IR, as written by a build with [crate::orchestration::Flags::EMIT_IR]
+#[derive(Debug, Clone)]
+pub struct IrSource {
+    paths: Arc<Paths>,
+    glyph_names: Arc<Vec<GlyphName>>,
+}
+
+impl IrSource {
+    /// Whether the directory looks like the output of a build that wrote IR
+    pub fn is_ir_dir(dir: &Path) -> bool {
+        Paths::new(dir)
+            .target_file(&WorkId::StaticMetadata)
+            .is_file()
+    }
+
+    fn restore_work(&self, id: WorkId, also_completes: Vec<WorkId>) -> Box<IrWork> {
+        Box::new(RestoreWork {
+            id,
+            also_completes,
+            paths: self.paths.clone(),
+        })
+    }
+}
+
+fn read<T: DeserializeOwned>(file: &Path) -> Result<T, Error> {
+    let raw = File::open(file).map_err(|e| BadSource::new(file, BadSourceKind::Io(e)))?;
+    serde_yaml::from_reader(BufReader::new(raw)).map_err(|e| BadSource::custom(file, e).into())
+}
+
+/// IR is only readable by the version of fontir that wrote it
+fn check_version(paths: &Paths) -> Result<(), Error> {
+    let file = paths.ir_version_file();
+    let version =
+        std::fs::read_to_string(file).map_err(|e| BadSource::new(file, BadSourceKind::Io(e)))?;
+    match version.trim().parse::<u32>() {
+        Ok(IR_VERSION) => Ok(()),
+        Ok(version) => Err(BadSource::custom(
+            file,
+            format!("IR version {version} can't be read, only version {IR_VERSION} can"),
+        )
+        .into()),
+        Err(e) => Err(BadSource::custom(file, e).into()),
+    }
+}
+
+impl Source for IrSource {
+    fn new(ir_dir: &Path) -> Result<Self, Error> {
+        let paths = Paths::new(ir_dir);
+        let required = |id: &WorkId| -> Result<PathBuf, Error> {
+            let file = paths.target_file(id);
+            if !file.is_file() {
+                return Err(BadSource::new(file, BadSourceKind::ExpectedFile).into());
+            }
+            Ok(file)
+        };
+        required(&WorkId::StaticMetadata)?;
+        check_version(&paths)?;
+
+        // The glyph order may have gained glyphs, e.g. a generated .notdef, and lost
+        // the glyphs that aren't exported; we need both.
+        let preliminary: GlyphOrder = read(&required(&WorkId::PreliminaryGlyphOrder)?)?;
+        let final_file = paths.target_file(&WorkId::GlyphOrder);
+        let final_order: GlyphOrder = if final_file.is_file() {
+            read(&final_file)?
+        } else {
+            GlyphOrder::new()
+        };
+        let mut glyph_names: Vec<GlyphName> = preliminary.names().cloned().collect();
+        glyph_names.extend(
+            final_order
+                .names()
+                .filter(|name| !preliminary.contains(*name))
+                .cloned(),
+        );
+        for name in glyph_names.iter() {
+            required(&WorkId::Glyph(name.clone()))?;
+        }
+        debug!("{} glyphs in IR at {ir_dir:?}", glyph_names.len());
+
+        Ok(IrSource {
+            paths: Arc::new(paths),
+            glyph_names: Arc::new(glyph_names),
+        })
+    }
+
+    fn create_static_metadata_work(&self) -> Result<Box<IrWork>, Error> {
+        Ok(self.restore_work(WorkId::StaticMetadata, vec![WorkId::PreliminaryGlyphOrder]))
+    }
+
+    fn create_global_metric_work(&self) -> Result<Box<IrWork>, Error> {
+        Ok(self.restore_work(WorkId::GlobalMetrics, Vec::new()))
+    }
+
+    fn create_glyph_ir_work(&self) -> Result<Vec<Box<IrWork>>, Error> {
+        Ok(self
+            .glyph_names
+            .iter()
+            .map(|name| {
+                self.restore_work(
+                    WorkId::Glyph(name.clone()),
+                    vec![WorkId::Anchor(name.clone())],
+                )
+            })
+            .collect())
+    }
+
+    fn create_feature_ir_work(&self) -> Result<Box<IrWork>, Error> {
+        Ok(self.restore_work(WorkId::Features, Vec::new()))
+    }
+
+    fn create_kerning_group_ir_work(&self) -> Result<Box<IrWork>, Error> {
+        Ok(self.restore_work(WorkId::KerningGroups, Vec::new()))
+    }
+
+    fn create_kerning_instance_ir_work(
+        &self,
+        at: NormalizedLocation,
+    ) -> Result<Box<IrWork>, Error> {
+        Ok(self.restore_work(WorkId::KernInstance(at), Vec::new()))
+    }
+
+    fn create_color_palette_work(&self) -> Result<Box<IrWork>, Error> {
+        Ok(self.restore_work(WorkId::ColorPalettes, Vec::new()))
+    }
+
+    fn create_paint_graph_work(&self) -> Result<Box<IrWork>, Error> {
+        Ok(self.restore_work(WorkId::PaintGraph, Vec::new()))
+    }
+
+    fn create_bitmap_strikes_work(&self) -> Result<Box<IrWork>, Error> {
+        Ok(self.restore_work(WorkId::BitmapStrikes, Vec::new()))
+    }
+
+    fn create_svg_documents_work(&self) -> Result<Box<IrWork>, Error> {
+        Ok(self.restore_work(WorkId::SvgDocuments, Vec::new()))
+    }
+}
+
+/// Reads one or more items back into the context
+#[derive(Debug)]
+struct RestoreWork {
+    id: WorkId,
+    also_completes: Vec<WorkId>,
+    paths: Arc<Paths>,
+}
+
+impl RestoreWork {
+    fn restore(&self, context: &Context, id: &WorkId) -> Result<(), Error> {
+        let file = self.paths.target_file(id);
+        // these are only written when the source has some
+        if !file.is_file() {
+            match id {
+                WorkId::Anchor(name) => {
+                    context
+                        .anchors
+                        .set(GlyphAnchors::new(name.clone(), Vec::new()));
+                    return Ok(());
+                }
+                WorkId::ColorPalettes
+                | WorkId::PaintGraph
+                | WorkId::BitmapStrikes
+                | WorkId::SvgDocuments => return Ok(()),
+                _ => (),
+            }
+        }
+        match id {
+            WorkId::StaticMetadata => context.static_metadata.set(read(&file)?),
+            WorkId::PreliminaryGlyphOrder => context.preliminary_glyph_order.set(read(&file)?),
+            WorkId::GlobalMetrics => context.global_metrics.set(read(&file)?),
+            WorkId::Glyph(..) => context.glyphs.set(read(&file)?),
+            WorkId::Anchor(..) => context.anchors.set(read(&file)?),
+            WorkId::Features => context.features.set(read(&file)?),
+            WorkId::KerningGroups => context.kerning_groups.set(read(&file)?),
+            WorkId::KernInstance(..) => context.kerning_at.set(read(&file)?),
+            WorkId::ColorPalettes => context.colors.set(read(&file)?),
+            WorkId::PaintGraph => context.paint_graph.set(read(&file)?),
+            WorkId::BitmapStrikes => context.bitmap_strikes.set(read(&file)?),
+            WorkId::SvgDocuments => context.svg_documents.set(read(&file)?),
+            WorkId::GlyphOrder | WorkId::CompatibilityCheck => {
+                unreachable!("{id:?} is derived from the restored IR, not restored")
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Work<Context, WorkId, Error> for RestoreWork {
+    fn id(&self) -> WorkId {
+        self.id.clone()
+    }
+
+    fn read_access(&self) -> Access<WorkId> {
+        Access::None
+    }
+
+    fn also_completes(&self) -> Vec<WorkId> {
+        self.also_completes.clone()
+    }
+
+    fn exec(&self, context: &Context) -> Result<(), Error> {
+        self.restore(context, &self.id)?;
+        for id in self.also_completes.iter() {
+            self.restore(context, id)?;
+        }
+        Ok(())
+    }
+}
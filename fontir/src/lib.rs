@@ -1,9 +1,13 @@
 //! Intermediate Representation (IR) types for font compilation
 
+pub mod compatibility;
+pub mod contour_order;
 pub mod error;
 pub mod feature_variations;
 pub mod glyph;
 pub mod ir;
+pub mod ir_diff;
+pub mod ir_source;
 pub mod orchestration;
 pub mod paths;
 pub mod source;
@@ -1,7 +1,7 @@
 //! Helps coordinate the graph execution for IR
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::Debug,
     fs::File,
     hash::Hash,
@@ -9,11 +9,13 @@ use std::{
     sync::Arc,
 };
 
-use crate::{error::Error, ir, paths::Paths};
+use crate::{error::Error, ir, paths::Paths, variations::ModelCache};
 use bitflags::bitflags;
 use fontdrasil::{
     coords::NormalizedLocation,
-    orchestration::{Access, AccessControlList, Identifier, IdentifierDiscriminant, Work},
+    orchestration::{
+        Access, AccessControlList, CancellationToken, Identifier, IdentifierDiscriminant, Work,
+    },
     types::GlyphName,
 };
 use parking_lot::RwLock;
@@ -40,12 +42,60 @@ bitflags! {
         const PRODUCTION_NAMES = 0b10000000;
         // If set, all the composite glyphs will be decomposed to simple glyphs
         const DECOMPOSE_COMPONENTS = 0b100000000;
+        // If set, glyph IR is dropped from memory once the BE work that needs it is done.
+        // Only effective if EMIT_IR is also set, evicted glyphs are re-read from disk.
+        const LOW_MEMORY = 0b1000000000;
+        // If set, glyphs whose sources mix components and outlines are decomposed so
+        // they can interpolate, as fontmake does. Otherwise they are reported as incompatible.
+        const REPAIR_COMPATIBILITY = 0b10000000000;
+        // If set, contours are reordered and start points rotated to best match the
+        // default master by geometry
+        const MATCH_CONTOURS = 0b100000000000;
+        // If set, identical lookups are shared and adjacent GSUB subtables with disjoint
+        // coverage are merged, at the cost of no longer matching fontmake byte for byte
+        const OPTIMIZE_LAYOUT = 0b1000000000000;
+        // If set, cmap has no format 4 subtables; everything is mapped by format 12
+        const OMIT_CMAP_FORMAT_4 = 0b10000000000000;
+        // If set, cmap gets a Macintosh Roman (1, 0) subtable for legacy systems
+        const CMAP_MAC_ROMAN = 0b100000000000000;
+        // If set, the {date} placeholder is dropped from templated name strings
+        const OMIT_NAME_DATES = 0b1000000000000000;
+        // If set, vertical metrics the source doesn't set follow the Google Fonts schema
+        const GOOGLE_FONTS_METRICS = 0b10000000000000000;
+        // If set, a PANOSE classification is computed if the source doesn't have one
+        const COMPUTE_PANOSE = 0b100000000000000000;
+        // If set, fsSelection, macStyle, italic angle and subfamily name disagreeing is an error,
+        // otherwise what can be is fixed with a warning
+        const STRICT_STYLE = 0b1000000000000000000;
+        // If set, the user coordinates of fvar named instances are rounded to whole numbers
+        const ROUND_INSTANCE_COORDINATES = 0b10000000000000000000;
+        // If set, gvar stores every delta of simple glyphs rather than letting IUP infer some
+        const NO_IUP = 0b100000000000000000000;
+        // If set, gvar stores every delta of a tuple whenever that is smaller than storing
+        // the points IUP needs, because all-point tuples can share their point numbers
+        const SHARE_GVAR_POINTS = 0b1000000000000000000000;
+        // If set, feature file rules that mention glyphs left out of the glyph order are
+        // dropped rather than failing the build, as when compiling a subset of the glyphs
+        const SUBSET_FEATURES = 0b10000000000000000000000;
+        // If set, a legacy kern table is written alongside GPOS for consumers that don't read GPOS
+        const LEGACY_KERN = 0b100000000000000000000000;
+        // If set, bitmap glyphs are written as CBDT and CBLC rather than sbix
+        const CBDT_BITMAPS = 0b1000000000000000000000000;
+        // If set, the feature file is linted for things that are legal but probably mistakes
+        const LINT_FEATURES = 0b10000000000000000000000000;
+        // If set, glyphs whose components, or their 2x2 transforms, vary across the
+        // designspace are converted to contours, as fontmake does. Otherwise they are an error.
+        const FLATTEN_VARIABLE_COMPONENTS = 0b100000000000000000000000000;
     }
 }
 
 impl Default for Flags {
     fn default() -> Self {
-        Flags::PREFER_SIMPLE_GLYPHS | Flags::PRODUCTION_NAMES
+        Flags::PREFER_SIMPLE_GLYPHS
+            | Flags::PRODUCTION_NAMES
+            | Flags::REPAIR_COMPATIBILITY
+            | Flags::OPTIMIZE_LAYOUT
+            | Flags::FLATTEN_VARIABLE_COMPONENTS
     }
 }
 
@@ -162,6 +212,8 @@ where
     acl: Arc<AccessControlList<I>>,
     persistent_storage: Arc<P>,
     value: Arc<RwLock<HashMap<I, Arc<T>>>>,
+    /// Entries dropped from memory that remain available from persistent storage
+    evicted: Arc<RwLock<HashSet<I>>>,
 }
 
 impl<I, T, P> ContextMap<I, T, P>
@@ -175,6 +227,7 @@ where
             acl,
             persistent_storage,
             value: Default::default(),
+            evicted: Default::default(),
         }
     }
 
@@ -183,10 +236,14 @@ where
             acl,
             persistent_storage: self.persistent_storage.clone(),
             value: self.value.clone(),
+            evicted: self.evicted.clone(),
         }
     }
 
     /// Read an item that might not exist
+    ///
+    /// Only considers items in memory, see [`ContextMap::get`] for items that
+    /// may have been evicted.
     pub fn try_get(&self, id: &I) -> Option<Arc<T>> {
         self.acl.assert_read_access(id);
         self.value.read().get(id).cloned()
@@ -194,14 +251,33 @@ where
 
     /// A copy of all the entries in the map. Values are arc'd so they are cheap, though not free, copies.
     pub fn all(&self) -> Vec<(I, Arc<T>)> {
-        self.value
+        let mut all: Vec<_> = self
+            .value
             .read()
             .iter()
             .map(|(id, v)| {
                 self.acl.assert_read_access(id);
                 (id.clone(), v.clone())
             })
-            .collect()
+            .collect();
+        let evicted: Vec<_> = self.evicted.read().iter().cloned().collect();
+        all.extend(evicted.into_iter().map(|id| {
+            let value = self.get(&id);
+            (id, value)
+        }));
+        all
+    }
+
+    /// Drop the in-memory copy of an item, subsequent reads restore it from persistent storage.
+    ///
+    /// Does nothing if persistent storage is inactive as the item would be lost.
+    pub fn evict(&self, id: &I) {
+        if !self.persistent_storage.active() {
+            return;
+        }
+        if self.value.write().remove(id).is_some() {
+            self.evicted.write().insert(id.clone());
+        }
     }
 
     /// Read item that you are sure must exist. Panic if not.
@@ -217,14 +293,17 @@ where
         // it's *not* in memory but perhaps it's written down?
         if self.persistent_storage.active() {
             if let Some(mut reader) = self.persistent_storage.reader(id) {
-                let restored = T::read(&mut reader);
-                self.value.write().insert(id.clone(), Arc::from(restored));
+                let restored: Arc<T> = Arc::from(T::read(&mut reader));
+                // Something went to the trouble of evicting it, don't bring it back
+                if !self.evicted.read().contains(id) {
+                    self.value.write().insert(id.clone(), restored.clone());
+                }
+                return restored;
             }
         }
 
         // if we still don't have an answer just give up
-        self.try_get(id)
-            .unwrap_or_else(|| panic!("{id:?} is not available"))
+        panic!("{id:?} is not available")
     }
 }
 
@@ -243,6 +322,7 @@ where
             value.write(&mut writer);
         }
 
+        self.evicted.write().remove(&key);
         self.value.write().insert(key, Arc::from(value));
     }
 }
@@ -320,6 +400,12 @@ pub enum WorkId {
     ColorPalettes,
     /// COLR data
     PaintGraph,
+    /// sbix or CBDT data
+    BitmapStrikes,
+    /// SVG data
+    SvgDocuments,
+    /// Check that glyph sources can be interpolated, repairing them if permitted
+    CompatibilityCheck,
 }
 
 impl WorkId {
@@ -343,12 +429,20 @@ impl Identifier for WorkId {
             WorkId::Anchor(..) => "IrAnchor",
             WorkId::ColorPalettes => "IrPalettes",
             WorkId::PaintGraph => "IrPaints",
+            WorkId::BitmapStrikes => "IrBitmaps",
+            WorkId::SvgDocuments => "IrSvg",
+            WorkId::CompatibilityCheck => "IrCompatibilityCheck",
         }
     }
 }
 
 pub type IrWork = dyn Work<Context, WorkId, Error> + Send;
 
+/// The version of the IR written with [Flags::EMIT_IR]
+///
+/// Bump this when a change to IR means IR written before can't be read back.
+pub const IR_VERSION: u32 = 2;
+
 pub struct IrPersistentStorage {
     active: bool,
     pub(crate) paths: Paths,
@@ -371,6 +465,13 @@ impl PersistentStorage<WorkId> for IrPersistentStorage {
     }
 
     fn writer(&self, id: &WorkId) -> Box<dyn Write> {
+        // every build writes static metadata, record which version of IR is alongside
+        if *id == WorkId::StaticMetadata {
+            let file = self.paths.ir_version_file();
+            std::fs::write(&file, IR_VERSION.to_string())
+                .map_err(|e| panic!("Unable to write {file:?} {e}"))
+                .unwrap();
+        }
         let file = self.paths.target_file(id);
         let raw_file = File::create(file.clone())
             .map_err(|e| panic!("Unable to write {file:?} {e}"))
@@ -421,6 +522,14 @@ pub struct Context {
     pub anchors: FeContextMap<ir::GlyphAnchors>,
     pub colors: FeContextItem<ir::ColorPalettes>,
     pub paint_graph: FeContextItem<ir::PaintGraph>,
+    pub bitmap_strikes: FeContextItem<ir::BitmapStrikes>,
+    pub svg_documents: FeContextItem<ir::SvgDocuments>,
+
+    // Not a work result; variation models built on demand and shared by all copies
+    pub variation_models: Arc<ModelCache>,
+
+    // Shared by all copies; long-running work should stop early once it's cancelled
+    pub cancellation: CancellationToken,
 }
 
 pub fn set_cached<T>(lock: &Arc<RwLock<Option<Arc<T>>>>, value: T) {
@@ -444,7 +553,11 @@ impl Context {
             kerning_at: self.kerning_at.clone_with_acl(acl.clone()),
             anchors: self.anchors.clone_with_acl(acl.clone()),
             colors: self.colors.clone_with_acl(acl.clone()),
-            paint_graph: self.paint_graph.clone_with_acl(acl),
+            paint_graph: self.paint_graph.clone_with_acl(acl.clone()),
+            bitmap_strikes: self.bitmap_strikes.clone_with_acl(acl.clone()),
+            svg_documents: self.svg_documents.clone_with_acl(acl),
+            variation_models: self.variation_models.clone(),
+            cancellation: self.cancellation.clone(),
         }
     }
 
@@ -491,7 +604,19 @@ impl Context {
                 acl.clone(),
                 persistent_storage.clone(),
             ),
-            paint_graph: ContextItem::new(WorkId::PaintGraph, acl, persistent_storage),
+            paint_graph: ContextItem::new(
+                WorkId::PaintGraph,
+                acl.clone(),
+                persistent_storage.clone(),
+            ),
+            bitmap_strikes: ContextItem::new(
+                WorkId::BitmapStrikes,
+                acl.clone(),
+                persistent_storage.clone(),
+            ),
+            svg_documents: ContextItem::new(WorkId::SvgDocuments, acl, persistent_storage),
+            variation_models: Default::default(),
+            cancellation: Default::default(),
         }
     }
 
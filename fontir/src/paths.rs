@@ -12,6 +12,7 @@ pub struct Paths {
     anchor_ir_dir: PathBuf,
     glyph_ir_dir: PathBuf,
     ir_input_file: PathBuf,
+    ir_version_file: PathBuf,
 }
 
 impl Paths {
@@ -20,11 +21,13 @@ impl Paths {
         let anchor_ir_dir = build_dir.join("anchor_ir");
         let glyph_ir_dir = build_dir.join("glyph_ir");
         let ir_input_file = build_dir.join("irinput.yml");
+        let ir_version_file = build_dir.join("ir_version.txt");
         Paths {
             build_dir,
             anchor_ir_dir,
             glyph_ir_dir,
             ir_input_file,
+            ir_version_file,
         }
     }
 
@@ -44,6 +47,11 @@ impl Paths {
         &self.ir_input_file
     }
 
+    /// Holds the [crate::orchestration::IR_VERSION] the IR was written with
+    pub fn ir_version_file(&self) -> &Path {
+        &self.ir_version_file
+    }
+
     fn anchor_ir_file(&self, name: &str) -> PathBuf {
         self.anchor_ir_dir.join(string_to_filename(name, ".yml"))
     }
@@ -76,6 +84,11 @@ impl Paths {
             WorkId::KernInstance(location) => self.kern_ir_file(location),
             WorkId::ColorPalettes => self.build_dir.join("colors.yml"),
             WorkId::PaintGraph => self.build_dir.join("paint_graph.yml"),
+            WorkId::BitmapStrikes => self.build_dir.join("bitmap_strikes.yml"),
+            WorkId::SvgDocuments => self.build_dir.join("svg_documents.yml"),
+            WorkId::CompatibilityCheck => {
+                unreachable!("{id:?} only checks the glyph IR, it has no IR of its own")
+            }
         }
     }
 }
@@ -2,10 +2,88 @@
 
 use std::path::Path;
 
-use fontdrasil::coords::NormalizedLocation;
+use fontdrasil::{
+    coords::{NormalizedLocation, UserCoord, UserLocation},
+    types::Axes,
+};
+use serde::{Deserialize, Serialize};
+use write_fonts::types::Tag;
 
 use crate::{error::Error, orchestration::IrWork};
 
+/// A range, in user space, to restrict an axis to.
+///
+/// A range of one value pins the axis.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct AxisLimit {
+    pub tag: Tag,
+    pub min: UserCoord,
+    pub max: UserCoord,
+}
+
+impl AxisLimit {
+    pub fn contains(&self, value: UserCoord) -> bool {
+        self.min <= value && value <= self.max
+    }
+
+    /// Whether a location is within every limit that applies to it
+    pub fn all_contain(limits: &[AxisLimit], location: &UserLocation) -> bool {
+        limits.iter().all(|limit| {
+            location
+                .get(limit.tag)
+                .map_or(true, |value| limit.contains(value))
+        })
+    }
+
+    /// Check every limit names one of the axes and doesn't exclude its default
+    pub fn check(limits: &[AxisLimit], axes: &Axes) -> Result<(), Error> {
+        for limit in limits {
+            let Some(axis) = axes.get(&limit.tag) else {
+                return Err(Error::UnknownEntry("axis", limit.tag.to_string()));
+            };
+            if limit.min > limit.max || !limit.contains(axis.default) {
+                return Err(Error::InvalidEntry(
+                    "axis limit",
+                    format!(
+                        "{}={}:{} must be ordered and include the default, {}",
+                        limit.tag,
+                        limit.min.to_f64(),
+                        limit.max.to_f64(),
+                        axis.default.to_f64()
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check every limit ends on the masters that remain within it
+    ///
+    /// Nothing is instanced, so an axis can only be limited to a range with masters at
+    /// its ends; the remaining masters are then normalized to the limited axes.
+    pub fn check_reached(limits: &[AxisLimit], axes: &Axes) -> Result<(), Error> {
+        for limit in limits {
+            let Some(axis) = axes.get(&limit.tag) else {
+                continue;
+            };
+            if (axis.min, axis.max) != (limit.min, limit.max) {
+                return Err(Error::InvalidEntry(
+                    "axis limit",
+                    format!(
+                        "{}={}:{} doesn't end on masters, the nearest range that does is {}:{}",
+                        limit.tag,
+                        limit.min.to_f64(),
+                        limit.max.to_f64(),
+                        axis.min.to_f64(),
+                        axis.max.to_f64()
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
 /// A source of data from which one could compile a font.
 ///
 /// Expected to be implemented once per font format, e.g. one for .glyphs, one for ufo+ds, etc.
@@ -60,4 +138,29 @@ pub trait Source {
     ///
     /// When run work should update [crate::orchestration::Context] with new [crate::ir::PaintGraph].
     fn create_paint_graph_work(&self) -> Result<Box<IrWork>, Error>;
+
+    /// Create a function that could be called to generate [crate::ir::BitmapStrikes].
+    ///
+    /// When run work should update [crate::orchestration::Context] with new
+    /// [crate::ir::BitmapStrikes], if the source has any bitmaps.
+    fn create_bitmap_strikes_work(&self) -> Result<Box<IrWork>, Error>;
+
+    /// Create a function that could be called to generate [crate::ir::SvgDocuments].
+    ///
+    /// When run work should update [crate::orchestration::Context] with new
+    /// [crate::ir::SvgDocuments], if the source has any SVG glyphs.
+    fn create_svg_documents_work(&self) -> Result<Box<IrWork>, Error>;
+
+    /// Restrict axes to the given ranges by dropping the masters and instances outside them.
+    ///
+    /// Nothing is interpolated, so each limit must end on masters, see
+    /// [AxisLimit::check_reached]. Must be called before any work is created.
+    fn limit_axes(&mut self, limits: &[AxisLimit]) -> Result<(), Error> {
+        if limits.is_empty() {
+            return Ok(());
+        }
+        Err(Error::UnsupportedConstruct(
+            "axis limits for this source format".to_string(),
+        ))
+    }
 }
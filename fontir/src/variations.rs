@@ -1,9 +1,10 @@
 //! Helps manipulate variation data.
 use std::{
     cmp::Ordering,
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     fmt::{Debug, Display},
     ops::{Add, Mul, Sub},
+    sync::Arc,
 };
 
 use fontdrasil::{
@@ -12,6 +13,7 @@ use fontdrasil::{
 };
 use log::{log_enabled, trace};
 use ordered_float::OrderedFloat;
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use write_fonts::{
@@ -208,11 +210,17 @@ impl VariationModel {
             return Ok(Vec::new());
         }
 
+        // as in new, locations may leave out axes, which are then at their default
         let point_seqs: HashMap<_, _> = point_seqs
             .iter()
             .map(|(loc, seq)| {
                 let mut loc = loc.clone();
                 loc.retain(|tag, _| self.axes.contains(tag));
+                for axis in self.axes.iter() {
+                    if !loc.contains(axis.tag) {
+                        loc.insert(axis.tag, NormalizedCoord::new(0.0));
+                    }
+                }
                 (loc, seq)
             })
             .collect();
@@ -286,13 +294,10 @@ impl VariationModel {
     ///
     /// Rust version of <https://github.com/fonttools/fonttools/blob/4ad6b0db/Lib/fontTools/varLib/models.py#L514-L545>
     ///
-    /// Not `pub` yet as it's currently only used for self-tests.
-    ///
     /// TODO: document invariants and what we are returning. Perhaps allow a different
     /// type parameter for the return value so that e.g. absolute Points are returned
     /// when the deltas are Vec2?
-    #[allow(dead_code)]
-    fn interpolate_from_deltas<V>(
+    pub fn interpolate_from_deltas<V>(
         location: &NormalizedLocation,
         deltasets: &[(VariationRegion, Vec<V>)],
     ) -> Vec<V>
@@ -330,6 +335,68 @@ pub enum DeltaError {
     UnknownLocation(NormalizedLocation),
 }
 
+/// Variation models shared by everything that computes deltas for a font.
+///
+/// Sources that don't define every master, such as glyphs with intermediate (brace)
+/// layers or a metric defined only at some masters, need a model of their own. Thousands
+/// of glyphs typically share a handful of distinct location sets, so each model is built
+/// once and handed out to gvar, HVAR, MVAR and anyone else that asks.
+///
+/// Rust version of the `getSubModel` cache in
+/// <https://github.com/fonttools/fonttools/blob/main/Lib/fontTools/varLib/models.py>
+#[derive(Debug, Default)]
+pub struct ModelCache {
+    models: RwLock<HashMap<ModelKey, Arc<VariationModel>>>,
+}
+
+/// The order of a model's axes and the locations it was built from
+type ModelKey = (Vec<Tag>, BTreeSet<NormalizedLocation>);
+
+impl ModelCache {
+    /// The model for locations in the space defined by axes, building it if necessary
+    pub fn get(
+        &self,
+        locations: impl IntoIterator<Item = NormalizedLocation>,
+        axes: &Axes,
+    ) -> Result<Arc<VariationModel>, VariationModelError> {
+        let key = (
+            axes.iter().map(|axis| axis.tag).collect::<Vec<_>>(),
+            locations.into_iter().collect::<BTreeSet<_>>(),
+        );
+        if let Some(model) = self.models.read().get(&key) {
+            return Ok(model.clone());
+        }
+        let model = Arc::new(VariationModel::new(
+            key.1.iter().cloned().collect(),
+            axes.clone(),
+        )?);
+        // if someone beat us to it keep theirs, they are identical anyway
+        Ok(self.models.write().entry(key).or_insert(model).clone())
+    }
+
+    /// Make a model, typically the global one, available without rebuilding it
+    pub fn insert(&self, model: VariationModel) -> Arc<VariationModel> {
+        let key = (
+            model.axes().map(|axis| axis.tag).collect(),
+            model.locations().cloned().collect(),
+        );
+        self.models
+            .write()
+            .entry(key)
+            .or_insert_with(|| Arc::new(model))
+            .clone()
+    }
+
+    /// The number of distinct models built so far
+    pub fn len(&self) -> usize {
+        self.models.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.models.read().is_empty()
+    }
+}
+
 /// Gryffindor!
 ///
 /// Sorts locations, and thus the resulting regions, based on the intuition that from most
@@ -1374,6 +1441,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn deltas_for_locations_missing_axes() {
+        // such as a variable metric in FEA that only mentions some axes
+        let origin = NormalizedLocation::for_pos(&[("wght", 0.0)]);
+        let max_wght = NormalizedLocation::for_pos(&[("wght", 1.0)]);
+        let axes = Axes::for_test(&["wght", "wdth"]);
+        let model =
+            VariationModel::new(HashSet::from([origin.clone(), max_wght.clone()]), axes).unwrap();
+
+        let point_seqs = HashMap::from([(origin, vec![10.0]), (max_wght, vec![12.0])]);
+
+        assert_eq!(
+            vec![
+                (
+                    region(&[("wdth", 0.0, 0.0, 0.0), ("wght", 0.0, 0.0, 0.0)]),
+                    vec![10.0]
+                ),
+                (
+                    region(&[("wdth", 0.0, 0.0, 0.0), ("wght", 0.0, 1.0, 1.0)]),
+                    vec![2.0]
+                ),
+            ],
+            model.deltas(&point_seqs).unwrap()
+        );
+    }
+
     #[derive(Debug, Default, Copy, Clone, PartialEq)]
     struct NoRoundF64(f64);
 
@@ -1594,4 +1687,43 @@ mod tests {
                 .collect::<Vec<_>>()
         );
     }
+
+    #[test]
+    fn model_cache_shares_models_for_the_same_locations() {
+        let axes = Axes::for_test(&["wght", "wdth"]);
+        let global = VariationModel::new(
+            HashSet::from([
+                NormalizedLocation::for_pos(&[("wght", 0.0), ("wdth", 0.0)]),
+                NormalizedLocation::for_pos(&[("wght", 1.0), ("wdth", 0.0)]),
+                NormalizedLocation::for_pos(&[("wght", 0.0), ("wdth", 1.0)]),
+            ]),
+            axes.clone(),
+        )
+        .unwrap();
+        let cache = ModelCache::default();
+        let inserted = cache.insert(global.clone());
+
+        // the global model is found rather than rebuilt
+        let found = cache.get(global.locations().cloned(), &axes).unwrap();
+        assert!(Arc::ptr_eq(&inserted, &found));
+
+        // a glyph with a brace layer needs its own model, but only one of them
+        let sparse = vec![
+            NormalizedLocation::for_pos(&[("wght", 0.0), ("wdth", 0.0)]),
+            NormalizedLocation::for_pos(&[("wght", 0.5), ("wdth", 0.0)]),
+            NormalizedLocation::for_pos(&[("wght", 1.0), ("wdth", 0.0)]),
+            NormalizedLocation::for_pos(&[("wght", 0.0), ("wdth", 1.0)]),
+        ];
+        let first = cache.get(sparse.iter().cloned(), &axes).unwrap();
+        let second = cache.get(sparse.into_iter().rev(), &axes).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(4, first.num_locations());
+        assert_eq!(2, cache.len());
+
+        // the same locations with a different axis order make a different model
+        let reordered = Axes::for_test(&["wdth", "wght"]);
+        let other = cache.get(global.locations().cloned(), &reordered).unwrap();
+        assert!(!Arc::ptr_eq(&inserted, &other));
+        assert_eq!(3, cache.len());
+    }
 }
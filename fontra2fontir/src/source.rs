@@ -161,6 +161,18 @@ impl Source for FontraIrSource {
     ) -> Result<Box<fontir::orchestration::IrWork>, fontir::error::Error> {
         todo!()
     }
+
+    fn create_bitmap_strikes_work(
+        &self,
+    ) -> Result<Box<fontir::orchestration::IrWork>, fontir::error::Error> {
+        todo!()
+    }
+
+    fn create_svg_documents_work(
+        &self,
+    ) -> Result<Box<fontir::orchestration::IrWork>, fontir::error::Error> {
+        todo!()
+    }
 }
 
 #[derive(Debug)]
@@ -10,7 +10,7 @@ extern crate proc_macro;
 use proc_macro2::TokenStream;
 use quote::{quote, quote_spanned};
 use std::iter;
-use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Fields};
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Fields, FieldsNamed, Ident};
 
 mod attrs;
 
@@ -22,6 +22,15 @@ pub fn from_plist(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
         Ok(thing) => thing,
         Err(e) => return e.into_compile_error().into(),
     };
+    let other_case = match other_field(&input) {
+        Ok(Some(other)) => quote! {
+            Some(unrecognized) if tokenizer.keep_unknown_keys => {
+                rec.#other.insert(unrecognized.into(), tokenizer.parse()?);
+            }
+        },
+        Ok(None) => quote! {},
+        Err(e) => return e.into_compile_error().into(),
+    };
 
     let name = input.ident;
 
@@ -40,6 +49,7 @@ pub fn from_plist(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
                     tokenizer.eat(b'=')?;
                     match key.as_str() {
                         #field_cases
+                        #other_case
                         Some(unrecognized) => tokenizer.skip_rec()?,
                         _ => (),
                     };
@@ -51,7 +61,7 @@ pub fn from_plist(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     proc_macro::TokenStream::from(expanded)
 }
 
-fn add_fieldcases(input: &DeriveInput) -> syn::Result<TokenStream> {
+fn named_fields(input: &DeriveInput) -> syn::Result<&FieldsNamed> {
     let Data::Struct(data) = &input.data else {
         return Err(syn::Error::new(
             input.ident.span(),
@@ -65,14 +75,35 @@ fn add_fieldcases(input: &DeriveInput) -> syn::Result<TokenStream> {
             "FromPlist only supports named fields",
         ));
     };
+    Ok(fields)
+}
 
-    let fields = fields
+/// The field marked `#[fromplist(other)]`, if any
+fn other_field(input: &DeriveInput) -> syn::Result<Option<&Ident>> {
+    let mut result = None;
+    for field in named_fields(input)?.named.iter() {
+        if !attrs::FieldAttrs::from_attrs(&field.attrs)?.other {
+            continue;
+        }
+        if result.is_some() {
+            return Err(syn::Error::new(
+                field.span(),
+                "only one field can be marked 'other'",
+            ));
+        }
+        result = field.ident.as_ref();
+    }
+    Ok(result)
+}
+
+fn add_fieldcases(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let fields = named_fields(input)?
         .named
         .iter()
         .filter_map(|f| {
             attrs::FieldAttrs::from_attrs(&f.attrs)
                 .ok()
-                .filter(|a| !a.ignore)
+                .filter(|a| !a.ignore && !a.other)
                 .map(|a| (f, a))
         })
         .flat_map(|(f, attrs)| {
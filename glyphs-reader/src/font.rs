@@ -13,13 +13,13 @@ use std::{fs, path};
 
 use crate::glyphdata::{Category, GlyphData, Subcategory};
 use ascii_plist_derive::FromPlist;
-use fontdrasil::types::WidthClass;
+use fontdrasil::{types::WidthClass, warnings::codes};
 use indexmap::{IndexMap, IndexSet};
 use kurbo::{Affine, Point, Vec2};
 use log::{debug, warn};
 use ordered_float::OrderedFloat;
 use regex::Regex;
-use smol_str::SmolStr;
+use smol_str::{format_smolstr, SmolStr};
 
 use crate::error::Error;
 use crate::plist::{FromPlist, Plist, Token, Tokenizer, VecDelimiters};
@@ -54,8 +54,12 @@ pub struct Font {
     pub axis_mappings: UserToDesignMapping,
     pub virtual_masters: Vec<BTreeMap<String, OrderedFloat<f64>>>,
     pub features: Vec<FeatureSnippet>,
+    /// Features marked automatic that have no code, for the compiler to generate
+    pub automatic_features: BTreeSet<SmolStr>,
     pub names: BTreeMap<String, String>,
     pub instances: Vec<Instance>,
+    /// The stems hinting refers to, whose widths are in each master
+    pub stems: Vec<Stem>,
     pub version_major: i32,
     pub version_minor: u32,
     pub date: Option<String>,
@@ -65,6 +69,9 @@ pub struct Font {
     pub kerning_rtl: Kerning,
 
     pub custom_parameters: CustomParameters,
+
+    /// Top-level keys we don't otherwise use, only kept by [`Font::load_lossless`]
+    pub other_stuff: BTreeMap<String, Plist>,
 }
 
 /// Custom parameter options that can be set on a glyphs font
@@ -110,15 +117,108 @@ pub struct CustomParameters {
     pub vhea_caret_slope_rise: Option<i64>,
     pub vhea_caret_offset: Option<i64>,
     pub meta_table: Option<MetaTableValues>,
+    pub baselines: Option<Baselines>,
     pub dont_use_production_names: Option<bool>,
+    /// Glyphs to rename at export, as (old, new) pairs
+    ///
+    /// If both glyphs exist, they swap outlines instead.
+    pub rename_glyphs: Option<Vec<(SmolStr, SmolStr)>>,
+    /// Glyphs to give new codepoints at export, taking them from any others
+    ///
+    /// An empty set removes the codepoints of the glyph.
+    pub reencode_glyphs: Option<Vec<(SmolStr, BTreeSet<u32>)>>,
+    /// Glyphs not to export, which may use `*` as a wildcard
+    pub remove_glyphs: Option<Vec<SmolStr>>,
+    /// The only glyphs to export, which may use `*` as a wildcard
+    pub keep_glyphs: Option<Vec<SmolStr>>,
+    /// Names of masters to leave out of a variable export
+    pub disable_masters: Option<Vec<SmolStr>>,
     // these fields are parsed via the config, but are stored
     // in the top-level `Font` struct
     pub virtual_masters: Option<Vec<BTreeMap<String, OrderedFloat<f64>>>>,
     pub glyph_order: Option<Vec<SmolStr>>,
     pub gasp_table: Option<BTreeMap<i64, i64>>,
     pub feature_for_feature_variations: Option<SmolStr>,
+    /// If true, glyphs whose masters aren't compatible fail the compile; otherwise
+    /// they are exported without variations
+    pub enforce_compatibility_check: Option<bool>,
+    /// The RGBA colors of each palette, from 'Color Palettes'
+    ///
+    /// Entries correspond across palettes, so every palette has the same length.
+    pub color_palettes: Option<Vec<Vec<[u8; 4]>>>,
+    /// CPAL palette type flags by palette, from 'Color Palette Types'
+    ///
+    /// See [`PALETTE_USABLE_WITH_LIGHT_BACKGROUND`] and [`PALETTE_USABLE_WITH_DARK_BACKGROUND`].
+    pub color_palette_types: Option<Vec<u32>>,
+    /// Every parameter as written in the source, in order
+    ///
+    /// This includes disabled parameters and those we don't know about. It
+    /// isn't written by [`Font::save`], which writes the fields above.
+    pub raw: Vec<CustomParameter>,
+}
+
+/// A custom parameter, as written in the source
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CustomParameter {
+    pub name: SmolStr,
+    pub value: Plist,
+    pub disabled: bool,
 }
 
+impl CustomParameters {
+    /// Every parameter as written in the source, including disabled ones
+    pub fn iter(&self) -> impl Iterator<Item = &CustomParameter> {
+        self.raw.iter()
+    }
+
+    /// The value of the first enabled parameter with the given name
+    pub fn get(&self, name: &str) -> Option<&Plist> {
+        self.get_all(name).next()
+    }
+
+    /// The values of every enabled parameter with the given name
+    ///
+    /// Some parameters, such as 'Virtual Master', can be repeated.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Plist> + 'a {
+        self.raw
+            .iter()
+            .filter(move |param| !param.disabled && param.name == name)
+            .map(|param| &param.value)
+    }
+
+    /// The value of the named parameter, if it is a boolean
+    ///
+    /// Glyphs writes booleans as 0 or 1.
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        self.get(name).and_then(Plist::as_bool)
+    }
+
+    /// The value of the named parameter, if it is an integer
+    pub fn get_int(&self, name: &str) -> Option<i64> {
+        self.get(name).and_then(Plist::as_i64)
+    }
+
+    /// The value of the named parameter, if it is a number
+    pub fn get_float(&self, name: &str) -> Option<f64> {
+        self.get(name).and_then(Plist::as_f64)
+    }
+
+    /// The value of the named parameter, if it is a string
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        self.get(name).and_then(Plist::as_str)
+    }
+
+    /// The value of the named parameter, if it is a list of strings
+    pub fn get_strings(&self, name: &str) -> Option<Vec<SmolStr>> {
+        self.get(name).and_then(Plist::as_vec_of_string)
+    }
+}
+
+/// A 'Color Palette Types' flag for palettes meant for light backgrounds
+pub const PALETTE_USABLE_WITH_LIGHT_BACKGROUND: u32 = 0x0001;
+/// A 'Color Palette Types' flag for palettes meant for dark backgrounds, e.g. dark mode
+pub const PALETTE_USABLE_WITH_DARK_BACKGROUND: u32 = 0x0002;
+
 /// Values for the 'meta Table' custom parameter
 #[derive(Clone, Debug, PartialEq, Hash, Default)]
 pub struct MetaTableValues {
@@ -150,6 +250,68 @@ impl MetaTableValues {
     }
 }
 
+/// Values for the 'Baselines' custom parameter
+///
+/// By axis, "horizontal" or "vertical", then by script tag, the position of each
+/// baseline of the script, e.g. `{horizontal = {latn = {default = romn; romn = 0; ideo = -120;};};}`.
+/// A value that is invalid anywhere is ignored entirely, with a warning.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub struct Baselines {
+    pub horizontal: BTreeMap<SmolStr, ScriptBaselines>,
+    pub vertical: BTreeMap<SmolStr, ScriptBaselines>,
+}
+
+/// The baselines of one script along one axis
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ScriptBaselines {
+    /// The tag of the baseline the script is aligned to by default
+    pub default: SmolStr,
+    /// Baseline tag => position
+    pub positions: BTreeMap<SmolStr, i64>,
+}
+
+impl Baselines {
+    fn from_plist(plist: &Plist) -> Option<Self> {
+        let mut ret = Baselines::default();
+        for (axis, scripts) in plist.as_dict()? {
+            let axis_baselines = match axis.as_str() {
+                "horizontal" => &mut ret.horizontal,
+                "vertical" => &mut ret.vertical,
+                _ => {
+                    log::warn!("Unknown baseline axis '{axis}'");
+                    return None;
+                }
+            };
+            for (script, baselines) in scripts.as_dict()? {
+                let baselines = baselines.as_dict()?;
+                let default = baselines.get("default").and_then(Plist::as_str)?;
+                let positions = baselines
+                    .iter()
+                    .filter(|(baseline, _)| *baseline != "default")
+                    .map(|(baseline, position)| Some((baseline.clone(), position.as_i64()?)))
+                    .collect::<Option<BTreeMap<_, _>>>()?;
+                if !positions.contains_key(default) {
+                    log::warn!("Baselines of '{script}' don't include the default '{default}'");
+                    return None;
+                }
+                axis_baselines.insert(
+                    script.clone(),
+                    ScriptBaselines {
+                        default: default.into(),
+                        positions,
+                    },
+                );
+            }
+        }
+
+        if ret.horizontal.len() + ret.vertical.len() > 0 {
+            Some(ret)
+        } else {
+            None
+        }
+    }
+}
+
 /// master id => { (name or class, name or class) => adjustment }
 #[derive(Clone, Debug, Default, PartialEq, Hash)]
 pub struct Kerning(BTreeMap<String, BTreeMap<(SmolStr, SmolStr), OrderedFloat<f64>>>);
@@ -163,6 +325,11 @@ impl Kerning {
         self.0.keys()
     }
 
+    /// Drop the kerning of a master, e.g. because it isn't exported
+    pub fn remove(&mut self, master_id: &str) {
+        self.0.remove(master_id);
+    }
+
     pub fn iter(
         &self,
     ) -> impl Iterator<Item = (&String, &BTreeMap<(SmolStr, SmolStr), OrderedFloat<f64>>)> {
@@ -271,6 +438,33 @@ pub struct Glyph {
     pub category: Option<Category>,
     pub sub_category: Option<Subcategory>,
     pub production_name: Option<SmolStr>,
+    /// Set via the [`MATH_EXTENDED_SHAPE_KEY`] user data key, the glyph is an
+    /// extended shape in the MATH table
+    pub math_extended_shape: bool,
+    pub note: Option<String>,
+    pub tags: Vec<String>,
+    /// The metrics keys of every layer that doesn't have its own
+    pub metrics_keys: MetricsKeys,
+    /// Keys we don't otherwise use, such as userData, only kept by
+    /// [`Font::load_lossless`]
+    pub other_stuff: BTreeMap<String, Plist>,
+}
+
+/// Formulas that set the spacing of a glyph from that of others
+///
+/// These are as written in the source, e.g. `=H` or `=|o+10`; see
+/// <https://handbook.glyphsapp.com/spacing/#metrics-keys>.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Hash)]
+pub struct MetricsKeys {
+    pub left: Option<SmolStr>,
+    pub right: Option<SmolStr>,
+    pub width: Option<SmolStr>,
+}
+
+impl MetricsKeys {
+    pub fn is_empty(&self) -> bool {
+        self.left.is_none() && self.right.is_none() && self.width.is_none()
+    }
 }
 
 impl Glyph {
@@ -300,7 +494,14 @@ pub struct Layer {
     pub vert_origin: Option<OrderedFloat<f64>>,
     pub shapes: Vec<Shape>,
     pub anchors: Vec<Anchor>,
+    pub guides: Vec<Guide>,
     pub attributes: LayerAttributes,
+    pub metrics_keys: MetricsKeys,
+    /// The image behind the layer, which for an sbix layer is its bitmap
+    pub background_image: Option<BackgroundImage>,
+    /// Keys we don't otherwise use, such as guides and hints, only kept by
+    /// [`Font::load_lossless`]
+    pub other_stuff: BTreeMap<String, Plist>,
 }
 
 impl Layer {
@@ -344,17 +545,74 @@ impl Layer {
             .collect()
     }
 
-    // TODO add is_alternate, is_color, etc.
+    /// Whether this is an alternate, or 'bracket', layer
+    ///
+    /// These are used in place of the master layer in part of the designspace.
+    pub fn is_alternate(&self) -> bool {
+        !self.attributes.axis_rules.is_empty()
+    }
+
+    /// Whether this is any kind of color layer
+    ///
+    /// A color layer may also be a master, intermediate or alternate layer.
+    pub fn is_color(&self) -> bool {
+        self.attributes.color
+            || self.attributes.color_palette.is_some()
+            || self.attributes.svg
+            || self.attributes.sbix_size.is_some()
+    }
+
+    /// Whether this is a full color layer, drawn with gradients and the like
+    pub fn is_full_color(&self) -> bool {
+        self.attributes.color
+    }
+
+    /// The palette index of a color palette layer, as used by COLRv0
+    ///
+    /// [`FOREGROUND_PALETTE_INDEX`] means the layer uses the text color.
+    pub fn color_palette_index(&self) -> Option<u16> {
+        self.attributes.color_palette
+    }
+
+    /// Whether this is an SVG layer
+    pub fn is_svg(&self) -> bool {
+        self.attributes.svg
+    }
+
+    /// The pixels per em of an sbix bitmap layer
+    pub fn sbix_size(&self) -> Option<i64> {
+        self.attributes.sbix_size
+    }
 }
 
+/// The palette index of a color palette layer drawn in the text color
+pub const FOREGROUND_PALETTE_INDEX: u16 = 0xFFFF;
+
 #[derive(Clone, Default, Debug, PartialEq, Hash)]
 pub struct LayerAttributes {
     pub coordinates: Vec<OrderedFloat<f64>>,
     pub color: bool,
+    /// The palette index of a color palette layer, or [`FOREGROUND_PALETTE_INDEX`]
+    pub color_palette: Option<u16>,
+    pub sbix_size: Option<i64>,
+    pub svg: bool,
     // in the same order that axes are declared for the font
     pub axis_rules: Vec<AxisRule>,
 }
 
+impl LayerAttributes {
+    /// Parse the value of a color palette layer's 'colorPalette' attribute
+    ///
+    /// This is either a palette index or "*", for the text color.
+    fn parse_color_palette(value: &Plist) -> Option<u16> {
+        match value {
+            Plist::String(s) if s == "*" => Some(FOREGROUND_PALETTE_INDEX),
+            Plist::String(s) => s.parse().ok(),
+            _ => value.as_i64().and_then(|idx| idx.try_into().ok()),
+        }
+    }
+}
+
 #[derive(Clone, Default, FromPlist, Debug, PartialEq, Hash)]
 pub struct AxisRule {
     // if missing, assume default min/max for font
@@ -390,6 +648,9 @@ impl FromPlist for LayerAttributes {
     fn parse(tokenizer: &mut Tokenizer<'_>) -> Result<Self, crate::plist::Error> {
         let mut coordinates = Vec::new();
         let mut color = false;
+        let mut color_palette = None;
+        let mut sbix_size = None;
+        let mut svg = false;
         let mut axis_rules = Vec::new();
 
         tokenizer.eat(b'{')?;
@@ -404,6 +665,15 @@ impl FromPlist for LayerAttributes {
             match key.as_str() {
                 "coordinates" => coordinates = tokenizer.parse()?,
                 "color" => color = tokenizer.parse()?,
+                "colorPalette" => {
+                    let value: Plist = tokenizer.parse()?;
+                    color_palette = LayerAttributes::parse_color_palette(&value);
+                    if color_palette.is_none() {
+                        log::warn!("invalid colorPalette layer attribute '{value:?}'");
+                    }
+                }
+                "sbixSize" => sbix_size = Some(tokenizer.parse()?),
+                "svg" => svg = tokenizer.parse()?,
                 "axisRules" => axis_rules = tokenizer.parse()?,
                 // skip unsupported attributes for now
                 // TODO: match the others
@@ -415,6 +685,9 @@ impl FromPlist for LayerAttributes {
         Ok(LayerAttributes {
             coordinates,
             color,
+            color_palette,
+            sbix_size,
+            svg,
             axis_rules,
         })
     }
@@ -497,7 +770,7 @@ impl Shape {
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
-enum FormatVersion {
+pub(crate) enum FormatVersion {
     #[default]
     V2,
     V3,
@@ -507,9 +780,9 @@ enum FormatVersion {
 // Types chosen specifically to accomodate plist translation.
 #[derive(Default, Debug, PartialEq, FromPlist)]
 #[allow(non_snake_case)]
-struct RawFont {
+pub(crate) struct RawFont {
     #[fromplist(key = ".formatVersion")]
-    format_version: FormatVersion,
+    pub(crate) format_version: FormatVersion,
     units_per_em: Option<i64>,
     metrics: Vec<RawMetric>,
     family_name: String,
@@ -534,6 +807,16 @@ struct RawFont {
     kerning_RTL: Kerning,
     custom_parameters: RawCustomParameters,
     numbers: Vec<NumberName>,
+    stems: Vec<Stem>,
+    #[fromplist(other)]
+    other_stuff: BTreeMap<String, Plist>,
+
+    // set when loading losslessly, for the data that has a field but isn't part of the model
+    #[fromplist(ignore)]
+    lossless: bool,
+    /// The names of glyphs that are parsed later, see [`crate::LazyFont`]
+    #[fromplist(ignore)]
+    pub(crate) deferred_glyph_names: Vec<SmolStr>,
 }
 
 #[derive(Default, Debug, PartialEq, FromPlist)]
@@ -541,6 +824,15 @@ struct NumberName {
     name: SmolStr,
 }
 
+/// A stem, whose width is set by each master
+///
+/// See <https://handbook.glyphsapp.com/masters/#stems>
+#[derive(Clone, Default, Debug, PartialEq, Eq, Hash, FromPlist)]
+pub struct Stem {
+    pub name: SmolStr,
+    pub horizontal: bool,
+}
+
 // we use a vec of tuples instead of a map because there can be multiple
 // values for the same name (e.g. 'Virtual Master')
 #[derive(Clone, Default, Debug, PartialEq, Eq, Hash)]
@@ -603,6 +895,10 @@ trait PlistParamsExt {
     fn as_axis_mappings(&self) -> Option<Vec<AxisMapping>>;
     fn as_virtual_master(&self) -> Option<BTreeMap<String, OrderedFloat<f64>>>;
     fn as_gasp_table(&self) -> Option<BTreeMap<i64, i64>>;
+    fn as_glyph_renames(&self) -> Option<Vec<(SmolStr, SmolStr)>>;
+    fn as_glyph_reencodings(&self) -> Option<Vec<(SmolStr, BTreeSet<u32>)>>;
+    fn as_color_palettes(&self) -> Option<Vec<Vec<[u8; 4]>>>;
+    fn as_palette_types(&self) -> Option<Vec<u32>>;
 }
 
 impl PlistParamsExt for Plist {
@@ -727,6 +1023,100 @@ impl PlistParamsExt for Plist {
                 .collect(),
         )
     }
+
+    // entries look like "a=a.ss01"
+    fn as_glyph_renames(&self) -> Option<Vec<(SmolStr, SmolStr)>> {
+        self.as_array()?
+            .iter()
+            .map(|entry| {
+                let (old, new) = entry.as_str()?.split_once('=')?;
+                let (old, new) = (old.trim(), new.trim());
+                (!old.is_empty() && !new.is_empty()).then(|| (old.into(), new.into()))
+            })
+            .collect()
+    }
+
+    // entries look like "a.sc=E000" or "ae=00E6,01FD"; nothing after the '='
+    // removes the codepoints of the glyph
+    fn as_glyph_reencodings(&self) -> Option<Vec<(SmolStr, BTreeSet<u32>)>> {
+        self.as_array()?
+            .iter()
+            .map(|entry| {
+                let (name, codepoints) = entry.as_str()?.split_once('=')?;
+                let codepoints = codepoints
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|cp| !cp.is_empty())
+                    .map(|cp| u32::from_str_radix(cp, 16).ok())
+                    .collect::<Option<_>>()?;
+                Some((name.trim().into(), codepoints))
+            })
+            .collect()
+    }
+
+    // Glyphs 3 writes colors as lists like (255,0,0,255), Glyphs 2 as strings
+    // like "255,0,0,255"; either may be a (gray, alpha) pair
+    fn as_color_palettes(&self) -> Option<Vec<Vec<[u8; 4]>>> {
+        let palettes: Vec<Vec<[u8; 4]>> = self
+            .as_array()?
+            .iter()
+            .map(|palette| {
+                palette
+                    .as_array()?
+                    .iter()
+                    .map(|color| {
+                        let values = color.as_vec_of_ints().or_else(|| {
+                            color
+                                .as_str()?
+                                .split(',')
+                                .map(|v| v.trim().parse::<i64>().ok())
+                                .collect()
+                        })?;
+                        let values = values
+                            .into_iter()
+                            .map(|v| u8::try_from(v).ok())
+                            .collect::<Option<Vec<_>>>()?;
+                        match *values.as_slice() {
+                            [gray, alpha] => Some([gray, gray, gray, alpha]),
+                            [r, g, b, a] => Some([r, g, b, a]),
+                            _ => None,
+                        }
+                    })
+                    .collect()
+            })
+            .collect::<Option<_>>()?;
+        if palettes
+            .iter()
+            .any(|palette| palette.len() != palettes[0].len())
+        {
+            log::warn!("'Color Palettes' must all have the same number of colors");
+            return None;
+        }
+        Some(palettes)
+    }
+
+    // entries are flags, or "light", "dark" or "light,dark"
+    fn as_palette_types(&self) -> Option<Vec<u32>> {
+        self.as_array()?
+            .iter()
+            .map(|entry| {
+                if let Some(flags) = entry.as_i64() {
+                    return u32::try_from(flags).ok();
+                }
+                entry
+                    .as_str()?
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|kind| !kind.is_empty())
+                    .map(|kind| match kind {
+                        "light" => Some(PALETTE_USABLE_WITH_LIGHT_BACKGROUND),
+                        "dark" => Some(PALETTE_USABLE_WITH_DARK_BACKGROUND),
+                        _ => None,
+                    })
+                    .try_fold(0, |flags, flag| Some(flags | flag?))
+            })
+            .collect()
+    }
 }
 
 impl RawCustomParameters {
@@ -841,9 +1231,19 @@ impl RawCustomParameters {
                 "meta Table" => {
                     add_and_report_issues!(meta_table, MetaTableValues::from_plist)
                 }
+                "Baselines" => add_and_report_issues!(baselines, Baselines::from_plist),
                 "Don't use Production Names" => {
                     add_and_report_issues!(dont_use_production_names, Plist::as_bool)
                 }
+                "Rename Glyphs" => add_and_report_issues!(rename_glyphs, Plist::as_glyph_renames),
+                "Reencode Glyphs" => {
+                    add_and_report_issues!(reencode_glyphs, Plist::as_glyph_reencodings)
+                }
+                "Remove Glyphs" => add_and_report_issues!(remove_glyphs, Plist::as_vec_of_string),
+                "Keep Glyphs" => add_and_report_issues!(keep_glyphs, Plist::as_vec_of_string),
+                "Disable Masters" => {
+                    add_and_report_issues!(disable_masters, Plist::as_vec_of_string)
+                }
                 // these might need to be handled? they're in the same list as
                 // the items above:
                 // https://github.com/googlefonts/glyphsLib/blob/74c63244fdb/Lib/glyphsLib/builder/custom_params.py#L429
@@ -865,11 +1265,36 @@ impl RawCustomParameters {
                 "Feature for Feature Variations" => {
                     add_and_report_issues!(feature_for_feature_variations, Plist::as_str, into)
                 }
-                _ => log::warn!("unknown custom parameter '{name}'"),
+                "Enforce Compatibility Check" => {
+                    add_and_report_issues!(enforce_compatibility_check, Plist::as_bool)
+                }
+                "Color Palettes" => {
+                    add_and_report_issues!(color_palettes, Plist::as_color_palettes)
+                }
+                "Color Palette Types" => {
+                    add_and_report_issues!(color_palette_types, Plist::as_palette_types)
+                }
+                // applied from raw along with the other names of the exported instance
+                "familyName" | "Name Table Entry" => (),
+                // Glyphs 2 instance names, converted to properties
+                "preferredFamilyName" | "preferredSubfamilyName" => (),
+                _ => log::warn!(
+                    code = codes::UNKNOWN_CUSTOM_PARAMETER;
+                    "unknown custom parameter '{name}'"
+                ),
             }
         }
         params.panose = panose.or(panose_old);
         params.virtual_masters = Some(virtual_masters).filter(|x| !x.is_empty());
+        params.raw = self
+            .0
+            .iter()
+            .map(|param| CustomParameter {
+                name: param.name.clone(),
+                value: param.value.clone(),
+                disabled: param.disabled == Some(true),
+            })
+            .collect();
         Ok(params)
     }
 
@@ -920,14 +1345,14 @@ struct RawMetric {
 }
 
 #[derive(Default, Clone, Debug, PartialEq, Eq, Hash, FromPlist)]
-struct RawName {
-    key: String,
-    value: Option<String>,
-    values: Vec<RawNameValue>,
+pub(crate) struct RawName {
+    pub(crate) key: String,
+    pub(crate) value: Option<String>,
+    pub(crate) values: Vec<RawNameValue>,
 }
 
 impl RawName {
-    fn is_empty(&self) -> bool {
+    pub(crate) fn is_empty(&self) -> bool {
         self.value.is_none() && self.values.is_empty()
     }
 
@@ -956,9 +1381,9 @@ impl RawName {
 }
 
 #[derive(Default, Clone, Debug, PartialEq, Eq, Hash, FromPlist)]
-struct RawNameValue {
-    language: String,
-    value: String,
+pub(crate) struct RawNameValue {
+    pub(crate) language: String,
+    pub(crate) value: String,
 }
 
 #[derive(Default, Clone, Debug, PartialEq, Eq, Hash, FromPlist)]
@@ -985,7 +1410,7 @@ pub struct Axis {
 }
 
 #[derive(Default, Clone, Debug, PartialEq, FromPlist)]
-struct RawGlyph {
+pub(crate) struct RawGlyph {
     layers: Vec<RawLayer>,
     glyphname: SmolStr,
     export: Option<bool>,
@@ -998,7 +1423,17 @@ struct RawGlyph {
     sub_category: Option<SmolStr>,
     #[fromplist(alt_name = "production")]
     production_name: Option<SmolStr>,
-    #[fromplist(ignore)]
+    user_data: Option<Plist>,
+    note: Option<String>,
+    tags: Vec<String>,
+    #[fromplist(alt_name = "leftMetricsKey")]
+    metric_left: Option<SmolStr>,
+    #[fromplist(alt_name = "rightMetricsKey")]
+    metric_right: Option<SmolStr>,
+    #[fromplist(alt_name = "widthMetricsKey")]
+    metric_width: Option<SmolStr>,
+    background_image: Option<RawBackgroundImage>,
+    #[fromplist(other)]
     other_stuff: BTreeMap<String, Plist>,
 }
 
@@ -1014,9 +1449,17 @@ struct RawLayer {
     paths: Vec<Path>,
     components: Vec<Component>,
     anchors: Vec<RawAnchor>,
+    #[fromplist(alt_name = "guideLines")]
+    guides: Vec<RawGuide>,
     #[fromplist(alt_name = "attr")]
     attributes: LayerAttributes,
-    #[fromplist(ignore)]
+    #[fromplist(alt_name = "leftMetricsKey")]
+    metric_left: Option<SmolStr>,
+    #[fromplist(alt_name = "rightMetricsKey")]
+    metric_right: Option<SmolStr>,
+    #[fromplist(alt_name = "widthMetricsKey")]
+    metric_width: Option<SmolStr>,
+    #[fromplist(other)]
     other_stuff: BTreeMap<String, Plist>,
 }
 
@@ -1062,7 +1505,18 @@ impl RawLayer {
         if !brace_coordinates.is_empty() {
             self.attributes.coordinates = brace_coordinates;
         }
-        // TODO: handle 'bracket' layers and other attributes
+        // color layers are also identified by name, e.g. "Color 1" or "iColor 64"
+        // https://github.com/googlefonts/glyphsLib/blob/c4db6b981d5/Lib/glyphsLib/classes.py#L3950
+        let name = self.name.trim();
+        if let Some(palette) = name.strip_prefix("Color ") {
+            self.attributes.color_palette =
+                LayerAttributes::parse_color_palette(&Plist::String(palette.trim().into()));
+        } else if let Some(size) = name.strip_prefix("iColor ") {
+            self.attributes.sbix_size = size.trim().parse().ok();
+        } else if name == "svg" {
+            self.attributes.svg = true;
+        }
+        // bracket layers are handled in build()
     }
 }
 
@@ -1070,7 +1524,7 @@ impl RawLayer {
 ///
 /// <https://github.com/schriftgestalt/GlyphsSDK/blob/Glyphs3/GlyphsFileFormat/GlyphsFileFormatv3.md#differences-between-version-2>
 #[derive(Default, Clone, Debug, PartialEq, FromPlist)]
-struct RawShape {
+pub(crate) struct RawShape {
     // TODO: add numerous unsupported attributes
 
     // When I'm a path
@@ -1086,7 +1540,7 @@ struct RawShape {
     // for components, an optional name to rename an anchor
     // on the target glyph during anchor propagation
     anchor: Option<SmolStr>,
-    transform: Option<String>, // v2
+    transform: Option<Affine>, // v2
     pos: Vec<f64>,             // v3
     angle: Option<f64>,        // v3
     scale: Vec<f64>,           // v3
@@ -1203,14 +1657,103 @@ impl Hash for Anchor {
     }
 }
 
+#[derive(Default, Clone, Debug, PartialEq, FromPlist)]
+struct RawGuide {
+    name: Option<String>,
+    pos: Option<Point>,       // v3
+    position: Option<String>, // v2
+    angle: Option<OrderedFloat<f64>>,
+    locked: Option<bool>,
+    #[fromplist(other)]
+    other_stuff: BTreeMap<String, Plist>,
+}
+
+/// A guide line, on a layer or across all the glyphs of a master
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Guide {
+    pub name: Option<String>,
+    pub pos: Point,
+    /// In degrees, counter-clockwise from horizontal
+    pub angle: OrderedFloat<f64>,
+    pub locked: bool,
+    /// Keys we don't otherwise use, such as alignment, only kept by
+    /// [`Font::load_lossless`]
+    pub other_stuff: BTreeMap<String, Plist>,
+}
+
+impl Hash for Guide {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        PointForEqAndHash::new(self.pos).hash(state);
+        self.angle.hash(state);
+        self.locked.hash(state);
+        self.other_stuff.hash(state);
+    }
+}
+
+#[derive(Default, Clone, Debug, PartialEq, FromPlist)]
+struct RawBackgroundImage {
+    image_path: Option<String>,
+    pos: Option<Point>,
+    #[fromplist(other)]
+    other_stuff: BTreeMap<String, Plist>,
+}
+
+/// An image placed in a layer, as used for the bitmaps of sbix layers
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BackgroundImage {
+    /// The image file, relative to the directory of the .glyphs file
+    pub path: String,
+    /// Where the bottom left of the image sits in the layer
+    pub pos: Point,
+    /// Keys we don't otherwise use, such as crop, only kept by
+    /// [`Font::load_lossless`]
+    pub other_stuff: BTreeMap<String, Plist>,
+}
+
+impl Hash for BackgroundImage {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.path.hash(state);
+        PointForEqAndHash::new(self.pos).hash(state);
+        self.other_stuff.hash(state);
+    }
+}
+
+impl From<RawGuide> for Guide {
+    fn from(raw: RawGuide) -> Guide {
+        let pos = if let Some(pos) = raw.pos {
+            pos
+        } else if let Some(raw) = raw.position {
+            Point::parse_plist(&raw).unwrap_or_default()
+        } else {
+            Point::ZERO
+        };
+        Guide {
+            name: raw.name,
+            pos,
+            angle: raw.angle.unwrap_or_default(),
+            locked: raw.locked.unwrap_or_default(),
+            other_stuff: raw.other_stuff,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Hash)]
 pub struct FontMaster {
     pub id: String,
     pub name: String,
     pub axes_values: Vec<OrderedFloat<f64>>,
-    metric_values: BTreeMap<String, MetricValue>,
+    pub(crate) metric_values: BTreeMap<String, MetricValue>,
     pub number_values: BTreeMap<SmolStr, OrderedFloat<f64>>,
+    /// Stem widths, by the name of the stem in [`Font::stems`]
+    pub stem_values: BTreeMap<SmolStr, OrderedFloat<f64>>,
     pub custom_parameters: CustomParameters,
+    /// MATH constants by name, e.g. AxisHeight, from the [`MATH_CONSTANTS_KEY`] user data key
+    pub math_constants: BTreeMap<SmolStr, OrderedFloat<f64>>,
+    /// The global guides, shown on every glyph in the master
+    pub guides: Vec<Guide>,
+    /// Keys we don't otherwise use, only kept by [`Font::load_lossless`]
+    pub other_stuff: BTreeMap<String, Plist>,
 }
 
 impl FontMaster {
@@ -1239,6 +1782,21 @@ impl FontMaster {
     pub fn italic_angle(&self) -> Option<f64> {
         self.read_metric("italic angle")
     }
+
+    /// The alignment zones, as (position, overshoot) pairs sorted by position
+    ///
+    /// These are the metrics with an overshoot, which may be negative.
+    pub fn alignment_zones(&self) -> Vec<(OrderedFloat<f64>, OrderedFloat<f64>)> {
+        let mut zones = self
+            .metric_values
+            .values()
+            .filter(|metric| metric.over != 0.0)
+            .map(|metric| (metric.pos, metric.over))
+            .collect::<Vec<_>>();
+        zones.sort();
+        zones.dedup();
+        zones
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Hash, FromPlist)]
@@ -1279,8 +1837,16 @@ struct RawFontMaster {
 
     custom_parameters: RawCustomParameters,
     number_values: Vec<OrderedFloat<f64>>,
+    stem_values: Vec<OrderedFloat<f64>>,
+    horizontal_stems: Vec<OrderedFloat<f64>>, // v2
+    vertical_stems: Vec<OrderedFloat<f64>>,   // v2
 
-    #[fromplist(ignore)]
+    #[fromplist(alt_name = "guideLines")]
+    guides: Vec<RawGuide>,
+
+    user_data: Option<Plist>,
+
+    #[fromplist(other)]
     other_stuff: BTreeMap<String, Plist>,
 }
 
@@ -1292,8 +1858,8 @@ struct RawMetricValue {
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct MetricValue {
-    pos: OrderedFloat<f64>,
-    over: OrderedFloat<f64>,
+    pub(crate) pos: OrderedFloat<f64>,
+    pub(crate) over: OrderedFloat<f64>,
 }
 
 impl From<RawMetricValue> for MetricValue {
@@ -1314,7 +1880,7 @@ pub struct Instance {
     pub axis_mappings: BTreeMap<String, AxisUserToDesignMap>,
     pub axes_values: Vec<OrderedFloat<f64>>,
     pub custom_parameters: CustomParameters,
-    properties: Vec<RawName>, // used for name resolution
+    pub(crate) properties: Vec<RawName>, // used for name resolution
 }
 
 /// <https://github.com/googlefonts/glyphsLib/blob/6f243c1f732ea1092717918d0328f3b5303ffe56/Lib/glyphsLib/classes.py#L150>
@@ -1573,25 +2139,84 @@ fn v2_to_v3_name(v2_prop: Option<&str>, v3_name: &str) -> Option<RawName> {
     })
 }
 
+/// Parse, keeping unknown keys if `lossless`
+fn parse_source<T: FromPlist>(content: &str, lossless: bool) -> Result<T, crate::plist::Error> {
+    if lossless {
+        T::parse_plist_lossless(content)
+    } else {
+        T::parse_plist(content)
+    }
+}
+
+/// Order the glyphs of a .glyphspackage by its order.plist
+///
+/// Glyphs that aren't listed there follow, sorted by name.
+pub(crate) fn order_package_glyphs<T>(
+    glyphs_package: &path::Path,
+    mut glyphs: HashMap<SmolStr, T>,
+) -> Result<Vec<T>, Error> {
+    let order_file = glyphs_package.join("order.plist");
+    let mut ordered_glyphs = Vec::new();
+    if order_file.exists() {
+        let order_data = fs::read_to_string(&order_file).map_err(Error::IoError)?;
+        let order_plist = Plist::parse(&order_data)
+            .map_err(|e| Error::ParseError(order_file.to_path_buf(), e.to_string()))?;
+        let order = order_plist
+            .expect_array()
+            .map_err(|e| Error::ParseError(order_file.to_path_buf(), e.to_string()))?;
+        for glyph_name in order {
+            let glyph_name = glyph_name
+                .expect_string()
+                .map_err(|e| Error::ParseError(order_file.to_path_buf(), e.to_string()))?;
+            if let Some(glyph) = glyphs.remove(glyph_name.as_str()) {
+                ordered_glyphs.push(glyph);
+            }
+        }
+    }
+    // sort the glyphs not in order.plist by their name
+    let mut glyph_names: Vec<_> = glyphs.keys().cloned().collect();
+    glyph_names.sort();
+    ordered_glyphs.extend(
+        glyph_names
+            .into_iter()
+            .map(|glyph_name| glyphs.remove(&glyph_name).unwrap()),
+    );
+    assert!(glyphs.is_empty());
+    Ok(ordered_glyphs)
+}
+
 impl RawFont {
     pub fn load_from_string(raw_content: &str) -> Result<Self, crate::plist::Error> {
+        Self::load_from_string_impl(raw_content, false)
+    }
+
+    fn load_from_string_impl(
+        raw_content: &str,
+        lossless: bool,
+    ) -> Result<Self, crate::plist::Error> {
         let raw_content = preprocess_unparsed_plist(raw_content);
-        Self::parse_plist(&raw_content)
+        let mut raw_font: RawFont = parse_source(&raw_content, lossless)?;
+        raw_font.lossless = lossless;
+        Ok(raw_font)
     }
 
     pub fn load(glyphs_file: &path::Path) -> Result<Self, Error> {
+        Self::load_impl(glyphs_file, false)
+    }
+
+    fn load_impl(glyphs_file: &path::Path, lossless: bool) -> Result<Self, Error> {
         if glyphs_file.extension() == Some(OsStr::new("glyphspackage")) {
-            return Self::load_package(glyphs_file);
+            return Self::load_package(glyphs_file, lossless);
         }
 
         debug!("Read glyphs {glyphs_file:?}");
         let raw_content = fs::read_to_string(glyphs_file).map_err(Error::IoError)?;
-        Self::load_from_string(&raw_content)
+        Self::load_from_string_impl(&raw_content, lossless)
             .map_err(|e| Error::ParseError(glyphs_file.to_path_buf(), e.to_string()))
     }
 
     /// load from a .glyphspackage
-    fn load_package(glyphs_package: &path::Path) -> Result<RawFont, Error> {
+    fn load_package(glyphs_package: &path::Path, lossless: bool) -> Result<RawFont, Error> {
         if !glyphs_package.is_dir() {
             return Err(Error::NotAGlyphsPackage(glyphs_package.to_path_buf()));
         }
@@ -1599,8 +2224,9 @@ impl RawFont {
 
         let fontinfo_file = glyphs_package.join("fontinfo.plist");
         let fontinfo_data = fs::read_to_string(&fontinfo_file).map_err(Error::IoError)?;
-        let mut raw_font = RawFont::parse_plist(&fontinfo_data)
+        let mut raw_font: RawFont = parse_source(&fontinfo_data, lossless)
             .map_err(|e| Error::ParseError(fontinfo_file.to_path_buf(), format!("{e}")))?;
+        raw_font.lossless = lossless;
 
         let mut glyphs: HashMap<SmolStr, RawGlyph> = HashMap::new();
         let glyphs_dir = glyphs_package.join("glyphs");
@@ -1611,7 +2237,7 @@ impl RawFont {
                 if path.extension() == Some(OsStr::new("glyph")) {
                     let glyph_data = fs::read_to_string(&path).map_err(Error::IoError)?;
                     let glyph_data = preprocess_unparsed_plist(&glyph_data);
-                    let glyph = RawGlyph::parse_plist(&glyph_data)
+                    let glyph: RawGlyph = parse_source(&glyph_data, lossless)
                         .map_err(|e| Error::ParseError(path.clone(), e.to_string()))?;
                     if glyph.glyphname.is_empty() {
                         return Err(Error::ParseError(
@@ -1624,35 +2250,7 @@ impl RawFont {
             }
         }
 
-        // if order.plist file exists, read it and sort glyphs in it accordingly
-        let order_file = glyphs_package.join("order.plist");
-        let mut ordered_glyphs = Vec::new();
-        if order_file.exists() {
-            let order_data = fs::read_to_string(&order_file).map_err(Error::IoError)?;
-            let order_plist = Plist::parse(&order_data)
-                .map_err(|e| Error::ParseError(order_file.to_path_buf(), e.to_string()))?;
-            let order = order_plist
-                .expect_array()
-                .map_err(|e| Error::ParseError(order_file.to_path_buf(), e.to_string()))?;
-            for glyph_name in order {
-                let glyph_name = glyph_name
-                    .expect_string()
-                    .map_err(|e| Error::ParseError(order_file.to_path_buf(), e.to_string()))?;
-                if let Some(glyph) = glyphs.remove(glyph_name.as_str()) {
-                    ordered_glyphs.push(glyph);
-                }
-            }
-        }
-        // sort the glyphs not in order.plist by their name
-        let mut glyph_names: Vec<_> = glyphs.keys().cloned().collect();
-        glyph_names.sort();
-        ordered_glyphs.extend(
-            glyph_names
-                .into_iter()
-                .map(|glyph_name| glyphs.remove(&glyph_name).unwrap()),
-        );
-        assert!(glyphs.is_empty());
-        raw_font.glyphs = ordered_glyphs;
+        raw_font.glyphs = order_package_glyphs(glyphs_package, glyphs)?;
 
         // ignore UIState.plist which stuff like displayStrings that are not used by us
 
@@ -1968,11 +2566,56 @@ impl RawFont {
                 instance.custom_parameters.string("postscriptFontName"),
                 "postscriptFontName",
             ));
+            instance.properties.extend(v2_to_v3_name(
+                instance.custom_parameters.string("preferredFamilyName"),
+                "preferredFamilyNames",
+            ));
+            instance.properties.extend(v2_to_v3_name(
+                instance.custom_parameters.string("preferredSubfamilyName"),
+                "preferredSubfamilyNames",
+            ));
         }
 
         Ok(())
     }
 
+    /// Glyphs 2 masters list horizontal and vertical stems, Glyphs 3 names them
+    /// in the font and lists their widths in each master
+    fn v2_to_v3_stems(&mut self) {
+        let n_horizontal = (self.font_master.iter())
+            .map(|m| m.horizontal_stems.len())
+            .max()
+            .unwrap_or_default();
+        let n_vertical = (self.font_master.iter())
+            .map(|m| m.vertical_stems.len())
+            .max()
+            .unwrap_or_default();
+        if n_horizontal + n_vertical == 0 {
+            return;
+        }
+        self.stems = (0..n_horizontal)
+            .map(|i| Stem {
+                name: format_smolstr!("hStem{i}"),
+                horizontal: true,
+            })
+            .chain((0..n_vertical).map(|i| Stem {
+                name: format_smolstr!("vStem{i}"),
+                horizontal: false,
+            }))
+            .collect();
+        for master in self.font_master.iter_mut() {
+            // a master with fewer stems than another has no value for the rest
+            let horizontal = std::mem::take(&mut master.horizontal_stems);
+            let vertical = std::mem::take(&mut master.vertical_stems);
+            master.stem_values = horizontal
+                .into_iter()
+                .chain(std::iter::repeat(OrderedFloat(0.0)))
+                .take(n_horizontal)
+                .chain(vertical)
+                .collect();
+        }
+    }
+
     fn v2_to_v3_layer_attributes(&mut self) {
         for raw_glyph in self.glyphs.iter_mut() {
             for layer in raw_glyph.layers.iter_mut() {
@@ -1989,6 +2632,7 @@ impl RawFont {
         self.v2_to_v3_instances()?;
         self.v2_to_v3_names()?; // uses instances
         self.v2_to_v3_layer_attributes();
+        self.v2_to_v3_stems();
         Ok(())
     }
 }
@@ -2001,8 +2645,8 @@ fn parse_alignment_zone(zone: &str) -> Option<(OrderedFloat<f64>, OrderedFloat<f
     Some((OrderedFloat(one as f64), OrderedFloat(two as f64)))
 }
 
-fn make_glyph_order(glyphs: &[RawGlyph], custom_order: Option<Vec<SmolStr>>) -> Vec<SmolStr> {
-    let mut valid_names: HashSet<_> = glyphs.iter().map(|g| &g.glyphname).collect();
+fn make_glyph_order(glyph_names: &[SmolStr], custom_order: Option<Vec<SmolStr>>) -> Vec<SmolStr> {
+    let mut valid_names: HashSet<_> = glyph_names.iter().collect();
     let mut glyph_order = Vec::new();
 
     // Add all valid glyphOrder entries in order
@@ -2015,10 +2659,10 @@ fn make_glyph_order(glyphs: &[RawGlyph], custom_order: Option<Vec<SmolStr>>) ->
 
     // Add anything left over in file order
     glyph_order.extend(
-        glyphs
+        glyph_names
             .iter()
-            .filter(|g| valid_names.contains(&g.glyphname))
-            .map(|g| g.glyphname.clone()),
+            .filter(|name| valid_names.contains(name))
+            .cloned(),
     );
 
     glyph_order
@@ -2274,11 +2918,7 @@ impl TryFrom<RawShape> for Shape {
             assert!(!glyph_name.is_empty(), "A pointless component");
 
             // V3 vs v2: The transform entry has been replaced by angle, pos and scale entries.
-            let mut transform = if let Some(transform) = from.transform {
-                Affine::parse_plist(&transform)?
-            } else {
-                Affine::IDENTITY
-            };
+            let mut transform = from.transform.unwrap_or(Affine::IDENTITY);
 
             // Glyphs 3 gives us {angle, pos, scale}. Glyphs 2 gives us the standard 2x3 matrix.
             // The matrix is more general and less ambiguous (what order do you apply the angle, pos, scale?)
@@ -2330,7 +2970,7 @@ impl TryFrom<RawShape> for Shape {
 /// to integer later in the build.
 /// It matches the output of the fontTools' Transform.rotate() used by glyphsLib.
 /// <https://github.com/fonttools/fonttools/blob/b7509b2/Lib/fontTools/misc/transform.py#L246-L258>
-fn normalized_rotation(angle_deg: f64) -> Affine {
+pub(crate) fn normalized_rotation(angle_deg: f64) -> Affine {
     const ROT_90: Affine = Affine::new([0.0, 1.0, -1.0, 0.0, 0.0, 0.0]);
     const ROT_180: Affine = Affine::new([-1.0, 0.0, 0.0, -1.0, 0.0, 0.0]);
     const ROT_270: Affine = Affine::new([0.0, -1.0, 1.0, 0.0, 0.0, 0.0]);
@@ -2403,14 +3043,56 @@ impl RawLayer {
             vert_origin: self.vert_origin,
             shapes,
             anchors,
+            guides: self.guides.into_iter().map(Into::into).collect(),
             attributes,
+            metrics_keys: MetricsKeys {
+                left: self.metric_left,
+                right: self.metric_right,
+                width: self.metric_width,
+            },
+            background_image: self.background_image.and_then(|raw| {
+                Some(BackgroundImage {
+                    path: raw.image_path?,
+                    pos: raw.pos.unwrap_or_default(),
+                    other_stuff: raw.other_stuff,
+                })
+            }),
+            other_stuff: self.other_stuff,
         })
     }
 }
 
+/// Glyph user data key of the GlyphsMath plugin that, if true, marks an extended shape
+pub const MATH_EXTENDED_SHAPE_KEY: &str = "com.nagwa.MathPlugin.extendedShape";
+
+/// Master user data key of the GlyphsMath plugin for a dict of MATH constants
+pub const MATH_CONSTANTS_KEY: &str = "com.nagwa.MathPlugin.constants";
+
 impl RawGlyph {
+    /// Parse and build a glyph whose parsing was deferred, see [`crate::LazyFont`]
+    pub(crate) fn load_deferred(
+        raw_content: &str,
+        path: &path::Path,
+        format_version: FormatVersion,
+        glyph_data: &GlyphData,
+    ) -> Result<Glyph, Error> {
+        let mut raw_glyph = RawGlyph::parse_plist(raw_content)
+            .map_err(|e| Error::ParseError(path.to_path_buf(), e.to_string()))?;
+        if format_version.is_v2() {
+            for layer in raw_glyph.layers.iter_mut() {
+                layer.v2_to_v3_attributes();
+            }
+        }
+        raw_glyph.build(format_version, glyph_data, false)
+    }
+
     // we pass in the radix because it depends on the version, stored in the font struct
-    fn build(self, format_version: FormatVersion, glyph_data: &GlyphData) -> Result<Glyph, Error> {
+    fn build(
+        self,
+        format_version: FormatVersion,
+        glyph_data: &GlyphData,
+        lossless: bool,
+    ) -> Result<Glyph, Error> {
         let mut instances = Vec::new();
         let mut bracket_layers = Vec::new();
         for layer in self.layers {
@@ -2456,6 +3138,18 @@ impl RawGlyph {
             }
         }
 
+        let math_extended_shape = self
+            .user_data
+            .as_ref()
+            .and_then(|user_data| user_data.get(MATH_EXTENDED_SHAPE_KEY))
+            .and_then(Plist::as_bool)
+            .unwrap_or_default();
+
+        let mut other_stuff = self.other_stuff;
+        if let Some(user_data) = self.user_data.filter(|_| lossless) {
+            other_stuff.insert("userData".into(), user_data);
+        }
+
         Ok(Glyph {
             name: self.glyphname,
             export: self.export.unwrap_or(true),
@@ -2467,6 +3161,15 @@ impl RawGlyph {
             category,
             sub_category,
             production_name,
+            math_extended_shape,
+            note: self.note,
+            tags: self.tags,
+            metrics_keys: MetricsKeys {
+                left: self.metric_left,
+                right: self.metric_right,
+                width: self.metric_width,
+            },
+            other_stuff,
         })
     }
 }
@@ -2514,6 +3217,12 @@ impl RawFeature {
         self.disabled == Some(1)
     }
 
+    /// Glyphs.app generates the code of automatic features; a source saved
+    /// without it expects the compiler to
+    fn is_empty_automatic(&self) -> bool {
+        self.automatic == Some(1) && !self.disabled() && self.code.trim().is_empty()
+    }
+
     /// Some glyphs sources store stylistic set names in the 'note' field
     ///
     /// See the little sidebar item here:
@@ -2758,7 +3467,7 @@ impl Instance {
         })
     }
 
-    fn family_name(&self) -> Option<&str> {
+    pub(crate) fn family_name(&self) -> Option<&str> {
         self.properties
             .iter()
             .find(|raw| raw.key == "familyNames")
@@ -2769,6 +3478,24 @@ impl Instance {
         // https://github.com/googlefonts/glyphsLib/blob/c4db6b981d577/Lib/glyphsLib/classes.py#L3271
     }
 
+    /// The typographic family name, if the instance overrides it.
+    pub fn typographic_family_name(&self) -> Option<&str> {
+        self.properties
+            .iter()
+            .find(|raw| raw.key == "preferredFamilyNames")
+            .and_then(RawName::get_value)
+    }
+
+    /// The typographic subfamily name, if the instance overrides it.
+    ///
+    /// Not used for the `fvar` named instance, which keeps [Instance::name].
+    pub fn typographic_subfamily_name(&self) -> Option<&str> {
+        self.properties
+            .iter()
+            .find(|raw| raw.key == "preferredSubfamilyNames")
+            .and_then(RawName::get_value)
+    }
+
     /// Get the optional postscript name to use for the `fvar` named instance.
     pub fn postscript_name(&self) -> Option<&str> {
         // https://handbook.glyphsapp.com/custom-parameter-descriptions/
@@ -2850,7 +3577,13 @@ impl TryFrom<RawFont> for Font {
         let glyph_data = GlyphData::default();
 
         let mut custom_parameters = from.custom_parameters.to_custom_params()?;
-        let glyph_order = make_glyph_order(&from.glyphs, custom_parameters.glyph_order.take());
+        let glyph_names = from
+            .glyphs
+            .iter()
+            .map(|g| g.glyphname.clone())
+            .chain(std::mem::take(&mut from.deferred_glyph_names))
+            .collect::<Vec<_>>();
+        let glyph_order = make_glyph_order(&glyph_names, custom_parameters.glyph_order.take());
 
         let default_master_idx = default_master_idx(&from);
 
@@ -2877,10 +3610,16 @@ impl TryFrom<RawFont> for Font {
         for raw_glyph in from.glyphs.into_iter() {
             glyphs.insert(
                 raw_glyph.glyphname.clone(),
-                raw_glyph.build(from.format_version, &glyph_data)?,
+                raw_glyph.build(from.format_version, &glyph_data, from.lossless)?,
             );
         }
 
+        let automatic_features = from
+            .features
+            .iter()
+            .filter(|feature| feature.is_empty_automatic())
+            .filter_map(|feature| feature.name().ok().map(SmolStr::from))
+            .collect();
         let mut features = Vec::new();
         for class in from.classes {
             features.push(class.class_to_feature()?);
@@ -2927,6 +3666,19 @@ impl TryFrom<RawFont> for Font {
             .into_iter()
             .map(|m| {
                 let custom_parameters = m.custom_parameters.to_custom_params()?;
+                let math_constants = m
+                    .user_data
+                    .as_ref()
+                    .and_then(|user_data| user_data.get(MATH_CONSTANTS_KEY))
+                    .and_then(Plist::as_dict)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|(name, value)| Some((name.clone(), value.as_f64()?.into())))
+                    .collect();
+                let mut other_stuff = m.other_stuff;
+                if let Some(user_data) = m.user_data.filter(|_| from.lossless) {
+                    other_stuff.insert("userData".into(), user_data);
+                }
                 Ok(FontMaster {
                     id: m.id,
                     name: m.name.unwrap_or_default(),
@@ -2951,7 +3703,16 @@ impl TryFrom<RawFont> for Font {
                         .zip(m.number_values.iter())
                         .map(|(k, v)| (k.name.clone(), *v))
                         .collect(),
+                    stem_values: from
+                        .stems
+                        .iter()
+                        .zip(m.stem_values.iter())
+                        .map(|(stem, v)| (stem.name.clone(), *v))
+                        .collect(),
                     custom_parameters,
+                    math_constants,
+                    guides: m.guides.into_iter().map(Into::into).collect(),
+                    other_stuff,
                 })
             })
             .collect::<Result<_, Error>>()?;
@@ -2967,19 +3728,22 @@ impl TryFrom<RawFont> for Font {
             axis_mappings,
             virtual_masters,
             features,
+            automatic_features,
             names,
             instances,
+            stems: from.stems,
             version_major: from.versionMajor.unwrap_or_default() as i32,
             version_minor: from.versionMinor.unwrap_or_default() as u32,
             date: from.date,
             kerning_ltr: from.kerning_LTR,
             kerning_rtl: from.kerning_RTL,
             custom_parameters,
+            other_stuff: from.other_stuff,
         })
     }
 }
 
-fn preprocess_unparsed_plist(s: &str) -> Cow<str> {
+pub(crate) fn preprocess_unparsed_plist(s: &str) -> Cow<str> {
     // Glyphs has a wide variety of unicode definitions, not all of them parser friendly
     // Make unicode always a string, without any wrapping () so we can parse as csv, radix based on format version
     let unicode_re =
@@ -3008,7 +3772,7 @@ impl Font {
         Ok(font)
     }
 
-    fn preprocess(&mut self) {
+    pub(crate) fn preprocess(&mut self) {
         // ensure that glyphs with components that have bracket layers
         // also have bracket layers.
         self.align_bracket_layers();
@@ -3091,11 +3855,24 @@ impl Font {
         }
     }
 
-    // load without propagating anchors or components
-    pub(crate) fn load_raw(glyphs_file: impl AsRef<path::Path>) -> Result<Font, Error> {
+    /// Load without preprocessing, such as propagating anchors.
+    ///
+    /// This is the font as it is in the source, which is what you want if you
+    /// are going to modify it and [save](Font::save) it again.
+    pub fn load_raw(glyphs_file: impl AsRef<path::Path>) -> Result<Font, Error> {
         RawFont::load(glyphs_file.as_ref()).and_then(Font::try_from)
     }
 
+    /// As [`Font::load_raw`], but also keeping the data we don't use.
+    ///
+    /// Keys that aren't part of the model, such as userData, notes, guides and
+    /// hints, are kept in the `other_stuff` of the font, its masters, glyphs
+    /// and layers, and are written back out by [`Font::save`]. This costs time
+    /// and memory that compiling a font doesn't need to spend.
+    pub fn load_lossless(glyphs_file: impl AsRef<path::Path>) -> Result<Font, Error> {
+        RawFont::load_impl(glyphs_file.as_ref(), true).and_then(Font::try_from)
+    }
+
     pub fn default_master(&self) -> &FontMaster {
         &self.masters[self.default_master_idx]
     }
@@ -3159,7 +3936,7 @@ impl From<Point> for PointForEqAndHash {
 
 /// Convert [kurbo::Affine] to this for eq and hash/
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-struct AffineForEqAndHash([OrderedFloat<f64>; 6]);
+pub(crate) struct AffineForEqAndHash([OrderedFloat<f64>; 6]);
 
 impl From<Affine> for AffineForEqAndHash {
     fn from(value: Affine) -> Self {
@@ -3858,6 +4635,19 @@ mod tests {
         assert_eq!("aalt", raw.name().unwrap());
     }
 
+    #[test]
+    fn only_empty_automatic_features_are_generated() {
+        let feature = |automatic, code: &str| RawFeature {
+            tag: Some("liga".into()),
+            automatic,
+            code: code.into(),
+            ..Default::default()
+        };
+        assert!(feature(Some(1), "\n ").is_empty_automatic());
+        assert!(!feature(Some(1), "sub f i by f_i;").is_empty_automatic());
+        assert!(!feature(None, "").is_empty_automatic());
+    }
+
     #[test]
     fn manual_kern_always_gets_insert_mark() {
         let feature = RawFeature {
@@ -4122,6 +4912,254 @@ mod tests {
         );
     }
 
+    #[test]
+    fn load_lossless_keeps_unknown_keys() {
+        let raw = "{ layerId = m01; hints = ({ type = Stem; }); }";
+        let layer = RawLayer::parse_plist_lossless(raw)
+            .unwrap()
+            .build(FormatVersion::V3)
+            .unwrap();
+        assert!(layer.other_stuff.contains_key("hints"), "{layer:?}");
+
+        let layer = RawLayer::parse_plist(raw)
+            .unwrap()
+            .build(FormatVersion::V3)
+            .unwrap();
+        assert!(layer.other_stuff.is_empty());
+
+        let font = Font::load_lossless(glyphs3_dir().join("IntermediateLayer.glyphs")).unwrap();
+        assert!(font.other_stuff.contains_key("userData"));
+    }
+
+    #[test]
+    fn read_guides() {
+        let font = Font::load_raw(glyphs3_dir().join("Oswald-O.glyphs")).unwrap();
+        let layer = &font.glyphs.get("O").unwrap().layers[0];
+        assert_eq!(
+            vec![Guide {
+                pos: Point::new(458.0, 721.0),
+                angle: 90.0.into(),
+                ..Default::default()
+            }],
+            layer.guides
+        );
+
+        // glyphs 2 names, on a master
+        let master = RawFontMaster::parse_plist(
+            r#"{
+            id = m01;
+            guideLines = ({ angle = 45; locked = 1; name = slant; position = "{10, 20}"; });
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![Guide {
+                name: Some("slant".into()),
+                pos: Point::new(10.0, 20.0),
+                angle: 45.0.into(),
+                locked: true,
+                ..Default::default()
+            }],
+            master
+                .guides
+                .into_iter()
+                .map(Guide::from)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn layer_kinds() {
+        let layer = |raw: &str, format_version| {
+            let mut raw = RawLayer::parse_plist(raw).unwrap();
+            if format_version == FormatVersion::V2 {
+                raw.v2_to_v3_attributes();
+            }
+            raw.build(format_version).unwrap()
+        };
+
+        let master = layer("{ layerId = m01; }", FormatVersion::V3);
+        assert!(master.is_master());
+        assert!(!(master.is_intermediate() || master.is_alternate() || master.is_color()));
+
+        let palette = layer(
+            "{ associatedMasterId = m01; attr = { colorPalette = 2; }; layerId = l1; }",
+            FormatVersion::V3,
+        );
+        assert!(palette.is_color() && !palette.is_master());
+        assert_eq!(Some(2), palette.color_palette_index());
+        let foreground = layer(
+            r#"{ associatedMasterId = m01; attr = { colorPalette = "*"; }; layerId = l1; }"#,
+            FormatVersion::V3,
+        );
+        assert_eq!(
+            Some(FOREGROUND_PALETTE_INDEX),
+            foreground.color_palette_index()
+        );
+        let full_color = layer(
+            "{ attr = { color = 1; coordinates = (100); }; associatedMasterId = m01; layerId = l1; }",
+            FormatVersion::V3,
+        );
+        assert!(full_color.is_full_color() && full_color.is_intermediate());
+        let alternate = layer(
+            "{ attr = { axisRules = ({ min = 600; }); }; associatedMasterId = m01; layerId = l1; }",
+            FormatVersion::V3,
+        );
+        assert!(alternate.is_alternate() && !alternate.is_color());
+
+        // glyphs 2 uses layer names instead
+        let palette = layer(
+            r#"{ associatedMasterId = m01; layerId = l1; name = "Color 1"; }"#,
+            FormatVersion::V2,
+        );
+        assert_eq!(Some(1), palette.color_palette_index());
+        let sbix = layer(
+            r#"{ associatedMasterId = m01; layerId = l1; name = "iColor 64"; }"#,
+            FormatVersion::V2,
+        );
+        assert_eq!(Some(64), sbix.sbix_size());
+        let svg = layer(
+            "{ associatedMasterId = m01; layerId = l1; name = svg; }",
+            FormatVersion::V2,
+        );
+        assert!(svg.is_svg() && svg.is_color());
+        let alternate = layer(
+            r#"{ associatedMasterId = m01; layerId = l1; name = "Bold [600]"; }"#,
+            FormatVersion::V2,
+        );
+        assert!(alternate.is_alternate());
+    }
+
+    #[test]
+    fn read_metrics_keys() {
+        let font = Font::load_raw(glyphs3_dir().join("ProductionNames.glyphs")).unwrap();
+        let glyph = font.glyphs.get("A_nbspace_idotless").unwrap();
+        assert_eq!(
+            MetricsKeys {
+                left: Some("A".into()),
+                right: Some("idotless".into()),
+                width: None,
+            },
+            glyph.metrics_keys
+        );
+        assert!(glyph.layers[0].metrics_keys.is_empty());
+
+        // glyphs 2 names
+        let glyph = RawGlyph::parse_plist(
+            r#"{
+            glyphname = a;
+            layers = ({ layerId = m01; leftMetricsKey = "=|"; width = 500; });
+            widthMetricsKey = "=n";
+            }"#,
+        )
+        .unwrap()
+        .build(FormatVersion::V2, &GlyphData::default(), false)
+        .unwrap();
+        assert_eq!(Some("=n"), glyph.metrics_keys.width.as_deref());
+        assert_eq!(Some("=|"), glyph.layers[0].metrics_keys.left.as_deref());
+    }
+
+    #[test]
+    fn read_notes_and_tags() {
+        let font = Font::load_raw(glyphs2_dir().join("AlumniSans-wononly.glyphs")).unwrap();
+        let glyph = font.glyphs.get("acutecomb").unwrap();
+        assert_eq!(Some("acutecomb"), glyph.note.as_deref());
+
+        let glyph = RawGlyph::parse_plist("{ glyphname = a; tags = (round, \"lower case\"); }")
+            .unwrap()
+            .build(FormatVersion::V3, &GlyphData::default(), false)
+            .unwrap();
+        assert_eq!(vec!["round", "lower case"], glyph.tags);
+    }
+
+    #[test]
+    fn read_enforce_compatibility_check() {
+        let font =
+            Font::load(&glyphs3_dir().join("WghtVar_EnforceCompatibilityCheck.glyphs")).unwrap();
+        assert_eq!(
+            Some(true),
+            font.custom_parameters.enforce_compatibility_check
+        );
+    }
+
+    #[test]
+    fn read_baselines() {
+        let font = Font::load(&glyphs3_dir().join("Baselines.glyphs")).unwrap();
+        let baselines = font.custom_parameters.baselines.unwrap();
+        assert!(baselines.vertical.is_empty());
+        assert_eq!(
+            vec![("hani", "ideo"), ("latn", "romn")],
+            baselines
+                .horizontal
+                .iter()
+                .map(|(script, b)| (script.as_str(), b.default.as_str()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            BTreeMap::from([("ideo".into(), -120), ("romn".into(), 0)]),
+            baselines.horizontal["latn"].positions
+        );
+    }
+
+    #[test]
+    fn read_sbix_background_image() {
+        let font = Font::load(&glyphs3_dir().join("Bitmaps.glyphs")).unwrap();
+        let layers = &font.glyphs["smile"].layers;
+        assert_eq!(None, layers[0].background_image);
+        assert_eq!(Some(64), layers[1].sbix_size());
+        let image = layers[1].background_image.as_ref().unwrap();
+        assert_eq!(
+            ("bitmaps/smile.png", Point::new(0.0, -100.0)),
+            (image.path.as_str(), image.pos)
+        );
+    }
+
+    #[test]
+    fn read_color_palette_params() {
+        let font = Font::load(&glyphs3_dir().join("ColorPalettes.glyphs")).unwrap();
+        let params = &font.custom_parameters;
+        assert_eq!(
+            Some(vec![
+                vec![[255, 0, 0, 255], [0, 0, 255, 255]],
+                vec![[255, 128, 128, 255], [128, 128, 255, 255]],
+            ]),
+            params.color_palettes
+        );
+        assert_eq!(
+            Some(vec![
+                PALETTE_USABLE_WITH_LIGHT_BACKGROUND,
+                PALETTE_USABLE_WITH_DARK_BACKGROUND
+            ]),
+            params.color_palette_types
+        );
+    }
+
+    #[test]
+    fn parse_glyphs2_palette_strings() {
+        let palettes = Plist::parse(r#"(("0,255", "255,0,0,128"))"#).unwrap();
+        assert_eq!(
+            Some(vec![vec![[0, 0, 0, 255], [255, 0, 0, 128]]]),
+            palettes.as_color_palettes()
+        );
+        let types = Plist::parse(r#"(0, "light, dark")"#).unwrap();
+        assert_eq!(Some(vec![0, 3]), types.as_palette_types());
+    }
+
+    #[test]
+    fn read_math_user_data() {
+        let font = Font::load(&glyphs3_dir().join("Math.glyphs")).unwrap();
+        assert_eq!(
+            BTreeMap::from([
+                ("AxisHeight".into(), 250.0.into()),
+                ("MinConnectorOverlap".into(), 20.0.into()),
+                ("ScriptPercentScaleDown".into(), 70.0.into()),
+            ]),
+            font.masters[0].math_constants
+        );
+        assert!(font.glyphs["integral"].math_extended_shape);
+        assert!(!font.glyphs["f"].math_extended_shape);
+    }
+
     #[test]
     fn read_fstype_none() {
         let font = Font::load(&glyphs3_dir().join("infinity.glyphs")).unwrap();
@@ -4219,7 +5257,9 @@ mod tests {
             ..Default::default()
         };
 
-        let cooked = raw.build(FormatVersion::V2, &GlyphData::default()).unwrap();
+        let cooked = raw
+            .build(FormatVersion::V2, &GlyphData::default(), false)
+            .unwrap();
         assert_eq!(
             (cooked.category, cooked.sub_category),
             (Some(Category::Letter), None)
@@ -4233,6 +5273,47 @@ mod tests {
         assert!(font.custom_parameters.fs_type.is_none())
     }
 
+    #[test]
+    fn query_raw_custom_parameters() {
+        let font = Font::load(&glyphs3_dir().join("custom_param_disable.glyphs")).unwrap();
+        let fs_type = font
+            .custom_parameters
+            .iter()
+            .find(|param| param.name == "fsType")
+            .unwrap();
+        assert!(fs_type.disabled);
+        assert_eq!(None, font.custom_parameters.get("fsType"));
+
+        let font =
+            Font::load(&glyphs3_dir().join("GlobalMetrics_font_customParameters.glyphs")).unwrap();
+        assert_eq!(
+            Some(true),
+            font.custom_parameters.get_bool("Use Typo Metrics")
+        );
+
+        // glyphs 2 keeps these on the masters
+        let font = Font::load(&glyphs2_dir().join("WghtVar_OS2.glyphs")).unwrap();
+        let params = &font.default_master().custom_parameters;
+        assert_eq!(Some(1193), params.get_int("typoAscender"));
+        assert_eq!(None, params.get_str("typoAscender"));
+    }
+
+    #[test]
+    fn read_rename_and_reencode_glyphs() {
+        let font = Font::load(&glyphs3_dir().join("RenameGlyphs.glyphs")).unwrap();
+        assert_eq!(
+            Some(vec![
+                ("a".into(), "a.ss01".into()),
+                ("g.alt".into(), "g".into())
+            ]),
+            font.custom_parameters.rename_glyphs
+        );
+        assert_eq!(
+            Some(vec![("b.sc".into(), BTreeSet::from([0x62, 0xE000]))]),
+            font.custom_parameters.reencode_glyphs
+        );
+    }
+
     #[test]
     fn parse_numbers() {
         let font = Font::load(&glyphs3_dir().join("number_value.glyphs")).unwrap();
@@ -4246,6 +5327,67 @@ mod tests {
         );
     }
 
+    #[test]
+    fn read_stems() {
+        let font = Font::load(&glyphs3_dir().join("Stems.glyphs")).unwrap();
+        assert_eq!(
+            vec![
+                Stem {
+                    name: "hStem".into(),
+                    horizontal: true
+                },
+                Stem {
+                    name: "vStem".into(),
+                    horizontal: false
+                }
+            ],
+            font.stems
+        );
+        assert_eq!(
+            BTreeMap::from([
+                ("hStem".into(), OrderedFloat(100.0)),
+                ("vStem".into(), OrderedFloat(150.0))
+            ]),
+            font.masters[1].stem_values
+        );
+    }
+
+    #[test]
+    fn v2_stems_to_v3() {
+        let font = Font::load(&glyphs2_dir().join("Stems.glyphs")).unwrap();
+        assert_eq!(
+            vec![("hStem0", true), ("vStem0", false), ("vStem1", false)],
+            font.stems
+                .iter()
+                .map(|stem| (stem.name.as_str(), stem.horizontal))
+                .collect::<Vec<_>>()
+        );
+        let stem_values = |master: &FontMaster| {
+            font.stems
+                .iter()
+                .map(|stem| master.stem_values[&stem.name].into_inner())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec![70.0, 80.0, 90.0], stem_values(&font.masters[0]));
+        // the bold master has no horizontal stem and one vertical stem
+        assert_eq!(vec![0.0, 150.0, 0.0], stem_values(&font.masters[1]));
+    }
+
+    #[test]
+    fn alignment_zones() {
+        let font = Font::load(&glyphs3_dir().join("Stems.glyphs")).unwrap();
+        assert_eq!(
+            vec![
+                (OrderedFloat(-200.0), OrderedFloat(-16.0)),
+                (OrderedFloat(0.0), OrderedFloat(-16.0)),
+                (OrderedFloat(510.0), OrderedFloat(12.0)),
+                (OrderedFloat(700.0), OrderedFloat(16.0)),
+                (OrderedFloat(800.0), OrderedFloat(16.0)),
+            ],
+            font.masters[1].alignment_zones()
+        );
+    }
+
     #[test]
     fn read_font_metrics() {
         let font =
@@ -4515,6 +5657,28 @@ mod tests {
         assert_eq!(font.names.get("preferredSubfamilyNames").unwrap(), "Italic")
     }
 
+    #[test]
+    fn instance_typographic_subfamily_name() {
+        let font = Font::load(&glyphs3_dir().join("InstanceNames.glyphs")).unwrap();
+        assert_eq!(
+            Some("Italic"),
+            font.instances[0].typographic_subfamily_name()
+        );
+    }
+
+    #[test]
+    fn v2_instance_typographic_names() {
+        let font = Font::load(&glyphs2_dir().join("InstanceTypographicNames.glyphs")).unwrap();
+        let instance = font.instances.iter().find(|i| i.name == "Bold").unwrap();
+        assert_eq!(
+            (Some("WghtVar Display"), Some("Heavy")),
+            (
+                instance.typographic_family_name(),
+                instance.typographic_subfamily_name()
+            )
+        );
+    }
+
     #[rstest]
     #[case::v2(glyphs2_dir())]
     #[case::v3(glyphs3_dir())]
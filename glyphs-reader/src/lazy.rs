@@ -0,0 +1,279 @@
+//! Loading a font without parsing its glyphs up front
+//!
+//! Most of a big source, such as a CJK font, is glyphs. We parse everything
+//! else eagerly, note where each glyph is in the source, and parse the glyphs
+//! when they are asked for, or all at once in parallel.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::OsStr,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use log::debug;
+use rayon::prelude::*;
+use smol_str::SmolStr;
+
+use crate::{
+    error::Error,
+    font::{self, FormatVersion, RawFont, RawGlyph},
+    glyphdata::GlyphData,
+    plist::{FromPlist, Tokenizer},
+    Font, Glyph,
+};
+
+/// A font whose glyphs are parsed on demand
+///
+/// Loading one of these reads the whole source, but only parses the font
+/// info and masters; finding the glyphs only requires skimming over them.
+pub struct LazyFont {
+    /// Everything but the glyphs, which is empty
+    font: Font,
+    format_version: FormatVersion,
+    glyph_data: GlyphData,
+    /// The files the glyphs are in, after preprocessing
+    sources: Vec<(PathBuf, String)>,
+    glyphs: BTreeMap<SmolStr, GlyphSource>,
+}
+
+/// Where to find a glyph we haven't parsed yet
+struct GlyphSource {
+    source_idx: usize,
+    range: Range<usize>,
+}
+
+impl LazyFont {
+    /// Load a .glyphs file or .glyphspackage, deferring the parsing of glyphs
+    pub fn load(glyphs_file: impl AsRef<Path>) -> Result<LazyFont, Error> {
+        let glyphs_file = glyphs_file.as_ref();
+        if glyphs_file.extension() == Some(OsStr::new("glyphspackage")) {
+            return Self::load_package(glyphs_file);
+        }
+
+        debug!("Read glyphs {glyphs_file:?} lazily");
+        let parse_error =
+            |e: crate::plist::Error| Error::ParseError(glyphs_file.into(), e.to_string());
+        let raw_content = fs::read_to_string(glyphs_file).map_err(Error::IoError)?;
+        let raw_content = font::preprocess_unparsed_plist(&raw_content).into_owned();
+
+        let mut glyphs = BTreeMap::new();
+        let mut glyph_names = Vec::new();
+        let header = match Tokenizer::new(&raw_content)
+            .find_array_items("glyphs")
+            .map_err(parse_error)?
+        {
+            Some((array, items)) => {
+                for range in items {
+                    let name = glyph_name(&raw_content[range.clone()], glyphs_file)?;
+                    glyph_names.push(name.clone());
+                    glyphs.insert(
+                        name,
+                        GlyphSource {
+                            source_idx: 0,
+                            range,
+                        },
+                    );
+                }
+                // the font without its glyphs
+                format!(
+                    "{}(){}",
+                    &raw_content[..array.start],
+                    &raw_content[array.end..]
+                )
+            }
+            None => raw_content.clone(),
+        };
+        let raw_font = RawFont::parse_plist(&header).map_err(parse_error)?;
+
+        Self::new(
+            raw_font,
+            glyph_names,
+            vec![(glyphs_file.to_path_buf(), raw_content)],
+            glyphs,
+        )
+    }
+
+    fn load_package(glyphs_package: &Path) -> Result<LazyFont, Error> {
+        if !glyphs_package.is_dir() {
+            return Err(Error::NotAGlyphsPackage(glyphs_package.to_path_buf()));
+        }
+        debug!("Read glyphs package {glyphs_package:?} lazily");
+
+        let fontinfo_file = glyphs_package.join("fontinfo.plist");
+        let fontinfo_data = fs::read_to_string(&fontinfo_file).map_err(Error::IoError)?;
+        let raw_font = RawFont::parse_plist(&fontinfo_data)
+            .map_err(|e| Error::ParseError(fontinfo_file, e.to_string()))?;
+
+        let mut sources = HashMap::new();
+        let glyphs_dir = glyphs_package.join("glyphs");
+        if glyphs_dir.is_dir() {
+            for entry in fs::read_dir(glyphs_dir).map_err(Error::IoError)? {
+                let entry = entry.map_err(Error::IoError)?;
+                let path = entry.path();
+                if path.extension() == Some(OsStr::new("glyph")) {
+                    let glyph_data = fs::read_to_string(&path).map_err(Error::IoError)?;
+                    let glyph_data = font::preprocess_unparsed_plist(&glyph_data).into_owned();
+                    let name = glyph_name(&glyph_data, &path)?;
+                    sources.insert(name.clone(), (name, path, glyph_data));
+                }
+            }
+        }
+
+        let mut glyph_names = Vec::new();
+        let mut glyphs = BTreeMap::new();
+        let sources = font::order_package_glyphs(glyphs_package, sources)?
+            .into_iter()
+            .enumerate()
+            .map(|(source_idx, (name, path, glyph_data))| {
+                glyph_names.push(name.clone());
+                glyphs.insert(
+                    name,
+                    GlyphSource {
+                        source_idx,
+                        range: 0..glyph_data.len(),
+                    },
+                );
+                (path, glyph_data)
+            })
+            .collect();
+
+        Self::new(raw_font, glyph_names, sources, glyphs)
+    }
+
+    fn new(
+        mut raw_font: RawFont,
+        glyph_names: Vec<SmolStr>,
+        sources: Vec<(PathBuf, String)>,
+        glyphs: BTreeMap<SmolStr, GlyphSource>,
+    ) -> Result<LazyFont, Error> {
+        let format_version = raw_font.format_version;
+        raw_font.deferred_glyph_names = glyph_names;
+        Ok(LazyFont {
+            font: Font::try_from(raw_font)?,
+            format_version,
+            // TODO: this should be provided in a manner that allows for overrides
+            glyph_data: GlyphData::default(),
+            sources,
+            glyphs,
+        })
+    }
+
+    /// The font, without any glyphs
+    ///
+    /// Everything else, including the glyph order, is loaded.
+    pub fn font(&self) -> &Font {
+        &self.font
+    }
+
+    /// The names of the glyphs, in order
+    pub fn glyph_order(&self) -> &[SmolStr] {
+        &self.font.glyph_order
+    }
+
+    /// Parse the named glyph, if there is one
+    ///
+    /// The glyph is as loaded by [`Font::load_raw`]; anchors aren't
+    /// propagated, since that needs the glyph's components.
+    pub fn glyph(&self, name: &str) -> Result<Option<Glyph>, Error> {
+        self.glyphs
+            .get(name)
+            .map(|source| self.parse_glyph(source))
+            .transpose()
+    }
+
+    /// Parse all the glyphs, in parallel
+    ///
+    /// The result is the same as that of [`Font::load`].
+    pub fn into_font(self) -> Result<Font, Error> {
+        let glyphs = self
+            .glyphs
+            .par_iter()
+            .map(|(name, source)| self.parse_glyph(source).map(|glyph| (name.clone(), glyph)))
+            .collect::<Result<BTreeMap<_, _>, Error>>()?;
+        let mut font = self.font;
+        font.glyphs = glyphs;
+        font.preprocess();
+        Ok(font)
+    }
+
+    fn parse_glyph(&self, source: &GlyphSource) -> Result<Glyph, Error> {
+        let (path, raw_content) = &self.sources[source.source_idx];
+        RawGlyph::load_deferred(
+            &raw_content[source.range.clone()],
+            path,
+            self.format_version,
+            &self.glyph_data,
+        )
+    }
+}
+
+/// Find the name of a glyph without parsing the rest of it
+fn glyph_name(raw_glyph: &str, path: &Path) -> Result<SmolStr, Error> {
+    match Tokenizer::new(raw_glyph).find_string("glyphname") {
+        Ok(Some(name)) if !name.is_empty() => Ok(name),
+        Ok(_) => Err(Error::ParseError(
+            path.to_path_buf(),
+            "Glyph dict must have a 'glyphname' key".to_string(),
+        )),
+        Err(e) => Err(Error::ParseError(path.to_path_buf(), e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testdata_dir() -> PathBuf {
+        // working dir varies CLI vs VSCode
+        let mut dir = Path::new("../resources/testdata");
+        if !dir.is_dir() {
+            dir = Path::new("./resources/testdata");
+        }
+        assert!(dir.is_dir());
+        dir.to_path_buf()
+    }
+
+    #[test]
+    fn same_as_eager_loading() {
+        let mut n_checked = 0;
+        for dir in ["glyphs2", "glyphs3"] {
+            for entry in fs::read_dir(testdata_dir().join(dir)).unwrap() {
+                let path = entry.unwrap().path();
+                if !path
+                    .extension()
+                    .is_some_and(|ext| ext == "glyphs" || ext == "glyphspackage")
+                {
+                    continue;
+                }
+                // some test files are deliberately broken
+                let Ok(font) = Font::load(&path) else {
+                    continue;
+                };
+                let lazy = LazyFont::load(&path).unwrap();
+                assert_eq!(font.glyph_order, lazy.glyph_order(), "{path:?}");
+                assert_eq!(font, lazy.into_font().unwrap(), "{path:?}");
+                n_checked += 1;
+            }
+        }
+        assert!(n_checked > 100, "only checked {n_checked} files");
+    }
+
+    #[test]
+    fn glyph_on_demand() {
+        for path in [
+            testdata_dir().join("glyphs2/WghtVar.glyphs"),
+            testdata_dir().join("glyphs3/WghtVar.glyphspackage"),
+            testdata_dir().join("glyphs3/IntermediateLayer.glyphs"),
+        ] {
+            let font = Font::load_raw(&path).unwrap();
+            let lazy = LazyFont::load(&path).unwrap();
+            assert!(lazy.font().glyphs.is_empty());
+            for name in &font.glyph_order {
+                assert_eq!(font.glyphs.get(name), lazy.glyph(name).unwrap().as_ref());
+            }
+            assert_eq!(None, lazy.glyph("not a glyph").unwrap());
+        }
+    }
+}
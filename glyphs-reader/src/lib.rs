@@ -5,11 +5,17 @@ mod font;
 pub mod glyphdata;
 mod glyphdata_bundled;
 mod glyphslib_enums;
+mod lazy;
 mod plist;
 mod propagate_anchors;
+mod write;
 
 pub use font::{
-    Axis, Component, CustomParameters, FeatureSnippet, Font, FontMaster, Glyph, InstanceType,
-    Layer, Node, NodeType, Path, Shape,
+    Axis, BackgroundImage, Baselines, Component, CustomParameter, CustomParameters, FeatureSnippet,
+    Font, FontMaster, Glyph, Gradient, Guide, InstanceType, Layer, MetricsKeys, Node, NodeType,
+    Path, ScriptBaselines, Shape, Stem, FOREGROUND_PALETTE_INDEX, MATH_CONSTANTS_KEY,
+    MATH_EXTENDED_SHAPE_KEY, PALETTE_USABLE_WITH_DARK_BACKGROUND,
+    PALETTE_USABLE_WITH_LIGHT_BACKGROUND,
 };
+pub use lazy::LazyFont;
 pub use plist::Plist;
@@ -1,5 +1,5 @@
 use std::collections::BTreeMap;
-use std::{borrow::Cow, fmt::Debug};
+use std::{borrow::Cow, fmt::Debug, ops::Range};
 
 use kurbo::{Affine, Point};
 use ordered_float::OrderedFloat;
@@ -152,7 +152,11 @@ fn skip_ws(s: &str, mut ix: usize) -> usize {
 }
 
 fn escape_string(buf: &mut String, s: &str) {
-    if !s.is_empty() && s.as_bytes().iter().all(|&b| is_alnum_strict(b)) {
+    // numeric strings are quoted so they don't read back as numbers
+    if !s.is_empty()
+        && s.as_bytes().iter().all(|&b| is_alnum_strict(b))
+        && s.parse::<f64>().is_err()
+    {
         buf.push_str(s);
     } else {
         buf.push('"');
@@ -340,15 +344,31 @@ impl Plist {
         Plist::String(s.into())
     }
 
-    #[allow(clippy::inherent_to_string, unused)]
+    /// Serialize in the style of Glyphs 3 files.
+    #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         let mut s = String::new();
         self.push_to_string(&mut s);
         s
     }
 
+    fn is_scalar(&self) -> bool {
+        !matches!(self, Plist::Array(_) | Plist::Dictionary(_))
+    }
+
     fn push_to_string(&self, s: &mut String) {
         match self {
+            // arrays of simple values, like coordinates, go on one line
+            Plist::Array(a) if a.iter().all(Plist::is_scalar) => {
+                s.push('(');
+                for (i, el) in a.iter().enumerate() {
+                    if i > 0 {
+                        s.push(',');
+                    }
+                    el.push_to_string(s);
+                }
+                s.push(')');
+            }
             Plist::Array(a) => {
                 s.push('(');
                 let mut delim = "\n";
@@ -393,7 +413,7 @@ impl Plist {
 
 impl FromPlist for Plist {
     fn parse(tokenizer: &mut Tokenizer) -> Result<Self, Error> {
-        let Tokenizer { content, idx } = tokenizer;
+        let Tokenizer { content, idx, .. } = tokenizer;
         let (val, end_idx) = Self::parse_rec(content, *idx)?;
         *idx = end_idx;
         Ok(val)
@@ -629,6 +649,12 @@ impl From<String> for Plist {
     }
 }
 
+impl From<&str> for Plist {
+    fn from(x: &str) -> Plist {
+        Plist::String(x.to_string())
+    }
+}
+
 impl From<i64> for Plist {
     fn from(x: i64) -> Plist {
         Plist::Integer(x)
@@ -684,6 +710,12 @@ where
     fn parse_plist(plist: &str) -> Result<Self, Error> {
         Tokenizer::new(plist).parse()
     }
+
+    /// As [`FromPlist::parse_plist`], but unknown keys are kept by structs
+    /// with a field marked `#[fromplist(other)]`.
+    fn parse_plist_lossless(plist: &str) -> Result<Self, Error> {
+        Tokenizer::lossless(plist).parse()
+    }
 }
 
 impl<T> FromPlist for Vec<T>
@@ -713,6 +745,9 @@ where
 pub struct Tokenizer<'a> {
     content: &'a str,
     idx: usize,
+    /// Whether structs with a catch-all field should fill it, rather than
+    /// skipping keys they don't recognize
+    pub(crate) keep_unknown_keys: bool,
 }
 
 impl Debug for Tokenizer<'_> {
@@ -728,7 +763,19 @@ impl Debug for Tokenizer<'_> {
 
 impl<'a> Tokenizer<'a> {
     pub fn new(content: &'a str) -> Tokenizer<'a> {
-        Tokenizer { content, idx: 0 }
+        Tokenizer {
+            content,
+            idx: 0,
+            keep_unknown_keys: false,
+        }
+    }
+
+    /// A tokenizer that keeps the keys structs don't recognize, see [`FromPlist::parse_plist_lossless`]
+    pub(crate) fn lossless(content: &'a str) -> Tokenizer<'a> {
+        Tokenizer {
+            keep_unknown_keys: true,
+            ..Tokenizer::new(content)
+        }
     }
 
     pub(crate) fn peek(&mut self) -> Result<Token<'a>, Error> {
@@ -786,6 +833,61 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    /// Move to the value of `key` in the dict that comes next, skipping the values before it
+    ///
+    /// Returns false, having consumed the whole dict, if there is no such key.
+    fn find_key(&mut self, key: &str) -> Result<bool, Error> {
+        self.eat(b'{')?;
+        loop {
+            if self.eat(b'}').is_ok() {
+                return Ok(false);
+            }
+            let next = self.lex()?.try_into_smolstr()?;
+            self.eat(b'=')?;
+            if next == key {
+                return Ok(true);
+            }
+            self.skip_rec()?;
+            self.eat(b';')?;
+        }
+    }
+
+    /// The string value of `key` in the dict that comes next, without parsing anything else
+    pub(crate) fn find_string(&mut self, key: &str) -> Result<Option<SmolStr>, Error> {
+        if !self.find_key(key)? {
+            return Ok(None);
+        }
+        self.parse().map(Some)
+    }
+
+    /// Where the array value of `key` in the dict that comes next is, and where each of its items are
+    ///
+    /// This lets us find things in the source without parsing them.
+    pub(crate) fn find_array_items(
+        &mut self,
+        key: &str,
+    ) -> Result<Option<(Range<usize>, Vec<Range<usize>>)>, Error> {
+        if !self.find_key(key)? {
+            return Ok(None);
+        }
+        let start = self.idx;
+        let mut items = Vec::new();
+        self.eat(b'(')?;
+        loop {
+            if self.eat(b')').is_ok() {
+                break;
+            }
+            let item_start = self.idx;
+            self.skip_rec()?;
+            items.push(item_start..self.idx);
+            if self.eat(b')').is_ok() {
+                break;
+            }
+            self.eat(b',')?;
+        }
+        Ok(Some((start..self.idx, items)))
+    }
+
     pub(crate) fn parse_delimited_vec<T>(
         &mut self,
         delim: VecDelimiters,
@@ -1187,4 +1289,22 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn to_string_round_trips() {
+        let plist = Plist::parse(
+            r#"{
+            name = "Regular Italic";
+            pos = (12,-3.5);
+            version = "1.000";
+            nodes = ((1,2,l),(3,4,o));
+            zones = ({pos = 0; over = -10;});
+            }"#,
+        )
+        .unwrap();
+        let text = plist.to_string();
+        assert!(text.contains("pos = (12,-3.5);"), "{text}");
+        assert!(text.contains("version = \"1.000\";"), "{text}");
+        assert_eq!(plist, Plist::parse(&text).unwrap());
+    }
 }
@@ -0,0 +1,1348 @@
+//! Writing a [`Font`] back to the Glyphs 3 format
+//!
+//! The font model is normalized when it is loaded, so we can't reproduce the
+//! original source byte-for-byte. What we aim for is that loading what we
+//! write gives back an equal [`Font`]. Values that were derived while loading,
+//! such as the default master and the axis mappings, are written as the
+//! custom parameters that produce them.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    ffi::OsStr,
+    fs, path,
+};
+
+use kurbo::{Affine, Point};
+use ordered_float::OrderedFloat;
+use smol_str::SmolStr;
+
+use crate::{
+    error::Error,
+    font::{
+        normalized_rotation, AffineForEqAndHash, Anchor, Color, FeatureSnippet, Gradient, Instance,
+        Kerning, LayerAttributes, RawName, FOREGROUND_PALETTE_INDEX,
+    },
+    glyphdata::GlyphData,
+    plist::{Dictionary, Plist},
+    Axis, BackgroundImage, Component, CustomParameters, Font, FontMaster, Glyph, Guide,
+    InstanceType, Layer, MetricsKeys, Node, NodeType, Path, Shape, Stem, MATH_CONSTANTS_KEY,
+    MATH_EXTENDED_SHAPE_KEY,
+};
+
+static FONTINFO_FILE: &str = "fontinfo.plist";
+static GLYPHS_DIR: &str = "glyphs";
+static ORDER_FILE: &str = "order.plist";
+
+// how the loader marks automatic features
+static AUTOMATIC: &str = "# automatic\n";
+
+impl Font {
+    /// The font as the contents of a Glyphs 3 file
+    pub fn to_plist(&self) -> Plist {
+        let glyph_data = GlyphData::default();
+        let mut dict = self.fontinfo();
+        let glyphs = self
+            .ordered_glyphs()
+            .map(|glyph| glyph_plist(glyph, &glyph_data))
+            .collect::<Vec<_>>();
+        dict.insert("glyphs".into(), glyphs.into());
+        dict.into()
+    }
+
+    /// Write the font to a .glyphs file, or to a .glyphspackage if `path` has
+    /// that extension.
+    ///
+    /// A font returned by [`Font::load`] includes the results of preprocessing,
+    /// such as propagated anchors; to modify a source, load it with
+    /// [`Font::load_raw`] instead.
+    pub fn save(&self, path: &path::Path) -> Result<(), Error> {
+        if path.extension() == Some(OsStr::new("glyphspackage")) {
+            return self.save_package(path);
+        }
+        fs::write(path, self.to_plist().to_string()).map_err(Error::IoError)
+    }
+
+    fn save_package(&self, path: &path::Path) -> Result<(), Error> {
+        let glyph_data = GlyphData::default();
+        let glyphs_dir = path.join(GLYPHS_DIR);
+        fs::create_dir_all(&glyphs_dir).map_err(Error::IoError)?;
+        // everything in the glyphs dir gets loaded, so remove glyphs we no longer have
+        for entry in fs::read_dir(&glyphs_dir).map_err(Error::IoError)? {
+            let entry_path = entry.map_err(Error::IoError)?.path();
+            if entry_path.extension() == Some(OsStr::new("glyph")) {
+                fs::remove_file(entry_path).map_err(Error::IoError)?;
+            }
+        }
+
+        let fontinfo = Plist::from(self.fontinfo()).to_string();
+        fs::write(path.join(FONTINFO_FILE), fontinfo).map_err(Error::IoError)?;
+
+        let mut file_names = HashSet::new();
+        let mut order = Vec::new();
+        for glyph in self.ordered_glyphs() {
+            let file_name = glyph_file_name(&glyph.name, &mut file_names);
+            let contents = glyph_plist(glyph, &glyph_data).to_string();
+            fs::write(glyphs_dir.join(file_name), contents).map_err(Error::IoError)?;
+            order.push(Plist::from(glyph.name.as_str()));
+        }
+        fs::write(path.join(ORDER_FILE), Plist::Array(order).to_string()).map_err(Error::IoError)
+    }
+
+    /// Glyphs in `glyph_order`, followed by any it doesn't mention
+    fn ordered_glyphs(&self) -> impl Iterator<Item = &Glyph> {
+        let ordered = self
+            .glyph_order
+            .iter()
+            .filter_map(|name| self.glyphs.get(name));
+        let in_order = self.glyph_order.iter().collect::<HashSet<_>>();
+        let rest = self
+            .glyphs
+            .values()
+            .filter(move |glyph| !in_order.contains(&glyph.name));
+        ordered.chain(rest)
+    }
+
+    /// Everything but the glyphs
+    fn fontinfo(&self) -> Dictionary {
+        let mut dict = Dictionary::new();
+        dict.insert(".formatVersion".into(), Plist::Integer(3));
+        dict.insert("unitsPerEm".into(), (self.units_per_em as i64).into());
+        dict.insert("versionMajor".into(), (self.version_major as i64).into());
+        dict.insert("versionMinor".into(), (self.version_minor as i64).into());
+        if let Some(date) = &self.date {
+            dict.insert("date".into(), date.as_str().into());
+        }
+        if let Some(family_name) = self.names.get("familyNames") {
+            dict.insert("familyName".into(), family_name.as_str().into());
+        }
+        insert_if_not_empty(&mut dict, "properties", self.properties());
+        insert_if_not_empty(&mut dict, "customParameters", self.font_parameters());
+
+        let axes = self.axes.iter().map(axis_plist).collect();
+        insert_if_not_empty(&mut dict, "axes", axes);
+
+        // masters store metrics and numbers as lists, parallel to the font's lists of names
+        let metric_names = unique_keys(self.masters.iter().map(|m| m.metric_values.keys()));
+        let number_names = unique_keys(self.masters.iter().map(|m| m.number_values.keys()));
+        let metrics = metric_names
+            .iter()
+            .map(|name| dict_plist([("type", (*name).into())]))
+            .collect();
+        let numbers = number_names
+            .iter()
+            .map(|name| dict_plist([("name", (*name).into())]))
+            .collect();
+        insert_if_not_empty(&mut dict, "metrics", metrics);
+        insert_if_not_empty(&mut dict, "numbers", numbers);
+        let stems = self.stems.iter().map(stem_plist).collect();
+        insert_if_not_empty(&mut dict, "stems", stems);
+        let stem_names = self
+            .stems
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect::<Vec<_>>();
+        let masters = self
+            .masters
+            .iter()
+            .map(|master| master_plist(master, &metric_names, &number_names, &stem_names))
+            .collect();
+        insert_if_not_empty(&mut dict, "fontMaster", masters);
+
+        let instances = self
+            .instances
+            .iter()
+            .map(|instance| instance_plist(instance, &self.axes))
+            .collect();
+        insert_if_not_empty(&mut dict, "instances", instances);
+
+        let mut classes = Vec::new();
+        let mut prefixes = Vec::new();
+        let mut features = Vec::new();
+        for snippet in &self.features {
+            match feature_plist(snippet) {
+                (FeatureKind::Class, plist) => classes.push(plist),
+                (FeatureKind::Prefix, plist) => prefixes.push(plist),
+                (FeatureKind::Feature, plist) => features.push(plist),
+            }
+        }
+        insert_if_not_empty(&mut dict, "classes", classes);
+        insert_if_not_empty(&mut dict, "featurePrefixes", prefixes);
+        insert_if_not_empty(&mut dict, "features", features);
+
+        if let Some(kerning) = kerning_plist(&self.kerning_ltr) {
+            dict.insert("kerningLTR".into(), kerning);
+        }
+        if let Some(kerning) = kerning_plist(&self.kerning_rtl) {
+            dict.insert("kerningRTL".into(), kerning);
+        }
+        insert_other_stuff(&mut dict, &self.other_stuff);
+        dict
+    }
+
+    /// The names, except those overridden by a variable instance
+    fn properties(&self) -> Vec<Plist> {
+        let family_name = self.names.get("familyNames").map(String::as_str);
+        let overridden = self
+            .instances
+            .iter()
+            .filter(|instance| {
+                instance.active
+                    && instance.type_ == InstanceType::Variable
+                    && instance
+                        .family_name()
+                        .map(|name| Some(name) == family_name)
+                        .unwrap_or(true)
+            })
+            .flat_map(|instance| instance.properties.iter())
+            .filter(|name| !name.is_empty())
+            .map(|name| name.key.as_str())
+            .collect::<HashSet<_>>();
+
+        self.names
+            .iter()
+            .filter(|(key, _)| *key != "familyNames")
+            .map(|(key, value)| match key.as_str() {
+                "version" => ("versionString", value),
+                key => (key, value),
+            })
+            .filter(|(key, _)| !overridden.contains(key))
+            .map(|(key, value)| {
+                // the plural keys are the localizable ones
+                let mut dict = Dictionary::new();
+                dict.insert("key".into(), key.into());
+                if key.ends_with('s') {
+                    let value = dict_plist([
+                        ("language", "dflt".into()),
+                        ("value", value.as_str().into()),
+                    ]);
+                    dict.insert("values".into(), vec![value].into());
+                } else {
+                    dict.insert("value".into(), value.as_str().into());
+                }
+                dict.into()
+            })
+            .collect()
+    }
+
+    fn font_parameters(&self) -> Vec<Plist> {
+        let mut params = Vec::new();
+        if self.masters.len() > 1 {
+            let origin = self.default_master().id.as_str();
+            params.push(param_plist("Variable Font Origin", origin.into()));
+        }
+        let mappings = self
+            .axes
+            .iter()
+            .filter_map(|axis| {
+                let mapping = self.axis_mappings.get(&axis.name)?;
+                let user_to_design = mapping
+                    .iter()
+                    .map(|(user, design)| (user.to_string().into(), design.into_inner().into()))
+                    .collect::<Dictionary>();
+                Some((axis.tag.as_str().into(), user_to_design.into()))
+            })
+            .collect::<Dictionary>();
+        if !mappings.is_empty() {
+            params.push(param_plist("Axis Mappings", mappings.into()));
+        }
+        for virtual_master in &self.virtual_masters {
+            params.push(param_plist(
+                "Virtual Master",
+                axis_locations_plist(virtual_master),
+            ));
+        }
+        params.extend(custom_parameters_plist(&self.custom_parameters));
+        params
+    }
+}
+
+/// The names in the order they first appear
+fn unique_keys<'a, T: AsRef<str> + 'a>(
+    keys: impl Iterator<Item = impl Iterator<Item = &'a T>>,
+) -> Vec<&'a str> {
+    let mut seen = HashSet::new();
+    keys.flatten()
+        .map(AsRef::as_ref)
+        .filter(|key| seen.insert(*key))
+        .collect()
+}
+
+/// Add the keys we kept but don't use, without replacing what we wrote
+fn insert_other_stuff(dict: &mut Dictionary, other_stuff: &BTreeMap<String, Plist>) {
+    for (key, value) in other_stuff {
+        dict.entry(key.as_str().into())
+            .or_insert_with(|| value.clone());
+    }
+}
+
+fn insert_if_not_empty(dict: &mut Dictionary, key: &str, values: Vec<Plist>) {
+    if !values.is_empty() {
+        dict.insert(key.into(), values.into());
+    }
+}
+
+fn dict_plist<const N: usize>(items: [(&str, Plist); N]) -> Plist {
+    items
+        .into_iter()
+        .map(|(key, value)| (key.into(), value))
+        .collect::<Dictionary>()
+        .into()
+}
+
+fn bool_plist(value: bool) -> Plist {
+    Plist::Integer(value as i64)
+}
+
+fn float_plist(value: OrderedFloat<f64>) -> Plist {
+    value.into_inner().into()
+}
+
+fn floats_plist(values: &[OrderedFloat<f64>]) -> Plist {
+    values
+        .iter()
+        .copied()
+        .map(float_plist)
+        .collect::<Vec<_>>()
+        .into()
+}
+
+fn ints_plist(values: impl Iterator<Item = i64>) -> Plist {
+    values.map(Plist::Integer).collect::<Vec<_>>().into()
+}
+
+fn point_plist(point: Point) -> Plist {
+    vec![point.x.into(), point.y.into()].into()
+}
+
+fn param_plist(name: &str, value: Plist) -> Plist {
+    dict_plist([("name", name.into()), ("value", value)])
+}
+
+fn axis_locations_plist(locations: &BTreeMap<String, OrderedFloat<f64>>) -> Plist {
+    locations
+        .iter()
+        .map(|(axis, location)| {
+            dict_plist([
+                ("Axis", axis.as_str().into()),
+                ("Location", float_plist(*location)),
+            ])
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// The raw parameters that parse to `params`
+fn custom_parameters_plist(params: &CustomParameters) -> Vec<Plist> {
+    // destructured so that adding a parameter without writing it is an error
+    let CustomParameters {
+        propagate_anchors,
+        use_typo_metrics,
+        is_fixed_pitch,
+        fs_type,
+        has_wws_names,
+        typo_ascender,
+        typo_descender,
+        typo_line_gap,
+        win_ascent,
+        win_descent,
+        hhea_ascender,
+        hhea_descender,
+        hhea_line_gap,
+        vhea_ascender,
+        vhea_descender,
+        vhea_line_gap,
+        underline_thickness,
+        underline_position,
+        strikeout_position,
+        strikeout_size,
+        subscript_x_offset,
+        subscript_x_size,
+        subscript_y_offset,
+        subscript_y_size,
+        superscript_x_offset,
+        superscript_x_size,
+        superscript_y_offset,
+        superscript_y_size,
+        unicode_range_bits,
+        codepage_range_bits,
+        panose,
+        lowest_rec_ppem,
+        hhea_caret_slope_run,
+        hhea_caret_slope_rise,
+        hhea_caret_offset,
+        vhea_caret_slope_run,
+        vhea_caret_slope_rise,
+        vhea_caret_offset,
+        meta_table,
+        baselines,
+        dont_use_production_names,
+        rename_glyphs,
+        reencode_glyphs,
+        remove_glyphs,
+        keep_glyphs,
+        disable_masters,
+        virtual_masters,
+        glyph_order,
+        gasp_table,
+        feature_for_feature_variations,
+        enforce_compatibility_check,
+        color_palettes,
+        color_palette_types,
+        // the fields above are what we write, so changes to them are saved
+        raw: _,
+    } = params;
+
+    let mut result = Vec::new();
+    let mut add = |name: &str, value: Option<Plist>| {
+        if let Some(value) = value {
+            result.push(param_plist(name, value));
+        }
+    };
+    let int = |value: &Option<i64>| value.map(Plist::Integer);
+    let bits = |bits: &Option<BTreeSet<u32>>| {
+        bits.as_ref()
+            .map(|bits| ints_plist(bits.iter().map(|bit| *bit as i64)))
+    };
+
+    add("Propagate Anchors", propagate_anchors.map(bool_plist));
+    add("Use Typo Metrics", use_typo_metrics.map(bool_plist));
+    add("isFixedPitch", is_fixed_pitch.map(bool_plist));
+    add("Has WWS Names", has_wws_names.map(bool_plist));
+    add(
+        "fsType",
+        fs_type.map(|fs_type| ints_plist((0..16).filter(|bit| fs_type & (1 << *bit) != 0))),
+    );
+    add("typoAscender", int(typo_ascender));
+    add("typoDescender", int(typo_descender));
+    add("typoLineGap", int(typo_line_gap));
+    add("winAscent", int(win_ascent));
+    add("winDescent", int(win_descent));
+    add("hheaAscender", int(hhea_ascender));
+    add("hheaDescender", int(hhea_descender));
+    add("hheaLineGap", int(hhea_line_gap));
+    add("vheaVertAscender", int(vhea_ascender));
+    add("vheaVertDescender", int(vhea_descender));
+    add("vheaVertLineGap", int(vhea_line_gap));
+    add("underlineThickness", underline_thickness.map(float_plist));
+    add("underlinePosition", underline_position.map(float_plist));
+    add("strikeoutPosition", int(strikeout_position));
+    add("strikeoutSize", int(strikeout_size));
+    add("subscriptXOffset", int(subscript_x_offset));
+    add("subscriptXSize", int(subscript_x_size));
+    add("subscriptYOffset", int(subscript_y_offset));
+    add("subscriptYSize", int(subscript_y_size));
+    add("superscriptXOffset", int(superscript_x_offset));
+    add("superscriptXSize", int(superscript_x_size));
+    add("superscriptYOffset", int(superscript_y_offset));
+    add("superscriptYSize", int(superscript_y_size));
+    add("unicodeRanges", bits(unicode_range_bits));
+    // bits below 64 are accepted in place of code page ids
+    add("codePageRanges", bits(codepage_range_bits));
+    add(
+        "panose",
+        panose
+            .as_ref()
+            .map(|panose| ints_plist(panose.iter().copied())),
+    );
+    add("openTypeHeadLowestRecPPEM", int(lowest_rec_ppem));
+    add("openTypeHheaCaretSlopeRun", int(hhea_caret_slope_run));
+    add("openTypeHheaCaretSlopeRise", int(hhea_caret_slope_rise));
+    add("openTypeHheaCaretOffset", int(hhea_caret_offset));
+    add("openTypeVheaCaretSlopeRun", int(vhea_caret_slope_run));
+    add("openTypeVheaCaretSlopeRise", int(vhea_caret_slope_rise));
+    add("openTypeVheaCaretOffset", int(vhea_caret_offset));
+    add(
+        "meta Table",
+        meta_table.as_ref().map(|meta| {
+            [("dlng", &meta.dlng), ("slng", &meta.slng)]
+                .into_iter()
+                .filter(|(_, langs)| !langs.is_empty())
+                .map(|(tag, langs)| {
+                    dict_plist([("tag", tag.into()), ("data", langs.join(",").into())])
+                })
+                .collect::<Vec<_>>()
+                .into()
+        }),
+    );
+    add(
+        "Baselines",
+        baselines.as_ref().map(|baselines| {
+            [
+                ("horizontal", &baselines.horizontal),
+                ("vertical", &baselines.vertical),
+            ]
+            .into_iter()
+            .filter(|(_, scripts)| !scripts.is_empty())
+            .map(|(axis, scripts)| {
+                let scripts = scripts
+                    .iter()
+                    .map(|(script, baselines)| {
+                        let baselines =
+                            std::iter::once(("default".into(), baselines.default.as_str().into()))
+                                .chain(baselines.positions.iter().map(|(tag, position)| {
+                                    (tag.clone(), Plist::Integer(*position))
+                                }))
+                                .collect::<Dictionary>();
+                        (script.clone(), baselines.into())
+                    })
+                    .collect::<Dictionary>();
+                (axis.into(), scripts.into())
+            })
+            .collect::<Dictionary>()
+            .into()
+        }),
+    );
+    add(
+        "Don't use Production Names",
+        dont_use_production_names.map(bool_plist),
+    );
+    add(
+        "Rename Glyphs",
+        rename_glyphs.as_ref().map(|renames| {
+            renames
+                .iter()
+                .map(|(old, new)| format!("{old}={new}").into())
+                .collect::<Vec<_>>()
+                .into()
+        }),
+    );
+    add(
+        "Reencode Glyphs",
+        reencode_glyphs.as_ref().map(|reencodings| {
+            reencodings
+                .iter()
+                .map(|(name, codepoints)| {
+                    let codepoints = codepoints
+                        .iter()
+                        .map(|cp| format!("{cp:04X}"))
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    format!("{name}={codepoints}").into()
+                })
+                .collect::<Vec<_>>()
+                .into()
+        }),
+    );
+    let names = |names: &Option<Vec<SmolStr>>| -> Option<Plist> {
+        names.as_ref().map(|names| {
+            names
+                .iter()
+                .map(|name| name.as_str().into())
+                .collect::<Vec<_>>()
+                .into()
+        })
+    };
+    add("Remove Glyphs", names(remove_glyphs));
+    add("Keep Glyphs", names(keep_glyphs));
+    add("Disable Masters", names(disable_masters));
+    for virtual_master in virtual_masters.iter().flatten() {
+        add("Virtual Master", Some(axis_locations_plist(virtual_master)));
+    }
+    add("glyphOrder", names(glyph_order));
+    add(
+        "gasp Table",
+        gasp_table.as_ref().map(|gasp| {
+            gasp.iter()
+                .map(|(ppem, behavior)| (ppem.to_string().into(), Plist::Integer(*behavior)))
+                .collect::<Dictionary>()
+                .into()
+        }),
+    );
+    add(
+        "Feature for Feature Variations",
+        feature_for_feature_variations
+            .as_ref()
+            .map(|feature| feature.as_str().into()),
+    );
+    add(
+        "Enforce Compatibility Check",
+        enforce_compatibility_check.map(bool_plist),
+    );
+    add(
+        "Color Palettes",
+        color_palettes.as_ref().map(|palettes| {
+            palettes
+                .iter()
+                .map(|palette| {
+                    palette
+                        .iter()
+                        .map(|color| ints_plist(color.iter().map(|v| *v as i64)))
+                        .collect::<Vec<_>>()
+                        .into()
+                })
+                .collect::<Vec<_>>()
+                .into()
+        }),
+    );
+    add(
+        "Color Palette Types",
+        color_palette_types
+            .as_ref()
+            .map(|types| ints_plist(types.iter().map(|flags| *flags as i64))),
+    );
+    result
+}
+
+fn axis_plist(axis: &Axis) -> Plist {
+    let mut dict = Dictionary::new();
+    dict.insert("name".into(), axis.name.as_str().into());
+    dict.insert("tag".into(), axis.tag.as_str().into());
+    if let Some(hidden) = axis.hidden {
+        dict.insert("hidden".into(), bool_plist(hidden));
+    }
+    dict.into()
+}
+
+/// Values for each name; a master missing one gets a placeholder
+fn parallel_values<T>(names: &[&str], get: impl Fn(&str) -> Option<T>, empty: Plist) -> Vec<Plist>
+where
+    T: Into<Plist>,
+{
+    let mut values = names.iter().map(|name| get(name)).collect::<Vec<_>>();
+    // placeholders can't be told apart from real values, so leave off any we can
+    while values.last().is_some_and(Option::is_none) {
+        values.pop();
+    }
+    values
+        .into_iter()
+        .map(|value| value.map(Into::into).unwrap_or_else(|| empty.clone()))
+        .collect()
+}
+
+fn stem_plist(stem: &Stem) -> Plist {
+    let mut dict = Dictionary::new();
+    if stem.horizontal {
+        dict.insert("horizontal".into(), Plist::Integer(1));
+    }
+    dict.insert("name".into(), stem.name.as_str().into());
+    dict.into()
+}
+
+fn master_plist(
+    master: &FontMaster,
+    metric_names: &[&str],
+    number_names: &[&str],
+    stem_names: &[&str],
+) -> Plist {
+    let mut dict = Dictionary::new();
+    dict.insert("id".into(), master.id.as_str().into());
+    dict.insert("name".into(), master.name.as_str().into());
+    if !master.axes_values.is_empty() {
+        dict.insert("axesValues".into(), floats_plist(&master.axes_values));
+    }
+    let metric_values = parallel_values(
+        metric_names,
+        |name| {
+            master.metric_values.get(name).map(|metric| {
+                let mut dict = Dictionary::new();
+                if metric.pos != 0.0 {
+                    dict.insert("pos".into(), float_plist(metric.pos));
+                }
+                if metric.over != 0.0 {
+                    dict.insert("over".into(), float_plist(metric.over));
+                }
+                dict
+            })
+        },
+        Dictionary::new().into(),
+    );
+    insert_if_not_empty(&mut dict, "metricValues", metric_values);
+    let number_values = parallel_values(
+        number_names,
+        |name| master.number_values.get(name).copied().map(float_plist),
+        Plist::Integer(0),
+    );
+    insert_if_not_empty(&mut dict, "numberValues", number_values);
+    let stem_values = parallel_values(
+        stem_names,
+        |name| master.stem_values.get(name).copied().map(float_plist),
+        Plist::Integer(0),
+    );
+    insert_if_not_empty(&mut dict, "stemValues", stem_values);
+    insert_if_not_empty(
+        &mut dict,
+        "customParameters",
+        custom_parameters_plist(&master.custom_parameters),
+    );
+    let guides = master.guides.iter().map(guide_plist).collect();
+    insert_if_not_empty(&mut dict, "guides", guides);
+    let mut user_data = master
+        .other_stuff
+        .get("userData")
+        .and_then(Plist::as_dict)
+        .cloned()
+        .unwrap_or_default();
+    user_data.remove(MATH_CONSTANTS_KEY);
+    if !master.math_constants.is_empty() {
+        let constants = master
+            .math_constants
+            .iter()
+            .map(|(name, value)| (name.clone(), float_plist(*value)))
+            .collect::<Dictionary>();
+        user_data.insert(MATH_CONSTANTS_KEY.into(), constants.into());
+    }
+    if !user_data.is_empty() {
+        dict.insert("userData".into(), user_data.into());
+    }
+    insert_other_stuff(&mut dict, &master.other_stuff);
+    dict.into()
+}
+
+fn instance_plist(instance: &Instance, axes: &[Axis]) -> Plist {
+    let mut dict = Dictionary::new();
+    dict.insert("name".into(), instance.name.as_str().into());
+    if instance.type_ == InstanceType::Variable {
+        dict.insert("type".into(), "variable".into());
+    }
+    if !instance.active {
+        dict.insert("exports".into(), Plist::Integer(0));
+    }
+    if !instance.axes_values.is_empty() {
+        dict.insert("axesValues".into(), floats_plist(&instance.axes_values));
+    }
+    let properties = instance.properties.iter().map(raw_name_plist).collect();
+    insert_if_not_empty(&mut dict, "properties", properties);
+
+    let mut params = custom_parameters_plist(&instance.custom_parameters);
+    // each instance maps its one location, whether or not that came from a parameter
+    let locations = axes
+        .iter()
+        .filter_map(|axis| {
+            let (user, _) = instance.axis_mappings.get(&axis.name)?.iter().next()?;
+            Some((axis.name.clone(), *user))
+        })
+        .collect::<BTreeMap<_, _>>();
+    if !locations.is_empty() {
+        params.push(param_plist(
+            "Axis Location",
+            axis_locations_plist(&locations),
+        ));
+    }
+    insert_if_not_empty(&mut dict, "customParameters", params);
+    dict.into()
+}
+
+fn raw_name_plist(name: &RawName) -> Plist {
+    let mut dict = Dictionary::new();
+    dict.insert("key".into(), name.key.as_str().into());
+    if let Some(value) = &name.value {
+        dict.insert("value".into(), value.as_str().into());
+    }
+    let values = name
+        .values
+        .iter()
+        .map(|value| {
+            dict_plist([
+                ("language", value.language.as_str().into()),
+                ("value", value.value.as_str().into()),
+            ])
+        })
+        .collect();
+    insert_if_not_empty(&mut dict, "values", values);
+    dict.into()
+}
+
+enum FeatureKind {
+    Class,
+    Prefix,
+    Feature,
+}
+
+/// Split a snippet back into the parts the loader assembles it from.
+///
+/// Code that wasn't assembled by the loader becomes an unnamed prefix.
+fn feature_plist(snippet: &FeatureSnippet) -> (FeatureKind, Plist) {
+    let (kind, name, automatic, code) = split_feature_snippet(&snippet.content);
+    let mut dict = Dictionary::new();
+    if let Some(name) = name {
+        let key = match kind {
+            FeatureKind::Feature => "tag",
+            FeatureKind::Class | FeatureKind::Prefix => "name",
+        };
+        dict.insert(key.into(), name.into());
+    }
+    if automatic {
+        dict.insert("automatic".into(), Plist::Integer(1));
+    }
+    if snippet.disabled {
+        dict.insert("disabled".into(), Plist::Integer(1));
+    }
+    dict.insert("code".into(), code.into());
+    (kind, dict.into())
+}
+
+/// The kind, name, whether it is automatic, and the code
+fn split_feature_snippet(content: &str) -> (FeatureKind, Option<&str>, bool, &str) {
+    fn strip_automatic(code: &str) -> (bool, &str) {
+        match code.strip_prefix(AUTOMATIC) {
+            Some(code) => (true, code),
+            None => (false, code),
+        }
+    }
+
+    let prefix = content
+        .strip_prefix("# Prefix: ")
+        .and_then(|rest| rest.split_once('\n'));
+    if let Some((name, code)) = prefix {
+        let (automatic, code) = strip_automatic(code);
+        // an empty name is how the loader writes a missing one
+        let name = Some(name).filter(|name| !name.is_empty());
+        return (FeatureKind::Prefix, name, automatic, code);
+    }
+
+    let (automatic, class) = strip_automatic(content);
+    let class = class
+        .strip_prefix('@')
+        .and_then(|class| class.strip_suffix("\n];"))
+        .and_then(|class| class.split_once(" = [ "))
+        .filter(|(name, _)| !name.contains(char::is_whitespace));
+    if let Some((name, code)) = class {
+        return (FeatureKind::Class, Some(name), automatic, code);
+    }
+
+    let feature = content
+        .strip_prefix("feature ")
+        .and_then(|rest| rest.split_once(" {\n"))
+        .and_then(|(name, rest)| {
+            let inner = rest.strip_suffix(&format!("\n}} {name};"))?;
+            Some((name, inner))
+        });
+    if let Some((name, inner)) = feature {
+        let (automatic, code) = strip_automatic(inner);
+        return (FeatureKind::Feature, Some(name), automatic, code);
+    }
+    (FeatureKind::Prefix, None, false, content)
+}
+
+fn kerning_plist(kerning: &Kerning) -> Option<Plist> {
+    let mut masters = Dictionary::new();
+    for (master_id, pairs) in kerning.iter() {
+        let mut lefts = BTreeMap::<_, Dictionary>::new();
+        for ((left, right), value) in pairs {
+            lefts
+                .entry(left.clone())
+                .or_default()
+                .insert(right.clone(), float_plist(*value));
+        }
+        let lefts = lefts
+            .into_iter()
+            .map(|(left, rights)| (left, rights.into()))
+            .collect::<Dictionary>();
+        masters.insert(master_id.as_str().into(), lefts.into());
+    }
+    (!masters.is_empty()).then(|| masters.into())
+}
+
+fn glyph_plist(glyph: &Glyph, glyph_data: &GlyphData) -> Plist {
+    let mut dict = Dictionary::new();
+    dict.insert("glyphname".into(), glyph.name.as_str().into());
+    if !glyph.export {
+        dict.insert("export".into(), Plist::Integer(0));
+    }
+    match glyph.unicode.len() {
+        0 => (),
+        1 => {
+            let codepoint = *glyph.unicode.first().unwrap();
+            dict.insert("unicode".into(), Plist::Integer(codepoint as i64));
+        }
+        _ => {
+            let codepoints = glyph
+                .unicode
+                .iter()
+                .map(|cp| Plist::Integer(*cp as i64))
+                .collect::<Vec<_>>();
+            dict.insert("unicode".into(), codepoints.into());
+        }
+    }
+
+    // the loader fills these in from glyph data, so we only write overrides
+    let defaults = glyph_data.query(&glyph.name, Some(&glyph.unicode));
+    let default_category = defaults.as_ref().map(|result| result.category);
+    if let Some(category) = glyph.category.filter(|c| Some(*c) != default_category) {
+        dict.insert("category".into(), category.to_string().into());
+    }
+    let default_sub_category = defaults.as_ref().and_then(|result| result.subcategory);
+    if let Some(sub_category) = glyph
+        .sub_category
+        .filter(|c| Some(*c) != default_sub_category)
+    {
+        dict.insert("subCategory".into(), sub_category.to_string().into());
+    }
+    let default_production_name = defaults
+        .as_ref()
+        .and_then(|result| result.production_name.as_ref())
+        .map(ToString::to_string);
+    if let Some(production_name) = glyph
+        .production_name
+        .as_ref()
+        .filter(|name| default_production_name.as_deref() != Some(name.as_str()))
+    {
+        dict.insert("production".into(), production_name.as_str().into());
+    }
+
+    if let Some(group) = &glyph.left_kern {
+        dict.insert("kernLeft".into(), group.as_str().into());
+    }
+    if let Some(group) = &glyph.right_kern {
+        dict.insert("kernRight".into(), group.as_str().into());
+    }
+    if let Some(note) = &glyph.note {
+        dict.insert("note".into(), note.as_str().into());
+    }
+    let tags = glyph.tags.iter().map(|tag| tag.as_str().into()).collect();
+    insert_if_not_empty(&mut dict, "tags", tags);
+    insert_metrics_keys(&mut dict, &glyph.metrics_keys);
+    let mut user_data = glyph
+        .other_stuff
+        .get("userData")
+        .and_then(Plist::as_dict)
+        .cloned()
+        .unwrap_or_default();
+    user_data.remove(MATH_EXTENDED_SHAPE_KEY);
+    if glyph.math_extended_shape {
+        user_data.insert(MATH_EXTENDED_SHAPE_KEY.into(), Plist::Integer(1));
+    }
+    if !user_data.is_empty() {
+        dict.insert("userData".into(), user_data.into());
+    }
+    let layers = glyph
+        .layers
+        .iter()
+        .chain(glyph.bracket_layers.iter())
+        .map(layer_plist)
+        .collect();
+    insert_if_not_empty(&mut dict, "layers", layers);
+    insert_other_stuff(&mut dict, &glyph.other_stuff);
+    dict.into()
+}
+
+fn layer_plist(layer: &Layer) -> Plist {
+    let mut dict = Dictionary::new();
+    dict.insert("layerId".into(), layer.layer_id.as_str().into());
+    if let Some(master_id) = &layer.associated_master_id {
+        dict.insert("associatedMasterId".into(), master_id.as_str().into());
+    }
+    dict.insert("width".into(), float_plist(layer.width));
+    if let Some(vert_width) = layer.vert_width {
+        dict.insert("vertWidth".into(), float_plist(vert_width));
+    }
+    if let Some(vert_origin) = layer.vert_origin {
+        dict.insert("vertOrigin".into(), float_plist(vert_origin));
+    }
+    if layer.attributes != LayerAttributes::default() {
+        dict.insert("attr".into(), layer_attributes_plist(&layer.attributes));
+    }
+    insert_metrics_keys(&mut dict, &layer.metrics_keys);
+    let shapes = layer.shapes.iter().map(shape_plist).collect();
+    insert_if_not_empty(&mut dict, "shapes", shapes);
+    let anchors = layer.anchors.iter().map(anchor_plist).collect();
+    insert_if_not_empty(&mut dict, "anchors", anchors);
+    let guides = layer.guides.iter().map(guide_plist).collect();
+    insert_if_not_empty(&mut dict, "guides", guides);
+    if let Some(image) = &layer.background_image {
+        dict.insert("backgroundImage".into(), background_image_plist(image));
+    }
+    insert_other_stuff(&mut dict, &layer.other_stuff);
+    dict.into()
+}
+
+fn background_image_plist(image: &BackgroundImage) -> Plist {
+    let mut dict = Dictionary::new();
+    dict.insert("imagePath".into(), image.path.as_str().into());
+    if image.pos != Point::ZERO {
+        dict.insert("pos".into(), point_plist(image.pos));
+    }
+    insert_other_stuff(&mut dict, &image.other_stuff);
+    dict.into()
+}
+
+fn insert_metrics_keys(dict: &mut Dictionary, metrics_keys: &MetricsKeys) {
+    for (key, value) in [
+        ("metricLeft", &metrics_keys.left),
+        ("metricRight", &metrics_keys.right),
+        ("metricWidth", &metrics_keys.width),
+    ] {
+        if let Some(value) = value {
+            dict.insert(key.into(), value.as_str().into());
+        }
+    }
+}
+
+fn layer_attributes_plist(attributes: &LayerAttributes) -> Plist {
+    let mut dict = Dictionary::new();
+    if !attributes.coordinates.is_empty() {
+        dict.insert("coordinates".into(), floats_plist(&attributes.coordinates));
+    }
+    if attributes.color {
+        dict.insert("color".into(), Plist::Integer(1));
+    }
+    match attributes.color_palette {
+        Some(FOREGROUND_PALETTE_INDEX) => {
+            dict.insert("colorPalette".into(), "*".into());
+        }
+        Some(idx) => {
+            dict.insert("colorPalette".into(), (idx as i64).into());
+        }
+        None => (),
+    }
+    if let Some(size) = attributes.sbix_size {
+        dict.insert("sbixSize".into(), size.into());
+    }
+    if attributes.svg {
+        dict.insert("svg".into(), Plist::Integer(1));
+    }
+    let axis_rules = attributes
+        .axis_rules
+        .iter()
+        .map(|rule| {
+            let mut dict = Dictionary::new();
+            if let Some(min) = rule.min {
+                dict.insert("min".into(), min.into());
+            }
+            if let Some(max) = rule.max {
+                dict.insert("max".into(), max.into());
+            }
+            dict.into()
+        })
+        .collect();
+    insert_if_not_empty(&mut dict, "axisRules", axis_rules);
+    dict.into()
+}
+
+fn anchor_plist(anchor: &Anchor) -> Plist {
+    let mut dict = Dictionary::new();
+    dict.insert("name".into(), anchor.name.as_str().into());
+    if anchor.pos != Point::ZERO {
+        dict.insert("pos".into(), point_plist(anchor.pos));
+    }
+    dict.into()
+}
+
+fn guide_plist(guide: &Guide) -> Plist {
+    let mut dict = Dictionary::new();
+    if let Some(name) = &guide.name {
+        dict.insert("name".into(), name.as_str().into());
+    }
+    if guide.pos != Point::ZERO {
+        dict.insert("pos".into(), point_plist(guide.pos));
+    }
+    if guide.angle != 0.0 {
+        dict.insert("angle".into(), float_plist(guide.angle));
+    }
+    if guide.locked {
+        dict.insert("locked".into(), Plist::Integer(1));
+    }
+    insert_other_stuff(&mut dict, &guide.other_stuff);
+    dict.into()
+}
+
+fn shape_plist(shape: &Shape) -> Plist {
+    let mut dict = match shape {
+        Shape::Path(path) => path_dict(path),
+        Shape::Component(component) => component_dict(component),
+    };
+    if shape.attributes().gradient != Gradient::default() {
+        let gradient = gradient_plist(&shape.attributes().gradient);
+        dict.insert("attr".into(), dict_plist([("gradient", gradient)]));
+    }
+    dict.into()
+}
+
+fn path_dict(path: &Path) -> Dictionary {
+    let mut dict = Dictionary::new();
+    dict.insert("closed".into(), bool_plist(path.closed));
+    let nodes = path.nodes.iter().map(node_plist).collect::<Vec<_>>();
+    dict.insert("nodes".into(), nodes.into());
+    dict
+}
+
+fn node_plist(node: &Node) -> Plist {
+    let node_type = match node.node_type {
+        NodeType::Line => "l",
+        NodeType::LineSmooth => "ls",
+        NodeType::OffCurve => "o",
+        NodeType::Curve => "c",
+        NodeType::CurveSmooth => "cs",
+        NodeType::QCurve => "q",
+        NodeType::QCurveSmooth => "qs",
+    };
+    vec![node.pt.x.into(), node.pt.y.into(), node_type.into()].into()
+}
+
+fn component_dict(component: &Component) -> Dictionary {
+    let mut dict = Dictionary::new();
+    dict.insert("ref".into(), component.name.as_str().into());
+    if let Some(anchor) = &component.anchor {
+        dict.insert("anchor".into(), anchor.as_str().into());
+    }
+    match decompose_transform(component.transform) {
+        Some((pos, angle, scale)) => {
+            if pos != Point::ZERO {
+                dict.insert("pos".into(), point_plist(pos));
+            }
+            if angle != 0.0 {
+                dict.insert("angle".into(), angle.into());
+            }
+            if scale != (1.0, 1.0) {
+                dict.insert("scale".into(), vec![scale.0.into(), scale.1.into()].into());
+            }
+        }
+        // Glyphs 3 still reads the Glyphs 2 matrix
+        None => {
+            let [a, b, c, d, e, f] = component.transform.as_coeffs();
+            let transform = format!("{{{a}, {b}, {c}, {d}, {e}, {f}}}");
+            dict.insert("transform".into(), transform.into());
+        }
+    }
+    dict
+}
+
+/// The pos, angle and scale that load as exactly this transform, if any.
+fn decompose_transform(transform: Affine) -> Option<(Point, f64, (f64, f64))> {
+    let [a, b, c, d, e, f] = transform.as_coeffs();
+    let angle = b.atan2(a).to_degrees();
+    // rounding lets common angles hit the exact rotations
+    let angle = (angle * 1e6).round() / 1e6;
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let scale = (a.hypot(b), d * cos - c * sin);
+
+    let rebuilt = Affine::translate((e, f))
+        * normalized_rotation(angle)
+        * Affine::scale_non_uniform(scale.0, scale.1);
+    (AffineForEqAndHash::from(rebuilt) == transform.into()).then_some((
+        Point::new(e, f),
+        angle,
+        scale,
+    ))
+}
+
+fn gradient_plist(gradient: &Gradient) -> Plist {
+    let mut dict = Dictionary::new();
+    if !gradient.start.is_empty() {
+        dict.insert("start".into(), floats_plist(&gradient.start));
+    }
+    if !gradient.end.is_empty() {
+        dict.insert("end".into(), floats_plist(&gradient.end));
+    }
+    let colors = gradient.colors.iter().map(color_plist).collect();
+    insert_if_not_empty(&mut dict, "colors", colors);
+    if !gradient.style.is_empty() {
+        dict.insert("type".into(), gradient.style.as_str().into());
+    }
+    dict.into()
+}
+
+fn color_plist(color: &Color) -> Plist {
+    let rgba = ints_plist([color.r, color.g, color.b, color.a].into_iter());
+    vec![rgba, float_plist(color.stop_offset)].into()
+}
+
+/// A file name for a glyph in a package.
+///
+/// Like Glyphs, we mark uppercase letters so names that differ only in case
+/// don't collide on case-insensitive file systems.
+fn glyph_file_name(name: &str, used: &mut HashSet<String>) -> String {
+    let mut stem = String::new();
+    for (i, c) in name.chars().enumerate() {
+        match c {
+            '.' if i == 0 => stem.push('_'),
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => stem.push('_'),
+            c if c.is_control() => stem.push('_'),
+            c if c.is_uppercase() => {
+                stem.push(c);
+                stem.push('_');
+            }
+            c => stem.push(c),
+        }
+    }
+    let mut file_name = format!("{stem}.glyph");
+    let mut suffix = 1;
+    while !used.insert(file_name.to_lowercase()) {
+        file_name = format!("{stem}#{suffix}.glyph");
+        suffix += 1;
+    }
+    file_name
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{
+        font::{RawFont, RawShape},
+        plist::FromPlist,
+    };
+
+    fn testdata_dir() -> PathBuf {
+        // working dir varies CLI vs VSCode
+        let mut dir = path::Path::new("../resources/testdata");
+        if !dir.is_dir() {
+            dir = path::Path::new("./resources/testdata");
+        }
+        assert!(dir.is_dir());
+        dir.to_path_buf()
+    }
+
+    type SortedMappings = Vec<(String, Vec<(OrderedFloat<f64>, OrderedFloat<f64>)>)>;
+
+    /// Axis mappings come back sorted by user value, as text, because they
+    /// are written as a dictionary; take them out so they can be compared
+    /// without order.
+    fn take_axis_mappings(font: &mut Font) -> SortedMappings {
+        let mappings = std::mem::take(&mut font.axis_mappings);
+        font.axes
+            .iter()
+            .filter_map(|axis| {
+                let mut values = mappings
+                    .get(&axis.name)?
+                    .iter()
+                    .copied()
+                    .collect::<Vec<_>>();
+                values.sort();
+                Some((axis.name.clone(), values))
+            })
+            .collect()
+    }
+
+    /// The parameters as written in the source come back as we write them,
+    /// which is not necessarily in the same order or form, so clear them.
+    fn clear_raw_custom_parameters(font: &mut Font) {
+        font.custom_parameters.raw.clear();
+        for master in font.masters.iter_mut() {
+            master.custom_parameters.raw.clear();
+        }
+        for instance in font.instances.iter_mut() {
+            instance.custom_parameters.raw.clear();
+        }
+    }
+
+    fn assert_same_font(mut expected: Font, mut actual: Font, context: &dyn std::fmt::Debug) {
+        assert_eq!(
+            take_axis_mappings(&mut expected),
+            take_axis_mappings(&mut actual),
+            "{context:?}"
+        );
+        clear_raw_custom_parameters(&mut expected);
+        clear_raw_custom_parameters(&mut actual);
+        pretty_assertions::assert_eq!(expected, actual, "{context:?}");
+    }
+
+    fn reload(font: &Font) -> Font {
+        let text = font.to_plist().to_string();
+        let raw = RawFont::load_from_string(&text).unwrap();
+        Font::try_from(raw).unwrap()
+    }
+
+    #[test]
+    fn round_trip_test_corpus() {
+        let mut n_checked = 0;
+        for dir in ["glyphs2", "glyphs3"] {
+            let mut paths = fs::read_dir(testdata_dir().join(dir))
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|ext| ext == "glyphs" || ext == "glyphspackage")
+                })
+                .collect::<Vec<_>>();
+            paths.sort();
+            for path in paths {
+                // some test files are deliberately broken
+                let Ok(font) = Font::load_raw(&path) else {
+                    continue;
+                };
+                let reloaded = reload(&font);
+                assert_same_font(font, reloaded, &path);
+                n_checked += 1;
+            }
+        }
+        assert!(n_checked > 100, "only checked {n_checked} files");
+    }
+
+    #[test]
+    fn save_lossless() {
+        let tempdir = tempfile::tempdir().unwrap();
+        for name in ["Oswald-O.glyphs", "IntermediateLayer.glyphs"] {
+            let font = Font::load_lossless(testdata_dir().join("glyphs3").join(name)).unwrap();
+            let out = tempdir.path().join(name);
+            font.save(&out).unwrap();
+            let saved = Font::load_lossless(&out).unwrap();
+            assert_same_font(font, saved, &out);
+        }
+    }
+
+    #[test]
+    fn component_transforms() {
+        for transform in [
+            Affine::IDENTITY,
+            Affine::translate((10.0, -20.0)),
+            Affine::translate((5.0, 5.0)) * normalized_rotation(90.0),
+            Affine::scale_non_uniform(-1.0, 1.0),
+            Affine::new([0.5, 0.25, -0.1, 0.8, 3.0, 4.0]),
+        ] {
+            let component = Component {
+                name: "a".into(),
+                transform,
+                anchor: None,
+                attributes: Default::default(),
+            };
+            let text = Plist::from(component_dict(&component)).to_string();
+            let raw = RawShape::parse_plist(&text).unwrap();
+            let Shape::Component(loaded) = Shape::try_from(raw).unwrap() else {
+                panic!("not a component: {text}");
+            };
+            assert_eq!(component, loaded, "{text}");
+        }
+    }
+
+    #[test]
+    fn feature_snippets() {
+        for (content, kind) in [
+            ("# automatic\n@Uppercase = [ A B C\n];", "classes"),
+            (
+                "# Prefix: languagesystems\nlanguagesystem DFLT dflt;",
+                "featurePrefixes",
+            ),
+            (
+                "feature liga {\n# automatic\nsub f i by f_i;\n} liga;",
+                "features",
+            ),
+        ] {
+            let font = Font {
+                units_per_em: 1000,
+                features: vec![FeatureSnippet::new(content.into(), true)],
+                ..Default::default()
+            };
+            let plist = font.to_plist();
+            assert!(plist.get(kind).is_some(), "{kind}: {plist:?}");
+            pretty_assertions::assert_eq!(font.features, reload(&font).features);
+        }
+    }
+
+    #[test]
+    fn save_package() {
+        let source = testdata_dir().join("glyphs3/WghtVar.glyphspackage");
+        let mut font = Font::load_raw(&source).unwrap();
+        let tempdir = tempfile::tempdir().unwrap();
+        let out = tempdir.path().join("WghtVar.glyphspackage");
+        font.save(&out).unwrap();
+        // a glyph that is removed must not come back
+        font.glyphs.remove("space");
+        font.glyph_order.retain(|name| name != "space");
+        font.save(&out).unwrap();
+
+        let saved = Font::load_raw(&out).unwrap();
+        assert_same_font(font, saved, &out);
+    }
+
+    #[test]
+    fn glyph_file_names() {
+        let mut used = HashSet::new();
+        assert_eq!("A_.glyph", glyph_file_name("A", &mut used));
+        assert_eq!("a.glyph", glyph_file_name("a", &mut used));
+        assert_eq!("_notdef.glyph", glyph_file_name(".notdef", &mut used));
+        assert_eq!("a_b.glyph", glyph_file_name("a/b", &mut used));
+        assert_eq!("a_b#1.glyph", glyph_file_name("a:b", &mut used));
+    }
+}
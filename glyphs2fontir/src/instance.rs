@@ -0,0 +1,304 @@
+//! Applying the custom parameters of the instance being exported
+//!
+//! Parameters set on the instance take precedence over those of the font,
+//! much as they do when Glyphs exports it. Some are applied to the font
+//! before we convert it, others when the relevant IR is made.
+
+use std::collections::HashSet;
+
+use glyphs_reader::{CustomParameters, Font, Layer, Plist};
+use log::warn;
+use smol_str::SmolStr;
+use write_fonts::types::NameId;
+
+use crate::rename_glyphs;
+
+/// Parameters that filter glyphs in Glyphs, which we can't apply
+const FILTER_PARAMETERS: &[&str] = &["Filter", "PreFilter"];
+
+/// Apply the parameters that change the glyphs of `font`
+pub(crate) fn resolve(font: &mut Font) {
+    let instance = font
+        .variable_export_settings(font.default_master())
+        .map(|instance| instance.custom_parameters.clone())
+        .unwrap_or_default();
+    let font_params = &font.custom_parameters;
+    let renames = either(&instance, font_params, |p| p.rename_glyphs.as_ref());
+    let reencodings = either(&instance, font_params, |p| p.reencode_glyphs.as_ref());
+    let remove = either(&instance, font_params, |p| p.remove_glyphs.as_ref());
+    let keep = either(&instance, font_params, |p| p.keep_glyphs.as_ref());
+
+    disable_masters(
+        font,
+        instance.disable_masters.as_deref().unwrap_or_default(),
+    );
+
+    for name in FILTER_PARAMETERS {
+        for filter in instance.get_all(name).chain(font_params.get_all(name)) {
+            warn!("The {name} custom parameter is not supported, ignoring {filter:?}");
+        }
+    }
+
+    // the glyphs this removes are decomposed where they're used, like any
+    // other glyph that isn't exported
+    for glyph in font.glyphs.values_mut() {
+        let matches = |patterns: &[SmolStr]| {
+            patterns
+                .iter()
+                .any(|pattern| matches_wildcard(pattern, &glyph.name))
+        };
+        if matches(&remove) || (!keep.is_empty() && !matches(&keep)) {
+            glyph.export = false;
+        }
+    }
+
+    rename_glyphs::apply(font, &renames, &reencodings);
+}
+
+/// Names set by the parameters of the instance being exported
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct NameOverrides {
+    /// From 'familyName', replaces the family the other names are derived from
+    pub(crate) family_name: Option<String>,
+    /// From 'preferredFamilyName', replaces the typographic (ID 16) family name
+    pub(crate) typographic_family_name: Option<String>,
+    /// From 'preferredSubfamilyName', replaces the typographic (ID 17) subfamily name
+    pub(crate) typographic_subfamily_name: Option<String>,
+    /// From 'Name Table Entry', each replaces a single name outright
+    pub(crate) entries: Vec<(NameId, String)>,
+}
+
+/// The names the instance being exported sets with custom parameters
+///
+/// Name Table Entries of the font apply too, those of the instance taking
+/// precedence if both set the same name.
+pub(crate) fn name_overrides(font: &Font) -> NameOverrides {
+    let exported = font.variable_export_settings(font.default_master());
+    let empty = CustomParameters::default();
+    let instance = exported
+        .map(|instance| &instance.custom_parameters)
+        .unwrap_or(&empty);
+    let family_name = instance
+        .get("familyName")
+        .and_then(Plist::as_str)
+        .map(str::to_string);
+    let typographic_family_name = exported
+        .and_then(|instance| instance.typographic_family_name())
+        .map(str::to_string);
+    let typographic_subfamily_name = exported
+        .and_then(|instance| instance.typographic_subfamily_name())
+        .map(str::to_string);
+    let entries = font
+        .custom_parameters
+        .get_all("Name Table Entry")
+        .chain(instance.get_all("Name Table Entry"))
+        .filter_map(|entry| {
+            let parsed = entry.as_str().and_then(parse_name_table_entry);
+            if parsed.is_none() {
+                warn!(
+                    "Ignoring Name Table Entry {entry:?}, only Windows English names are supported"
+                );
+            }
+            parsed
+        })
+        .collect();
+    NameOverrides {
+        family_name,
+        typographic_family_name,
+        typographic_subfamily_name,
+        entries,
+    }
+}
+
+/// Parse `nameID; value` or `nameID platformID encodingID languageID; value`
+///
+/// We only write names for Windows English (US), so entries for other platforms or
+/// languages are None.
+fn parse_name_table_entry(entry: &str) -> Option<(NameId, String)> {
+    let (ids, value) = entry.split_once(';')?;
+    let parse = |id: &str| match id.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => id.parse::<u16>().ok(),
+    };
+    let ids = ids
+        .split_whitespace()
+        .map(parse)
+        .collect::<Option<Vec<_>>>()?;
+    match ids[..] {
+        [name_id] | [name_id, 3, 1, 0x409] => {
+            Some((NameId::new(name_id), value.trim().to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// Drop the masters the variable export leaves out, with their layers and kerning
+///
+/// The axes, and so the variation model, then only span the masters that remain.
+///
+/// This is the only way the variable export narrows the axes; we don't read axis
+/// ranges from its other parameters. Unknown ones are warned about when the source
+/// is read, use `--axis-limit` to limit the axes instead.
+fn disable_masters(font: &mut Font, names: &[SmolStr]) {
+    if names.is_empty() {
+        return;
+    }
+    for name in names {
+        if !font.masters.iter().any(|m| m.name == name.as_str()) {
+            warn!("Disable Masters lists '{name}' but there is no such master");
+        }
+    }
+    let default_id = font.default_master().id.clone();
+    let disabled: HashSet<_> = font
+        .masters
+        .iter()
+        .filter(|m| names.iter().any(|name| name.as_str() == m.name))
+        .filter(|m| {
+            if m.id == default_id {
+                warn!(
+                    "Disable Masters can't disable the default master '{}'",
+                    m.name
+                );
+            }
+            m.id != default_id
+        })
+        .map(|m| m.id.clone())
+        .collect();
+    remove_masters(font, &disabled);
+}
+
+/// Remove masters, along with their layers and kerning
+///
+/// The default master must not be among them.
+pub(crate) fn remove_masters(font: &mut Font, disabled: &HashSet<String>) {
+    if disabled.is_empty() {
+        return;
+    }
+    let default_id = font.default_master().id.clone();
+    font.masters.retain(|m| !disabled.contains(&m.id));
+    font.default_master_idx = font
+        .masters
+        .iter()
+        .position(|m| m.id == default_id)
+        .expect("the default master is never removed");
+    // intermediate and alternate layers go with the master they're associated with
+    let keep = |layer: &Layer| {
+        !disabled.contains(&layer.layer_id)
+            && !matches!(&layer.associated_master_id, Some(id) if disabled.contains(id))
+    };
+    for glyph in font.glyphs.values_mut() {
+        glyph.layers.retain(keep);
+        glyph.bracket_layers.retain(keep);
+    }
+    for id in disabled.iter() {
+        font.kerning_ltr.remove(id);
+        font.kerning_rtl.remove(id);
+    }
+}
+
+/// The value of a parameter of the instance, or failing that the font
+fn either<T: Clone>(
+    instance: &CustomParameters,
+    font: &CustomParameters,
+    get: impl Fn(&CustomParameters) -> Option<&Vec<T>>,
+) -> Vec<T> {
+    get(instance)
+        .or_else(|| get(font))
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Match a glyph name against a pattern where `*` matches anything
+fn matches_wildcard(pattern: &str, name: &str) -> bool {
+    let Some((prefix, rest)) = pattern.split_once('*') else {
+        return pattern == name;
+    };
+    let Some(name) = name.strip_prefix(prefix) else {
+        return false;
+    };
+    // try each place the wildcard could end
+    (0..=name.len())
+        .filter(|idx| name.is_char_boundary(*idx))
+        .any(|idx| matches_wildcard(rest, &name[idx..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn exported(font: &Font) -> Vec<&str> {
+        font.glyph_order
+            .iter()
+            .filter(|name| font.glyphs[*name].export)
+            .map(SmolStr::as_str)
+            .collect()
+    }
+
+    #[test]
+    fn disable_masters() {
+        let path = Path::new("../resources/testdata/glyphs3/WghtVar_DisableMasters.glyphs");
+        let mut font = Font::load(path).unwrap();
+        resolve(&mut font);
+        assert_eq!(
+            vec!["Regular", "Bold"],
+            font.masters
+                .iter()
+                .map(|m| m.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("Bold", font.default_master().name);
+        let master_ids: HashSet<_> = font.masters.iter().map(|m| m.id.as_str()).collect();
+        assert!(font
+            .glyphs
+            .values()
+            .flat_map(|g| g.layers.iter())
+            .all(|l| master_ids.contains(l.layer_id.as_str())));
+    }
+
+    #[test]
+    fn name_table_entries() {
+        assert_eq!(
+            Some((NameId::new(5), "Version 2.000".to_string())),
+            parse_name_table_entry("5; Version 2.000")
+        );
+        assert_eq!(
+            Some((NameId::FAMILY_NAME, "Family".to_string())),
+            parse_name_table_entry("1 3 1 0x409; Family")
+        );
+        assert_eq!(
+            Some((NameId::FAMILY_NAME, "Family".to_string())),
+            parse_name_table_entry("1 3 1 1033; Family")
+        );
+        // other platforms and languages
+        assert_eq!(None, parse_name_table_entry("1 1 0 0; Family"));
+        assert_eq!(None, parse_name_table_entry("1 3 1 0x407; Familie"));
+        assert_eq!(None, parse_name_table_entry("no separator"));
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(matches_wildcard("a", "a"));
+        assert!(!matches_wildcard("a", "a.sc"));
+        assert!(matches_wildcard("*.sc", "a.sc"));
+        assert!(matches_wildcard("*", "anything"));
+        assert!(matches_wildcard("a*.ss0*", "aacute.ss01"));
+        assert!(!matches_wildcard("*.sc", "a.ss01"));
+    }
+
+    #[test]
+    fn instance_parameters_override_font() {
+        let path = Path::new("../resources/testdata/glyphs3/InstanceGlyphSet.glyphs");
+        let mut font = Font::load(path).unwrap();
+        resolve(&mut font);
+        // the instance removes the small caps, and the font's removal of b is overridden
+        assert_eq!(vec!["a", "b"], exported(&font));
+
+        let mut font = Font::load(path).unwrap();
+        font.instances.clear();
+        font.custom_parameters.keep_glyphs = Some(vec!["*.sc".into()]);
+        resolve(&mut font);
+        assert_eq!(vec!["a.sc", "b.sc"], exported(&font));
+    }
+}
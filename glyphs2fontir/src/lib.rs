@@ -1,4 +1,7 @@
 //! Converts glyphs.app sources into IR for font compilation.
 mod erase_open_corners;
+mod instance;
+mod metrics_keys;
+mod rename_glyphs;
 pub mod source;
 mod toir;
@@ -0,0 +1,277 @@
+//! Applying metrics keys
+//!
+//! A metrics key is a formula that sets a sidebearing or the width of a layer
+//! from the spacing of another glyph, such as `=H` or `=|o+10`. Glyphs applies
+//! them as you edit, but a source can fall out of date when the glyphs the keys
+//! refer to change, so we apply them again.
+//!
+//! See <https://handbook.glyphsapp.com/spacing/#metrics-keys>
+
+use glyphs_reader::{Font, Glyph, Layer, MetricsKeys, Shape};
+use kurbo::{Affine, BezPath, Rect, Shape as _};
+use log::{debug, warn};
+use smol_str::SmolStr;
+
+use crate::toir::to_ir_path;
+
+/// Components nested deeper than this are assumed to be a cycle
+const MAX_COMPONENT_DEPTH: usize = 64;
+
+/// The spacing of a layer, as set by its metrics keys
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Spacing {
+    /// How far to move the outline and anchors to the right
+    pub(crate) x_offset: f64,
+    pub(crate) width: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Metric {
+    Left,
+    Right,
+    Width,
+}
+
+impl Metric {
+    fn opposite(self) -> Metric {
+        match self {
+            Metric::Left => Metric::Right,
+            Metric::Right => Metric::Left,
+            Metric::Width => Metric::Width,
+        }
+    }
+
+    fn key(self, keys: &MetricsKeys) -> Option<&SmolStr> {
+        match self {
+            Metric::Left => keys.left.as_ref(),
+            Metric::Right => keys.right.as_ref(),
+            Metric::Width => keys.width.as_ref(),
+        }
+    }
+}
+
+/// A metrics key, parsed
+#[derive(Clone, Debug, PartialEq)]
+enum MetricsKey<'a> {
+    Constant(f64),
+    Reference {
+        /// The glyph to measure, this one if None
+        glyph: Option<&'a str>,
+        /// Measure the other side of the glyph, written `|`
+        opposite: bool,
+        arithmetic: Option<(u8, f64)>,
+    },
+}
+
+impl MetricsKey<'_> {
+    fn parse<'a>(key: &'a str, font: &Font) -> Option<MetricsKey<'a>> {
+        let key = key.trim();
+        let key = key.strip_prefix('=').unwrap_or(key).trim();
+        if let Ok(value) = key.parse() {
+            return Some(MetricsKey::Constant(value));
+        }
+        let (opposite, key) = match key.strip_prefix('|') {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, key),
+        };
+        // glyph names can contain '-', so only look for arithmetic if this isn't one
+        let (glyph, arithmetic) = if key.is_empty() || font.glyphs.contains_key(key) {
+            (key, None)
+        } else {
+            let idx = key.rfind(['+', '-', '*', '/'])?;
+            let operand = key[idx + 1..].trim().parse().ok()?;
+            (key[..idx].trim_end(), Some((key.as_bytes()[idx], operand)))
+        };
+        Some(MetricsKey::Reference {
+            glyph: (!glyph.is_empty()).then_some(glyph),
+            opposite,
+            arithmetic,
+        })
+    }
+
+    /// The value of this key for `metric` of `layer` of `glyph_name`
+    fn evaluate(
+        &self,
+        font: &Font,
+        glyph_name: &str,
+        layer: &Layer,
+        metric: Metric,
+    ) -> Option<f64> {
+        let (glyph, opposite, arithmetic) = match self {
+            MetricsKey::Constant(value) => return Some(*value),
+            MetricsKey::Reference {
+                glyph,
+                opposite,
+                arithmetic,
+            } => (glyph, opposite, arithmetic),
+        };
+        let (glyph_name, layer) = match glyph {
+            Some(name) => (*name, matching_layer(font.glyphs.get(*name)?, layer)?),
+            None => (glyph_name, layer),
+        };
+        let metric = if *opposite { metric.opposite() } else { metric };
+        let value = measure(font, glyph_name, layer, metric)?;
+        Some(match arithmetic {
+            None => value,
+            Some((b'+', operand)) => value + operand,
+            Some((b'-', operand)) => value - operand,
+            Some((b'*', operand)) => value * operand,
+            Some((_, operand)) => value / operand,
+        })
+    }
+}
+
+/// The spacing the metrics keys of `layer` give it, if that isn't what it has
+pub(crate) fn resolve(font: &Font, glyph: &Glyph, layer: &Layer) -> Option<Spacing> {
+    if layer.metrics_keys.is_empty() && glyph.metrics_keys.is_empty() {
+        return None;
+    }
+    let key = |metric: Metric| {
+        let formula = metric
+            .key(&layer.metrics_keys)
+            .or_else(|| metric.key(&glyph.metrics_keys))?;
+        let value = MetricsKey::parse(formula, font)
+            .and_then(|parsed| parsed.evaluate(font, &glyph.name, layer, metric));
+        if value.is_none() {
+            debug!(
+                "Unable to apply metrics key '{formula}' of '{}' layer {}",
+                glyph.name, layer.layer_id
+            );
+        }
+        value
+    };
+
+    // values that only differ after rounding are as Glyphs left them
+    let differs = |a: f64, b: f64| a.round() != b.round();
+    let width = layer.width.into_inner();
+    let mut spacing = Spacing {
+        x_offset: 0.0,
+        width,
+    };
+    if let Some(bounds) = bounds(font, &glyph.name, layer) {
+        if let Some(left) = key(Metric::Left).filter(|left| differs(*left, bounds.x0)) {
+            spacing.x_offset = left - bounds.x0;
+            spacing.width += spacing.x_offset;
+        }
+        let right_edge = bounds.x1 + spacing.x_offset;
+        if let Some(right) =
+            key(Metric::Right).filter(|right| differs(*right, spacing.width - right_edge))
+        {
+            spacing.width = right_edge + right;
+        }
+    }
+    if let Some(width) = key(Metric::Width).filter(|w| differs(*w, spacing.width)) {
+        spacing.width = width;
+    }
+
+    if spacing.x_offset == 0.0 && spacing.width == width {
+        return None;
+    }
+    warn!(
+        "'{}' layer {} is out of date with its metrics keys, moving it by {} and changing its width to {}",
+        glyph.name, layer.layer_id, spacing.x_offset, spacing.width
+    );
+    Some(spacing)
+}
+
+/// The layer of `glyph` that corresponds to `layer` of another glyph
+fn matching_layer<'a>(glyph: &'a Glyph, layer: &Layer) -> Option<&'a Layer> {
+    let mut layers = glyph.layers.iter().chain(glyph.bracket_layers.iter());
+    layers
+        .find(|l| l.master_id() == layer.master_id() && l.attributes == layer.attributes)
+        .or_else(|| {
+            glyph
+                .layers
+                .iter()
+                .find(|l| l.layer_id == layer.master_id())
+        })
+}
+
+fn measure(font: &Font, glyph_name: &str, layer: &Layer, metric: Metric) -> Option<f64> {
+    let width = layer.width.into_inner();
+    match metric {
+        Metric::Width => Some(width),
+        Metric::Left => bounds(font, glyph_name, layer).map(|bounds| bounds.x0),
+        Metric::Right => bounds(font, glyph_name, layer).map(|bounds| width - bounds.x1),
+    }
+}
+
+/// The bounds of the outline of `layer`, including its components
+fn bounds(font: &Font, glyph_name: &str, layer: &Layer) -> Option<Rect> {
+    let mut paths = Vec::new();
+    add_outline(font, glyph_name, layer, Affine::IDENTITY, 0, &mut paths);
+    paths
+        .iter()
+        .filter(|path| !path.is_empty())
+        .map(|path| path.bounding_box())
+        .reduce(|a, b| a.union(b))
+}
+
+fn add_outline(
+    font: &Font,
+    glyph_name: &str,
+    layer: &Layer,
+    transform: Affine,
+    depth: usize,
+    paths: &mut Vec<BezPath>,
+) {
+    if depth > MAX_COMPONENT_DEPTH {
+        return;
+    }
+    for shape in layer.shapes.iter() {
+        match shape {
+            Shape::Path(path) => {
+                if let Ok(mut path) = to_ir_path(glyph_name.into(), path) {
+                    path.apply_affine(transform);
+                    paths.push(path);
+                }
+            }
+            Shape::Component(component) => {
+                let Some(base) = font
+                    .glyphs
+                    .get(component.name.as_str())
+                    .and_then(|glyph| matching_layer(glyph, layer))
+                else {
+                    continue;
+                };
+                add_outline(
+                    font,
+                    &component.name,
+                    base,
+                    transform * component.transform,
+                    depth + 1,
+                    paths,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_keys() {
+        let mut font = Font::default();
+        font.glyphs.insert("a-cy".into(), Default::default());
+        let reference = |glyph, opposite, arithmetic| MetricsKey::Reference {
+            glyph,
+            opposite,
+            arithmetic,
+        };
+        for (key, expected) in [
+            ("=40", MetricsKey::Constant(40.0)),
+            ("H", reference(Some("H"), false, None)),
+            ("=|", reference(None, true, None)),
+            ("=|o", reference(Some("o"), true, None)),
+            ("=n+10", reference(Some("n"), false, Some((b'+', 10.0)))),
+            ("=o*1.5", reference(Some("o"), false, Some((b'*', 1.5)))),
+            ("=a-cy", reference(Some("a-cy"), false, None)),
+            ("=a-cy-5", reference(Some("a-cy"), false, Some((b'-', 5.0)))),
+        ] {
+            assert_eq!(Some(expected), MetricsKey::parse(key, &font), "{key}");
+        }
+        assert_eq!(None, MetricsKey::parse("=auto+", &font));
+    }
+}
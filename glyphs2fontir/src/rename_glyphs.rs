@@ -0,0 +1,145 @@
+//! The 'Rename Glyphs' and 'Reencode Glyphs' custom parameters
+//!
+//! These change the glyphs of a font at export: renaming a glyph to the name
+//! of another swaps their outlines, so e.g. "a=a.ss01" makes the alternate
+//! the default, and reencoding moves codepoints from one glyph to another.
+//!
+//! See <https://handbook.glyphsapp.com/custom-parameter-descriptions/>
+//! and <https://github.com/googlefonts/glyphsLib/blob/74c63244fdb/Lib/glyphsLib/builder/custom_params.py#L1020>
+
+use std::collections::BTreeSet;
+
+use glyphs_reader::{glyphdata::GlyphData, Font, Shape};
+use log::warn;
+use smol_str::SmolStr;
+
+/// Rename, or swap, glyphs and then reencode them
+pub(crate) fn apply(
+    font: &mut Font,
+    renames: &[(SmolStr, SmolStr)],
+    reencodings: &[(SmolStr, BTreeSet<u32>)],
+) {
+    for (old, new) in renames {
+        rename_glyph(font, old, new);
+    }
+    for (name, codepoints) in reencodings {
+        reencode_glyph(font, name, codepoints);
+    }
+}
+
+fn rename_glyph(font: &mut Font, old: &SmolStr, new: &SmolStr) {
+    let Some(mut glyph) = font.glyphs.remove(old) else {
+        warn!("Unable to rename '{old}' to '{new}', there is no '{old}'");
+        return;
+    };
+
+    // the glyphs trade places; names, codepoints and so on stay put
+    if let Some(other) = font.glyphs.get_mut(new) {
+        std::mem::swap(&mut glyph.layers, &mut other.layers);
+        std::mem::swap(&mut glyph.bracket_layers, &mut other.bracket_layers);
+        std::mem::swap(&mut glyph.metrics_keys, &mut other.metrics_keys);
+        font.glyphs.insert(old.clone(), glyph);
+        return;
+    }
+
+    // there's nothing to swap with, so this is a plain rename
+    let glyph_data = GlyphData::default();
+    let production_name = |name: &str, glyph_unicode: &BTreeSet<u32>| -> Option<SmolStr> {
+        glyph_data
+            .query(name, Some(glyph_unicode))
+            .and_then(|result| result.production_name)
+            .map(Into::into)
+    };
+    // a production name that came from the old name no longer applies
+    if glyph.production_name.is_some()
+        && glyph.production_name == production_name(old, &glyph.unicode)
+    {
+        glyph.production_name = production_name(new, &glyph.unicode);
+    }
+    glyph.name = new.clone();
+    font.glyphs.insert(new.clone(), glyph);
+
+    for name in font.glyph_order.iter_mut().filter(|name| **name == *old) {
+        *name = new.clone();
+    }
+    let layers = font.glyphs.values_mut().flat_map(|glyph| {
+        glyph
+            .layers
+            .iter_mut()
+            .chain(glyph.bracket_layers.iter_mut())
+    });
+    for layer in layers {
+        for shape in layer.shapes.iter_mut() {
+            if let Shape::Component(component) = shape {
+                if component.name == *old {
+                    component.name = new.clone();
+                }
+            }
+        }
+    }
+}
+
+fn reencode_glyph(font: &mut Font, name: &SmolStr, codepoints: &BTreeSet<u32>) {
+    if !font.glyphs.contains_key(name) {
+        warn!("Unable to reencode '{name}', there is no such glyph");
+        return;
+    }
+    for glyph in font.glyphs.values_mut() {
+        if glyph.name == *name {
+            glyph.unicode = codepoints.clone();
+        } else {
+            glyph.unicode.retain(|cp| !codepoints.contains(cp));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn glyph_widths(font: &Font) -> Vec<(&str, f64)> {
+        font.glyph_order
+            .iter()
+            .map(|name| {
+                (
+                    name.as_str(),
+                    font.glyphs[name].layers[0].width.into_inner(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rename_and_reencode() {
+        let mut font = Font::load(Path::new(
+            "../resources/testdata/glyphs3/RenameGlyphs.glyphs",
+        ))
+        .unwrap();
+        let params = font.custom_parameters.clone();
+        apply(
+            &mut font,
+            params.rename_glyphs.as_deref().unwrap_or_default(),
+            params.reencode_glyphs.as_deref().unwrap_or_default(),
+        );
+
+        assert_eq!(
+            vec![
+                ("a", 600.0),
+                ("a.ss01", 500.0),
+                ("adieresis", 500.0),
+                ("g", 550.0),
+                ("b", 500.0),
+                ("b.sc", 400.0),
+            ],
+            glyph_widths(&font)
+        );
+        assert_eq!(BTreeSet::from([0x61]), font.glyphs["a"].unicode);
+        assert!(font.glyphs["a.ss01"].unicode.is_empty());
+        assert_eq!("g", font.glyphs["g"].name);
+        assert!(!font.glyphs.contains_key("g.alt"));
+        assert!(font.glyphs["b"].unicode.is_empty());
+        assert_eq!(BTreeSet::from([0x62, 0xE000]), font.glyphs["b.sc"].unicode);
+    }
+}
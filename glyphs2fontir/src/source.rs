@@ -1,6 +1,7 @@
 use std::{
     borrow::Cow,
     collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs,
     path::Path,
     sync::Arc,
 };
@@ -17,19 +18,22 @@ use fontir::{
     error::{BadGlyph, BadGlyphKind, BadSource, Error},
     feature_variations::{overlay_feature_variations, NBox},
     ir::{
-        self, AnchorBuilder, Color, ColorPalettes, Condition, ConditionSet, GdefCategories,
-        GlobalMetric, GlobalMetrics, GlyphInstance, GlyphOrder, KernGroup, KernSide, KerningGroups,
-        KerningInstance, MetaTableValues, NameBuilder, NameKey, NamedInstance, PostscriptNames,
-        Rule, StaticMetadata, Substitution, VariableFeature, DEFAULT_VENDOR_ID,
+        self, AnchorBuilder, BaseTableValues, Color, ColorPalettes, Condition, ConditionSet,
+        FeatureWriter, FeatureWriterMode, FeatureWriters, GdefCategories, GlobalMetric,
+        GlobalMetrics, GlyphInstance, GlyphOrder, KernGroup, KernSide, KerningGroups,
+        KerningInstance, MathValues, MetaTableValues, NameBuilder, NameKey, NamedInstance,
+        PostscriptHints, PostscriptNames, Rule, ScriptBaselines, StaticMetadata, Substitution,
+        VariableFeature, DEFAULT_VENDOR_ID,
     },
     orchestration::{Context, Flags, IrWork, WorkId},
-    source::Source,
+    source::{AxisLimit, Source},
 };
 use glyphs_reader::{
     glyphdata::{Category, Subcategory},
-    Font, InstanceType, Layer,
+    Font, Gradient, InstanceType, Layer, LazyFont,
 };
 use indexmap::IndexMap;
+use kurbo::{Affine, Shape as _, Vec2};
 use ordered_float::OrderedFloat;
 use smol_str::{format_smolstr, SmolStr};
 use write_fonts::{
@@ -41,7 +45,11 @@ use write_fonts::{
     types::{NameId, Tag},
 };
 
-use crate::toir::{design_location, to_ir_contours_and_components, to_ir_features, FontInfo};
+use crate::{
+    instance,
+    metrics_keys::{self, Spacing},
+    toir::{design_location, to_ir_contours_and_components, to_ir_features, to_ir_path, FontInfo},
+};
 
 #[derive(Debug, Clone)]
 pub struct GlyphsIrSource {
@@ -65,8 +73,10 @@ impl GlyphsIrSource {
 
 impl Source for GlyphsIrSource {
     fn new(glyphs_file: &Path) -> Result<Self, Error> {
-        // We have to read the glyphs file then shred it to figure out if anything changed
-        let font_info = FontInfo::try_from(Font::load(glyphs_file).map_err(|e| {
+        // We have to read the glyphs file then shred it to figure out if anything changed.
+        // Glyphs are most of a big source, so parse them in parallel.
+        let font = LazyFont::load(glyphs_file).and_then(LazyFont::into_font);
+        let font_info = FontInfo::try_from(font.map_err(|e| {
             BadSource::custom(
                 glyphs_file.to_path_buf(),
                 format!("Unable to read glyphs file: {e}"),
@@ -138,9 +148,54 @@ impl Source for GlyphsIrSource {
         &self,
     ) -> Result<Box<fontir::orchestration::IrWork>, fontir::error::Error> {
         Ok(Box::new(PaintGraphWork {
-            _font_info: self.font_info.clone(),
+            font_info: self.font_info.clone(),
         }))
     }
+
+    fn create_bitmap_strikes_work(&self) -> Result<Box<IrWork>, Error> {
+        Ok(Box::new(BitmapStrikesWork {
+            font_info: self.font_info.clone(),
+            font_file_path: self.source_path.clone(),
+        }))
+    }
+
+    fn create_svg_documents_work(&self) -> Result<Box<IrWork>, Error> {
+        Ok(Box::new(SvgDocumentsWork {
+            font_info: self.font_info.clone(),
+            font_file_path: self.source_path.clone(),
+        }))
+    }
+
+    fn limit_axes(&mut self, limits: &[AxisLimit]) -> Result<(), Error> {
+        if limits.is_empty() {
+            return Ok(());
+        }
+        AxisLimit::check(limits, &self.font_info.axes)?;
+        let font_info = Arc::get_mut(&mut self.font_info)
+            .expect("axes are limited before any work shares the source");
+        let axes = font_info.axes.clone();
+        let within = |axes_values: &[OrderedFloat<f64>]| {
+            let location = design_location(&axes, axes_values).to_user(&axes);
+            AxisLimit::all_contain(limits, &location)
+        };
+
+        let mut font = std::mem::take(&mut font_info.font);
+        let outside: HashSet<_> = font
+            .masters
+            .iter()
+            .filter(|m| !within(&m.axes_values))
+            .map(|m| m.id.clone())
+            .collect();
+        instance::remove_masters(&mut font, &outside);
+        font.instances
+            .retain(|i| i.type_ == InstanceType::Variable || within(&i.axes_values));
+
+        // the instance parameters were applied when the source was loaded
+        let limited = FontInfo::try_from_resolved(font)?;
+        AxisLimit::check_reached(limits, &limited.axes)?;
+        *font_info = limited;
+        Ok(())
+    }
 }
 
 impl GlyphsIrSource {
@@ -191,6 +246,7 @@ fn try_name_id(name: &str) -> Option<NameId> {
 }
 
 fn names(font: &Font, flags: SelectionFlags) -> HashMap<NameKey, String> {
+    let overrides = instance::name_overrides(font);
     let mut builder = NameBuilder::default();
     builder.set_version(font.version_major, font.version_minor);
     for (name, value) in font.names.iter() {
@@ -198,6 +254,16 @@ fn names(font: &Font, flags: SelectionFlags) -> HashMap<NameKey, String> {
             builder.add(name_id, value.clone());
         }
     }
+    if let Some(family_name) = overrides.family_name {
+        builder.add(NameId::FAMILY_NAME, family_name);
+    }
+    // before the fallbacks, so the WWS names can fall back to them
+    if let Some(typographic_family) = overrides.typographic_family_name {
+        builder.add(NameId::TYPOGRAPHIC_FAMILY_NAME, typographic_family);
+    }
+    if let Some(typographic_subfamily) = overrides.typographic_subfamily_name {
+        builder.add(NameId::TYPOGRAPHIC_SUBFAMILY_NAME, typographic_subfamily);
+    }
 
     let subfamily = if flags.contains(SelectionFlags::BOLD | SelectionFlags::ITALIC) {
         "Bold Italic"
@@ -244,6 +310,10 @@ fn names(font: &Font, flags: SelectionFlags) -> HashMap<NameKey, String> {
         .map(|v| v.as_str())
         .unwrap_or(DEFAULT_VENDOR_ID);
     builder.apply_default_fallbacks(vendor);
+    builder.apply_wws_names(flags.contains(SelectionFlags::WWS));
+    for (name_id, value) in overrides.entries {
+        builder.add(name_id, value);
+    }
 
     builder.into_inner()
 }
@@ -279,6 +349,8 @@ impl Work<Context, WorkId, Error> for StaticMetadataWork {
                     return None;
                 }
                 Some(NamedInstance {
+                    // like fontmake, even if the instance overrides its typographic
+                    // subfamily name, which only goes in the name table
                     name: inst.name.clone(),
                     postscript_name: inst.postscript_name().map(str::to_string),
                     location: font_info
@@ -343,9 +415,11 @@ impl Work<Context, WorkId, Error> for StaticMetadataWork {
             .flat_map(|glyph| glyph.layers.iter())
             .any(|layer| layer.vert_width.is_some() || layer.vert_origin.is_some());
 
+        // the variable export settings take precedence over the font
         let dont_use_prod_names = font
-            .custom_parameters
-            .dont_use_production_names
+            .variable_export_settings(font.default_master())
+            .and_then(|instance| instance.custom_parameters.dont_use_production_names)
+            .or(font.custom_parameters.dont_use_production_names)
             .unwrap_or(false);
 
         let postscript_names =
@@ -388,6 +462,7 @@ impl Work<Context, WorkId, Error> for StaticMetadataWork {
         .map_err(Error::VariationModelError)?;
         static_metadata.misc.selection_flags = selection_flags;
         static_metadata.variations = variations;
+        static_metadata.feature_writers = automatic_feature_writers(&font.automatic_features);
         // treat "    " (four spaces) as equivalent to no value; it means
         // 'null', per the spec
         if let Some(vendor_id) = font.vendor_id().filter(|id| *id != "    ") {
@@ -429,6 +504,11 @@ impl Work<Context, WorkId, Error> for StaticMetadataWork {
             static_metadata.misc.panose = Some(bytes.into());
         }
 
+        static_metadata.misc.enforce_compatibility = font
+            .custom_parameters
+            .enforce_compatibility_check
+            .unwrap_or_default();
+
         static_metadata.misc.version_major = font.version_major;
         static_metadata.misc.version_minor = font.version_minor;
         if let Some(lowest_rec_ppm) = font.custom_parameters.lowest_rec_ppem {
@@ -459,6 +539,40 @@ impl Work<Context, WorkId, Error> for StaticMetadataWork {
             });
         }
 
+        if let Some(baselines) = default_instance
+            .and_then(|di| di.custom_parameters.baselines.as_ref())
+            .or(default_master.custom_parameters.baselines.as_ref())
+            .or(font.custom_parameters.baselines.as_ref())
+        {
+            static_metadata.misc.base_table =
+                base_table_values(baselines).filter(|values| !values.is_empty());
+        }
+
+        let math_constants: BTreeMap<_, _> = default_master
+            .math_constants
+            .iter()
+            .filter_map(|(name, value)| {
+                let rounded = value.round();
+                let Ok(value) = i16::try_from(rounded as i64) else {
+                    warn!("MATH constant {name} {value} is out of bounds, ignoring it");
+                    return None;
+                };
+                Some((name.clone(), value))
+            })
+            .collect();
+        let extended_shapes: BTreeSet<GlyphName> = font
+            .glyphs
+            .values()
+            .filter(|glyph| glyph.export && glyph.math_extended_shape)
+            .map(|glyph| glyph.name.clone().into())
+            .collect();
+        if !math_constants.is_empty() || !extended_shapes.is_empty() {
+            static_metadata.misc.math = Some(MathValues {
+                constants: math_constants,
+                extended_shapes,
+            });
+        }
+
         if let Some(gasp) = &font.custom_parameters.gasp_table {
             for (max_ppem, behavior) in gasp.iter() {
                 let Ok(range_max_ppem) = (*max_ppem).try_into() else {
@@ -479,6 +593,8 @@ impl Work<Context, WorkId, Error> for StaticMetadataWork {
             }
         }
 
+        static_metadata.postscript_hints = get_postscript_hints(font_info, font);
+
         let mut glyph_order: GlyphOrder =
             font.glyph_order.iter().cloned().map(Into::into).collect();
 
@@ -499,6 +615,74 @@ impl Work<Context, WorkId, Error> for StaticMetadataWork {
     }
 }
 
+/// Convert the 'Baselines' custom parameter, ignoring it if any tag or position is invalid
+fn base_table_values(baselines: &glyphs_reader::Baselines) -> Option<BaseTableValues> {
+    let tag = |raw: &SmolStr| {
+        Tag::new_checked(raw.as_bytes())
+            .inspect_err(|_| warn!("Ignoring Baselines, '{raw}' is not a valid tag"))
+            .ok()
+    };
+    let convert = |scripts: &BTreeMap<SmolStr, glyphs_reader::ScriptBaselines>| {
+        scripts
+            .iter()
+            .map(|(script, baselines)| {
+                let positions = baselines
+                    .positions
+                    .iter()
+                    .map(|(baseline, position)| {
+                        let Ok(position) = i16::try_from(*position) else {
+                            warn!(
+                                "Ignoring Baselines, '{baseline}' of '{script}' is out of bounds"
+                            );
+                            return None;
+                        };
+                        Some((tag(baseline)?, position))
+                    })
+                    .collect::<Option<_>>()?;
+                let baselines = ScriptBaselines {
+                    default: tag(&baselines.default)?,
+                    positions,
+                };
+                Some((tag(script)?, baselines))
+            })
+            .collect::<Option<_>>()
+    };
+    Some(BaseTableValues {
+        horizontal: convert(&baselines.horizontal)?,
+        vertical: convert(&baselines.vertical)?,
+    })
+}
+
+/// Generate the features Glyphs.app would have filled in for empty automatic features
+fn automatic_feature_writers(automatic_features: &BTreeSet<SmolStr>) -> FeatureWriters {
+    let mut writers = FeatureWriters::default();
+    for name in automatic_features {
+        let Ok(tag) = Tag::new_checked(name.as_bytes()) else {
+            continue;
+        };
+        let writer = match name.as_str() {
+            "ccmp" => &mut writers.ccmp,
+            "locl" => &mut writers.locl,
+            "frac" | "numr" | "dnom" => &mut writers.frac,
+            "liga" | "case" | "ordn" | "sups" | "subs" | "sinf" => &mut writers.automatic,
+            _ => {
+                warn!("Generating automatic feature '{name}' is not supported, it will be empty");
+                continue;
+            }
+        };
+        // the feature is in the FEA, empty, so add to it
+        writer
+            .get_or_insert_with(|| FeatureWriter {
+                mode: FeatureWriterMode::Append,
+                features: Some(Default::default()),
+            })
+            .features
+            .get_or_insert_with(Default::default)
+            .insert(tag);
+    }
+    writers
+}
+
 fn make_feature_variations(fontinfo: &FontInfo) -> Option<VariableFeature> {
     // by default, glyphs registers feature variations under 'rlig'
     // https://glyphsapp.com/learn/switching-shapes#g-1-alternate-layers-bracket-layers__feature-variations
@@ -679,6 +863,46 @@ fn get_number_values(
     Some(values)
 }
 
+/// The alignment zones and stems of each master, as they'd go in a CFF private dict
+///
+/// See <https://github.com/googlefonts/glyphsLib/blob/74c63244fdb/Lib/glyphsLib/builder/masters.py#L91>
+fn get_postscript_hints(
+    fontinfo: &FontInfo,
+    font: &Font,
+) -> HashMap<NormalizedLocation, PostscriptHints> {
+    let hints = font
+        .masters
+        .iter()
+        .map(|m| {
+            let location = fontinfo.locations.get(&m.axes_values).cloned().unwrap();
+            let mut hints = PostscriptHints::default();
+            for (pos, over) in m.alignment_zones() {
+                let zone = (pos.min(pos + over), pos.max(pos + over));
+                // the baseline zone is a blue value even though it overshoots downwards
+                if pos == 0.0 || over >= 0.0 {
+                    hints.blue_values.push(zone);
+                } else {
+                    hints.other_blues.push(zone);
+                }
+            }
+            for stem in font.stems.iter() {
+                let Some(width) = m.stem_values.get(&stem.name).filter(|w| **w != 0.0) else {
+                    continue;
+                };
+                match stem.horizontal {
+                    true => hints.stem_snap_h.push(*width),
+                    false => hints.stem_snap_v.push(*width),
+                }
+            }
+            (location, hints)
+        })
+        .collect::<HashMap<_, _>>();
+    if hints.values().all(PostscriptHints::is_empty) {
+        return Default::default();
+    }
+    hints
+}
+
 /// determine the GDEF category for this glyph, if appropriate
 // see
 // <https://github.com/googlefonts/glyphsLib/blob/e2ebf5b517/Lib/glyphsLib/builder/features.py#L205>
@@ -842,6 +1066,14 @@ impl Work<Context, WorkId, Error> for GlobalMetricWork {
                     .unwrap_or(font.units_per_em as f64),
             );
 
+            if context.flags.contains(Flags::GOOGLE_FONTS_METRICS) {
+                metrics.populate_google_fonts_defaults(
+                    pos,
+                    static_metadata.units_per_em,
+                    Some(ascender),
+                    Some(descender),
+                );
+            }
             metrics.populate_defaults(
                 pos,
                 static_metadata.units_per_em,
@@ -1316,7 +1548,9 @@ impl Work<Context, WorkId, Error> for GlyphIrWork {
         for layer in layers.iter() {
             seen_master_ids.insert(layer.master_id());
 
-            let (location, instance) = process_layer(glyph, layer, font_info, &global_metrics)?;
+            let spacing = metrics_keys::resolve(font, glyph, layer);
+            let (location, instance) =
+                process_layer(glyph, layer, spacing, font_info, &global_metrics)?;
 
             for (tag, coord) in location.iter() {
                 axis_positions.entry(*tag).or_default().insert(*coord);
@@ -1326,8 +1560,9 @@ impl Work<Context, WorkId, Error> for GlyphIrWork {
             // we only care about anchors from exportable glyphs
             // https://github.com/googlefonts/fontc/issues/1397
             if glyph.export {
+                let offset = anchor_offset(spacing);
                 for anchor in layer.anchors.iter() {
-                    ir_anchors.add(anchor.name.clone(), location.clone(), anchor.pos)?;
+                    ir_anchors.add(anchor.name.clone(), location.clone(), anchor.pos + offset)?;
                 }
             }
         }
@@ -1353,15 +1588,17 @@ impl Work<Context, WorkId, Error> for GlyphIrWork {
                     .iter()
                     .find(|l| l.master_id() == missing_master_id)
                 {
-                    let (loc, instance) = process_layer(glyph, layer, font_info, &global_metrics)?;
+                    let spacing = metrics_keys::resolve(font, glyph, layer);
+                    let (loc, instance) =
+                        process_layer(glyph, layer, spacing, font_info, &global_metrics)?;
                     ir_glyph.try_add_source(&loc, instance)?;
                     for (tag, coord) in loc.iter() {
                         axis_positions.entry(*tag).or_default().insert(*coord);
                     }
-                    layer
-                        .anchors
-                        .iter()
-                        .try_for_each(|a| ir_anchors.add(a.name.clone(), loc.clone(), a.pos))?;
+                    let offset = anchor_offset(spacing);
+                    layer.anchors.iter().try_for_each(|a| {
+                        ir_anchors.add(a.name.clone(), loc.clone(), a.pos + offset)
+                    })?;
                 }
             }
         }
@@ -1387,9 +1624,15 @@ impl Work<Context, WorkId, Error> for GlyphIrWork {
     }
 }
 
+/// How far a layer's metrics keys move its anchors
+fn anchor_offset(spacing: Option<Spacing>) -> Vec2 {
+    Vec2::new(spacing.map_or(0.0, |spacing| spacing.x_offset), 0.0)
+}
+
 fn process_layer(
     glyph: &glyphs_reader::Glyph,
     instance: &Layer,
+    spacing: Option<Spacing>,
     font_info: &FontInfo,
     global_metrics: &GlobalMetrics,
 ) -> Result<(NormalizedLocation, GlyphInstance), Error> {
@@ -1429,14 +1672,25 @@ fn process_layer(
         .into_inner();
 
     // TODO populate width and height properly
-    let (contours, components) =
+    let (mut contours, mut components) =
         to_ir_contours_and_components(glyph.name.clone().into(), &instance.shapes)?;
+    let mut width = instance.width.into_inner();
+    if let Some(spacing) = spacing {
+        let offset = Affine::translate((spacing.x_offset, 0.0));
+        contours
+            .iter_mut()
+            .for_each(|contour| contour.apply_affine(offset));
+        components
+            .iter_mut()
+            .for_each(|component| component.transform = offset * component.transform);
+        width = spacing.width;
+    }
     let glyph_instance = GlyphInstance {
         // https://github.com/googlefonts/fontmake-rs/issues/285 glyphs non-spacing marks are 0-width
         width: if glyph.is_nonspacing_mark() {
             0.0
         } else {
-            instance.width.into_inner()
+            width
         },
         height: Some(height),
         vertical_origin: Some(vertical_origin),
@@ -1497,10 +1751,8 @@ impl Work<Context, WorkId, Error> for ColorPaletteWork {
     }
 
     fn exec(&self, context: &Context) -> Result<(), Error> {
-        // Since we're only [for now, baby steps] producing one palette we can dedup it
-        let palette = self
-            .font_info
-            .font
+        let font = &self.font_info.font;
+        let gradient_colors = font
             .glyphs
             .values()
             .flat_map(|g| {
@@ -1516,21 +1768,73 @@ impl Work<Context, WorkId, Error> for ColorPaletteWork {
                 a: c.a as u8,
             })
             .collect::<Vec<_>>();
-        debug!("{} color palette entries", palette.len());
-        match ColorPalettes::new(vec![palette]) {
-            Ok(Some(palettes)) => {
-                context.colors.set(palettes);
-                Ok(())
+
+        let palettes = match &font.custom_parameters.color_palettes {
+            Some(palettes) if !palettes.is_empty() => {
+                // gradients name colors rather than palette entries, so every
+                // palette needs an entry for each color they use
+                let mut extra_colors = gradient_colors;
+                extra_colors.sort();
+                extra_colors.dedup();
+                let palettes: Vec<Vec<_>> = palettes
+                    .iter()
+                    .map(|palette| {
+                        palette
+                            .iter()
+                            .map(|&[r, g, b, a]| Color { r, g, b, a })
+                            .collect()
+                    })
+                    .collect();
+                extra_colors.retain(|color| !palettes[0].contains(color));
+                palettes
+                    .into_iter()
+                    .map(|palette| palette.into_iter().chain(extra_colors.clone()).collect())
+                    .collect()
+            }
+            // With only the one palette of gradient colors we can dedup it, and sort
+            // it for stability in output
+            _ => {
+                let mut palette = gradient_colors;
+                palette.sort();
+                palette.dedup();
+                vec![palette]
+            }
+        };
+        debug!(
+            "{} color palette(s) of {} entries",
+            palettes.len(),
+            palettes[0].len()
+        );
+        let Some(mut color_palettes) = ColorPalettes::new(palettes)? else {
+            return Ok(());
+        };
+        if let Some(types) = &font.custom_parameters.color_palette_types {
+            let n_palettes = color_palettes.palettes.len();
+            if types.len() != n_palettes {
+                warn!(
+                    "{} color palette types for {n_palettes} palettes, palettes without a type get none",
+                    types.len()
+                );
             }
-            Ok(None) => Ok(()),
-            Err(e) => Err(e),
+            let types = (0..n_palettes)
+                .map(|i| types.get(i).copied().unwrap_or_default())
+                .collect();
+            color_palettes = color_palettes.with_palette_types(types);
         }
+        context.colors.set(color_palettes);
+        Ok(())
     }
 }
 
+/// Reads the gradients of full color layers
+///
+/// Each color glyph is filled by the first gradient of its color layers, with
+/// the geometry of the gradient taken from every master that has a color layer.
+/// The gradient fills the outline of the glyph itself, not of the shape that has
+/// it, and the other shapes of the color layers are dropped with a warning each.
 #[derive(Debug)]
 struct PaintGraphWork {
-    _font_info: Arc<FontInfo>,
+    font_info: Arc<FontInfo>,
 }
 
 impl Work<Context, WorkId, Error> for PaintGraphWork {
@@ -1546,8 +1850,264 @@ impl Work<Context, WorkId, Error> for PaintGraphWork {
         Access::Variant(WorkId::PaintGraph)
     }
 
-    fn exec(&self, _context: &Context) -> Result<(), Error> {
-        debug!("TODO: actually create paint graph");
+    fn exec(&self, context: &Context) -> Result<(), Error> {
+        let font = &self.font_info.font;
+        let default_master_id = font.default_master().id.as_str();
+
+        let mut base_glyphs = BTreeMap::new();
+        for glyph in font.glyphs.values().filter(|glyph| glyph.export) {
+            let mut paint: Option<ir::GradientPaint> = None;
+            let mut geometry = Vec::new();
+            for master in font.masters.iter() {
+                let shapes: Vec<_> = glyph
+                    .layers
+                    .iter()
+                    .filter(|layer| {
+                        layer.is_full_color()
+                            && layer.master_id() == master.id
+                            && !layer.is_alternate()
+                            && !layer.is_intermediate()
+                    })
+                    .flat_map(|layer| layer.shapes.iter())
+                    .collect();
+                let Some((path_idx, path)) =
+                    shapes
+                        .iter()
+                        .enumerate()
+                        .find_map(|(i, shape)| match shape {
+                            glyphs_reader::Shape::Path(path)
+                                if !path.attributes.gradient.colors.is_empty() =>
+                            {
+                                Some((i, path))
+                            }
+                            _ => None,
+                        })
+                else {
+                    if !shapes.is_empty() {
+                        warn!(
+                            "Dropping the {} shape(s) of the color layer of {} in master {}, none has a gradient",
+                            shapes.len(),
+                            glyph.name,
+                            master.name
+                        );
+                    }
+                    continue;
+                };
+                // there is one paint per glyph, clipped to the glyph's own outline
+                for (i, shape) in shapes.iter().enumerate().filter(|(i, _)| *i != path_idx) {
+                    let what = match shape {
+                        glyphs_reader::Shape::Path(..) => "path",
+                        glyphs_reader::Shape::Component(..) => "component",
+                    };
+                    warn!(
+                        "Dropping {what} {i} of the color layer of {} in master {}, only the first gradient is used",
+                        glyph.name, master.name
+                    );
+                }
+                let gradient = &path.attributes.gradient;
+                let bbox = to_ir_path(glyph.name.clone().into(), path)
+                    .map_err(|e| BadGlyph::new(glyph.name.clone(), e))?
+                    .bounding_box();
+                let location = self.font_info.master_positions[&master.id].clone();
+                let Some(values) = gradient_geometry(gradient, bbox) else {
+                    warn!(
+                        "Ignoring the gradient of {} at {location:?}, it has no start or end",
+                        glyph.name
+                    );
+                    continue;
+                };
+                geometry.push((location, values));
+
+                // stops are shared by all masters, take them from the default
+                if master.id == default_master_id {
+                    let kind = if gradient.style == "circle" {
+                        ir::GradientKind::Radial
+                    } else {
+                        ir::GradientKind::Linear
+                    };
+                    let mut stops: Vec<_> = gradient
+                        .colors
+                        .iter()
+                        .map(|c| ir::ColorStop {
+                            offset: c.stop_offset,
+                            color: Color {
+                                r: c.r as u8,
+                                g: c.g as u8,
+                                b: c.b as u8,
+                                a: c.a as u8,
+                            },
+                        })
+                        .collect();
+                    stops.sort_by_key(|stop| stop.offset);
+                    paint = Some(ir::GradientPaint {
+                        kind,
+                        stops,
+                        geometry: Vec::new(),
+                    });
+                }
+            }
+            let Some(mut paint) = paint else {
+                if !geometry.is_empty() {
+                    warn!(
+                        "Ignoring the gradients of {}, the default master has none",
+                        glyph.name
+                    );
+                }
+                continue;
+            };
+            paint.geometry = geometry;
+            base_glyphs.insert(glyph.name.clone().into(), paint);
+        }
+        debug!("{} color glyph(s) have a gradient", base_glyphs.len());
+        if !base_glyphs.is_empty() {
+            context.paint_graph.set(ir::PaintGraph { base_glyphs });
+        }
+        Ok(())
+    }
+}
+
+/// The points and radii of a gradient, in font units, in COLR field order
+///
+/// Glyphs positions a gradient relative to the bounds of its shape. A linear
+/// gradient runs from start to end; COLR also wants a third point to orient the
+/// color bands, which is end turned a quarter turn about start so the bands are
+/// perpendicular to the line. A radial gradient is centered on start and
+/// reaches out to end.
+fn gradient_geometry(gradient: &Gradient, bbox: kurbo::Rect) -> Option<Vec<OrderedFloat<f64>>> {
+    let point = |rel: &[OrderedFloat<f64>]| -> Option<kurbo::Point> {
+        let [x, y] = rel else {
+            return None;
+        };
+        Some(kurbo::Point::new(
+            bbox.x0 + x.0 * bbox.width(),
+            bbox.y0 + y.0 * bbox.height(),
+        ))
+    };
+    let start = point(&gradient.start)?;
+    let end = point(&gradient.end)?;
+    let values = if gradient.style == "circle" {
+        let radius = (end - start).hypot();
+        vec![start.x, start.y, 0.0, start.x, start.y, radius]
+    } else {
+        let direction = end - start;
+        let rotation = start + Vec2::new(-direction.y, direction.x);
+        vec![start.x, start.y, end.x, end.y, rotation.x, rotation.y]
+    };
+    Some(values.into_iter().map(OrderedFloat).collect())
+}
+
+/// Reads the images of the sbix layers of the default master
+#[derive(Debug)]
+struct BitmapStrikesWork {
+    font_info: Arc<FontInfo>,
+    font_file_path: Option<Arc<Path>>,
+}
+
+impl Work<Context, WorkId, Error> for BitmapStrikesWork {
+    fn id(&self) -> WorkId {
+        WorkId::BitmapStrikes
+    }
+
+    fn read_access(&self) -> Access<WorkId> {
+        Access::None
+    }
+
+    fn write_access(&self) -> Access<WorkId> {
+        Access::Variant(WorkId::BitmapStrikes)
+    }
+
+    fn exec(&self, context: &Context) -> Result<(), Error> {
+        let font = &self.font_info.font;
+        let default_master_id = font.default_master().id.as_str();
+        // image paths are relative to the .glyphs file
+        let image_dir = self
+            .font_file_path
+            .as_ref()
+            .and_then(|path| path.parent())
+            .unwrap_or(Path::new(""));
+
+        let mut strikes = ir::BitmapStrikes::default();
+        for glyph in font.glyphs.values().filter(|glyph| glyph.export) {
+            for layer in glyph.layers.iter() {
+                let (Some(ppem), Some(image)) = (layer.sbix_size(), &layer.background_image) else {
+                    continue;
+                };
+                if layer.master_id() != default_master_id {
+                    continue;
+                }
+                let Ok(ppem) = u16::try_from(ppem) else {
+                    warn!("{}: sbix size {ppem} is out of bounds", glyph.name);
+                    continue;
+                };
+                let path = image_dir.join(&image.path);
+                let png = fs::read(&path).map_err(|e| BadSource::new(&path, e))?;
+                // the image is positioned in font units, the bitmap in pixels
+                let to_pixels =
+                    |v: f64| (v * ppem as f64 / font.units_per_em as f64).round() as i16;
+                strikes.strikes.entry(ppem).or_default().glyphs.insert(
+                    glyph.name.clone().into(),
+                    ir::BitmapGlyph {
+                        png,
+                        left: to_pixels(image.pos.x),
+                        bottom: to_pixels(image.pos.y),
+                    },
+                );
+            }
+        }
+        if !strikes.is_empty() {
+            context.bitmap_strikes.set(strikes);
+        }
+        Ok(())
+    }
+}
+
+/// Reads the documents of the SVG layers of the default master
+#[derive(Debug)]
+struct SvgDocumentsWork {
+    font_info: Arc<FontInfo>,
+    font_file_path: Option<Arc<Path>>,
+}
+
+impl Work<Context, WorkId, Error> for SvgDocumentsWork {
+    fn id(&self) -> WorkId {
+        WorkId::SvgDocuments
+    }
+
+    fn read_access(&self) -> Access<WorkId> {
+        Access::None
+    }
+
+    fn write_access(&self) -> Access<WorkId> {
+        Access::Variant(WorkId::SvgDocuments)
+    }
+
+    fn exec(&self, context: &Context) -> Result<(), Error> {
+        let font = &self.font_info.font;
+        let default_master_id = font.default_master().id.as_str();
+        // image paths are relative to the .glyphs file
+        let image_dir = self
+            .font_file_path
+            .as_ref()
+            .and_then(|path| path.parent())
+            .unwrap_or(Path::new(""));
+
+        let mut documents = BTreeMap::new();
+        for glyph in font.glyphs.values().filter(|glyph| glyph.export) {
+            let Some(image) = glyph
+                .layers
+                .iter()
+                .filter(|layer| layer.is_svg() && layer.master_id() == default_master_id)
+                .find_map(|layer| layer.background_image.as_ref())
+            else {
+                continue;
+            };
+            let path = image_dir.join(&image.path);
+            let document = fs::read_to_string(&path).map_err(|e| BadSource::new(&path, e))?;
+            documents.insert(glyph.name.clone().into(), document);
+        }
+        if !documents.is_empty() {
+            context.svg_documents.set(ir::SvgDocuments { documents });
+        }
         Ok(())
     }
 }
@@ -1574,7 +2134,7 @@ mod tests {
         paths::Paths,
         source::Source,
     };
-    use glyphs_reader::{glyphdata::Category, Font};
+    use glyphs_reader::{glyphdata::Category, CustomParameter, Font, Plist};
 
     use ir::{test_helpers::Round2, Panose};
     use write_fonts::types::{NameId, Tag};
@@ -2057,6 +2617,60 @@ mod tests {
         assert_eq!(the_best_names(), names);
     }
 
+    #[test]
+    fn instance_name_parameters() {
+        let mut font = Font::load(&glyphs3_dir().join("InstanceGlyphSet.glyphs")).unwrap();
+        let instance = font
+            .instances
+            .iter_mut()
+            .find(|i| i.type_ == InstanceType::Variable)
+            .unwrap();
+        for (name, value) in [
+            ("familyName", "Renamed"),
+            ("Name Table Entry", "5; Version 9.000"),
+        ] {
+            instance.custom_parameters.raw.push(CustomParameter {
+                name: name.into(),
+                value: Plist::String(value.into()),
+                disabled: false,
+            });
+        }
+        let names = names(&font, SelectionFlags::REGULAR);
+        let get = |name_id| {
+            names
+                .get(&NameKey::new_bmp_only(name_id))
+                .map(String::as_str)
+        };
+        assert_eq!(Some("Renamed"), get(NameId::FAMILY_NAME));
+        assert_eq!(Some("Renamed Regular"), get(NameId::FULL_NAME));
+        assert_eq!(Some("Version 9.000"), get(NameId::VERSION_STRING));
+    }
+
+    #[test]
+    fn instance_typographic_names() {
+        let font = Font::load(&glyphs3_dir().join("InstanceTypographicNames.glyphs")).unwrap();
+        let names = names(&font, SelectionFlags::ITALIC);
+        let get = |name_id| {
+            names
+                .get(&NameKey::new_bmp_only(name_id))
+                .map(String::as_str)
+        };
+        assert_eq!(
+            Some("Cormorant Display"),
+            get(NameId::TYPOGRAPHIC_FAMILY_NAME)
+        );
+        assert_eq!(
+            Some("Light Italic"),
+            get(NameId::TYPOGRAPHIC_SUBFAMILY_NAME)
+        );
+        // the WWS subfamily falls back to the instance's typographic subfamily
+        assert_eq!(
+            Some("Cormorant Display Light"),
+            get(NameId::WWS_FAMILY_NAME)
+        );
+        assert_eq!(Some("Light Italic"), get(NameId::WWS_SUBFAMILY_NAME));
+    }
+
     #[test]
     fn name_table_with_basic_names_light_origin() {
         let font = Font::load(&glyphs3_dir().join("LightOriginNames.glyphs")).unwrap();
@@ -2638,6 +3252,19 @@ mod tests {
         assert_eq!(Tag::new(b"RGHT"), static_metadata.misc.vendor_id);
     }
 
+    #[test]
+    fn enforce_compatibility_only_if_asked() {
+        let enforce_compatibility = |glyphs_file: &str| {
+            let (_, context) = build_static_metadata(glyphs3_dir().join(glyphs_file));
+            let static_metadata = context.static_metadata.get();
+            static_metadata.misc.enforce_compatibility
+        };
+        assert!(!enforce_compatibility("WghtVar.glyphs"));
+        assert!(enforce_compatibility(
+            "WghtVar_EnforceCompatibilityCheck.glyphs"
+        ));
+    }
+
     #[test]
     fn prefer_default_master_panose() {
         let (_, context) = build_static_metadata(glyphs3_dir().join("MultiplePanose.glyphs"));
@@ -2686,6 +3313,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn postscript_hints_by_master() {
+        let (_, context) = build_static_metadata(glyphs3_dir().join("Stems.glyphs"));
+        let static_metadata = context.static_metadata.get();
+        let zones = |zones: &[(f64, f64)]| {
+            zones
+                .iter()
+                .map(|(bottom, top)| (OrderedFloat(*bottom), OrderedFloat(*top)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            Some(&PostscriptHints {
+                blue_values: zones(&[(-16.0, 0.0), (510.0, 522.0), (700.0, 716.0), (800.0, 816.0)]),
+                other_blues: zones(&[(-216.0, -200.0)]),
+                stem_snap_h: vec![OrderedFloat(100.0)],
+                stem_snap_v: vec![OrderedFloat(150.0)],
+            }),
+            static_metadata
+                .postscript_hints
+                .get(&NormalizedLocation::for_pos(&[("wght", 1.0)]))
+        );
+    }
+
+    #[test]
+    fn no_postscript_hints() {
+        let (_, context) = build_static_metadata(glyphs3_dir().join("Dated.glyphs"));
+        assert!(context.static_metadata.get().postscript_hints.is_empty());
+    }
+
     #[test]
     fn mark_width_zeroing() {
         let (source, context) = build_global_metrics(glyphs3_dir().join("SpacingMark.glyphs"));
@@ -2783,6 +3439,39 @@ mod tests {
         assert_eq!(get_components(&yen_bracket), ["peso.BRACKET.varAlt01"]);
     }
 
+    #[test]
+    fn metrics_keys_update_spacing() {
+        let (source, context) = build_global_metrics(glyphs3_dir().join("MetricsKeys.glyphs"));
+        build_glyphs(&source, &context).unwrap();
+
+        // (glyph, width, left of the outline)
+        for (name, width, left) in [
+            // the reference, and a glyph already in sync with it
+            ("n", 500.0, Some(50.0)),
+            ("m", 800.0, Some(50.0)),
+            // =n on the left, =n+10 on the right
+            ("h", 410.0, Some(50.0)),
+            // left sidebearing matches the right
+            ("u", 520.0, Some(100.0)),
+            ("nbspace", 200.0, None),
+        ] {
+            let glyph = context.get_glyph(name);
+            let instance = glyph.default_instance();
+            let outline_left = instance
+                .contours
+                .first()
+                .map(|contour| kurbo::Shape::bounding_box(contour).x0);
+            assert_eq!((width, left), (instance.width, outline_left), "{name}");
+        }
+
+        // anchors move with the outline
+        let anchors = context.get_anchor("h");
+        assert_eq!(
+            kurbo::Point::new(200.0, 700.0),
+            anchors.anchors[0].default_pos()
+        );
+    }
+
     #[test]
     fn bracket_glyph_anchors() {
         let (source, context) =
@@ -2924,6 +3613,22 @@ mod tests {
         assert_eq!(readable, [(WGHT, 0.0, 1.0)])
     }
 
+    #[test]
+    fn named_instances_keep_their_name() {
+        // Bold has a preferredSubfamilyName of Heavy
+        let (_, context) =
+            build_static_metadata(glyphs2_dir().join("InstanceTypographicNames.glyphs"));
+        let static_metadata = context.static_metadata.get();
+        assert_eq!(
+            vec!["Regular", "Medium", "Bold"],
+            static_metadata
+                .named_instances
+                .iter()
+                .map(|ni| ni.name.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn glyphs2_delete_default_point_axes() {
         // in old glyphs sources, if the Axes custom parameter is not used, we
@@ -2943,4 +3648,53 @@ mod tests {
             [Tag::new(b"wght")]
         );
     }
+
+    #[test]
+    fn empty_automatic_features_are_generated() {
+        let writers = automatic_feature_writers(&BTreeSet::from([
+            SmolStr::new("liga"),
+            SmolStr::new("case"),
+            SmolStr::new("frac"),
+            SmolStr::new("aalt"),
+        ]));
+        assert_eq!(
+            Some(FeatureWriter {
+                mode: FeatureWriterMode::Append,
+                features: Some([Tag::new(b"case"), Tag::new(b"liga")].into()),
+            }),
+            writers.automatic
+        );
+        assert_eq!(
+            Some(FeatureWriter {
+                mode: FeatureWriterMode::Append,
+                features: Some([Tag::new(b"frac")].into()),
+            }),
+            writers.frac
+        );
+        assert!(writers.ccmp.is_none());
+        // the defaults are left alone
+        assert_eq!(FeatureWriters::default().kern, writers.kern);
+    }
+
+    #[test]
+    fn invalid_baselines_are_ignored_entirely() {
+        let script = |default: &str, positions: &[(&str, i64)]| glyphs_reader::ScriptBaselines {
+            default: default.into(),
+            positions: positions
+                .iter()
+                .map(|(tag, position)| (SmolStr::new(tag), *position))
+                .collect(),
+        };
+        let mut baselines = glyphs_reader::Baselines::default();
+        baselines
+            .horizontal
+            .insert("latn".into(), script("romn", &[("romn", 0)]));
+        assert!(base_table_values(&baselines).is_some());
+
+        // one bad script loses them all, as with the UFO lib key
+        baselines
+            .horizontal
+            .insert("hani".into(), script("ideo", &[("ideo", 100_000)]));
+        assert_eq!(None, base_table_values(&baselines));
+    }
 }
@@ -20,6 +20,8 @@ use fontir::{
 };
 use glyphs_reader::{Component, FeatureSnippet, Font, NodeType, Path, Shape};
 
+use crate::instance;
+
 pub(crate) fn to_ir_contours_and_components(
     glyph_name: GlyphName,
     shapes: &[Shape],
@@ -78,7 +80,10 @@ fn add_to_path<'a>(
     Ok(())
 }
 
-fn to_ir_path(glyph_name: GlyphName, src_path: &Path) -> Result<BezPath, PathConversionError> {
+pub(crate) fn to_ir_path(
+    glyph_name: GlyphName,
+    src_path: &Path,
+) -> Result<BezPath, PathConversionError> {
     // Based on https://github.com/googlefonts/glyphsLib/blob/24b4d340e4c82948ba121dcfe563c1450a8e69c9/Lib/glyphsLib/builder/paths.py#L20
     // See also https://github.com/fonttools/ufoLib2/blob/4d8a9600148b670b0840120658d9aab0b38a9465/src/ufoLib2/pointPens/glyphPointPen.py#L16
     if src_path.nodes.is_empty() {
@@ -281,7 +286,15 @@ pub(crate) struct FontInfo {
 impl TryFrom<Font> for FontInfo {
     type Error = Error;
 
-    fn try_from(font: Font) -> Result<Self, Self::Error> {
+    fn try_from(mut font: Font) -> Result<Self, Self::Error> {
+        instance::resolve(&mut font);
+        FontInfo::try_from_resolved(font)
+    }
+}
+
+impl FontInfo {
+    /// Like [`FontInfo::try_from`] for a font whose instance parameters are already applied
+    pub(crate) fn try_from_resolved(font: Font) -> Result<Self, Error> {
         let master_indices: HashMap<_, _> = font
             .masters
             .iter()
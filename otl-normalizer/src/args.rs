@@ -6,7 +6,7 @@ pub struct Args {
     #[arg(short, long)]
     /// Optional destination path for writing output. Default is stdout.
     pub out: Option<PathBuf>,
-    /// Target table to print, one of gpos/gsub/all (case insensitive)
+    /// Target table to print, one of gpos/gsub/gdef/all (case insensitive)
     #[arg(short, long, default_value_t)]
     pub table: Table,
     /// Index of font to examine, if target is a font collection
@@ -10,8 +10,9 @@ use std::{
 use write_fonts::{
     read::{
         tables::{
+            gdef::{Gdef, MarkGlyphSets},
             gpos::DeviceOrVariationIndex,
-            layout::{FeatureList, ScriptList},
+            layout::{ClassDef, FeatureList, ScriptList},
         },
         ReadError,
     },
@@ -19,11 +20,11 @@ use write_fonts::{
     types::{GlyphId16, Tag},
 };
 
-use crate::{glyph_names::NameMap, variations::DeltaComputer};
+use crate::{error::Error, glyph_names::NameMap, variations::DeltaComputer};
 
 pub(crate) struct LanguageSystem {
-    script: Tag,
-    lang: Tag,
+    pub(crate) script: Tag,
+    pub(crate) lang: Tag,
 }
 
 /// A trait for things that need a gid->name map to be printed
@@ -31,6 +32,12 @@ pub(crate) trait PrintNames {
     fn fmt_names(&self, f: &mut std::fmt::Formatter<'_>, names: &NameMap) -> std::fmt::Result;
 }
 
+/// A trait for rules that can be matched up between two fonts when diffing
+pub(crate) trait DiffKey {
+    /// Print the part of the rule that identifies it, e.g. the glyphs it applies to
+    fn fmt_key(&self, f: &mut std::fmt::Formatter<'_>, names: &NameMap) -> std::fmt::Result;
+}
+
 /// A set of lookups for a specific feature and language system
 pub(crate) struct Feature {
     pub(crate) feature: Tag,
@@ -151,7 +158,7 @@ impl Feature {
 }
 
 // used in our sorting impls, so we always put DFLT/dflt above other tags
-fn tag_to_int(tag: Tag) -> u32 {
+pub(crate) fn tag_to_int(tag: Tag) -> u32 {
     if tag == Tag::new(b"DFLT") {
         0
     } else if tag == Tag::new(b"dflt") {
@@ -232,6 +239,106 @@ pub(crate) fn get_lang_systems(
     result
 }
 
+/// Print the rules of a nested lookup, separated by commas
+pub(crate) fn fmt_rule_list<T: PrintNames>(
+    f: &mut std::fmt::Formatter<'_>,
+    rules: &[T],
+    names: &NameMap,
+) -> std::fmt::Result {
+    if rules.is_empty() {
+        return f.write_str("<no matching rules>");
+    }
+    for (i, rule) in rules.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        rule.fmt_names(f, names)?;
+    }
+    Ok(())
+}
+
+/// The GDEF tables that lookup flags refer to, for printing the glyphs they select
+#[derive(Default)]
+pub(crate) struct FlagGlyphs<'a> {
+    mark_glyph_sets: Option<MarkGlyphSets<'a>>,
+    mark_attach_classes: Option<ClassDef<'a>>,
+}
+
+impl<'a> FlagGlyphs<'a> {
+    pub(crate) fn new(gdef: Option<&Gdef<'a>>) -> Self {
+        let Some(gdef) = gdef else {
+            return Default::default();
+        };
+        FlagGlyphs {
+            mark_glyph_sets: gdef.mark_glyph_sets_def().transpose().unwrap(),
+            mark_attach_classes: gdef.mark_attach_class_def().transpose().unwrap(),
+        }
+    }
+
+    fn filter_set(&self, filter_id: u16) -> Option<GlyphSet> {
+        let filter_set = self
+            .mark_glyph_sets
+            .as_ref()
+            .map(|gsets| gsets.coverages().get(filter_id as usize))
+            .transpose()
+            .unwrap();
+        filter_set.map(|cov| cov.iter().collect())
+    }
+
+    fn attach_class(&self, class: u16) -> Option<GlyphSet> {
+        self.mark_attach_classes.as_ref().map(|class_def| {
+            class_def
+                .iter()
+                .filter_map(|(gid, glyph_class)| (glyph_class == class).then_some(gid))
+                .collect()
+        })
+    }
+}
+
+/// Print the rules of one type, noting the lookup flags wherever they change
+///
+/// Mark attachment classes and filter sets are printed as the glyphs they
+/// contain, since their ids are arbitrary.
+pub(crate) fn print_rules<T: PrintNames + Clone>(
+    f: &mut dyn io::Write,
+    type_name: &str,
+    rules: &[SingleRule<T>],
+    names: &NameMap,
+    flag_glyphs: &FlagGlyphs,
+) -> Result<(), Error> {
+    if rules.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(f, "# {} {type_name} rules", rules.len(),)?;
+    let mut last_flag = None;
+    let mut last_filter_set = None;
+    for rule in rules {
+        let (flags, filter_set_id) = rule.lookup_flags();
+        if last_flag != Some(flags) {
+            // the attachment class is in the high byte
+            let without_class = LookupFlag::from_bits_truncate(flags.to_bits() & 0xff);
+            writeln!(f, "# lookupflag {without_class:?}")?;
+            if let Some(glyphs) = flags
+                .mark_attachment_class()
+                .and_then(|class| flag_glyphs.attach_class(class))
+            {
+                writeln!(f, "# attachment class glyphs: {}", glyphs.printer(names))?;
+            }
+            last_flag = Some(flags);
+        }
+
+        if filter_set_id != last_filter_set {
+            if let Some(glyphs) = filter_set_id.and_then(|id| flag_glyphs.filter_set(id)) {
+                writeln!(f, "# filter glyphs: {}", glyphs.printer(names))?;
+            }
+        }
+        last_filter_set = filter_set_id;
+        writeln!(f, "{}", rule.printer(names))?;
+    }
+    Ok(())
+}
+
 impl GlyphSet {
     pub(crate) fn is_empty(&self) -> bool {
         match self {
@@ -0,0 +1,334 @@
+//! Contextual and chained contextual lookups, shared by GSUB and GPOS
+//!
+//! All three formats of (chained) sequence context are decomposed into the
+//! same rule type, with classes and coverages expanded to glyph sets, so
+//! that equivalent rules compare equal however they were encoded.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use write_fonts::{
+    read::{
+        tables::layout::{
+            ChainedSequenceContext, ClassDef, CoverageTable, SequenceContext, SequenceLookupRecord,
+        },
+        ReadError,
+    },
+    types::GlyphId16,
+};
+
+use crate::{
+    common::{DiffKey, GlyphSet, PrintNames},
+    glyph_names::NameMap,
+};
+
+/// A rule from a contextual lookup, before nested lookups are resolved
+#[derive(Clone, Debug)]
+pub(crate) struct ContextRule {
+    backtrack: Vec<GlyphSet>,
+    input: Vec<GlyphSet>,
+    lookahead: Vec<GlyphSet>,
+    /// (sequence index, lookup index)
+    lookups: Vec<(u16, u16)>,
+}
+
+/// A rule from a (chained) contextual lookup.
+///
+/// Rather than the ids of nested lookups, which differ between compilers, we
+/// store what those lookups do to the glyphs at each position.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ContextualRule<T> {
+    /// In reading order, unlike in the font
+    pub(crate) backtrack: Vec<GlyphSet>,
+    pub(crate) input: Vec<GlyphSet>,
+    pub(crate) lookahead: Vec<GlyphSet>,
+    pub(crate) actions: Vec<(u16, T)>,
+}
+
+impl ContextRule {
+    /// Resolve each nested lookup, given its id and the glyphs at its position
+    pub(crate) fn resolve<T>(
+        self,
+        mut resolve_lookup: impl FnMut(u16, &HashSet<GlyphId16>) -> T,
+    ) -> ContextualRule<T> {
+        let actions = self
+            .lookups
+            .iter()
+            .map(|(seq_idx, lookup_id)| {
+                let glyphs = self
+                    .input
+                    .get(*seq_idx as usize)
+                    .map(|set| set.iter().collect())
+                    .unwrap_or_default();
+                (*seq_idx, resolve_lookup(*lookup_id, &glyphs))
+            })
+            .collect();
+        ContextualRule {
+            backtrack: self.backtrack,
+            input: self.input,
+            lookahead: self.lookahead,
+            actions,
+        }
+    }
+}
+
+/// One glyph is a single glyph, however it was encoded
+pub(crate) fn glyph_set(glyphs: impl IntoIterator<Item = GlyphId16>) -> GlyphSet {
+    let glyphs = glyphs.into_iter().collect::<BTreeSet<_>>();
+    match glyphs.len() {
+        1 => GlyphSet::Single(*glyphs.first().unwrap()),
+        _ => GlyphSet::Multiple(glyphs),
+    }
+}
+
+pub(crate) fn coverage_set(
+    coverage: Result<CoverageTable, ReadError>,
+) -> Result<GlyphSet, ReadError> {
+    coverage.map(|coverage| glyph_set(coverage.iter()))
+}
+
+/// The glyphs in each class, including class 0, which is every glyph not in another class
+fn class_sets(class_def: &ClassDef, num_glyphs: u16) -> BTreeMap<u16, GlyphSet> {
+    let mut classes = BTreeMap::<u16, Vec<GlyphId16>>::new();
+    for gid in (0..num_glyphs).map(GlyphId16::new) {
+        classes.entry(class_def.get(gid)).or_default().push(gid);
+    }
+    classes
+        .into_iter()
+        .map(|(class, glyphs)| (class, glyph_set(glyphs)))
+        .collect()
+}
+
+fn class_set(classes: &BTreeMap<u16, GlyphSet>, class: u16) -> GlyphSet {
+    classes
+        .get(&class)
+        .cloned()
+        .unwrap_or_else(|| GlyphSet::Multiple(Default::default()))
+}
+
+fn lookup_records(records: &[SequenceLookupRecord]) -> Vec<(u16, u16)> {
+    records
+        .iter()
+        .map(|rec| (rec.sequence_index(), rec.lookup_list_index()))
+        .collect()
+}
+
+pub(crate) fn get_context_rules(
+    subtables: &[SequenceContext],
+    num_glyphs: u16,
+) -> Result<Vec<ContextRule>, ReadError> {
+    let mut result = Vec::new();
+    for sub in subtables {
+        match sub {
+            SequenceContext::Format1(sub) => {
+                for (first, rule_set) in sub.coverage()?.iter().zip(sub.seq_rule_sets().iter()) {
+                    let Some(rule_set) = rule_set.transpose()? else {
+                        continue;
+                    };
+                    for rule in rule_set.seq_rules().iter() {
+                        let rule = rule?;
+                        let input = std::iter::once(first)
+                            .chain(rule.input_sequence().iter().map(|gid| gid.get()))
+                            .map(GlyphSet::Single)
+                            .collect();
+                        result.push(ContextRule {
+                            backtrack: Vec::new(),
+                            input,
+                            lookahead: Vec::new(),
+                            lookups: lookup_records(rule.seq_lookup_records()),
+                        });
+                    }
+                }
+            }
+            SequenceContext::Format2(sub) => {
+                let coverage = sub.coverage()?.iter().collect::<HashSet<_>>();
+                let classes = class_sets(&sub.class_def()?, num_glyphs);
+                for (first_class, rule_set) in sub.class_seq_rule_sets().iter().enumerate() {
+                    let Some(rule_set) = rule_set.transpose()? else {
+                        continue;
+                    };
+                    // only glyphs in the coverage can start a match
+                    let first = glyph_set(
+                        class_set(&classes, first_class as u16)
+                            .iter()
+                            .filter(|gid| coverage.contains(gid)),
+                    );
+                    for rule in rule_set.class_seq_rules().iter() {
+                        let rule = rule?;
+                        let input = std::iter::once(first.clone())
+                            .chain(
+                                rule.input_sequence()
+                                    .iter()
+                                    .map(|class| class_set(&classes, class.get())),
+                            )
+                            .collect();
+                        result.push(ContextRule {
+                            backtrack: Vec::new(),
+                            input,
+                            lookahead: Vec::new(),
+                            lookups: lookup_records(rule.seq_lookup_records()),
+                        });
+                    }
+                }
+            }
+            SequenceContext::Format3(sub) => {
+                let input = sub
+                    .coverages()
+                    .iter()
+                    .map(coverage_set)
+                    .collect::<Result<_, _>>()?;
+                result.push(ContextRule {
+                    backtrack: Vec::new(),
+                    input,
+                    lookahead: Vec::new(),
+                    lookups: lookup_records(sub.seq_lookup_records()),
+                });
+            }
+        }
+    }
+    Ok(result)
+}
+
+pub(crate) fn get_chain_context_rules(
+    subtables: &[ChainedSequenceContext],
+    num_glyphs: u16,
+) -> Result<Vec<ContextRule>, ReadError> {
+    let mut result = Vec::new();
+    for sub in subtables {
+        match sub {
+            ChainedSequenceContext::Format1(sub) => {
+                let rule_sets = sub.chained_seq_rule_sets();
+                for (first, rule_set) in sub.coverage()?.iter().zip(rule_sets.iter()) {
+                    let Some(rule_set) = rule_set.transpose()? else {
+                        continue;
+                    };
+                    for rule in rule_set.chained_seq_rules().iter() {
+                        let rule = rule?;
+                        let glyphs = |seq: &[write_fonts::types::BigEndian<GlyphId16>]| {
+                            seq.iter()
+                                .map(|gid| GlyphSet::Single(gid.get()))
+                                .collect::<Vec<_>>()
+                        };
+                        let mut backtrack = glyphs(rule.backtrack_sequence());
+                        backtrack.reverse();
+                        let input = std::iter::once(GlyphSet::Single(first))
+                            .chain(glyphs(rule.input_sequence()))
+                            .collect();
+                        result.push(ContextRule {
+                            backtrack,
+                            input,
+                            lookahead: glyphs(rule.lookahead_sequence()),
+                            lookups: lookup_records(rule.seq_lookup_records()),
+                        });
+                    }
+                }
+            }
+            ChainedSequenceContext::Format2(sub) => {
+                let coverage = sub.coverage()?.iter().collect::<HashSet<_>>();
+                let backtrack_classes = class_sets(&sub.backtrack_class_def()?, num_glyphs);
+                let input_classes = class_sets(&sub.input_class_def()?, num_glyphs);
+                let lookahead_classes = class_sets(&sub.lookahead_class_def()?, num_glyphs);
+                let rule_sets = sub.chained_class_seq_rule_sets();
+                for (first_class, rule_set) in rule_sets.iter().enumerate() {
+                    let Some(rule_set) = rule_set.transpose()? else {
+                        continue;
+                    };
+                    let first = glyph_set(
+                        class_set(&input_classes, first_class as u16)
+                            .iter()
+                            .filter(|gid| coverage.contains(gid)),
+                    );
+                    for rule in rule_set.chained_class_seq_rules().iter() {
+                        let rule = rule?;
+                        let sets = |classes: &BTreeMap<u16, GlyphSet>,
+                                    seq: &[write_fonts::types::BigEndian<u16>]| {
+                            seq.iter()
+                                .map(|class| class_set(classes, class.get()))
+                                .collect::<Vec<_>>()
+                        };
+                        let mut backtrack = sets(&backtrack_classes, rule.backtrack_sequence());
+                        backtrack.reverse();
+                        let input = std::iter::once(first.clone())
+                            .chain(sets(&input_classes, rule.input_sequence()))
+                            .collect();
+                        result.push(ContextRule {
+                            backtrack,
+                            input,
+                            lookahead: sets(&lookahead_classes, rule.lookahead_sequence()),
+                            lookups: lookup_records(rule.seq_lookup_records()),
+                        });
+                    }
+                }
+            }
+            ChainedSequenceContext::Format3(sub) => {
+                let mut backtrack = sub
+                    .backtrack_coverages()
+                    .iter()
+                    .map(coverage_set)
+                    .collect::<Result<Vec<_>, _>>()?;
+                backtrack.reverse();
+                result.push(ContextRule {
+                    backtrack,
+                    input: sub
+                        .input_coverages()
+                        .iter()
+                        .map(coverage_set)
+                        .collect::<Result<_, _>>()?,
+                    lookahead: sub
+                        .lookahead_coverages()
+                        .iter()
+                        .map(coverage_set)
+                        .collect::<Result<_, _>>()?,
+                    lookups: lookup_records(sub.seq_lookup_records()),
+                });
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Print the context as "backtrack input' lookahead"
+pub(crate) fn fmt_context(
+    f: &mut std::fmt::Formatter<'_>,
+    backtrack: &[GlyphSet],
+    input: &[GlyphSet],
+    lookahead: &[GlyphSet],
+    names: &NameMap,
+) -> std::fmt::Result {
+    let mut first = true;
+    let mut sep = |f: &mut std::fmt::Formatter<'_>| {
+        if !std::mem::take(&mut first) {
+            f.write_str(" ")?;
+        }
+        Ok(())
+    };
+    for glyphs in backtrack {
+        sep(f)?;
+        write!(f, "{}", glyphs.printer(names))?;
+    }
+    for glyphs in input {
+        sep(f)?;
+        write!(f, "{}'", glyphs.printer(names))?;
+    }
+    for glyphs in lookahead {
+        sep(f)?;
+        write!(f, "{}", glyphs.printer(names))?;
+    }
+    Ok(())
+}
+
+impl<T: PrintNames> PrintNames for ContextualRule<T> {
+    fn fmt_names(&self, f: &mut std::fmt::Formatter<'_>, names: &NameMap) -> std::fmt::Result {
+        fmt_context(f, &self.backtrack, &self.input, &self.lookahead, names)?;
+        for (seq_idx, nested) in &self.actions {
+            write!(f, "\n  @{seq_idx}: ")?;
+            nested.fmt_names(f, names)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> DiffKey for ContextualRule<T> {
+    fn fmt_key(&self, f: &mut std::fmt::Formatter<'_>, names: &NameMap) -> std::fmt::Result {
+        fmt_context(f, &self.backtrack, &self.input, &self.lookahead, names)
+    }
+}
@@ -0,0 +1,310 @@
+//! Comparing the normalized layout rules of two fonts
+//!
+//! Rules are compared by their printed form, with glyph ids resolved to
+//! names, so the two fonts may have different glyph orders. Each rule also
+//! has a key (generally the glyphs it applies to) which lets us report a
+//! rule whose values differ as changed, rather than as removed and added.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
+
+use write_fonts::{
+    read::{FontRef, TableProvider},
+    types::Tag,
+};
+
+use crate::{
+    common::{self, DiffKey, Feature, PrintNames, SingleRule},
+    error::Error,
+    glyph_names::NameMap,
+};
+
+/// The rules of one lookup type, as text, by key
+type RulesByKey = BTreeMap<String, String>;
+
+/// The rules that apply in one feature and language system, by lookup type
+pub(crate) type RuleTexts = BTreeMap<&'static str, RulesByKey>;
+
+/// The rules for each (feature, script, language) in a font
+pub(crate) type FontRules = BTreeMap<(Tag, Tag, Tag), RuleTexts>;
+
+/// The differences between the layout rules of two fonts
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LayoutDiff {
+    /// One entry per feature and lookup type with differences.
+    ///
+    /// Language systems with identical differences share an entry.
+    pub features: Vec<FeatureDiff>,
+}
+
+/// The differences in the rules of one type, in a feature
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeatureDiff {
+    pub feature: Tag,
+    /// The (script, language) pairs these differences apply to
+    pub lang_systems: Vec<(Tag, Tag)>,
+    /// The lookup type, e.g. "PairPos" or "MarkToBase"
+    pub rule_type: String,
+    /// Rules only in the second font
+    pub added: Vec<String>,
+    /// Rules only in the first font
+    pub removed: Vec<String>,
+    /// Rules that apply to the same glyphs but do different things
+    pub changed: Vec<RuleChange>,
+}
+
+/// A rule that differs between two fonts
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RuleChange {
+    pub before: String,
+    pub after: String,
+}
+
+/// Compare the GPOS rules of two fonts.
+///
+/// A font without a GPOS table is treated as having no rules.
+pub fn diff_gpos(font_a: &FontRef, font_b: &FontRef) -> Result<LayoutDiff, Error> {
+    let gpos_rules = |font: &FontRef| -> Result<FontRules, Error> {
+        let names = NameMap::from_font(font)?;
+        let gdef = font.gdef().ok();
+        match font.gpos() {
+            Ok(gpos) => crate::gpos::rules_for_diff(&gpos, gdef.as_ref(), &names),
+            Err(_) => Ok(Default::default()),
+        }
+    };
+    Ok(diff_rules(&gpos_rules(font_a)?, &gpos_rules(font_b)?))
+}
+
+impl LayoutDiff {
+    /// `true` if the two fonts have the same rules
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+}
+
+/// Add the text of each rule to `texts`, under `rule_type`
+pub(crate) fn add_rules<T: PrintNames + DiffKey + Clone>(
+    texts: &mut RuleTexts,
+    rule_type: &'static str,
+    rules: &[SingleRule<T>],
+    names: &NameMap,
+) {
+    struct Key<'a, T>(&'a T, &'a NameMap);
+
+    impl<T: DiffKey> Display for Key<'_, T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            self.0.fmt_key(f, self.1)
+        }
+    }
+
+    for rule in rules {
+        let key = Key(rule.rule(), names).to_string();
+        let (flags, filter_set) = rule.lookup_flags();
+        let mut text = rule.printer(names).to_string();
+        // filter set ids are not comparable between fonts, so we only note that there is one
+        if !flags.is_empty() || filter_set.is_some() {
+            text.push_str(&format!(" # lookupflag {flags:?}"));
+        }
+        let by_key = texts.entry(rule_type).or_default();
+        // the same glyphs can be in more than one rule, e.g. with different flags
+        match by_key.get_mut(&key) {
+            Some(existing) => {
+                existing.push('\n');
+                existing.push_str(&text);
+            }
+            None => {
+                by_key.insert(key, text);
+            }
+        }
+    }
+}
+
+/// Record the rules for a feature under each of its language systems
+pub(crate) fn add_lang_systems(result: &mut FontRules, feature: &Feature, rules: RuleTexts) {
+    for sys in &feature.lang_systems {
+        let entry = result
+            .entry((feature.feature, sys.script, sys.lang))
+            .or_default();
+        for (rule_type, by_key) in &rules {
+            entry
+                .entry(*rule_type)
+                .or_default()
+                .extend(by_key.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+    }
+}
+
+fn diff_rules(rules_a: &FontRules, rules_b: &FontRules) -> LayoutDiff {
+    let empty = RuleTexts::new();
+    let mut keys = rules_a.keys().chain(rules_b.keys()).collect::<Vec<_>>();
+    keys.sort_by_key(|(feature, script, lang)| {
+        (
+            common::tag_to_int(*feature),
+            common::tag_to_int(*script),
+            common::tag_to_int(*lang),
+        )
+    });
+    keys.dedup();
+
+    // we group identical differences across language systems, like when printing
+    let mut features: Vec<FeatureDiff> = Vec::new();
+    let mut group_idx = HashMap::new();
+    for &(feature, script, lang) in keys {
+        let a = rules_a.get(&(feature, script, lang)).unwrap_or(&empty);
+        let b = rules_b.get(&(feature, script, lang)).unwrap_or(&empty);
+        let mut rule_types = a.keys().chain(b.keys()).collect::<Vec<_>>();
+        rule_types.sort();
+        rule_types.dedup();
+        for rule_type in rule_types {
+            let empty = RulesByKey::new();
+            let Some(diff) = diff_rule_type(
+                a.get(rule_type).unwrap_or(&empty),
+                b.get(rule_type).unwrap_or(&empty),
+            ) else {
+                continue;
+            };
+            let (added, removed, changed) = diff;
+            let group_key = (
+                feature,
+                *rule_type,
+                added.clone(),
+                removed.clone(),
+                changed.clone(),
+            );
+            match group_idx.get(&group_key) {
+                Some(&idx) => features[idx].lang_systems.push((script, lang)),
+                None => {
+                    group_idx.insert(group_key, features.len());
+                    features.push(FeatureDiff {
+                        feature,
+                        lang_systems: vec![(script, lang)],
+                        rule_type: rule_type.to_string(),
+                        added,
+                        removed,
+                        changed,
+                    });
+                }
+            }
+        }
+    }
+    LayoutDiff { features }
+}
+
+type RuleTypeDiff = (Vec<String>, Vec<String>, Vec<RuleChange>);
+
+/// Added, removed and changed rules, or `None` if there are no differences
+fn diff_rule_type(a: &RulesByKey, b: &RulesByKey) -> Option<RuleTypeDiff> {
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    let mut changed = Vec::new();
+    for (key, before) in a {
+        match b.get(key) {
+            None => removed.push(before.clone()),
+            Some(after) if after != before => changed.push(RuleChange {
+                before: before.clone(),
+                after: after.clone(),
+            }),
+            Some(_) => (),
+        }
+    }
+    added.extend(
+        b.iter()
+            .filter(|(key, _)| !a.contains_key(*key))
+            .map(|(_, after)| after.clone()),
+    );
+    if added.is_empty() && removed.is_empty() && changed.is_empty() {
+        None
+    } else {
+        Some((added, removed, changed))
+    }
+}
+
+impl Display for LayoutDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for feature in &self.features {
+            write!(f, "# {}: ", feature.feature)?;
+            for (i, (script, lang)) in feature.lang_systems.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{script}/{lang}")?;
+            }
+            writeln!(f, " ({})", feature.rule_type)?;
+            for rule in &feature.removed {
+                writeln!(f, "- {rule}")?;
+            }
+            for rule in &feature.added {
+                writeln!(f, "+ {rule}")?;
+            }
+            for RuleChange { before, after } in &feature.changed {
+                writeln!(f, "< {before}")?;
+                writeln!(f, "> {after}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fea_rs::compile::{NopFeatureProvider, NopVariationInfo, Opts};
+    use fontdrasil::types::GlyphName;
+    use write_fonts::tables::{cmap::Cmap, maxp::Maxp};
+
+    use super::*;
+
+    fn compile(glyphs: &[&str], fea: &str) -> Vec<u8> {
+        let glyph_map = glyphs.iter().copied().map(GlyphName::new).collect();
+        let (tree, errs) = fea_rs::parse::parse_string(fea);
+        assert!(!errs.has_errors(), "{errs:?}");
+        let (compilation, _) = fea_rs::compile::compile::<NopVariationInfo, NopFeatureProvider>(
+            &tree,
+            &glyph_map,
+            None,
+            None,
+            Opts::new(),
+        )
+        .unwrap();
+        let mut builder = compilation.to_font_builder().unwrap();
+        builder.add_table(&Maxp::new(glyphs.len() as u16)).unwrap();
+        builder.add_table(&glyph_map.make_post_table()).unwrap();
+        builder.add_table(&Cmap::default()).unwrap();
+        builder.build()
+    }
+
+    #[test]
+    fn added_removed_and_changed() {
+        let a = compile(
+            &[".notdef", "A", "V", "T", "o"],
+            "feature kern { pos A V -50; pos T o -80; pos V A -40; } kern;",
+        );
+        // different glyph order, same semantics for T o
+        let b = compile(
+            &[".notdef", "o", "T", "V", "A"],
+            "feature kern { pos A V -60; pos T o -80; pos A A 5; } kern;",
+        );
+        let diff = diff_gpos(&FontRef::new(&a).unwrap(), &FontRef::new(&b).unwrap()).unwrap();
+        assert_eq!(1, diff.features.len(), "{diff}");
+        let kern = &diff.features[0];
+        assert_eq!(Tag::new(b"kern"), kern.feature);
+        assert_eq!("PairPos", kern.rule_type);
+        assert_eq!(1, kern.added.len());
+        assert!(kern.added[0].starts_with("A ") && kern.added[0].ends_with(" A"));
+        assert_eq!(1, kern.removed.len());
+        assert!(kern.removed[0].starts_with("V "));
+        assert_eq!(1, kern.changed.len());
+        assert!(kern.changed[0].before.contains("-50"));
+        assert!(kern.changed[0].after.contains("-60"));
+    }
+
+    #[test]
+    fn identical_fonts() {
+        let fea = "feature kern { pos A V -50; } kern;";
+        let a = compile(&[".notdef", "A", "V"], fea);
+        let b = compile(&[".notdef", "V", "A"], fea);
+        let diff = diff_gpos(&FontRef::new(&a).unwrap(), &FontRef::new(&b).unwrap()).unwrap();
+        assert!(diff.is_empty(), "{diff}");
+    }
+}
@@ -1,17 +1,110 @@
-//! Normalizing the ligature caret table
+//! Normalizing GDEF
+//!
+//! Class and set ids are assigned arbitrarily by compilers, so we print
+//! attachment classes and mark filter sets sorted by their contents instead.
 
-use std::{fmt::Display, io};
+use std::{collections::BTreeMap, fmt::Display, io};
 
 use fontdrasil::types::GlyphName;
 use write_fonts::read::{
-    tables::gdef::{CaretValue, Gdef, LigGlyph},
+    tables::{
+        gdef::{CaretValue, Gdef, LigGlyph},
+        layout::ClassDef,
+    },
     ReadError,
 };
 
-use crate::{common::DeviceOrDeltas, variations::DeltaComputer, Error, NameMap};
+use crate::{
+    common::{DeviceOrDeltas, GlyphSet},
+    variations::DeltaComputer,
+    Error, NameMap,
+};
 
-/// Print normalized GDEF ligature carets
+/// Print normalized GDEF glyph classes, attachment classes, mark filter sets and ligature carets
 pub fn print(f: &mut dyn io::Write, table: &Gdef, names: &NameMap) -> Result<(), Error> {
+    print_glyph_classes(f, table, names)?;
+    print_mark_attach_classes(f, table, names)?;
+    print_mark_glyph_sets(f, table, names)?;
+    print_lig_caret_list(f, table, names)
+}
+
+fn print_glyph_classes(f: &mut dyn io::Write, table: &Gdef, names: &NameMap) -> Result<(), Error> {
+    let Some(class_def) = table.glyph_class_def().transpose()? else {
+        return Ok(());
+    };
+    let classes = glyphs_by_class(&class_def);
+    writeln!(f, "# glyph classes")?;
+    for (class, glyphs) in &classes {
+        let class_name = match class {
+            1 => "base".to_string(),
+            2 => "ligature".to_string(),
+            3 => "mark".to_string(),
+            4 => "component".to_string(),
+            other => format!("unknown class {other}"),
+        };
+        writeln!(f, "{class_name}: {}", glyphs.printer(names))?;
+    }
+    Ok(())
+}
+
+fn print_mark_attach_classes(
+    f: &mut dyn io::Write,
+    table: &Gdef,
+    names: &NameMap,
+) -> Result<(), Error> {
+    let Some(class_def) = table.mark_attach_class_def().transpose()? else {
+        return Ok(());
+    };
+    let classes = glyphs_by_class(&class_def);
+    print_sorted_sets(f, "mark attachment classes", classes.into_values(), names)
+}
+
+fn print_mark_glyph_sets(
+    f: &mut dyn io::Write,
+    table: &Gdef,
+    names: &NameMap,
+) -> Result<(), Error> {
+    let Some(mark_glyph_sets) = table.mark_glyph_sets_def().transpose()? else {
+        return Ok(());
+    };
+    let sets = mark_glyph_sets
+        .coverages()
+        .iter()
+        .map(|coverage| coverage.map(|coverage| coverage.iter().collect::<GlyphSet>()))
+        .collect::<Result<Vec<_>, _>>()?;
+    print_sorted_sets(f, "mark filter sets", sets.into_iter(), names)
+}
+
+fn glyphs_by_class(class_def: &ClassDef) -> BTreeMap<u16, GlyphSet> {
+    let mut classes = BTreeMap::<u16, GlyphSet>::new();
+    for (gid, class) in class_def.iter() {
+        classes
+            .entry(class)
+            .or_insert_with(|| GlyphSet::Multiple(Default::default()))
+            .add(gid);
+    }
+    classes
+}
+
+fn print_sorted_sets(
+    f: &mut dyn io::Write,
+    header: &str,
+    sets: impl Iterator<Item = GlyphSet>,
+    names: &NameMap,
+) -> Result<(), Error> {
+    let mut sets = sets.collect::<Vec<_>>();
+    if sets.is_empty() {
+        return Ok(());
+    }
+    sets.sort();
+    writeln!(f, "# {header}")?;
+    for glyphs in &sets {
+        writeln!(f, "{}", glyphs.printer(names))?;
+    }
+    Ok(())
+}
+
+fn print_lig_caret_list(f: &mut dyn io::Write, table: &Gdef, names: &NameMap) -> Result<(), Error> {
     let var_store = table
         .item_var_store()
         .map(|ivs| ivs.and_then(DeltaComputer::new))
@@ -26,6 +119,7 @@ pub fn print(f: &mut dyn io::Write, table: &Gdef, names: &NameMap) -> Result<(),
         return Ok(());
     };
 
+    writeln!(f, "# ligature carets")?;
     let coverage = lig_carets.coverage()?;
     for (gid, lig_glyph) in coverage.iter().zip(lig_carets.lig_glyphs().iter()) {
         let lig_glyph = lig_glyph?;
@@ -102,3 +196,64 @@ impl Display for ResolvedCaret {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use write_fonts::{
+        read::FontRead,
+        tables::{gdef as wgdef, layout::ClassDef as WClassDef},
+        types::GlyphId16,
+    };
+
+    use super::*;
+
+    fn print_to_string(gdef: &wgdef::Gdef) -> String {
+        let data = write_fonts::dump_table(gdef).unwrap();
+        let gdef = Gdef::read(data.as_slice().into()).unwrap();
+        let names = NameMap(
+            ["a", "b", "acute", "grave", "dotbelow"]
+                .into_iter()
+                .enumerate()
+                .map(|(i, name)| (GlyphId16::new(i as u16 + 1), GlyphName::new(name)))
+                .collect(),
+        );
+        let mut out = Vec::new();
+        print(&mut out, &gdef, &names).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn class_def(classes: &[(u16, u16)]) -> WClassDef {
+        classes
+            .iter()
+            .map(|(gid, class)| (GlyphId16::new(*gid), *class))
+            .collect()
+    }
+
+    #[test]
+    fn attachment_class_ids_are_ignored() {
+        let glyph_classes = class_def(&[(1, 1), (2, 1), (3, 3), (4, 3), (5, 3)]);
+        let a = wgdef::Gdef::new(
+            Some(glyph_classes.clone()),
+            None,
+            None,
+            Some(class_def(&[(3, 1), (4, 1), (5, 2)])),
+        );
+        let b = wgdef::Gdef::new(
+            Some(glyph_classes),
+            None,
+            None,
+            Some(class_def(&[(3, 2), (4, 2), (5, 1)])),
+        );
+        let printed = print_to_string(&a);
+        assert_eq!(printed, print_to_string(&b));
+        assert_eq!(
+            "# glyph classes\n\
+             base: [a,b]\n\
+             mark: [acute,grave,dotbelow]\n\
+             # mark attachment classes\n\
+             [acute,grave]\n\
+             [dotbelow]\n",
+            printed
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, HashSet},
     fmt::{Debug, Display},
     io,
 };
@@ -7,7 +7,7 @@ use std::{
 use write_fonts::{
     read::{
         tables::{
-            gdef::{Gdef, MarkGlyphSets},
+            gdef::Gdef,
             gpos::{AnchorTable, Gpos, PositionLookupList, PositionSubtables, ValueRecord},
             layout::DeviceOrVariationIndex,
         },
@@ -18,7 +18,9 @@ use write_fonts::{
 };
 
 use crate::{
-    common::{self, DeviceOrDeltas, GlyphSet, Lookup, PrintNames, SingleRule},
+    common::{self, DeviceOrDeltas, FlagGlyphs, Lookup, PrintNames, SingleRule},
+    contextual::{self, ContextualRule},
+    diff::{self, FontRules, RuleTexts},
     error::Error,
     glyph_names::NameMap,
     gpos::cursive::CursivePosRule,
@@ -28,11 +30,12 @@ use crate::{
 mod cursive;
 mod marks;
 mod pairpos;
+mod singlepos;
 
 #[cfg(test)]
 mod test_helpers;
 
-use self::{marks::MarkAttachmentRule, pairpos::PairPosRule};
+use self::{marks::MarkAttachmentRule, pairpos::PairPosRule, singlepos::SinglePosRule};
 
 /// Print normalized GPOS layout rules for the provided font
 pub fn print(
@@ -41,21 +44,18 @@ pub fn print(
     gdef: Option<&Gdef>,
     names: &NameMap,
 ) -> Result<(), Error> {
-    let var_store = gdef
-        .as_ref()
-        .and_then(|gdef| gdef.item_var_store())
-        .map(|ivs| ivs.and_then(DeltaComputer::new))
-        .transpose()
-        .unwrap();
-    let mark_glyph_sets = gdef
-        .and_then(|gdef| gdef.mark_glyph_sets_def())
-        .transpose()
-        .unwrap();
+    let var_store = delta_computer(gdef);
+    let flag_glyphs = FlagGlyphs::new(gdef);
 
     let script_list = table.script_list().unwrap();
     let feature_list = table.feature_list().unwrap();
     let lang_systems = common::get_lang_systems(&script_list, &feature_list);
-    let lookup_rules = get_lookup_rules(&table.lookup_list().unwrap(), var_store.as_ref());
+    let num_glyphs = names.0.len() as u16;
+    let lookup_rules = get_lookup_rules(
+        &table.lookup_list().unwrap(),
+        var_store.as_ref(),
+        num_glyphs,
+    );
 
     // so first we iterate through each feature/language/script set
     for sys in &lang_systems {
@@ -64,60 +64,68 @@ pub fn print(
 
         // then for each feature/language/script we iterate through
         // all rules, split by the rule (lookup) type
+        let single = lookup_rules.single_rules(&sys.lookups);
         let pairpos = lookup_rules.pairpos_rules(&sys.lookups);
         let markmark = lookup_rules.markmark_rules(&sys.lookups);
         let markbase = lookup_rules.markbase_rules(&sys.lookups);
         let markliga = lookup_rules.markliga_rules(&sys.lookups);
         let cursive = lookup_rules.cursive_rules(&sys.lookups);
-
-        print_rules(f, "PairPos", &pairpos, names, mark_glyph_sets.as_ref())?;
-        print_rules(f, "MarkToBase", &markbase, names, mark_glyph_sets.as_ref())?;
-        print_rules(f, "MarkToMark", &markmark, names, mark_glyph_sets.as_ref())?;
-        print_rules(f, "MarkToLig", &markliga, names, mark_glyph_sets.as_ref())?;
-        print_rules(f, "CursivePos", &cursive, names, mark_glyph_sets.as_ref())?;
+        let contextual = lookup_rules.contextual_rules(&sys.lookups);
+
+        common::print_rules(f, "SinglePos", &single, names, &flag_glyphs)?;
+        common::print_rules(f, "PairPos", &pairpos, names, &flag_glyphs)?;
+        common::print_rules(f, "MarkToBase", &markbase, names, &flag_glyphs)?;
+        common::print_rules(f, "MarkToMark", &markmark, names, &flag_glyphs)?;
+        common::print_rules(f, "MarkToLig", &markliga, names, &flag_glyphs)?;
+        common::print_rules(f, "CursivePos", &cursive, names, &flag_glyphs)?;
+        common::print_rules(f, "ContextualPos", &contextual, names, &flag_glyphs)?;
     }
 
     Ok(())
 }
 
-fn print_rules<T: PrintNames + Clone>(
-    f: &mut dyn io::Write,
-    type_name: &str,
-    rules: &[SingleRule<T>],
+/// The GPOS rules for each feature and language system, for diffing
+pub(crate) fn rules_for_diff(
+    table: &Gpos,
+    gdef: Option<&Gdef>,
     names: &NameMap,
-    mark_glyph_sets: Option<&MarkGlyphSets>,
-) -> Result<(), Error> {
-    if rules.is_empty() {
-        return Ok(());
-    }
-
-    writeln!(f, "# {} {type_name} rules", rules.len(),)?;
-    let mut last_flag = None;
-    let mut last_filter_set = None;
-    for rule in rules {
-        let (flags, filter_set_id) = rule.lookup_flags();
-        if last_flag != Some(flags) {
-            writeln!(f, "# lookupflag {flags:?}")?;
-            last_flag = Some(flags);
-        }
+) -> Result<FontRules, Error> {
+    let var_store = delta_computer(gdef);
+    let script_list = table.script_list()?;
+    let feature_list = table.feature_list()?;
+    let lang_systems = common::get_lang_systems(&script_list, &feature_list);
+    let num_glyphs = names.0.len() as u16;
+    let lookup_rules = get_lookup_rules(&table.lookup_list()?, var_store.as_ref(), num_glyphs);
 
-        if filter_set_id != last_filter_set {
-            if let Some(filter_id) = filter_set_id {
-                let filter_set = mark_glyph_sets
-                    .as_ref()
-                    .map(|gsets| gsets.coverages().get(filter_id as usize))
-                    .transpose()
-                    .unwrap();
-                let glyphs = filter_set.map(|cov| cov.iter().collect::<GlyphSet>());
-                if let Some(glyphs) = glyphs {
-                    writeln!(f, "# filter glyphs: {}", glyphs.printer(names))?;
-                }
-            }
-        }
-        last_filter_set = filter_set_id;
-        writeln!(f, "{}", rule.printer(names))?;
+    let mut result = FontRules::new();
+    for sys in &lang_systems {
+        let single = lookup_rules.single_rules(&sys.lookups);
+        // pairs are not combined, so that each pair can be matched up
+        let pairpos = lookup_rules.merged_pairpos_rules(&sys.lookups);
+        let markbase = lookup_rules.markbase_rules(&sys.lookups);
+        let markmark = lookup_rules.markmark_rules(&sys.lookups);
+        let markliga = lookup_rules.markliga_rules(&sys.lookups);
+        let cursive = lookup_rules.cursive_rules(&sys.lookups);
+        let contextual = lookup_rules.contextual_rules(&sys.lookups);
+
+        let mut rules = RuleTexts::new();
+        diff::add_rules(&mut rules, "SinglePos", &single, names);
+        diff::add_rules(&mut rules, "PairPos", &pairpos, names);
+        diff::add_rules(&mut rules, "MarkToBase", &markbase, names);
+        diff::add_rules(&mut rules, "MarkToMark", &markmark, names);
+        diff::add_rules(&mut rules, "MarkToLig", &markliga, names);
+        diff::add_rules(&mut rules, "CursivePos", &cursive, names);
+        diff::add_rules(&mut rules, "ContextualPos", &contextual, names);
+        diff::add_lang_systems(&mut result, sys, rules);
     }
-    Ok(())
+    Ok(result)
+}
+
+fn delta_computer(gdef: Option<&Gdef>) -> Option<DeltaComputer> {
+    gdef.and_then(|gdef| gdef.item_var_store())
+        .map(|ivs| ivs.and_then(DeltaComputer::new))
+        .transpose()
+        .unwrap()
 }
 
 /// A value plus an optional device table or set of deltas
@@ -251,45 +259,99 @@ impl ResolvedValue {
 
 #[derive(Clone, Debug, Default)]
 struct LookupRules {
+    single: Vec<Lookup<SinglePosRule>>,
     pairpos: Vec<Lookup<PairPosRule>>,
     markbase: Vec<Lookup<MarkAttachmentRule>>,
     markmark: Vec<Lookup<MarkAttachmentRule>>,
     markliga: Vec<Lookup<MarkAttachmentRule>>,
     cursive: Vec<Lookup<CursivePosRule>>,
+    contextual: Vec<Lookup<ContextualRule<NestedRules>>>,
     // decomposed rules for each lookup, in lookup order
 }
 
+/// What a nested lookup does, as seen from a contextual rule
+#[derive(Clone, Debug)]
+enum NestedRules {
+    Single(Vec<SinglePosRule>),
+    Pair(Vec<PairPosRule>),
+    Mark(Vec<MarkAttachmentRule>),
+    Cursive(Vec<CursivePosRule>),
+    /// Contextual lookups called from contextual lookups are not expanded further
+    Contextual,
+    /// The referenced lookup does not exist
+    Missing(u16),
+}
+
 impl LookupRules {
-    fn pairpos_rules<'a>(&'a self, lookups: &[u16]) -> Vec<SingleRule<'a, PairPosRule>> {
-        use std::collections::hash_map;
-        // so these rules are currently decomposed, (each rule is for a
-        // single pair of glyphs) so we want to normalize them and then combine.
-        let mut pairmap = HashMap::<_, _>::new();
-        for rule in self
-            .pairpos
+    fn single_rules<'a>(&'a self, lookups: &[u16]) -> Vec<SingleRule<'a, SinglePosRule>> {
+        let mut result = self
+            .single
             .iter()
             .filter(|lookup| lookups.contains(&lookup.lookup_id))
-            .flat_map(|lookup| lookup.iter())
+            .flat_map(Lookup::iter)
+            .collect::<Vec<_>>();
+        result.sort_unstable();
+        result
+    }
+
+    fn contextual_rules<'a>(
+        &'a self,
+        lookups: &[u16],
+    ) -> Vec<SingleRule<'a, ContextualRule<NestedRules>>> {
+        // the first matching rule is applied, so these keep their order
+        self.contextual
+            .iter()
+            .filter(|lookup| lookups.contains(&lookup.lookup_id))
+            .flat_map(Lookup::iter)
+            .collect()
+    }
+
+    /// The rules of lookup `id` that could apply to any glyph in `glyphs`
+    ///
+    /// Contextual lookups are checked by the caller, since they are resolved last.
+    fn nested_rules(&self, id: u16, glyphs: &HashSet<GlyphId16>) -> NestedRules {
+        fn find<T: Clone>(
+            lookups: &[Lookup<T>],
+            id: u16,
+            applies: impl Fn(&T) -> bool,
+        ) -> Option<Vec<T>> {
+            lookups
+                .iter()
+                .find(|lookup| lookup.lookup_id == id)
+                .map(|lookup| {
+                    lookup
+                        .iter()
+                        .filter(|rule| applies(rule.rule()))
+                        .map(|rule| rule.rule().clone())
+                        .collect()
+                })
+        }
+        let mark_applies = |rule: &MarkAttachmentRule| {
+            rule.iter_base_mark_pairs()
+                .any(|(base, mark)| glyphs.contains(&base) || glyphs.contains(&mark))
+        };
+        if let Some(rules) = find(&self.single, id, |r| glyphs.contains(&r.glyph)) {
+            NestedRules::Single(rules)
+        } else if let Some(rules) = find(&self.pairpos, id, |r| glyphs.contains(&r.first)) {
+            NestedRules::Pair(rules)
+        } else if let Some(rules) = find(&self.markbase, id, mark_applies)
+            .or_else(|| find(&self.markmark, id, mark_applies))
+            .or_else(|| find(&self.markliga, id, mark_applies))
         {
-            match pairmap.entry((
-                rule.rule().first,
-                rule.rule().second.clone(),
-                rule.lookup_flags(),
-            )) {
-                hash_map::Entry::Vacant(entry) => {
-                    entry.insert(rule);
-                }
-                hash_map::Entry::Occupied(mut entry) => {
-                    let prev_rule = entry.get_mut().rule_mut();
-                    prev_rule.merge(rule.rule());
-                }
-            };
+            NestedRules::Mark(rules)
+        } else if let Some(rules) = find(&self.cursive, id, |r| glyphs.contains(&r.glyph)) {
+            NestedRules::Cursive(rules)
+        } else {
+            NestedRules::Missing(id)
         }
+    }
 
+    fn pairpos_rules<'a>(&'a self, lookups: &[u16]) -> Vec<SingleRule<'a, PairPosRule>> {
+        use std::collections::hash_map;
         // now for any given first glyph + adjustment if there are multiple
         // second glyphs we combine these
         let mut seen = HashMap::<_, _>::new();
-        for rule in pairmap.into_values() {
+        for rule in self.merged_pairpos_rules(lookups) {
             match seen.entry((
                 rule.rule().first,
                 rule.lookup_flags(),
@@ -313,6 +375,38 @@ impl LookupRules {
         result
     }
 
+    /// One rule per pair of glyphs, with the adjustments from all lookups summed
+    fn merged_pairpos_rules<'a>(&'a self, lookups: &[u16]) -> Vec<SingleRule<'a, PairPosRule>> {
+        use std::collections::hash_map;
+        // so these rules are currently decomposed, (each rule is for a
+        // single pair of glyphs) so we want to normalize them and then combine.
+        let mut pairmap = HashMap::<_, _>::new();
+        for rule in self
+            .pairpos
+            .iter()
+            .filter(|lookup| lookups.contains(&lookup.lookup_id))
+            .flat_map(|lookup| lookup.iter())
+        {
+            match pairmap.entry((
+                rule.rule().first,
+                rule.rule().second.clone(),
+                rule.lookup_flags(),
+            )) {
+                hash_map::Entry::Vacant(entry) => {
+                    entry.insert(rule);
+                }
+                hash_map::Entry::Occupied(mut entry) => {
+                    let prev_rule = entry.get_mut().rule_mut();
+                    prev_rule.merge(rule.rule());
+                }
+            };
+        }
+
+        let mut result: Vec<_> = pairmap.into_values().collect();
+        result.sort_unstable();
+        result
+    }
+
     fn markbase_rules<'a>(&'a self, lookups: &[u16]) -> Vec<SingleRule<'a, MarkAttachmentRule>> {
         let lookups = self
             .markbase
@@ -418,8 +512,11 @@ fn normalize_mark_lookups<'a>(
 fn get_lookup_rules(
     lookups: &PositionLookupList,
     delta_computer: Option<&DeltaComputer>,
+    num_glyphs: u16,
 ) -> LookupRules {
     let mut result = LookupRules::default();
+    // contextual rules are resolved once we know what every lookup does
+    let mut raw_contextual = Vec::new();
     for (id, lookup) in lookups.lookups().iter().enumerate() {
         let lookup = lookup.unwrap();
         let flag = lookup.lookup_flag();
@@ -429,6 +526,13 @@ fn get_lookup_rules(
             .flatten();
         let subtables = lookup.subtables().unwrap();
         match subtables {
+            PositionSubtables::Single(subs) => {
+                let subs = subs.iter().flat_map(|sub| sub.ok()).collect::<Vec<_>>();
+                let rules = singlepos::get_single_rules(&subs, delta_computer).unwrap();
+                result
+                    .single
+                    .push(Lookup::new(id, rules, flag, mark_filter_id));
+            }
             PositionSubtables::Pair(subs) => {
                 let subs = subs.iter().flat_map(|sub| sub.ok()).collect::<Vec<_>>();
                 let rules = pairpos::get_pairpos_rules(&subs, delta_computer).unwrap();
@@ -462,12 +566,56 @@ fn get_lookup_rules(
                     .cursive
                     .push(Lookup::new(id, rules, flag, mark_filter_id));
             }
-            _ => (),
+            PositionSubtables::Contextual(subs) => {
+                let subs = subs.iter().flat_map(|sub| sub.ok()).collect::<Vec<_>>();
+                let rules = contextual::get_context_rules(&subs, num_glyphs).unwrap();
+                raw_contextual.push((id, rules, flag, mark_filter_id));
+            }
+            PositionSubtables::ChainContextual(subs) => {
+                let subs = subs.iter().flat_map(|sub| sub.ok()).collect::<Vec<_>>();
+                let rules = contextual::get_chain_context_rules(&subs, num_glyphs).unwrap();
+                raw_contextual.push((id, rules, flag, mark_filter_id));
+            }
         }
     }
+
+    let contextual_ids = raw_contextual
+        .iter()
+        .map(|(id, ..)| *id as u16)
+        .collect::<HashSet<_>>();
+    for (id, rules, flag, mark_filter_id) in raw_contextual {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                rule.resolve(|lookup_id, glyphs| {
+                    if contextual_ids.contains(&lookup_id) {
+                        NestedRules::Contextual
+                    } else {
+                        result.nested_rules(lookup_id, glyphs)
+                    }
+                })
+            })
+            .collect();
+        result
+            .contextual
+            .push(Lookup::new(id, rules, flag, mark_filter_id));
+    }
     result
 }
 
+impl PrintNames for NestedRules {
+    fn fmt_names(&self, f: &mut std::fmt::Formatter<'_>, names: &NameMap) -> std::fmt::Result {
+        match self {
+            NestedRules::Single(rules) => common::fmt_rule_list(f, rules, names),
+            NestedRules::Pair(rules) => common::fmt_rule_list(f, rules, names),
+            NestedRules::Mark(rules) => common::fmt_rule_list(f, rules, names),
+            NestedRules::Cursive(rules) => common::fmt_rule_list(f, rules, names),
+            NestedRules::Contextual => f.write_str("<contextual>"),
+            NestedRules::Missing(id) => write!(f, "<missing lookup {id}>"),
+        }
+    }
+}
+
 impl From<i16> for ResolvedValue {
     fn from(src: i16) -> ResolvedValue {
         ResolvedValue {
@@ -548,7 +696,7 @@ mod tests {
         )
         .unwrap();
 
-        let rules = get_lookup_rules(&lookup_list, None);
+        let rules = get_lookup_rules(&lookup_list, None, 100);
 
         let our_rules = rules
             .pairpos_rules(&[0, 1])
@@ -591,7 +739,7 @@ mod tests {
         )
         .unwrap();
 
-        let rules = get_lookup_rules(&lookup_list, None);
+        let rules = get_lookup_rules(&lookup_list, None, 100);
 
         let our_rules = rules
             .pairpos_rules(&[0, 1])
@@ -647,7 +795,7 @@ mod tests {
         )
         .unwrap();
 
-        let rules = get_lookup_rules(&lookup_list, None);
+        let rules = get_lookup_rules(&lookup_list, None, 100);
 
         let mark_base_rules = rules
             .markbase_rules(&[0, 1])
@@ -666,4 +814,47 @@ mod tests {
 
         assert_eq!(mark_base_rules, expected,)
     }
+
+    #[test]
+    fn chain_context_resolves_nested_single_pos() {
+        // pos g1 [g3 g4]' by the nested lookup, which only has a value for g3
+        let coverage = |gids: &[u16]| -> wlayout::CoverageTable {
+            gids.iter().copied().map(GlyphId16::new).collect()
+        };
+        let single = wgpos::SinglePos::format_1(
+            coverage(&[3]),
+            wgpos::ValueRecord::new().with_x_advance(-50),
+        );
+        let chain = wgpos::PositionChainContext::from(wlayout::ChainedSequenceContext::format_3(
+            vec![coverage(&[1])],
+            vec![coverage(&[3, 4])],
+            vec![],
+            vec![wlayout::SequenceLookupRecord::new(0, 1)],
+        ));
+        let lookup_list = wlayout::LookupList::new(vec![
+            wgpos::PositionLookup::ChainContextual(wlayout::Lookup::new(
+                LookupFlag::empty(),
+                vec![chain],
+            )),
+            wgpos::PositionLookup::Single(wlayout::Lookup::new(LookupFlag::empty(), vec![single])),
+        ]);
+        let lookup_list = write_fonts::dump_table(&lookup_list).unwrap();
+        let lookup_list = write_fonts::read::tables::gpos::PositionLookupList::read(
+            lookup_list.as_slice().into(),
+        )
+        .unwrap();
+
+        let rules = get_lookup_rules(&lookup_list, None, 10);
+        let names = NameMap(
+            (0..10u16)
+                .map(|gid| (GlyphId16::new(gid), format!("g{gid}").into()))
+                .collect(),
+        );
+        let printed = rules
+            .contextual_rules(&[0])
+            .iter()
+            .map(|rule| rule.printer(&names).to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["g1 [g3,g4]'\n  @0: g3 -50"], printed);
+    }
 }
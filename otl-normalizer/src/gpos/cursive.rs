@@ -3,7 +3,7 @@ use std::collections::HashSet;
 use write_fonts::read::{tables::gpos::CursivePosFormat1, ReadError};
 
 use crate::{
-    common::PrintNames,
+    common::{DiffKey, PrintNames},
     gpos::{GlyphId16, ResolvedAnchor},
     variations::DeltaComputer,
 };
@@ -36,6 +36,12 @@ impl PrintNames for CursivePosRule {
     }
 }
 
+impl DiffKey for CursivePosRule {
+    fn fmt_key(&self, f: &mut std::fmt::Formatter<'_>, names: &crate::NameMap) -> std::fmt::Result {
+        f.write_str(names.get(self.glyph).as_str())
+    }
+}
+
 pub(super) fn get_cursive_rules(
     subtables: &[CursivePosFormat1],
     delta_computer: Option<&DeltaComputer>,
@@ -9,7 +9,11 @@ use write_fonts::read::{
     ReadError,
 };
 
-use crate::{common::GlyphSet, glyph_names::NameMap, variations::DeltaComputer};
+use crate::{
+    common::{DiffKey, GlyphSet},
+    glyph_names::NameMap,
+    variations::DeltaComputer,
+};
 
 use super::{PrintNames, ResolvedAnchor};
 
@@ -58,6 +62,12 @@ impl PrintNames for MarkAttachmentRule {
     }
 }
 
+impl DiffKey for MarkAttachmentRule {
+    fn fmt_key(&self, f: &mut std::fmt::Formatter<'_>, names: &NameMap) -> std::fmt::Result {
+        f.write_str(names.get(self.base).as_str())
+    }
+}
+
 impl MarkAttachmentRule {
     pub(crate) fn iter_base_mark_pairs(&self) -> impl Iterator<Item = (GlyphId16, GlyphId16)> + '_ {
         self.marks
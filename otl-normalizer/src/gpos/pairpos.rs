@@ -9,7 +9,11 @@ use write_fonts::read::{
     ReadError,
 };
 
-use crate::{common::GlyphSet, glyph_names::NameMap, variations::DeltaComputer};
+use crate::{
+    common::{DiffKey, GlyphSet},
+    glyph_names::NameMap,
+    variations::DeltaComputer,
+};
 
 use super::{PrintNames, ResolvedValueRecord};
 
@@ -43,6 +47,17 @@ impl PrintNames for PairPosRule {
     }
 }
 
+impl DiffKey for PairPosRule {
+    fn fmt_key(&self, f: &mut std::fmt::Formatter<'_>, names: &NameMap) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}",
+            names.get(self.first),
+            self.second.printer(names)
+        )
+    }
+}
+
 impl Debug for PairPosRule {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("PairPosRule")
@@ -0,0 +1,72 @@
+use std::collections::HashSet;
+
+use write_fonts::read::{tables::gpos::SinglePos, types::GlyphId16, ReadError};
+
+use crate::{
+    common::{DiffKey, PrintNames},
+    glyph_names::NameMap,
+    variations::DeltaComputer,
+};
+
+use super::ResolvedValueRecord;
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct SinglePosRule {
+    pub glyph: GlyphId16,
+    record: ResolvedValueRecord,
+}
+
+impl PrintNames for SinglePosRule {
+    fn fmt_names(&self, f: &mut std::fmt::Formatter<'_>, names: &NameMap) -> std::fmt::Result {
+        let name = names.get(self.glyph);
+        if self.record.is_zero() {
+            write!(f, "{name} 0")
+        } else {
+            write!(f, "{name} {}", self.record)
+        }
+    }
+}
+
+impl DiffKey for SinglePosRule {
+    fn fmt_key(&self, f: &mut std::fmt::Formatter<'_>, names: &NameMap) -> std::fmt::Result {
+        f.write_str(names.get(self.glyph).as_str())
+    }
+}
+
+pub(super) fn get_single_rules(
+    subtables: &[SinglePos],
+    delta_computer: Option<&DeltaComputer>,
+) -> Result<Vec<SinglePosRule>, ReadError> {
+    // the first subtable to cover a glyph wins
+    let mut seen = HashSet::new();
+    let mut result = Vec::new();
+    for sub in subtables {
+        match sub {
+            SinglePos::Format1(sub) => {
+                let record = ResolvedValueRecord::new(
+                    sub.value_record(),
+                    sub.offset_data(),
+                    delta_computer,
+                )?;
+                for glyph in sub.coverage()?.iter() {
+                    if seen.insert(glyph) {
+                        result.push(SinglePosRule {
+                            glyph,
+                            record: record.clone(),
+                        });
+                    }
+                }
+            }
+            SinglePos::Format2(sub) => {
+                for (glyph, record) in sub.coverage()?.iter().zip(sub.value_records().iter()) {
+                    let record =
+                        ResolvedValueRecord::new(record?, sub.offset_data(), delta_computer)?;
+                    if seen.insert(glyph) {
+                        result.push(SinglePosRule { glyph, record });
+                    }
+                }
+            }
+        }
+    }
+    Ok(result)
+}
@@ -1,16 +1,564 @@
-use std::io;
+use std::{collections::HashSet, io};
 
-use write_fonts::read::tables::{gdef::Gdef, gsub::Gsub};
+use write_fonts::{
+    read::{
+        tables::{
+            gdef::Gdef,
+            gsub::{
+                AlternateSubstFormat1, Gsub, LigatureSubstFormat1, MultipleSubstFormat1,
+                SingleSubst, SubstitutionLookupList, SubstitutionSubtables,
+            },
+        },
+        ReadError,
+    },
+    tables::layout::LookupFlag,
+    types::GlyphId16,
+};
 
-use crate::{error::Error, glyph_names::NameMap};
+use crate::{
+    common::{self, FlagGlyphs, Lookup, PrintNames, SingleRule},
+    contextual,
+    error::Error,
+    glyph_names::NameMap,
+};
+
+mod reverse;
+
+use self::reverse::ReverseChainRule;
+
+type ContextualRule = contextual::ContextualRule<NestedRules>;
 
 /// Print normalized GSUB layout rules for the provided font
 pub fn print(
-    _f: &mut dyn io::Write,
-    _table: &Gsub,
-    _gdef: Option<&Gdef>,
-    _names: &NameMap,
+    f: &mut dyn io::Write,
+    table: &Gsub,
+    gdef: Option<&Gdef>,
+    names: &NameMap,
 ) -> Result<(), Error> {
-    //TODO: do we *ever* want to support this? GPOS seems like the big one?
+    let flag_glyphs = FlagGlyphs::new(gdef);
+
+    let script_list = table.script_list().unwrap();
+    let feature_list = table.feature_list().unwrap();
+    let lang_systems = common::get_lang_systems(&script_list, &feature_list);
+    let num_glyphs = names.0.len() as u16;
+    let lookup_rules = get_lookup_rules(&table.lookup_list().unwrap(), num_glyphs)?;
+
+    for sys in &lang_systems {
+        writeln!(f,)?;
+        sys.fmt_header(f)?;
+
+        let single = sorted_rules(&lookup_rules.single, &sys.lookups);
+        let multiple = sorted_rules(&lookup_rules.multiple, &sys.lookups);
+        let alternate = sorted_rules(&lookup_rules.alternate, &sys.lookups);
+        let ligature = sorted_rules(&lookup_rules.ligature, &sys.lookups);
+        // the first matching rule is applied, so these keep their order
+        let contextual = ordered_rules(&lookup_rules.contextual, &sys.lookups);
+        let reverse = ordered_rules(&lookup_rules.reverse, &sys.lookups);
+
+        common::print_rules(f, "SingleSub", &single, names, &flag_glyphs)?;
+        common::print_rules(f, "MultipleSub", &multiple, names, &flag_glyphs)?;
+        common::print_rules(f, "AlternateSub", &alternate, names, &flag_glyphs)?;
+        common::print_rules(f, "LigatureSub", &ligature, names, &flag_glyphs)?;
+        common::print_rules(f, "ContextualSub", &contextual, names, &flag_glyphs)?;
+        common::print_rules(f, "ReverseChainSub", &reverse, names, &flag_glyphs)?;
+    }
     Ok(())
 }
+
+/// Replace one glyph with another
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct SingleSubRule {
+    target: GlyphId16,
+    replacement: GlyphId16,
+}
+
+/// Replace one glyph with a sequence
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct MultipleSubRule {
+    target: GlyphId16,
+    replacement: Vec<GlyphId16>,
+}
+
+/// Replace one glyph with one of a set of alternates
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct AlternateSubRule {
+    target: GlyphId16,
+    alternates: Vec<GlyphId16>,
+}
+
+/// Replace a sequence of glyphs with one
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct LigatureSubRule {
+    components: Vec<GlyphId16>,
+    ligature: GlyphId16,
+}
+
+/// The rules of all the lookups in a GSUB table, by type
+#[derive(Clone, Debug, Default)]
+struct LookupRules {
+    single: Vec<Lookup<SingleSubRule>>,
+    multiple: Vec<Lookup<MultipleSubRule>>,
+    alternate: Vec<Lookup<AlternateSubRule>>,
+    ligature: Vec<Lookup<LigatureSubRule>>,
+    contextual: Vec<Lookup<ContextualRule>>,
+    reverse: Vec<Lookup<ReverseChainRule>>,
+}
+
+/// What a nested lookup does, as seen from a contextual rule
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum NestedRules {
+    Single(Vec<SingleSubRule>),
+    Multiple(Vec<MultipleSubRule>),
+    Alternate(Vec<AlternateSubRule>),
+    Ligature(Vec<LigatureSubRule>),
+    /// Contextual lookups called from contextual lookups are not expanded further
+    Contextual,
+    /// The referenced lookup does not exist
+    Missing(u16),
+}
+
+fn sorted_rules<'a, T: Clone + Ord>(
+    lookups: &'a [Lookup<T>],
+    ids: &[u16],
+) -> Vec<SingleRule<'a, T>> {
+    let mut result = ordered_rules(lookups, ids);
+    result.sort();
+    result
+}
+
+fn ordered_rules<'a, T: Clone>(lookups: &'a [Lookup<T>], ids: &[u16]) -> Vec<SingleRule<'a, T>> {
+    lookups
+        .iter()
+        .filter(|lookup| ids.contains(&lookup.lookup_id))
+        .flat_map(Lookup::iter)
+        .collect()
+}
+
+fn get_lookup_rules(
+    lookups: &SubstitutionLookupList,
+    num_glyphs: u16,
+) -> Result<LookupRules, Error> {
+    let mut result = LookupRules::default();
+    // contextual rules are resolved once we know what every lookup does
+    let mut raw_contextual = Vec::new();
+    for (id, lookup) in lookups.lookups().iter().enumerate() {
+        let lookup = lookup?;
+        let flag = lookup.lookup_flag();
+        let mark_filter_id = flag
+            .contains(LookupFlag::USE_MARK_FILTERING_SET)
+            .then_some(lookup.mark_filtering_set())
+            .flatten();
+        match lookup.subtables()? {
+            SubstitutionSubtables::Single(subs) => {
+                let subs = subs.iter().collect::<Result<Vec<_>, _>>()?;
+                let rules = get_single_rules(&subs)?;
+                result
+                    .single
+                    .push(Lookup::new(id, rules, flag, mark_filter_id));
+            }
+            SubstitutionSubtables::Multiple(subs) => {
+                let subs = subs.iter().collect::<Result<Vec<_>, _>>()?;
+                let rules = get_multiple_rules(&subs)?;
+                result
+                    .multiple
+                    .push(Lookup::new(id, rules, flag, mark_filter_id));
+            }
+            SubstitutionSubtables::Alternate(subs) => {
+                let subs = subs.iter().collect::<Result<Vec<_>, _>>()?;
+                let rules = get_alternate_rules(&subs)?;
+                result
+                    .alternate
+                    .push(Lookup::new(id, rules, flag, mark_filter_id));
+            }
+            SubstitutionSubtables::Ligature(subs) => {
+                let subs = subs.iter().collect::<Result<Vec<_>, _>>()?;
+                let rules = get_ligature_rules(&subs)?;
+                result
+                    .ligature
+                    .push(Lookup::new(id, rules, flag, mark_filter_id));
+            }
+            SubstitutionSubtables::Contextual(subs) => {
+                let subs = subs.iter().collect::<Result<Vec<_>, _>>()?;
+                let rules = contextual::get_context_rules(&subs, num_glyphs)?;
+                raw_contextual.push((id, rules, flag, mark_filter_id));
+            }
+            SubstitutionSubtables::ChainContextual(subs) => {
+                let subs = subs.iter().collect::<Result<Vec<_>, _>>()?;
+                let rules = contextual::get_chain_context_rules(&subs, num_glyphs)?;
+                raw_contextual.push((id, rules, flag, mark_filter_id));
+            }
+            SubstitutionSubtables::Reverse(subs) => {
+                let subs = subs.iter().collect::<Result<Vec<_>, _>>()?;
+                let rules = reverse::get_reverse_chain_rules(&subs)?;
+                result
+                    .reverse
+                    .push(Lookup::new(id, rules, flag, mark_filter_id));
+            }
+        }
+    }
+
+    let contextual_ids = raw_contextual
+        .iter()
+        .map(|(id, ..)| *id as u16)
+        .collect::<HashSet<_>>();
+    for (id, rules, flag, mark_filter_id) in raw_contextual {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                rule.resolve(|lookup_id, glyphs| {
+                    if contextual_ids.contains(&lookup_id) {
+                        NestedRules::Contextual
+                    } else {
+                        result.nested_rules(lookup_id, glyphs)
+                    }
+                })
+            })
+            .collect();
+        result
+            .contextual
+            .push(Lookup::new(id, rules, flag, mark_filter_id));
+    }
+    Ok(result)
+}
+
+impl LookupRules {
+    /// The rules of lookup `id` that could apply to any glyph in `glyphs`
+    ///
+    /// Contextual lookups are checked by the caller, since they are resolved last.
+    fn nested_rules(&self, id: u16, glyphs: &HashSet<GlyphId16>) -> NestedRules {
+        fn find<T: Clone>(
+            lookups: &[Lookup<T>],
+            id: u16,
+            applies: impl Fn(&T) -> bool,
+        ) -> Option<Vec<T>> {
+            lookups
+                .iter()
+                .find(|lookup| lookup.lookup_id == id)
+                .map(|lookup| {
+                    lookup
+                        .iter()
+                        .filter(|rule| applies(rule.rule()))
+                        .map(|rule| rule.rule().clone())
+                        .collect()
+                })
+        }
+        if let Some(rules) = find(&self.single, id, |r| glyphs.contains(&r.target)) {
+            NestedRules::Single(rules)
+        } else if let Some(rules) = find(&self.multiple, id, |r| glyphs.contains(&r.target)) {
+            NestedRules::Multiple(rules)
+        } else if let Some(rules) = find(&self.alternate, id, |r| glyphs.contains(&r.target)) {
+            NestedRules::Alternate(rules)
+        } else if let Some(rules) = find(&self.ligature, id, |r| glyphs.contains(&r.components[0]))
+        {
+            NestedRules::Ligature(rules)
+        } else if self.reverse.iter().any(|lookup| lookup.lookup_id == id) {
+            NestedRules::Contextual
+        } else {
+            NestedRules::Missing(id)
+        }
+    }
+}
+
+// In all of these, the first subtable to cover a glyph wins, so we ignore
+// any later rules for the same input.
+
+fn get_single_rules(subtables: &[SingleSubst]) -> Result<Vec<SingleSubRule>, ReadError> {
+    let mut seen = HashSet::new();
+    let mut result = Vec::new();
+    for sub in subtables {
+        let mut push = |target: GlyphId16, replacement: GlyphId16| {
+            if seen.insert(target) {
+                result.push(SingleSubRule {
+                    target,
+                    replacement,
+                });
+            }
+        };
+        match sub {
+            SingleSubst::Format1(sub) => {
+                let delta = sub.delta_glyph_id() as i32;
+                for target in sub.coverage()?.iter() {
+                    // deltas wrap around, modulo 65536
+                    let replacement = (target.to_u16() as i32 + delta).rem_euclid(0x10000);
+                    push(target, GlyphId16::new(replacement as u16));
+                }
+            }
+            SingleSubst::Format2(sub) => {
+                for (target, replacement) in sub
+                    .coverage()?
+                    .iter()
+                    .zip(sub.substitute_glyph_ids().iter())
+                {
+                    push(target, replacement.get());
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn get_multiple_rules(
+    subtables: &[MultipleSubstFormat1],
+) -> Result<Vec<MultipleSubRule>, ReadError> {
+    let mut seen = HashSet::new();
+    let mut result = Vec::new();
+    for sub in subtables {
+        for (target, sequence) in sub.coverage()?.iter().zip(sub.sequences().iter()) {
+            let sequence = sequence?;
+            if seen.insert(target) {
+                result.push(MultipleSubRule {
+                    target,
+                    replacement: sequence
+                        .substitute_glyph_ids()
+                        .iter()
+                        .map(|gid| gid.get())
+                        .collect(),
+                });
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn get_alternate_rules(
+    subtables: &[AlternateSubstFormat1],
+) -> Result<Vec<AlternateSubRule>, ReadError> {
+    let mut seen = HashSet::new();
+    let mut result = Vec::new();
+    for sub in subtables {
+        for (target, alt_set) in sub.coverage()?.iter().zip(sub.alternate_sets().iter()) {
+            let alt_set = alt_set?;
+            if seen.insert(target) {
+                result.push(AlternateSubRule {
+                    target,
+                    alternates: alt_set
+                        .alternate_glyph_ids()
+                        .iter()
+                        .map(|gid| gid.get())
+                        .collect(),
+                });
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn get_ligature_rules(
+    subtables: &[LigatureSubstFormat1],
+) -> Result<Vec<LigatureSubRule>, ReadError> {
+    let mut seen = HashSet::new();
+    let mut result = Vec::new();
+    for sub in subtables {
+        for (first, lig_set) in sub.coverage()?.iter().zip(sub.ligature_sets().iter()) {
+            for ligature in lig_set?.ligatures().iter() {
+                let ligature = ligature?;
+                let components = std::iter::once(first)
+                    .chain(ligature.component_glyph_ids().iter().map(|gid| gid.get()))
+                    .collect::<Vec<_>>();
+                // within a set the first matching ligature wins, too
+                if seen.insert(components.clone()) {
+                    result.push(LigatureSubRule {
+                        components,
+                        ligature: ligature.ligature_glyph(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn write_glyphs(
+    f: &mut std::fmt::Formatter<'_>,
+    glyphs: &[GlyphId16],
+    names: &NameMap,
+) -> std::fmt::Result {
+    for (i, gid) in glyphs.iter().enumerate() {
+        if i > 0 {
+            f.write_str(" ")?;
+        }
+        f.write_str(names.get(*gid).as_str())?;
+    }
+    Ok(())
+}
+
+impl PrintNames for SingleSubRule {
+    fn fmt_names(&self, f: &mut std::fmt::Formatter<'_>, names: &NameMap) -> std::fmt::Result {
+        write!(
+            f,
+            "{} -> {}",
+            names.get(self.target),
+            names.get(self.replacement)
+        )
+    }
+}
+
+impl PrintNames for MultipleSubRule {
+    fn fmt_names(&self, f: &mut std::fmt::Formatter<'_>, names: &NameMap) -> std::fmt::Result {
+        write!(f, "{} -> ", names.get(self.target))?;
+        write_glyphs(f, &self.replacement, names)
+    }
+}
+
+impl PrintNames for AlternateSubRule {
+    fn fmt_names(&self, f: &mut std::fmt::Formatter<'_>, names: &NameMap) -> std::fmt::Result {
+        write!(f, "{} from [", names.get(self.target))?;
+        write_glyphs(f, &self.alternates, names)?;
+        f.write_str("]")
+    }
+}
+
+impl PrintNames for LigatureSubRule {
+    fn fmt_names(&self, f: &mut std::fmt::Formatter<'_>, names: &NameMap) -> std::fmt::Result {
+        write_glyphs(f, &self.components, names)?;
+        write!(f, " -> {}", names.get(self.ligature))
+    }
+}
+
+impl PrintNames for NestedRules {
+    fn fmt_names(&self, f: &mut std::fmt::Formatter<'_>, names: &NameMap) -> std::fmt::Result {
+        match self {
+            NestedRules::Single(rules) => common::fmt_rule_list(f, rules, names),
+            NestedRules::Multiple(rules) => common::fmt_rule_list(f, rules, names),
+            NestedRules::Alternate(rules) => common::fmt_rule_list(f, rules, names),
+            NestedRules::Ligature(rules) => common::fmt_rule_list(f, rules, names),
+            NestedRules::Contextual => f.write_str("<contextual>"),
+            NestedRules::Missing(id) => write!(f, "<missing lookup {id}>"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use write_fonts::{
+        read::FontRead,
+        tables::{gsub as wgsub, layout as wlayout},
+    };
+
+    use super::*;
+    use crate::common::GlyphSet;
+
+    fn gids(raw: &[u16]) -> Vec<GlyphId16> {
+        raw.iter().copied().map(GlyphId16::new).collect()
+    }
+
+    fn single_subst(pairs: &[(u16, u16)]) -> wgsub::SingleSubst {
+        let (targets, replacements): (Vec<_>, Vec<_>) = pairs
+            .iter()
+            .map(|(a, b)| (GlyphId16::new(*a), GlyphId16::new(*b)))
+            .unzip();
+        wgsub::SingleSubst::format_2(targets.into_iter().collect(), replacements)
+    }
+
+    fn cov(gids: &[u16]) -> wlayout::CoverageTable {
+        gids.iter().copied().map(GlyphId16::new).collect()
+    }
+
+    fn read_lookups(lookups: Vec<wgsub::SubstitutionLookup>) -> LookupRules {
+        let lookup_list = wlayout::LookupList::new(lookups);
+        let data = write_fonts::dump_table(&lookup_list).unwrap();
+        let lookup_list = SubstitutionLookupList::read(data.as_slice().into()).unwrap();
+        get_lookup_rules(&lookup_list, 20).unwrap()
+    }
+
+    #[test]
+    fn first_subtable_wins() {
+        let lookup = wgsub::SubstitutionLookup::Single(wlayout::Lookup::new(
+            LookupFlag::empty(),
+            vec![
+                single_subst(&[(1, 10), (2, 11)]),
+                // 2 is already covered by the first subtable
+                single_subst(&[(2, 12), (3, 13)]),
+            ],
+        ));
+        let rules = read_lookups(vec![lookup]);
+        let rules = sorted_rules(&rules.single, &[0])
+            .into_iter()
+            .map(|rule| {
+                (
+                    rule.rule().target.to_u16(),
+                    rule.rule().replacement.to_u16(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(vec![(1, 10), (2, 11), (3, 13)], rules);
+    }
+
+    #[test]
+    fn ligature_components() {
+        let lig_set = |ligs: Vec<wgsub::Ligature>| wgsub::LigatureSet::new(ligs);
+        let sub = wgsub::LigatureSubstFormat1::new(
+            gids(&[1]).into_iter().collect(),
+            vec![lig_set(vec![
+                wgsub::Ligature::new(GlyphId16::new(15), gids(&[2, 3])),
+                wgsub::Ligature::new(GlyphId16::new(14), gids(&[2])),
+            ])],
+        );
+        let lookup = wgsub::SubstitutionLookup::Ligature(wlayout::Lookup::new(
+            LookupFlag::empty(),
+            vec![sub],
+        ));
+        let rules = read_lookups(vec![lookup]);
+        let rules = sorted_rules(&rules.ligature, &[0])
+            .into_iter()
+            .map(|rule| rule.rule().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                LigatureSubRule {
+                    components: gids(&[1, 2]),
+                    ligature: GlyphId16::new(14)
+                },
+                LigatureSubRule {
+                    components: gids(&[1, 2, 3]),
+                    ligature: GlyphId16::new(15)
+                },
+            ],
+            rules
+        );
+    }
+
+    #[test]
+    fn chain_context_inlines_nested_lookup() {
+        // sub a [b c]' d by b.sc/c.sc, where only b and e have a mapping in the nested lookup
+        let nested = wgsub::SubstitutionLookup::Single(wlayout::Lookup::new(
+            Default::default(),
+            vec![wgsub::SingleSubst::format_2(
+                cov(&[2, 5]),
+                vec![GlyphId16::new(12), GlyphId16::new(15)],
+            )],
+        ));
+        let chain =
+            wgsub::SubstitutionChainContext::from(wlayout::ChainedSequenceContext::format_3(
+                vec![cov(&[1])],
+                vec![cov(&[2, 3])],
+                vec![cov(&[4])],
+                vec![wlayout::SequenceLookupRecord::new(0, 1)],
+            ));
+        let chain = wgsub::SubstitutionLookup::ChainContextual(wlayout::Lookup::new(
+            Default::default(),
+            vec![chain],
+        ));
+        let rules = read_lookups(vec![chain, nested]);
+
+        let rules = ordered_rules(&rules.contextual, &[0])
+            .into_iter()
+            .map(|rule| rule.rule().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![ContextualRule {
+                backtrack: vec![GlyphSet::Single(GlyphId16::new(1))],
+                input: vec![[2, 3].into_iter().map(GlyphId16::new).collect()],
+                lookahead: vec![GlyphSet::Single(GlyphId16::new(4))],
+                actions: vec![(
+                    0,
+                    NestedRules::Single(vec![SingleSubRule {
+                        target: GlyphId16::new(2),
+                        replacement: GlyphId16::new(12),
+                    }])
+                )],
+            }],
+            rules
+        );
+    }
+}
@@ -0,0 +1,71 @@
+//! Reverse chaining single substitutions
+
+use write_fonts::{
+    read::{tables::gsub::ReverseChainSingleSubstFormat1, ReadError},
+    types::GlyphId16,
+};
+
+use crate::{
+    common::{GlyphSet, PrintNames},
+    contextual::{coverage_set, fmt_context, glyph_set},
+    glyph_names::NameMap,
+};
+
+/// A rule from a reverse chaining single substitution lookup
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ReverseChainRule {
+    /// In reading order, unlike in the font
+    backtrack: Vec<GlyphSet>,
+    lookahead: Vec<GlyphSet>,
+    /// (target, replacement)
+    substitutions: Vec<(GlyphId16, GlyphId16)>,
+}
+
+pub(super) fn get_reverse_chain_rules(
+    subtables: &[ReverseChainSingleSubstFormat1],
+) -> Result<Vec<ReverseChainRule>, ReadError> {
+    subtables
+        .iter()
+        .map(|sub| {
+            let mut backtrack = sub
+                .backtrack_coverages()
+                .iter()
+                .map(coverage_set)
+                .collect::<Result<Vec<_>, _>>()?;
+            backtrack.reverse();
+            let lookahead = sub
+                .lookahead_coverages()
+                .iter()
+                .map(coverage_set)
+                .collect::<Result<_, _>>()?;
+            let substitutions = sub
+                .coverage()?
+                .iter()
+                .zip(sub.substitute_glyph_ids().iter().map(|gid| gid.get()))
+                .collect();
+            Ok(ReverseChainRule {
+                backtrack,
+                lookahead,
+                substitutions,
+            })
+        })
+        .collect()
+}
+
+impl PrintNames for ReverseChainRule {
+    fn fmt_names(&self, f: &mut std::fmt::Formatter<'_>, names: &NameMap) -> std::fmt::Result {
+        let input = glyph_set(self.substitutions.iter().map(|(target, _)| *target));
+        fmt_context(
+            f,
+            &self.backtrack,
+            std::slice::from_ref(&input),
+            &self.lookahead,
+            names,
+        )?;
+        for (i, (target, replacement)) in self.substitutions.iter().enumerate() {
+            f.write_str(if i == 0 { "\n  " } else { ", " })?;
+            write!(f, "{} -> {}", names.get(*target), names.get(*replacement))?;
+        }
+        Ok(())
+    }
+}
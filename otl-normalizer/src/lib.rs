@@ -1,9 +1,12 @@
 //! Generating a normalized text representation for OpenType layout tables
 //!
-//! This currently supports a subset of GPOS (kerning and marks)
+//! This currently supports GSUB, GDEF, and the single, pair, mark, cursive
+//! and contextual lookups of GPOS.
 
 pub mod args;
 mod common;
+mod contextual;
+mod diff;
 mod error;
 mod gdef;
 mod glyph_names;
@@ -11,6 +14,7 @@ mod gpos;
 mod gsub;
 mod variations;
 
+pub use diff::{diff_gpos, FeatureDiff, LayoutDiff, RuleChange};
 pub use error::Error;
 pub use glyph_names::NameMap;
 
@@ -37,7 +37,7 @@ fn main() -> Result<(), Error> {
     let gdef = font.gdef().ok();
 
     if matches!(to_print, args::Table::All | args::Table::Gdef) {
-        if let Some(gdef) = gdef.as_ref().filter(|gdef| gdef.lig_caret_list().is_some()) {
+        if let Some(gdef) = gdef.as_ref() {
             writeln!(&mut write_target, "# GDEF #")?;
             otl_normalizer::print_gdef(&mut write_target, gdef, &name_map)?;
         }
@@ -51,10 +51,9 @@ fn main() -> Result<(), Error> {
     }
 
     if matches!(to_print, args::Table::All | args::Table::Gsub) {
-        if let Ok(_gsub) = font.gsub() {
-            // we don't currently handle GSUB, and it's not clear we want to?
-            //writeln!(&mut write_target, "# GSUB #")?;
-            //otl_normalizer::print_gsub(&mut write_target, &gsub, gdef.as_ref(), &name_map)?;
+        if let Ok(gsub) = font.gsub() {
+            writeln!(&mut write_target, "# GSUB #")?;
+            otl_normalizer::print_gsub(&mut write_target, &gsub, gdef.as_ref(), &name_map)?;
         }
     }
     write_target.flush().unwrap();
@@ -69,6 +69,7 @@ FLAGS = flags.FLAGS
 # used instead of a tag for the normalized mark/kern output
 MARK_KERN_NAME = "(mark/kern)"
 LIG_CARET_NAME = "ligcaret"
+SUBST_NAME = "subst"
 # maximum chars of stderr to include when reporting errors; prevents
 # too much bloat when run in CI
 MAX_ERR_LEN = 1000
@@ -183,6 +184,8 @@ def run_normalizer(normalizer_bin: Path, font_file: Path, table: str):
         out_path = font_file.with_suffix(".markkern.txt")
     elif table == "gdef":
         out_path = font_file.with_suffix(f".{LIG_CARET_NAME}.txt")
+    elif table == "gsub":
+        out_path = font_file.with_suffix(f".{SUBST_NAME}.txt")
     else:
         raise ValueError(f"unknown table for normalizer: '{table}'")
 
@@ -250,6 +253,7 @@ def build_fontc(source: Path, fontc_bin: Path, build_dir: Path):
         out_file.name,
         source,
         "--emit-debug",
+        "--no-layout-optimization",
     ]
     if not FLAGS.production_names:
         cmd.append("--no-production-names")
@@ -1005,6 +1009,8 @@ def generate_output(
     fontmake_gpos = run_normalizer(otl_norm_bin, fontmake_ttf, "gpos")
     fontc_gdef = run_normalizer(otl_norm_bin, fontc_ttf, "gdef")
     fontmake_gdef = run_normalizer(otl_norm_bin, fontmake_ttf, "gdef")
+    fontc_gsub = run_normalizer(otl_norm_bin, fontc_ttf, "gsub")
+    fontmake_gsub = run_normalizer(otl_norm_bin, fontmake_ttf, "gsub")
 
     fontc = etree.parse(fontc_ttx)
     fontmake = etree.parse(fontmake_ttx)
@@ -1020,6 +1026,10 @@ def generate_output(
         fontc[LIG_CARET_NAME] = fontc_gdef
     if len(fontmake_gdef):
         fontmake[LIG_CARET_NAME] = fontmake_gdef
+    if len(fontc_gsub):
+        fontc[SUBST_NAME] = fontc_gsub
+    if len(fontmake_gsub):
+        fontmake[SUBST_NAME] = fontmake_gsub
     result = {"fontc": fontc, "fontmake": fontmake}
     if len(size_diffs) > 0:
         result["sizes"] = size_diffs
@@ -1188,6 +1198,7 @@ def delete_things_we_must_rebuild(rebuild: str, fontmake_ttf: Path, fontc_ttf: P
                 ttf_path.with_suffix(".ttx"),
                 ttf_path.with_suffix(".markkern.txt"),
                 ttf_path.with_suffix(".ligcaret.txt"),
+                ttf_path.with_suffix(".subst.txt"),
             ]:
                 if path.exists():
                     os.remove(path)
@@ -14,13 +14,15 @@ use fontdrasil::{
 use fontir::{
     error::{BadSource, BadSourceKind, Error},
     ir::{
-        AnchorBuilder, Condition, ConditionSet, FeaturesSource, GdefCategories, GlobalMetric,
-        GlobalMetrics, GlyphOrder, KernGroup, KernSide, KerningGroups, KerningInstance,
-        MetaTableValues, NameBuilder, NameKey, NamedInstance, Panose, PostscriptNames, Rule,
-        StaticMetadata, Substitution, VariableFeature, DEFAULT_VENDOR_ID,
+        AnchorBuilder, AxisMapping, BaseTableValues, CodepointRange, Condition, ConditionSet,
+        FeatureWriter, FeatureWriterMode, FeatureWriters, FeaturesSource, GdefCategories,
+        GlobalMetric, GlobalMetrics, GlyphOrder, KernGroup, KernSide, KerningGroups,
+        KerningInstance, MathValues, MetaTableValues, NameBuilder, NameKey, NamedInstance, Panose,
+        PostscriptNames, Rule, ScriptBaselines, StaticMetadata, Substitution, VariableFeature,
+        DEFAULT_VENDOR_ID,
     },
     orchestration::{Context, Flags, IrWork, WorkId},
-    source::Source,
+    source::{AxisLimit, Source},
 };
 use log::{debug, log_enabled, trace, warn, Level};
 use norad::{
@@ -28,6 +30,11 @@ use norad::{
     fontinfo::StyleMapStyle,
 };
 use plist::{Dictionary, Value};
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+use smol_str::SmolStr;
 use write_fonts::{
     read::tables::gasp::GaspRangeBehavior,
     tables::{gasp::GaspRange, gdef::GlyphClassDef, head, os2::SelectionFlags},
@@ -47,6 +54,8 @@ pub struct DesignSpaceIrSource {
     designspace_dir: Arc<PathBuf>,
     glyphs: Arc<HashMap<GlyphName, HashMap<PathBuf, Vec<DesignLocation>>>>,
     fea_files: Arc<Vec<PathBuf>>,
+    /// The avar2 mappings of the designspace, as (input, output) pairs
+    axis_mappings: Arc<Vec<(DesignLocation, DesignLocation)>>,
 }
 
 fn glif_files(
@@ -109,7 +118,10 @@ pub(crate) fn layer_dir<'a>(
 
     name_to_path
         .get(&glyph_name)
-        .ok_or_else(|| Error::NoSuchLayer(source.filename.clone()))
+        .ok_or_else(|| Error::NoSuchLayer {
+            ufo: source.filename.clone(),
+            layer: glyph_name.to_string(),
+        })
 }
 
 impl DesignSpaceIrSource {
@@ -261,12 +273,22 @@ impl Source for DesignSpaceIrSource {
             })
             .collect();
 
+        let axis_mappings = if designspace_or_ufo_file.extension() == Some("designspace".as_ref()) {
+            let xml = std::fs::read(designspace_or_ufo_file)
+                .map_err(|e| BadSource::new(designspace_or_ufo_file, BadSourceKind::Io(e)))?;
+            parse_axis_mappings(&xml, &axis_tags_by_name)
+                .map_err(|e| BadSource::custom(designspace_or_ufo_file, e))?
+        } else {
+            Vec::new()
+        };
+
         Ok(DesignSpaceIrSource {
             designspace_or_ufo: Arc::new(designspace_or_ufo_file.to_path_buf()),
             designspace: Arc::new(designspace),
             designspace_dir: Arc::new(designspace_dir),
             glyphs: Arc::new(glyphs),
             fea_files: Arc::new(fea_files),
+            axis_mappings: Arc::new(axis_mappings),
         })
     }
 
@@ -276,6 +298,7 @@ impl Source for DesignSpaceIrSource {
             designspace_dir: self.designspace_dir.clone(),
             designspace: self.designspace.clone(),
             glyph_names: Arc::new(self.glyphs.keys().cloned().collect()),
+            axis_mappings: self.axis_mappings.clone(),
         }))
     }
 
@@ -353,6 +376,114 @@ impl Source for DesignSpaceIrSource {
     ) -> Result<Box<fontir::orchestration::IrWork>, fontir::error::Error> {
         Ok(Box::new(PaintGraphWork {}))
     }
+
+    fn create_bitmap_strikes_work(
+        &self,
+    ) -> Result<Box<fontir::orchestration::IrWork>, fontir::error::Error> {
+        Ok(Box::new(BitmapStrikesWork {}))
+    }
+
+    fn create_svg_documents_work(
+        &self,
+    ) -> Result<Box<fontir::orchestration::IrWork>, fontir::error::Error> {
+        Ok(Box::new(SvgDocumentsWork {}))
+    }
+
+    fn limit_axes(&mut self, limits: &[AxisLimit]) -> Result<(), Error> {
+        if limits.is_empty() {
+            return Ok(());
+        }
+        let axes = to_ir_axes(&self.designspace.axes)?;
+        AxisLimit::check(limits, &axes)?;
+        let tags_by_name: HashMap<_, _> = axes.iter().map(|a| (a.name.as_str(), a.tag)).collect();
+        let within = |location: &[designspace::Dimension]| {
+            let location = to_design_location(&tags_by_name, location).to_user(&axes);
+            AxisLimit::all_contain(limits, &location)
+        };
+
+        let designspace = Arc::make_mut(&mut self.designspace);
+        let (sources, dropped): (Vec<_>, Vec<_>) = std::mem::take(&mut designspace.sources)
+            .into_iter()
+            .partition(|s| within(&s.location));
+        designspace.sources = sources;
+        designspace.instances.retain(|i| within(&i.location));
+
+        // shrink each limited axis to the masters that remain, keeping the map
+        // defined at the new extremes
+        for limit in limits {
+            let axis = axes.get(&limit.tag).unwrap();
+            let (min, max) = designspace
+                .sources
+                .iter()
+                .filter_map(|s| {
+                    to_design_location(&tags_by_name, &s.location)
+                        .get(limit.tag)
+                        .map(|c| c.to_user(&axis.converter))
+                })
+                .fold((axis.default, axis.default), |(min, max), value| {
+                    (min.min(value), max.max(value))
+                });
+            let ds_axis = designspace
+                .axes
+                .iter_mut()
+                .find(|a| a.name == axis.name)
+                .unwrap();
+            ds_axis.minimum = Some(min.to_f64() as f32);
+            ds_axis.maximum = Some(max.to_f64() as f32);
+            if let Some(map) = ds_axis.map.as_mut() {
+                map.retain(|m| min.to_f64() <= m.input as f64 && m.input as f64 <= max.to_f64());
+                for value in [min, max] {
+                    if !map.iter().any(|m| m.input as f64 == value.to_f64()) {
+                        map.push(designspace::AxisMapping {
+                            input: value.to_f64() as f32,
+                            output: value.to_design(&axis.converter).to_f64() as f32,
+                        });
+                    }
+                }
+                map.sort_by(|a, b| a.input.total_cmp(&b.input));
+            }
+        }
+
+        let dropped: HashSet<_> = dropped
+            .iter()
+            .map(|s| to_design_location(&tags_by_name, &s.location))
+            .collect();
+        for files in Arc::make_mut(&mut self.glyphs).values_mut() {
+            for locations in files.values_mut() {
+                locations.retain(|loc| !dropped.contains(loc));
+            }
+            files.retain(|_, locations| !locations.is_empty());
+        }
+        let fea_files: HashSet<_> = designspace
+            .sources
+            .iter()
+            .map(|s| self.designspace_dir.join(&s.filename).join("features.fea"))
+            .collect();
+        Arc::make_mut(&mut self.fea_files).retain(|f| fea_files.contains(f));
+
+        let limited = to_ir_axes(&designspace.axes)?;
+        AxisLimit::check_reached(limits, &limited)?;
+
+        // avar2 mappings must stay within the limited axes
+        let within_axes = |location: &DesignLocation| {
+            location.iter().all(|(tag, coord)| {
+                limited.get(tag).is_none_or(|axis| {
+                    let value = coord.to_user(&axis.converter);
+                    axis.min <= value && value <= axis.max
+                })
+            })
+        };
+        let axis_mappings = Arc::make_mut(&mut self.axis_mappings);
+        let before = axis_mappings.len();
+        axis_mappings.retain(|(input, output)| within_axes(input) && within_axes(output));
+        if axis_mappings.len() < before {
+            warn!(
+                "Dropped {} avar2 mappings that reach outside the axis limits",
+                before - axis_mappings.len()
+            );
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -361,6 +492,7 @@ struct StaticMetadataWork {
     designspace_dir: Arc<PathBuf>,
     designspace: Arc<DesignSpaceDocument>,
     glyph_names: Arc<HashSet<GlyphName>>,
+    axis_mappings: Arc<Vec<(DesignLocation, DesignLocation)>>,
 }
 
 #[derive(Debug)]
@@ -397,6 +529,12 @@ struct ColorPaletteWork {}
 #[derive(Debug)]
 struct PaintGraphWork {}
 
+#[derive(Debug)]
+struct BitmapStrikesWork {}
+
+#[derive(Debug)]
+struct SvgDocumentsWork {}
+
 fn default_master(designspace: &DesignSpaceDocument) -> Option<(usize, &designspace::Source)> {
     let ds_axes = to_ir_axes(&designspace.axes).ok()?;
     let tags_by_name: HashMap<_, _> = ds_axes.iter().map(|a| (a.name.as_str(), a.tag)).collect();
@@ -543,6 +681,112 @@ fn postscript_names(lib_plist: &plist::Dictionary) -> Result<Option<PostscriptNa
     Ok(Some(postscript_names))
 }
 
+const FEATURE_WRITERS: &str = "com.github.googlei18n.ufo2ft.featureWriters";
+
+/// Which features to generate, per the ufo2ft featureWriters lib key.
+///
+/// The designspace lib takes precedence over the lib of the default master.
+/// See <https://github.com/googlefonts/ufo2ft/blob/main/Lib/ufo2ft/featureWriters/__init__.py>
+fn feature_writers(
+    designspace_lib: &plist::Dictionary,
+    lib_plist: &plist::Dictionary,
+) -> Result<FeatureWriters, BadSource> {
+    let Some(raw_writers) = designspace_lib
+        .get(FEATURE_WRITERS)
+        .or_else(|| lib_plist.get(FEATURE_WRITERS))
+    else {
+        return Ok(Default::default());
+    };
+    let raw_writers = raw_writers.as_array().ok_or_else(|| {
+        BadSource::custom("lib.plist", format!("{FEATURE_WRITERS} isn't an array"))
+    })?;
+
+    // Listing writers replaces the defaults, an empty list means no writers at all
+    let mut writers = FeatureWriters::none();
+    for raw_writer in raw_writers {
+        let raw_writer = raw_writer.as_dictionary().ok_or_else(|| {
+            BadSource::custom(
+                "lib.plist",
+                format!("{FEATURE_WRITERS} entries must be dictionaries"),
+            )
+        })?;
+        let class = raw_writer
+            .get("class")
+            .and_then(|v| v.as_string())
+            .ok_or_else(|| {
+                BadSource::custom("lib.plist", format!("{FEATURE_WRITERS} entry has no class"))
+            })?;
+        if let Some(module) = raw_writer.get("module").and_then(|v| v.as_string()) {
+            if module != "ufo2ft.featureWriters" {
+                warn!("Custom feature writer {module}.{class} is not supported, ignoring it");
+                continue;
+            }
+        }
+        let writer = feature_writer(raw_writer.get("options"))?;
+        match class {
+            "KernFeatureWriter" => writers.kern = Some(writer),
+            "MarkFeatureWriter" => writers.mark = Some(writer),
+            "CursFeatureWriter" => writers.curs = Some(writer),
+            "CcmpFeatureWriter" => writers.ccmp = Some(writer),
+            "LoclFeatureWriter" => writers.locl = Some(writer),
+            "FracFeatureWriter" => writers.frac = Some(writer),
+            "AutomaticFeatureWriter" => writers.automatic = Some(writer),
+            // GDEF is always built
+            "GdefFeatureWriter" => (),
+            _ => warn!("Unsupported feature writer {class}, ignoring it"),
+        }
+    }
+    Ok(writers)
+}
+
+fn feature_writer(options: Option<&plist::Value>) -> Result<FeatureWriter, BadSource> {
+    let mut writer = FeatureWriter::default();
+    let Some(options) = options else {
+        return Ok(writer);
+    };
+    let options = options.as_dictionary().ok_or_else(|| {
+        BadSource::custom("lib.plist", "feature writer options must be a dictionary")
+    })?;
+    for (key, value) in options {
+        match key.as_str() {
+            "mode" => {
+                writer.mode = match value.as_string() {
+                    Some("skip") => FeatureWriterMode::Skip,
+                    Some("append") => FeatureWriterMode::Append,
+                    _ => {
+                        return Err(BadSource::custom(
+                            "lib.plist",
+                            format!(
+                                "feature writer mode must be 'skip' or 'append', not {value:?}"
+                            ),
+                        ))
+                    }
+                }
+            }
+            "features" => {
+                let features = value
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|v| {
+                        v.as_string()
+                            .and_then(|s| Tag::new_checked(s.as_bytes()).ok())
+                            .ok_or_else(|| {
+                                BadSource::custom(
+                                    "lib.plist",
+                                    format!("feature writer features must be tags, not {v:?}"),
+                                )
+                            })
+                    })
+                    .collect::<Result<_, _>>()?;
+                writer.features = Some(features);
+            }
+            other => warn!("Unsupported feature writer option '{other}', ignoring it"),
+        }
+    }
+    Ok(writer)
+}
+
 pub(crate) fn vertical_origin(
     glyph: &norad::Glyph,
     path: &PathBuf,
@@ -699,6 +943,13 @@ fn names(font_info: &norad::FontInfo) -> HashMap<NameKey, String> {
         .as_deref()
         .unwrap_or(DEFAULT_VENDOR_ID);
     builder.apply_default_fallbacks(vendor);
+    // fsSelection bit 8 says the names are already WWS-conformant
+    builder.apply_wws_names(
+        font_info
+            .open_type_os2_selection
+            .as_ref()
+            .is_some_and(|bits| bits.contains(&8)),
+    );
 
     // Name's that don't get individual fields
     if let Some(name_records) = font_info.open_type_name_records.as_ref() {
@@ -808,6 +1059,7 @@ impl Work<Context, WorkId, Error> for StaticMetadataWork {
                 Err(e) => return Err(e)?,
             };
         let glyph_order = glyph_order(&lib_plist, &self.glyph_names)?;
+        let feature_writers = feature_writers(&self.designspace.lib, &lib_plist)?;
         let glyph_categories = glyph_categories(&lib_plist).map(|categories| GdefCategories {
             categories,
             prefer_gdef_categories_in_fea: true,
@@ -955,6 +1207,30 @@ impl Work<Context, WorkId, Error> for StaticMetadataWork {
             .get("public.openTypeMeta")
             .and_then(parse_meta_table_values);
 
+        if let Some(raw_ranges) = lib_plist.get(CMAP_RANGES_KEY) {
+            static_metadata.misc.cmap_ranges = parse_cmap_ranges(raw_ranges)?;
+        }
+
+        if let Some(raw_constants) = lib_plist.get(MATH_CONSTANTS_KEY) {
+            // Only math fonts pay for reading the lib of every glyph
+            let ufo_dir = designspace_dir.join(&default_master.filename);
+            static_metadata.misc.math = Some(MathValues {
+                constants: parse_math_constants(raw_constants)?,
+                extended_shapes: math_extended_shapes(&ufo_dir, default_master)?,
+            });
+        }
+
+        if let Some(raw_baselines) = lib_plist.get(BASELINES_KEY) {
+            // as with the Baselines parameter of Glyphs, an invalid value is ignored
+            static_metadata.misc.base_table = match parse_baselines(raw_baselines) {
+                Ok(baselines) => Some(baselines).filter(|baselines| !baselines.is_empty()),
+                Err(e) => {
+                    warn!("Ignoring {BASELINES_KEY}: {e}");
+                    None
+                }
+            };
+        }
+
         if let Some(gasp_records) = font_info_at_default.open_type_gasp_range_records.as_ref() {
             static_metadata.misc.gasp = gasp_records
                 .iter()
@@ -970,6 +1246,15 @@ impl Work<Context, WorkId, Error> for StaticMetadataWork {
                 .collect();
         }
         static_metadata.variations = variations;
+        static_metadata.feature_writers = feature_writers;
+        static_metadata.axis_mappings = self
+            .axis_mappings
+            .iter()
+            .map(|(input, output)| AxisMapping {
+                input: input.to_normalized(&static_metadata.axes),
+                output: output.to_normalized(&static_metadata.axes),
+            })
+            .collect();
 
         context.preliminary_glyph_order.set(glyph_order);
         context.static_metadata.set(static_metadata);
@@ -1017,6 +1302,207 @@ fn is_glyph_only(source: &norad::designspace::Source) -> bool {
     source.layer.is_some()
 }
 
+/// Maps glyph names to arrays of codepoint ranges for cmap format 13, e.g. "0000-007F"
+const CMAP_RANGES_KEY: &str = "com.github.googlefonts.fontc.cmapRanges";
+
+fn parse_cmap_ranges(plist: &plist::Value) -> Result<Vec<CodepointRange>, BadSource> {
+    let bad = |msg: String| BadSource::custom("lib.plist", format!("{CMAP_RANGES_KEY}: {msg}"));
+    let ranges = plist
+        .as_dictionary()
+        .ok_or_else(|| bad("not a dictionary".to_string()))?;
+    let parse_codepoint = |raw: &str| {
+        u32::from_str_radix(raw.trim(), 16)
+            .ok()
+            .filter(|cp| *cp <= 0x10FFFF)
+            .ok_or_else(|| bad(format!("'{raw}' is not a hexadecimal codepoint")))
+    };
+    let mut result = Vec::new();
+    for (glyph_name, glyph_ranges) in ranges {
+        let glyph_ranges = glyph_ranges
+            .as_array()
+            .ok_or_else(|| bad(format!("the ranges of '{glyph_name}' are not an array")))?;
+        for raw in glyph_ranges {
+            let raw = raw
+                .as_string()
+                .ok_or_else(|| bad(format!("the ranges of '{glyph_name}' must be strings")))?;
+            let (first, last) = raw.split_once('-').unwrap_or((raw, raw));
+            let (first, last) = (parse_codepoint(first)?, parse_codepoint(last)?);
+            if first > last {
+                return Err(bad(format!("'{raw}' is not a valid range")));
+            }
+            result.push(CodepointRange {
+                first,
+                last,
+                glyph: glyph_name.as_str().into(),
+            });
+        }
+    }
+    Ok(result)
+}
+
+/// MATH constants by name, e.g. AxisHeight, as written by the GlyphsMath plugin
+const MATH_CONSTANTS_KEY: &str = "com.nagwa.MathPlugin.constants";
+/// Glyph lib key that, if true, marks an extended shape for MATH
+const MATH_EXTENDED_SHAPE_KEY: &str = "com.nagwa.MathPlugin.extendedShape";
+
+fn parse_math_constants(plist: &plist::Value) -> Result<BTreeMap<SmolStr, i16>, BadSource> {
+    let bad = |msg: String| BadSource::custom("lib.plist", format!("{MATH_CONSTANTS_KEY}: {msg}"));
+    let constants = plist
+        .as_dictionary()
+        .ok_or_else(|| bad("not a dictionary".to_string()))?;
+    constants
+        .iter()
+        .map(|(name, value)| {
+            let value = value
+                .as_signed_integer()
+                .or_else(|| value.as_real().map(|v| v.round() as i64))
+                .and_then(|v| i16::try_from(v).ok())
+                .ok_or_else(|| bad(format!("{name} is not an i16")))?;
+            Ok((name.as_str().into(), value))
+        })
+        .collect()
+}
+
+/// The glyphs of the default layer whose lib marks them as an extended shape
+fn math_extended_shapes(
+    ufo_dir: &Path,
+    default_master: &designspace::Source,
+) -> Result<BTreeSet<GlyphName>, Error> {
+    let mut extended_shapes = BTreeSet::new();
+    for (glyph_name, glif_file) in glif_files(ufo_dir, &mut HashMap::new(), default_master)? {
+        let glyph = norad::Glyph::load(&glif_file).map_err(|e| BadSource::custom(&glif_file, e))?;
+        if glyph
+            .lib
+            .get(MATH_EXTENDED_SHAPE_KEY)
+            .and_then(|v| v.as_boolean())
+            .unwrap_or_default()
+        {
+            extended_shapes.insert(glyph_name);
+        }
+    }
+    Ok(extended_shapes)
+}
+
+/// Baseline positions for BASE, by axis ("horizontal" or "vertical") then script tag,
+/// e.g. {"horizontal": {"latn": {"default": "romn", "romn": 0, "ideo": -120}}}
+const BASELINES_KEY: &str = "com.github.googlefonts.fontc.baselines";
+
+fn parse_baselines(plist: &plist::Value) -> Result<BaseTableValues, String> {
+    let parse_tag =
+        |raw: &str| Tag::from_str(raw).map_err(|_| format!("'{raw}' is not a valid tag"));
+    let axes = plist
+        .as_dictionary()
+        .ok_or_else(|| "not a dictionary".to_string())?;
+    let mut result = BaseTableValues::default();
+    for (axis, scripts) in axes {
+        let axis_baselines = match axis.as_str() {
+            "horizontal" => &mut result.horizontal,
+            "vertical" => &mut result.vertical,
+            _ => return Err(format!("'{axis}' is not horizontal or vertical")),
+        };
+        let scripts = scripts
+            .as_dictionary()
+            .ok_or_else(|| format!("{axis} is not a dictionary"))?;
+        for (script, baselines) in scripts {
+            let baselines = baselines
+                .as_dictionary()
+                .ok_or_else(|| format!("'{script}' is not a dictionary"))?;
+            let mut default = None;
+            let mut positions = BTreeMap::new();
+            for (baseline, value) in baselines {
+                if baseline == "default" {
+                    let value = value
+                        .as_string()
+                        .ok_or_else(|| format!("the default of '{script}' is not a string"))?;
+                    default = Some(parse_tag(value)?);
+                    continue;
+                }
+                let position = value
+                    .as_signed_integer()
+                    .and_then(|v| i16::try_from(v).ok())
+                    .ok_or_else(|| format!("'{baseline}' of '{script}' is not an i16"))?;
+                positions.insert(parse_tag(baseline)?, position);
+            }
+            let default = default.ok_or_else(|| format!("'{script}' has no default baseline"))?;
+            if !positions.contains_key(&default) {
+                return Err(format!(
+                    "'{script}' has no position for its default '{default}'"
+                ));
+            }
+            axis_baselines.insert(parse_tag(script)?, ScriptBaselines { default, positions });
+        }
+    }
+    Ok(result)
+}
+
+/// Parse the avar2 `<mappings>` of a designspace, which norad doesn't read.
+///
+/// Returns (input, output) pairs of design locations. See
+/// <https://fonttools.readthedocs.io/en/latest/designspaceLib/xml.html#mappings-element>
+fn parse_axis_mappings(
+    xml: &[u8],
+    tags_by_name: &HashMap<&str, Tag>,
+) -> Result<Vec<(DesignLocation, DesignLocation)>, String> {
+    let parse_dimension = |dimension: &BytesStart| -> Result<(Tag, DesignCoord), String> {
+        let mut name = None;
+        let mut xvalue = None;
+        for attr in dimension.attributes() {
+            let attr = attr.map_err(|e| e.to_string())?;
+            let value = attr.unescape_value().map_err(|e| e.to_string())?;
+            match attr.key.as_ref() {
+                b"name" => name = Some(value.into_owned()),
+                b"xvalue" => xvalue = Some(value.into_owned()),
+                _ => (),
+            }
+        }
+        let name = name.ok_or("mapping dimension has no name")?;
+        let tag = *tags_by_name
+            .get(name.as_str())
+            .ok_or_else(|| format!("mapping references undefined axis '{name}'"))?;
+        let xvalue = xvalue
+            .ok_or_else(|| format!("mapping dimension '{name}' has no xvalue"))?
+            .parse::<f64>()
+            .map_err(|e| format!("mapping dimension '{name}': {e}"))?;
+        Ok((tag, DesignCoord::new(xvalue)))
+    };
+
+    let mut reader = Reader::from_reader(xml);
+    reader.config_mut().trim_text(true);
+    let mut in_mappings = false;
+    // The location of the <input> or <output> we're in, if any
+    let mut current = None;
+    let mut input = None;
+    let mut mappings = Vec::new();
+    loop {
+        match reader.read_event().map_err(|e| e.to_string())? {
+            Event::Start(start) => match start.name().as_ref() {
+                b"mappings" => in_mappings = true,
+                b"input" | b"output" if in_mappings => current = Some(DesignLocation::new()),
+                _ => (),
+            },
+            Event::Empty(empty) if empty.name().as_ref() == b"dimension" => {
+                if let Some(location) = current.as_mut() {
+                    let (tag, coord) = parse_dimension(&empty)?;
+                    location.insert(tag, coord);
+                }
+            }
+            Event::End(end) => match end.name().as_ref() {
+                b"mappings" => in_mappings = false,
+                b"input" if in_mappings => input = current.take(),
+                b"output" if in_mappings => {
+                    let output = current.take().unwrap_or_default();
+                    let input = input.take().ok_or("mapping <output> without <input>")?;
+                    mappings.push((input, output));
+                }
+                _ => (),
+            },
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+    Ok(mappings)
+}
+
 fn parse_meta_table_values(plist: &plist::Value) -> Option<MetaTableValues> {
     let plist = plist.as_dictionary()?;
     let mut ret = MetaTableValues::default();
@@ -1361,6 +1847,14 @@ impl Work<Context, WorkId, Error> for GlobalMetricsWork {
                 font_info.open_type_vhea_caret_offset,
             );
 
+            if context.flags.contains(Flags::GOOGLE_FONTS_METRICS) {
+                metrics.populate_google_fonts_defaults(
+                    pos,
+                    static_metadata.units_per_em,
+                    font_info.ascender,
+                    font_info.descender,
+                );
+            }
             populate_default_metrics(
                 &mut metrics,
                 pos,
@@ -1424,7 +1918,7 @@ fn kerning_groups_for(
 ) -> Result<BTreeMap<KernGroup, BTreeSet<GlyphName>>, Error> {
     let ufo_dir = designspace_dir.join(&source.filename);
     let data_request = norad::DataRequest::none().groups(true);
-    Ok(norad::Font::load_requested_data(&ufo_dir, data_request)
+    let groups: BTreeMap<_, _> = norad::Font::load_requested_data(&ufo_dir, data_request)
         .map_err(|e| BadSource::custom(ufo_dir, e))?
         .groups
         .into_iter()
@@ -1455,7 +1949,38 @@ fn kerning_groups_for(
                 None
             }
         })
-        .collect())
+        .collect();
+    check_kerning_group_overlaps(&groups)?;
+    Ok(groups)
+}
+
+/// Each glyph may be in at most one group per side, otherwise which kerning applies is ambiguous
+///
+/// See <https://unifiedfontobject.org/versions/ufo3/groups.plist/#kerning-groups>
+fn check_kerning_group_overlaps(
+    groups: &BTreeMap<KernGroup, BTreeSet<GlyphName>>,
+) -> Result<(), Error> {
+    let mut groups_by_glyph: BTreeMap<(u8, &GlyphName), Vec<&KernGroup>> = BTreeMap::new();
+    for (group, members) in groups {
+        for glyph_name in members {
+            groups_by_glyph
+                .entry((group.side_ord(), glyph_name))
+                .or_default()
+                .push(group);
+        }
+    }
+    let overlaps: Vec<_> = groups_by_glyph
+        .into_iter()
+        .filter(|(_, groups)| groups.len() > 1)
+        .map(|((_, glyph_name), groups)| {
+            let groups: Vec<_> = groups.iter().map(|g| g.to_string()).collect();
+            format!("{glyph_name} in {}", groups.join(" and "))
+        })
+        .collect();
+    if !overlaps.is_empty() {
+        return Err(Error::OverlappingKerningGroups(overlaps));
+    }
+    Ok(())
 }
 
 /// UFO specific behaviour for kern groups
@@ -1468,10 +1993,13 @@ trait KernGroupExt {
 
 impl KernGroupExt for KernGroup {
     fn from_group_name(name: &str) -> Option<KernGroup> {
+        // a bare prefix doesn't name a group
         name.strip_prefix(UFO_KERN1_PREFIX)
+            .filter(|name| !name.is_empty())
             .map(|name| Self::Side1(name.into()))
             .or_else(|| {
                 name.strip_prefix(UFO_KERN2_PREFIX)
+                    .filter(|name| !name.is_empty())
                     .map(|name| Self::Side2(name.into()))
             })
     }
@@ -1773,6 +2301,44 @@ impl Work<Context, WorkId, Error> for PaintGraphWork {
     }
 }
 
+impl Work<Context, WorkId, Error> for BitmapStrikesWork {
+    fn id(&self) -> WorkId {
+        WorkId::BitmapStrikes
+    }
+
+    fn read_access(&self) -> Access<WorkId> {
+        Access::None
+    }
+
+    fn write_access(&self) -> Access<WorkId> {
+        Access::Variant(WorkId::BitmapStrikes)
+    }
+
+    fn exec(&self, _context: &Context) -> Result<(), Error> {
+        debug!("UFO has no bitmap layers, bitmaps can come from a strike directory");
+        Ok(())
+    }
+}
+
+impl Work<Context, WorkId, Error> for SvgDocumentsWork {
+    fn id(&self) -> WorkId {
+        WorkId::SvgDocuments
+    }
+
+    fn read_access(&self) -> Access<WorkId> {
+        Access::None
+    }
+
+    fn write_access(&self) -> Access<WorkId> {
+        Access::Variant(WorkId::SvgDocuments)
+    }
+
+    fn exec(&self, _context: &Context) -> Result<(), Error> {
+        debug!("UFO has no SVG layers, SVG documents can come from a directory");
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{
@@ -1851,6 +2417,25 @@ mod tests {
         );
     }
 
+    #[test]
+    pub fn missing_layer_is_named() {
+        let source = designspace::Source {
+            filename: "WghtVar-Regular.ufo".to_string(),
+            layer: Some("{500}".to_string()),
+            ..Default::default()
+        };
+        let ufo_dir = ufo_dir(&source.filename);
+        let Err(Error::NoSuchLayer { ufo, layer }) =
+            glif_files(&ufo_dir, &mut HashMap::new(), &source)
+        else {
+            panic!("there is no {{500}} layer");
+        };
+        assert_eq!(
+            ("WghtVar-Regular.ufo", "{500}"),
+            (ufo.as_str(), layer.as_str())
+        );
+    }
+
     fn load_designspace(name: &str) -> DesignSpaceIrSource {
         DesignSpaceIrSource::new(&testdata_dir().join(name)).unwrap()
     }
@@ -2302,6 +2887,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn kerning_groups_may_not_overlap_on_a_side() {
+        let group = |group: KernGroup, members: &[&str]| {
+            (group, members.iter().map(|m| GlyphName::new(*m)).collect())
+        };
+        let mut groups: BTreeMap<_, BTreeSet<_>> = [
+            group(KernGroup::Side1("O".into()), &["O", "Q"]),
+            group(KernGroup::Side2("O".into()), &["O", "Q"]),
+            group(KernGroup::Side2("C".into()), &["C"]),
+        ]
+        .into_iter()
+        .collect();
+        assert!(check_kerning_group_overlaps(&groups).is_ok());
+
+        groups.extend([group(KernGroup::Side2("Q".into()), &["Q"])]);
+        let Err(Error::OverlappingKerningGroups(overlaps)) = check_kerning_group_overlaps(&groups)
+        else {
+            panic!("Q is in two side2 groups");
+        };
+        assert_eq!(vec!["Q in side2.O and side2.Q"], overlaps);
+    }
+
+    #[test]
+    fn bare_group_prefix_is_not_a_group() {
+        assert_eq!(None, KernGroup::from_group_name("public.kern1."));
+        assert_eq!(
+            Some(KernGroup::Side2("A".into())),
+            KernGroup::from_group_name("public.kern2.A")
+        );
+    }
+
     #[test]
     fn captures_anchors() {
         let base_name = "A".into();
@@ -2327,6 +2943,129 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_baselines_from_lib() {
+        let mut latn = plist::Dictionary::new();
+        latn.insert("default".into(), "romn".into());
+        latn.insert("romn".into(), 0.into());
+        latn.insert("ideo".into(), (-120).into());
+        let mut hani = latn.clone();
+        hani.insert("default".into(), "ideo".into());
+        let mut horizontal = plist::Dictionary::new();
+        horizontal.insert("latn".into(), latn.into());
+        horizontal.insert("hani".into(), hani.into());
+        let mut baselines = plist::Dictionary::new();
+        baselines.insert("horizontal".into(), horizontal.into());
+
+        let baselines = parse_baselines(&plist::Value::Dictionary(baselines)).unwrap();
+
+        let positions = BTreeMap::from([(Tag::new(b"ideo"), -120), (Tag::new(b"romn"), 0)]);
+        assert_eq!(
+            BaseTableValues {
+                horizontal: BTreeMap::from([
+                    (
+                        Tag::new(b"hani"),
+                        ScriptBaselines {
+                            default: Tag::new(b"ideo"),
+                            positions: positions.clone(),
+                        }
+                    ),
+                    (
+                        Tag::new(b"latn"),
+                        ScriptBaselines {
+                            default: Tag::new(b"romn"),
+                            positions,
+                        }
+                    ),
+                ]),
+                vertical: Default::default(),
+            },
+            baselines
+        );
+    }
+
+    #[test]
+    fn baselines_need_a_position_for_the_default() {
+        let mut latn = plist::Dictionary::new();
+        latn.insert("default".into(), "romn".into());
+        latn.insert("ideo".into(), (-120).into());
+        let mut horizontal = plist::Dictionary::new();
+        horizontal.insert("latn".into(), latn.into());
+        let mut baselines = plist::Dictionary::new();
+        baselines.insert("horizontal".into(), horizontal.into());
+
+        assert!(parse_baselines(&plist::Value::Dictionary(baselines)).is_err());
+    }
+
+    #[test]
+    fn parse_math_constants_from_lib() {
+        let mut constants = plist::Dictionary::new();
+        constants.insert("AxisHeight".into(), 250.into());
+        constants.insert("ScriptPercentScaleDown".into(), 70.4.into());
+
+        assert_eq!(
+            BTreeMap::from([
+                ("AxisHeight".into(), 250),
+                ("ScriptPercentScaleDown".into(), 70)
+            ]),
+            parse_math_constants(&plist::Value::Dictionary(constants)).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_cmap_ranges_from_lib() {
+        let mut ranges = plist::Dictionary::new();
+        ranges.insert(
+            "latin".into(),
+            plist::Value::Array(vec!["0000-007F".into(), "00A0-00FF".into()]),
+        );
+        ranges.insert("pua".into(), plist::Value::Array(vec!["E000".into()]));
+        let ranges = parse_cmap_ranges(&plist::Value::Dictionary(ranges)).unwrap();
+        assert_eq!(
+            vec![
+                (0, 0x7F, "latin"),
+                (0xA0, 0xFF, "latin"),
+                (0xE000, 0xE000, "pua")
+            ],
+            ranges
+                .iter()
+                .map(|r| (r.first, r.last, r.glyph.as_str()))
+                .collect::<Vec<_>>()
+        );
+
+        let mut backwards = plist::Dictionary::new();
+        backwards.insert("a".into(), plist::Value::Array(vec!["007F-0000".into()]));
+        assert!(parse_cmap_ranges(&plist::Value::Dictionary(backwards)).is_err());
+    }
+
+    #[test]
+    fn static_metadata_has_axis_mappings() {
+        let (_, context) = build_static_metadata("avar2.designspace", default_test_flags());
+        assert_eq!(
+            vec![AxisMapping {
+                input: NormalizedLocation::for_pos(&[("movx", 1.0)]),
+                output: NormalizedLocation::for_pos(&[("movx", 1.0), ("movy", 0.5)]),
+            }],
+            context.static_metadata.get().axis_mappings
+        );
+    }
+
+    #[test]
+    fn axis_mappings_must_name_known_axes() {
+        let xml = br#"<designspace format="5.1"><axes>
+            <axis tag="wght" name="Weight" minimum="400" maximum="700" default="400"/>
+            <mappings><mapping>
+              <input><dimension name="Width" xvalue="100"/></input>
+              <output><dimension name="Weight" xvalue="700"/></output>
+            </mapping></mappings>
+        </axes></designspace>"#;
+        let tags_by_name = HashMap::from([("Weight", Tag::new(b"wght"))]);
+        assert_eq!(
+            Err("mapping references undefined axis 'Width'".to_string()),
+            parse_axis_mappings(xml, &tags_by_name)
+        );
+    }
+
     #[test]
     fn postscript_names_happy_path() {
         // Given
@@ -2576,6 +3315,155 @@ mod tests {
         assert!(super::parse_meta_table_values(&plist::Value::Dictionary(plist)).is_none())
     }
 
+    fn lib_with_feature_writers(writers: &str) -> plist::Dictionary {
+        let xml = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <plist version="1.0">
+            <dict>
+                <key>com.github.googlei18n.ufo2ft.featureWriters</key>
+                <array>{writers}</array>
+            </dict>
+            </plist>"#
+        );
+        plist::Value::from_reader_xml(xml.as_bytes())
+            .unwrap()
+            .into_dictionary()
+            .unwrap()
+    }
+
+    #[test]
+    fn feature_writers_default_to_all() {
+        let lib = plist::Dictionary::new();
+        assert_eq!(
+            FeatureWriters::default(),
+            feature_writers(&lib, &lib).unwrap()
+        );
+    }
+
+    #[test]
+    fn empty_feature_writers_disables_all() {
+        let lib = lib_with_feature_writers("");
+        assert_eq!(
+            FeatureWriters::none(),
+            feature_writers(&lib, &Default::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn feature_writers_with_options() {
+        let lib = lib_with_feature_writers(
+            r#"
+            <dict>
+                <key>class</key>
+                <string>KernFeatureWriter</string>
+                <key>options</key>
+                <dict>
+                    <key>mode</key>
+                    <string>append</string>
+                </dict>
+            </dict>
+            <dict>
+                <key>module</key>
+                <string>ufo2ft.featureWriters</string>
+                <key>class</key>
+                <string>MarkFeatureWriter</string>
+                <key>options</key>
+                <dict>
+                    <key>features</key>
+                    <array><string>mark</string></array>
+                </dict>
+            </dict>
+            "#,
+        );
+        assert_eq!(
+            FeatureWriters {
+                kern: Some(FeatureWriter {
+                    mode: FeatureWriterMode::Append,
+                    features: None,
+                }),
+                mark: Some(FeatureWriter {
+                    mode: FeatureWriterMode::Skip,
+                    features: Some([Tag::new(b"mark")].into()),
+                }),
+                curs: None,
+                ccmp: None,
+                locl: None,
+                frac: None,
+                automatic: None,
+            },
+            feature_writers(&Default::default(), &lib).unwrap()
+        );
+    }
+
+    #[test]
+    fn ccmp_and_locl_feature_writers() {
+        let lib = lib_with_feature_writers(
+            r#"
+            <dict><key>class</key><string>CcmpFeatureWriter</string></dict>
+            <dict><key>class</key><string>LoclFeatureWriter</string></dict>
+            "#,
+        );
+        let writers = feature_writers(&lib, &Default::default()).unwrap();
+        assert_eq!(Some(FeatureWriter::default()), writers.ccmp);
+        assert_eq!(Some(FeatureWriter::default()), writers.locl);
+        assert!(writers.frac.is_none());
+        assert!(writers.kern.is_none());
+    }
+
+    #[test]
+    fn frac_feature_writer_options() {
+        let lib = lib_with_feature_writers(
+            r#"
+            <dict>
+                <key>class</key>
+                <string>FracFeatureWriter</string>
+                <key>options</key>
+                <dict>
+                    <key>features</key>
+                    <array><string>frac</string></array>
+                </dict>
+            </dict>
+            "#,
+        );
+        let writers = feature_writers(&lib, &Default::default()).unwrap();
+        assert_eq!(
+            Some(FeatureWriter {
+                mode: FeatureWriterMode::Skip,
+                features: Some([Tag::new(b"frac")].into()),
+            }),
+            writers.frac
+        );
+    }
+
+    #[test]
+    fn designspace_feature_writers_take_precedence() {
+        let designspace_lib = lib_with_feature_writers(
+            "<dict><key>class</key><string>CursFeatureWriter</string></dict>",
+        );
+        let ufo_lib = lib_with_feature_writers("");
+        let writers = feature_writers(&designspace_lib, &ufo_lib).unwrap();
+        assert!(writers.curs.is_some());
+        assert!(writers.kern.is_none());
+    }
+
+    #[test]
+    fn reject_bad_feature_writer_mode() {
+        let lib = lib_with_feature_writers(
+            r#"
+            <dict>
+                <key>class</key>
+                <string>KernFeatureWriter</string>
+                <key>options</key>
+                <dict>
+                    <key>mode</key>
+                    <string>replace</string>
+                </dict>
+            </dict>
+            "#,
+        );
+        assert!(feature_writers(&lib, &Default::default()).is_err());
+    }
+
     fn fixed_pitch_of(name: &str) -> Option<bool> {
         let (_, context) = build_static_metadata(name, default_test_flags());
         let static_metadata = context.static_metadata.get();